@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+"""Example Utopia backend plugin: writes a Markdown outline of the program.
+
+Compile with: utopia compile app.uto --target plugin:examples/plugins/outline.py
+"""
+import json
+import sys
+
+PROTOCOL_VERSION = 1
+
+
+def describe():
+    return {
+        "protocol_version": PROTOCOL_VERSION,
+        "name": "outline",
+        "extension": ".md",
+        "unsupported": [],
+    }
+
+
+def generate(program):
+    lines = ["# Program outline", ""]
+    diagnostics = []
+    for block in program["language_blocks"]:
+        lines.append(f"## @lang {block['language']}")
+        for function in block["functions"]:
+            parameters = ", ".join(parameter["name"] for parameter in function["parameters"])
+            lines.append(f"- `{function['name']}({parameters})`: {len(function['body'])} statements")
+            if not function["body"]:
+                span = function["span"]
+                diagnostics.append({
+                    "severity": "warning",
+                    "message": f"function '{function['name']}' is empty",
+                    "line": span["line"],
+                    "column": span["column"],
+                })
+        lines.append("")
+    lines.append(f"{len(program['global_statements'])} top-level statements")
+    return {
+        "protocol_version": PROTOCOL_VERSION,
+        "files": [{"path": "outline.md", "contents": "\n".join(lines) + "\n"}],
+        "diagnostics": diagnostics,
+    }
+
+
+def main():
+    request = json.load(sys.stdin)
+    if request["protocol_version"] != PROTOCOL_VERSION:
+        sys.exit(f"unsupported protocol version {request['protocol_version']}")
+    if request["request"] == "describe":
+        response = describe()
+    else:
+        response = generate(request["program"])
+    json.dump(response, sys.stdout)
+
+
+if __name__ == "__main__":
+    main()
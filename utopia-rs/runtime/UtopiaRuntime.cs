@@ -0,0 +1,114 @@
+// Utopia runtime support for generated C# code
+//
+// Defines how Utopia values are typed, compared and printed, the builtins
+// that have no direct .NET equivalent, and the registry cross-language calls
+// are dispatched through. Generated by the Utopia compiler; uses the base
+// class library only. The file has no using directives, so it can also be
+// pasted into the generated file.
+
+namespace Utopia.Generated
+{
+    public static class UtopiaRuntime
+    {
+        private static readonly System.Collections.Concurrent.ConcurrentDictionary<string, System.Func<object[], object>> Functions =
+            new System.Collections.Concurrent.ConcurrentDictionary<string, System.Func<object[], object>>();
+
+        /// <summary>The Utopia type name of a .NET value</summary>
+        public static string TypeOf(object value)
+        {
+            switch (value)
+            {
+                case null: return "null";
+                case bool _: return "boolean";
+                case string _: case char _: return "string";
+                case System.Collections.IDictionary _: return "object";
+                case System.Collections.IEnumerable _: return "array";
+                case System.Delegate _: return "function";
+                case System.IConvertible _: return "number";
+                default: throw new System.ArgumentException("not a Utopia value: " + value.GetType().Name);
+            }
+        }
+
+        /// <summary>Whether a condition on the value holds</summary>
+        public static bool IsTruthy(object value)
+        {
+            switch (TypeOf(value))
+            {
+                case "null": return false;
+                case "boolean": return (bool)value;
+                case "string": return value.ToString().Length > 0;
+                case "number":
+                    var number = System.Convert.ToDouble(value);
+                    return number != 0 && !double.IsNaN(number);
+                default: return true;
+            }
+        }
+
+        /// <summary>The text <c>println</c> writes for the value</summary>
+        public static string ToDisplay(object value)
+        {
+            switch (TypeOf(value))
+            {
+                case "null": return "null";
+                case "boolean": return (bool)value ? "true" : "false";
+                case "number":
+                    var number = System.Convert.ToDouble(value);
+                    return number == System.Math.Floor(number) && System.Math.Abs(number) < 1e15
+                        ? ((long)number).ToString(System.Globalization.CultureInfo.InvariantCulture)
+                        : number.ToString("R", System.Globalization.CultureInfo.InvariantCulture);
+                case "object":
+                    var entries = new System.Collections.Generic.List<string>();
+                    foreach (System.Collections.DictionaryEntry entry in (System.Collections.IDictionary)value)
+                    {
+                        entries.Add(entry.Key + ": " + ToDisplay(entry.Value));
+                    }
+                    return "{" + string.Join(", ", entries) + "}";
+                case "array":
+                    var elements = new System.Collections.Generic.List<string>();
+                    foreach (var element in (System.Collections.IEnumerable)value)
+                    {
+                        elements.Add(ToDisplay(element));
+                    }
+                    return "[" + string.Join(", ", elements) + "]";
+                case "function": return "<function>";
+                default: return value.ToString();
+            }
+        }
+
+        /// <summary><c>+</c> on operands whose types are only known at run time</summary>
+        public static object Add(object left, object right)
+        {
+            if (TypeOf(left) == "string" || TypeOf(right) == "string")
+            {
+                return ToDisplay(left) + ToDisplay(right);
+            }
+            return System.Convert.ToDouble(left) + System.Convert.ToDouble(right);
+        }
+
+        public static void Println(params object[] values)
+        {
+            var texts = new string[values.Length];
+            for (var i = 0; i < values.Length; i++)
+            {
+                texts[i] = ToDisplay(values[i]);
+            }
+            System.Console.WriteLine(string.Join(" ", texts));
+        }
+
+        /// <summary>Provide the implementation of <c>language::name</c></summary>
+        public static void Register(string language, string name, System.Func<object[], object> function)
+        {
+            Functions[language + "::" + name] = function;
+        }
+
+        /// <summary>Call <c>language::name</c></summary>
+        public static object CrossCall(string language, string name, object[] args)
+        {
+            if (!Functions.TryGetValue(language + "::" + name, out var function))
+            {
+                throw new System.InvalidOperationException("no implementation registered for " + language + "::" + name);
+            }
+            return function(args);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Utopia runtime support for generated Java code
+//
+// Defines how Utopia values are typed, compared and printed, the builtins
+// that have no direct Java equivalent, and the registry cross-language calls
+// are dispatched through. Generated by the Utopia compiler; uses the Java
+// standard library only. The class is package-private and the file has no
+// imports, so it can also be pasted into the generated file.
+
+final class UtopiaRuntime {
+    /** An implementation of a function in another language */
+    @FunctionalInterface
+    interface Function {
+        Object call(Object... args);
+    }
+
+    private static final java.util.Map<String, Function> FUNCTIONS = new java.util.concurrent.ConcurrentHashMap<>();
+
+    private UtopiaRuntime() {}
+
+    /** The Utopia type name of a Java value */
+    static String typeOf(Object value) {
+        if (value instanceof UtopiaValue) return ((UtopiaValue) value).getTypeInfo();
+        if (value == null) return "null";
+        if (value instanceof Number) return "number";
+        if (value instanceof String || value instanceof Character) return "string";
+        if (value instanceof Boolean) return "boolean";
+        if (value instanceof java.util.List || value.getClass().isArray()) return "array";
+        if (value instanceof java.util.Map) return "object";
+        if (value instanceof Function) return "function";
+        throw new IllegalArgumentException("not a Utopia value: " + value.getClass().getName());
+    }
+
+    /** Whether a condition on the value holds */
+    static boolean isTruthy(Object value) {
+        if (value instanceof UtopiaValue) return isTruthy(((UtopiaValue) value).getData());
+        if (value == null) return false;
+        if (value instanceof Boolean) return (Boolean) value;
+        if (value instanceof Number) {
+            double number = ((Number) value).doubleValue();
+            return number != 0 && !Double.isNaN(number);
+        }
+        if (value instanceof String) return !((String) value).isEmpty();
+        return true;
+    }
+
+    /** The text `println` writes for the value */
+    static String toDisplay(Object value) {
+        if (value instanceof UtopiaValue) return toDisplay(((UtopiaValue) value).getData());
+        if (value == null) return "null";
+        if (value instanceof Number) {
+            double number = ((Number) value).doubleValue();
+            if (number == Math.rint(number) && Math.abs(number) < 1e15) return Long.toString((long) number);
+            return Double.toString(number);
+        }
+        if (value instanceof java.util.List) {
+            java.util.StringJoiner joiner = new java.util.StringJoiner(", ", "[", "]");
+            for (Object element : (java.util.List<?>) value) joiner.add(toDisplay(element));
+            return joiner.toString();
+        }
+        if (value instanceof java.util.Map) {
+            java.util.StringJoiner joiner = new java.util.StringJoiner(", ", "{", "}");
+            for (java.util.Map.Entry<?, ?> entry : ((java.util.Map<?, ?>) value).entrySet()) {
+                joiner.add(entry.getKey() + ": " + toDisplay(entry.getValue()));
+            }
+            return joiner.toString();
+        }
+        if (value instanceof Function) return "<function>";
+        return String.valueOf(value);
+    }
+
+    static void println(Object... values) {
+        java.util.StringJoiner joiner = new java.util.StringJoiner(" ");
+        for (Object value : values) joiner.add(toDisplay(value));
+        System.out.println(joiner);
+    }
+
+    /** The length of a string, array or list */
+    static double len(Object value) {
+        if (value instanceof UtopiaValue) return len(((UtopiaValue) value).getData());
+        if (value instanceof CharSequence) return ((CharSequence) value).length();
+        if (value instanceof java.util.Collection) return ((java.util.Collection<?>) value).size();
+        if (value != null && value.getClass().isArray()) return java.lang.reflect.Array.getLength(value);
+        throw new IllegalArgumentException("len expects a string or an array, found " + typeOf(value));
+    }
+
+    static Object pop(Object array) {
+        java.util.List<?> list = (java.util.List<?>) array;
+        return list.isEmpty() ? null : list.remove(list.size() - 1);
+    }
+
+    /** Provide the implementation of `language::name` */
+    static void register(String language, String name, Function function) {
+        FUNCTIONS.put(language + "::" + name, function);
+    }
+
+    /** Call `language::name` */
+    static Object crossCall(String language, String name, Object... args) {
+        Function function = FUNCTIONS.get(language + "::" + name);
+        if (function == null) {
+            throw new IllegalStateException("no implementation registered for " + language + "::" + name);
+        }
+        return function.call(args);
+    }
+}
@@ -0,0 +1,27 @@
+// Utopia runtime support for generated Java code: the value model
+//
+// Generated by the Utopia compiler. The class is package-private and the file
+// has no imports, so it can also be pasted into the generated file.
+
+/** A value with the Utopia type it was created as */
+final class UtopiaValue {
+    private final Object data;
+    private final String typeInfo;
+
+    public UtopiaValue(Object data, String typeInfo) {
+        this.data = data;
+        this.typeInfo = typeInfo;
+    }
+
+    public static UtopiaValue of(Object data) {
+        return new UtopiaValue(data, UtopiaRuntime.typeOf(data));
+    }
+
+    public Object getData() { return data; }
+    public String getTypeInfo() { return typeInfo; }
+
+    @Override
+    public String toString() {
+        return UtopiaRuntime.toDisplay(data);
+    }
+}
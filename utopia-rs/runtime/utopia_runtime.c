@@ -0,0 +1,153 @@
+/*
+ * Utopia runtime support for generated C code
+ */
+
+#include "utopia_runtime.h"
+
+#include <ctype.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+utopia_value utopia_null(void) {
+    utopia_value value = { UTOPIA_NULL, { 0 } };
+    return value;
+}
+
+utopia_value utopia_number(double number) {
+    utopia_value value = { UTOPIA_NUMBER, { 0 } };
+    value.as.number = number;
+    return value;
+}
+
+utopia_value utopia_string(const char *string) {
+    utopia_value value = { UTOPIA_STRING, { 0 } };
+    value.as.string = string;
+    return value;
+}
+
+utopia_value utopia_boolean(bool boolean) {
+    utopia_value value = { UTOPIA_BOOLEAN, { 0 } };
+    value.as.boolean = boolean;
+    return value;
+}
+
+const char *utopia_type_of(utopia_value value) {
+    switch (value.type) {
+    case UTOPIA_NUMBER: return "number";
+    case UTOPIA_STRING: return "string";
+    case UTOPIA_BOOLEAN: return "boolean";
+    case UTOPIA_ARRAY: return "array";
+    default: return "null";
+    }
+}
+
+bool utopia_is_truthy(utopia_value value) {
+    switch (value.type) {
+    case UTOPIA_NUMBER: return value.as.number != 0 && !isnan(value.as.number);
+    case UTOPIA_STRING: return value.as.string[0] != '\0';
+    case UTOPIA_BOOLEAN: return value.as.boolean;
+    case UTOPIA_ARRAY: return true;
+    default: return false;
+    }
+}
+
+void utopia_print(utopia_value value) {
+    size_t i;
+    switch (value.type) {
+    case UTOPIA_NUMBER:
+        if (value.as.number == floor(value.as.number) && fabs(value.as.number) < 1e15) {
+            printf("%.0f", value.as.number);
+        } else {
+            printf("%.17g", value.as.number);
+        }
+        break;
+    case UTOPIA_STRING:
+        fputs(value.as.string, stdout);
+        break;
+    case UTOPIA_BOOLEAN:
+        fputs(value.as.boolean ? "true" : "false", stdout);
+        break;
+    case UTOPIA_ARRAY:
+        putchar('[');
+        for (i = 0; i < value.as.array.length; i++) {
+            if (i > 0) fputs(", ", stdout);
+            utopia_print(value.as.array.elements[i]);
+        }
+        putchar(']');
+        break;
+    default:
+        fputs("null", stdout);
+    }
+}
+
+static char *utopia_copy(const char *text, size_t length) {
+    char *result = malloc(length + 1);
+    if (result == NULL) {
+        fputs("utopia: out of memory\n", stderr);
+        exit(1);
+    }
+    memcpy(result, text, length);
+    result[length] = '\0';
+    return result;
+}
+
+char *utopia_upper(const char *text) {
+    char *result = utopia_copy(text, strlen(text));
+    char *c;
+    for (c = result; *c; c++) *c = (char) toupper((unsigned char) *c);
+    return result;
+}
+
+char *utopia_lower(const char *text) {
+    char *result = utopia_copy(text, strlen(text));
+    char *c;
+    for (c = result; *c; c++) *c = (char) tolower((unsigned char) *c);
+    return result;
+}
+
+char *utopia_trim(const char *text) {
+    size_t length;
+    while (isspace((unsigned char) *text)) text++;
+    length = strlen(text);
+    while (length > 0 && isspace((unsigned char) text[length - 1])) length--;
+    return utopia_copy(text, length);
+}
+
+bool utopia_contains(const char *text, const char *part) {
+    return strstr(text, part) != NULL;
+}
+
+typedef struct utopia_registration {
+    const char *language;
+    const char *name;
+    utopia_function function;
+    struct utopia_registration *next;
+} utopia_registration;
+
+static utopia_registration *utopia_registrations = NULL;
+
+void utopia_register(const char *language, const char *name, utopia_function function) {
+    utopia_registration *entry = malloc(sizeof *entry);
+    if (entry == NULL) {
+        fputs("utopia: out of memory\n", stderr);
+        exit(1);
+    }
+    entry->language = language;
+    entry->name = name;
+    entry->function = function;
+    entry->next = utopia_registrations;
+    utopia_registrations = entry;
+}
+
+utopia_value utopia_cross_call(const char *language, const char *name, size_t argc, const utopia_value *args) {
+    utopia_registration *entry;
+    for (entry = utopia_registrations; entry != NULL; entry = entry->next) {
+        if (strcmp(entry->language, language) == 0 && strcmp(entry->name, name) == 0) {
+            return entry->function(argc, args);
+        }
+    }
+    fprintf(stderr, "utopia: no implementation registered for %s::%s\n", language, name);
+    exit(1);
+}
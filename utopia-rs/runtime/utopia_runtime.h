@@ -0,0 +1,63 @@
+/*
+ * Utopia runtime support for generated C code
+ *
+ * Defines the Utopia value model, the builtins C has no function for, and
+ * the registry cross-language calls are dispatched through. Generated by the
+ * Utopia compiler; uses the C standard library only. Compile
+ * utopia_runtime.c together with the generated file.
+ */
+
+#ifndef UTOPIA_RUNTIME_H
+#define UTOPIA_RUNTIME_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+typedef enum {
+    UTOPIA_NULL,
+    UTOPIA_NUMBER,
+    UTOPIA_STRING,
+    UTOPIA_BOOLEAN,
+    UTOPIA_ARRAY,
+} utopia_type;
+
+/* A Utopia value; strings and arrays are owned by the caller */
+typedef struct utopia_value {
+    utopia_type type;
+    union {
+        double number;
+        const char *string;
+        bool boolean;
+        struct {
+            struct utopia_value *elements;
+            size_t length;
+        } array;
+    } as;
+} utopia_value;
+
+typedef utopia_value (*utopia_function)(size_t argc, const utopia_value *args);
+
+utopia_value utopia_null(void);
+utopia_value utopia_number(double number);
+utopia_value utopia_string(const char *string);
+utopia_value utopia_boolean(bool boolean);
+
+/* The Utopia type name of the value */
+const char *utopia_type_of(utopia_value value);
+/* Whether a condition on the value holds */
+bool utopia_is_truthy(utopia_value value);
+/* Write the value the way `println` does */
+void utopia_print(utopia_value value);
+
+/* String builtins; the result is allocated with malloc */
+char *utopia_upper(const char *text);
+char *utopia_lower(const char *text);
+char *utopia_trim(const char *text);
+bool utopia_contains(const char *text, const char *part);
+
+/* Provide the implementation of `language::name` */
+void utopia_register(const char *language, const char *name, utopia_function function);
+/* Call `language::name`; exits with a message if it has no implementation */
+utopia_value utopia_cross_call(const char *language, const char *name, size_t argc, const utopia_value *args);
+
+#endif /* UTOPIA_RUNTIME_H */
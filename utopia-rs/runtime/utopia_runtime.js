@@ -0,0 +1,84 @@
+// Utopia runtime support for generated JavaScript code
+//
+// Defines how Utopia values are typed, compared and printed, the builtin
+// standard library, and the registry cross-language calls are dispatched
+// through. Generated by the Utopia compiler; has no dependencies.
+
+const utopia = (() => {
+    class UtopiaError extends Error {}
+
+    function typeOf(value) {
+        if (value === null || value === undefined) return "null";
+        if (Array.isArray(value)) return "array";
+        switch (typeof value) {
+            case "number": return "number";
+            case "string": return "string";
+            case "boolean": return "boolean";
+            case "function": return "function";
+            case "object": return "object";
+            default: throw new UtopiaError(`not a Utopia value: ${String(value)}`);
+        }
+    }
+
+    function isTruthy(value) {
+        return Boolean(value) || Array.isArray(value) || typeOf(value) === "object";
+    }
+
+    function toDisplay(value) {
+        switch (typeOf(value)) {
+            case "null": return "null";
+            case "array": return `[${value.map(toDisplay).join(", ")}]`;
+            case "object": return `{${Object.entries(value).map(([key, item]) => `${key}: ${toDisplay(item)}`).join(", ")}}`;
+            case "function": return "<function>";
+            default: return String(value);
+        }
+    }
+
+    const builtins = {
+        println: (...values) => console.log(values.map(toDisplay).join(" ")),
+        toString: toDisplay,
+        toNumber: (value) => value === null || value === undefined ? 0 : Number(value),
+        toBoolean: isTruthy,
+        len: (value) => value.length,
+        push: (array, value) => { array.push(value); },
+        pop: (array) => array.length > 0 ? array.pop() : null,
+        upper: (text) => text.toUpperCase(),
+        lower: (text) => text.toLowerCase(),
+        trim: (text) => text.trim(),
+        contains: (text, part) => text.includes(part),
+        abs: Math.abs,
+        sqrt: Math.sqrt,
+        floor: Math.floor,
+        ceil: Math.ceil,
+        round: Math.round,
+        pow: Math.pow,
+        min: Math.min,
+        max: Math.max,
+        keys: (object) => Object.keys(object),
+        hasKey: (object, key) => Object.prototype.hasOwnProperty.call(object, key),
+    };
+    builtins.print = builtins.println;
+    builtins.str = builtins.toString;
+
+    const functions = new Map();
+
+    // Provide the implementation of `language::name`
+    function register(language, name, implementation) {
+        functions.set(`${language}::${name}`, implementation);
+    }
+
+    // Call `language::name` with an array of arguments
+    function crossCall(language, name, args) {
+        const implementation = functions.get(`${language}::${name}`);
+        if (implementation === undefined) {
+            throw new UtopiaError(`no implementation registered for ${language}::${name}`);
+        }
+        return implementation(...args);
+    }
+
+    return { UtopiaError, typeOf, isTruthy, toDisplay, builtins, register, crossCall };
+})();
+
+if (typeof module !== "undefined" && module.exports) {
+    module.exports = utopia;
+}
@@ -0,0 +1,139 @@
+"""Utopia runtime support for generated Python code.
+
+Defines how Utopia values are typed, compared and printed, the builtin
+standard library, and the registry cross-language calls are dispatched
+through. Generated by the Utopia compiler; uses the standard library only.
+"""
+
+import math
+import sys
+
+__all__ = ["UtopiaError", "cross_call", "register", "type_of", "is_truthy", "to_display", "utopia_add", "utopia_mod", "BUILTINS"]
+
+
+class UtopiaError(Exception):
+    """An error raised by the runtime rather than by the program."""
+
+
+def type_of(value):
+    """The Utopia type name of a Python value."""
+    if value is None:
+        return "null"
+    if isinstance(value, bool):
+        return "boolean"
+    if isinstance(value, (int, float)):
+        return "number"
+    if isinstance(value, str):
+        return "string"
+    if isinstance(value, list):
+        return "array"
+    if isinstance(value, dict):
+        return "object"
+    if callable(value):
+        return "function"
+    raise UtopiaError("not a Utopia value: %r" % (value,))
+
+
+def is_truthy(value):
+    """Whether a condition on the value holds."""
+    if isinstance(value, float) and math.isnan(value):
+        return False
+    return value is not None and value is not False and value != 0 and value != ""
+
+
+def to_display(value):
+    """The text `println` writes for the value."""
+    kind = type_of(value)
+    if kind == "null":
+        return "null"
+    if kind == "boolean":
+        return "true" if value else "false"
+    if kind == "number":
+        if float(value).is_integer() and abs(value) < 1e15:
+            return str(int(value))
+        return repr(float(value))
+    if kind == "array":
+        return "[" + ", ".join(to_display(element) for element in value) + "]"
+    if kind == "object":
+        return "{" + ", ".join("%s: %s" % (key, to_display(item)) for key, item in value.items()) + "}"
+    if kind == "function":
+        return "<function>"
+    return value
+
+
+def utopia_add(left, right):
+    """`+` on operands whose types are only known at run time."""
+    if type_of(left) == "string" or type_of(right) == "string":
+        return to_display(left) + to_display(right)
+    return left + right
+
+
+def utopia_mod(left, right):
+    """`%` keeping the sign of the dividend; ints stay ints, like folded constants."""
+    if isinstance(left, int) and isinstance(right, int):
+        remainder = abs(left) % abs(right)
+        return -remainder if left < 0 else remainder
+    return math.fmod(left, right)
+
+
+def _number(value):
+    if type_of(value) == "string":
+        try:
+            return float(value)
+        except ValueError:
+            return math.nan
+    if type_of(value) in ("number", "boolean"):
+        return float(value)
+    return 0.0 if value is None else math.nan
+
+
+def _pop(array):
+    return array.pop() if array else None
+
+
+BUILTINS = {
+    "println": lambda *values: print(" ".join(to_display(value) for value in values)),
+    "toString": to_display,
+    "toNumber": _number,
+    "toBoolean": is_truthy,
+    "len": len,
+    "push": lambda array, value: array.append(value),
+    "pop": _pop,
+    "upper": lambda text: text.upper(),
+    "lower": lambda text: text.lower(),
+    "trim": lambda text: text.strip(),
+    "contains": lambda text, part: part in text,
+    "abs": abs,
+    "sqrt": math.sqrt,
+    "floor": math.floor,
+    "ceil": math.ceil,
+    "round": lambda number: math.floor(number + 0.5),
+    "pow": math.pow,
+    "min": min,
+    "max": max,
+    "keys": lambda obj: list(obj.keys()),
+    "hasKey": lambda obj, key: key in obj,
+}
+BUILTINS["print"] = BUILTINS["println"]
+BUILTINS["str"] = BUILTINS["toString"]
+
+_functions = {}
+
+
+def register(language, name, function):
+    """Provide the implementation of `language::name`."""
+    _functions[(language, name)] = function
+
+
+def cross_call(language, name, args):
+    """Call `language::name` with a list of arguments.
+
+    Registered implementations are used first; Python functions are also
+    found in the main module.
+    """
+    function = _functions.get((language, name))
+    if function is None and language == "python":
+        function = getattr(sys.modules["__main__"], name, None)
+    if function is None:
+        raise UtopiaError("no implementation registered for %s::%s" % (language, name))
+    return function(*args)
@@ -0,0 +1,85 @@
+// Utopia runtime support for generated Rust code
+//
+// Defines the Utopia value model, how values are compared and printed, and
+// the registry cross-language calls are dispatched through. Generated by the
+// Utopia compiler; uses the standard library only. Paths are written out in
+// full, so the module can also be pasted into the generated file.
+
+/// A Utopia value
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtopiaValue {
+    Null,
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Array(Vec<UtopiaValue>),
+    Object(std::collections::BTreeMap<String, UtopiaValue>),
+}
+
+impl UtopiaValue {
+    /// The Utopia type name of the value
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            UtopiaValue::Null => "null",
+            UtopiaValue::Number(_) => "number",
+            UtopiaValue::String(_) => "string",
+            UtopiaValue::Boolean(_) => "boolean",
+            UtopiaValue::Array(_) => "array",
+            UtopiaValue::Object(_) => "object",
+        }
+    }
+
+    /// Whether a condition on the value holds
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            UtopiaValue::Null => false,
+            UtopiaValue::Number(number) => *number != 0.0 && !number.is_nan(),
+            UtopiaValue::String(string) => !string.is_empty(),
+            UtopiaValue::Boolean(boolean) => *boolean,
+            UtopiaValue::Array(_) | UtopiaValue::Object(_) => true,
+        }
+    }
+}
+
+/// The text `println` writes for the value
+impl std::fmt::Display for UtopiaValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UtopiaValue::Null => write!(f, "null"),
+            UtopiaValue::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => write!(f, "{}", *number as i64),
+            UtopiaValue::Number(number) => write!(f, "{}", number),
+            UtopiaValue::String(string) => write!(f, "{}", string),
+            UtopiaValue::Boolean(boolean) => write!(f, "{}", boolean),
+            UtopiaValue::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            UtopiaValue::Object(properties) => {
+                let properties: Vec<String> = properties.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+                write!(f, "{{{}}}", properties.join(", "))
+            }
+        }
+    }
+}
+
+/// An implementation of a function in another language
+pub type UtopiaFunction = fn(Vec<UtopiaValue>) -> Result<UtopiaValue, Box<dyn std::error::Error>>;
+
+fn utopia_functions() -> &'static std::sync::Mutex<std::collections::HashMap<String, UtopiaFunction>> {
+    static FUNCTIONS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<String, UtopiaFunction>>> = std::sync::OnceLock::new();
+    FUNCTIONS.get_or_init(Default::default)
+}
+
+/// Provide the implementation of `language::name`
+pub fn register(language: &str, name: &str, function: UtopiaFunction) {
+    utopia_functions().lock().unwrap().insert(format!("{}::{}", language, name), function);
+}
+
+/// Call `language::name`
+pub fn cross_call(language: &str, name: &str, args: Vec<UtopiaValue>) -> Result<UtopiaValue, Box<dyn std::error::Error>> {
+    let function = utopia_functions().lock().unwrap().get(&format!("{}::{}", language, name)).copied();
+    match function {
+        Some(function) => function(args),
+        None => Err(format!("no implementation registered for {}::{}", language, name).into()),
+    }
+}
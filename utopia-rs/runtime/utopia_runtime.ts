@@ -0,0 +1,89 @@
+// Utopia runtime support for generated TypeScript code
+//
+// Defines how Utopia values are typed, compared and printed, the builtin
+// standard library, and the registry cross-language calls are dispatched
+// through. Generated by the Utopia compiler; has no dependencies.
+
+export type UtopiaValue =
+    | null
+    | number
+    | string
+    | boolean
+    | UtopiaValue[]
+    | { [key: string]: UtopiaValue }
+    | ((...args: UtopiaValue[]) => UtopiaValue);
+
+export const utopia = (() => {
+    class UtopiaError extends Error {}
+
+    function typeOf(value: UtopiaValue | undefined): string {
+        if (value === null || value === undefined) return "null";
+        if (Array.isArray(value)) return "array";
+        switch (typeof value) {
+            case "number": return "number";
+            case "string": return "string";
+            case "boolean": return "boolean";
+            case "function": return "function";
+            case "object": return "object";
+            default: throw new UtopiaError(`not a Utopia value: ${String(value)}`);
+        }
+    }
+
+    function isTruthy(value: UtopiaValue): boolean {
+        return Boolean(value) || Array.isArray(value) || typeOf(value) === "object";
+    }
+
+    function toDisplay(value: UtopiaValue): string {
+        switch (typeOf(value)) {
+            case "null": return "null";
+            case "array": return `[${(value as UtopiaValue[]).map(toDisplay).join(", ")}]`;
+            case "object": return `{${Object.entries(value as { [key: string]: UtopiaValue }).map(([key, item]) => `${key}: ${toDisplay(item)}`).join(", ")}}`;
+            case "function": return "<function>";
+            default: return String(value);
+        }
+    }
+
+    const builtins = {
+        println: (...values: UtopiaValue[]): void => console.log(values.map(toDisplay).join(" ")),
+        print: (...values: UtopiaValue[]): void => console.log(values.map(toDisplay).join(" ")),
+        toString: toDisplay,
+        str: toDisplay,
+        toNumber: (value: UtopiaValue): number => value === null ? 0 : Number(value),
+        toBoolean: isTruthy,
+        len: (value: string | UtopiaValue[]): number => value.length,
+        push: (array: UtopiaValue[], value: UtopiaValue): void => { array.push(value); },
+        pop: (array: UtopiaValue[]): UtopiaValue => array.length > 0 ? array.pop() as UtopiaValue : null,
+        upper: (text: string): string => text.toUpperCase(),
+        lower: (text: string): string => text.toLowerCase(),
+        trim: (text: string): string => text.trim(),
+        contains: (text: string, part: string): boolean => text.includes(part),
+        abs: Math.abs,
+        sqrt: Math.sqrt,
+        floor: Math.floor,
+        ceil: Math.ceil,
+        round: Math.round,
+        pow: Math.pow,
+        min: (a: number, b: number): number => Math.min(a, b),
+        max: (a: number, b: number): number => Math.max(a, b),
+        keys: (object: { [key: string]: UtopiaValue }): string[] => Object.keys(object),
+        hasKey: (object: { [key: string]: UtopiaValue }, key: string): boolean => Object.prototype.hasOwnProperty.call(object, key),
+    };
+
+    const functions = new Map<string, (...args: UtopiaValue[]) => UtopiaValue>();
+
+    // Provide the implementation of `language::name`
+    function register(language: string, name: string, implementation: (...args: UtopiaValue[]) => UtopiaValue): void {
+        functions.set(`${language}::${name}`, implementation);
+    }
+
+    // Call `language::name` with an array of arguments
+    function crossCall(language: string, name: string, args: UtopiaValue[]): UtopiaValue {
+        const implementation = functions.get(`${language}::${name}`);
+        if (implementation === undefined) {
+            throw new UtopiaError(`no implementation registered for ${language}::${name}`);
+        }
+        return implementation(...args);
+    }
+
+    return { UtopiaError, typeOf, isTruthy, toDisplay, builtins, register, crossCall };
+})();
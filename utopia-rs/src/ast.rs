@@ -5,10 +5,28 @@
 
 use crate::{Span, types::Type};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use indexmap::IndexMap;
+
+/// Version of the JSON form of the AST, increased whenever a change to the
+/// AST types changes how a program is serialized
+pub const AST_VERSION: u32 = 1;
+
+/// Value of the `format` field of an AST document
+pub const AST_FORMAT: &str = "utopia-ast";
+
+/// A program in its versioned JSON form, as exchanged with other tools
+///
+/// The layout of every node is documented in `docs/ast-json.md`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AstDocument {
+    pub format: String,
+    pub version: u32,
+    pub program: Program,
+}
 
 /// Metadata about a Utopia program
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Metadata {
     pub languages: Vec<String>,
     pub functions: Vec<FunctionInfo>,
@@ -104,6 +122,7 @@ pub trait Visitor {
 pub struct Program {
     pub language_blocks: Vec<LanguageBlock>,
     pub global_statements: Vec<Statement>,
+    #[serde(default)]
     pub metadata: Metadata,
     pub span: Span,
 }
@@ -132,6 +151,41 @@ impl Program {
     pub fn add_global_statement(&mut self, statement: Statement) {
         self.global_statements.push(statement);
     }
+
+    /// The program as a versioned AST document
+    pub fn to_json(&self) -> crate::Result<String> {
+        let document = AstDocument { format: AST_FORMAT.to_string(), version: AST_VERSION, program: self.clone() };
+        Ok(serde_json::to_string_pretty(&document)? + "\n")
+    }
+
+    /// Read a program from a versioned AST document
+    ///
+    /// Documents may leave out `metadata`; the languages of the program are
+    /// then taken from its language blocks.
+    pub fn from_json(json: &str) -> crate::Result<Program> {
+        let header: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid AST document: {}", e))?;
+        if header.get("format").and_then(|format| format.as_str()) != Some(AST_FORMAT) {
+            return Err(format!("Not an AST document: expected \"format\": \"{}\"", AST_FORMAT).into());
+        }
+        match header.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version == u64::from(AST_VERSION) => {}
+            Some(version) => return Err(format!("Unsupported AST version {} (this compiler reads version {})", version, AST_VERSION).into()),
+            None => return Err("AST document has no version".into()),
+        }
+
+        let document: AstDocument = serde_json::from_value(header)
+            .map_err(|e| format!("Invalid AST document: {}", e))?;
+        let mut program = document.program;
+        if program.metadata.languages.is_empty() {
+            for block in &program.language_blocks {
+                if !program.metadata.languages.contains(&block.language) {
+                    program.metadata.languages.push(block.language.clone());
+                }
+            }
+        }
+        Ok(program)
+    }
 }
 
 impl AstNode for Program {
@@ -191,6 +245,7 @@ pub struct Function {
     pub parameters: Vec<Parameter>,
     pub return_type: Option<Type>,
     pub body: Vec<Statement>,
+    #[serde(default)]
     pub is_exported: bool,
     pub language: String,
     pub span: Span,
@@ -307,6 +362,118 @@ impl Statement {
             Statement::ClassDeclaration { span, .. } => *span,
         }
     }
+
+    /// Call `f` on every expression in this statement, including nested statements,
+    /// function bodies and lambdas, parents before children
+    pub fn walk_expressions<'a>(&'a self, f: &mut dyn FnMut(&'a Expression)) {
+        match self {
+            Statement::Expression { expression, .. } => expression.walk(f),
+            Statement::VariableDeclaration { value, .. } | Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    value.walk(f);
+                }
+            }
+            Statement::Assignment { target, value, .. } => {
+                target.walk(f);
+                value.walk(f);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                condition.walk(f);
+                for statement in then_branch.iter().chain(else_branch.iter().flatten()) {
+                    statement.walk_expressions(f);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                condition.walk(f);
+                for statement in body {
+                    statement.walk_expressions(f);
+                }
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(init) = init {
+                    init.walk_expressions(f);
+                }
+                for expression in condition.iter().chain(update.iter()) {
+                    expression.walk(f);
+                }
+                for statement in body {
+                    statement.walk_expressions(f);
+                }
+            }
+            Statement::Block { statements, .. } | Statement::FunctionDeclaration { body: statements, .. } => {
+                for statement in statements {
+                    statement.walk_expressions(f);
+                }
+            }
+            Statement::ClassDeclaration { methods, fields, .. } => {
+                for field in fields {
+                    if let Some(value) = &field.default_value {
+                        value.walk(f);
+                    }
+                }
+                for statement in methods.iter().flat_map(|method| &method.body) {
+                    statement.walk_expressions(f);
+                }
+            }
+            Statement::Import { .. } | Statement::Export { .. } => {}
+        }
+    }
+
+    /// Call `f` on every expression in this statement like `walk_expressions`,
+    /// but children before parents so `f` may replace what it is given
+    pub fn walk_expressions_mut(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        match self {
+            Statement::Expression { expression, .. } => expression.walk_mut(f),
+            Statement::VariableDeclaration { value, .. } | Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    value.walk_mut(f);
+                }
+            }
+            Statement::Assignment { target, value, .. } => {
+                target.walk_mut(f);
+                value.walk_mut(f);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                condition.walk_mut(f);
+                for statement in then_branch.iter_mut().chain(else_branch.iter_mut().flatten()) {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                condition.walk_mut(f);
+                for statement in body {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(init) = init {
+                    init.walk_expressions_mut(f);
+                }
+                for expression in condition.iter_mut().chain(update.iter_mut()) {
+                    expression.walk_mut(f);
+                }
+                for statement in body {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Statement::Block { statements, .. } | Statement::FunctionDeclaration { body: statements, .. } => {
+                for statement in statements {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Statement::ClassDeclaration { methods, fields, .. } => {
+                for field in fields {
+                    if let Some(value) = &mut field.default_value {
+                        value.walk_mut(f);
+                    }
+                }
+                for statement in methods.iter_mut().flat_map(|method| &mut method.body) {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Statement::Import { .. } | Statement::Export { .. } => {}
+        }
+    }
 }
 
 impl AstNode for Statement {
@@ -328,6 +495,11 @@ pub enum Expression {
     },
     Identifier {
         name: String,
+        /// Type of the variable or function the name refers to, filled in by
+        /// the type checker before code generation; `None` when the program
+        /// does not declare the name
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resolved_type: Option<Type>,
         span: Span,
     },
     Binary {
@@ -377,7 +549,7 @@ pub enum Expression {
         span: Span,
     },
     Object {
-        properties: HashMap<String, Expression>,
+        properties: IndexMap<String, Expression>,
         span: Span,
     },
     Lambda {
@@ -406,6 +578,94 @@ impl Expression {
             Expression::Assignment { span, .. } => *span,
         }
     }
+
+    /// Call `f` on this expression and every expression inside it, including
+    /// lambda bodies, parents before children
+    pub fn walk<'a>(&'a self, f: &mut dyn FnMut(&'a Expression)) {
+        f(self);
+        match self {
+            Expression::Binary { left, right, .. } => {
+                left.walk(f);
+                right.walk(f);
+            }
+            Expression::Unary { operand, .. } | Expression::Postfix { operand, .. } => operand.walk(f),
+            Expression::Assignment { target, value, .. } => {
+                target.walk(f);
+                value.walk(f);
+            }
+            Expression::Call { callee, arguments, .. } => {
+                callee.walk(f);
+                for argument in arguments {
+                    argument.walk(f);
+                }
+            }
+            Expression::CrossCall { arguments, .. } | Expression::Array { elements: arguments, .. } => {
+                for argument in arguments {
+                    argument.walk(f);
+                }
+            }
+            Expression::MemberAccess { object, .. } => object.walk(f),
+            Expression::ArrayAccess { array, index, .. } => {
+                array.walk(f);
+                index.walk(f);
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values() {
+                    value.walk(f);
+                }
+            }
+            Expression::Lambda { body, .. } => {
+                for statement in body {
+                    statement.walk_expressions(f);
+                }
+            }
+            Expression::Literal { .. } | Expression::Identifier { .. } => {}
+        }
+    }
+
+    /// Call `f` on every expression inside this one and then on this one,
+    /// children before parents so `f` may replace what it is given
+    pub fn walk_mut(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        match self {
+            Expression::Binary { left, right, .. } => {
+                left.walk_mut(f);
+                right.walk_mut(f);
+            }
+            Expression::Unary { operand, .. } | Expression::Postfix { operand, .. } => operand.walk_mut(f),
+            Expression::Assignment { target, value, .. } => {
+                target.walk_mut(f);
+                value.walk_mut(f);
+            }
+            Expression::Call { callee, arguments, .. } => {
+                callee.walk_mut(f);
+                for argument in arguments {
+                    argument.walk_mut(f);
+                }
+            }
+            Expression::CrossCall { arguments, .. } | Expression::Array { elements: arguments, .. } => {
+                for argument in arguments {
+                    argument.walk_mut(f);
+                }
+            }
+            Expression::MemberAccess { object, .. } => object.walk_mut(f),
+            Expression::ArrayAccess { array, index, .. } => {
+                array.walk_mut(f);
+                index.walk_mut(f);
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values_mut() {
+                    value.walk_mut(f);
+                }
+            }
+            Expression::Lambda { body, .. } => {
+                for statement in body {
+                    statement.walk_expressions_mut(f);
+                }
+            }
+            Expression::Literal { .. } | Expression::Identifier { .. } => {}
+        }
+        f(self);
+    }
 }
 
 impl AstNode for Expression {
@@ -512,6 +772,12 @@ impl PrettyPrinter {
         std::mem::take(&mut self.output)
     }
 
+    /// Print a single expression as Utopia source
+    pub fn print_expression(&mut self, expression: &Expression) -> String {
+        expression.accept(self);
+        std::mem::take(&mut self.output)
+    }
+
     fn indent_str(&self) -> String {
         "  ".repeat(self.indent)
     }
@@ -575,47 +841,86 @@ impl Visitor for PrettyPrinter {
     }
 
     fn visit_function(&mut self, function: &Function) -> Self::Result {
-        let params: Vec<String> = function.parameters.iter()
-            .map(|p| format!("{}: {:?}", p.name, p.param_type))
-            .collect();
-        
         let return_type = function.return_type.as_ref()
-            .map(|t| format!(" -> {:?}", t))
+            .map(|t| format!(" -> {}", t))
             .unwrap_or_default();
         
         self.write_line(&format!("function {}({}){} {{", 
                                 function.name, 
-                                params.join(", "),
+                                format_parameters(&function.parameters),
                                 return_type));
-        self.increase_indent();
-        
-        for statement in &function.body {
-            statement.accept(self);
-        }
-        
-        self.decrease_indent();
+        self.print_body(&function.body);
         self.write_line("}");
     }
 
     fn visit_statement(&mut self, statement: &Statement) -> Self::Result {
         match statement {
             Statement::Expression { expression, .. } => {
+                self.output.push_str(&self.indent_str());
                 expression.accept(self);
                 self.output.push_str(";\n");
             }
             Statement::VariableDeclaration { name, value, var_type, is_const, .. } => {
                 let keyword = if *is_const { "const" } else { "let" };
                 let type_annotation = var_type.as_ref()
-                    .map(|t| format!(": {:?}", t))
-                    .unwrap_or_default();
-                let assignment = value.as_ref()
-                    .map(|_| " = ".to_string())
+                    .map(|t| format!(": {}", t))
                     .unwrap_or_default();
                 
-                self.write_line(&format!("{} {}{}{}", keyword, name, type_annotation, assignment));
+                self.output.push_str(&self.indent_str());
+                self.output.push_str(&format!("{} {}{}", keyword, name, type_annotation));
                 if let Some(value) = value {
+                    self.output.push_str(" = ");
                     value.accept(self);
                 }
+                self.output.push_str(";\n");
+            }
+            Statement::Assignment { target, value, .. } => {
+                self.output.push_str(&self.indent_str());
+                target.accept(self);
+                self.output.push_str(" = ");
+                value.accept(self);
+                self.output.push_str(";\n");
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.output.push_str(&self.indent_str());
+                self.output.push_str("if (");
+                condition.accept(self);
+                self.output.push_str(") {\n");
+                self.print_body(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.write_line("} else {");
+                    self.print_body(else_branch);
+                }
+                self.write_line("}");
+            }
+            Statement::While { condition, body, .. } => {
+                self.output.push_str(&self.indent_str());
+                self.output.push_str("while (");
+                condition.accept(self);
+                self.output.push_str(") {\n");
+                self.print_body(body);
+                self.write_line("}");
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                let init = init.as_ref()
+                    .map(|init| {
+                        let mut printer = PrettyPrinter::new();
+                        init.accept(&mut printer);
+                        printer.output.trim().trim_end_matches(';').to_string()
+                    })
+                    .unwrap_or_default();
+                self.output.push_str(&self.indent_str());
+                self.output.push_str(&format!("for ({}; ", init));
+                if let Some(condition) = condition {
+                    condition.accept(self);
+                }
+                self.output.push_str("; ");
+                if let Some(update) = update {
+                    update.accept(self);
+                }
+                self.output.push_str(") {\n");
+                self.print_body(body);
+                self.write_line("}");
             }
             Statement::Return { value, .. } => {
                 self.output.push_str(&self.indent_str());
@@ -626,44 +931,45 @@ impl Visitor for PrettyPrinter {
                 }
                 self.output.push_str(";\n");
             }
+            Statement::Import { module, items, .. } => {
+                if items.is_empty() {
+                    self.write_line(&format!("import {};", module));
+                } else {
+                    self.write_line(&format!("import {{ {} }} from {};", items.join(", "), module));
+                }
+            }
+            Statement::Export { item, .. } => {
+                self.write_line(&format!("export {};", item));
+            }
+            Statement::Block { statements, .. } => {
+                self.write_line("{");
+                self.print_body(statements);
+                self.write_line("}");
+            }
             Statement::FunctionDeclaration { name, parameters, return_type, body, .. } => {
-                let params_str = parameters.iter()
-                    .map(|p| format!("{}: {:?}", p.name, p.param_type))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                
                 let return_type_str = return_type.as_ref()
-                    .map(|t| format!(" -> {:?}", t))
+                    .map(|t| format!(" -> {}", t))
                     .unwrap_or_default();
                 
-                self.write_line(&format!("function {}({}){} {{", name, params_str, return_type_str));
-                self.indent += 1;
-                
-                for stmt in body {
-                    stmt.accept(self);
-                }
-                
-                self.indent -= 1;
+                self.write_line(&format!("function {}({}){} {{", name, format_parameters(parameters), return_type_str));
+                self.print_body(body);
                 self.write_line("}");
             }
-            Statement::ClassDeclaration { name, superclass, methods, fields, .. } => {
+            Statement::ClassDeclaration { name, superclass, methods, .. } => {
                 let superclass_str = superclass.as_ref()
-                    .map(|s| format!("extends {}", s))
+                    .map(|s| format!(" extends {}", s))
                     .unwrap_or_default();
                 
-                self.write_line(&format!("class {}({}) {{", name, superclass_str));
-                self.indent += 1;
+                self.write_line(&format!("class {}{} {{", name, superclass_str));
+                self.increase_indent();
                 
                 for method in methods {
                     method.accept(self);
                 }
                 
+                self.decrease_indent();
                 self.write_line("}");
             }
-            // Add other statement types as needed
-            _ => {
-                self.write_line(&format!("{:?}", statement));
-            }
         }
     }
 
@@ -681,7 +987,7 @@ impl Visitor for PrettyPrinter {
                 self.output.push_str(name);
             }
             Expression::Binary { left, operator, right, .. } => {
-                left.accept(self);
+                self.print_operand(left, operator.precedence());
                 match operator {
                     BinaryOperator::Add => self.output.push_str(" + "),
                     BinaryOperator::Subtract => self.output.push_str(" - "),
@@ -697,7 +1003,8 @@ impl Visitor for PrettyPrinter {
                     BinaryOperator::And => self.output.push_str(" && "),
                     BinaryOperator::Or => self.output.push_str(" || "),
                 }
-                right.accept(self);
+                // Operators are left-associative, so an equal-precedence right operand needs parentheses
+                self.print_operand(right, operator.precedence() + 1);
             }
             Expression::Unary { operator, operand, .. } => {
                 match operator {
@@ -705,7 +1012,7 @@ impl Visitor for PrettyPrinter {
                     UnaryOperator::Minus => self.output.push_str("-"),
                     UnaryOperator::Plus => self.output.push_str("+"),
                 }
-                operand.accept(self);
+                self.print_operand(operand, u8::MAX);
             }
             Expression::Postfix { operand, operator, .. } => {
                 operand.accept(self);
@@ -719,25 +1026,101 @@ impl Visitor for PrettyPrinter {
                 self.output.push_str(" = ");
                 value.accept(self);
             }
+            Expression::Call { callee, arguments, .. } => {
+                callee.accept(self);
+                self.print_arguments(arguments);
+            }
             Expression::CrossCall { language, function, arguments, .. } => {
                 self.output.push_str(&format!("{}::{}", language, function));
-                self.output.push('(');
-                for (i, arg) in arguments.iter().enumerate() {
+                self.print_arguments(arguments);
+            }
+            Expression::MemberAccess { object, property, .. } => {
+                object.accept(self);
+                self.output.push('.');
+                self.output.push_str(property);
+            }
+            Expression::ArrayAccess { array, index, .. } => {
+                array.accept(self);
+                self.output.push('[');
+                index.accept(self);
+                self.output.push(']');
+            }
+            Expression::Array { elements, .. } => {
+                self.output.push('[');
+                for (i, element) in elements.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    arg.accept(self);
+                    element.accept(self);
                 }
-                self.output.push(')');
+                self.output.push(']');
+            }
+            Expression::Object { properties, .. } => {
+                self.output.push('{');
+                for (i, (key, value)) in properties.iter().enumerate() {
+                    self.output.push_str(if i > 0 { ", " } else { " " });
+                    self.output.push_str(&format!("{}: ", key));
+                    value.accept(self);
+                }
+                self.output.push_str(if properties.is_empty() { "}" } else { " }" });
+            }
+            Expression::Lambda { parameters, body, .. } => {
+                self.output.push_str(&format!("({}) => {{\n", format_parameters(parameters)));
+                self.print_body(body);
+                self.output.push_str(&self.indent_str());
+                self.output.push('}');
             }
-            // Add other expression types as needed
-            _ => {
-                self.output.push_str(&format!("{:?}", expression));
+        }
+    }
+}
+
+impl PrettyPrinter {
+    fn print_body(&mut self, statements: &[Statement]) {
+        self.increase_indent();
+        for statement in statements {
+            statement.accept(self);
+        }
+        self.decrease_indent();
+    }
+
+    fn print_arguments(&mut self, arguments: &[Expression]) {
+        self.output.push('(');
+        for (i, arg) in arguments.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
             }
+            arg.accept(self);
+        }
+        self.output.push(')');
+    }
+
+    /// Print an operand, parenthesized if it binds looser than `min_precedence`
+    fn print_operand(&mut self, operand: &Expression, min_precedence: u8) {
+        let needs_parens = match operand {
+            Expression::Binary { operator, .. } => operator.precedence() < min_precedence,
+            Expression::Assignment { .. } | Expression::Lambda { .. } => true,
+            _ => false,
+        };
+        if needs_parens {
+            self.output.push('(');
+            operand.accept(self);
+            self.output.push(')');
+        } else {
+            operand.accept(self);
         }
     }
 }
 
+fn format_parameters(parameters: &[Parameter]) -> String {
+    parameters.iter()
+        .map(|p| match &p.param_type {
+            Some(param_type) => format!("{}: {}", p.name, param_type),
+            None => p.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -769,4 +1152,35 @@ mod tests {
         assert!(output.contains("Program {"));
         assert!(output.contains("}"));
     }
+
+    #[test]
+    fn test_ast_json_roundtrip() {
+        let source = "@lang main {\n    function f(a) {\n        return { b: a, c: [1, \"x\", true, null] };\n    }\n}\nlet y = -f(2) + 1;";
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+
+        let json = program.to_json().unwrap();
+        assert!(json.starts_with("{\n  \"format\": \"utopia-ast\",\n  \"version\": 1,"), "{}", json);
+        let read = Program::from_json(&json).unwrap();
+        assert_eq!(read.language_blocks[0].functions, program.language_blocks[0].functions);
+        assert_eq!(read.global_statements, program.global_statements);
+        assert_eq!(read.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_ast_json_checks_format_and_version() {
+        let span = r#"{"start": 0, "end": 0, "line": 1, "column": 1}"#;
+        let document = |header: &str| format!(
+            r#"{{{header} "program": {{"language_blocks": [{{"language": "python", "functions": [], "statements": [], "span": {span}}}], "global_statements": [], "span": {span}}}}}"#,
+        );
+
+        // Metadata is optional and rebuilt from the blocks
+        let program = Program::from_json(&document(r#""format": "utopia-ast", "version": 1,"#)).unwrap();
+        assert_eq!(program.metadata.languages, vec!["python".to_string()]);
+
+        let newer = Program::from_json(&document(r#""format": "utopia-ast", "version": 2,"#)).unwrap_err();
+        assert_eq!(newer.to_string(), "Unsupported AST version 2 (this compiler reads version 1)");
+        let untagged = Program::from_json(&document("")).unwrap_err();
+        assert!(untagged.to_string().starts_with("Not an AST document"), "{}", untagged);
+    }
 } 
\ No newline at end of file
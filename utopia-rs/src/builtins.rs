@@ -0,0 +1,348 @@
+//! The builtin standard library
+//!
+//! Every function a Utopia program can call without declaring it is listed
+//! once in `LIBRARY`, with the signature the type checker uses and the effects
+//! of calling it. Targets write these calls differently, so each backend describes
+//! its spelling with a `Builtins` table of templates and lowers every call
+//! through `Builtins::call`. A builtin a target has no template for is
+//! reported like any other construct the backend cannot translate, which
+//! makes the compile fail instead of emitting a call to a function that does
+//! not exist. Adding a builtin means adding it here and to the tables of the
+//! targets that can express it.
+
+use crate::{
+    ast::*,
+    operators::{self, substitute, Binding},
+    transformers::unsupported_builtin,
+    types::Type,
+    Result,
+};
+
+/// The type of a builtin parameter or result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Any,
+    Number,
+    String,
+    Boolean,
+    Array,
+    Object,
+    Void,
+}
+
+impl Kind {
+    pub fn to_type(self) -> Type {
+        match self {
+            Kind::Any => Type::Unknown,
+            Kind::Number => Type::Number,
+            Kind::String => Type::String,
+            Kind::Boolean => Type::Boolean,
+            Kind::Array => Type::array(Type::Unknown),
+            Kind::Object => Type::Object(Default::default()),
+            Kind::Void => Type::Void,
+        }
+    }
+}
+
+/// A function every program can call
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub parameters: &'static [Kind],
+    /// Whether more arguments than `parameters` are accepted, of any type
+    pub variadic: bool,
+    pub returns: Kind,
+    /// Whether calling it prints or reads input
+    pub io: bool,
+    /// Whether it changes the array or object passed as its first argument
+    pub mutates: bool,
+}
+
+impl Builtin {
+    const fn new(name: &'static str, parameters: &'static [Kind], returns: Kind) -> Self {
+        Self { name, parameters, variadic: false, returns, io: false, mutates: false }
+    }
+
+    const fn variadic(mut self) -> Self {
+        self.variadic = true;
+        self
+    }
+
+    const fn io(mut self) -> Self {
+        self.io = true;
+        self
+    }
+
+    const fn mutating(mut self) -> Self {
+        self.mutates = true;
+        self
+    }
+
+    /// Whether the result depends on the arguments only and nothing else happens
+    pub fn is_pure(&self) -> bool {
+        !self.io && !self.mutates
+    }
+
+    /// Whether the builtin can be called with `count` arguments
+    pub fn accepts(&self, count: usize) -> bool {
+        count == self.parameters.len() || self.variadic && count > self.parameters.len()
+    }
+
+    pub fn return_type(&self) -> Type {
+        self.returns.to_type()
+    }
+
+    pub fn function_type(&self) -> Type {
+        Type::function(self.parameters.iter().map(|kind| kind.to_type()).collect(), self.return_type())
+    }
+}
+
+/// Every builtin, in the order the language reference lists them
+pub const LIBRARY: &[Builtin] = &[
+    Builtin::new("println", &[], Kind::Void).variadic().io(),
+    Builtin::new("toString", &[Kind::Any], Kind::String),
+    Builtin::new("toNumber", &[Kind::Any], Kind::Number),
+    Builtin::new("toBoolean", &[Kind::Any], Kind::Boolean),
+    Builtin::new("len", &[Kind::Any], Kind::Number),
+    Builtin::new("push", &[Kind::Array, Kind::Any], Kind::Void).mutating(),
+    Builtin::new("pop", &[Kind::Array], Kind::Any).mutating(),
+    Builtin::new("upper", &[Kind::String], Kind::String),
+    Builtin::new("lower", &[Kind::String], Kind::String),
+    Builtin::new("trim", &[Kind::String], Kind::String),
+    Builtin::new("contains", &[Kind::String, Kind::String], Kind::Boolean),
+    Builtin::new("abs", &[Kind::Number], Kind::Number),
+    Builtin::new("sqrt", &[Kind::Number], Kind::Number),
+    Builtin::new("floor", &[Kind::Number], Kind::Number),
+    Builtin::new("ceil", &[Kind::Number], Kind::Number),
+    Builtin::new("round", &[Kind::Number], Kind::Number),
+    Builtin::new("pow", &[Kind::Number, Kind::Number], Kind::Number),
+    Builtin::new("min", &[Kind::Number, Kind::Number], Kind::Number),
+    Builtin::new("max", &[Kind::Number, Kind::Number], Kind::Number),
+    Builtin::new("keys", &[Kind::Object], Kind::Array),
+    Builtin::new("hasKey", &[Kind::Object, Kind::String], Kind::Boolean),
+];
+
+/// Other names programs use for builtins
+pub const ALIASES: &[(&str, &str)] = &[("print", "println"), ("str", "toString")];
+
+/// The builtin called `name`, resolving aliases
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    let name = ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name, |(_, builtin)| builtin);
+    LIBRARY.iter().find(|builtin| builtin.name == name)
+}
+
+/// The builtin `expression` calls, unless the program declares a name that shadows it
+pub(crate) fn called(expression: &Expression) -> Option<&'static Builtin> {
+    match expression {
+        Expression::Call { callee, .. } => match callee.as_ref() {
+            Expression::Identifier { name, resolved_type: None, .. } => lookup(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether `expression` is a call the target's `Builtins` table lowers
+pub(crate) fn is_builtin_call(expression: &Expression) -> bool {
+    called(expression).is_some()
+}
+
+/// How a target writes calls to builtins
+///
+/// Templates refer to the arguments as `{0}`, `{1}`, …, to all of them joined
+/// by the table's separator as `{args}`, and to one format specifier per
+/// argument, chosen by its type, as `{formats}`. A template registered as
+/// `name/N` is used instead of `name` for calls with exactly N arguments.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtins {
+    templates: &'static [(&'static str, &'static str)],
+    /// Templates looked up before `templates`
+    overrides: &'static [(&'static str, &'static str)],
+    separator: &'static str,
+    /// Specifiers for string, number, boolean and other arguments
+    formats: [&'static str; 4],
+    /// Emitted in place of a builtin the target has no template for
+    placeholder: &'static str,
+}
+
+impl Builtins {
+    pub const fn new(templates: &'static [(&'static str, &'static str)]) -> Self {
+        Self {
+            templates,
+            overrides: &[],
+            separator: ", ",
+            formats: ["", "", "", ""],
+            placeholder: "/* unsupported builtin */",
+        }
+    }
+
+    /// Replace or add templates, for a target that spells most builtins like another
+    pub const fn overriding(mut self, overrides: &'static [(&'static str, &'static str)]) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Join `{args}` with `separator`
+    pub const fn separator(mut self, separator: &'static str) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Format specifiers for string, number, boolean and other arguments
+    pub const fn formats(mut self, formats: [&'static str; 4]) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Emit `placeholder` for builtins the target cannot express
+    pub const fn placeholder(mut self, placeholder: &'static str) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Lower `expression` if it calls a builtin, generating the arguments with `generate`
+    pub fn call(&self, expression: &Expression, generate: &mut dyn FnMut(&Expression) -> Result<String>) -> Result<Option<String>> {
+        let (Some(builtin), Expression::Call { arguments, span, .. }) = (called(expression), expression) else {
+            return Ok(None);
+        };
+        let Some(template) = self.template(builtin.name, arguments.len()).filter(|_| builtin.accepts(arguments.len())) else {
+            return Ok(Some(unsupported_builtin(builtin.name, *span, self.placeholder)));
+        };
+
+        let mut operands = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            let code = generate(argument)?;
+            let binding = binding(argument, &code);
+            operands.push((code, binding));
+        }
+        // Only commas keep compound arguments apart without parentheses
+        let delimited = self.separator.trim() == ",";
+        let args = operands.iter()
+            .map(|(code, binding)| match binding {
+                Binding::Loose if !delimited => format!("({})", code),
+                _ => code.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(self.separator);
+        let formats = arguments.iter()
+            .map(|argument| match operators::kind(argument) {
+                Type::String => self.formats[0],
+                Type::Number => self.formats[1],
+                Type::Boolean => self.formats[2],
+                _ => self.formats[3],
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let names: Vec<String> = (0..operands.len()).map(|index| format!("{{{}}}", index)).collect();
+        let args = (args, Binding::Atom);
+        let formats = (formats, Binding::Atom);
+        let mut placeholders: Vec<(&str, &(String, Binding))> = vec![("{args}", &args), ("{formats}", &formats)];
+        placeholders.extend(names.iter().map(String::as_str).zip(&operands));
+        Ok(Some(substitute(template, &placeholders)))
+    }
+
+    fn template(&self, name: &str, arity: usize) -> Option<&'static str> {
+        let find = |key: &str| self.overrides.iter().chain(self.templates)
+            .find(|(name, _)| *name == key)
+            .map(|(_, template)| *template);
+        find(&format!("{}/{}", name, arity)).or_else(|| find(name))
+    }
+
+    /// Whether the target has a template for `name`
+    pub fn supports(&self, name: &str) -> bool {
+        let prefix = format!("{}/", name);
+        self.overrides.iter().chain(self.templates)
+            .any(|(key, _)| *key == name || key.starts_with(&prefix))
+    }
+}
+
+/// How generated argument code binds when a template puts it next to other code
+fn binding(argument: &Expression, code: &str) -> Binding {
+    let compound = matches!(argument,
+        Expression::Binary { .. } | Expression::Unary { .. } | Expression::Postfix { .. }
+            | Expression::Assignment { .. } | Expression::Lambda { .. })
+        || code.starts_with('-');
+    if compound && !is_wrapped(code) {
+        Binding::Loose
+    } else {
+        Binding::Atom
+    }
+}
+
+/// Whether `code` is a single parenthesized group
+fn is_wrapped(code: &str) -> bool {
+    let Some(inner) = code.strip_prefix('(').and_then(|code| code.strip_suffix(')')) else {
+        return false;
+    };
+    let mut depth = 0usize;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return false,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, lexer::Lexer, parser::Parser, transformers::TransformerManager};
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap()
+    }
+
+    /// `statements` at top level and in a main block, since backends emit one or the other
+    fn program(statements: &str) -> Program {
+        parse(&format!("{}\n@lang main {{\n{}\n}}\n", statements, statements))
+    }
+
+    #[test]
+    fn test_lowers_through_target_tables() {
+        let program = program("let name = \"Ada\";\nprint(len(name) + 1, upper(name));\nlet root = sqrt(len(name) * 4);\n");
+        let manager = TransformerManager::new();
+
+        let python = manager.transform("python", &program).unwrap();
+        assert!(python.diagnostics.is_empty(), "{:?}", python.diagnostics);
+        assert!(python.code().contains("print(len(name) + 1, name.upper())"), "{}", python.code());
+        assert!(python.code().contains("math.sqrt(len(name) * 4)"), "{}", python.code());
+
+        let rust = manager.transform("rust", &program).unwrap();
+        assert!(rust.code().contains("println!("), "{}", rust.code());
+        assert!(!rust.code().contains("print(len"), "{}", rust.code());
+    }
+
+    #[test]
+    fn test_missing_lowering_is_reported() {
+        let program = parse("@lang main {\nlet count = 7;\nlet label = toString(count);\n}\n");
+        let output = TransformerManager::new().transform("c", &program).unwrap();
+        assert!(output.code().contains("/* unsupported builtin */"), "{}", output.code());
+        assert!(!output.diagnostics.is_empty());
+        assert!(output.diagnostics.iter().all(|diagnostic| diagnostic.message.contains("c backend cannot translate the builtin `toString`")), "{:?}", output.diagnostics);
+    }
+
+    #[test]
+    fn test_declared_functions_shadow_builtins() {
+        let program = parse("@lang main {\nfunction toString(x) {\n}\nlet label = toString(2);\n}\n");
+        let output = TransformerManager::new().transform("c", &program).unwrap();
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        assert!(output.code().contains("toString(2.0)"), "{}", output.code());
+    }
+
+    #[test]
+    fn test_interpreter_implements_library() {
+        let program = parse(r#"
+            let xs = [1, 2];
+            push(xs, 3);
+            println(len(xs), pop(xs), trim("  a "), upper("a"), lower("B"), contains("abc", "b"));
+            println(abs(-2), sqrt(16), floor(2.5), ceil(2.5), round(2.4), pow(2, 3), min(1, 2), max(1, 2));
+            println(str(1) + "!", toNumber("4") + 1, toBoolean(1), len(keys({a: 1})), hasKey({a: 1}, "b"));
+        "#);
+        let output = Interpreter::new().run(&program).unwrap();
+        assert_eq!(output, "3 3 a A b true\n2 4 2 3 2 8 1 2\n1! 5 true 1 false\n");
+    }
+}
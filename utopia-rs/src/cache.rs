@@ -0,0 +1,849 @@
+//! Incremental compilation cache
+//!
+//! The cache lives in `.utopia-cache/` next to the compiler output. For every
+//! input file it records a hash of the source text, a span-insensitive hash of
+//! each compilation unit (functions and top-level statement groups), and the
+//! generated code and source map per target. A compile whose source is
+//! unchanged, or whose units are all unchanged and still at the same source
+//! positions, reuses the stored output.
+//!
+//! Otherwise the program is optimized and compared unit by unit against the
+//! optimized program the stored code was generated from; optimizations such
+//! as inlining carry changes across units, so this can differ from the units
+//! whose source changed. Only the changed units and the units that call them
+//! are generated again, from a program holding just those units. The source
+//! map tells which lines of the stored code each unit produced, and the new
+//! code of a unit replaces those lines. When units were added or removed, or
+//! the new code does not fit the stored file, the output is generated whole.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    ast::*,
+    callgraph::{block_unit_id, function_unit_id, CallGraph, GLOBAL_UNIT},
+    mangle::Rename,
+    sourcemap::{Mapping, Position, SourceMap},
+    transformers::{OutputFile, TransformOutput},
+    Result,
+};
+
+/// Directory name of the cache inside the output directory
+pub const CACHE_DIR: &str = ".utopia-cache";
+
+/// Bump when the on-disk format changes
+const CACHE_FORMAT_VERSION: u32 = 5;
+
+const INDEX_FILE: &str = "index.json";
+
+/// Stable 64-bit FNV-1a hash, used so cache keys survive compiler rebuilds
+pub fn content_hash(bytes: &[u8]) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let hash = bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+/// Hash an AST fragment, ignoring source positions so that edits elsewhere
+/// in the file do not invalidate it
+pub fn fragment_hash<T: Serialize>(fragment: &T) -> Result<String> {
+    let mut value = serde_json::to_value(fragment)?;
+    strip_spans(&mut value);
+    Ok(content_hash(value.to_string().as_bytes()))
+}
+
+/// Hash the line and column of every span in an AST fragment; the source map
+/// of the generated code changes whenever this does
+pub fn position_hash<T: Serialize>(fragment: &T) -> Result<String> {
+    let mut positions = Vec::new();
+    collect_positions(&serde_json::to_value(fragment)?, &mut positions);
+    let positions: String = positions
+        .iter()
+        .map(|position| format!("{}:{};", position.line, position.column))
+        .collect();
+    Ok(content_hash(positions.as_bytes()))
+}
+
+fn collect_positions(value: &serde_json::Value, positions: &mut Vec<Position>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map {
+                if key == "span" || key == "call_site" {
+                    positions.extend(Position::deserialize(child).ok());
+                } else {
+                    collect_positions(child, positions);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_positions(item, positions);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn strip_spans(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.remove("span");
+            map.remove("call_site");
+            for child in map.values_mut() {
+                strip_spans(child);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                strip_spans(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Fingerprint of a single compilation unit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitFingerprint {
+    pub hash: String,
+    /// Hash of the unit's source positions, see `position_hash`
+    pub positions: String,
+    pub dependencies: Vec<String>,
+}
+
+/// Fingerprints of every unit in a program, keyed by unit id
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitTable {
+    pub units: BTreeMap<String, UnitFingerprint>,
+}
+
+impl UnitTable {
+    /// Compute unit fingerprints for a parsed program
+    pub fn from_program(program: &Program) -> Result<Self> {
+        let call_graph = CallGraph::build(program);
+        let mut units = BTreeMap::new();
+
+        for (id, fragment) in unit_fragments(program)? {
+            let dependencies = call_graph
+                .callees(&id)
+                .into_iter()
+                .filter(|callee| *callee != id)
+                .map(str::to_string)
+                .collect();
+            let fingerprint = UnitFingerprint {
+                hash: fragment_hash(&fragment)?,
+                positions: position_hash(&fragment)?,
+                dependencies,
+            };
+            units.insert(id, fingerprint);
+        }
+
+        Ok(Self { units })
+    }
+}
+
+/// The AST fragment of every unit in a program; of two functions with the
+/// same name in one language only the first is a unit
+fn unit_fragments(program: &Program) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut fragments: Vec<(String, serde_json::Value)> = Vec::new();
+    let mut insert = |id: String, fragment: serde_json::Value| {
+        if !fragments.iter().any(|(known, _)| *known == id) {
+            fragments.push((id, fragment));
+        }
+    };
+
+    for (index, block) in program.language_blocks.iter().enumerate() {
+        for function in &block.functions {
+            insert(function_unit_id(&block.language, &function.name), serde_json::to_value(function)?);
+        }
+        if !block.statements.is_empty() {
+            let fragment = (&block.raw_content, &block.statements);
+            insert(block_unit_id(&block.language, index), serde_json::to_value(fragment)?);
+        }
+    }
+    if !program.global_statements.is_empty() {
+        insert(GLOBAL_UNIT.to_string(), serde_json::to_value(&program.global_statements)?);
+    }
+
+    Ok(fragments)
+}
+
+/// `program` with only the given units, to generate the code of just those
+pub fn partial_program(program: &Program, units: &BTreeSet<String>) -> Program {
+    let mut partial = program.clone();
+    for (index, block) in partial.language_blocks.iter_mut().enumerate() {
+        let language = block.language.clone();
+        block.functions.retain(|function| units.contains(&function_unit_id(&language, &function.name)));
+        if !units.contains(&block_unit_id(&language, index)) {
+            block.statements.clear();
+        }
+    }
+    if !units.contains(GLOBAL_UNIT) {
+        partial.global_statements.clear();
+    }
+    partial
+}
+
+/// What a run of generated lines came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Origin {
+    /// The lines before the first mapped one, such as imports
+    Start,
+    Unit(String),
+    /// Lines mapped to a span outside every unit, such as a language block's
+    Scaffolding(Position),
+}
+
+/// Consecutive lines (0-based, end exclusive) of generated code with one origin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Run {
+    start: usize,
+    end: usize,
+    origin: Origin,
+}
+
+/// Divide generated code into runs of lines by origin, using its source map
+///
+/// A mapped line comes from the unit holding the span it maps to. Inlined
+/// code keeps the spans of the function it came from, so a span held by
+/// several units belongs to the unit of the line above. A line without a
+/// mapping, such as a closing brace or a function body some backends don't
+/// map, belongs to the run above it. Returns `None` if a line can't be
+/// attributed to one unit.
+fn unit_runs(code: &str, source_map: &SourceMap, program: &Program) -> Result<Option<Vec<Run>>> {
+    let mut holders: HashMap<Position, Vec<String>> = HashMap::new();
+    for (id, fragment) in unit_fragments(program)? {
+        let mut positions = Vec::new();
+        collect_positions(&fragment, &mut positions);
+        for position in positions {
+            let units = holders.entry(position).or_default();
+            if !units.contains(&id) {
+                units.push(id.clone());
+            }
+        }
+    }
+
+    // The first mapping of every line, if it has one
+    let line_count = code.split('\n').count();
+    let mut mapped: Vec<Option<Position>> = vec![None; line_count];
+    for mapping in &source_map.mappings {
+        if let Some(line @ None) = mapped.get_mut(mapping.generated.line - 1) {
+            *line = Some(mapping.source);
+        }
+    }
+
+    let mut runs = vec![Run { start: 0, end: 0, origin: Origin::Start }];
+    for (line, source) in mapped.into_iter().enumerate() {
+        let above = &runs[runs.len() - 1].origin;
+        let origin = match source.map(|source| (source, holders.get(&source).map_or(&[][..], Vec::as_slice))) {
+            None => above.clone(),
+            Some((source, [])) => Origin::Scaffolding(source),
+            Some((_, [unit])) => Origin::Unit(unit.clone()),
+            Some((_, units)) => match above {
+                Origin::Unit(above) if units.contains(above) => Origin::Unit(above.clone()),
+                _ => return Ok(None),
+            },
+        };
+        let last = runs.len() - 1;
+        if runs[last].origin == origin {
+            runs[last].end = line + 1;
+        } else {
+            runs.push(Run { start: line, end: line + 1, origin });
+        }
+    }
+    Ok(Some(runs))
+}
+
+/// Why a unit keeps the cached output from being reused, if it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebuildReason {
+    UpToDate,
+    New,
+    Changed,
+    /// Unchanged, but at a different place in the source, so the source map is stale
+    Moved,
+    DependencyChanged(String),
+    Removed,
+    TargetNotCached,
+}
+
+impl fmt::Display for RebuildReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebuildReason::UpToDate => write!(f, "unchanged"),
+            RebuildReason::New => write!(f, "new unit"),
+            RebuildReason::Changed => write!(f, "source changed"),
+            RebuildReason::Moved => write!(f, "moved in the source"),
+            RebuildReason::DependencyChanged(dependency) => write!(f, "depends on changed unit {dependency}"),
+            RebuildReason::Removed => write!(f, "removed"),
+            RebuildReason::TargetNotCached => write!(f, "no cached output for this target"),
+        }
+    }
+}
+
+/// Per-unit rebuild decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitStatus {
+    pub unit: String,
+    pub reason: RebuildReason,
+}
+
+/// Result of comparing a program against the cache
+#[derive(Debug, Clone, Default)]
+pub struct RebuildPlan {
+    pub units: Vec<UnitStatus>,
+}
+
+impl RebuildPlan {
+    /// Whether the output has to be regenerated
+    pub fn needs_rebuild(&self) -> bool {
+        self.units.iter().any(|status| status.reason != RebuildReason::UpToDate)
+    }
+
+    /// Units whose changes cause the output to be regenerated
+    pub fn changed_units(&self) -> impl Iterator<Item = &UnitStatus> {
+        self.units.iter().filter(|status| status.reason != RebuildReason::UpToDate)
+    }
+}
+
+/// Why the output is generated as a whole instead of patching the cached one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WholeOutputReason {
+    NotCached,
+    /// The cached code could not be divided into the lines of each unit
+    Undivided,
+    UnitsAddedOrRemoved,
+    /// The new code of the units does not fit the cached file
+    Misfit,
+}
+
+impl fmt::Display for WholeOutputReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WholeOutputReason::NotCached => write!(f, "no cached output for this target"),
+            WholeOutputReason::Undivided => write!(f, "the cached code cannot be divided into units"),
+            WholeOutputReason::UnitsAddedOrRemoved => write!(f, "units were added or removed after optimization"),
+            WholeOutputReason::Misfit => write!(f, "the regenerated code does not fit the cached file"),
+        }
+    }
+}
+
+/// Generated code for one target, together with the settings it was built with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetOutput {
+    settings: String,
+    pub code: String,
+    pub source_map: SourceMap,
+    /// Files written next to the main file, such as the target's runtime
+    pub files: Vec<OutputFile>,
+    /// Names escaped for the target, reported by `--verbose`
+    pub renames: Vec<Rename>,
+    /// Fingerprints of the optimized program the code was generated from
+    units: UnitTable,
+    /// The lines of `code` by origin, if it could be divided
+    runs: Option<Vec<Run>>,
+}
+
+impl TargetOutput {
+    /// Output generated from `program`, the optimized program given to the backend
+    pub fn new(settings: &str, generated: &TransformOutput, program: &Program) -> Result<Self> {
+        let code = generated.code().to_string();
+        Ok(Self {
+            settings: settings.to_string(),
+            runs: unit_runs(&code, &generated.source_map, program)?,
+            code,
+            source_map: generated.source_map.clone(),
+            files: generated.files.iter().skip(1).cloned().collect(),
+            renames: generated.renames.clone(),
+            units: UnitTable::from_program(program)?,
+        })
+    }
+
+    /// Units whose code comes right before or after that of `units`; they
+    /// are generated along with them so that `splice` can check the new code
+    /// in the same surroundings
+    pub fn neighbours(&self, units: &BTreeSet<String>) -> BTreeSet<String> {
+        let runs = self.runs.as_deref().unwrap_or_default();
+        let mut neighbours = BTreeSet::new();
+        for (index, run) in runs.iter().enumerate() {
+            if matches!(&run.origin, Origin::Unit(unit) if units.contains(unit)) {
+                for adjacent in [index.checked_sub(1), Some(index + 1)].into_iter().flatten().filter_map(|index| runs.get(index)) {
+                    if let Origin::Unit(unit) = &adjacent.origin {
+                        if !units.contains(unit) {
+                            neighbours.insert(unit.clone());
+                        }
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// The output for `program`: this output with the runs of the
+    /// `regenerated` units replaced by their runs in `part`
+    ///
+    /// `part` is generated from `program` with only the regenerated units and
+    /// their `neighbours`. It fits if it starts with the same code (imports,
+    /// declarations, a bundled runtime), needs no files this output lacks,
+    /// and every regenerated unit has as many runs as here, each between runs
+    /// of the same origins. Unmapped lines at the end of a run can be
+    /// scaffolding that depends on units left out of `part`, or a body the
+    /// backend doesn't map, so those of a regenerated run and of the run
+    /// before it must be the same in both.
+    pub fn splice(&self, part: &TargetOutput, regenerated: &BTreeSet<String>, program: &Program) -> Result<std::result::Result<TargetOutput, WholeOutputReason>> {
+        let (Some(runs), Some(part_runs)) = (&self.runs, &part.runs) else {
+            return Ok(Err(WholeOutputReason::Undivided));
+        };
+        let old = self.mapped_lines();
+        let new = part.mapped_lines();
+        let lines = |lines: &[(&str, Vec<Mapping>)], run: &Run| lines[run.start..run.end].iter().map(|(line, _)| *line).collect::<Vec<_>>().join("\n");
+        if lines(&old, &runs[0]) != lines(&new, &part_runs[0]) || part.files.iter().any(|file| !self.files.contains(file)) {
+            return Ok(Err(WholeOutputReason::Misfit));
+        }
+
+        let mut replaced = Vec::new();
+        for unit in regenerated {
+            let of_unit = |runs: &[Run]| -> Vec<usize> {
+                (0..runs.len()).filter(|&index| matches!(&runs[index].origin, Origin::Unit(origin) if origin == unit)).collect()
+            };
+            let (old_runs, new_runs) = (of_unit(runs), of_unit(part_runs));
+            if old_runs.len() != new_runs.len() {
+                return Ok(Err(WholeOutputReason::Misfit));
+            }
+            for (index, new_index) in old_runs.into_iter().zip(new_runs) {
+                // Every unit run follows the start run
+                let (before, new_before) = (&runs[index - 1], &part_runs[new_index - 1]);
+                let after = runs.get(index + 1).map(|run| &run.origin);
+                let new_after = part_runs.get(new_index + 1).map(|run| &run.origin);
+                let (run, new_run) = (&runs[index], &part_runs[new_index]);
+                let tails_match = |old_run: &Run, new_run: &Run| {
+                    unmapped_tail(&old[old_run.start..old_run.end]) == unmapped_tail(&new[new_run.start..new_run.end])
+                };
+                if before.origin != new_before.origin || after != new_after || !tails_match(before, new_before) || !tails_match(run, new_run) {
+                    return Ok(Err(WholeOutputReason::Misfit));
+                }
+                replaced.push((run, new_run.start..new_run.end));
+            }
+        }
+        replaced.sort_unstable_by_key(|(run, _)| run.start);
+
+        let mut spliced = Vec::with_capacity(old.len());
+        let mut kept = 0;
+        for (run, new_lines) in replaced {
+            spliced.extend_from_slice(&old[kept..run.start]);
+            spliced.extend_from_slice(&new[new_lines]);
+            kept = run.end;
+        }
+        spliced.extend_from_slice(&old[kept..]);
+
+        let code = spliced.iter().map(|(line, _)| *line).collect::<Vec<_>>().join("\n");
+        let mappings = spliced
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (_, mappings))| {
+                mappings.iter().map(move |mapping| Mapping {
+                    generated: Position { line: index + 1, column: mapping.generated.column },
+                    source: mapping.source,
+                })
+            })
+            .collect();
+        let source_map = SourceMap { format: self.source_map.format, mappings };
+        let mut renames = self.renames.clone();
+        renames.extend(part.renames.iter().filter(|rename| !self.renames.contains(rename)).cloned());
+
+        Ok(Ok(Self {
+            settings: self.settings.clone(),
+            runs: unit_runs(&code, &source_map, program)?,
+            code,
+            source_map,
+            files: self.files.clone(),
+            renames,
+            units: UnitTable::from_program(program)?,
+        }))
+    }
+
+    /// Lines of the code, each with the mappings that start on it
+    fn mapped_lines(&self) -> Vec<(&str, Vec<Mapping>)> {
+        let mut lines: Vec<(&str, Vec<Mapping>)> = self.code.split('\n').map(|line| (line, Vec::new())).collect();
+        for mapping in &self.source_map.mappings {
+            if let Some((_, mappings)) = lines.get_mut(mapping.generated.line - 1) {
+                mappings.push(*mapping);
+            }
+        }
+        lines
+    }
+}
+
+/// The unmapped lines a run ends with
+fn unmapped_tail<'a>(lines: &[(&'a str, Vec<Mapping>)]) -> Vec<&'a str> {
+    lines.iter().rev().take_while(|(_, mappings)| mappings.is_empty()).map(|(line, _)| *line).collect()
+}
+
+/// Cached state of one input file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheEntry {
+    source_hash: String,
+    units: UnitTable,
+    outputs: BTreeMap<String, TargetOutput>,
+}
+
+impl CacheEntry {
+    fn output(&self, target: &str, settings: &str) -> Option<&TargetOutput> {
+        self.outputs.get(target).filter(|output| output.settings == settings)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheIndex {
+    format_version: u32,
+    compiler_version: String,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+impl Default for CacheIndex {
+    fn default() -> Self {
+        Self {
+            format_version: CACHE_FORMAT_VERSION,
+            compiler_version: crate::VERSION.to_string(),
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+/// On-disk build cache for one output directory
+pub struct BuildCache {
+    dir: PathBuf,
+    index: CacheIndex,
+}
+
+impl BuildCache {
+    /// Open (or start) the cache in `output_dir`. A cache written by another
+    /// compiler version or in an unreadable format is discarded.
+    pub fn open<P: AsRef<Path>>(output_dir: P) -> Result<Self> {
+        let dir = output_dir.as_ref().join(CACHE_DIR);
+        let index_path = dir.join(INDEX_FILE);
+
+        let index = match std::fs::read_to_string(&index_path) {
+            Ok(contents) => serde_json::from_str::<CacheIndex>(&contents)
+                .ok()
+                .filter(|index| {
+                    index.format_version == CACHE_FORMAT_VERSION && index.compiler_version == crate::VERSION
+                })
+                .unwrap_or_default(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CacheIndex::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self { dir, index })
+    }
+
+    /// Directory the cache is stored in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached output for `input` if neither the source text nor the target
+    /// settings changed since it was stored
+    pub fn fresh_output(&self, input: &str, source_hash: &str, target: &str, settings: &str) -> Option<&TargetOutput> {
+        let entry = self.index.entries.get(input)?;
+        if entry.source_hash != source_hash {
+            return None;
+        }
+        entry.output(target, settings)
+    }
+
+    /// Compare freshly computed units against the cached ones
+    pub fn plan(&self, input: &str, units: &UnitTable, target: &str, settings: &str) -> RebuildPlan {
+        let Some(entry) = self.index.entries.get(input) else {
+            return RebuildPlan {
+                units: units
+                    .units
+                    .keys()
+                    .map(|unit| UnitStatus { unit: unit.clone(), reason: RebuildReason::New })
+                    .collect(),
+            };
+        };
+
+        compare_units(&entry.units, units, entry.output(target, settings).is_some())
+    }
+
+    /// Compare the units of an optimized program against those the cached
+    /// output for `target` was generated from, giving that output and the
+    /// units whose code has to be regenerated into it
+    pub fn regeneration_plan(&self, input: &str, units: &UnitTable, target: &str, settings: &str) -> std::result::Result<(&TargetOutput, RebuildPlan), WholeOutputReason> {
+        let output = self.unit_output(input, target, settings).ok_or(WholeOutputReason::NotCached)?;
+        if output.runs.is_none() {
+            return Err(WholeOutputReason::Undivided);
+        }
+        let plan = compare_units(&output.units, units, true);
+        if plan.units.iter().any(|status| matches!(status.reason, RebuildReason::New | RebuildReason::Removed)) {
+            return Err(WholeOutputReason::UnitsAddedOrRemoved);
+        }
+        Ok((output, plan))
+    }
+
+    /// Cached output for `input` regardless of the source hash, to reuse or
+    /// patch as a plan says
+    pub fn unit_output(&self, input: &str, target: &str, settings: &str) -> Option<&TargetOutput> {
+        let entry = self.index.entries.get(input)?;
+        entry.output(target, settings)
+    }
+
+    /// Record the result of a compile
+    pub fn store(&mut self, input: &str, source_hash: &str, units: UnitTable, target: &str, output: TargetOutput) {
+        let entry = self.index.entries.entry(input.to_string()).or_default();
+
+        // Outputs for other targets are only valid for the units they were built
+        // from, and their source maps for the positions of those units
+        if entry.units != units {
+            entry.outputs.clear();
+        }
+        entry.source_hash = source_hash.to_string();
+        entry.units = units;
+        entry.outputs.insert(target.to_string(), output);
+    }
+
+    /// Refresh the source hash of an entry whose units did not change
+    pub fn touch(&mut self, input: &str, source_hash: &str) {
+        if let Some(entry) = self.index.entries.get_mut(input) {
+            entry.source_hash = source_hash.to_string();
+        }
+    }
+
+    /// Write the cache index to disk
+    pub fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let contents = serde_json::to_string(&self.index)?;
+        std::fs::write(self.dir.join(INDEX_FILE), contents)?;
+        Ok(())
+    }
+}
+
+/// Rebuild plan of `units` against the units an output was built from
+fn compare_units(previous: &UnitTable, units: &UnitTable, target_cached: bool) -> RebuildPlan {
+    let mut reasons: BTreeMap<String, RebuildReason> = BTreeMap::new();
+
+    for (unit, fingerprint) in &units.units {
+        let reason = match previous.units.get(unit) {
+            None => RebuildReason::New,
+            Some(previous) if previous.hash != fingerprint.hash => RebuildReason::Changed,
+            Some(_) if !target_cached => RebuildReason::TargetNotCached,
+            Some(previous) if previous.positions != fingerprint.positions => RebuildReason::Moved,
+            Some(_) => RebuildReason::UpToDate,
+        };
+        reasons.insert(unit.clone(), reason);
+    }
+
+    let removed: Vec<&String> = previous
+        .units
+        .keys()
+        .filter(|unit| !units.units.contains_key(*unit))
+        .collect();
+
+    // Propagate changes to every unit that (transitively) calls a changed
+    // or removed one; callers of removed units are found in the old table
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (unit, fingerprint) in units.units.iter().chain(&previous.units) {
+        for dependency in &fingerprint.dependencies {
+            dependents.entry(dependency.as_str()).or_default().push(unit.as_str());
+        }
+    }
+    let mut worklist: Vec<String> = reasons
+        .iter()
+        .filter(|(_, reason)| matches!(reason, RebuildReason::New | RebuildReason::Changed))
+        .map(|(unit, _)| unit.clone())
+        .chain(removed.iter().map(|unit| (*unit).clone()))
+        .collect();
+    while let Some(changed) = worklist.pop() {
+        for &dependent in dependents.get(changed.as_str()).into_iter().flatten() {
+            if let Some(reason) = reasons.get_mut(dependent) {
+                if matches!(reason, RebuildReason::UpToDate | RebuildReason::TargetNotCached) {
+                    *reason = RebuildReason::DependencyChanged(changed.clone());
+                    worklist.push(dependent.to_string());
+                }
+            }
+        }
+    }
+
+    let mut statuses: Vec<UnitStatus> = reasons
+        .into_iter()
+        .map(|(unit, reason)| UnitStatus { unit, reason })
+        .collect();
+    statuses.extend(
+        removed
+            .into_iter()
+            .map(|unit| UnitStatus { unit: unit.clone(), reason: RebuildReason::Removed }),
+    );
+
+    RebuildPlan { units: statuses }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lexer::Lexer,
+        parser::Parser,
+        pipeline::{NoHooks, PipelineOptions, Stage},
+        Compiler, Config, Span,
+    };
+
+    fn units(source: &str) -> UnitTable {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        UnitTable::from_program(&program).unwrap()
+    }
+
+    fn generated(code: &str) -> TransformOutput {
+        TransformOutput::new("python", ".py", code.to_string())
+    }
+
+    fn output(code: &str) -> TargetOutput {
+        TargetOutput::new("O2", &generated(code), &Program::new(Span::new(0, 0, 1, 1))).unwrap()
+    }
+
+    /// Optimize `source` for `target`, returning the program and the code generated from it
+    fn build(source: &str, target: &str, level: u8) -> (Program, TargetOutput) {
+        let mut config = Config::new();
+        config.optimization_level = level;
+        let mut compiler = Compiler::new(config);
+        let options = PipelineOptions::new(target);
+        let optimized = compiler.run(source, &options.clone().stop_after(Stage::Optimize), &mut NoHooks).unwrap();
+        let program = optimized.program.unwrap();
+        let generated = compiler.generate(&program, &options, &mut NoHooks).unwrap();
+        let output = TargetOutput::new("O2", generated.generated.as_ref().unwrap(), &program).unwrap();
+        (program, output)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("utopia-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    const PROGRAM: &str = "@lang main {\n\
+        function square(x) { return x * x; }\n\
+        function area(r) { return square(r) * 3; }\n\
+        function unrelated() { return 0; }\n\
+    }";
+
+    #[test]
+    fn test_fragment_hash_ignores_spans() {
+        let shifted = format!("\n\n{PROGRAM}");
+        let hashes = |table: UnitTable| table.units.into_values().map(|unit| unit.hash).collect::<Vec<_>>();
+        assert_eq!(hashes(units(PROGRAM)), hashes(units(&shifted)));
+
+        // Moving units invalidates their source map, so they are rebuilt
+        let mut cache = BuildCache::open(temp_dir("moved")).unwrap();
+        cache.store("a.uto", "h1", units(PROGRAM), "python", output("code"));
+        let plan = cache.plan("a.uto", &units(&shifted), "python", "O2");
+        assert!(plan.units.iter().all(|status| status.reason == RebuildReason::Moved));
+        let trailing = PROGRAM.replace("x * x; }", "x * x; }   ");
+        assert!(!cache.plan("a.uto", &units(&trailing), "python", "O2").needs_rebuild());
+    }
+
+    #[test]
+    fn test_plan_rebuilds_changed_unit_and_dependents() {
+        let mut cache = BuildCache::open(temp_dir("plan")).unwrap();
+        cache.store("a.uto", "h1", units(PROGRAM), "python", output("code"));
+
+        let edited = PROGRAM.replace("x * x", "x * x * 1");
+        let plan = cache.plan("a.uto", &units(&edited), "python", "O2");
+
+        let reason = |unit: &str| plan.units.iter().find(|s| s.unit == unit).unwrap().reason.clone();
+        assert_eq!(reason("main::square"), RebuildReason::Changed);
+        assert_eq!(reason("main::area"), RebuildReason::DependencyChanged("main::square".to_string()));
+        assert_eq!(reason("main::unrelated"), RebuildReason::UpToDate);
+        assert!(plan.needs_rebuild());
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let mut cache = BuildCache::open(&dir).unwrap();
+        cache.store("a.uto", "h1", units(PROGRAM), "python", output("print(1)\n"));
+        cache.save().unwrap();
+
+        let reopened = BuildCache::open(&dir).unwrap();
+        let code = |output: Option<&TargetOutput>| output.map(|output| output.code.clone());
+        assert_eq!(code(reopened.fresh_output("a.uto", "h1", "python", "O2")), Some("print(1)\n".to_string()));
+        assert_eq!(code(reopened.fresh_output("a.uto", "h2", "python", "O2")), None);
+        assert_eq!(code(reopened.fresh_output("a.uto", "h1", "python", "O3")), None);
+        assert!(!reopened.plan("a.uto", &units(PROGRAM), "python", "O2").needs_rebuild());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Patch the cached output of `source` with the units of `edited` that changed
+    fn splice(source: &str, edited: &str, target: &str, level: u8) -> (std::result::Result<TargetOutput, WholeOutputReason>, TargetOutput, BTreeSet<String>) {
+        let (_, cached) = build(source, target, level);
+        let (program, whole) = build(edited, target, level);
+        let mut cache = BuildCache::open(temp_dir(&format!("splice-{target}-{level}"))).unwrap();
+        cache.store("a.uto", "h1", units(source), target, cached);
+
+        let (cached, plan) = cache
+            .regeneration_plan("a.uto", &UnitTable::from_program(&program).unwrap(), target, "O2")
+            .unwrap();
+        let regenerated: BTreeSet<String> = plan.changed_units().map(|status| status.unit.clone()).collect();
+        let generated_units = regenerated.union(&cached.neighbours(&regenerated)).cloned().collect();
+        let partial = partial_program(&program, &generated_units);
+        let mut compiler = Compiler::new(Config::new());
+        let generated = compiler.generate(&partial, &PipelineOptions::new(target), &mut NoHooks).unwrap();
+        let part = TargetOutput::new("O2", generated.generated.as_ref().unwrap(), &partial).unwrap();
+        (cached.splice(&part, &regenerated, &program).unwrap(), whole, regenerated)
+    }
+
+    #[test]
+    fn test_regenerated_units_are_spliced_into_cached_output() {
+        let source = "@lang main {\n\
+            function square(x) { return x * x; }\n\
+            function area(r) {\n\
+                let a = square(r);\n\
+                if (a > 10) { println(\"big\"); }\n\
+                return a * 3;\n\
+            }\n\
+            function unrelated(n) {\n\
+                let total = 0;\n\
+                for (let i = 0; i < n; i = i + 1) { total = total + i; }\n\
+                return total;\n\
+            }\n\
+            println(area(2));\n\
+            println(unrelated(4));\n\
+        }";
+        let edited = source.replace("return x * x;", "return x * x * x;");
+
+        for target in ["python", "javascript", "ruby", "c", "java", "kotlin"] {
+            for level in [0, 2] {
+                let (spliced, whole, regenerated) = splice(source, &edited, target, level);
+                if level == 0 {
+                    assert!(!regenerated.contains("main::unrelated"), "{target}: {regenerated:?}");
+                }
+                let spliced = spliced.unwrap_or_else(|reason| panic!("{target} at O{level}: {reason}"));
+                assert_eq!(spliced.code, whole.code, "{target} at O{level}");
+                assert_eq!(spliced.source_map, whole.source_map, "{target} at O{level}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_splice_rejects_code_that_depends_on_other_units() {
+        // The Python block declares stubs for the JavaScript functions, which
+        // a program holding only the changed Python unit does not have
+        let source = "@lang python {\n\
+            function greet(name) { return \"hi \" + name; }\n\
+            println(greet(\"a\"));\n\
+        }\n\
+        @lang javascript {\n\
+            function half(n) { return n / 2; }\n\
+            console.log(half(8));\n\
+        }\n";
+        let edited = source.replace("\"hi \" + name", "name + \"!\"");
+        let (spliced, _, _) = splice(source, &edited, "python", 0);
+        assert_eq!(spliced.err(), Some(WholeOutputReason::Misfit));
+    }
+}
@@ -0,0 +1,386 @@
+//! Call graph for Utopia programs
+//!
+//! Builds a directed graph of calls between compilation units (functions and
+//! top-level statement groups), following both same-language calls and
+//! cross-language `language::function` calls.
+
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::{Bfs, Reversed};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::ast::*;
+
+/// Unit id used for the program's global statements
+pub const GLOBAL_UNIT: &str = "<global>";
+
+/// Kind of compilation unit in the call graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitKind {
+    Function,
+    Statements,
+}
+
+/// A node in the call graph
+#[derive(Debug, Clone)]
+pub struct CallGraphNode {
+    pub id: String,
+    pub language: String,
+    pub kind: UnitKind,
+}
+
+/// Directed call graph: an edge `a -> b` means unit `a` calls unit `b`
+pub struct CallGraph {
+    graph: DiGraph<CallGraphNode, ()>,
+    nodes: HashMap<String, NodeIndex>,
+}
+
+impl CallGraph {
+    /// Build the call graph for a program
+    pub fn build(program: &Program) -> Self {
+        let mut call_graph = Self {
+            graph: DiGraph::new(),
+            nodes: HashMap::new(),
+        };
+
+        // Register every unit first so calls can be resolved regardless of order
+        for (index, block) in program.language_blocks.iter().enumerate() {
+            for function in &block.functions {
+                call_graph.add_node(function_unit_id(&block.language, &function.name), &block.language, UnitKind::Function);
+            }
+            for name in declared_functions(&block.statements) {
+                call_graph.add_node(function_unit_id(&block.language, name), &block.language, UnitKind::Function);
+            }
+            if !block.statements.is_empty() {
+                call_graph.add_node(block_unit_id(&block.language, index), &block.language, UnitKind::Statements);
+            }
+        }
+        for name in declared_functions(&program.global_statements) {
+            call_graph.add_node(function_unit_id("global", name), "global", UnitKind::Function);
+        }
+        if !program.global_statements.is_empty() {
+            call_graph.add_node(GLOBAL_UNIT.to_string(), "global", UnitKind::Statements);
+        }
+
+        // Add call edges
+        for (index, block) in program.language_blocks.iter().enumerate() {
+            for function in &block.functions {
+                let caller = function_unit_id(&block.language, &function.name);
+                call_graph.add_calls(&caller, &block.language, &function.body);
+            }
+            for statement in &block.statements {
+                if let Statement::FunctionDeclaration { name, body, .. } = statement {
+                    let caller = function_unit_id(&block.language, name);
+                    call_graph.add_calls(&caller, &block.language, body);
+                }
+            }
+            if !block.statements.is_empty() {
+                let caller = block_unit_id(&block.language, index);
+                call_graph.add_calls(&caller, &block.language, &block.statements);
+            }
+        }
+        for statement in &program.global_statements {
+            if let Statement::FunctionDeclaration { name, body, .. } = statement {
+                let caller = function_unit_id("global", name);
+                call_graph.add_calls(&caller, "global", body);
+            }
+        }
+        if !program.global_statements.is_empty() {
+            call_graph.add_calls(GLOBAL_UNIT, "global", &program.global_statements);
+        }
+
+        call_graph
+    }
+
+    fn add_node(&mut self, id: String, language: &str, kind: UnitKind) {
+        if self.nodes.contains_key(&id) {
+            return;
+        }
+        let index = self.graph.add_node(CallGraphNode {
+            id: id.clone(),
+            language: language.to_string(),
+            kind,
+        });
+        self.nodes.insert(id, index);
+    }
+
+    fn add_calls(&mut self, caller: &str, language: &str, statements: &[Statement]) {
+        let Some(&from) = self.nodes.get(caller) else {
+            return;
+        };
+
+        let mut calls = Vec::new();
+        collect_calls_in_statements(statements, &mut calls);
+
+        for call in calls {
+            let target = match call {
+                CallRef::Local(name) => self.resolve_local(language, &name),
+                CallRef::Cross(target_language, name) => {
+                    self.nodes.get(&function_unit_id(&target_language, &name)).copied()
+                }
+            };
+            if let Some(to) = target {
+                if !self.graph.contains_edge(from, to) {
+                    self.graph.add_edge(from, to, ());
+                }
+            }
+        }
+    }
+
+    /// Resolve a plain call: prefer the caller's language, then globals, then any block
+    fn resolve_local(&self, language: &str, name: &str) -> Option<NodeIndex> {
+        if let Some(&index) = self.nodes.get(&function_unit_id(language, name)) {
+            return Some(index);
+        }
+        if let Some(&index) = self.nodes.get(&function_unit_id("global", name)) {
+            return Some(index);
+        }
+        let suffix = format!("::{name}");
+        self.graph
+            .node_indices()
+            .find(|&index| {
+                let node = &self.graph[index];
+                node.kind == UnitKind::Function && node.id.ends_with(&suffix)
+            })
+    }
+
+    /// Check whether a unit exists in the graph
+    pub fn contains(&self, id: &str) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    /// All units in the graph
+    pub fn units(&self) -> impl Iterator<Item = &CallGraphNode> {
+        self.graph.node_weights()
+    }
+
+    /// Units called directly by `id`
+    pub fn callees(&self, id: &str) -> Vec<&str> {
+        self.neighbors(id, petgraph::Direction::Outgoing)
+    }
+
+    /// Units that call `id` directly
+    pub fn callers(&self, id: &str) -> Vec<&str> {
+        self.neighbors(id, petgraph::Direction::Incoming)
+    }
+
+    fn neighbors(&self, id: &str, direction: petgraph::Direction) -> Vec<&str> {
+        let Some(&index) = self.nodes.get(id) else {
+            return Vec::new();
+        };
+        let mut neighbors: Vec<&str> = self
+            .graph
+            .neighbors_directed(index, direction)
+            .map(|neighbor| self.graph[neighbor].id.as_str())
+            .collect();
+        neighbors.sort_unstable();
+        neighbors
+    }
+
+    /// Whether `id` can call itself, directly or through other units
+    pub fn is_recursive(&self, id: &str) -> bool {
+        self.reachable_from(self.callees(id)).contains(id)
+    }
+
+    /// Every unit that transitively calls one of `roots`, excluding the roots themselves
+    pub fn transitive_callers<'a>(&self, roots: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+        let reversed = Reversed(&self.graph);
+        let mut result = BTreeSet::new();
+        let roots: Vec<NodeIndex> = roots
+            .into_iter()
+            .filter_map(|id| self.nodes.get(id).copied())
+            .collect();
+
+        for &root in &roots {
+            let mut bfs = Bfs::new(reversed, root);
+            while let Some(index) = bfs.next(reversed) {
+                if !roots.contains(&index) {
+                    result.insert(self.graph[index].id.clone());
+                }
+            }
+        }
+        result
+    }
+
+    /// Every unit reachable from `roots` by following calls, including the roots
+    pub fn reachable_from<'a>(&self, roots: impl IntoIterator<Item = &'a str>) -> BTreeSet<String> {
+        let mut result = BTreeSet::new();
+        for root in roots {
+            let Some(&index) = self.nodes.get(root) else {
+                continue;
+            };
+            let mut bfs = Bfs::new(&self.graph, index);
+            while let Some(visited) = bfs.next(&self.graph) {
+                result.insert(self.graph[visited].id.clone());
+            }
+        }
+        result
+    }
+}
+
+/// Unit id for a function defined in a language block
+pub fn function_unit_id(language: &str, name: &str) -> String {
+    format!("{language}::{name}")
+}
+
+/// Unit id for the top-level statements of the `index`-th language block
+pub fn block_unit_id(language: &str, index: usize) -> String {
+    format!("{language}#{index}")
+}
+
+fn declared_functions(statements: &[Statement]) -> impl Iterator<Item = &str> {
+    statements.iter().filter_map(|statement| match statement {
+        Statement::FunctionDeclaration { name, .. } => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+/// A call site found while walking the AST
+enum CallRef {
+    Local(String),
+    Cross(String, String),
+}
+
+fn collect_calls_in_statements(statements: &[Statement], calls: &mut Vec<CallRef>) {
+    for statement in statements {
+        collect_calls_in_statement(statement, calls);
+    }
+}
+
+fn collect_calls_in_statement(statement: &Statement, calls: &mut Vec<CallRef>) {
+    match statement {
+        Statement::Expression { expression, .. } => collect_calls_in_expression(expression, calls),
+        Statement::VariableDeclaration { value, .. } | Statement::Return { value, .. } => {
+            if let Some(value) = value {
+                collect_calls_in_expression(value, calls);
+            }
+        }
+        Statement::Assignment { target, value, .. } => {
+            collect_calls_in_expression(target, calls);
+            collect_calls_in_expression(value, calls);
+        }
+        Statement::If { condition, then_branch, else_branch, .. } => {
+            collect_calls_in_expression(condition, calls);
+            collect_calls_in_statements(then_branch, calls);
+            if let Some(else_branch) = else_branch {
+                collect_calls_in_statements(else_branch, calls);
+            }
+        }
+        Statement::While { condition, body, .. } => {
+            collect_calls_in_expression(condition, calls);
+            collect_calls_in_statements(body, calls);
+        }
+        Statement::For { init, condition, update, body, .. } => {
+            if let Some(init) = init {
+                collect_calls_in_statement(init, calls);
+            }
+            if let Some(condition) = condition {
+                collect_calls_in_expression(condition, calls);
+            }
+            if let Some(update) = update {
+                collect_calls_in_expression(update, calls);
+            }
+            collect_calls_in_statements(body, calls);
+        }
+        Statement::Block { statements, .. } => collect_calls_in_statements(statements, calls),
+        Statement::ClassDeclaration { methods, .. } => {
+            for method in methods {
+                collect_calls_in_statements(&method.body, calls);
+            }
+        }
+        // Nested declarations are units of their own
+        Statement::FunctionDeclaration { .. } | Statement::Import { .. } | Statement::Export { .. } => {}
+    }
+}
+
+fn collect_calls_in_expression(expression: &Expression, calls: &mut Vec<CallRef>) {
+    match expression {
+        Expression::Call { callee, arguments, .. } => {
+            if let Expression::Identifier { name, .. } = callee.as_ref() {
+                calls.push(CallRef::Local(name.clone()));
+            } else {
+                collect_calls_in_expression(callee, calls);
+            }
+            for argument in arguments {
+                collect_calls_in_expression(argument, calls);
+            }
+        }
+        Expression::CrossCall { language, function, arguments, .. } => {
+            calls.push(CallRef::Cross(language.clone(), function.clone()));
+            for argument in arguments {
+                collect_calls_in_expression(argument, calls);
+            }
+        }
+        // A bare reference to a function (e.g. passed as a callback) is also a use
+        Expression::Identifier { name, .. } => calls.push(CallRef::Local(name.clone())),
+        Expression::Binary { left, right, .. } => {
+            collect_calls_in_expression(left, calls);
+            collect_calls_in_expression(right, calls);
+        }
+        Expression::Unary { operand, .. } | Expression::Postfix { operand, .. } => {
+            collect_calls_in_expression(operand, calls);
+        }
+        Expression::Assignment { target, value, .. } => {
+            collect_calls_in_expression(target, calls);
+            collect_calls_in_expression(value, calls);
+        }
+        Expression::MemberAccess { object, .. } => collect_calls_in_expression(object, calls),
+        Expression::ArrayAccess { array, index, .. } => {
+            collect_calls_in_expression(array, calls);
+            collect_calls_in_expression(index, calls);
+        }
+        Expression::Array { elements, .. } => {
+            for element in elements {
+                collect_calls_in_expression(element, calls);
+            }
+        }
+        Expression::Object { properties, .. } => {
+            for value in properties.values() {
+                collect_calls_in_expression(value, calls);
+            }
+        }
+        Expression::Lambda { body, .. } => collect_calls_in_statements(body, calls),
+        Expression::Literal { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn parse(source: &str) -> Program {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_cross_language_edges() {
+        let program = parse(
+            "@lang python { function helper(x) { return x * 2; } }\n\
+             @lang javascript { function main() { return python::helper(21); } }",
+        );
+        let graph = CallGraph::build(&program);
+
+        assert_eq!(graph.callees("javascript::main"), vec!["python::helper"]);
+        assert_eq!(graph.callers("python::helper"), vec!["javascript::main"]);
+    }
+
+    #[test]
+    fn test_transitive_callers() {
+        let program = parse(
+            "@lang main {\n\
+                 function a() { return b(); }\n\
+                 function b() { return c(); }\n\
+                 function c() { return 1; }\n\
+                 function d() { return 2; }\n\
+             }",
+        );
+        let graph = CallGraph::build(&program);
+
+        let callers = graph.transitive_callers(["main::c"]);
+        assert!(callers.contains("main::a"));
+        assert!(callers.contains("main::b"));
+        assert!(!callers.contains("main::c"));
+        assert!(!callers.contains("main::d"));
+    }
+}
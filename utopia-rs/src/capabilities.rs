@@ -0,0 +1,244 @@
+//! Target capabilities
+//!
+//! Every transformer declares which language features its target can express.
+//! Before code generation the program is checked against the chosen target, so
+//! that a lambda compiled to COBOL or an array compiled to SQL is reported at
+//! its location instead of turning into a placeholder in the output.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{ast::*, diagnostics::Diagnostic, Span};
+
+/// A language feature a target may lack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    Classes,
+    Lambdas,
+    /// Raising errors at run time; no Utopia syntax requires it yet, but
+    /// the generated runtime uses it to fail unresolved cross-language calls
+    Exceptions,
+    /// Object literals and field access, which need heap allocation
+    DynamicObjects,
+    Arrays,
+    CrossCalls,
+    Modules,
+}
+
+impl Feature {
+    /// All features, in the order of the capability matrix
+    pub const ALL: [Feature; 7] = [
+        Feature::Classes,
+        Feature::Lambdas,
+        Feature::Exceptions,
+        Feature::DynamicObjects,
+        Feature::Arrays,
+        Feature::CrossCalls,
+        Feature::Modules,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Classes => "classes",
+            Feature::Lambdas => "lambdas",
+            Feature::Exceptions => "exceptions",
+            Feature::DynamicObjects => "dynamic objects",
+            Feature::Arrays => "arrays",
+            Feature::CrossCalls => "cross-language calls",
+            Feature::Modules => "imports and exports",
+        }
+    }
+
+    /// Column heading in the capability matrix
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Feature::Classes => "class",
+            Feature::Lambdas => "lambda",
+            Feature::Exceptions => "except",
+            Feature::DynamicObjects => "object",
+            Feature::Arrays => "array",
+            Feature::CrossCalls => "xcall",
+            Feature::Modules => "module",
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The features a target supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    features: BTreeSet<Feature>,
+}
+
+impl Capabilities {
+    /// A target that can express every feature
+    pub fn all() -> Self {
+        Self { features: Feature::ALL.into_iter().collect() }
+    }
+
+    /// Every feature except `missing`
+    pub fn without(missing: &[Feature]) -> Self {
+        Self { features: Feature::ALL.into_iter().filter(|feature| !missing.contains(feature)).collect() }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn missing(&self) -> Vec<Feature> {
+        Feature::ALL.into_iter().filter(|feature| !self.supports(*feature)).collect()
+    }
+
+    /// Report every use of a feature the target lacks, in source order
+    ///
+    /// Only code the target generates is checked: the blocks whose language
+    /// `generates_block` accepts, and global code if `generates_global_code`.
+    pub fn check(&self, program: &Program, target: &str, generates_block: impl Fn(&str) -> bool, generates_global_code: bool) -> Vec<Diagnostic> {
+        let mut uses = Vec::new();
+        for block in program.language_blocks.iter().filter(|block| generates_block(&block.language)) {
+            for function in &block.functions {
+                feature_uses(&function.body, &mut uses);
+            }
+            feature_uses(&block.statements, &mut uses);
+        }
+        if generates_global_code {
+            feature_uses(&program.global_statements, &mut uses);
+        }
+
+        uses.sort_by_key(|(_, span)| (span.line, span.column));
+        uses.into_iter()
+            .filter(|(feature, _)| !self.supports(*feature))
+            .map(|(feature, span)| Diagnostic::error(format!("{} does not support {}", target, feature), span))
+            .collect()
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Features used by the statements, with the location of each use
+fn feature_uses(statements: &[Statement], uses: &mut Vec<(Feature, Span)>) {
+    statement_uses(statements, uses);
+    // `walk_expressions` descends into nested statements and function bodies itself
+    for statement in statements {
+        statement.walk_expressions(&mut |expression| expression_uses(expression, uses));
+    }
+}
+
+fn statement_uses(statements: &[Statement], uses: &mut Vec<(Feature, Span)>) {
+    for statement in statements {
+        match statement {
+            Statement::ClassDeclaration { methods, span, .. } => {
+                uses.push((Feature::Classes, *span));
+                for method in methods {
+                    statement_uses(&method.body, uses);
+                }
+            }
+            Statement::Import { span, .. } | Statement::Export { span, .. } => uses.push((Feature::Modules, *span)),
+            Statement::If { then_branch, else_branch, .. } => {
+                statement_uses(then_branch, uses);
+                statement_uses(else_branch.as_deref().unwrap_or_default(), uses);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } | Statement::FunctionDeclaration { body, .. } => {
+                statement_uses(body, uses);
+            }
+            Statement::For { init, body, .. } => {
+                statement_uses(init.as_deref().map(std::slice::from_ref).unwrap_or_default(), uses);
+                statement_uses(body, uses);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn expression_uses(expression: &Expression, uses: &mut Vec<(Feature, Span)>) {
+    let feature = match expression {
+        Expression::Lambda { .. } => Feature::Lambdas,
+        Expression::Object { .. } => Feature::DynamicObjects,
+        Expression::Array { .. } | Expression::ArrayAccess { .. } => Feature::Arrays,
+        Expression::CrossCall { .. } => Feature::CrossCalls,
+        _ => return,
+    };
+    uses.push((feature, expression.span()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pipeline::{NoHooks, PipelineOptions}, transformers::TransformerManager, Compiler, Config};
+
+    const SOURCE: &str = "function total(xs) {\n    let sum = 0;\n    for (let i = 0; i < len(xs); i++) {\n        sum = sum + xs[i];\n    }\n    return sum;\n}\nlet point = {x: 1, y: 2};\nprint(total([1, 2, 3]));\n";
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_reports_missing_features_at_their_use() {
+        let program = parse(SOURCE);
+        assert!(Capabilities::all().check(&program, "python", |_| true, true).is_empty());
+
+        let diagnostics = Capabilities::without(&[Feature::Arrays, Feature::DynamicObjects]).check(&program, "sql", |_| true, true);
+        let found: Vec<(String, usize, usize)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.message.clone(), diagnostic.span.line, diagnostic.span.column))
+            .collect();
+        assert_eq!(found, vec![
+            ("sql does not support arrays".to_string(), 4, 21),
+            ("sql does not support dynamic objects".to_string(), 8, 13),
+            ("sql does not support arrays".to_string(), 9, 13),
+        ]);
+    }
+
+    #[test]
+    fn test_targets_declare_their_features() {
+        let transformers = TransformerManager::new();
+        assert_eq!(transformers.capabilities("python"), Some(Capabilities::all()));
+        assert!(!transformers.capabilities("sql").unwrap().supports(Feature::Arrays));
+        assert!(!transformers.capabilities("cobol").unwrap().supports(Feature::Lambdas));
+        assert!(!transformers.capabilities("embedded-c").unwrap().supports(Feature::DynamicObjects));
+        // Aliases share their backend's row
+        let matrix = transformers.capability_matrix();
+        assert!(matrix.contains_key("embedded-c") && !matrix.contains_key("arduino"));
+
+        let mut compiler = Compiler::new(Config::new());
+        let output = compiler.run(SOURCE, &PipelineOptions::new("sql"), &mut NoHooks).unwrap();
+        assert!(output.has_errors());
+        assert!(output.code.is_none());
+        let partial = compiler.run(SOURCE, &PipelineOptions::new("sql").allow_partial(true), &mut NoHooks).unwrap();
+        assert!(!partial.has_errors() && partial.code.is_some());
+    }
+
+    #[test]
+    fn test_checks_only_generated_code() {
+        // C reports global code as dropped and leaves Python blocks to the runtime
+        let program = parse("@lang python {\nlet p = {x: 1};\n}\n@lang main {\nlet q = {x: 2};\n}\nlet r = {x: 3};\n");
+        let transformers = TransformerManager::new();
+        let lines = |target: &str| -> Vec<usize> {
+            transformers.check_capabilities(target, &program).unwrap().iter().map(|diagnostic| diagnostic.span.line).collect()
+        };
+        assert_eq!(lines("c"), vec![5]);
+        assert_eq!(lines("cobol"), vec![5, 7]);
+    }
+
+    /// The matrix lists what the target language can express; constructs a
+    /// backend does not lower yet are reported as untranslatable instead
+    #[test]
+    fn test_codegen_gaps_are_not_missing_features() {
+        let program = parse("@lang main {\nlet xs = [1, 2];\nprint(xs[0]);\n}\n");
+        let transformers = TransformerManager::new();
+        assert!(transformers.check_capabilities("python", &program).unwrap().is_empty());
+
+        let output = transformers.transform("python", &program).unwrap();
+        assert!(!output.diagnostics.is_empty());
+        assert!(output.diagnostics.iter().all(|diagnostic| diagnostic.message.starts_with("python backend cannot translate")), "{:?}", output.diagnostics);
+    }
+}
@@ -5,6 +5,7 @@
 
 use clap::{Parser, Subcommand, ValueEnum};
 use colored::*;
+use std::collections::BTreeSet;
 use std::path::Path;
 use std::time::Instant;
 use std::io::Write;
@@ -12,12 +13,25 @@ use std::io::Write;
 use crate::{
     Compiler, 
     Config, 
+    Program,
+    ProjectConfig,
 
-    transformers::TransformerManager,
+    transformers::{Transformer, TransformerManager},
+    templates::TemplateTransformer,
+    plugin::{PluginTransformer, PLUGIN_PREFIX},
+    diagnostics::{Diagnostic, DiagnosticKind},
+    optimizer::{DumpAfter, OptimizationStats, PassPipeline},
+    pipeline::{EmitKind, NoHooks, PipelineHooks, PipelineOptions, PipelineOutput, Stage},
+    cache::{content_hash, partial_program, BuildCache, RebuildPlan, TargetOutput, UnitTable, WholeOutputReason},
+    callgraph::CallGraph,
+    capabilities::Feature,
+    effects::EffectAnalysis,
     reverse::ReverseCompiler,
     lexer::Lexer,
-    parser::Parser as UtopiaParser,
+    mangle::Rename,
+    sourcemap::{SourceMap, SourceMapFormat},
     utils::{read_file, write_file},
+    watch::FileWatcher,
     Result,
 };
 
@@ -51,9 +65,18 @@ pub enum Commands {
         #[arg(short, long)]
         output: Option<String>,
         
-        /// Target language/platform
-        #[arg(short, long, default_value = "assembly")]
-        target: String,
+        /// Target language/platform, a comma-separated list of targets, or `all`
+        /// (default `assembly`, or the first target loaded with --target-dir)
+        #[arg(short, long)]
+        target: Option<String>,
+        
+        /// Load a user-defined target from a template directory (repeatable)
+        #[arg(long, value_name = "DIR")]
+        target_dir: Vec<String>,
+        
+        /// Read the input as an AST JSON document instead of Utopia source
+        #[arg(long)]
+        from_ast: bool,
         
         /// Optimization level (0-3)
         #[arg(short = 'O', long, default_value = "2")]
@@ -70,6 +93,31 @@ pub enum Commands {
         /// Show generated code
         #[arg(long)]
         show_code: bool,
+        
+        /// Output to produce: an intermediate result or the generated code
+        #[arg(long, value_enum, default_value = "code")]
+        emit: EmitKind,
+        
+        /// Optimizer options: passes=+pass,-pass, dump-after=pass,...|all or fast-math=on|off
+        #[arg(short = 'C', long = "codegen", value_name = "OPTION=VALUE")]
+        codegen: Vec<String>,
+        
+        /// Disable the incremental build cache
+        #[arg(long)]
+        no_cache: bool,
+        
+        /// Explain which units changed and which were regenerated
+        #[arg(long)]
+        explain_rebuild: bool,
+        
+        /// Write the output even if the target cannot translate some constructs
+        #[arg(long)]
+        allow_partial: bool,
+        
+        /// Paste the target's runtime support files into the output instead of
+        /// writing them next to it
+        #[arg(long)]
+        bundle_runtime: bool,
     },
     
     /// Convert from another language to Utopia format
@@ -160,6 +208,10 @@ pub enum Commands {
         /// Information type
         #[arg(short, long, default_value = "all")]
         info_type: InfoType,
+        
+        /// Print which language features every target supports
+        #[arg(long)]
+        targets: bool,
     },
     
     /// Benchmark compilation performance
@@ -219,6 +271,45 @@ pub enum Commands {
         target: String,
     },
     
+    /// Watch Utopia files and rebuild on change
+    Watch {
+        /// File or project directory to watch
+        #[arg(default_value = ".")]
+        input: String,
+        
+        /// Target languages to compile (defaults to the targets in utopia.toml)
+        #[arg(short, long)]
+        target: Vec<String>,
+        
+        /// Output directory (defaults to utopia.toml, then next to each source)
+        #[arg(long)]
+        out_dir: Option<String>,
+        
+        /// Optimization level (0-3)
+        #[arg(short = 'O', long, default_value = "2")]
+        optimization: u8,
+        
+        /// Restart the program after every successful rebuild
+        #[arg(long)]
+        run: bool,
+        
+        /// Target platform used to execute the program with --run
+        #[arg(long, default_value = "python")]
+        run_target: String,
+        
+        /// Polling interval in milliseconds
+        #[arg(long, default_value = "250")]
+        interval: u64,
+        
+        /// Wait until files have been quiet this long (ms) before rebuilding
+        #[arg(long, default_value = "300")]
+        debounce: u64,
+        
+        /// Arguments to pass to the program with --run
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    
     /// Clean build artifacts
     Clean {
         /// Project directory
@@ -274,9 +365,26 @@ pub fn run_cli() -> Result<()> {
     
     match cli.command {
         Commands::Compile { 
-            input, output, target, optimization, debug, stats, show_code 
+            input, output, target, target_dir, from_ast, optimization, debug, stats, show_code, emit, codegen, no_cache, explain_rebuild, allow_partial, bundle_runtime 
         } => {
-            handle_compile(input, output, target, optimization, debug, stats, show_code, cli.verbose)
+            let template_targets = target_dir.iter()
+                .map(TemplateTransformer::load)
+                .collect::<Result<Vec<_>>>()?;
+            let target = target
+                .or_else(|| template_targets.first().map(|loaded| loaded.target_name().to_string()))
+                .unwrap_or_else(|| "assembly".to_string());
+            let plugins = target.split(',')
+                .map(str::trim)
+                .filter(|name| name.starts_with(PLUGIN_PREFIX))
+                .map(PluginTransformer::load)
+                .collect::<Result<Vec<_>>>()?;
+            let cache_options = CacheOptions { enabled: !no_cache, explain_rebuild };
+            let settings = CompileSettings { optimization, emit, codegen, debug, allow_partial, bundle_runtime, from_ast, template_targets, plugins };
+            if target == "all" || target.contains(',') {
+                handle_compile_targets(input, output, &target, settings, cli.verbose)
+            } else {
+                handle_compile(input, output, target, settings, stats, show_code, cache_options, cli.verbose)
+            }
         }
         
         Commands::Convert { 
@@ -303,8 +411,13 @@ pub fn run_cli() -> Result<()> {
             handle_lsp(version, debug)
         }
         
-        Commands::Info { info_type } => {
-            handle_info(info_type)
+        Commands::Info { info_type, targets } => {
+            if targets {
+                print_capability_matrix(&TransformerManager::new());
+                Ok(())
+            } else {
+                handle_info(info_type)
+            }
         }
         
         Commands::Benchmark { input, iterations, targets, output } => {
@@ -323,6 +436,37 @@ pub fn run_cli() -> Result<()> {
             handle_run(input, args, target, cli.verbose)
         }
         
+        Commands::Watch { 
+            input, target, out_dir, optimization, run, run_target, interval, debounce, args 
+        } => {
+            let project_dir = if Path::new(&input).is_dir() {
+                Path::new(&input).to_path_buf()
+            } else {
+                Path::new(&input).parent().map(Path::to_path_buf).unwrap_or_default()
+            };
+            let project = ProjectConfig::load(&project_dir)?.unwrap_or_default();
+            
+            let mut targets = if target.is_empty() { project.build_targets() } else { target };
+            if targets.is_empty() {
+                targets.push("python".to_string());
+            }
+            let out_dir = out_dir.or_else(|| {
+                project.output_directory()
+                    .map(|dir| project_dir.join(dir).display().to_string())
+            });
+            
+            let options = WatchOptions {
+                targets,
+                out_dir,
+                optimization,
+                run: run.then_some(run_target),
+                args,
+                interval: std::time::Duration::from_millis(interval),
+                debounce: std::time::Duration::from_millis(debounce),
+            };
+            handle_watch(input, options, cli.verbose)
+        }
+        
         Commands::Clean { path, all } => {
             handle_clean(path, all, cli.verbose)
         }
@@ -338,17 +482,86 @@ fn print_banner(verbose: bool) {
     }
 }
 
+/// How `compile` should build the program
+pub struct CompileSettings {
+    pub optimization: u8,
+    pub emit: EmitKind,
+    /// Raw `-C` options
+    pub codegen: Vec<String>,
+    pub debug: bool,
+    /// Accept output with placeholders for constructs the target cannot translate
+    pub allow_partial: bool,
+    /// Paste runtime support files into the output instead of writing them next to it
+    pub bundle_runtime: bool,
+    /// The input is an AST JSON document rather than Utopia source
+    pub from_ast: bool,
+    /// Targets loaded with `--target-dir`
+    pub template_targets: Vec<TemplateTransformer>,
+    /// Plugins of the `plugin:<executable>` targets
+    pub plugins: Vec<PluginTransformer>,
+}
+
+impl CompileSettings {
+    /// Settings for a plain build at the given optimization level
+    pub fn new(optimization: u8) -> Self {
+        Self {
+            optimization,
+            emit: EmitKind::Code,
+            codegen: Vec::new(),
+            debug: false,
+            allow_partial: false,
+            bundle_runtime: false,
+            from_ast: false,
+            template_targets: Vec::new(),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+/// Apply `-C option=value` settings to a compiler configuration
+fn apply_codegen_options(config: &mut Config, options: &[String]) -> Result<()> {
+    for option in options {
+        let (key, value) = option.split_once('=')
+            .ok_or_else(|| format!("Invalid -C option '{}', expected OPTION=VALUE", option))?;
+        match key.trim() {
+            "passes" => {
+                // Validate early so typos fail before any work is done
+                PassPipeline::for_level(config.optimization_level).apply_overrides(value)?;
+                let passes = match config.passes.take() {
+                    Some(existing) => format!("{},{}", existing, value),
+                    None => value.to_string(),
+                };
+                config.passes = Some(passes);
+            }
+            "dump-after" => {
+                DumpAfter::parse(value)?;
+                config.dump_after = Some(value.to_string());
+            }
+            "fast-math" => {
+                config.fast_math = match value.trim() {
+                    "on" | "true" | "yes" => true,
+                    "off" | "false" | "no" => false,
+                    other => return Err(format!("Invalid fast-math value '{}', expected on or off", other).into()),
+                };
+            }
+            other => return Err(format!("Unknown -C option '{}' (expected passes, dump-after or fast-math)", other).into()),
+        }
+    }
+    Ok(())
+}
+
 fn handle_compile(
     input: String,
     output: Option<String>,
     target: String,
-    optimization: u8,
-    debug: bool,
+    settings: CompileSettings,
     stats: bool,
     show_code: bool,
+    cache_options: CacheOptions,
     verbose: bool,
 ) -> Result<()> {
     let start_time = Instant::now();
+    let CompileSettings { optimization, emit, codegen, debug, allow_partial, bundle_runtime, from_ast, template_targets, plugins } = settings;
     
     if verbose {
         println!("{} {}", "📝 Compiling:".bright_blue().bold(), input.bright_white());
@@ -361,40 +574,183 @@ fn handle_compile(
     config.optimization_level = optimization;
     config.debug = debug;
     config.target = target.clone();
+    apply_codegen_options(&mut config, &codegen)?;
+    let dump_requested = config.dump_after.is_some();
+    let passes = config.passes.clone();
+    let fast_math = config.fast_math;
     
     // Initialize compiler
     let mut compiler = Compiler::new(config);
-    let transformer_manager = TransformerManager::new();
+    // Cached output of a template target is only valid for the templates it was built with
+    let template_fingerprint = template_targets.iter()
+        .rfind(|loaded| loaded.target_name() == target)
+        .map(|loaded| format!("+templates={}", loaded.fingerprint()))
+        .unwrap_or_default();
+    // and that of a plugin target for the executable; one that cannot be hashed is not cached
+    let plugin_fingerprint = plugins.iter()
+        .find(|plugin| plugin.target() == target)
+        .map(|plugin| plugin.fingerprint().map(|fingerprint| format!("+plugin={}", fingerprint)));
+    for loaded in template_targets {
+        if verbose {
+            println!("{} {} from {}", "🧩 Loaded target:".bright_blue().bold(), loaded.target_name().bright_yellow(), loaded.dir().display());
+        }
+        compiler.add_template_target(loaded);
+    }
+    for plugin in plugins {
+        compiler.add_plugin(plugin);
+    }
+    let mut hooks = StageReporter::new(debug);
+    let options = PipelineOptions::new(&target).allow_partial(allow_partial).bundle_runtime(bundle_runtime);
     
-    // Read input file
+    // Read input file; an AST document replaces lexing and parsing
     let source_code = read_file(&input)?;
-    
-    // Parse the program
-    let mut lexer = Lexer::new(&source_code);
-    let tokens = lexer.tokenize()?;
-    let mut parser = UtopiaParser::new(tokens);
-    let program = match parser.parse() {
-        Ok(program) => program,
-        Err(e) => {
-            println!("DEBUG: Parser error: {}", e);
-            return Err(e);
+    let mut imported = if from_ast { Some(Program::from_json(&source_code)?) } else { None };
+    
+    // Intermediate results are printed (or written to -o) instead of generated code
+    if emit != EmitKind::Code {
+        let options = options.stop_after(emit.last_stage());
+        let result = match imported {
+            Some(program) => compiler.run_from_ast(program, &options, &mut hooks)?,
+            None => compiler.run(&source_code, &options, &mut hooks)?,
+        };
+        report_diagnostics(&input, &result.diagnostics, true);
+        print_pass_dumps(&result);
+        if result.has_errors() {
+            return Err(format!("{} failed with {} errors", input, result.error_count()).into());
         }
-    };
-    
-    // Generate code
-    let generated_code = transformer_manager.transform(&target, &program)?;
+        let rendered = result.render(emit)?;
+        match output {
+            Some(output_file) => write_file(&output_file, &rendered)?,
+            None => print!("{}", rendered),
+        }
+        if stats {
+            print_stage_timings(&result);
+            print_pass_reports(&result);
+        }
+        return Ok(());
+    }
     
     // Determine output file
     let output_file = output.unwrap_or_else(|| {
         let input_path = Path::new(&input);
         let stem = input_path.file_stem().unwrap().to_str().unwrap();
-        let extension = transformer_manager.get_file_extension(&target)
+        let extension = compiler.transformers().get_file_extension(&target)
             .unwrap_or_else(|| ".out".to_string());
         format!("{}{}", stem, extension)
     });
     
-    // Write output
-    write_file(&output_file, &generated_code)?;
+    // Open the incremental build cache next to the output
+    // AST dumps need the optimizer to run, so they bypass the cache
+    let mut cache = if cache_options.enabled && !dump_requested && plugin_fingerprint != Some(None) {
+        let output_dir = Path::new(&output_file).parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        Some(BuildCache::open(output_dir)?)
+    } else {
+        None
+    };
+    let cache_key = std::fs::canonicalize(&input)
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| input.clone());
+    let source_hash = content_hash(source_code.as_bytes());
+    let cache_settings = format!(
+        "O{}{}{}{}{}{}{}{}",
+        optimization,
+        if debug { "+debug" } else { "" },
+        if allow_partial { "+partial" } else { "" },
+        if bundle_runtime { "+bundle-runtime" } else { "" },
+        if fast_math { "+fast-math" } else { "" },
+        passes.map(|passes| format!("+passes={}", passes)).unwrap_or_default(),
+        template_fingerprint,
+        plugin_fingerprint.flatten().unwrap_or_default(),
+    );
+    
+    let fresh_output = cache.as_ref()
+        .and_then(|cache| cache.fresh_output(&cache_key, &source_hash, &target, &cache_settings))
+        .cloned();
+    
+    let mut result = None;
+    let built = if let Some(cached) = fresh_output {
+        if cache_options.explain_rebuild {
+            println!("{} {} unchanged, reusing cached {} output", "♻️  Cache:".bright_blue().bold(), input.bright_white(), target.bright_yellow());
+        }
+        cached
+    } else {
+        // Parse the program
+        let parse_options = options.clone().stop_after(Stage::Parse);
+        let mut parsed = match imported.take() {
+            Some(program) => compiler.run_from_ast(program, &parse_options, &mut hooks)?,
+            None => compiler.run(&source_code, &parse_options, &mut hooks)?,
+        };
+        let program = parsed.program.take().ok_or("No AST was produced")?;
+        
+        let units = UnitTable::from_program(&program)?;
+        let plan = cache.as_ref().map(|cache| cache.plan(&cache_key, &units, &target, &cache_settings));
+        
+        if cache_options.explain_rebuild {
+            match &plan {
+                Some(plan) => print_rebuild_plan(&input, plan),
+                None => println!("{} cache disabled, rebuilding everything", "♻️  Cache:".bright_blue().bold()),
+            }
+        }
+        
+        let reused = match (&cache, &plan) {
+            (Some(cache), Some(plan)) if !plan.needs_rebuild() => {
+                cache.unit_output(&cache_key, &target, &cache_settings).cloned()
+            }
+            _ => None,
+        };
+        
+        match reused {
+            Some(cached) => {
+                if let Some(cache) = cache.as_mut() {
+                    cache.touch(&cache_key, &source_hash);
+                    cache.save()?;
+                }
+                cached
+            }
+            None => {
+                // Resolve, type-check and optimize, then generate the units that changed
+                let rest = compiler.run_from_ast(program, &options.clone().stop_after(Stage::Optimize), &mut hooks)?;
+                parsed.merge(rest);
+                let mut built = None;
+                if let (Some(optimized), false) = (parsed.program.clone(), parsed.has_errors()) {
+                    let regeneration = match &cache {
+                        Some(cache) => cache.regeneration_plan(&cache_key, &UnitTable::from_program(&optimized)?, &target, &cache_settings),
+                        None => Err(WholeOutputReason::NotCached),
+                    };
+                    let explain = cache_options.explain_rebuild && cache.is_some();
+                    let (generated, output) = generate_output(&mut compiler, &optimized, regeneration, &cache_settings, &options, &mut hooks, explain)?;
+                    parsed.merge(generated);
+                    built = output;
+                }
+                report_diagnostics(&input, &parsed.diagnostics, true);
+                print_pass_dumps(&parsed);
+                if parsed.has_errors() {
+                    return Err(format!("{} failed with {} errors", input, parsed.error_count()).into());
+                }
+                
+                let built = built.ok_or("No code was generated")?;
+                if let Some(cache) = cache.as_mut() {
+                    cache.store(&cache_key, &source_hash, units, &target, built.clone());
+                    cache.save()?;
+                }
+                result = Some(parsed);
+                built
+            }
+        }
+    };
+    
+    // Write output, with its source map and any extra files the target generates next to it;
+    // the extra files are written on cache hits too, in case the output directory was cleaned
+    write_output(Path::new(&output_file), &built.code, &built.source_map, &input)?;
+    let output_dir = Path::new(&output_file).parent().unwrap_or_else(|| Path::new("."));
+    for file in &built.files {
+        write_file(output_dir.join(&file.path), &file.contents)?;
+    }
+    if verbose {
+        print_renames(&input, &target, &built.renames);
+    }
     
     let compile_time = start_time.elapsed();
     
@@ -406,8 +762,12 @@ fn handle_compile(
         println!("{} {:.2}ms", "⏱️  Time:".bright_blue().bold(), compile_time.as_millis().to_string().bright_green());
         
         if stats {
-            println!("{} {} bytes", "📏 Size:".bright_blue().bold(), generated_code.len().to_string().bright_cyan());
-            println!("{} {} lines", "📄 Lines:".bright_blue().bold(), generated_code.lines().count().to_string().bright_cyan());
+            println!("{} {} bytes", "📏 Size:".bright_blue().bold(), built.code.len().to_string().bright_cyan());
+            println!("{} {} lines", "📄 Lines:".bright_blue().bold(), built.code.lines().count().to_string().bright_cyan());
+            if let Some(result) = &result {
+                print_stage_timings(result);
+                print_pass_reports(result);
+            }
         }
     }
     
@@ -415,13 +775,333 @@ fn handle_compile(
         println!();
         println!("{}", "Generated Code:".bright_yellow().bold());
         println!("{}", "─".repeat(40).bright_blue());
-        println!("{}", generated_code);
+        println!("{}", built.code);
         println!("{}", "─".repeat(40).bright_blue());
     }
     
     Ok(())
 }
 
+/// Compile one input for several targets, each into its own directory
+///
+/// The input is parsed and checked once, then every target is optimized and
+/// generated in parallel into `<output>/<target>/`. The incremental build
+/// cache only applies to single-target builds.
+fn handle_compile_targets(input: String, output: Option<String>, targets: &str, settings: CompileSettings, verbose: bool) -> Result<()> {
+    let CompileSettings { optimization, emit, codegen, debug, allow_partial, bundle_runtime, from_ast, template_targets, plugins } = settings;
+    if emit != EmitKind::Code {
+        return Err(format!("--emit {:?} needs a single target", emit).into());
+    }
+    
+    let mut config = Config::new();
+    config.optimization_level = optimization;
+    config.debug = debug;
+    apply_codegen_options(&mut config, &codegen)?;
+    let mut compiler = Compiler::new(config);
+    for loaded in template_targets {
+        compiler.add_template_target(loaded);
+    }
+    for plugin in plugins {
+        compiler.add_plugin(plugin);
+    }
+    
+    let targets: Vec<String> = if targets == "all" {
+        compiler.transformers().capability_matrix().into_keys().collect()
+    } else {
+        targets.split(',').map(|target| target.trim().to_string()).filter(|target| !target.is_empty()).collect()
+    };
+    if verbose {
+        println!("{} {}", "📝 Compiling:".bright_blue().bold(), input.bright_white());
+        println!("{} {}", "🎯 Targets:".bright_blue().bold(), targets.join(", ").bright_yellow());
+    }
+    
+    let source_code = read_file(&input)?;
+    let options = PipelineOptions::new("").allow_partial(allow_partial).bundle_runtime(bundle_runtime);
+    let mut hooks = StageReporter::new(debug);
+    let (checked, builds) = if from_ast {
+        compiler.run_targets_from_ast(Program::from_json(&source_code)?, &targets, &options, &mut hooks)?
+    } else {
+        compiler.run_targets(&source_code, &targets, &options, &mut hooks)?
+    };
+    report_diagnostics(&input, &checked.diagnostics, true);
+    if checked.has_errors() {
+        return Err(format!("{} failed with {} errors", input, checked.error_count()).into());
+    }
+    
+    let output_dir = Path::new(output.as_deref().unwrap_or("."));
+    let stem = Path::new(&input).file_stem().and_then(|stem| stem.to_str()).unwrap_or("main");
+    let mut rows = Vec::new();
+    for build in &builds {
+        let (status, size, written) = match &build.output {
+            Ok(result) => {
+                report_diagnostics(&input, &result.diagnostics, verbose);
+                match result.generated.as_ref().filter(|_| !result.has_errors()) {
+                    Some(generated) => {
+                        // `plugin:./path` is no directory name, so plugins use the name they report
+                        let directory_name = match generated.metadata.get("target") {
+                            Some(name) if build.target.starts_with(PLUGIN_PREFIX) => name,
+                            _ => &build.target,
+                        };
+                        let directory = output_dir.join(directory_name);
+                        std::fs::create_dir_all(&directory)?;
+                        let extension = generated.metadata.get("extension").map_or("", String::as_str);
+                        let main = directory.join(format!("{}{}", stem, extension));
+                        write_output(&main, generated.code(), &generated.source_map, &input)?;
+                        for file in generated.files.iter().skip(1) {
+                            write_file(directory.join(&file.path), &file.contents)?;
+                        }
+                        if verbose {
+                            print_renames(&input, directory_name, &generated.renames);
+                        }
+                        let status = if generated.is_partial() { "partial" } else { "ok" };
+                        (status.to_string(), generated.code().len().to_string(), main.display().to_string())
+                    }
+                    None => (format!("{} errors", result.error_count()), "-".to_string(), String::new()),
+                }
+            }
+            Err(error) => {
+                eprintln!("{}", format!("{}: {}: {}", input, build.target, error).bright_red());
+                ("failed".to_string(), "-".to_string(), String::new())
+            }
+        };
+        rows.push((build, status, size, written));
+    }
+    
+    println!("{:<14} {:<9} {:>10} {:>10}  {}", "target", "status", "bytes", "ms", "output");
+    for (build, status, size, written) in &rows {
+        let status = if build.succeeded() { status.bright_green() } else { status.bright_red() };
+        println!("{:<14} {:<9} {:>10} {:>10.2}  {}", build.target, status, size, millis(build.duration), written);
+    }
+    
+    let failures = builds.iter().filter(|build| !build.succeeded()).count();
+    if failures > 0 {
+        return Err(format!("{} of {} targets failed", failures, builds.len()).into());
+    }
+    Ok(())
+}
+
+/// Write generated code to `output_file` and its source map next to it
+fn write_output(output_file: &Path, code: &str, source_map: &SourceMap, input: &str) -> Result<()> {
+    let map_file = source_map.path_for(output_file);
+    let file_name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    
+    let mut contents = code.to_string();
+    if source_map.format == SourceMapFormat::V3 {
+        // Lets browsers and `node --enable-source-maps` find the map
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&format!("//# sourceMappingURL={}\n", file_name(&map_file)));
+    }
+    write_file(output_file, &contents)?;
+    
+    // Sources are resolved relative to the map, so name the input directly
+    // when it sits next to the output and use its full path otherwise
+    let input_path = std::fs::canonicalize(input).unwrap_or_else(|_| Path::new(input).to_path_buf());
+    let output_dir = std::fs::canonicalize(output_file).ok().and_then(|path| path.parent().map(Path::to_path_buf));
+    let source = if input_path.parent() == output_dir.as_deref() {
+        file_name(&input_path)
+    } else {
+        input_path.to_string_lossy().into_owned()
+    };
+    write_file(&map_file, &source_map.render(&file_name(output_file), &source))
+}
+
+/// Pipeline hooks that trace every stage to stderr with `--debug`
+struct StageReporter {
+    enabled: bool,
+}
+
+impl StageReporter {
+    fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl PipelineHooks for StageReporter {
+    fn after_stage(&mut self, stage: Stage, elapsed: std::time::Duration, output: &PipelineOutput) {
+        if self.enabled {
+            let line = format!("[{:<10}] {:>9.3}ms  {}", stage.name(), elapsed.as_secs_f64() * 1000.0, stage_summary(stage, output));
+            eprintln!("{}", line.bright_black());
+        }
+    }
+}
+
+/// One-line description of what a stage produced
+fn stage_summary(stage: Stage, output: &PipelineOutput) -> String {
+    match stage {
+        Stage::Lex => format!("{} tokens", output.tokens.as_ref().map_or(0, Vec::len)),
+        Stage::Parse => {
+            let (blocks, functions) = output.program.as_ref().map_or((0, 0), |program| {
+                let functions = program.language_blocks.iter().map(|block| block.functions.len()).sum();
+                (program.language_blocks.len(), functions)
+            });
+            format!("{} language blocks, {} functions", blocks, functions)
+        }
+        Stage::Resolve | Stage::TypeCheck => {
+            format!("{} errors, {} warnings", output.error_count(), output.warning_count())
+        }
+        Stage::Optimize => {
+            let total = output.optimization_stats.as_ref().map_or(0, OptimizationStats::total_optimizations);
+            format!("{} passes, {} optimizations", output.pass_reports.len(), total)
+        }
+        Stage::Generate => format!("{} bytes", output.code.as_ref().map_or(0, String::len)),
+    }
+}
+
+fn print_pass_reports(output: &PipelineOutput) {
+    if output.pass_reports.is_empty() {
+        return;
+    }
+    println!("{}", "🔧 Optimization passes:".bright_blue().bold());
+    for report in &output.pass_reports {
+        println!("   {:<12} {:>4} changes {:>9.3}ms", report.pass.name(), report.stats.total_optimizations(), report.duration.as_secs_f64() * 1000.0);
+    }
+}
+
+/// List the names that were escaped because `target` reserves them
+fn print_renames(input: &str, target: &str, renames: &[Rename]) {
+    for rename in renames {
+        println!(
+            "{} {}:{}:{}: '{}' is reserved in {}, written as '{}'",
+            "🔤 Renamed:".bright_blue().bold(),
+            input, rename.span.line, rename.span.column, rename.name, target, rename.escaped,
+        );
+    }
+}
+
+/// Print the AST dumps requested with `-C dump-after`
+fn print_pass_dumps(output: &PipelineOutput) {
+    for report in &output.pass_reports {
+        if let Some(dump) = &report.dump {
+            eprintln!("{}", format!("── AST after {} ──", report.pass).bright_blue().bold());
+            eprint!("{}", dump);
+        }
+    }
+}
+
+fn print_stage_timings(output: &PipelineOutput) {
+    println!("{}", "⏱️  Stages:".bright_blue().bold());
+    for timing in &output.timings {
+        println!("   {:<12} {:>9.3}ms", timing.stage.name(), timing.duration.as_secs_f64() * 1000.0);
+    }
+}
+
+/// Print diagnostics as `file:line:column: kind: message`
+fn report_diagnostics(file: &str, diagnostics: &[Diagnostic], show_warnings: bool) {
+    for diagnostic in diagnostics {
+        let line = format!("{}:{}", file, diagnostic);
+        match diagnostic.kind {
+            DiagnosticKind::Error => eprintln!("{}", line.bright_red()),
+            DiagnosticKind::Warning if show_warnings => eprintln!("{}", line.bright_yellow()),
+            DiagnosticKind::Info | DiagnosticKind::Hint if show_warnings => eprintln!("{}", line.bright_black()),
+            _ => {}
+        }
+    }
+}
+
+/// Run `file` through every stage, dumping each intermediate result
+pub fn debug_compile(file: &str, target: &str) -> Result<()> {
+    let source = read_file(file)?;
+    let mut compiler = Compiler::new(Config::new());
+    let mut hooks = StageDumper;
+    let result = compiler.run(&source, &PipelineOptions::new(target), &mut hooks)?;
+    report_diagnostics(file, &result.diagnostics, true);
+    if result.has_errors() {
+        return Err(format!("{} failed with {} errors", file, result.error_count()).into());
+    }
+    Ok(())
+}
+
+/// Pipeline hooks for `--debug-compile`: print every stage's output
+struct StageDumper;
+
+impl PipelineHooks for StageDumper {
+    fn after_stage(&mut self, stage: Stage, elapsed: std::time::Duration, output: &PipelineOutput) {
+        println!("{}", format!("── {} ({:.3}ms): {}", stage.name(), elapsed.as_secs_f64() * 1000.0, stage_summary(stage, output)).bright_blue().bold());
+        let emit = match stage {
+            Stage::Lex => EmitKind::Tokens,
+            Stage::Parse => EmitKind::Ast,
+            Stage::Resolve => return,
+            Stage::TypeCheck => EmitKind::TypedAst,
+            Stage::Optimize => EmitKind::OptimizedAst,
+            Stage::Generate => EmitKind::Code,
+        };
+        if let Ok(rendered) = output.render(emit) {
+            println!("{}", rendered);
+        }
+    }
+}
+
+/// Incremental build cache settings for `compile`
+#[derive(Debug, Clone, Copy)]
+pub struct CacheOptions {
+    pub enabled: bool,
+    pub explain_rebuild: bool,
+}
+
+fn print_rebuild_plan(input: &str, plan: &RebuildPlan) {
+    println!("{} {}", "♻️  Rebuild plan for".bright_blue().bold(), input.bright_white());
+    if !plan.needs_rebuild() {
+        println!("   all {} units unchanged, reusing cached output", plan.units.len());
+        return;
+    }
+    for status in plan.changed_units() {
+        println!("{}", format!("   {} - {}", status.unit, status.reason).bright_yellow());
+    }
+}
+
+/// Generate code for an optimized program. With a regeneration plan only the
+/// units it lists are generated and patched into the cached output; if they
+/// don't fit, the whole program is generated after all. The output is `None`
+/// when generation reported errors.
+fn generate_output(
+    compiler: &mut Compiler,
+    program: &Program,
+    regeneration: std::result::Result<(&TargetOutput, RebuildPlan), WholeOutputReason>,
+    settings: &str,
+    options: &PipelineOptions,
+    hooks: &mut dyn PipelineHooks,
+    explain: bool,
+) -> Result<(PipelineOutput, Option<TargetOutput>)> {
+    let reason = match regeneration {
+        Ok((cached, plan)) => {
+            // Units next to the regenerated ones decide what scaffolding surrounds them
+            let regenerated: BTreeSet<String> = plan.changed_units().map(|status| status.unit.clone()).collect();
+            let generated_units = regenerated.union(&cached.neighbours(&regenerated)).cloned().collect();
+            let partial = partial_program(program, &generated_units);
+            let generated = compiler.generate(&partial, options, hooks)?;
+            let part = match &generated.generated {
+                Some(part) if !generated.has_errors() => part,
+                _ => return Ok((generated, None)),
+            };
+            match cached.splice(&TargetOutput::new(settings, part, &partial)?, &regenerated, program)? {
+                Ok(spliced) => {
+                    if explain {
+                        let units: Vec<&str> = regenerated.iter().map(String::as_str).collect();
+                        println!("{}", format!(
+                            "   regenerating {} ({} of {} units), keeping the cached code of the others",
+                            units.join(", "), regenerated.len(), plan.units.len()
+                        ).bright_black());
+                    }
+                    return Ok((generated, Some(spliced)));
+                }
+                Err(reason) => reason,
+            }
+        }
+        Err(reason) => reason,
+    };
+    if explain {
+        println!("{}", format!("   regenerating the whole output: {}", reason).bright_black());
+    }
+    let generated = compiler.generate(program, options, hooks)?;
+    let output = match &generated.generated {
+        Some(whole) if !generated.has_errors() => Some(TargetOutput::new(settings, whole, program)?),
+        _ => None,
+    };
+    Ok((generated, output))
+}
+
 fn handle_convert(
     input: String,
     from: Option<String>,
@@ -514,9 +1194,72 @@ fn handle_analyze(
     
     println!("{}", "─".repeat(50).bright_blue());
     
+    if matches!(analysis_type, AnalysisType::All | AnalysisType::Performance) {
+        let program = crate::parser::Parser::new(tokens).parse()?;
+        let effects = EffectAnalysis::analyze(&program, &CallGraph::build(&program));
+        let report = render_effects(&effects, &format);
+        println!();
+        println!("{}", "Function Effects:".bright_yellow().bold());
+        print!("{}", report);
+        if let Some(export) = export {
+            write_file(&export, &report)?;
+            if verbose {
+                println!("{} {}", "💾 Exported:".bright_green(), export.bright_white());
+            }
+        }
+    }
+    
     Ok(())
 }
 
+/// One row per function: its effects and the outside variables it reads and writes
+fn render_effects(effects: &EffectAnalysis, format: &OutputFormat) -> String {
+    let join = |names: &std::collections::BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join(", ");
+    match format {
+        OutputFormat::Json => {
+            let functions: Vec<serde_json::Value> = effects.functions()
+                .map(|(id, effects)| serde_json::json!({
+                    "function": id,
+                    "effects": effects.kinds().iter().map(|kind| kind.name()).collect::<Vec<_>>(),
+                    "reads": effects.reads,
+                    "writes": effects.writes,
+                    "reads_memory": effects.reads_memory,
+                    "writes_memory": effects.writes_memory,
+                }))
+                .collect();
+            format!("{}\n", serde_json::to_string_pretty(&serde_json::json!({ "functions": functions })).unwrap_or_default())
+        }
+        OutputFormat::Markdown => {
+            let mut output = String::from("| Function | Effects | Reads | Writes |\n|---|---|---|---|\n");
+            for (id, effects) in effects.functions() {
+                output.push_str(&format!("| `{}` | {} | {} | {} |\n", id, effects, join(&effects.reads), join(&effects.writes)));
+            }
+            output
+        }
+        _ => {
+            let width = effects.functions().map(|(id, _)| id.len()).max().unwrap_or(0).max("Function".len());
+            let mut output = format!("{:<width$}  {:<32}  {}\n", "Function", "Effects", "Reads / Writes");
+            for (id, effects) in effects.functions() {
+                let mut variables = Vec::new();
+                if !effects.reads.is_empty() {
+                    variables.push(format!("reads {}", join(&effects.reads)));
+                }
+                if !effects.writes.is_empty() {
+                    variables.push(format!("writes {}", join(&effects.writes)));
+                }
+                if effects.reads_memory || effects.writes_memory {
+                    let access = if effects.writes_memory { "changes" } else { "reads" };
+                    variables.push(format!("{} fields or elements", access));
+                }
+                let row = format!("{:<width$}  {:<32}  {}", id, effects.to_string(), variables.join("; "));
+                output.push_str(row.trim_end());
+                output.push('\n');
+            }
+            output
+        }
+    }
+}
+
 fn handle_check(files: Vec<String>, strict: bool, warnings: bool, verbose: bool) -> Result<()> {
     if verbose {
         println!("{} {} files", "🔍 Checking:".bright_blue().bold(), files.len().to_string().bright_cyan());
@@ -561,6 +1304,7 @@ fn handle_check(files: Vec<String>, strict: bool, warnings: bool, verbose: bool)
                 "📊 Summary:".bright_blue().bold(),
                 total_errors.to_string().bright_red(),
                 total_warnings.to_string().bright_yellow());
+        return Err(format!("Check failed with {} errors", total_errors).into());
     }
     
     Ok(())
@@ -618,19 +1362,132 @@ fn handle_info(info_type: InfoType) -> Result<()> {
 }
 
 fn handle_benchmark(
-    _input: String,
-    _iterations: usize,
-    _targets: Vec<String>,
-    _output: Option<String>,
+    input: String,
+    iterations: usize,
+    targets: Vec<String>,
+    output: Option<String>,
     verbose: bool,
 ) -> Result<()> {
     if verbose {
         println!("{}", "🏁 Running benchmarks...".bright_blue().bold());
     }
-    println!("{}", "Benchmark functionality not yet implemented. Coming soon!".bright_yellow());
+    
+    let files = benchmark_inputs(&input)?;
+    let targets = if targets.is_empty() { vec!["python".to_string()] } else { targets };
+    let iterations = iterations.max(1);
+    let mut results = Vec::new();
+    
+    for file in &files {
+        let source = read_file(file)?;
+        for target in &targets {
+            let mut compiler = Compiler::new(Config::new());
+            let options = PipelineOptions::new(target);
+            let mut stage_totals = [std::time::Duration::ZERO; Stage::ALL.len()];
+            let mut best = std::time::Duration::MAX;
+            
+            for _ in 0..iterations {
+                let run = compiler.run(&source, &options, &mut NoHooks)?;
+                if run.has_errors() {
+                    report_diagnostics(file, &run.diagnostics, false);
+                    return Err(format!("{} failed with {} errors", file, run.error_count()).into());
+                }
+                for timing in &run.timings {
+                    stage_totals[timing.stage as usize] += timing.duration;
+                }
+                best = best.min(run.total_time());
+            }
+            
+            results.push(BenchmarkResult {
+                file: file.clone(),
+                target: target.clone(),
+                stage_averages: stage_totals.map(|total| total / u32::try_from(iterations).unwrap_or(u32::MAX)),
+                best,
+            });
+        }
+    }
+    
+    print_benchmark_table(&results, iterations);
+    
+    if let Some(output) = output {
+        let json: Vec<serde_json::Value> = results.iter().map(BenchmarkResult::to_json).collect();
+        let report = serde_json::json!({ "iterations": iterations, "results": json });
+        write_file(&output, &serde_json::to_string_pretty(&report)?)?;
+        println!("{} {}", "📁 Results:".bright_blue().bold(), output.bright_white());
+    }
+    
     Ok(())
 }
 
+/// Average stage timings for one file and target
+struct BenchmarkResult {
+    file: String,
+    target: String,
+    stage_averages: [std::time::Duration; Stage::ALL.len()],
+    best: std::time::Duration,
+}
+
+impl BenchmarkResult {
+    fn average_total(&self) -> std::time::Duration {
+        self.stage_averages.iter().sum()
+    }
+    
+    fn to_json(&self) -> serde_json::Value {
+        let stages: serde_json::Map<String, serde_json::Value> = Stage::ALL.iter()
+            .map(|stage| (stage.name().to_string(), serde_json::json!(millis(self.stage_averages[*stage as usize]))))
+            .collect();
+        serde_json::json!({
+            "file": self.file,
+            "target": self.target,
+            "stages_ms": stages,
+            "average_ms": millis(self.average_total()),
+            "best_ms": millis(self.best),
+        })
+    }
+}
+
+fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The `.uto` files to benchmark: the input file, or every source in a directory
+fn benchmark_inputs(input: &str) -> Result<Vec<String>> {
+    let path = Path::new(input);
+    if path.is_file() {
+        return Ok(vec![input.to_string()]);
+    }
+    let files: Vec<String> = FileWatcher::new(path)?.files()
+        .map(|file| file.display().to_string())
+        .collect();
+    if files.is_empty() {
+        return Err(format!("No .uto files found in {}", input).into());
+    }
+    Ok(files)
+}
+
+fn print_benchmark_table(results: &[BenchmarkResult], iterations: usize) {
+    println!();
+    println!("{} ({} iterations, average ms per stage)", "🏁 Benchmark Results".bright_yellow().bold(), iterations);
+    
+    let mut header = format!("{:<28} {:<12}", "file", "target");
+    for stage in Stage::ALL {
+        header.push_str(&format!(" {:>10}", stage.name()));
+    }
+    header.push_str(&format!(" {:>10} {:>10}", "total", "best"));
+    println!("{}", header.bright_white().bold());
+    println!("{}", "─".repeat(header.chars().count()).bright_blue());
+    
+    for result in results {
+        let file = Path::new(&result.file).file_name()
+            .map_or_else(|| result.file.clone(), |name| name.to_string_lossy().into_owned());
+        let mut row = format!("{:<28} {:<12}", file, result.target);
+        for average in &result.stage_averages {
+            row.push_str(&format!(" {:>10.3}", millis(*average)));
+        }
+        row.push_str(&format!(" {:>10.3} {:>10.3}", millis(result.average_total()), millis(result.best)));
+        println!("{}", row);
+    }
+}
+
 fn handle_repl(language: String, completion: bool) -> Result<()> {
     println!("{}", "🎮 Starting Utopia REPL...".bright_blue().bold());
     println!("{}", "Type 'exit' or 'quit' to exit, 'help' for commands".bright_cyan());
@@ -743,7 +1600,62 @@ fn handle_run(input: Option<String>, args: Vec<String>, target: String, verbose:
         }
     });
     
-    if !std::path::Path::new(&filename).exists() {
+    let mut prepared = prepare_run(&filename, &target, &args, verbose)?;
+    
+    // Execute the compiled program, pointing its stack traces back at the source
+    prepared.command.stderr(std::process::Stdio::piped());
+    let execute_result = prepared.command.spawn().and_then(|mut child| {
+        if let Some(stderr) = child.stderr.take() {
+            for line in std::io::BufRead::lines(std::io::BufReader::new(stderr)) {
+                eprintln!("{}", prepared.rewrite_trace(&line?));
+            }
+        }
+        child.wait()
+    });
+    
+    // Clean up temporary file
+    prepared.cleanup();
+    
+    match execute_result {
+        Ok(status) => {
+            if verbose {
+                if status.success() {
+                    println!("{}", "✅ Program executed successfully!".bright_green().bold());
+                } else {
+                    println!("{}", "❌ Program execution failed.".bright_red().bold());
+                }
+            }
+            Ok(())
+        }
+        Err(e) => {
+            Err(format!("Failed to execute program: {}. Make sure {} is installed.", e, prepared.execution_target).into())
+        }
+    }
+}
+
+/// A compiled program written to a temporary file, ready to execute
+struct PreparedRun {
+    temp_file: String,
+    execution_target: String,
+    command: std::process::Command,
+    source: String,
+    source_map: SourceMap,
+}
+
+impl PreparedRun {
+    fn cleanup(&self) {
+        let _ = std::fs::remove_file(&self.temp_file);
+    }
+    
+    /// Replace locations in the temporary file with `.uto` locations
+    fn rewrite_trace(&self, line: &str) -> String {
+        self.source_map.rewrite_trace(line, &self.temp_file, &self.source)
+    }
+}
+
+/// Compile `filename` for direct execution and build the interpreter command
+fn prepare_run(filename: &str, target: &str, args: &[String], verbose: bool) -> Result<PreparedRun> {
+    if !std::path::Path::new(filename).exists() {
         return Err(format!("File not found: {}", filename).into());
     }
     
@@ -751,31 +1663,30 @@ fn handle_run(input: Option<String>, args: Vec<String>, target: String, verbose:
     let execution_target = if target == "auto" || target.is_empty() || target == "native" {
         "python".to_string()
     } else {
-        target
+        target.to_string()
     };
     
     if verbose {
         println!("{}", format!("📝 Compiling {} to {} for execution...", filename, execution_target).bright_green());
     }
     
-    // Read and parse the file
-    let source = std::fs::read_to_string(&filename)
+    // Read and compile the file
+    let source = std::fs::read_to_string(filename)
         .map_err(|e| format!("Error reading file {}: {}", filename, e))?;
     
-    // Create transformer manager
-    let transformer_manager = crate::transformers::TransformerManager::new();
-    
-    // Parse the program
-    let mut lexer = crate::lexer::Lexer::new(&source);
-    let tokens = lexer.tokenize()
-        .map_err(|e| format!("Tokenization error: {}", e))?;
-    let mut parser = crate::parser::Parser::new(tokens);
-    let program = parser.parse()
-        .map_err(|e| format!("Parse error: {}", e))?;
-    
-    // Generate code
-    let result = transformer_manager.transform(&execution_target, &program)
+    let mut config = Config::new();
+    config.target = execution_target.clone();
+    let mut compiler = Compiler::new(config);
+    // Only the temporary file is executed, so the runtime goes into it
+    let options = PipelineOptions::new(&execution_target).bundle_runtime(true);
+    let output = compiler.run(&source, &options, &mut NoHooks)
         .map_err(|e| format!("Compilation error: {}", e))?;
+    report_diagnostics(filename, &output.diagnostics, verbose);
+    if output.has_errors() {
+        return Err(format!("Compilation error: {} errors in {}", output.error_count(), filename).into());
+    }
+    let result = output.code.ok_or("Compilation error: no code was generated")?;
+    let source_map = output.generated.map(|generated| generated.source_map).unwrap_or_default();
     
     // Write to temporary file for execution
     let temp_file = match execution_target.as_str() {
@@ -788,66 +1699,262 @@ fn handle_run(input: Option<String>, args: Vec<String>, target: String, verbose:
         _ => format!("temp_{}.out", std::process::id()),
     };
     
-    std::fs::write(&temp_file, result)
-        .map_err(|e| format!("Error writing temporary file: {}", e))?;
-    
-    // Execute the compiled program
-    let execute_result = match execution_target.as_str() {
+    let command = match execution_target.as_str() {
         "python" => {
             if verbose {
                 println!("{}", "🐍 Executing with Python...".bright_yellow());
             }
-            std::process::Command::new("python3")
-                .arg(&temp_file)
-                .args(&args)
-                .status()
+            let mut command = std::process::Command::new("python3");
+            command.arg(&temp_file).args(args);
+            command
         }
         "javascript" | "js" => {
             if verbose {
                 println!("{}", "🚀 Executing with Node.js...".bright_yellow());
             }
-            std::process::Command::new("node")
-                .arg(&temp_file)
-                .args(&args)
-                .status()
+            let mut command = std::process::Command::new("node");
+            command.arg(&temp_file).args(args);
+            command
         }
         "go" => {
             if verbose {
                 println!("{}", "🔷 Executing with Go...".bright_yellow());
             }
-            std::process::Command::new("go")
-                .arg("run")
-                .arg(&temp_file)
-                .args(&args)
-                .status()
+            let mut command = std::process::Command::new("go");
+            command.arg("run").arg(&temp_file).args(args);
+            command
         }
         _ => {
-            // Clean up temp file
-            let _ = std::fs::remove_file(&temp_file);
             return Err(format!("Direct execution not supported for target language: {}", execution_target).into());
         }
     };
     
-    // Clean up temporary file
-    let _ = std::fs::remove_file(&temp_file);
+    std::fs::write(&temp_file, result)
+        .map_err(|e| format!("Error writing temporary file: {}", e))?;
     
-    match execute_result {
-        Ok(status) => {
-            if verbose {
-                if status.success() {
-                    println!("{}", "✅ Program executed successfully!".bright_green().bold());
-                } else {
-                    println!("{}", "❌ Program execution failed.".bright_red().bold());
+    Ok(PreparedRun {
+        temp_file,
+        execution_target,
+        command,
+        source: filename.to_string(),
+        source_map,
+    })
+}
+
+/// Options for `utopia watch`
+struct WatchOptions {
+    targets: Vec<String>,
+    out_dir: Option<String>,
+    optimization: u8,
+    run: Option<String>,
+    args: Vec<String>,
+    interval: std::time::Duration,
+    debounce: std::time::Duration,
+}
+
+/// How long a program started by `watch --run` gets to exit after SIGTERM
+/// before it is killed
+const STOP_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// A program started by `watch --run`, terminated before each restart
+struct RunningProgram {
+    child: std::process::Child,
+    prepared: PreparedRun,
+}
+
+impl RunningProgram {
+    fn start(entry: &str, target: &str, args: &[String], verbose: bool) -> Result<Self> {
+        let mut prepared = prepare_run(entry, target, args, verbose)?;
+        match prepared.command.spawn() {
+            Ok(child) => Ok(Self { child, prepared }),
+            Err(e) => {
+                prepared.cleanup();
+                Err(format!("Failed to execute program: {}. Make sure {} is installed.", e, prepared.execution_target).into())
+            }
+        }
+    }
+    
+    /// Report (once) whether the program exited on its own
+    fn reap(&mut self) -> bool {
+        match self.child.try_wait() {
+            Ok(Some(status)) => {
+                let message = format!("⏹  Program exited ({})", status);
+                println!("{}", message.bright_black());
+                self.prepared.cleanup();
+                true
+            }
+            Ok(None) => false,
+            Err(_) => true,
+        }
+    }
+    
+    /// Ask the program to terminate so it can clean up, and kill it if it
+    /// is still running after `STOP_GRACE_PERIOD`
+    fn stop(mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            if !self.terminate() || !self.exits_within(STOP_GRACE_PERIOD) {
+                let _ = self.child.kill();
+            }
+            let _ = self.child.wait();
+        }
+        self.prepared.cleanup();
+    }
+
+    /// Send SIGTERM, returning whether it was delivered
+    #[cfg(unix)]
+    fn terminate(&self) -> bool {
+        let Ok(pid) = libc::pid_t::try_from(self.child.id()) else { return false };
+        // SAFETY: `kill` only sends a signal; the child has not been waited
+        // for yet, so its pid still belongs to it
+        unsafe { libc::kill(pid, libc::SIGTERM) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    fn terminate(&self) -> bool {
+        false
+    }
+
+    fn exits_within(&mut self, timeout: std::time::Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+}
+
+fn handle_watch(input: String, options: WatchOptions, verbose: bool) -> Result<()> {
+    let mut watcher = FileWatcher::new(&input)?;
+    if let Some(out_dir) = &options.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+    
+    println!("{} {} ({} files)", "👀 Watching:".bright_blue().bold(), input.bright_white(), watcher.files().count());
+    println!("{} {}", "🎯 Targets:".bright_blue().bold(), options.targets.join(", ").bright_yellow());
+    if let Some(run_target) = &options.run {
+        println!("{} {}", "🚀 Running with:".bright_blue().bold(), run_target.bright_yellow());
+    }
+    println!("{}", "Press Ctrl+C to stop".bright_black());
+    
+    let entry = watch_entry_point(&input, &watcher);
+    let sources: Vec<String> = watcher.files().map(|path| path.display().to_string()).collect();
+    let mut program = watch_rebuild(&sources, entry.as_deref(), &options, None, verbose);
+    
+    loop {
+        let changed = watcher.wait_for_changes(options.interval, options.debounce, || {
+            if program.as_mut().is_some_and(RunningProgram::reap) {
+                program = None;
+            }
+        });
+        
+        println!();
+        for path in &changed {
+            println!("{} {}", "🔄 Changed:".bright_blue().bold(), path.display().to_string().bright_white());
+        }
+        
+        // Rebuild every file that still exists; removed files have nothing to compile
+        let sources: Vec<String> = changed.iter()
+            .filter(|path| path.exists())
+            .map(|path| path.display().to_string())
+            .collect();
+        let entry = watch_entry_point(&input, &watcher);
+        program = watch_rebuild(&sources, entry.as_deref(), &options, program.take(), verbose);
+    }
+}
+
+/// The file `watch --run` executes: the input file itself, or `main.uto` in a directory
+fn watch_entry_point(input: &str, watcher: &FileWatcher) -> Option<String> {
+    let path = Path::new(input);
+    if path.is_file() {
+        return Some(input.to_string());
+    }
+    watcher.files()
+        .find(|file| file.file_name().is_some_and(|name| name == "main.uto"))
+        .map(|file| file.display().to_string())
+}
+
+/// Check and compile the changed files, then restart the program if requested
+fn watch_rebuild(
+    sources: &[String],
+    entry: Option<&str>,
+    options: &WatchOptions,
+    running: Option<RunningProgram>,
+    verbose: bool,
+) -> Option<RunningProgram> {
+    let started = Instant::now();
+    let mut failed = false;
+    
+    for source in sources {
+        match check_file(source, false, false) {
+            Ok((0, _)) => {}
+            Ok((errors, _)) => {
+                println!("{} {} - {} errors", "❌".bright_red(), source.bright_white(), errors.to_string().bright_red());
+                failed = true;
+                continue;
+            }
+            Err(e) => {
+                println!("{} {} - {}", "❌".bright_red(), source.bright_white(), e.to_string().bright_red());
+                failed = true;
+                continue;
+            }
+        }
+        
+        for target in &options.targets {
+            let output = watch_output_path(source, target, options.out_dir.as_deref());
+            let cache_options = CacheOptions { enabled: true, explain_rebuild: verbose };
+            match handle_compile(source.clone(), Some(output.clone()), target.clone(), CompileSettings::new(options.optimization), false, false, cache_options, verbose) {
+                Ok(()) => println!("{} {} → {}", "✅".bright_green(), source.bright_white(), output.bright_cyan()),
+                Err(e) => {
+                    println!("{} {} ({}) - {}", "❌".bright_red(), source.bright_white(), target, e.to_string().bright_red());
+                    failed = true;
                 }
             }
-            Ok(())
         }
+    }
+    
+    println!("{} {}ms", "⏱️  Rebuilt in".bright_blue(), started.elapsed().as_millis().to_string().bright_green());
+    
+    let run_target = options.run.as_ref()?;
+    
+    // Keep the previous program running when the rebuild failed
+    if failed {
+        println!("{}", "⚠️  Build failed, not restarting".bright_yellow());
+        return running;
+    }
+    if let Some(running) = running {
+        running.stop();
+    }
+    
+    let Some(entry) = entry else {
+        println!("{}", "⚠️  No main.uto found to run".bright_yellow());
+        return None;
+    };
+    match RunningProgram::start(entry, run_target, &options.args, verbose) {
+        Ok(program) => Some(program),
         Err(e) => {
-            Err(format!("Failed to execute program: {}. Make sure {} is installed.", e, execution_target).into())
+            println!("{} {}", "❌".bright_red(), e.to_string().bright_red());
+            None
         }
     }
 }
 
+fn watch_output_path(source: &str, target: &str, out_dir: Option<&str>) -> String {
+    let transformer_manager = TransformerManager::new();
+    let stem = Path::new(source).file_stem().and_then(|stem| stem.to_str()).unwrap_or("out");
+    let extension = transformer_manager.get_file_extension(target)
+        .unwrap_or_else(|| ".out".to_string());
+    let file_name = format!("{}{}", stem, extension);
+    match out_dir {
+        Some(dir) => Path::new(dir).join(file_name).display().to_string(),
+        None => Path::new(source).with_file_name(file_name).display().to_string(),
+    }
+}
+
 fn handle_clean(_path: String, _all: bool, verbose: bool) -> Result<()> {
     if verbose {
         println!("{}", "🧹 Cleaning build artifacts...".bright_blue().bold());
@@ -873,16 +1980,20 @@ fn detect_language_from_extension(filename: &str) -> String {
     }
 }
 
-fn check_file(file: &str, _strict: bool, _warnings: bool) -> Result<(usize, usize)> {
+fn check_file(file: &str, strict: bool, warnings: bool) -> Result<(usize, usize)> {
     let source_code = read_file(file)?;
-    let mut lexer = Lexer::new(&source_code);
-    let tokens = lexer.tokenize()?;
+    let mut compiler = Compiler::new(Config::new());
+    let options = PipelineOptions::new("python").stop_after(Stage::TypeCheck);
+    let output = compiler.run(&source_code, &options, &mut NoHooks)?;
     
-    let mut parser = UtopiaParser::new(tokens);
-    let _program = parser.parse()?;
+    report_diagnostics(file, &output.diagnostics, warnings || strict);
     
-    // Return (errors, warnings)
-    Ok((0, 0))
+    // Return (errors, warnings); strict mode treats warnings as errors
+    if strict {
+        Ok((output.error_count() + output.warning_count(), 0))
+    } else {
+        Ok((output.error_count(), output.warning_count()))
+    }
 }
 
 fn format_utopia_code(source: &str, _indent: usize) -> Result<String> {
@@ -940,6 +2051,28 @@ fn show_targets_info() {
     println!();
 }
 
+/// Table of targets and the language features each one supports
+fn print_capability_matrix(transformers: &TransformerManager) {
+    println!("{}", "🎯 Target Capabilities:".bright_blue().bold());
+    println!();
+    let mut header = format!("  {:<14}", "Target");
+    for feature in Feature::ALL {
+        header.push_str(&format!(" {:^7}", feature.short_name()));
+    }
+    println!("{}", header.trim_end().bold());
+    for (target, capabilities) in transformers.capability_matrix() {
+        let mut row = format!("  {:<14}", target);
+        for feature in Feature::ALL {
+            let mark = if capabilities.supports(feature) { "✓".green() } else { "✗".red() };
+            row.push_str(&format!("    {}   ", mark));
+        }
+        println!("{}", row.trim_end());
+    }
+    println!();
+    let legend: Vec<String> = Feature::ALL.iter().map(|feature| format!("{} = {}", feature.short_name(), feature.name())).collect();
+    println!("  {}", legend.join(", ").bright_black());
+}
+
 fn show_features_info() {
     println!("{}", "✨ Key Features:".bright_blue().bold());
     println!();
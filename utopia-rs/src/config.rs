@@ -2,12 +2,22 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub optimization_level: u8,
     pub target: String,
     pub debug: bool,
+    /// Optimization pass overrides such as `+simplify,-inline`
+    #[serde(default)]
+    pub passes: Option<String>,
+    /// Passes to dump the AST after (`all` or a comma separated list)
+    #[serde(default)]
+    pub dump_after: Option<String>,
+    /// Allow optimizations that are only exact for real numbers, not IEEE floats
+    #[serde(default)]
+    pub fast_math: bool,
     pub language_configs: HashMap<String, LanguageConfig>,
 }
 
@@ -17,6 +27,9 @@ impl Config {
             optimization_level: 2,
             target: "native".to_string(),
             debug: false,
+            passes: None,
+            dump_after: None,
+            fast_math: false,
             language_configs: HashMap::new(),
         }
     }
@@ -47,4 +60,86 @@ impl Default for LanguageConfig {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+}
+
+/// Name of the project configuration file
+pub const PROJECT_CONFIG_FILE: &str = "utopia.toml";
+
+/// Project settings read from `utopia.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub compiler: CompilerSection,
+    pub output: OutputSection,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompilerSection {
+    pub default_target: Option<String>,
+    pub targets: Vec<String>,
+    pub optimize: Option<bool>,
+    pub verbose: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSection {
+    pub directory: Option<String>,
+    pub preserve_comments: Option<bool>,
+}
+
+impl ProjectConfig {
+    /// Load `utopia.toml` from `dir`, if present
+    pub fn load<P: AsRef<Path>>(dir: P) -> crate::Result<Option<Self>> {
+        let path = dir.as_ref().join(PROJECT_CONFIG_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(Some(Self::parse(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(contents: &str) -> crate::Result<Self> {
+        toml::from_str(contents).map_err(|e| format!("Invalid {}: {}", PROJECT_CONFIG_FILE, e).into())
+    }
+
+    /// Targets to build: the `targets` list, else `default_target`, else `UTOPIA_TARGET`
+    pub fn build_targets(&self) -> Vec<String> {
+        if !self.compiler.targets.is_empty() {
+            return self.compiler.targets.clone();
+        }
+        self.compiler.default_target.clone()
+            .or_else(|| std::env::var("UTOPIA_TARGET").ok())
+            .into_iter()
+            .collect()
+    }
+
+    /// Output directory, falling back to `UTOPIA_OUTPUT_DIR`
+    pub fn output_directory(&self) -> Option<String> {
+        self.output.directory.clone()
+            .or_else(|| std::env::var("UTOPIA_OUTPUT_DIR").ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_config_parse() {
+        let config = ProjectConfig::parse(
+            "[compiler]\ndefault_target = \"python\"\noptimize = true\n\n[output]\ndirectory = \"./output\"\n",
+        ).unwrap();
+
+        assert_eq!(config.compiler.default_target.as_deref(), Some("python"));
+        assert_eq!(config.build_targets(), vec!["python".to_string()]);
+        assert_eq!(config.output.directory.as_deref(), Some("./output"));
+    }
+
+    #[test]
+    fn test_project_config_targets_list() {
+        let config = ProjectConfig::parse("[compiler]\ntargets = [\"python\", \"go\"]\n").unwrap();
+        assert_eq!(config.build_targets(), vec!["python".to_string(), "go".to_string()]);
+    }
+}
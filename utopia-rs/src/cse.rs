@@ -0,0 +1,441 @@
+//! Common subexpression elimination
+//!
+//! Within a straight-line run of statements, a pure expression computed more
+//! than once from the same variable values is computed once. When the first
+//! occurrence initializes a variable that is never reassigned, later
+//! occurrences read that variable; otherwise the value goes into a
+//! `const __cseN` temporary declared before the first occurrence.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::*,
+    callgraph::CallGraph,
+    effects::{EffectAnalysis, Effects},
+    inliner::is_trivial,
+    propagation::collect_assigned,
+};
+
+/// Removes repeated pure computations
+pub struct CommonSubexpressions {
+    next_id: usize,
+    pub eliminated: usize,
+}
+
+impl CommonSubexpressions {
+    pub fn new() -> Self {
+        Self { next_id: 0, eliminated: 0 }
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        let effects = EffectAnalysis::analyze(program, &CallGraph::build(program));
+        let mut read_by_functions = HashSet::new();
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                for statement in &function.body {
+                    collect_identifiers(statement, &mut read_by_functions);
+                }
+            }
+            collect_function_reads(&block.statements, &mut read_by_functions);
+        }
+        collect_function_reads(&program.global_statements, &mut read_by_functions);
+
+        for block in &mut program.language_blocks {
+            let pure = Purity { functions: effects.callable_from(&block.language), read_by_functions: &read_by_functions };
+            for function in &mut block.functions {
+                self.eliminate(&mut function.body, &pure);
+            }
+            self.eliminate(&mut block.statements, &pure);
+        }
+        let pure = Purity { functions: effects.callable_from("global"), read_by_functions: &read_by_functions };
+        self.eliminate(&mut program.global_statements, &pure);
+    }
+
+    fn eliminate(&mut self, statements: &mut Vec<Statement>, pure: &Purity) {
+        for statement in statements.iter_mut() {
+            match statement {
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.eliminate(then_branch, pure);
+                    if let Some(else_branch) = else_branch {
+                        self.eliminate(else_branch, pure);
+                    }
+                }
+                Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } | Statement::FunctionDeclaration { body, .. } => {
+                    self.eliminate(body, pure);
+                }
+                _ => {}
+            }
+        }
+
+        // Each round removes the largest repeated expression; replacing it can expose smaller ones
+        while let Some(repeated) = largest_repeated(statements, pure) {
+            self.replace(statements, repeated);
+            self.eliminated += 1;
+        }
+    }
+
+    fn replace(&mut self, statements: &mut Vec<Statement>, repeated: Repeated) {
+        let Repeated { expression, occurrences } = repeated;
+        let first = occurrences[0];
+        let span = expression.span();
+        log::debug!("Computing {} once, at line {}", PrettyPrinter::new().print_expression(&expression), span.line);
+
+        // `let t = a + b; ... a + b` reuses `t` when nothing else is ever assigned to it
+        let reusable = match &statements[first] {
+            Statement::VariableDeclaration { name, value: Some(value), .. } if same(value, &expression) => {
+                let mut assigned = HashSet::new();
+                collect_assigned(statements, &mut assigned);
+                (!assigned.contains(name)).then(|| name.clone())
+            }
+            _ => None,
+        };
+        let (name, replaced) = match &reusable {
+            Some(name) => (name.clone(), &occurrences[1..]),
+            None => (format!("__cse{}", self.next_id), &occurrences[..]),
+        };
+
+        let replacement = Expression::Identifier { name: name.clone(), resolved_type: None, span };
+        for &index in replaced {
+            if let Some(root) = evaluated_root_mut(&mut statements[index]) {
+                each_unconditional_mut(root, &mut |candidate| {
+                    if same(candidate, &expression) {
+                        *candidate = replacement.clone();
+                    }
+                });
+            }
+        }
+        if reusable.is_none() {
+            self.next_id += 1;
+            statements.insert(first, Statement::VariableDeclaration { name, value: Some(expression), var_type: None, is_const: true, span });
+        }
+    }
+}
+
+impl Default for CommonSubexpressions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What calls may read and change
+struct Purity<'a> {
+    /// Effects of the functions callable by name
+    functions: HashMap<String, Effects>,
+    /// Variables read inside any function, which a call may observe
+    read_by_functions: &'a HashSet<String>,
+}
+
+/// A pure expression computed by several statements of one straight-line run
+struct Repeated {
+    expression: Expression,
+    /// Indices of the statements computing it, in order
+    occurrences: Vec<usize>,
+}
+
+/// Find the largest pure expression computed at least twice from the same variable values
+fn largest_repeated(statements: &[Statement], pure: &Purity) -> Option<Repeated> {
+    let mut versions: HashMap<String, usize> = HashMap::new();
+    // Bumped by every change to a variable that read-only functions may read
+    let mut writes = 0;
+    // Bumped by every call that may change any variable
+    let mut epoch = 0;
+    let mut seen: HashMap<String, Repeated> = HashMap::new();
+    let mut order = Vec::new();
+
+    for (index, statement) in statements.iter().enumerate() {
+        // A call earlier in the statement could change the operands of the expressions after it
+        let root = evaluated_root(statement).filter(|root| {
+            let mut effects = false;
+            root.walk(&mut |expression| effects |= has_side_effects(expression, pure));
+            !effects
+        });
+        let mut candidates = Vec::new();
+        if let Some(root) = root {
+            each_unconditional(root, &mut |expression| {
+                if is_candidate(expression, pure) {
+                    candidates.push(expression);
+                }
+            });
+        }
+        for expression in candidates {
+            let key = value_key(expression, &versions, writes, epoch, pure);
+            let entry = seen.entry(key.clone()).or_insert_with(|| {
+                order.push(key);
+                Repeated { expression: expression.clone(), occurrences: Vec::new() }
+            });
+            if entry.occurrences.last() != Some(&index) {
+                entry.occurrences.push(index);
+            }
+        }
+
+        // Values computed before a change to their variables are not available after it
+        let mut assigned = HashSet::new();
+        collect_assigned(std::slice::from_ref(statement), &mut assigned);
+        if let Statement::VariableDeclaration { name, .. } = statement {
+            assigned.insert(name.clone());
+        }
+        let writes_memory = matches!(statement, Statement::Assignment { target, .. } if !matches!(target, Expression::Identifier { .. }));
+        if writes_memory || assigned.iter().any(|name| pure.read_by_functions.contains(name)) {
+            writes += 1;
+        }
+        for name in assigned {
+            *versions.entry(name).or_default() += 1;
+        }
+        // Assignments to variables are covered by their versions
+        let mut effects = false;
+        if !matches!(statement, Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. }) {
+            statement.walk_expressions(&mut |expression| {
+                effects |= match expression {
+                    Expression::Assignment { target, .. } => !matches!(target.as_ref(), Expression::Identifier { .. }),
+                    _ => has_side_effects(expression, pure),
+                };
+            });
+        }
+        if effects {
+            epoch += 1;
+        }
+    }
+
+    let mut best: Option<Repeated> = None;
+    for key in order {
+        let repeated = seen.remove(&key).expect("every key in order was seen");
+        let count = repeated.occurrences.len();
+        let size = expression_size(&repeated.expression);
+        let better = best.as_ref().is_none_or(|best| size > expression_size(&best.expression));
+        if count >= 2 && better {
+            best = Some(repeated);
+        }
+    }
+    best
+}
+
+/// The expression together with the versions of the variables it reads
+///
+/// A call of a function that reads variables outside of it also depends on every change to them.
+fn value_key(expression: &Expression, versions: &HashMap<String, usize>, writes: usize, epoch: usize, pure: &Purity) -> String {
+    let mut key = PrettyPrinter::new().print_expression(expression);
+    let mut calls = false;
+    let mut names = Vec::new();
+    expression.walk(&mut |expression| match expression {
+        Expression::Identifier { name, .. } => names.push(name.as_str()),
+        Expression::Call { callee, .. } => calls |= !calls_pure(callee, pure),
+        _ => {}
+    });
+    names.sort_unstable();
+    names.dedup();
+    for name in names {
+        key.push_str(&format!(" {}@{}", name, versions.get(name).copied().unwrap_or(0)));
+    }
+    if calls {
+        key.push_str(&format!(" writes@{writes}"));
+    }
+    key.push_str(&format!(" epoch@{epoch}"));
+    key
+}
+
+fn expression_size(expression: &Expression) -> usize {
+    let mut size = 0;
+    expression.walk(&mut |_| size += 1);
+    size
+}
+
+/// Non-constant arithmetic, comparisons and calls of read-only functions over variables and literals
+fn is_candidate(expression: &Expression, pure: &Purity) -> bool {
+    let mut reads_variable = false;
+    expression.walk(&mut |expression| reads_variable |= matches!(expression, Expression::Identifier { .. }));
+    !is_trivial(expression) && reads_variable && is_pure(expression, pure)
+}
+
+fn is_pure(expression: &Expression, pure: &Purity) -> bool {
+    match expression {
+        Expression::Literal { .. } | Expression::Identifier { .. } => true,
+        Expression::Unary { operand, .. } => is_pure(operand, pure),
+        Expression::Binary { left, right, .. } => is_pure(left, pure) && is_pure(right, pure),
+        Expression::Call { callee, arguments, .. } => {
+            calls_read_only(callee, pure) && arguments.iter().all(|argument| is_pure(argument, pure))
+        }
+        _ => false,
+    }
+}
+
+/// Whether evaluating `expression` itself may change variables
+fn has_side_effects(expression: &Expression, pure: &Purity) -> bool {
+    match expression {
+        Expression::Call { callee, .. } => !calls_read_only(callee, pure),
+        Expression::CrossCall { .. } | Expression::Assignment { .. } | Expression::Postfix { .. } => true,
+        _ => false,
+    }
+}
+
+fn calls_read_only(callee: &Expression, pure: &Purity) -> bool {
+    matches!(callee, Expression::Identifier { name, .. } if pure.functions.get(name).is_some_and(Effects::is_read_only))
+}
+
+fn calls_pure(callee: &Expression, pure: &Purity) -> bool {
+    matches!(callee, Expression::Identifier { name, .. } if pure.functions.get(name).is_some_and(Effects::is_pure))
+}
+
+/// The expression `statement` evaluates before any of its own effects
+fn evaluated_root(statement: &Statement) -> Option<&Expression> {
+    match statement {
+        Statement::Expression { expression, .. }
+        | Statement::If { condition: expression, .. }
+        | Statement::VariableDeclaration { value: Some(expression), .. }
+        | Statement::Return { value: Some(expression), .. }
+        | Statement::Assignment { target: Expression::Identifier { .. }, value: expression, .. } => Some(expression),
+        _ => None,
+    }
+}
+
+fn evaluated_root_mut(statement: &mut Statement) -> Option<&mut Expression> {
+    match statement {
+        Statement::Expression { expression, .. }
+        | Statement::If { condition: expression, .. }
+        | Statement::VariableDeclaration { value: Some(expression), .. }
+        | Statement::Return { value: Some(expression), .. }
+        | Statement::Assignment { target: Expression::Identifier { .. }, value: expression, .. } => Some(expression),
+        _ => None,
+    }
+}
+
+/// Visit the parts of `expression` that always run, outermost first
+///
+/// The right side of `&&` and `||` may not run at all, so computing it earlier could fail
+/// where the original did not.
+fn each_unconditional<'a>(expression: &'a Expression, visit: &mut dyn FnMut(&'a Expression)) {
+    visit(expression);
+    match expression {
+        Expression::Binary { left, operator: BinaryOperator::And | BinaryOperator::Or, .. } => each_unconditional(left, visit),
+        Expression::Binary { left, right, .. } => {
+            each_unconditional(left, visit);
+            each_unconditional(right, visit);
+        }
+        Expression::Unary { operand, .. } => each_unconditional(operand, visit),
+        Expression::Call { arguments, .. } => {
+            for argument in arguments {
+                each_unconditional(argument, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn each_unconditional_mut(expression: &mut Expression, visit: &mut dyn FnMut(&mut Expression)) {
+    visit(expression);
+    match expression {
+        Expression::Binary { left, operator: BinaryOperator::And | BinaryOperator::Or, .. } => each_unconditional_mut(left, visit),
+        Expression::Binary { left, right, .. } => {
+            each_unconditional_mut(left, visit);
+            each_unconditional_mut(right, visit);
+        }
+        Expression::Unary { operand, .. } => each_unconditional_mut(operand, visit),
+        Expression::Call { arguments, .. } => {
+            for argument in arguments {
+                each_unconditional_mut(argument, visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Variables read by the functions declared anywhere in `statements`
+fn collect_function_reads(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } => collect_identifiers(statement, names),
+            Statement::If { then_branch, else_branch, .. } => {
+                collect_function_reads(then_branch, names);
+                if let Some(else_branch) = else_branch {
+                    collect_function_reads(else_branch, names);
+                }
+            }
+            Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+                collect_function_reads(body, names);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_identifiers(statement: &Statement, names: &mut HashSet<String>) {
+    statement.walk_expressions(&mut |expression| {
+        if let Expression::Identifier { name, .. } = expression {
+            names.insert(name.clone());
+        }
+    });
+}
+
+/// Structural equality, ignoring source positions
+fn same(left: &Expression, right: &Expression) -> bool {
+    PrettyPrinter::new().print_expression(left) == PrettyPrinter::new().print_expression(right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    /// Eliminate common subexpressions in `source`, checking it prints the same before and after
+    fn eliminate(source: &str) -> (String, usize) {
+        let original = parse(source);
+        let mut optimized = original.clone();
+        let mut cse = CommonSubexpressions::new();
+        cse.run(&mut optimized);
+
+        let printed = PrettyPrinter::new().print(&optimized);
+        let expected = Interpreter::new().run(&original).unwrap();
+        assert_eq!(expected, Interpreter::new().run(&optimized).unwrap(), "{}", printed);
+        (printed, cse.eliminated)
+    }
+
+    #[test]
+    fn test_eliminates_repeated_expressions() {
+        let (output, eliminated) = eliminate(
+            "function area(w, h) {\n    return w * h;\n}\nlet a = 3;\nlet b = 4;\nlet x = (a + b) * 2 + area(a, b);\nlet y = (a + b) * 3 - area(a, b);\nprint(x, y);\n",
+        );
+        assert_eq!(eliminated, 2);
+        assert!(output.contains("const __cse0 = area(a, b);\n  const __cse1 = a + b;\n  let x = __cse1 * 2 + __cse0;"), "{}", output);
+        assert!(output.contains("let y = __cse1 * 3 - __cse0;"), "{}", output);
+    }
+
+    #[test]
+    fn test_reuses_variables_holding_the_value() {
+        let (output, eliminated) = eliminate("let a = 3;\nlet b = 4;\nlet sum = a + b;\nlet twice = (a + b) * 2;\nprint(sum, twice);\n");
+        assert_eq!(eliminated, 1);
+        assert!(output.contains("let twice = sum * 2;"), "{}", output);
+        assert!(!output.contains("__cse"), "{}", output);
+    }
+
+    #[test]
+    fn test_pure_calls_do_not_depend_on_globals() {
+        let (output, eliminated) = eliminate(
+            "let rate = 2;\nfunction cost(x) {\n    return x * rate;\n}\nfunction square(x) {\n    return x * x;\n}\nlet a = 3;\n\
+             let p = square(a) + cost(a);\nrate = 5;\nlet q = square(a) + cost(a);\nprint(p, q);\n",
+        );
+        assert_eq!(eliminated, 1);
+        assert!(output.contains("const __cse0 = square(a);"), "{}", output);
+        assert_eq!(output.matches("cost(a)").count(), 2, "{}", output);
+    }
+
+    #[test]
+    fn test_respects_changes_between_occurrences() {
+        for source in [
+            // Reassigned operand
+            "let a = 3;\nlet x = a * a;\na = 5;\nlet y = a * a;\nprint(x, y);\n",
+            // Global read by a read-only function
+            "let g = 1;\nfunction scaled(n) {\n    return n * g;\n}\nlet x = scaled(2);\ng = 10;\nlet y = scaled(2);\nprint(x, y);\n",
+            // Impure call changing an operand
+            "let a = 1;\nfunction bump() {\n    a = a + 1;\n    return 0;\n}\nlet x = a * 2;\nbump();\nlet y = a * 2;\nprint(x, y);\n",
+            // Only computed when the left side allows it
+            "let a = 0;\nlet b = 6;\nlet x = a != 0 && b / a > 1;\nlet y = a > 0 && b / a > 2;\nprint(x, y);\n",
+        ] {
+            let (output, _) = eliminate(source);
+            assert!(!output.contains("__cse"), "{}", output);
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! Whole-program dead code elimination
+//!
+//! Functions are live when the call graph reaches them from an entry point:
+//! the top-level statements of the program and of every language block,
+//! functions named `main` and exported functions. Cross-language calls are
+//! followed like any other call. Unreachable functions are removed, and so are
+//! language blocks left with neither statements nor functions.
+//!
+//! Within each function and block, a backward liveness analysis finds local
+//! variables that are never read and stores whose value is overwritten or
+//! goes out of scope before it is read.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+    ast::*,
+    callgraph::{block_unit_id, function_unit_id, CallGraph, GLOBAL_UNIT},
+    diagnostics::Diagnostic,
+    Span,
+};
+
+/// Functions and blocks removed because nothing reaches them
+#[derive(Debug, Default)]
+pub struct Unreachable {
+    /// Call graph ids of the removed functions
+    pub functions: Vec<String>,
+    /// Languages of the removed blocks
+    pub blocks: Vec<String>,
+}
+
+/// Units reachable from the program's entry points, or `None` when it has no entry point
+pub fn live_units(program: &Program, graph: &CallGraph) -> Option<BTreeSet<String>> {
+    let mut roots = Vec::new();
+    let exported = exported_names(program);
+    for (index, block) in program.language_blocks.iter().enumerate() {
+        if !block.statements.is_empty() {
+            roots.push(block_unit_id(&block.language, index));
+        }
+        let declared = block.statements.iter().filter_map(|statement| match statement {
+            Statement::FunctionDeclaration { name, .. } => Some(name),
+            _ => None,
+        });
+        for name in block.functions.iter().map(|function| &function.name).chain(declared) {
+            if name == "main" || exported.contains(name.as_str()) {
+                roots.push(function_unit_id(&block.language, name));
+            }
+        }
+    }
+    if !program.global_statements.is_empty() {
+        roots.push(GLOBAL_UNIT.to_string());
+    }
+    if roots.is_empty() {
+        return None;
+    }
+    Some(graph.reachable_from(roots.iter().map(String::as_str)))
+}
+
+fn exported_names(program: &Program) -> HashSet<&str> {
+    program.global_statements.iter()
+        .chain(program.language_blocks.iter().flat_map(|block| &block.statements))
+        .filter_map(|statement| match statement {
+            Statement::Export { item, .. } => Some(item.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Remove the functions no entry point reaches and the blocks left empty
+pub fn remove_unreachable(program: &mut Program) -> Unreachable {
+    let graph = CallGraph::build(program);
+    let Some(live) = live_units(program, &graph) else {
+        return Unreachable::default();
+    };
+
+    let mut removed = Unreachable::default();
+    for block in &mut program.language_blocks {
+        let language = block.language.clone();
+        let mut is_live = |name: &str| {
+            let id = function_unit_id(&language, name);
+            let keep = live.contains(&id);
+            if !keep {
+                removed.functions.push(id);
+            }
+            keep
+        };
+        block.functions.retain(|function| is_live(&function.name));
+        block.statements.retain(|statement| match statement {
+            Statement::FunctionDeclaration { name, .. } => is_live(name),
+            _ => true,
+        });
+    }
+    program.global_statements.retain(|statement| match statement {
+        Statement::FunctionDeclaration { name, .. } => {
+            let id = function_unit_id("global", name);
+            let keep = live.contains(&id);
+            if !keep {
+                removed.functions.push(id);
+            }
+            keep
+        }
+        _ => true,
+    });
+    program.language_blocks.retain(|block| {
+        let keep = !block.functions.is_empty() || !block.statements.is_empty();
+        if !keep {
+            removed.blocks.push(block.language.clone());
+        }
+        keep
+    });
+    removed
+}
+
+/// What a dead store finding is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadKind {
+    /// A variable that is never read at all
+    UnusedVariable,
+    /// A value that is never read before being overwritten or going out of scope
+    DeadStore,
+}
+
+/// An unused variable or dead store
+#[derive(Debug, Clone)]
+pub struct DeadStore {
+    pub kind: DeadKind,
+    pub name: String,
+    pub span: Span,
+}
+
+impl DeadStore {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let message = match self.kind {
+            DeadKind::UnusedVariable => format!("variable `{}` is never used", self.name),
+            DeadKind::DeadStore => format!("value assigned to `{}` is never read", self.name),
+        };
+        Diagnostic::warning(message, self.span)
+    }
+}
+
+/// Finds, and optionally removes, unused variables and dead stores
+pub struct DeadStores {
+    remove: bool,
+    pub found: Vec<DeadStore>,
+    /// Statements removed or reduced to their side effects
+    pub removed: usize,
+}
+
+impl DeadStores {
+    /// Only report when `remove` is false
+    pub fn new(remove: bool) -> Self {
+        Self { remove, found: Vec::new(), removed: 0 }
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        // Top-level variables can be read by any function
+        let mut read_by_functions = HashSet::new();
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                for statement in &function.body {
+                    collect_names(statement, &mut read_by_functions);
+                }
+            }
+        }
+        for statement in program.language_blocks.iter().flat_map(|block| &block.statements).chain(&program.global_statements) {
+            if matches!(statement, Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. }) {
+                collect_names(statement, &mut read_by_functions);
+            }
+        }
+
+        for block in &mut program.language_blocks {
+            for function in &mut block.functions {
+                self.unit(&function.parameters, &mut function.body, &HashSet::new());
+            }
+            self.unit(&[], &mut block.statements, &read_by_functions);
+        }
+        self.unit(&[], &mut program.global_statements, &read_by_functions);
+    }
+
+    /// Analyze one function or block; `shared` are names code outside it may read
+    fn unit(&mut self, parameters: &[Parameter], body: &mut Vec<Statement>, shared: &HashSet<String>) {
+        self.nested_units(body);
+
+        let mut declarations: HashMap<String, usize> = HashMap::new();
+        for parameter in parameters {
+            *declarations.entry(parameter.name.clone()).or_default() += 1;
+        }
+        count_declarations(body, &mut declarations);
+
+        // Variables captured by nested functions are read whenever those run
+        let mut escaping = shared.clone();
+        for statement in body.iter() {
+            collect_captured(statement, &mut escaping);
+        }
+
+        // Shadowed names are left alone
+        let candidates: HashSet<String> = declarations.into_iter()
+            .filter(|(name, count)| *count == 1 && !escaping.contains(name))
+            .map(|(name, _)| name)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let mut read = HashSet::new();
+        collect_reads(body, &mut read);
+        let mut liveness = Liveness { candidates: &candidates, read: &read, record: true, remove: self.remove, found: Vec::new(), removed: 0 };
+        liveness.statements(body, &mut HashSet::new());
+
+        // Reported in source order; the analysis runs backwards
+        liveness.found.reverse();
+        self.found.extend(liveness.found);
+        self.removed += liveness.removed;
+    }
+}
+
+impl DeadStores {
+    /// Analyze the functions and methods declared inside `statements`
+    fn nested_units(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FunctionDeclaration { parameters, body, .. } => self.unit(parameters, body, &HashSet::new()),
+                Statement::ClassDeclaration { methods, .. } => {
+                    for method in methods {
+                        self.unit(&method.parameters, &mut method.body, &HashSet::new());
+                    }
+                }
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.nested_units(then_branch);
+                    if let Some(else_branch) = else_branch {
+                        self.nested_units(else_branch);
+                    }
+                }
+                Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+                    self.nested_units(body);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for DeadStores {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+/// What happens to a statement after the analysis
+enum Outcome {
+    Keep,
+    Remove,
+    /// Keep only the side effects of its value
+    Replace(Box<Statement>),
+}
+
+/// Backward liveness over structured statements
+struct Liveness<'a> {
+    /// Variables that may be reported
+    candidates: &'a HashSet<String>,
+    /// Variables read anywhere in the unit
+    read: &'a HashSet<String>,
+    /// Whether to record findings and remove dead code; off while loops reach a fixpoint
+    record: bool,
+    remove: bool,
+    found: Vec<DeadStore>,
+    removed: usize,
+}
+
+impl Liveness<'_> {
+    /// Turn the variables live after `statements` into those live before them
+    fn statements(&mut self, statements: &mut Vec<Statement>, live: &mut HashSet<String>) {
+        for index in (0..statements.len()).rev() {
+            match self.statement(&mut statements[index], live) {
+                Outcome::Keep => {}
+                Outcome::Remove => {
+                    statements.remove(index);
+                    self.removed += 1;
+                }
+                Outcome::Replace(effects) => {
+                    statements[index] = *effects;
+                    self.removed += 1;
+                }
+            }
+        }
+    }
+
+    /// Transfer `statement`, saying whether it stays
+    fn statement(&mut self, statement: &mut Statement, live: &mut HashSet<String>) -> Outcome {
+        match statement {
+            Statement::VariableDeclaration { name, value, span, .. } => {
+                let dead = self.is_dead(name, live);
+                if dead {
+                    let kind = if self.read.contains(name.as_str()) { DeadKind::DeadStore } else { DeadKind::UnusedVariable };
+                    // An initializer is only worth reporting when the variable is otherwise used
+                    if kind == DeadKind::UnusedVariable || value.is_some() {
+                        self.report(kind, name, *span);
+                    }
+                }
+                live.remove(name.as_str());
+                let removable = dead && self.remove && !self.read.contains(name.as_str());
+                if removable {
+                    return match value.take().filter(has_effects) {
+                        Some(value) => Outcome::Replace(Box::new(Statement::Expression { expression: value, span: *span })),
+                        None => Outcome::Remove,
+                    };
+                }
+                if let Some(value) = value {
+                    add_reads(value, live);
+                }
+            }
+            Statement::Assignment { target: Expression::Identifier { name, .. }, value, span } => return self.store(name, value, *span, live),
+            Statement::Expression { expression: Expression::Assignment { target, value, .. }, span } => {
+                if let Expression::Identifier { name, .. } = target.as_ref() {
+                    return self.store(name, value, *span, live);
+                }
+                add_reads(target, live);
+                add_reads(value, live);
+            }
+            Statement::Assignment { target, value, .. } => {
+                add_reads(target, live);
+                add_reads(value, live);
+            }
+            Statement::Expression { expression, .. } => add_reads(expression, live),
+            Statement::Return { value, .. } => {
+                // Nothing after a return runs
+                live.clear();
+                if let Some(value) = value {
+                    add_reads(value, live);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                let mut then_live = live.clone();
+                self.statements(then_branch, &mut then_live);
+                if let Some(else_branch) = else_branch {
+                    self.statements(else_branch, live);
+                }
+                live.extend(then_live);
+                add_reads(condition, live);
+            }
+            Statement::While { condition, body, .. } => {
+                let head = self.loop_head(live, Some(&*condition), None, body);
+                let mut body_live = head.clone();
+                self.statements(body, &mut body_live);
+                *live = head;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                let head = self.loop_head(live, condition.as_ref(), update.as_ref(), body);
+                let mut body_live = head.clone();
+                if let Some(update) = update {
+                    add_reads(update, &mut body_live);
+                }
+                self.statements(body, &mut body_live);
+                *live = head;
+                if let Some(init) = init {
+                    let mut init_statements = vec![init.as_ref().clone()];
+                    let record = std::mem::replace(&mut self.record, false);
+                    self.statements(&mut init_statements, live);
+                    self.record = record;
+                }
+            }
+            Statement::Block { statements, .. } => self.statements(statements, live),
+            Statement::Export { item, .. } => {
+                live.insert(item.clone());
+            }
+            Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } | Statement::Import { .. } => {}
+        }
+        Outcome::Keep
+    }
+
+    /// Transfer `name = value`
+    fn store(&mut self, name: &str, value: &mut Expression, span: Span, live: &mut HashSet<String>) -> Outcome {
+        let dead = self.is_dead(name, live);
+        if dead && self.read.contains(name) {
+            self.report(DeadKind::DeadStore, name, span);
+        }
+        live.remove(name);
+        if dead && self.remove {
+            if !has_effects(value) {
+                return Outcome::Remove;
+            }
+            let value = std::mem::replace(value, Expression::Literal { value: LiteralValue::Null, span });
+            return Outcome::Replace(Box::new(Statement::Expression { expression: value, span }));
+        }
+        add_reads(value, live);
+        Outcome::Keep
+    }
+
+    /// Variables live at the top of a loop: after it, or read by its condition or a later iteration
+    fn loop_head(&mut self, after: &HashSet<String>, condition: Option<&Expression>, update: Option<&Expression>, body: &[Statement]) -> HashSet<String> {
+        let record = std::mem::replace(&mut self.record, false);
+        let remove = std::mem::replace(&mut self.remove, false);
+
+        let mut head = after.clone();
+        if let Some(condition) = condition {
+            add_reads(condition, &mut head);
+        }
+        loop {
+            let mut body_live = head.clone();
+            if let Some(update) = update {
+                add_reads(update, &mut body_live);
+            }
+            let mut scratch = body.to_vec();
+            self.statements(&mut scratch, &mut body_live);
+            let before = head.len();
+            head.extend(body_live);
+            if head.len() == before {
+                break;
+            }
+        }
+
+        self.record = record;
+        self.remove = remove;
+        head
+    }
+
+    fn is_dead(&self, name: &str, live: &HashSet<String>) -> bool {
+        self.record && self.candidates.contains(name) && !live.contains(name)
+    }
+
+    fn report(&mut self, kind: DeadKind, name: &str, span: Span) {
+        // The optimizer's own temporaries are removed silently
+        if name.starts_with("__") {
+            return;
+        }
+        self.found.push(DeadStore { kind, name: name.to_string(), span });
+    }
+}
+
+/// Whether evaluating `expression` can do more than produce a value
+fn has_effects(expression: &Expression) -> bool {
+    let mut effects = false;
+    expression.walk(&mut |expression| {
+        effects |= matches!(
+            expression,
+            Expression::Call { .. } | Expression::CrossCall { .. } | Expression::Assignment { .. } | Expression::Postfix { .. }
+        );
+    });
+    effects
+}
+
+/// Add the variables `expression` reads; plain assignment targets are not reads
+fn add_reads(expression: &Expression, live: &mut HashSet<String>) {
+    match expression {
+        Expression::Assignment { target, value, .. } => {
+            if !matches!(target.as_ref(), Expression::Identifier { .. }) {
+                add_reads(target, live);
+            }
+            add_reads(value, live);
+        }
+        expression => expression.walk(&mut |expression| {
+            if let Expression::Identifier { name, .. } = expression {
+                live.insert(name.clone());
+            }
+        }),
+    }
+}
+
+/// Variables read anywhere in `statements`, including by nested functions
+fn collect_reads(statements: &[Statement], read: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::Expression { expression: value, .. }
+            | Statement::VariableDeclaration { value: Some(value), .. }
+            | Statement::Return { value: Some(value), .. }
+            | Statement::Assignment { target: Expression::Identifier { .. }, value, .. } => add_reads(value, read),
+            Statement::Assignment { target, value, .. } => {
+                add_reads(target, read);
+                add_reads(value, read);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                add_reads(condition, read);
+                collect_reads(then_branch, read);
+                if let Some(else_branch) = else_branch {
+                    collect_reads(else_branch, read);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                add_reads(condition, read);
+                collect_reads(body, read);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(init) = init {
+                    collect_reads(std::slice::from_ref(init.as_ref()), read);
+                }
+                for expression in condition.iter().chain(update.iter()) {
+                    add_reads(expression, read);
+                }
+                collect_reads(body, read);
+            }
+            Statement::Block { statements, .. } => collect_reads(statements, read),
+            Statement::Export { item, .. } => {
+                read.insert(item.clone());
+            }
+            Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } => collect_names(statement, read),
+            Statement::VariableDeclaration { value: None, .. } | Statement::Return { value: None, .. } | Statement::Import { .. } => {}
+        }
+    }
+}
+
+fn collect_names(statement: &Statement, names: &mut HashSet<String>) {
+    statement.walk_expressions(&mut |expression| {
+        if let Expression::Identifier { name, .. } = expression {
+            names.insert(name.clone());
+        }
+    });
+}
+
+/// Names used inside functions, classes and lambdas nested in `statement`
+fn collect_captured(statement: &Statement, names: &mut HashSet<String>) {
+    match statement {
+        Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } => collect_names(statement, names),
+        statement => statement.walk_expressions(&mut |expression| {
+            if let Expression::Lambda { body, .. } = expression {
+                for statement in body {
+                    collect_names(statement, names);
+                }
+            }
+        }),
+    }
+    match statement {
+        Statement::If { then_branch, else_branch, .. } => {
+            for statement in then_branch.iter().chain(else_branch.iter().flatten()) {
+                collect_captured(statement, names);
+            }
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+            for statement in body {
+                collect_captured(statement, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn count_declarations(statements: &[Statement], declarations: &mut HashMap<String, usize>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, .. } => *declarations.entry(name.clone()).or_default() += 1,
+            Statement::If { then_branch, else_branch, .. } => {
+                count_declarations(then_branch, declarations);
+                if let Some(else_branch) = else_branch {
+                    count_declarations(else_branch, declarations);
+                }
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => count_declarations(body, declarations),
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    count_declarations(std::slice::from_ref(init.as_ref()), declarations);
+                }
+                count_declarations(body, declarations);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, optimizer::Optimizer};
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    fn dead_stores(source: &str) -> Vec<(DeadKind, String, usize)> {
+        let mut dead_stores = DeadStores::new(false);
+        dead_stores.run(&mut parse(source));
+        dead_stores.found.into_iter().map(|found| (found.kind, found.name, found.span.line)).collect()
+    }
+
+    #[test]
+    fn test_removes_unreachable_functions_and_blocks() {
+        let mut program = parse(
+            "@lang python {\n    function helper(x) {\n        return x * 2;\n    }\n    function unused() {\n        return 1;\n    }\n}\n\
+             @lang rust {\n    function orphan() {\n        return 2;\n    }\n}\n\
+             @lang javascript {\n    function compute(n) {\n        return python::helper(n);\n    }\n}\n\
+             @lang main {\n    println(javascript::compute(4));\n}\n",
+        );
+        let removed = remove_unreachable(&mut program);
+        assert_eq!(removed.functions, vec!["python::unused", "rust::orphan"]);
+        assert_eq!(removed.blocks, vec!["rust"]);
+        let languages: Vec<&str> = program.language_blocks.iter().map(|block| block.language.as_str()).collect();
+        assert_eq!(languages, vec!["python", "javascript", "main"]);
+
+        // Without statements or a `main` function there is no entry point to start from
+        let mut library = parse("@lang python {\n    function helper(x) {\n        return x * 2;\n    }\n}\n");
+        assert!(remove_unreachable(&mut library).functions.is_empty());
+        assert_eq!(library.language_blocks[0].functions.len(), 1);
+    }
+
+    #[test]
+    fn test_reports_unused_variables_and_dead_stores() {
+        let found = dead_stores(
+            "function f(n) {\n    let total = 0;\n    let scratch = n * 3;\n    total = n;\n    total = n + 1;\n    return total;\n}\n\
+             let sum = 0;\nfor (let i = 0; i < 3; i++) {\n    sum = sum + i;\n}\nlet last = 0;\nlast = sum;\nprint(f(last));\n",
+        );
+        assert_eq!(found, vec![
+            (DeadKind::DeadStore, "total".to_string(), 2),
+            (DeadKind::UnusedVariable, "scratch".to_string(), 3),
+            (DeadKind::DeadStore, "total".to_string(), 4),
+            (DeadKind::DeadStore, "last".to_string(), 12),
+        ]);
+
+        // Values read by nested functions or a later iteration are live
+        assert!(dead_stores(
+            "let seen = 0;\nfunction show() {\n    print(seen);\n}\nseen = 1;\nshow();\n\
+             let i = 0;\nlet previous = 0;\nwhile (i < 3) {\n    print(previous);\n    previous = i;\n    i = i + 1;\n}\n",
+        ).is_empty());
+    }
+
+    #[test]
+    fn test_removal_keeps_side_effects() {
+        let original = parse(
+            "function noisy(x) {\n    print(x);\n    return x;\n}\nfunction f(n) {\n    let unused = noisy(n);\n    let result = 0;\n    result = n * 2;\n    result = noisy(n + 1);\n    return result;\n}\nprint(f(3));\n",
+        );
+        let mut optimized = original.clone();
+        let mut dead_stores = DeadStores::new(true);
+        dead_stores.run(&mut optimized);
+        assert_eq!(dead_stores.removed, 2);
+
+        let output = PrettyPrinter::new().print(&optimized);
+        assert!(output.contains("    noisy(n);\n"), "{}", output);
+        assert!(!output.contains("unused"), "{}", output);
+        assert!(!output.contains("n * 2"), "{}", output);
+        assert_eq!(Interpreter::new().run(&original).unwrap(), Interpreter::new().run(&optimized).unwrap());
+    }
+
+    #[test]
+    fn test_optimizer_reports_from_o1_and_removes_from_o2() {
+        let source = "function f(n) {\n    let unused = n * 2;\n    return n;\n}\nfunction never() {\n    return 0;\n}\nprint(f(1));\n";
+        for level in [1, 2] {
+            let mut optimizer = Optimizer::for_level(level);
+            let output = PrettyPrinter::new().print(&optimizer.optimize(parse(source)).unwrap());
+            assert_eq!(optimizer.diagnostics().len(), 1);
+            assert_eq!(optimizer.diagnostics()[0].message, "variable `unused` is never used");
+            assert!(!output.contains("never()"), "{}", output);
+            assert_eq!(output.contains("unused"), level < 2, "{}", output);
+        }
+    }
+}
@@ -89,6 +89,16 @@ impl Diagnostic {
     }
 }
 
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.kind)?;
+        if let Some(code) = &self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
 /// A suggestion for fixing a diagnostic
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Suggestion {
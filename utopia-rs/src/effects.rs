@@ -0,0 +1,390 @@
+//! Effect analysis
+//!
+//! Classifies every function by what calling it can do besides computing its
+//! result: read or write variables declared outside of it, read or write the
+//! fields and elements of objects and arrays, perform IO, or call into another
+//! language block. The effects of callees are included by following the call
+//! graph, so a function that calls a logging helper does IO as well.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::{
+    ast::*,
+    builtins,
+    callgraph::{function_unit_id, CallGraph},
+    inliner::declared_names,
+    propagation::collect_assigned,
+};
+
+/// One class of effect, in the order reports list them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EffectKind {
+    Pure,
+    ReadsGlobal,
+    WritesGlobal,
+    Io,
+    CrossLanguage,
+}
+
+impl EffectKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EffectKind::Pure => "pure",
+            EffectKind::ReadsGlobal => "reads global",
+            EffectKind::WritesGlobal => "writes global",
+            EffectKind::Io => "io",
+            EffectKind::CrossLanguage => "cross-language",
+        }
+    }
+}
+
+impl std::fmt::Display for EffectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What calling a function may do, including everything its callees do
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Effects {
+    /// Variables declared outside the function that it reads
+    pub reads: BTreeSet<String>,
+    /// Variables declared outside the function that it assigns
+    pub writes: BTreeSet<String>,
+    /// Reads fields of objects or elements of arrays, which other code may change
+    pub reads_memory: bool,
+    /// Changes fields of objects or elements of arrays, or calls a method that may
+    pub writes_memory: bool,
+    /// Prints, reads input or calls a function defined outside the program
+    pub io: bool,
+    /// Calls a function of another language block
+    pub cross_language: bool,
+}
+
+impl Effects {
+    /// The result depends only on the arguments and calling has no other effect
+    pub fn is_pure(&self) -> bool {
+        self.is_read_only() && self.reads.is_empty() && !self.reads_memory
+    }
+
+    /// Calling changes nothing, so calls may be repeated, dropped or moved past
+    /// code that does not write what the function reads
+    pub fn is_read_only(&self) -> bool {
+        self.writes.is_empty() && !self.writes_memory && !self.io && !self.cross_language
+    }
+
+    pub fn kinds(&self) -> Vec<EffectKind> {
+        if self.is_pure() {
+            return vec![EffectKind::Pure];
+        }
+        let mut kinds = Vec::new();
+        if !self.reads.is_empty() || self.reads_memory {
+            kinds.push(EffectKind::ReadsGlobal);
+        }
+        if !self.writes.is_empty() || self.writes_memory {
+            kinds.push(EffectKind::WritesGlobal);
+        }
+        if self.io {
+            kinds.push(EffectKind::Io);
+        }
+        if self.cross_language {
+            kinds.push(EffectKind::CrossLanguage);
+        }
+        kinds
+    }
+
+    /// Add the effects of a callee, returning whether anything changed
+    fn merge(&mut self, other: &Effects) -> bool {
+        let before = (self.reads.len(), self.writes.len(), self.reads_memory, self.writes_memory, self.io, self.cross_language);
+        self.reads.extend(other.reads.iter().cloned());
+        self.writes.extend(other.writes.iter().cloned());
+        self.reads_memory |= other.reads_memory;
+        self.writes_memory |= other.writes_memory;
+        self.io |= other.io;
+        self.cross_language |= other.cross_language;
+        before != (self.reads.len(), self.writes.len(), self.reads_memory, self.writes_memory, self.io, self.cross_language)
+    }
+}
+
+impl std::fmt::Display for Effects {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kinds: Vec<&str> = self.kinds().into_iter().map(EffectKind::name).collect();
+        write!(f, "{}", kinds.join(", "))
+    }
+}
+
+/// Effects of every function in a program
+#[derive(Debug, Clone, Default)]
+pub struct EffectAnalysis {
+    /// By call graph unit id
+    functions: BTreeMap<String, Effects>,
+}
+
+impl EffectAnalysis {
+    pub fn analyze(program: &Program, graph: &CallGraph) -> Self {
+        let mut functions = BTreeMap::new();
+        let global_variables = top_level_variables(&program.global_statements);
+        let global_functions = declared_functions(&program.global_statements);
+        for block in &program.language_blocks {
+            let mut variables = global_variables.clone();
+            let mut callable = global_functions.clone();
+            for other in program.language_blocks.iter().filter(|other| other.language == block.language) {
+                variables.extend(top_level_variables(&other.statements));
+                callable.extend(other.functions.iter().map(|function| function.name.as_str()));
+                callable.extend(declared_functions(&other.statements));
+            }
+            let scope = Scope { variables: &variables, functions: &callable };
+            for function in &block.functions {
+                functions.insert(function_unit_id(&block.language, &function.name), scope.effects(&function.parameters, &function.body));
+            }
+            for (name, parameters, body) in declarations(&block.statements) {
+                functions.insert(function_unit_id(&block.language, name), scope.effects(parameters, body));
+            }
+        }
+        let scope = Scope { variables: &global_variables, functions: &global_functions };
+        for (name, parameters, body) in declarations(&program.global_statements) {
+            functions.insert(function_unit_id("global", name), scope.effects(parameters, body));
+        }
+
+        // A function has the effects of everything it calls
+        let ids: Vec<String> = functions.keys().cloned().collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in &ids {
+                let callees: Vec<Effects> = graph.callees(id).into_iter()
+                    .filter(|callee| callee != id)
+                    .filter_map(|callee| functions.get(callee).cloned())
+                    .collect();
+                let effects = functions.get_mut(id).expect("every id was analyzed");
+                for callee in &callees {
+                    changed |= effects.merge(callee);
+                }
+            }
+        }
+
+        Self { functions }
+    }
+
+    /// Effects of the function with this call graph unit id
+    pub fn effects(&self, id: &str) -> Option<&Effects> {
+        self.functions.get(id)
+    }
+
+    /// Every function with its effects, ordered by unit id
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Effects)> {
+        self.functions.iter().map(|(id, effects)| (id.as_str(), effects))
+    }
+
+    /// Unit ids of the pure functions
+    pub fn pure(&self) -> HashSet<String> {
+        self.functions.iter().filter(|(_, effects)| effects.is_pure()).map(|(id, _)| id.clone()).collect()
+    }
+
+    /// Effects of the functions code of `language` calls by name: its own and global ones
+    pub fn callable_from(&self, language: &str) -> HashMap<String, Effects> {
+        let mut callable = HashMap::new();
+        for owner in ["global", language] {
+            for (id, effects) in &self.functions {
+                if let Some((_, name)) = id.split_once("::").filter(|(function_owner, _)| *function_owner == owner) {
+                    callable.insert(name.to_string(), effects.clone());
+                }
+            }
+        }
+        callable
+    }
+}
+
+/// Effects of running `statements`, which call the functions in `functions` by name
+///
+/// Variables the statements declare themselves are not reported.
+pub(crate) fn statement_effects(statements: &[Statement], functions: &HashMap<String, Effects>) -> Effects {
+    let names: HashSet<&str> = functions.keys().map(String::as_str).collect();
+    let scope = Scope { variables: &HashSet::new(), functions: &names };
+    let mut effects = scope.effects(&[], statements);
+    for statement in statements {
+        statement.walk_expressions(&mut |expression| {
+            if let Expression::Call { callee, .. } = expression {
+                if let Expression::Identifier { name, .. } = callee.as_ref() {
+                    if let Some(callee) = functions.get(name) {
+                        effects.merge(callee);
+                    }
+                }
+            }
+        });
+    }
+    effects
+}
+
+/// Names visible to the functions of one block
+struct Scope<'a> {
+    variables: &'a HashSet<String>,
+    functions: &'a HashSet<&'a str>,
+}
+
+impl Scope<'_> {
+    /// Effects of a function body alone, without those of its callees
+    fn effects(&self, parameters: &[Parameter], body: &[Statement]) -> Effects {
+        let mut locals: HashSet<String> = parameters.iter().map(|parameter| parameter.name.clone()).collect();
+        declared_names(body, &mut locals);
+        let mut effects = Effects::default();
+
+        let mut assigned = HashSet::new();
+        collect_assigned(body, &mut assigned);
+        effects.writes = assigned.into_iter().filter(|name| !locals.contains(name)).collect();
+
+        // An identifier that is only ever assigned is not read
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        let mut stores: HashMap<&str, usize> = HashMap::new();
+        for statement in body {
+            statement.walk_expressions(&mut |expression| {
+                match expression {
+                    Expression::Identifier { name, .. } => *uses.entry(name).or_default() += 1,
+                    Expression::Assignment { target, .. } => store(target, &mut stores, &mut effects),
+                    _ => {}
+                }
+                self.expression(expression, &mut effects);
+            });
+        }
+        assignment_targets(body, &mut |target| store(target, &mut stores, &mut effects));
+        effects.reads = uses.into_iter()
+            .filter(|(name, count)| *count > stores.get(name).copied().unwrap_or(0))
+            .filter(|(name, _)| !locals.contains(*name) && self.variables.contains(*name))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        effects
+    }
+
+    fn expression(&self, expression: &Expression, effects: &mut Effects) {
+        match expression {
+            Expression::MemberAccess { .. } | Expression::ArrayAccess { .. } => effects.reads_memory = true,
+            Expression::Postfix { operand, .. } if !matches!(operand.as_ref(), Expression::Identifier { .. }) => {
+                effects.writes_memory = true;
+            }
+            Expression::Call { callee, .. } => match callee.as_ref() {
+                Expression::Identifier { name, .. } if self.functions.contains(name.as_str()) => {}
+                Expression::Identifier { name, .. } => match builtins::lookup(name) {
+                    Some(builtin) => {
+                        effects.io |= builtin.io;
+                        effects.writes_memory |= builtin.mutates;
+                    }
+                    None => effects.io = true,
+                },
+                Expression::MemberAccess { object, .. } if matches!(object.as_ref(), Expression::Identifier { name, .. } if name == "console") => {
+                    effects.io = true;
+                }
+                // A method may change the object it is called on
+                Expression::MemberAccess { .. } => effects.writes_memory = true,
+                _ => effects.io = true,
+            },
+            Expression::CrossCall { .. } => effects.cross_language = true,
+            _ => {}
+        }
+    }
+}
+
+/// Record an assignment: to a variable, which does not read it, or to a field or element
+fn store<'a>(target: &'a Expression, stores: &mut HashMap<&'a str, usize>, effects: &mut Effects) {
+    match target {
+        Expression::Identifier { name, .. } => *stores.entry(name).or_default() += 1,
+        _ => effects.writes_memory = true,
+    }
+}
+
+/// Targets of assignment statements, which `walk_expressions` only sees as plain expressions
+fn assignment_targets<'a>(statements: &'a [Statement], visit: &mut dyn FnMut(&'a Expression)) {
+    for statement in statements {
+        match statement {
+            Statement::Assignment { target, .. } => visit(target),
+            Statement::If { then_branch, else_branch, .. } => {
+                assignment_targets(then_branch, visit);
+                if let Some(else_branch) = else_branch {
+                    assignment_targets(else_branch, visit);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    assignment_targets(std::slice::from_ref(init.as_ref()), visit);
+                }
+                assignment_targets(body, visit);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => assignment_targets(body, visit),
+            _ => {}
+        }
+    }
+}
+
+/// Variables declared by the top-level statements of a block or of global code
+fn top_level_variables(statements: &[Statement]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    declared_names(statements, &mut names);
+    names
+}
+
+fn declared_functions(statements: &[Statement]) -> HashSet<&str> {
+    declarations(statements).map(|(name, _, _)| name).collect()
+}
+
+fn declarations(statements: &[Statement]) -> impl Iterator<Item = (&str, &[Parameter], &[Statement])> {
+    statements.iter().filter_map(|statement| match statement {
+        Statement::FunctionDeclaration { name, parameters, body, .. } => Some((name.as_str(), parameters.as_slice(), body.as_slice())),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(source: &str) -> EffectAnalysis {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+        EffectAnalysis::analyze(&program, &CallGraph::build(&program))
+    }
+
+    fn kinds(analysis: &EffectAnalysis, id: &str) -> Vec<EffectKind> {
+        analysis.effects(id).unwrap().kinds()
+    }
+
+    #[test]
+    fn test_classifies_functions_with_their_callees() {
+        let analysis = analyze(
+            "let total = 0;\nlet scale = 3;\nfunction square(x) {\n    let y = x * x;\n    return y;\n}\n\
+             function scaled(x) {\n    return square(x) * scale;\n}\nfunction add(x) {\n    total = total + x;\n}\n\
+             function log(x) {\n    print(x);\n}\nfunction report() {\n    log(total);\n}\n\
+             @lang python {\n    function helper(n) {\n        return len(str(n));\n    }\n}\n\
+             @lang javascript {\n    function bridge(n) {\n        return python::helper(n);\n    }\n}\n",
+        );
+        use EffectKind::*;
+        assert_eq!(kinds(&analysis, "global::square"), vec![Pure]);
+        assert_eq!(kinds(&analysis, "global::scaled"), vec![ReadsGlobal]);
+        assert_eq!(kinds(&analysis, "global::add"), vec![ReadsGlobal, WritesGlobal]);
+        assert_eq!(kinds(&analysis, "global::log"), vec![Io]);
+        assert_eq!(kinds(&analysis, "global::report"), vec![ReadsGlobal, Io]);
+        assert_eq!(kinds(&analysis, "python::helper"), vec![Pure]);
+        assert_eq!(kinds(&analysis, "javascript::bridge"), vec![CrossLanguage]);
+
+        let scaled = analysis.effects("global::scaled").unwrap();
+        assert!(scaled.is_read_only() && !scaled.is_pure());
+        assert_eq!(scaled.reads.iter().collect::<Vec<_>>(), vec!["scale"]);
+        let pure: BTreeSet<String> = analysis.pure().into_iter().collect();
+        assert_eq!(pure.into_iter().collect::<Vec<_>>(), vec!["global::square", "python::helper"]);
+    }
+
+    #[test]
+    fn test_assignments_and_memory() {
+        let analysis = analyze(
+            "let total = 0;\nfunction set(x) {\n    total = x;\n}\nfunction first(items) {\n    return items[0];\n}\n\
+             function clear(items) {\n    items[0] = 0;\n}\nfunction push(items, x) {\n    items.push(x);\n}\n",
+        );
+        let set = analysis.effects("global::set").unwrap();
+        assert!(set.reads.is_empty(), "assigning a variable does not read it");
+        assert_eq!(set.writes.iter().collect::<Vec<_>>(), vec!["total"]);
+
+        let first = analysis.effects("global::first").unwrap();
+        assert!(first.reads_memory && first.is_read_only() && !first.is_pure());
+        assert!(analysis.effects("global::clear").unwrap().writes_memory);
+        assert!(analysis.effects("global::push").unwrap().writes_memory);
+        assert_eq!(analysis.effects("global::push").unwrap().to_string(), "reads global, writes global");
+    }
+}
@@ -0,0 +1,436 @@
+//! Indentation-aware code writer shared by the transformers
+//!
+//! Backends write their output line by line into a `CodeWriter`. Indentation
+//! comes from how blocks are nested, never from the text itself: a block
+//! opened with `open` (or `block`) indents everything written until the
+//! matching `close`, including multi-line text produced elsewhere, so nested
+//! bodies line up at any depth in whitespace-sensitive targets such as Python,
+//! Nim and Haskell.
+//!
+//! Each target describes its block delimiters with a `BlockStyle` and its
+//! reserved words with `Keywords`. Calling `map` before writing a statement
+//! records its span for the source map (see `sourcemap`).
+
+use std::borrow::Cow;
+
+use crate::{sourcemap, Span};
+
+/// How a target delimits blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockStyle {
+    /// Appended to the header line that opens a block
+    pub open: &'static str,
+    /// Ends a block; nothing is written when empty
+    pub close: &'static str,
+    /// Whether `close` goes at the end of the block's last line, as in Lisp,
+    /// instead of on a line of its own
+    pub close_inline: bool,
+    /// Whether a continuation such as `else` shares a line with `close`,
+    /// as in `} else {`
+    pub joins_continuation: bool,
+    /// Whether a continuation is preceded by `close` on a line of its own,
+    /// as in Allman-style `}` / `else` / `{`
+    pub closes_before_continuation: bool,
+    /// Statement written into a block that would otherwise be empty
+    pub empty: &'static str,
+    /// Starts a comment that runs to the end of the line; an inline `close`
+    /// goes on a line of its own after such a comment
+    pub line_comment: &'static str,
+}
+
+impl BlockStyle {
+    /// `header {` ... `}`
+    pub const BRACES: BlockStyle = BlockStyle::new(" {", "}").joined();
+    /// `header`, then `{` on a line of its own ... `}`
+    pub const ALLMAN: BlockStyle = BlockStyle::new("\n{", "}").separate();
+    /// `header:` followed by an indented body, with `pass` for an empty one
+    pub const COLON: BlockStyle = BlockStyle::new(":", "").or_empty("pass");
+    /// Headers carry their own opening keyword and blocks end with `end`
+    pub const END: BlockStyle = BlockStyle::new("", "end");
+    /// Only indentation; headers and closing lines are written by the backend
+    pub const INDENT: BlockStyle = BlockStyle::new("", "");
+
+    pub const fn new(open: &'static str, close: &'static str) -> Self {
+        Self { open, close, close_inline: false, joins_continuation: false, closes_before_continuation: false, empty: "", line_comment: "" }
+    }
+
+    /// Put `close` at the end of the last line of the block
+    pub const fn inline_close(self) -> Self {
+        Self { close_inline: true, ..self }
+    }
+
+    /// Write continuations on the closing line
+    pub const fn joined(self) -> Self {
+        Self { joins_continuation: true, ..self }
+    }
+
+    /// Close the block before writing a continuation
+    pub const fn separate(self) -> Self {
+        Self { closes_before_continuation: true, ..self }
+    }
+
+    /// Line comments start with `prefix`
+    pub const fn line_comment(self, prefix: &'static str) -> Self {
+        Self { line_comment: prefix, ..self }
+    }
+
+    /// Fill empty blocks with `statement`
+    pub const fn or_empty(self, statement: &'static str) -> Self {
+        Self { empty: statement, ..self }
+    }
+}
+
+/// How a reserved word used as an identifier is made legal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// `class` becomes `class_`
+    Suffix(&'static str),
+    /// `class` becomes `@class`
+    Prefix(&'static str),
+    /// `type` becomes `` `type` `` or `[type]`
+    Wrap(&'static str, &'static str),
+}
+
+/// Reserved words of a target and how to escape them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keywords {
+    pub words: &'static [&'static str],
+    pub escape: Escape,
+    /// Whether the target reserves `End` and `END` as well as `end`; the
+    /// words are then written in lowercase
+    pub ignore_case: bool,
+}
+
+impl Keywords {
+    /// A target without reserved words
+    pub const NONE: Keywords = Keywords::new(&[], Escape::Suffix("_"));
+
+    pub const fn new(words: &'static [&'static str], escape: Escape) -> Self {
+        Self { words, escape, ignore_case: false }
+    }
+
+    /// Reserve the words in any letter case
+    pub const fn ignore_case(self) -> Self {
+        Self { ignore_case: true, ..self }
+    }
+
+    pub fn is_reserved(&self, name: &str) -> bool {
+        if self.ignore_case {
+            self.words.iter().any(|word| word.eq_ignore_ascii_case(name))
+        } else {
+            self.words.contains(&name)
+        }
+    }
+
+    /// `name`, escaped if it is reserved
+    pub fn escape<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        if !self.is_reserved(name) {
+            return Cow::Borrowed(name);
+        }
+        Cow::Owned(match self.escape {
+            Escape::Suffix(suffix) => format!("{}{}", name, suffix),
+            Escape::Prefix(prefix) => format!("{}{}", prefix, name),
+            Escape::Wrap(open, close) => format!("{}{}{}", open, name, close),
+        })
+    }
+}
+
+/// A block opened by `open`, waiting for its `close`
+#[derive(Debug)]
+struct Frame {
+    close: String,
+    lines_at_open: usize,
+}
+
+/// Line-oriented output buffer that tracks indentation
+#[derive(Debug)]
+pub struct CodeWriter {
+    output: String,
+    margin: &'static str,
+    indent: &'static str,
+    style: BlockStyle,
+    keywords: Keywords,
+    level: usize,
+    frames: Vec<Frame>,
+    lines: usize,
+    pending_span: Option<Span>,
+    pending_notes: String,
+}
+
+impl CodeWriter {
+    /// A writer indenting by `indent` per level and delimiting blocks with `style`
+    pub fn new(indent: &'static str, style: BlockStyle) -> Self {
+        Self {
+            output: String::new(),
+            margin: "",
+            indent,
+            style,
+            keywords: Keywords::NONE,
+            level: 0,
+            frames: Vec::new(),
+            lines: 0,
+            pending_span: None,
+            pending_notes: String::new(),
+        }
+    }
+
+    pub fn with_keywords(mut self, keywords: Keywords) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// Start every following line with `margin`, ahead of the indentation,
+    /// for fixed-format languages such as COBOL
+    pub fn set_margin(&mut self, margin: &'static str) {
+        self.margin = margin;
+    }
+
+    pub fn style(&self) -> BlockStyle {
+        self.style
+    }
+
+    pub fn keywords(&self) -> Keywords {
+        self.keywords
+    }
+
+    /// `name`, escaped if it is a reserved word of the target
+    pub fn escape<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        self.keywords.escape(name)
+    }
+
+    /// Attribute the next line written to `span` in the source map
+    pub fn map(&mut self, span: Span) {
+        self.pending_span = Some(span);
+    }
+
+    /// Carry `markers`, such as those of `unsupported_placement`, into the
+    /// output ahead of the next line written without writing a line of their own
+    pub fn note(&mut self, markers: impl AsRef<str>) {
+        self.pending_notes.push_str(markers.as_ref());
+    }
+
+    /// Write `text` at the current indentation; every line of multi-line
+    /// text is indented, keeping the relative indentation it already has
+    pub fn line(&mut self, text: impl AsRef<str>) {
+        let text = text.as_ref();
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        for line in text.split('\n') {
+            if !line.is_empty() {
+                self.output.push_str(self.margin);
+                for _ in 0..self.level {
+                    self.output.push_str(self.indent);
+                }
+                self.output.push_str(&std::mem::take(&mut self.pending_notes));
+                if let Some(span) = self.pending_span.take() {
+                    self.output.push_str(&sourcemap::marker(span));
+                }
+                self.output.push_str(line);
+            }
+            self.output.push('\n');
+            self.lines += 1;
+        }
+    }
+
+    /// Write each of `lines`
+    pub fn lines<I, S>(&mut self, lines: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for line in lines {
+            self.line(line);
+        }
+    }
+
+    /// An empty line, unless the output already ends with one
+    pub fn blank(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    pub fn indent(&mut self) {
+        self.level += 1;
+    }
+
+    pub fn dedent(&mut self) {
+        self.level = self.level.saturating_sub(1);
+    }
+
+    /// Run `body` one level deeper
+    pub fn indented<T>(&mut self, body: impl FnOnce(&mut Self) -> T) -> T {
+        self.indent();
+        let result = body(self);
+        self.dedent();
+        result
+    }
+
+    /// Write `header` with the style's opening delimiter and start a block
+    pub fn open(&mut self, header: impl AsRef<str>) {
+        let close = self.style.close.to_string();
+        self.open_with(header, close);
+    }
+
+    /// Start a block that ends with `close` instead of the style's delimiter
+    pub fn open_with(&mut self, header: impl AsRef<str>, close: impl Into<String>) {
+        self.line(format!("{}{}", header.as_ref(), self.style.open));
+        self.frames.push(Frame { close: close.into(), lines_at_open: self.lines });
+        self.indent();
+    }
+
+    /// Continue the current block with another branch, such as `else`
+    pub fn reopen(&mut self, header: impl AsRef<str>) {
+        self.fill_empty();
+        self.dedent();
+        let close = self.frames.last().map_or(String::new(), |frame| frame.close.clone());
+        if self.style.joins_continuation && !close.is_empty() {
+            self.line(format!("{} {}{}", close, header.as_ref(), self.style.open));
+        } else {
+            if self.style.closes_before_continuation && !close.is_empty() {
+                self.line(close);
+            }
+            self.line(format!("{}{}", header.as_ref(), self.style.open));
+        }
+        self.indent();
+        if let Some(frame) = self.frames.last_mut() {
+            frame.lines_at_open = self.lines;
+        }
+    }
+
+    /// End the innermost block
+    pub fn close(&mut self) {
+        self.fill_empty();
+        self.dedent();
+        let Some(frame) = self.frames.pop() else { return };
+        if frame.close.is_empty() {
+            return;
+        }
+        if self.style.close_inline && self.output.ends_with('\n') && !self.ends_in_comment() {
+            self.output.pop();
+            self.output.push_str(&frame.close);
+            self.output.push('\n');
+        } else {
+            self.line(frame.close);
+        }
+    }
+
+    /// `open`, `body`, `close`
+    pub fn block<T>(&mut self, header: impl AsRef<str>, body: impl FnOnce(&mut Self) -> T) -> T {
+        self.open(header);
+        let result = body(self);
+        self.close();
+        result
+    }
+
+    /// `open_with`, `body`, `close`
+    pub fn block_with<T>(&mut self, header: impl AsRef<str>, close: impl Into<String>, body: impl FnOnce(&mut Self) -> T) -> T {
+        self.open_with(header, close);
+        let result = body(self);
+        self.close();
+        result
+    }
+
+    /// A writer for a fragment that is placed into this one later, with the
+    /// same indentation, style and reserved words; the margin is added when
+    /// the fragment is written
+    pub fn nested(&self) -> Self {
+        Self::new(self.indent, self.style).with_keywords(self.keywords)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.output.is_empty()
+    }
+
+    /// The code written so far, ending with the notes no line followed
+    pub fn finish(mut self) -> String {
+        self.output.push_str(&self.pending_notes);
+        self.output
+    }
+
+    /// Whether the last line written is a line comment
+    fn ends_in_comment(&self) -> bool {
+        let prefix = self.style.line_comment;
+        let last = self.output[..self.output.len() - 1].rsplit('\n').next().unwrap_or("");
+        !prefix.is_empty() && sourcemap::strip_markers(last).trim_start().starts_with(prefix)
+    }
+
+    fn fill_empty(&mut self) {
+        let empty = self.style.empty;
+        if !empty.is_empty() && self.frames.last().is_some_and(|frame| frame.lines_at_open == self.lines) {
+            self.line(empty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_blocks_are_indented_by_depth() {
+        let mut out = CodeWriter::new("    ", BlockStyle::COLON);
+        out.block("def f(x)", |out| {
+            out.open("if x");
+            out.line("a = 1\nb = 2");
+            out.reopen("else");
+            out.close();
+            out.block("while x", |out| out.line("x = x - 1"));
+        });
+        assert_eq!(out.finish(), "def f(x):\n    if x:\n        a = 1\n        b = 2\n    else:\n        pass\n    while x:\n        x = x - 1\n");
+    }
+
+    #[test]
+    fn test_block_styles() {
+        let mut braces = CodeWriter::new("  ", BlockStyle::BRACES);
+        braces.block("if (x)", |out| {
+            out.line("y();");
+            out.reopen("else");
+            out.line("z();");
+        });
+        assert_eq!(braces.finish(), "if (x) {\n  y();\n} else {\n  z();\n}\n");
+
+        let mut allman = CodeWriter::new("    ", BlockStyle::ALLMAN);
+        allman.block("if (x)", |out| {
+            out.line("y();");
+            out.reopen("else");
+            out.line("z();");
+        });
+        assert_eq!(allman.finish(), "if (x)\n{\n    y();\n}\nelse\n{\n    z();\n}\n");
+
+        let mut vb = CodeWriter::new("    ", BlockStyle::INDENT);
+        vb.block_with("If x Then", "End If", |out| {
+            out.line("y()");
+            out.reopen("Else");
+            out.line("z()");
+        });
+        assert_eq!(vb.finish(), "If x Then\n    y()\nElse\n    z()\nEnd If\n");
+
+        let mut lisp = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close());
+        lisp.block("(defun f (x)", |out| out.block("(progn", |out| out.line("(print x)")));
+        assert_eq!(lisp.finish(), "(defun f (x)\n  (progn\n    (print x)))\n");
+
+        let mut commented = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close().line_comment(";"));
+        commented.block("(progn", |out| {
+            out.map(Span::new(0, 0, 1, 1));
+            out.line(";; todo");
+        });
+        assert_eq!(sourcemap::strip_markers(&commented.finish()), "(progn\n  ;; todo\n)\n");
+    }
+
+    #[test]
+    fn test_map_and_escape() {
+        let keywords = Keywords::new(&["class", "def"], Escape::Suffix("_"));
+        let mut out = CodeWriter::new("    ", BlockStyle::COLON).with_keywords(keywords);
+        out.block("if x", |out| {
+            out.map(Span::new(0, 0, 3, 5));
+            out.line(format!("{} = 1", out.escape("class")));
+        });
+        let (code, map) = sourcemap::SourceMap::extract(&out.finish(), sourcemap::SourceMapFormat::Sidecar);
+        assert_eq!(code, "if x:\n    class_ = 1\n");
+        assert_eq!(map.mappings[0].generated, sourcemap::Position { line: 2, column: 5 });
+        assert_eq!(Keywords::new(&["type"], Escape::Wrap("`", "`")).escape("type"), "`type`");
+        assert_eq!(Keywords::NONE.escape("class"), "class");
+        let case_insensitive = Keywords::new(&["end"], Escape::Wrap("[", "]")).ignore_case();
+        assert_eq!(case_insensitive.escape("End"), "[End]");
+    }
+}
@@ -0,0 +1,719 @@
+//! Function inlining
+//!
+//! Replaces calls to small, non-recursive functions with their bodies. A call
+//! that makes up a whole statement (`f(x);`, `let y = f(x);`, `y = f(x);` or
+//! `return f(x);`) is replaced by the callee's body with its parameters and
+//! locals renamed, and early returns rewritten into assignments to a result
+//! variable. Calls anywhere else are inlined when the callee is a
+//! read-only function (see `effects`) whose body is a single `return`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::*,
+    callgraph::{function_unit_id, CallGraph},
+    effects::{EffectAnalysis, Effects},
+    propagation::collect_assigned,
+    types::Type,
+    Span,
+};
+
+/// How large a function may be and still be inlined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlineBudget {
+    /// Largest callee body, in AST nodes
+    pub max_callee_size: usize,
+}
+
+impl InlineBudget {
+    /// -O2 inlines functions of up to 16 AST nodes and -O3 up to 40
+    pub fn for_level(level: u8) -> Self {
+        let max_callee_size = match level {
+            0 | 1 => 8,
+            2 => 16,
+            _ => 40,
+        };
+        Self { max_callee_size }
+    }
+}
+
+impl Default for InlineBudget {
+    fn default() -> Self {
+        Self::for_level(2)
+    }
+}
+
+/// Size of a statement list in AST nodes, used as the cost of inlining it
+pub fn body_size(statements: &[Statement]) -> usize {
+    let mut size = 0;
+    for statement in statements {
+        size += statement_count(statement);
+        statement.walk_expressions(&mut |_| size += 1);
+    }
+    size
+}
+
+fn statement_count(statement: &Statement) -> usize {
+    1 + match statement {
+        Statement::If { then_branch, else_branch, .. } => {
+            then_branch.iter().chain(else_branch.iter().flatten()).map(statement_count).sum()
+        }
+        Statement::For { init, body, .. } => {
+            init.iter().map(|init| statement_count(init)).sum::<usize>() + body.iter().map(statement_count).sum::<usize>()
+        }
+        Statement::While { body, .. } | Statement::Block { statements: body, .. } | Statement::FunctionDeclaration { body, .. } => {
+            body.iter().map(statement_count).sum()
+        }
+        Statement::ClassDeclaration { methods, .. } => {
+            methods.iter().flat_map(|method| &method.body).map(statement_count).sum()
+        }
+        _ => 0,
+    }
+}
+
+/// A function definition as seen by the inliner and purity analysis
+struct Definition<'a> {
+    language: &'a str,
+    name: &'a str,
+    parameters: &'a [Parameter],
+    body: &'a [Statement],
+}
+
+/// Every function in the program: block functions and `function` statements in blocks and global code
+fn definitions(program: &Program) -> Vec<Definition<'_>> {
+    let mut definitions = Vec::new();
+    for block in &program.language_blocks {
+        for function in &block.functions {
+            definitions.push(Definition {
+                language: &block.language,
+                name: &function.name,
+                parameters: &function.parameters,
+                body: &function.body,
+            });
+        }
+        definitions.extend(declared_definitions(&block.language, &block.statements));
+    }
+    definitions.extend(declared_definitions("global", &program.global_statements));
+    definitions
+}
+
+fn declared_definitions<'a>(language: &'a str, statements: &'a [Statement]) -> impl Iterator<Item = Definition<'a>> {
+    statements.iter().filter_map(move |statement| match statement {
+        Statement::FunctionDeclaration { name, parameters, body, .. } => Some(Definition { language, name, parameters, body }),
+        _ => None,
+    })
+}
+
+fn statements_write_only_locals(statements: &[Statement], is_local: &dyn Fn(&Expression) -> bool) -> bool {
+    statements.iter().all(|statement| match statement {
+        Statement::Assignment { target, .. } => is_local(target),
+        Statement::If { then_branch, else_branch, .. } => {
+            statements_write_only_locals(then_branch, is_local)
+                && else_branch.as_deref().is_none_or(|branch| statements_write_only_locals(branch, is_local))
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+            statements_write_only_locals(body, is_local)
+        }
+        Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } => false,
+        _ => true,
+    })
+}
+
+/// Names of the variables declared anywhere in `statements`, including nested blocks
+pub(crate) fn declared_names(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, .. } => {
+                names.insert(name.clone());
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                declared_names(then_branch, names);
+                if let Some(else_branch) = else_branch {
+                    declared_names(else_branch, names);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    declared_names(std::slice::from_ref(init.as_ref()), names);
+                }
+                declared_names(body, names);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => declared_names(body, names),
+            _ => {}
+        }
+    }
+}
+
+fn contains_return(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Return { .. } => true,
+        Statement::If { then_branch, else_branch, .. } => {
+            contains_return(then_branch) || else_branch.as_deref().is_some_and(contains_return)
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+            contains_return(body)
+        }
+        _ => false,
+    })
+}
+
+/// Rewrite `return` statements into assignments to `result`
+///
+/// Statements following an `if` that may return are moved into both of its
+/// branches, so that every return ends the path it is on. Returns inside
+/// loops or blocks cannot be expressed this way and make this fail.
+fn lower_returns(statements: Vec<Statement>, result: Option<&str>) -> Option<Vec<Statement>> {
+    let mut lowered = Vec::new();
+    let mut statements = statements.into_iter();
+    while let Some(statement) = statements.next() {
+        match statement {
+            Statement::Return { value, span } => {
+                match (value, result) {
+                    (Some(value), Some(result)) => lowered.push(Statement::Assignment {
+                        target: Expression::Identifier { name: result.to_string(), resolved_type: None, span },
+                        value,
+                        span,
+                    }),
+                    (Some(value), None) if !is_trivial(&value) => {
+                        lowered.push(Statement::Expression { expression: value, span });
+                    }
+                    _ => {}
+                }
+                return Some(lowered);
+            }
+            Statement::If { condition, then_branch, else_branch, span }
+                if contains_return(&then_branch) || else_branch.as_deref().is_some_and(contains_return) =>
+            {
+                let rest: Vec<Statement> = statements.collect();
+                let mut then_branch = then_branch;
+                then_branch.extend(rest.iter().cloned());
+                let mut else_branch = else_branch.unwrap_or_default();
+                else_branch.extend(rest);
+
+                let then_branch = lower_returns(then_branch, result)?;
+                let else_branch = lower_returns(else_branch, result)?;
+                lowered.push(Statement::If {
+                    condition,
+                    then_branch,
+                    else_branch: (!else_branch.is_empty()).then_some(else_branch),
+                    span,
+                });
+                return Some(lowered);
+            }
+            Statement::While { ref body, .. } | Statement::For { ref body, .. } | Statement::Block { statements: ref body, .. }
+                if contains_return(body) =>
+            {
+                return None;
+            }
+            statement => lowered.push(statement),
+        }
+    }
+    Some(lowered)
+}
+
+pub(crate) fn is_trivial(expression: &Expression) -> bool {
+    matches!(expression, Expression::Literal { .. } | Expression::Identifier { .. })
+}
+
+/// Number of assignments to `name` in `statements`
+fn assignments_to(statements: &[Statement], name: &str) -> usize {
+    let mut assigned = HashSet::new();
+    collect_assigned(statements, &mut assigned);
+    if !assigned.contains(name) {
+        return 0;
+    }
+    statements.iter().map(|statement| match statement {
+        Statement::Assignment { target: Expression::Identifier { name: target, .. }, .. } if target == name => 1,
+        Statement::If { then_branch, else_branch, .. } => {
+            assignments_to(then_branch, name) + else_branch.as_deref().map_or(0, |branch| assignments_to(branch, name))
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+            assignments_to(body, name)
+        }
+        _ => 0,
+    }).sum()
+}
+
+/// A function that may be inlined
+#[derive(Clone)]
+struct Candidate {
+    parameters: Vec<String>,
+    body: Vec<Statement>,
+    /// Calls change nothing, so arguments may be substituted and calls inlined into any expression
+    read_only: bool,
+    /// Names the body uses without declaring them, which must not be shadowed at the call site
+    free_names: HashSet<String>,
+    /// Parameters and locals, renamed at every call site
+    locals: HashSet<String>,
+    /// Parameters the body assigns to, which always need their own variable
+    assigned: HashSet<String>,
+    /// The returned expression, when the body is a single `return`
+    expression: Option<Expression>,
+}
+
+/// What a statement does with the result of the call it contains
+enum CallSite {
+    Discard,
+    Declare { name: String, var_type: Option<Type>, is_const: bool, span: Span },
+    Assign { target: Expression, span: Span },
+    Return { span: Span },
+}
+
+impl CallSite {
+    fn finish(self, value: Expression) -> Option<Statement> {
+        match self {
+            CallSite::Discard => None,
+            CallSite::Declare { name, var_type, is_const, span } => {
+                Some(Statement::VariableDeclaration { name, value: Some(value), var_type, is_const, span })
+            }
+            CallSite::Assign { target, span } => Some(Statement::Assignment { target, value, span }),
+            CallSite::Return { span } => Some(Statement::Return { value: Some(value), span }),
+        }
+    }
+}
+
+/// Inlines calls within each language block and within global code
+pub struct Inliner {
+    budget: InlineBudget,
+    next_id: usize,
+    /// Number of call sites inlined
+    pub inlined: usize,
+}
+
+impl Inliner {
+    pub fn new(budget: InlineBudget) -> Self {
+        Self { budget, next_id: 0, inlined: 0 }
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        let graph = CallGraph::build(program);
+        let effects = EffectAnalysis::analyze(program, &graph);
+
+        let global_definitions: Vec<Definition<'_>> = declared_definitions("global", &program.global_statements).collect();
+        let globals = self.candidates(&global_definitions, &graph, &effects);
+        let mut block_candidates = Vec::new();
+        for block in &program.language_blocks {
+            let mut candidates = globals.clone();
+            let definitions: Vec<Definition<'_>> = definitions(program).into_iter()
+                .filter(|definition| definition.language == block.language)
+                .collect();
+            candidates.extend(self.candidates(&definitions, &graph, &effects));
+            block_candidates.push(candidates);
+        }
+
+        for (block, candidates) in program.language_blocks.iter_mut().zip(&block_candidates) {
+            for function in &mut block.functions {
+                let mut declared: HashSet<String> = function.parameters.iter().map(|parameter| parameter.name.clone()).collect();
+                declared_names(&function.body, &mut declared);
+                self.inline_in(&mut function.body, candidates, &declared, Some(&function.name));
+            }
+            self.inline_in_unit(&mut block.statements, candidates);
+        }
+        self.inline_in_unit(&mut program.global_statements, &globals);
+    }
+
+    fn candidates(&self, definitions: &[Definition<'_>], graph: &CallGraph, effects: &EffectAnalysis) -> HashMap<String, Candidate> {
+        let mut candidates = HashMap::new();
+        for definition in definitions {
+            let id = function_unit_id(definition.language, definition.name);
+            let inlinable = !graph.is_recursive(&id)
+                && body_size(definition.body) <= self.budget.max_callee_size
+                && definition.parameters.iter().all(|parameter| parameter.default_value.is_none())
+                && statements_write_only_locals(definition.body, &|_| true)
+                && !has_lambda(definition.body)
+                && lower_returns(definition.body.to_vec(), Some("result")).is_some();
+            if !inlinable {
+                continue;
+            }
+
+            let parameters: Vec<String> = definition.parameters.iter().map(|parameter| parameter.name.clone()).collect();
+            let mut locals: HashSet<String> = parameters.iter().cloned().collect();
+            declared_names(definition.body, &mut locals);
+
+            let mut free_names = HashSet::new();
+            for statement in definition.body {
+                statement.walk_expressions(&mut |expression| {
+                    if let Expression::Identifier { name, .. } = expression {
+                        if !locals.contains(name) {
+                            free_names.insert(name.clone());
+                        }
+                    }
+                });
+            }
+
+            let mut assigned = HashSet::new();
+            collect_assigned(definition.body, &mut assigned);
+            assigned.retain(|name| parameters.contains(name));
+
+            let expression = match definition.body {
+                [Statement::Return { value: Some(value), .. }] => Some(value.clone()),
+                _ => None,
+            };
+
+            candidates.insert(definition.name.to_string(), Candidate {
+                parameters,
+                body: definition.body.to_vec(),
+                read_only: effects.effects(&id).is_some_and(Effects::is_read_only),
+                free_names,
+                locals,
+                assigned,
+                expression,
+            });
+        }
+        candidates
+    }
+
+    /// Inline into the `function` statements of a statement list and into the list itself
+    fn inline_in_unit(&mut self, statements: &mut Vec<Statement>, candidates: &HashMap<String, Candidate>) {
+        for statement in statements.iter_mut() {
+            if let Statement::FunctionDeclaration { name, parameters, body, .. } = statement {
+                let mut declared: HashSet<String> = parameters.iter().map(|parameter| parameter.name.clone()).collect();
+                declared_names(body, &mut declared);
+                self.inline_in(body, candidates, &declared, Some(name));
+            }
+        }
+
+        // Top-level variables are the ones the functions see, so only nested declarations can shadow them
+        let mut declared = HashSet::new();
+        for statement in statements.iter() {
+            if !matches!(statement, Statement::VariableDeclaration { .. }) {
+                declared_names(std::slice::from_ref(statement), &mut declared);
+            }
+        }
+        self.inline_in(statements, candidates, &declared, None);
+    }
+
+    fn inline_in(
+        &mut self,
+        statements: &mut Vec<Statement>,
+        candidates: &HashMap<String, Candidate>,
+        declared: &HashSet<String>,
+        caller: Option<&str>,
+    ) {
+        let mut i = 0;
+        while i < statements.len() {
+            match &mut statements[i] {
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.inline_in(then_branch, candidates, declared, caller);
+                    if let Some(else_branch) = else_branch {
+                        self.inline_in(else_branch, candidates, declared, caller);
+                    }
+                }
+                Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+                    self.inline_in(body, candidates, declared, caller);
+                }
+                _ => {}
+            }
+
+            if let Some(replacement) = self.inline_statement(&statements[i], candidates, declared, caller) {
+                let count = replacement.len();
+                statements.splice(i..=i, replacement);
+                i += count;
+                continue;
+            }
+            self.inline_expressions(&mut statements[i], candidates, declared, caller);
+            i += 1;
+        }
+    }
+
+    /// Replace a statement consisting of a call with the callee's body
+    fn inline_statement(
+        &mut self,
+        statement: &Statement,
+        candidates: &HashMap<String, Candidate>,
+        declared: &HashSet<String>,
+        caller: Option<&str>,
+    ) -> Option<Vec<Statement>> {
+        let (call, site) = match statement {
+            Statement::Expression { expression, .. } => (expression, CallSite::Discard),
+            Statement::VariableDeclaration { name, value: Some(value), var_type, is_const, span } => (value, CallSite::Declare {
+                name: name.clone(),
+                var_type: var_type.clone(),
+                is_const: *is_const,
+                span: *span,
+            }),
+            Statement::Assignment { target: target @ Expression::Identifier { .. }, value, span } => {
+                (value, CallSite::Assign { target: target.clone(), span: *span })
+            }
+            Statement::Return { value: Some(value), span } => (value, CallSite::Return { span: *span }),
+            _ => return None,
+        };
+        let Expression::Call { callee, arguments, .. } = call else {
+            return None;
+        };
+        let Expression::Identifier { name: callee, .. } = callee.as_ref() else {
+            return None;
+        };
+        let candidate = candidates.get(callee)?;
+        if caller == Some(callee.as_str())
+            || arguments.len() != candidate.parameters.len()
+            || !candidate.free_names.is_disjoint(declared)
+        {
+            return None;
+        }
+
+        let prefix = format!("__inl{}_", self.next_id);
+        self.next_id += 1;
+
+        // Bind the arguments, substituting the ones that are safe to repeat
+        let mut prologue = Vec::new();
+        let mut replacements: HashMap<String, Expression> = HashMap::new();
+        for (parameter, argument) in candidate.parameters.iter().zip(arguments) {
+            let substitute = !candidate.assigned.contains(parameter)
+                && match argument {
+                    Expression::Literal { .. } => true,
+                    Expression::Identifier { .. } => candidate.read_only,
+                    _ => false,
+                };
+            if substitute {
+                replacements.insert(parameter.clone(), argument.clone());
+            } else {
+                let name = format!("{prefix}{parameter}");
+                let span = argument.span();
+                replacements.insert(parameter.clone(), Expression::Identifier { name: name.clone(), resolved_type: None, span });
+                prologue.push(Statement::VariableDeclaration {
+                    name,
+                    value: Some(argument.clone()),
+                    var_type: None,
+                    is_const: false,
+                    span,
+                });
+            }
+        }
+        for local in candidate.locals.iter().filter(|local| !candidate.parameters.contains(local)) {
+            replacements.insert(local.clone(), Expression::Identifier { name: format!("{prefix}{local}"), resolved_type: None, span: call.span() });
+        }
+
+        let mut body = candidate.body.clone();
+        rename(&mut body, &replacements, &prefix);
+
+        let result = format!("{prefix}result");
+        let wants_result = !matches!(site, CallSite::Discard);
+        let mut body = lower_returns(body, wants_result.then_some(result.as_str()))?;
+
+        if wants_result {
+            let single_final_assignment = assignments_to(&body, &result) == 1
+                && matches!(body.last(), Some(Statement::Assignment { target: Expression::Identifier { name, .. }, .. }) if *name == result);
+            let value = if single_final_assignment {
+                let Some(Statement::Assignment { value, .. }) = body.pop() else {
+                    unreachable!("checked above");
+                };
+                value
+            } else {
+                let span = call.span();
+                prologue.push(Statement::VariableDeclaration {
+                    name: result.clone(),
+                    value: None,
+                    var_type: None,
+                    is_const: false,
+                    span,
+                });
+                Expression::Identifier { name: result, resolved_type: None, span }
+            };
+            body.extend(site.finish(value));
+        }
+
+        self.inlined += 1;
+        log::debug!("Inlined call to {} at line {}", callee, statement.span().line);
+        prologue.extend(body);
+        Some(prologue)
+    }
+
+    /// Inline read-only single-`return` functions called anywhere in the statement's own expressions
+    fn inline_expressions(
+        &mut self,
+        statement: &mut Statement,
+        candidates: &HashMap<String, Candidate>,
+        declared: &HashSet<String>,
+        caller: Option<&str>,
+    ) {
+        let mut inlined = 0;
+        let mut rewrite = |expression: &mut Expression| {
+            let Expression::Call { callee, arguments, .. } = expression else {
+                return;
+            };
+            let Expression::Identifier { name, .. } = callee.as_ref() else {
+                return;
+            };
+            let Some(candidate) = candidates.get(name) else {
+                return;
+            };
+            let Some(body) = &candidate.expression else {
+                return;
+            };
+            if !candidate.read_only
+                || caller == Some(name.as_str())
+                || arguments.len() != candidate.parameters.len()
+                || !arguments.iter().all(is_trivial)
+                || !candidate.free_names.is_disjoint(declared)
+            {
+                return;
+            }
+
+            let replacements: HashMap<String, Expression> = candidate.parameters.iter().cloned().zip(arguments.iter().cloned()).collect();
+            let mut body = body.clone();
+            body.walk_mut(&mut |expression| substitute(expression, &replacements));
+            *expression = body;
+            inlined += 1;
+        };
+
+        match statement {
+            Statement::Expression { expression: value, .. }
+            | Statement::VariableDeclaration { value: Some(value), .. }
+            | Statement::Return { value: Some(value), .. }
+            | Statement::If { condition: value, .. }
+            | Statement::While { condition: value, .. } => value.walk_mut(&mut rewrite),
+            Statement::Assignment { target, value, .. } => {
+                target.walk_mut(&mut rewrite);
+                value.walk_mut(&mut rewrite);
+            }
+            Statement::For { init, condition, update, .. } => {
+                if let Some(init) = init {
+                    init.walk_expressions_mut(&mut rewrite);
+                }
+                for expression in condition.iter_mut().chain(update.iter_mut()) {
+                    expression.walk_mut(&mut rewrite);
+                }
+            }
+            _ => {}
+        }
+        self.inlined += inlined;
+    }
+}
+
+pub(crate) fn has_lambda(statements: &[Statement]) -> bool {
+    let mut found = false;
+    for statement in statements {
+        statement.walk_expressions(&mut |expression| found |= matches!(expression, Expression::Lambda { .. }));
+    }
+    found
+}
+
+/// Replace a parameter or local by its argument or new name, keeping the span of a renamed use
+fn substitute(expression: &mut Expression, replacements: &HashMap<String, Expression>) {
+    let Expression::Identifier { name, span, .. } = expression else {
+        return;
+    };
+    let Some(replacement) = replacements.get(name) else {
+        return;
+    };
+    let span = *span;
+    *expression = replacement.clone();
+    if let Expression::Identifier { span: renamed, .. } = expression {
+        *renamed = span;
+    }
+}
+
+/// Apply `replacements` to every use of a parameter or local and prefix every declaration
+pub(crate) fn rename(statements: &mut [Statement], replacements: &HashMap<String, Expression>, prefix: &str) {
+    for statement in statements.iter_mut() {
+        statement.walk_expressions_mut(&mut |expression| substitute(expression, replacements));
+    }
+    rename_declarations(statements, prefix);
+}
+
+fn rename_declarations(statements: &mut [Statement], prefix: &str) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, .. } => *name = format!("{prefix}{name}"),
+            Statement::If { then_branch, else_branch, .. } => {
+                rename_declarations(then_branch, prefix);
+                if let Some(else_branch) = else_branch {
+                    rename_declarations(else_branch, prefix);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    rename_declarations(std::slice::from_mut(init.as_mut()), prefix);
+                }
+                rename_declarations(body, prefix);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => rename_declarations(body, prefix),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::PrettyPrinter, optimizer::Optimizer};
+
+    const SOURCE: &str = r#"
+@lang python {
+    function square(x) {
+        return x * x;
+    }
+    function clamp(v) {
+        if (v > 10) {
+            return 10;
+        }
+        return v;
+    }
+    function scale(x) {
+        let t = x + 1;
+        return t * 2;
+    }
+    function fact(n) {
+        if (n <= 1) {
+            return 1;
+        }
+        return n * fact(n - 1);
+    }
+    let t = input();
+    print(square(t) + 1);
+    let b = clamp(t + 20);
+    let c = scale(t);
+    print(b, c, fact(5));
+}
+"#;
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    fn inline(source: &str, budget: InlineBudget) -> (String, usize) {
+        let mut program = parse(source);
+        let mut inliner = Inliner::new(budget);
+        inliner.run(&mut program);
+        (PrettyPrinter::new().print(&program), inliner.inlined)
+    }
+
+    #[test]
+    fn test_inlines_with_renaming_and_early_returns() {
+        let (output, inlined) = inline(SOURCE, InlineBudget::for_level(2));
+
+        assert_eq!(inlined, 3, "{}", output);
+        assert!(output.contains("print(t * t + 1);"), "{}", output);
+        assert!(output.contains("let __inl0_v = t + 20;"), "{}", output);
+        assert!(output.contains("__inl0_result = 10;"), "{}", output);
+        assert!(output.contains("__inl0_result = __inl0_v;"), "{}", output);
+        assert!(output.contains("let b = __inl0_result;"), "{}", output);
+        // The callee's `t` must not capture the caller's `t`
+        assert!(output.contains("let __inl1_t = t + 1;"), "{}", output);
+        assert!(output.contains("let c = __inl1_t * 2;"), "{}", output);
+        assert!(output.contains("fact(5)"), "{}", output);
+    }
+
+    #[test]
+    fn test_budget_follows_optimization_level() {
+        let source = "function mix(a, b) {\n    let s = a + b;\n    let d = a - b;\n    let p = s * d;\n    return p + s + d;\n}\nlet m = mix(input(), 2);\nprint(m);\n";
+        let (_, inlined) = inline(source, InlineBudget::for_level(2));
+        assert_eq!(inlined, 0);
+        let (output, inlined) = inline(source, InlineBudget::for_level(3));
+        assert_eq!(inlined, 1);
+        assert!(output.contains("let __inl0_a = input();"), "{}", output);
+        assert!(output.contains("let __inl0_s = __inl0_a + 2;"), "{}", output);
+    }
+
+    #[test]
+    fn test_optimizer_counts_inlined_calls() {
+        let program = parse("function twice(n) {\n    return n * 2;\n}\nprint(twice(3), twice(4));\n");
+        let mut optimizer = Optimizer::for_level(2);
+        let program = optimizer.optimize(program).unwrap();
+
+        assert_eq!(optimizer.get_stats().function_inlines, 2);
+        assert!(PrettyPrinter::new().print(&program).contains("print(6, 8);"));
+    }
+}
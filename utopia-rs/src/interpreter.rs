@@ -0,0 +1,690 @@
+//! Reference interpreter for Utopia programs
+//!
+//! Executes the AST directly, independent of any backend, and captures what
+//! the program prints. Optimizer transformations are checked by running a
+//! program before and after them and comparing the output.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{ast::*, builtins, Result, Span};
+
+/// Statements executed before the interpreter gives up on a program
+const DEFAULT_STEP_LIMIT: usize = 1_000_000;
+
+/// Nested calls allowed before the interpreter reports runaway recursion,
+/// well before the recursion of the tree walk itself would overflow the stack
+const DEFAULT_DEPTH_LIMIT: usize = 200;
+
+/// A runtime value
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Array(Rc<RefCell<Vec<Value>>>),
+    Object(Rc<RefCell<BTreeMap<String, Value>>>),
+    Function(Rc<Closure>),
+}
+
+impl Value {
+    fn is_truthy(&self) -> bool {
+        match self {
+            Value::Boolean(value) => *value,
+            Value::Null => false,
+            Value::Number(value) => *value != 0.0 && !value.is_nan(),
+            Value::String(value) => !value.is_empty(),
+            Value::Array(_) | Value::Object(_) | Value::Function(_) => true,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Null => "null",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Function(_) => "function",
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => write!(f, "{}", *value as i64),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::Null => write!(f, "null"),
+            Value::Array(elements) => {
+                let elements: Vec<String> = elements.borrow().iter().map(ToString::to_string).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Value::Object(properties) => {
+                let properties: Vec<String> = properties.borrow().iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+                write!(f, "{{{}}}", properties.join(", "))
+            }
+            Value::Function(_) => write!(f, "<function>"),
+        }
+    }
+}
+
+/// A function value together with the scope it was defined in
+#[derive(Debug)]
+pub struct Closure {
+    parameters: Vec<Parameter>,
+    body: Vec<Statement>,
+    scope: Scope,
+}
+
+/// A chain of variable scopes, innermost first
+#[derive(Debug, Clone)]
+struct Scope(Rc<Frame>);
+
+#[derive(Debug)]
+struct Frame {
+    variables: RefCell<HashMap<String, Value>>,
+    parent: Option<Scope>,
+}
+
+impl Scope {
+    fn root() -> Self {
+        Scope(Rc::new(Frame { variables: RefCell::new(HashMap::new()), parent: None }))
+    }
+
+    fn child(&self) -> Self {
+        Scope(Rc::new(Frame { variables: RefCell::new(HashMap::new()), parent: Some(self.clone()) }))
+    }
+
+    fn define(&self, name: &str, value: Value) {
+        self.0.variables.borrow_mut().insert(name.to_string(), value);
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        let mut scope = Some(self);
+        while let Some(Scope(frame)) = scope {
+            if let Some(value) = frame.variables.borrow().get(name) {
+                return Some(value.clone());
+            }
+            scope = frame.parent.as_ref();
+        }
+        None
+    }
+
+    fn assign(&self, name: &str, value: Value) -> bool {
+        let mut scope = Some(self);
+        while let Some(Scope(frame)) = scope {
+            if let Some(slot) = frame.variables.borrow_mut().get_mut(name) {
+                *slot = value;
+                return true;
+            }
+            scope = frame.parent.as_ref();
+        }
+        false
+    }
+}
+
+/// How a statement finished
+enum Flow {
+    Normal,
+    Return(Value),
+}
+
+/// Tree-walking interpreter that records printed lines
+pub struct Interpreter {
+    output: String,
+    steps: usize,
+    step_limit: usize,
+    depth: usize,
+    depth_limit: usize,
+    /// Top-level scope of every language block, for `language::function` calls
+    languages: HashMap<String, Scope>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self {
+            output: String::new(),
+            steps: 0,
+            step_limit: DEFAULT_STEP_LIMIT,
+            depth: 0,
+            depth_limit: DEFAULT_DEPTH_LIMIT,
+            languages: HashMap::new(),
+        }
+    }
+
+    pub fn with_step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    pub fn with_depth_limit(mut self, depth_limit: usize) -> Self {
+        self.depth_limit = depth_limit;
+        self
+    }
+
+    /// Run a program and return everything it printed
+    ///
+    /// Global statements run first, then the statements of every language
+    /// block in order. All functions are defined before anything runs.
+    pub fn run(&mut self, program: &Program) -> Result<String> {
+        let global = Scope::root();
+        self.hoist(&program.global_statements, &global);
+
+        let mut block_scopes = Vec::new();
+        for block in &program.language_blocks {
+            let scope = self.languages.entry(block.language.clone()).or_insert_with(|| global.child()).clone();
+            for function in &block.functions {
+                define_function(&scope, &function.name, &function.parameters, &function.body);
+            }
+            self.hoist(&block.statements, &scope);
+            block_scopes.push(scope);
+        }
+
+        self.execute_all(&program.global_statements, &global)?;
+        for (block, scope) in program.language_blocks.iter().zip(block_scopes) {
+            self.execute_all(&block.statements, &scope)?;
+        }
+        Ok(std::mem::take(&mut self.output))
+    }
+
+    fn hoist(&self, statements: &[Statement], scope: &Scope) {
+        for statement in statements {
+            if let Statement::FunctionDeclaration { name, parameters, body, .. } = statement {
+                define_function(scope, name, parameters, body);
+            }
+        }
+    }
+
+    fn execute_all(&mut self, statements: &[Statement], scope: &Scope) -> Result<Flow> {
+        for statement in statements {
+            if let Flow::Return(value) = self.execute(statement, scope)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn execute_block(&mut self, statements: &[Statement], scope: &Scope) -> Result<Flow> {
+        let scope = scope.child();
+        self.hoist(statements, &scope);
+        self.execute_all(statements, &scope)
+    }
+
+    fn step(&mut self, span: Span) -> Result<()> {
+        self.steps += 1;
+        if self.steps > self.step_limit {
+            return Err(format!("{}:{}: step limit of {} exceeded", span.line, span.column, self.step_limit).into());
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, statement: &Statement, scope: &Scope) -> Result<Flow> {
+        self.step(statement.span())?;
+        match statement {
+            Statement::Expression { expression, .. } => {
+                self.evaluate(expression, scope)?;
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate(value, scope)?,
+                    None => Value::Null,
+                };
+                scope.define(name, value);
+            }
+            Statement::Assignment { target, value, .. } => {
+                let value = self.evaluate(value, scope)?;
+                self.assign(target, value, scope)?;
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                if self.evaluate(condition, scope)?.is_truthy() {
+                    return self.execute_block(then_branch, scope);
+                } else if let Some(else_branch) = else_branch {
+                    return self.execute_block(else_branch, scope);
+                }
+            }
+            Statement::While { condition, body, span } => {
+                while self.evaluate(condition, scope)?.is_truthy() {
+                    if let Flow::Return(value) = self.execute_block(body, scope)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    self.step(*span)?;
+                }
+            }
+            Statement::For { init, condition, update, body, span } => {
+                let scope = scope.child();
+                if let Some(init) = init {
+                    self.execute(init, &scope)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if !self.evaluate(condition, &scope)?.is_truthy() {
+                            break;
+                        }
+                    }
+                    if let Flow::Return(value) = self.execute_block(body, &scope)? {
+                        return Ok(Flow::Return(value));
+                    }
+                    if let Some(update) = update {
+                        self.evaluate(update, &scope)?;
+                    }
+                    self.step(*span)?;
+                }
+            }
+            Statement::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate(value, scope)?,
+                    None => Value::Null,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Statement::Block { statements, .. } => return self.execute_block(statements, scope),
+            Statement::FunctionDeclaration { name, parameters, body, .. } => define_function(scope, name, parameters, body),
+            Statement::Import { .. } | Statement::Export { .. } => {}
+            Statement::ClassDeclaration { name, span, .. } => {
+                return Err(format!("{}:{}: class `{}` is not supported by the interpreter", span.line, span.column, name).into());
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn assign(&mut self, target: &Expression, value: Value, scope: &Scope) -> Result<()> {
+        match target {
+            Expression::Identifier { name, span, .. } => {
+                if !scope.assign(name, value) {
+                    return Err(format!("{}:{}: assignment to undeclared variable `{}`", span.line, span.column, name).into());
+                }
+            }
+            Expression::ArrayAccess { array, index, span } => {
+                let container = self.evaluate(array, scope)?;
+                let index = self.evaluate(index, scope)?;
+                match (&container, &index) {
+                    (Value::Array(elements), Value::Number(position)) => {
+                        let mut elements = elements.borrow_mut();
+                        let position = array_index(*position, elements.len() + 1, *span)?;
+                        if position == elements.len() {
+                            elements.push(value);
+                        } else {
+                            elements[position] = value;
+                        }
+                    }
+                    (Value::Object(properties), Value::String(key)) => {
+                        properties.borrow_mut().insert(key.clone(), value);
+                    }
+                    _ => return Err(type_error(*span, &format!("cannot index a {} with a {}", container.type_name(), index.type_name()))),
+                }
+            }
+            Expression::MemberAccess { object, property, span } => match self.evaluate(object, scope)? {
+                Value::Object(properties) => {
+                    properties.borrow_mut().insert(property.clone(), value);
+                }
+                other => return Err(type_error(*span, &format!("cannot set `{}` on a {}", property, other.type_name()))),
+            },
+            other => {
+                let span = other.span();
+                return Err(format!("{}:{}: invalid assignment target", span.line, span.column).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate(&mut self, expression: &Expression, scope: &Scope) -> Result<Value> {
+        match expression {
+            Expression::Literal { value, .. } => Ok(match value {
+                LiteralValue::Number(value) => Value::Number(*value),
+                LiteralValue::String(value) => Value::String(value.clone()),
+                LiteralValue::Boolean(value) => Value::Boolean(*value),
+                LiteralValue::Null => Value::Null,
+            }),
+            Expression::Identifier { name, span, .. } => scope.lookup(name)
+                .ok_or_else(|| format!("{}:{}: undefined variable `{}`", span.line, span.column, name).into()),
+            Expression::Binary { left, operator: BinaryOperator::And, right, .. } => {
+                let left = self.evaluate(left, scope)?;
+                if left.is_truthy() { self.evaluate(right, scope) } else { Ok(left) }
+            }
+            Expression::Binary { left, operator: BinaryOperator::Or, right, .. } => {
+                let left = self.evaluate(left, scope)?;
+                if left.is_truthy() { Ok(left) } else { self.evaluate(right, scope) }
+            }
+            Expression::Binary { left, operator, right, span } => {
+                let left = self.evaluate(left, scope)?;
+                let right = self.evaluate(right, scope)?;
+                binary(operator, left, right, *span)
+            }
+            Expression::Unary { operator, operand, span } => {
+                let operand = self.evaluate(operand, scope)?;
+                match (operator, operand) {
+                    (UnaryOperator::Not, operand) => Ok(Value::Boolean(!operand.is_truthy())),
+                    (UnaryOperator::Minus, Value::Number(value)) => Ok(Value::Number(-value)),
+                    (UnaryOperator::Plus, Value::Number(value)) => Ok(Value::Number(value)),
+                    (_, operand) => Err(type_error(*span, &format!("cannot negate a {}", operand.type_name()))),
+                }
+            }
+            Expression::Postfix { operand, operator, span } => {
+                let Value::Number(old) = self.evaluate(operand, scope)? else {
+                    return Err(type_error(*span, "can only increment or decrement a number"));
+                };
+                let new = match operator {
+                    PostfixOperator::Increment => old + 1.0,
+                    PostfixOperator::Decrement => old - 1.0,
+                };
+                self.assign(operand, Value::Number(new), scope)?;
+                Ok(Value::Number(old))
+            }
+            Expression::Assignment { target, value, .. } => {
+                let value = self.evaluate(value, scope)?;
+                self.assign(target, value.clone(), scope)?;
+                Ok(value)
+            }
+            Expression::Call { callee, arguments, span } => {
+                if let Some(name) = builtin_name(callee) {
+                    if scope.lookup(&name).is_none() {
+                        let arguments = self.evaluate_all(arguments, scope)?;
+                        return self.call_builtin(&name, arguments, *span);
+                    }
+                }
+                let function = self.evaluate(callee, scope)?;
+                let arguments = self.evaluate_all(arguments, scope)?;
+                self.call(function, arguments, *span)
+            }
+            Expression::CrossCall { language, function, arguments, span } => {
+                let callee = self.languages.get(language).and_then(|scope| scope.lookup(function))
+                    .ok_or_else(|| format!("{}:{}: undefined function `{}::{}`", span.line, span.column, language, function))?;
+                let arguments = self.evaluate_all(arguments, scope)?;
+                self.call(callee, arguments, *span)
+            }
+            Expression::MemberAccess { object, property, span } => {
+                let object = self.evaluate(object, scope)?;
+                match (&object, property.as_str()) {
+                    (Value::Object(properties), _) => Ok(properties.borrow().get(property).cloned().unwrap_or(Value::Null)),
+                    (Value::Array(elements), "length") => Ok(Value::Number(elements.borrow().len() as f64)),
+                    (Value::String(value), "length") => Ok(Value::Number(value.chars().count() as f64)),
+                    _ => Err(type_error(*span, &format!("a {} has no property `{}`", object.type_name(), property))),
+                }
+            }
+            Expression::ArrayAccess { array, index, span } => {
+                let container = self.evaluate(array, scope)?;
+                let index = self.evaluate(index, scope)?;
+                match (&container, &index) {
+                    (Value::Array(elements), Value::Number(position)) => {
+                        let elements = elements.borrow();
+                        let position = array_index(*position, elements.len(), *span)?;
+                        Ok(elements[position].clone())
+                    }
+                    (Value::String(value), Value::Number(position)) => {
+                        let characters: Vec<char> = value.chars().collect();
+                        let position = array_index(*position, characters.len(), *span)?;
+                        Ok(Value::String(characters[position].to_string()))
+                    }
+                    (Value::Object(properties), Value::String(key)) => Ok(properties.borrow().get(key).cloned().unwrap_or(Value::Null)),
+                    _ => Err(type_error(*span, &format!("cannot index a {} with a {}", container.type_name(), index.type_name()))),
+                }
+            }
+            Expression::Array { elements, .. } => {
+                let elements = self.evaluate_all(elements, scope)?;
+                Ok(Value::Array(Rc::new(RefCell::new(elements))))
+            }
+            Expression::Object { properties, .. } => {
+                let mut values = BTreeMap::new();
+                for (key, value) in properties {
+                    values.insert(key.clone(), self.evaluate(value, scope)?);
+                }
+                Ok(Value::Object(Rc::new(RefCell::new(values))))
+            }
+            Expression::Lambda { parameters, body, .. } => Ok(Value::Function(Rc::new(Closure {
+                parameters: parameters.clone(),
+                body: body.clone(),
+                scope: scope.clone(),
+            }))),
+        }
+    }
+
+    fn evaluate_all(&mut self, expressions: &[Expression], scope: &Scope) -> Result<Vec<Value>> {
+        expressions.iter().map(|expression| self.evaluate(expression, scope)).collect()
+    }
+
+    fn call(&mut self, function: Value, arguments: Vec<Value>, span: Span) -> Result<Value> {
+        let Value::Function(closure) = function else {
+            return Err(type_error(span, &format!("a {} is not callable", function.type_name())));
+        };
+        if self.depth == self.depth_limit {
+            return Err(format!("{}:{}: call depth limit of {} exceeded", span.line, span.column, self.depth_limit).into());
+        }
+        self.depth += 1;
+        let result = self.call_closure(&closure, arguments);
+        self.depth -= 1;
+        result
+    }
+
+    fn call_closure(&mut self, closure: &Closure, arguments: Vec<Value>) -> Result<Value> {
+        let scope = closure.scope.child();
+        let mut arguments = arguments.into_iter();
+        for parameter in &closure.parameters {
+            let value = match (arguments.next(), &parameter.default_value) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.evaluate(default, &scope)?,
+                (None, None) => Value::Null,
+            };
+            scope.define(&parameter.name, value);
+        }
+        self.hoist(&closure.body, &scope);
+        match self.execute_all(&closure.body, &scope)? {
+            Flow::Return(value) => Ok(value),
+            Flow::Normal => Ok(Value::Null),
+        }
+    }
+
+    fn call_builtin(&mut self, name: &str, arguments: Vec<Value>, span: Span) -> Result<Value> {
+        let Some(builtin) = builtins::lookup(if name == "console.log" { "println" } else { name }) else {
+            return Err(format!("{}:{}: undefined function `{}`", span.line, span.column, name).into());
+        };
+        if !builtin.accepts(arguments.len()) {
+            return Err(format!("{}:{}: `{}` expects {} arguments, found {}", span.line, span.column, name, builtin.parameters.len(), arguments.len()).into());
+        }
+        let expected = |kind: &str, found: &Value| type_error(span, &format!("{} expects {}, found {}", name, kind, found.type_name()));
+        let number = |index: usize| match &arguments[index] {
+            Value::Number(value) => Ok(*value),
+            other => Err(expected("a number", other)),
+        };
+        let string = |index: usize| match &arguments[index] {
+            Value::String(value) => Ok(value.as_str()),
+            other => Err(expected("a string", other)),
+        };
+        let array = |index: usize| match &arguments[index] {
+            Value::Array(elements) => Ok(elements),
+            other => Err(expected("an array", other)),
+        };
+        let object = |index: usize| match &arguments[index] {
+            Value::Object(properties) => Ok(properties),
+            other => Err(expected("an object", other)),
+        };
+
+        match builtin.name {
+            "println" => {
+                let line: Vec<String> = arguments.iter().map(ToString::to_string).collect();
+                self.output.push_str(&line.join(" "));
+                self.output.push('\n');
+                Ok(Value::Null)
+            }
+            "toString" => Ok(Value::String(arguments[0].to_string())),
+            "toNumber" => Ok(Value::Number(match &arguments[0] {
+                Value::Number(value) => *value,
+                Value::String(value) => value.trim().parse().unwrap_or(f64::NAN),
+                Value::Boolean(value) => f64::from(u8::from(*value)),
+                Value::Null => 0.0,
+                _ => f64::NAN,
+            })),
+            "toBoolean" => Ok(Value::Boolean(arguments[0].is_truthy())),
+            "len" => match &arguments[0] {
+                Value::Array(elements) => Ok(Value::Number(elements.borrow().len() as f64)),
+                Value::String(value) => Ok(Value::Number(value.chars().count() as f64)),
+                Value::Object(properties) => Ok(Value::Number(properties.borrow().len() as f64)),
+                _ => Err(type_error(span, "len expects an array, a string or an object")),
+            },
+            "push" => {
+                array(0)?.borrow_mut().push(arguments[1].clone());
+                Ok(Value::Null)
+            }
+            "pop" => Ok(array(0)?.borrow_mut().pop().unwrap_or(Value::Null)),
+            "upper" => Ok(Value::String(string(0)?.to_uppercase())),
+            "lower" => Ok(Value::String(string(0)?.to_lowercase())),
+            "trim" => Ok(Value::String(string(0)?.trim().to_string())),
+            "contains" => Ok(Value::Boolean(string(0)?.contains(string(1)?))),
+            "abs" => Ok(Value::Number(number(0)?.abs())),
+            "sqrt" => Ok(Value::Number(number(0)?.sqrt())),
+            "floor" => Ok(Value::Number(number(0)?.floor())),
+            "ceil" => Ok(Value::Number(number(0)?.ceil())),
+            "round" => Ok(Value::Number(number(0)?.round())),
+            "pow" => Ok(Value::Number(number(0)?.powf(number(1)?))),
+            "min" => Ok(Value::Number(number(0)?.min(number(1)?))),
+            "max" => Ok(Value::Number(number(0)?.max(number(1)?))),
+            "keys" => {
+                let keys = object(0)?.borrow().keys().map(|key| Value::String(key.clone())).collect();
+                Ok(Value::Array(Rc::new(RefCell::new(keys))))
+            }
+            "hasKey" => Ok(Value::Boolean(object(0)?.borrow().contains_key(string(1)?))),
+            _ => Err(format!("{}:{}: builtin `{}` is not implemented", span.line, span.column, name).into()),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn define_function(scope: &Scope, name: &str, parameters: &[Parameter], body: &[Statement]) {
+    let closure = Closure {
+        parameters: parameters.to_vec(),
+        body: body.to_vec(),
+        scope: scope.clone(),
+    };
+    scope.define(name, Value::Function(Rc::new(closure)));
+}
+
+/// Name of a builtin a call may refer to: one from the standard library or `console.log`
+fn builtin_name(callee: &Expression) -> Option<String> {
+    match callee {
+        Expression::Identifier { name, .. } if builtins::lookup(name).is_some() => Some(name.clone()),
+        Expression::MemberAccess { object, property, .. } if property == "log" => match object.as_ref() {
+            Expression::Identifier { name, .. } if name == "console" => Some("console.log".to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn binary(operator: &BinaryOperator, left: Value, right: Value, span: Span) -> Result<Value> {
+    use BinaryOperator::*;
+
+    let result = match (operator, &left, &right) {
+        (Equal, _, _) => Value::Boolean(left == right),
+        (NotEqual, _, _) => Value::Boolean(left != right),
+        (Add, Value::String(_), _) | (Add, _, Value::String(_)) => Value::String(format!("{}{}", left, right)),
+        (_, Value::Number(a), Value::Number(b)) => match operator {
+            Add => Value::Number(a + b),
+            Subtract => Value::Number(a - b),
+            Multiply => Value::Number(a * b),
+            Divide => Value::Number(a / b),
+            Modulo => Value::Number(a % b),
+            Less => Value::Boolean(a < b),
+            LessEqual => Value::Boolean(a <= b),
+            Greater => Value::Boolean(a > b),
+            GreaterEqual => Value::Boolean(a >= b),
+            Equal | NotEqual | And | Or => unreachable!("handled above"),
+        },
+        (Less | LessEqual | Greater | GreaterEqual, Value::String(a), Value::String(b)) => Value::Boolean(match operator {
+            Less => a < b,
+            LessEqual => a <= b,
+            Greater => a > b,
+            _ => a >= b,
+        }),
+        _ => {
+            return Err(type_error(span, &format!(
+                "unsupported operands for {:?}: {} and {}", operator, left.type_name(), right.type_name()
+            )))
+        }
+    };
+    Ok(result)
+}
+
+fn array_index(position: f64, len: usize, span: Span) -> Result<usize> {
+    if position < 0.0 || position.fract() != 0.0 || position as usize >= len {
+        return Err(format!("{}:{}: index {} out of bounds", span.line, span.column, position).into());
+    }
+    Ok(position as usize)
+}
+
+fn type_error(span: Span, message: &str) -> Box<dyn std::error::Error> {
+    format!("{}:{}: type error: {}", span.line, span.column, message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> Result<String> {
+        let tokens = crate::lexer::Lexer::new(source).tokenize()?;
+        let program = crate::parser::Parser::new(tokens).parse()?;
+        Interpreter::new().with_step_limit(10_000).run(&program)
+    }
+
+    #[test]
+    fn test_runs_functions_loops_and_closures() {
+        let output = run(r#"
+@lang python {
+    function fib(n) {
+        if (n < 2) {
+            return n;
+        }
+        return fib(n - 1) + fib(n - 2);
+    }
+    let values = [];
+    for (let i = 0; i < 5; i++) {
+        values[i] = fib(i + 5);
+    }
+    let total = 0;
+    function add(x) {
+        total = total + x;
+    }
+    let j = 0;
+    while (j < values.length) {
+        add(values[j]);
+        j = j + 1;
+    }
+    print(values, total, "n=" + j, 7 / 2);
+}
+"#).unwrap();
+        assert_eq!(output, "[5, 8, 13, 21, 34] 81 n=5 3.5\n");
+    }
+
+    #[test]
+    fn test_reports_runtime_errors() {
+        assert!(run("@lang python {\n    print(missing);\n}\n").unwrap_err().to_string().contains("undefined variable `missing`"));
+        assert!(run("@lang python {\n    while (true) {\n    }\n}\n").unwrap_err().to_string().contains("step limit"));
+        let error = run("@lang python {\n    function down(n) {\n        return down(n + 1);\n    }\n    down(0);\n}\n").unwrap_err();
+        assert_eq!(error.to_string(), "3:16: call depth limit of 200 exceeded");
+    }
+}
@@ -0,0 +1,1184 @@
+//! Mid-level intermediate representation
+//!
+//! Lowers the typed AST into functions made of basic blocks in static single
+//! assignment form: every value is defined by exactly one instruction, and a
+//! variable that changes along different paths is merged by a `phi` at the
+//! start of the block where the paths meet. Every value carries a `Type`,
+//! `unknown` where the program does not pin one down.
+//!
+//! Parameters and locals of functions become values. Variables declared by
+//! the top-level statements of a language block or of the program are kept
+//! in memory as globals, named like the units of the call graph
+//! (`python::x`), and are read and written with `load` and `store`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use indexmap::IndexMap;
+use petgraph::{algo::dominators::simple_fast, graph::{DiGraph, NodeIndex}};
+
+use crate::{
+    ast::*,
+    callgraph::{block_unit_id, function_unit_id, GLOBAL_UNIT},
+    propagation::collect_assigned,
+    types::Type,
+    Result,
+};
+
+/// Index of a value within its function
+pub type ValueId = usize;
+
+/// Index of a basic block within its function
+pub type BlockId = usize;
+
+/// What an instruction computes
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstructionKind {
+    Const(LiteralValue),
+    Binary { operator: BinaryOperator, left: ValueId, right: ValueId },
+    Unary { operator: UnaryOperator, operand: ValueId },
+    /// Call of a function of the program, by unit id, or of a builtin, by name
+    Call { function: String, arguments: Vec<ValueId> },
+    /// Call of a function held in a value
+    CallIndirect { callee: ValueId, arguments: Vec<ValueId> },
+    CallMethod { object: ValueId, method: String, arguments: Vec<ValueId> },
+    CrossCall { language: String, function: String, arguments: Vec<ValueId> },
+    /// The value coming from whichever predecessor control arrived from
+    Phi(Vec<(BlockId, ValueId)>),
+    Load(String),
+    Store { global: String, value: ValueId },
+    Array(Vec<ValueId>),
+    Object(Vec<(String, ValueId)>),
+    Index { array: ValueId, index: ValueId },
+    Member { object: ValueId, property: String },
+    StoreIndex { array: ValueId, index: ValueId, value: ValueId },
+    StoreMember { object: ValueId, property: String, value: ValueId },
+}
+
+impl InstructionKind {
+    /// Values read by the instruction
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            InstructionKind::Const(_) | InstructionKind::Load(_) => Vec::new(),
+            InstructionKind::Binary { left, right, .. } => vec![*left, *right],
+            InstructionKind::Unary { operand, .. } => vec![*operand],
+            InstructionKind::Call { arguments, .. } | InstructionKind::CrossCall { arguments, .. } => arguments.clone(),
+            InstructionKind::CallIndirect { callee: first, arguments } | InstructionKind::CallMethod { object: first, arguments, .. } => {
+                std::iter::once(*first).chain(arguments.iter().copied()).collect()
+            }
+            InstructionKind::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+            InstructionKind::Store { value, .. } => vec![*value],
+            InstructionKind::Array(elements) => elements.clone(),
+            InstructionKind::Object(properties) => properties.iter().map(|(_, value)| *value).collect(),
+            InstructionKind::Index { array, index } => vec![*array, *index],
+            InstructionKind::Member { object, .. } => vec![*object],
+            InstructionKind::StoreIndex { array, index, value } => vec![*array, *index, *value],
+            InstructionKind::StoreMember { object, value, .. } => vec![*object, *value],
+        }
+    }
+}
+
+/// An instruction and the value it defines, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub result: Option<ValueId>,
+    pub kind: InstructionKind,
+}
+
+/// How control leaves a basic block
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Return(Option<ValueId>),
+    Jump(BlockId),
+    Branch { condition: ValueId, then_block: BlockId, else_block: BlockId },
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then_block, else_block, .. } => vec![*then_block, *else_block],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+
+    fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Terminator::Return(value) => value.iter().copied().collect(),
+            Terminator::Branch { condition, .. } => vec![*condition],
+            Terminator::Jump(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+/// Straight-line instructions, phis first, ending in a terminator
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub id: BlockId,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+/// A function or a group of top-level statements, lowered to basic blocks
+///
+/// Values `%0` to `%n-1` are the `n` parameters; block 0 is the entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrFunction {
+    /// Unit id, as in the call graph
+    pub name: String,
+    pub language: String,
+    pub parameters: Vec<String>,
+    pub return_type: Type,
+    pub blocks: Vec<BasicBlock>,
+    /// Type of every value, indexed by `ValueId`
+    pub types: Vec<Type>,
+}
+
+impl IrFunction {
+    pub fn value_type(&self, value: ValueId) -> &Type {
+        self.types.get(value).unwrap_or(&Type::Unknown)
+    }
+
+    /// Predecessors of every block
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for block in &self.blocks {
+            for successor in block.terminator.successors() {
+                if let Some(list) = predecessors.get_mut(successor) {
+                    list.push(block.id);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// Check the SSA and type invariants, listing every violation
+    pub fn verify(&self) -> Result<()> {
+        let problems = Verifier::new(self).run();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Invalid IR for {}:\n  {}", self.name, problems.join("\n  ")).into())
+        }
+    }
+}
+
+/// A lowered program
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Module {
+    /// Top-level variables and their declared or inferred types
+    pub globals: BTreeMap<String, Type>,
+    pub functions: Vec<IrFunction>,
+}
+
+impl Module {
+    /// Lower a program, preferably one annotated by the type checker
+    ///
+    /// Functions come before the top-level statements of their block, in
+    /// source order. Lambdas, classes and nested functions are not supported
+    /// yet and fail the lowering.
+    pub fn lower(program: &Program) -> Result<Self> {
+        let context = Context::new(program);
+        let mut functions = Vec::new();
+
+        for (index, block) in program.language_blocks.iter().enumerate() {
+            for function in &block.functions {
+                functions.push(context.function(&block.language, &function.name, &function.parameters, &function.body)?);
+            }
+            functions.extend(context.statements(&block.language, &block_unit_id(&block.language, index), &block.statements)?);
+        }
+        functions.extend(context.statements("global", GLOBAL_UNIT, &program.global_statements)?);
+
+        let mut module = Module { globals: context.globals, functions };
+        module.propagate_return_types();
+        Ok(module)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&IrFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        let problems: Vec<String> = self.functions.iter()
+            .filter_map(|function| function.verify().err().map(|error| error.to_string()))
+            .collect();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("\n").into())
+        }
+    }
+
+    /// Type the results of calls whose callee's return type was only inferred after lowering
+    fn propagate_return_types(&mut self) {
+        let returns: HashMap<String, Type> = self.functions.iter()
+            .map(|function| (function.name.clone(), function.return_type.clone()))
+            .collect();
+        for function in &mut self.functions {
+            for block in &function.blocks {
+                for instruction in &block.instructions {
+                    if let (Some(result), InstructionKind::Call { function: callee, .. }) = (instruction.result, &instruction.kind) {
+                        if function.types[result] == Type::Unknown {
+                            if let Some(return_type) = returns.get(callee) {
+                                function.types[result] = return_type.clone();
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What every unit needs to know about the rest of the program
+struct Context {
+    globals: BTreeMap<String, Type>,
+    /// Declared return type of every function, by unit id
+    functions: HashMap<String, Type>,
+}
+
+impl Context {
+    fn new(program: &Program) -> Self {
+        let mut context = Context { globals: BTreeMap::new(), functions: HashMap::new() };
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                context.declare_function(&block.language, &function.name, function.return_type.as_ref());
+            }
+            context.declare_unit(&block.language, &block.statements);
+        }
+        context.declare_unit("global", &program.global_statements);
+        context
+    }
+
+    fn declare_function(&mut self, language: &str, name: &str, return_type: Option<&Type>) {
+        self.functions.insert(function_unit_id(language, name), return_type.cloned().unwrap_or(Type::Unknown));
+    }
+
+    fn declare_unit(&mut self, language: &str, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FunctionDeclaration { name, return_type, .. } => {
+                    self.declare_function(language, name, return_type.as_ref());
+                }
+                Statement::VariableDeclaration { name, var_type, .. } => {
+                    let declared = var_type.clone().unwrap_or(Type::Unknown);
+                    let global = self.globals.entry(function_unit_id(language, name)).or_insert(Type::Unknown);
+                    if *global == Type::Unknown {
+                        *global = declared;
+                    } else if *global != declared {
+                        *global = Type::Unknown;
+                    }
+                }
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.declare_unit(language, then_branch);
+                    self.declare_unit(language, else_branch.as_deref().unwrap_or_default());
+                }
+                Statement::While { body, .. } | Statement::Block { statements: body, .. } => self.declare_unit(language, body),
+                Statement::For { init, body, .. } => {
+                    self.declare_unit(language, init.as_deref().map(std::slice::from_ref).unwrap_or_default());
+                    self.declare_unit(language, body);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The global a name refers to from `language`: the block's own, then the program's
+    fn global(&self, language: &str, name: &str) -> String {
+        [language, "global"].iter()
+            .map(|scope| function_unit_id(scope, name))
+            .find(|id| self.globals.contains_key(id))
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Resolve a plain call the way the call graph does, or keep the name of a builtin
+    fn callee(&self, language: &str, name: &str) -> String {
+        let suffix = format!("::{name}");
+        [function_unit_id(language, name), function_unit_id("global", name)].into_iter()
+            .find(|id| self.functions.contains_key(id))
+            .or_else(|| self.functions.keys().filter(|id| id.ends_with(&suffix)).min().cloned())
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    fn return_type(&self, callee: &str) -> Type {
+        match (callee, crate::builtins::lookup(callee)) {
+            ("console.log", _) => Type::Void,
+            (_, Some(builtin)) => builtin.return_type(),
+            _ => self.functions.get(callee).cloned().unwrap_or(Type::Unknown),
+        }
+    }
+
+    fn function(&self, language: &str, name: &str, parameters: &[Parameter], body: &[Statement]) -> Result<IrFunction> {
+        let mut builder = Builder::new(self, language, true);
+        for (index, parameter) in parameters.iter().enumerate() {
+            builder.types.push(parameter.param_type.clone().unwrap_or(Type::Unknown));
+            builder.variables.insert(parameter.name.clone(), index);
+        }
+        builder.statements(body)?;
+
+        let declared = self.functions.get(&function_unit_id(language, name)).cloned().unwrap_or(Type::Unknown);
+        let mut function = builder.finish(function_unit_id(language, name), parameters.iter().map(|parameter| parameter.name.clone()).collect());
+        function.return_type = if declared == Type::Unknown { inferred_return_type(&function) } else { declared };
+        Ok(function)
+    }
+
+    /// Lower the top-level statements of a unit and the functions they declare
+    fn statements(&self, language: &str, unit: &str, statements: &[Statement]) -> Result<Vec<IrFunction>> {
+        let mut functions = Vec::new();
+        let mut rest = Vec::new();
+        for statement in statements {
+            match statement {
+                Statement::FunctionDeclaration { name, parameters, body, .. } => {
+                    functions.push(self.function(language, name, parameters, body)?);
+                }
+                statement => rest.push(statement.clone()),
+            }
+        }
+        if !rest.is_empty() {
+            let mut builder = Builder::new(self, language, false);
+            builder.statements(&rest)?;
+            let mut function = builder.finish(unit.to_string(), Vec::new());
+            function.return_type = Type::Void;
+            functions.push(function);
+        }
+        Ok(functions)
+    }
+}
+
+fn inferred_return_type(function: &IrFunction) -> Type {
+    let returned: Vec<Type> = function.blocks.iter()
+        .filter_map(|block| match block.terminator {
+            Terminator::Return(value) => Some(value.map_or(Type::Void, |value| function.value_type(value).clone())),
+            _ => None,
+        })
+        .collect();
+    common_type(&returned)
+}
+
+/// The type shared by all of `types`, or `unknown`
+fn common_type(types: &[Type]) -> Type {
+    match types.split_first() {
+        Some((first, rest)) if rest.iter().all(|other| other == first) => first.clone(),
+        _ => Type::Unknown,
+    }
+}
+
+fn binary_type(operator: BinaryOperator, left: &Type, right: &Type) -> Type {
+    match operator {
+        BinaryOperator::Add => match (left, right) {
+            (Type::Number, Type::Number) => Type::Number,
+            (Type::String, _) | (_, Type::String) => Type::String,
+            _ => Type::Unknown,
+        },
+        BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => Type::Number,
+        BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::Less | BinaryOperator::LessEqual
+        | BinaryOperator::Greater | BinaryOperator::GreaterEqual => Type::Boolean,
+        BinaryOperator::And | BinaryOperator::Or => common_type(&[left.clone(), right.clone()]),
+    }
+}
+
+/// A block under construction; the terminator is set once control leaves it
+struct PendingBlock {
+    instructions: Vec<Instruction>,
+    terminator: Option<Terminator>,
+}
+
+/// Lowers one unit, tracking the current value of every local variable
+struct Builder<'a> {
+    context: &'a Context,
+    language: &'a str,
+    /// Whether declarations create locals (functions) or globals (top-level statements)
+    locals: bool,
+    blocks: Vec<PendingBlock>,
+    types: Vec<Type>,
+    current: BlockId,
+    variables: BTreeMap<String, ValueId>,
+    /// Bindings shadowed by the declarations of every open scope
+    scopes: Vec<Vec<(String, Option<ValueId>)>>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(context: &'a Context, language: &'a str, locals: bool) -> Self {
+        Self {
+            context,
+            language,
+            locals,
+            blocks: vec![PendingBlock { instructions: Vec::new(), terminator: None }],
+            types: Vec::new(),
+            current: 0,
+            variables: BTreeMap::new(),
+            scopes: vec![Vec::new()],
+        }
+    }
+
+    fn finish(mut self, name: String, parameters: Vec<String>) -> IrFunction {
+        self.terminate(Terminator::Return(None));
+        let blocks = self.blocks.into_iter().enumerate()
+            .map(|(id, block)| BasicBlock {
+                id,
+                instructions: block.instructions,
+                terminator: block.terminator.unwrap_or(Terminator::Unreachable),
+            })
+            .collect();
+        IrFunction {
+            name,
+            language: self.language.to_string(),
+            parameters,
+            return_type: Type::Unknown,
+            blocks,
+            types: self.types,
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PendingBlock { instructions: Vec::new(), terminator: None });
+        self.blocks.len() - 1
+    }
+
+    fn emit(&mut self, kind: InstructionKind, value_type: Type) -> ValueId {
+        let result = self.types.len();
+        self.types.push(value_type);
+        self.blocks[self.current].instructions.push(Instruction { result: Some(result), kind });
+        result
+    }
+
+    fn emit_effect(&mut self, kind: InstructionKind) {
+        self.blocks[self.current].instructions.push(Instruction { result: None, kind });
+    }
+
+    fn terminated(&self) -> bool {
+        self.blocks[self.current].terminator.is_some()
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.terminate_block(self.current, terminator);
+    }
+
+    fn terminate_block(&mut self, block: BlockId, terminator: Terminator) {
+        self.blocks[block].terminator.get_or_insert(terminator);
+    }
+
+    fn declare(&mut self, name: &str, value: ValueId) {
+        let shadowed = self.variables.insert(name.to_string(), value);
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.iter().any(|(declared, _)| declared == name) {
+                scope.push((name.to_string(), shadowed));
+            }
+        }
+    }
+
+    /// Lower statements in a scope of their own, dropping their declarations afterwards
+    fn scoped(&mut self, statements: &[Statement]) -> Result<()> {
+        self.scopes.push(Vec::new());
+        let lowered = self.statements(statements);
+        for (name, shadowed) in self.scopes.pop().unwrap_or_default().into_iter().rev() {
+            match shadowed {
+                Some(value) => self.variables.insert(name, value),
+                None => self.variables.remove(&name),
+            };
+        }
+        lowered
+    }
+
+    /// Lower statements until control leaves the current block for good
+    fn statements(&mut self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            if self.terminated() {
+                break;
+            }
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                self.expression(expression)?;
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                let value = match value {
+                    Some(value) => self.expression(value)?,
+                    None => self.emit(InstructionKind::Const(LiteralValue::Null), Type::Null),
+                };
+                if self.locals {
+                    self.declare(name, value);
+                } else {
+                    let global = function_unit_id(self.language, name);
+                    self.emit_effect(InstructionKind::Store { global, value });
+                }
+            }
+            Statement::Assignment { target, value, .. } => {
+                let value = self.expression(value)?;
+                self.assign(target, value)?;
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.branch(condition, then_branch, else_branch.as_deref().unwrap_or_default())?;
+            }
+            Statement::While { condition, body, .. } => self.repeat(Some(condition), body, None)?,
+            Statement::For { init, condition, update, body, .. } => {
+                self.scopes.push(Vec::new());
+                if let Some(init) = init {
+                    self.statement(init)?;
+                }
+                self.repeat(condition.as_ref(), body, update.as_ref())?;
+                for (name, shadowed) in self.scopes.pop().unwrap_or_default() {
+                    match shadowed {
+                        Some(value) => self.variables.insert(name, value),
+                        None => self.variables.remove(&name),
+                    };
+                }
+            }
+            Statement::Return { value, .. } => {
+                let value = value.as_ref().map(|value| self.expression(value)).transpose()?;
+                self.terminate(Terminator::Return(value));
+            }
+            Statement::Block { statements, .. } => self.scoped(statements)?,
+            Statement::Import { .. } | Statement::Export { .. } => {}
+            Statement::FunctionDeclaration { name, .. } => {
+                return Err(format!("Nested function '{}' cannot be lowered to IR yet", name).into());
+            }
+            Statement::ClassDeclaration { name, .. } => {
+                return Err(format!("Class '{}' cannot be lowered to IR yet", name).into());
+            }
+        }
+        Ok(())
+    }
+
+    fn branch(&mut self, condition: &Expression, then_branch: &[Statement], else_branch: &[Statement]) -> Result<()> {
+        let condition = self.expression(condition)?;
+        let then_block = self.new_block();
+        let else_block = self.new_block();
+        self.terminate(Terminator::Branch { condition, then_block, else_block });
+        let before = self.variables.clone();
+
+        let mut exits = Vec::new();
+        for (block, statements) in [(then_block, then_branch), (else_block, else_branch)] {
+            self.current = block;
+            self.variables = before.clone();
+            self.scoped(statements)?;
+            if !self.terminated() {
+                exits.push((self.current, self.variables.clone()));
+            }
+        }
+
+        // With both branches returning, the rest of the statements is never lowered
+        if exits.is_empty() {
+            return Ok(());
+        }
+        let merge = self.new_block();
+        for (block, _) in &exits {
+            self.terminate_block(*block, Terminator::Jump(merge));
+        }
+        self.current = merge;
+        self.variables = self.join(&exits);
+        Ok(())
+    }
+
+    /// Merge the variables reaching the current block from several predecessors
+    ///
+    /// Variables that differ get a phi; ones missing from any predecessor
+    /// went out of scope and are dropped.
+    fn join(&mut self, exits: &[(BlockId, BTreeMap<String, ValueId>)]) -> BTreeMap<String, ValueId> {
+        let mut variables = BTreeMap::new();
+        let Some(((_, first), rest)) = exits.split_first() else {
+            return variables;
+        };
+        for (name, &value) in first {
+            let Some(incoming) = rest.iter()
+                .map(|(block, variables)| variables.get(name).map(|&value| (*block, value)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            if incoming.iter().all(|(_, other)| *other == value) {
+                variables.insert(name.clone(), value);
+                continue;
+            }
+            let incoming: Vec<(BlockId, ValueId)> = std::iter::once((exits[0].0, value)).chain(incoming).collect();
+            let types: Vec<Type> = incoming.iter().map(|(_, value)| self.types[*value].clone()).collect();
+            let phi = self.emit(InstructionKind::Phi(incoming), common_type(&types));
+            variables.insert(name.clone(), phi);
+        }
+        variables
+    }
+
+    /// Lower a `while` or `for` loop
+    ///
+    /// The header starts with a phi for every local the loop may change; the
+    /// values coming around the back edge are filled in once the body is lowered.
+    fn repeat(&mut self, condition: Option<&Expression>, body: &[Statement], update: Option<&Expression>) -> Result<()> {
+        let mut assigned = HashSet::new();
+        collect_assigned(body, &mut assigned);
+        let around: Vec<Statement> = condition.into_iter().chain(update)
+            .map(|expression| Statement::Expression { expression: expression.clone(), span: expression.span() })
+            .collect();
+        collect_assigned(&around, &mut assigned);
+
+        let entry = self.current;
+        let header = self.new_block();
+        self.terminate(Terminator::Jump(header));
+        self.current = header;
+
+        let mut phis = Vec::new();
+        let changed: BTreeSet<String> = assigned.into_iter().filter(|name| self.variables.contains_key(name)).collect();
+        for name in changed {
+            let initial = self.variables[&name];
+            let phi = self.emit(InstructionKind::Phi(vec![(entry, initial)]), self.types[initial].clone());
+            self.variables.insert(name.clone(), phi);
+            phis.push((name, phi));
+        }
+
+        let condition = match condition {
+            Some(condition) => self.expression(condition)?,
+            None => self.emit(InstructionKind::Const(LiteralValue::Boolean(true)), Type::Boolean),
+        };
+        let body_block = self.new_block();
+        let exit = self.new_block();
+        self.terminate(Terminator::Branch { condition, then_block: body_block, else_block: exit });
+        let after_condition = self.variables.clone();
+
+        self.current = body_block;
+        self.scoped(body)?;
+        if !self.terminated() {
+            if let Some(update) = update {
+                self.expression(update)?;
+            }
+            let latch = self.current;
+            self.terminate(Terminator::Jump(header));
+            for (name, phi) in phis {
+                let value = self.variables[&name];
+                if self.types[value] != self.types[phi] {
+                    self.types[phi] = Type::Unknown;
+                }
+                if let Some(Instruction { kind: InstructionKind::Phi(incoming), .. }) =
+                    self.blocks[header].instructions.iter_mut().find(|instruction| instruction.result == Some(phi))
+                {
+                    incoming.push((latch, value));
+                }
+            }
+        }
+
+        self.current = exit;
+        self.variables = after_condition;
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<ValueId> {
+        let value = match expression {
+            Expression::Literal { value, .. } => self.emit(InstructionKind::Const(value.clone()), value.type_hint()),
+            Expression::Identifier { name, .. } => {
+                if let Some(&value) = self.variables.get(name) {
+                    value
+                } else {
+                    let global = self.context.global(self.language, name);
+                    let value_type = self.context.globals.get(&global).cloned().unwrap_or(Type::Unknown);
+                    self.emit(InstructionKind::Load(global), value_type)
+                }
+            }
+            Expression::Binary { left, operator: operator @ (BinaryOperator::And | BinaryOperator::Or), right, .. } => {
+                self.short_circuit(*operator, left, right)?
+            }
+            Expression::Binary { left, operator, right, .. } => {
+                let left = self.expression(left)?;
+                let right = self.expression(right)?;
+                let value_type = binary_type(*operator, &self.types[left], &self.types[right]);
+                self.emit(InstructionKind::Binary { operator: *operator, left, right }, value_type)
+            }
+            Expression::Unary { operator, operand, .. } => {
+                let operand = self.expression(operand)?;
+                let value_type = if *operator == UnaryOperator::Not { Type::Boolean } else { Type::Number };
+                self.emit(InstructionKind::Unary { operator: *operator, operand }, value_type)
+            }
+            Expression::Postfix { operand, operator, .. } => {
+                let old = self.expression(operand)?;
+                let one = self.emit(InstructionKind::Const(LiteralValue::Number(1.0)), Type::Number);
+                let operator = match operator {
+                    PostfixOperator::Increment => BinaryOperator::Add,
+                    PostfixOperator::Decrement => BinaryOperator::Subtract,
+                };
+                let new = self.emit(InstructionKind::Binary { operator, left: old, right: one }, Type::Number);
+                self.assign(operand, new)?;
+                old
+            }
+            Expression::Assignment { target, value, .. } => {
+                let value = self.expression(value)?;
+                self.assign(target, value)?;
+                value
+            }
+            Expression::Call { callee, arguments, .. } => self.call(callee, arguments)?,
+            Expression::CrossCall { language, function, arguments, .. } => {
+                let arguments = self.expressions(arguments)?;
+                let value_type = self.context.return_type(&function_unit_id(language, function));
+                self.emit(InstructionKind::CrossCall { language: language.clone(), function: function.clone(), arguments }, value_type)
+            }
+            Expression::MemberAccess { object, property, .. } => {
+                let object = self.expression(object)?;
+                let value_type = match &self.types[object] {
+                    Type::Object(fields) => fields.get(property).cloned().unwrap_or(Type::Unknown),
+                    Type::Array(_) | Type::String if property == "length" => Type::Number,
+                    _ => Type::Unknown,
+                };
+                self.emit(InstructionKind::Member { object, property: property.clone() }, value_type)
+            }
+            Expression::ArrayAccess { array, index, .. } => {
+                let array = self.expression(array)?;
+                let index = self.expression(index)?;
+                let value_type = match &self.types[array] {
+                    Type::Array(element) => element.as_ref().clone(),
+                    Type::String => Type::String,
+                    _ => Type::Unknown,
+                };
+                self.emit(InstructionKind::Index { array, index }, value_type)
+            }
+            Expression::Array { elements, .. } => {
+                let elements = self.expressions(elements)?;
+                let types: Vec<Type> = elements.iter().map(|element| self.types[*element].clone()).collect();
+                self.emit(InstructionKind::Array(elements), Type::Array(Box::new(common_type(&types))))
+            }
+            Expression::Object { properties, .. } => {
+                let mut fields = IndexMap::new();
+                let mut values = Vec::new();
+                for (name, property) in properties {
+                    let value = self.expression(property)?;
+                    fields.insert(name.clone(), self.types[value].clone());
+                    values.push((name.clone(), value));
+                }
+                self.emit(InstructionKind::Object(values), Type::Object(fields))
+            }
+            Expression::Lambda { .. } => return Err("Lambdas cannot be lowered to IR yet".into()),
+        };
+        Ok(value)
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) -> Result<Vec<ValueId>> {
+        expressions.iter().map(|expression| self.expression(expression)).collect()
+    }
+
+    fn call(&mut self, callee: &Expression, arguments: &[Expression]) -> Result<ValueId> {
+        let value = match callee {
+            Expression::Identifier { name, .. } if !self.variables.contains_key(name) => {
+                let function = self.context.callee(self.language, name);
+                let arguments = self.expressions(arguments)?;
+                let value_type = self.context.return_type(&function);
+                self.emit(InstructionKind::Call { function, arguments }, value_type)
+            }
+            // Methods of names the program does not define, such as `console.log`, are builtins
+            Expression::MemberAccess { object, property, .. } if matches!(object.as_ref(), Expression::Identifier { name, .. }
+                if !self.variables.contains_key(name) && !self.context.globals.contains_key(&self.context.global(self.language, name))) =>
+            {
+                let Expression::Identifier { name, .. } = object.as_ref() else { unreachable!() };
+                let function = format!("{name}.{property}");
+                let arguments = self.expressions(arguments)?;
+                let value_type = self.context.return_type(&function);
+                self.emit(InstructionKind::Call { function, arguments }, value_type)
+            }
+            Expression::MemberAccess { object, property, .. } => {
+                let object = self.expression(object)?;
+                let arguments = self.expressions(arguments)?;
+                self.emit(InstructionKind::CallMethod { object, method: property.clone(), arguments }, Type::Unknown)
+            }
+            callee => {
+                let callee = self.expression(callee)?;
+                let arguments = self.expressions(arguments)?;
+                let value_type = match &self.types[callee] {
+                    Type::Function { return_type, .. } => return_type.as_ref().clone(),
+                    _ => Type::Unknown,
+                };
+                self.emit(InstructionKind::CallIndirect { callee, arguments }, value_type)
+            }
+        };
+        Ok(value)
+    }
+
+    /// `&&` and `||` only evaluate their right operand when the left one does not decide the result
+    fn short_circuit(&mut self, operator: BinaryOperator, left: &Expression, right: &Expression) -> Result<ValueId> {
+        let left = self.expression(left)?;
+        let decided = self.current;
+        let right_block = self.new_block();
+        let merge = self.new_block();
+        let (then_block, else_block) = if operator == BinaryOperator::And { (right_block, merge) } else { (merge, right_block) };
+        self.terminate(Terminator::Branch { condition: left, then_block, else_block });
+        let before = self.variables.clone();
+
+        self.current = right_block;
+        let right = self.expression(right)?;
+        let evaluated = self.current;
+        self.terminate(Terminator::Jump(merge));
+
+        self.current = merge;
+        let after = std::mem::take(&mut self.variables);
+        self.variables = self.join(&[(decided, before), (evaluated, after)]);
+        let value_type = common_type(&[self.types[left].clone(), self.types[right].clone()]);
+        Ok(self.emit(InstructionKind::Phi(vec![(decided, left), (evaluated, right)]), value_type))
+    }
+
+    fn assign(&mut self, target: &Expression, value: ValueId) -> Result<()> {
+        match target {
+            Expression::Identifier { name, .. } if self.variables.contains_key(name) => {
+                self.variables.insert(name.clone(), value);
+            }
+            Expression::Identifier { name, .. } => {
+                let global = self.context.global(self.language, name);
+                self.emit_effect(InstructionKind::Store { global, value });
+            }
+            Expression::MemberAccess { object, property, .. } => {
+                let object = self.expression(object)?;
+                self.emit_effect(InstructionKind::StoreMember { object, property: property.clone(), value });
+            }
+            Expression::ArrayAccess { array, index, .. } => {
+                let array = self.expression(array)?;
+                let index = self.expression(index)?;
+                self.emit_effect(InstructionKind::StoreIndex { array, index, value });
+            }
+            _ => return Err("Invalid assignment target".into()),
+        }
+        Ok(())
+    }
+}
+
+/// Checks one function, collecting a message per violated invariant
+struct Verifier<'a> {
+    function: &'a IrFunction,
+    predecessors: Vec<Vec<BlockId>>,
+    /// Block and position of the definition of every value; parameters come first in the entry block
+    definitions: Vec<Option<(BlockId, usize)>>,
+    problems: Vec<String>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(function: &'a IrFunction) -> Self {
+        Self {
+            function,
+            predecessors: function.predecessors(),
+            definitions: vec![None; function.types.len()],
+            problems: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Vec<String> {
+        let function = self.function;
+        if function.blocks.is_empty() {
+            return vec!["function has no blocks".to_string()];
+        }
+        if function.parameters.len() > function.types.len() {
+            self.problems.push("parameters have no types".to_string());
+        }
+        for parameter in 0..function.parameters.len().min(function.types.len()) {
+            self.definitions[parameter] = Some((0, 0));
+        }
+        self.check_definitions();
+
+        let mut graph = DiGraph::<(), ()>::new();
+        for _ in &function.blocks {
+            graph.add_node(());
+        }
+        for block in &function.blocks {
+            for successor in block.terminator.successors() {
+                if successor < function.blocks.len() {
+                    graph.add_edge(NodeIndex::new(block.id), NodeIndex::new(successor), ());
+                }
+            }
+        }
+        let dominators = simple_fast(&graph, NodeIndex::new(0));
+        let dominates = |definition: BlockId, block: BlockId| {
+            dominators.dominators(NodeIndex::new(block))
+                .is_some_and(|mut chain| chain.any(|dominator| dominator.index() == definition))
+        };
+
+        for block in &function.blocks {
+            // Nothing flows out of unreachable blocks, so uses there cannot be wrong
+            if dominators.dominators(NodeIndex::new(block.id)).is_none() {
+                continue;
+            }
+            for (position, instruction) in block.instructions.iter().enumerate() {
+                if let InstructionKind::Phi(incoming) = &instruction.kind {
+                    self.check_phi(block.id, instruction.result, incoming, &dominates);
+                    continue;
+                }
+                for operand in instruction.kind.operands() {
+                    self.check_use(operand, block.id, position + 1, &dominates);
+                }
+                self.check_types(block.id, &instruction.kind);
+            }
+            for operand in block.terminator.operands() {
+                self.check_use(operand, block.id, block.instructions.len() + 1, &dominates);
+            }
+            if let Terminator::Return(Some(value)) = block.terminator {
+                let returned = function.value_type(value);
+                if ![Type::Unknown, Type::Void].contains(&function.return_type) && *returned != Type::Unknown && *returned != function.return_type {
+                    self.problems.push(format!("bb{} returns {} from a function returning {}", block.id, returned, function.return_type));
+                }
+            }
+        }
+        self.problems
+    }
+
+    fn check_definitions(&mut self) {
+        let function = self.function;
+        for (index, block) in function.blocks.iter().enumerate() {
+            if block.id != index {
+                self.problems.push(format!("block {} is numbered bb{}", index, block.id));
+            }
+            let mut past_phis = false;
+            for (position, instruction) in block.instructions.iter().enumerate() {
+                let is_phi = matches!(instruction.kind, InstructionKind::Phi(_));
+                if is_phi && past_phis {
+                    self.problems.push(format!("bb{index} has a phi after other instructions"));
+                }
+                past_phis |= !is_phi;
+
+                let Some(result) = instruction.result else { continue };
+                match self.definitions.get_mut(result) {
+                    None => self.problems.push(format!("%{result} has no type")),
+                    Some(Some(_)) => self.problems.push(format!("%{result} is defined more than once")),
+                    Some(definition) => *definition = Some((index, position + 1)),
+                }
+            }
+            for successor in block.terminator.successors() {
+                if successor >= function.blocks.len() {
+                    self.problems.push(format!("bb{index} jumps to missing bb{successor}"));
+                }
+            }
+        }
+    }
+
+    fn check_use(&mut self, value: ValueId, block: BlockId, position: usize, dominates: &impl Fn(BlockId, BlockId) -> bool) {
+        match self.definitions.get(value).copied().flatten() {
+            None => self.problems.push(format!("%{value} is used in bb{block} but never defined")),
+            Some((definition, defined_at)) if definition == block => {
+                if defined_at >= position {
+                    self.problems.push(format!("%{value} is used in bb{block} before it is defined"));
+                }
+            }
+            Some((definition, _)) => {
+                if !dominates(definition, block) {
+                    self.problems.push(format!("%{value} is defined in bb{definition}, which does not dominate its use in bb{block}"));
+                }
+            }
+        }
+    }
+
+    fn check_phi(&mut self, block: BlockId, result: Option<ValueId>, incoming: &[(BlockId, ValueId)], dominates: &impl Fn(BlockId, BlockId) -> bool) {
+        let name = result.map_or_else(|| "phi".to_string(), |result| format!("phi %{result}"));
+        let predecessors = self.predecessors[block].clone();
+        for predecessor in &predecessors {
+            if incoming.iter().filter(|(from, _)| from == predecessor).count() != 1 {
+                self.problems.push(format!("{name} in bb{block} needs exactly one value from bb{predecessor}"));
+            }
+        }
+        for &(from, value) in incoming {
+            if !predecessors.contains(&from) {
+                self.problems.push(format!("{name} in bb{block} has a value from bb{from}, which is not a predecessor"));
+                continue;
+            }
+            // The value has to be available at the end of the predecessor
+            let end = self.function.blocks[from].instructions.len() + 1;
+            self.check_use(value, from, end, dominates);
+        }
+    }
+
+    fn check_types(&mut self, block: BlockId, kind: &InstructionKind) {
+        let numeric = match kind {
+            InstructionKind::Binary {
+                operator: operator @ (BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo),
+                left,
+                right,
+            } => Some((binary_mnemonic(*operator), vec![*left, *right])),
+            InstructionKind::Unary { operator: UnaryOperator::Minus, operand } => Some(("neg", vec![*operand])),
+            _ => None,
+        };
+        let Some((mnemonic, operands)) = numeric else { return };
+        for operand in operands {
+            let operand_type = self.function.value_type(operand);
+            if !matches!(operand_type, Type::Number | Type::Unknown) {
+                self.problems.push(format!("'{mnemonic}' in bb{block} expects numbers, got {operand_type} %{operand}"));
+            }
+        }
+    }
+}
+
+fn binary_mnemonic(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "add",
+        BinaryOperator::Subtract => "sub",
+        BinaryOperator::Multiply => "mul",
+        BinaryOperator::Divide => "div",
+        BinaryOperator::Modulo => "mod",
+        BinaryOperator::Equal => "eq",
+        BinaryOperator::NotEqual => "ne",
+        BinaryOperator::Less => "lt",
+        BinaryOperator::LessEqual => "le",
+        BinaryOperator::Greater => "gt",
+        BinaryOperator::GreaterEqual => "ge",
+        BinaryOperator::And => "and",
+        BinaryOperator::Or => "or",
+    }
+}
+
+fn unary_mnemonic(operator: UnaryOperator) -> &'static str {
+    match operator {
+        UnaryOperator::Not => "not",
+        UnaryOperator::Minus => "neg",
+        UnaryOperator::Plus => "pos",
+    }
+}
+
+fn values(values: &[ValueId]) -> String {
+    values.iter().map(|value| format!("%{value}")).collect::<Vec<_>>().join(", ")
+}
+
+impl fmt::Display for InstructionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstructionKind::Const(LiteralValue::Number(number)) => write!(f, "const {number}"),
+            InstructionKind::Const(LiteralValue::String(string)) => write!(f, "const {string:?}"),
+            InstructionKind::Const(LiteralValue::Boolean(boolean)) => write!(f, "const {boolean}"),
+            InstructionKind::Const(LiteralValue::Null) => write!(f, "const null"),
+            InstructionKind::Binary { operator, left, right } => write!(f, "{} %{left}, %{right}", binary_mnemonic(*operator)),
+            InstructionKind::Unary { operator, operand } => write!(f, "{} %{operand}", unary_mnemonic(*operator)),
+            InstructionKind::Call { function, arguments } => write!(f, "call {function}({})", values(arguments)),
+            InstructionKind::CallIndirect { callee, arguments } => write!(f, "call %{callee}({})", values(arguments)),
+            InstructionKind::CallMethod { object, method, arguments } => write!(f, "call %{object}.{method}({})", values(arguments)),
+            InstructionKind::CrossCall { language, function, arguments } => {
+                write!(f, "crosscall {language}::{function}({})", values(arguments))
+            }
+            InstructionKind::Phi(incoming) => {
+                let incoming: Vec<String> = incoming.iter().map(|(block, value)| format!("[bb{block}: %{value}]")).collect();
+                write!(f, "phi {}", incoming.join(", "))
+            }
+            InstructionKind::Load(global) => write!(f, "load @{global}"),
+            InstructionKind::Store { global, value } => write!(f, "store @{global}, %{value}"),
+            InstructionKind::Array(elements) => write!(f, "array [{}]", values(elements)),
+            InstructionKind::Object(properties) => {
+                let properties: Vec<String> = properties.iter().map(|(name, value)| format!("{name}: %{value}")).collect();
+                write!(f, "object {{{}}}", properties.join(", "))
+            }
+            InstructionKind::Index { array, index } => write!(f, "index %{array}[%{index}]"),
+            InstructionKind::Member { object, property } => write!(f, "member %{object}.{property}"),
+            InstructionKind::StoreIndex { array, index, value } => write!(f, "store %{array}[%{index}], %{value}"),
+            InstructionKind::StoreMember { object, property, value } => write!(f, "store %{object}.{property}, %{value}"),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Return(None) => write!(f, "ret"),
+            Terminator::Return(Some(value)) => write!(f, "ret %{value}"),
+            Terminator::Jump(target) => write!(f, "jmp bb{target}"),
+            Terminator::Branch { condition, then_block, else_block } => write!(f, "br %{condition}, bb{then_block}, bb{else_block}"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl fmt::Display for IrFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().enumerate()
+            .map(|(index, name)| format!("%{index} {name}: {}", self.value_type(index)))
+            .collect();
+        writeln!(f, "fn {}({}) -> {} {{", self.name, parameters.join(", "), self.return_type)?;
+        for block in &self.blocks {
+            writeln!(f, "bb{}:", block.id)?;
+            for instruction in &block.instructions {
+                match instruction.result {
+                    Some(result) => writeln!(f, "  %{result}: {} = {}", self.value_type(result), instruction.kind)?,
+                    None => writeln!(f, "  {}", instruction.kind)?,
+                }
+            }
+            writeln!(f, "  {}", block.terminator)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, global_type) in &self.globals {
+            writeln!(f, "global @{name}: {global_type}")?;
+        }
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 || !self.globals.is_empty() {
+                writeln!(f)?;
+            }
+            write!(f, "{function}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_lowers_control_flow_to_ssa() {
+        let source = "function sum(n) {\n    let total = 0;\n    let i = 0;\n    while (i < n) {\n        if (i % 2 == 0) {\n            total = total + i;\n        }\n        i = i + 1;\n    }\n    return total;\n}\nprint(sum(10));\n";
+        let program = parse(source);
+        assert_eq!(Interpreter::new().run(&program).unwrap().trim(), "20");
+
+        let module = Module::lower(&program).unwrap();
+        module.verify().unwrap();
+        let sum = module.function("global::sum").unwrap();
+        let dump = sum.to_string();
+        assert!(dump.starts_with("fn global::sum(%0 n: unknown) -> number {\nbb0:\n"), "{}", dump);
+        // The loop header merges both variables changed in the loop, and the if merges `total`
+        assert!(dump.contains("bb1:\n  %3: number = phi [bb0: %2], [bb6: %13]\n  %4: number = phi [bb0: %1], [bb6: %11]\n  %5: boolean = lt %3, %0\n  br %5, bb2, bb3\n"), "{}", dump);
+        assert!(dump.contains("bb6:\n  %11: number = phi [bb4: %10], [bb5: %4]\n"), "{}", dump);
+        assert!(dump.contains("bb3:\n  ret %4\n"), "{}", dump);
+
+        let main = module.function(GLOBAL_UNIT).unwrap();
+        assert!(main.to_string().contains("call global::sum(%0)"), "{}", main);
+        assert_eq!(main.value_type(1), &Type::Number);
+    }
+
+    #[test]
+    fn test_globals_and_short_circuit() {
+        let source = "@lang python {\n    let count = 0;\n    function bump(flag) {\n        if (flag && count < 3) {\n            count++;\n        }\n        return count;\n    }\n}\n";
+        let module = Module::lower(&parse(source)).unwrap();
+        module.verify().unwrap();
+        assert!(module.globals.contains_key("python::count"));
+
+        let dump = module.to_string();
+        assert!(dump.contains("load @python::count"), "{}", dump);
+        assert!(dump.contains("store @python::count, %"), "{}", dump);
+        assert!(dump.contains("br %0, bb1, bb2"), "{}", dump);
+        assert!(dump.contains("= phi [bb0: %0], [bb1: %3]"), "{}", dump);
+
+        assert!(Module::lower(&parse("function outer() {\n    function inner() {\n        return 1;\n    }\n    return inner();\n}\n")).is_err());
+    }
+
+    #[test]
+    fn test_verifier_rejects_broken_functions() {
+        let mut module = Module::lower(&parse("function pick(a, b) {\n    let x = a;\n    if (a > b) {\n        x = b;\n    }\n    return x - 1;\n}\n")).unwrap();
+        module.verify().unwrap();
+
+        let function = &mut module.functions[0];
+        let merge = function.blocks.len() - 1;
+        // The phi merging `x` loses the value from one of its predecessors and becomes a string
+        let Some(Instruction { result: Some(phi), kind: InstructionKind::Phi(incoming) }) = function.blocks[merge].instructions.first_mut() else {
+            panic!("{}", function);
+        };
+        incoming.pop();
+        function.types[*phi] = Type::String;
+        // A value defined in the `then` branch, which does not dominate the merge block
+        let branch_value = function.types.len();
+        function.types.push(Type::Number);
+        function.blocks[1].instructions.push(Instruction { result: Some(branch_value), kind: InstructionKind::Const(LiteralValue::Number(2.0)) });
+        function.blocks[merge].terminator = Terminator::Return(Some(branch_value));
+
+        let error = module.verify().unwrap_err().to_string();
+        assert!(error.contains("phi %3 in bb3 needs exactly one value from bb2"), "{}", error);
+        assert!(error.contains("'sub' in bb3 expects numbers, got string %3"), "{}", error);
+        assert!(error.contains("which does not dominate its use in bb3"), "{}", error);
+    }
+}
@@ -45,19 +45,50 @@
 #![allow(clippy::module_name_repetitions)]
 
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use pipeline::{NoHooks, PipelineHooks, PipelineOptions, PipelineOutput, SharedHooks, Stage, StageTiming, TargetBuild};
+use rayon::prelude::*;
+use resolver::Resolver;
+use plugin::PluginTransformer;
+use templates::TemplateTransformer;
 
 pub mod ast;
 pub mod transformers;
+pub mod builtins;
+pub mod cache;
+pub mod callgraph;
+pub mod capabilities;
 pub mod cli;
 pub mod config;
+pub mod cse;
+pub mod deadcode;
 pub mod diagnostics;
+pub mod effects;
+pub mod emitter;
+pub mod inliner;
+pub mod interpreter;
+pub mod ir;
 pub mod lexer;
+pub mod loops;
+pub mod mangle;
+pub mod operators;
 pub mod optimizer;
 pub mod parser;
+pub mod pipeline;
+pub mod plugin;
+pub mod propagation;
+pub mod resolver;
 pub mod reverse;
-// pub mod runtime; // TODO: Implement runtime module
+pub mod simplify;
+pub mod sourcemap;
+pub mod tailcall;
+pub mod templates;
+pub mod runtime;
 pub mod types;
 pub mod utils;
+pub mod watch;
 
 // Re-export commonly used types
 pub use ast::*;
@@ -69,7 +100,7 @@ pub use lexer::*;
 pub use optimizer::*;
 pub use parser::*;
 pub use reverse::*;
-// pub use runtime::*; // TODO: Implement runtime module
+pub use runtime::Runtime;
 pub use types::*;
 
 /// Result type used throughout the compiler
@@ -104,19 +135,22 @@ impl Span {
 }
 
 /// Main compiler structure
+///
+/// Every CLI command drives source code through the same staged pipeline:
+/// lex, parse, resolve, type-check, optimize and generate.
 pub struct Compiler {
     config: Config,
     type_system: TypeSystem,
     optimizer: Optimizer,
-    transformers: std::collections::HashMap<String, Box<dyn Transformer>>,
+    transformers: TransformerManager,
 }
 
 impl Compiler {
     /// Create a new compiler instance
     pub fn new(config: Config) -> Self {
         let type_system = TypeSystem::new();
-        let optimizer = Optimizer::new();
-        let transformers = std::collections::HashMap::new();
+        let optimizer = Optimizer::for_level(config.optimization_level);
+        let transformers = TransformerManager::new();
 
         Self {
             config,
@@ -126,32 +160,215 @@ impl Compiler {
         }
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Optimizer used by the last pipeline run, with its statistics
+    pub fn optimizer(&self) -> &Optimizer {
+        &self.optimizer
+    }
+
+    pub fn transformers(&self) -> &TransformerManager {
+        &self.transformers
+    }
+
     /// Compile source code to target language
     pub fn compile(&mut self, source: &str, target: &str) -> Result<String> {
-        // Lexical analysis
-        let mut lexer = Lexer::new(source);
-        let tokens = lexer.tokenize()?;
+        let output = self.run(source, &PipelineOptions::new(target), &mut NoHooks)?;
+        if output.has_errors() {
+            return Err(format_errors(&output.diagnostics).into());
+        }
+        output.code.ok_or_else(|| "No code was generated".into())
+    }
 
-        // Syntax analysis
-        let mut parser = Parser::new(tokens);
-        let ast = parser.parse()?;
+    /// Add a transformer for code generation
+    pub fn add_transformer(&mut self, name: String, transformer: Box<dyn Transformer>) {
+        self.transformers.register(&name, transformer);
+    }
+
+    /// Add a user-defined target loaded from a template directory
+    pub fn add_template_target(&mut self, target: TemplateTransformer) {
+        self.transformers.add_template_target(target);
+    }
+
+    /// Add a target generated by a plugin executable
+    pub fn add_plugin(&mut self, plugin: PluginTransformer) {
+        self.transformers.add_plugin(plugin);
+    }
+
+    /// Run the pipeline on source code, stopping after `options.stop_after`
+    ///
+    /// Lexer and parser failures are returned as errors. Resolution and type
+    /// errors are reported as diagnostics in the output and stop the pipeline
+    /// before optimization.
+    pub fn run(&mut self, source: &str, options: &PipelineOptions, hooks: &mut dyn PipelineHooks) -> Result<PipelineOutput> {
+        let mut output = PipelineOutput::default();
 
-        // Type checking
-        let typed_ast = self.type_system.check(&ast)?;
+        hooks.before_stage(Stage::Lex);
+        let started = Instant::now();
+        let tokens = Lexer::new(source).tokenize()?;
+        output.tokens = Some(tokens.clone());
+        finish_stage(Stage::Lex, started, &mut output, hooks);
+        if options.stop_after == Stage::Lex {
+            return Ok(output);
+        }
 
-        // Optimization
-        let optimized_ast = self.optimizer.optimize(typed_ast)?;
+        hooks.before_stage(Stage::Parse);
+        let started = Instant::now();
+        let program = Parser::new(tokens).parse()?;
+        output.program = Some(program.clone());
+        finish_stage(Stage::Parse, started, &mut output, hooks);
+        if options.stop_after == Stage::Parse {
+            return Ok(output);
+        }
 
-        // Code generation
-        let transformer = self.transformers.get(target)
-            .ok_or_else(|| format!("Unknown target: {}", target))?;
-        
-        transformer.transform(&optimized_ast)
+        let rest = self.run_from_ast(program, options, hooks)?;
+        output.merge(rest);
+        Ok(output)
     }
 
-    /// Add a transformer for code generation
-    pub fn add_transformer(&mut self, name: String, transformer: Box<dyn Transformer>) {
-        self.transformers.insert(name, transformer);
+    /// Run the stages after parsing on an already parsed program, such as
+    /// one read with `Program::from_json`
+    pub fn run_from_ast(&mut self, program: Program, options: &PipelineOptions, hooks: &mut dyn PipelineHooks) -> Result<PipelineOutput> {
+        let mut output = PipelineOutput::default();
+        if options.stop_after <= Stage::Parse {
+            output.program = Some(program);
+            return Ok(output);
+        }
+
+        hooks.before_stage(Stage::Resolve);
+        let started = Instant::now();
+        output.diagnostics.extend(Resolver::new().resolve(&program));
+        output.program = Some(program);
+        finish_stage(Stage::Resolve, started, &mut output, hooks);
+        if options.stop_after == Stage::Resolve || output.has_errors() {
+            return Ok(output);
+        }
+
+        hooks.before_stage(Stage::TypeCheck);
+        let started = Instant::now();
+        let program = output.program.take().unwrap_or_else(|| Program::new(Span::new(0, 0, 1, 1)));
+        let (typed, diagnostics) = self.type_system.check_program(&program);
+        output.diagnostics.extend(diagnostics);
+        output.program = Some(typed);
+        finish_stage(Stage::TypeCheck, started, &mut output, hooks);
+        if options.stop_after == Stage::TypeCheck || output.has_errors() {
+            return Ok(output);
+        }
+
+        let program = output.program.take().unwrap_or_else(|| Program::new(Span::new(0, 0, 1, 1)));
+        let rest = self.optimize_and_generate(program, options, hooks)?;
+        output.merge(rest);
+        Ok(output)
+    }
+
+    /// Run the optimize and generate stages on a type-checked program
+    fn optimize_and_generate(&mut self, program: Program, options: &PipelineOptions, hooks: &mut dyn PipelineHooks) -> Result<PipelineOutput> {
+        let mut output = PipelineOutput::default();
+
+        hooks.before_stage(Stage::Optimize);
+        let started = Instant::now();
+        self.optimizer = Optimizer::from_config(&self.config)?;
+        self.optimizer.set_tail_calls(!self.transformers.eliminates_tail_calls(&options.target));
+        output.program = Some(self.optimizer.optimize(program)?);
+        output.optimization_stats = Some(self.optimizer.get_stats().clone());
+        output.pass_reports = self.optimizer.reports().to_vec();
+        output.diagnostics.extend(self.optimizer.diagnostics().iter().cloned());
+        finish_stage(Stage::Optimize, started, &mut output, hooks);
+        if options.stop_after == Stage::Optimize {
+            return Ok(output);
+        }
+
+        let generated = match &output.program {
+            Some(program) => self.generate(program, options, hooks)?,
+            None => return Ok(output),
+        };
+        output.merge(generated);
+        Ok(output)
+    }
+
+    /// Run only the generate stage on an optimized program, such as one that
+    /// holds just the units an incremental build regenerates
+    pub fn generate(&mut self, program: &Program, options: &PipelineOptions, hooks: &mut dyn PipelineHooks) -> Result<PipelineOutput> {
+        let mut output = PipelineOutput::default();
+
+        hooks.before_stage(Stage::Generate);
+        let started = Instant::now();
+        // Features the target lacks are reported before generation
+        let mut unsupported = self.transformers.check_capabilities(&options.target, program)
+            .unwrap_or_default();
+        if !options.allow_partial && !unsupported.is_empty() {
+            output.diagnostics.extend(unsupported);
+            finish_stage(Stage::Generate, started, &mut output, hooks);
+            return Ok(output);
+        }
+
+        let mut generated = self.transformers.transform(&options.target, program)?;
+        if options.bundle_runtime {
+            generated.bundle_runtime();
+        }
+        generated.diagnostics.splice(0..0, unsupported.drain(..));
+        // Placeholders make the output incomplete, which only partial builds accept
+        if options.allow_partial {
+            for diagnostic in &mut generated.diagnostics {
+                diagnostic.kind = DiagnosticKind::Warning;
+            }
+        }
+        output.diagnostics.extend(generated.diagnostics.iter().cloned());
+        output.code = Some(generated.code().to_string());
+        output.generated = Some(generated);
+        finish_stage(Stage::Generate, started, &mut output, hooks);
+
+        Ok(output)
+    }
+
+    /// Build one source for several targets
+    ///
+    /// Lexing, parsing, resolution and type checking run once; each target is
+    /// then optimized and generated on its own thread, since the optimizer
+    /// depends on the target. A target that fails does not stop the others.
+    /// `hooks` see the stages of every target, interleaved as the threads run.
+    pub fn run_targets(&mut self, source: &str, targets: &[String], options: &PipelineOptions, hooks: &mut (dyn PipelineHooks + Send)) -> Result<(PipelineOutput, Vec<TargetBuild>)> {
+        let checked = self.run(source, &options.clone().stop_after(Stage::TypeCheck), hooks)?;
+        Ok(self.build_targets(checked, targets, options, hooks))
+    }
+
+    /// Build an already parsed program for several targets, like `run_targets`
+    pub fn run_targets_from_ast(&mut self, program: Program, targets: &[String], options: &PipelineOptions, hooks: &mut (dyn PipelineHooks + Send)) -> Result<(PipelineOutput, Vec<TargetBuild>)> {
+        let checked = self.run_from_ast(program, &options.clone().stop_after(Stage::TypeCheck), hooks)?;
+        Ok(self.build_targets(checked, targets, options, hooks))
+    }
+
+    /// Optimize and generate a type-checked program for every target in parallel
+    fn build_targets(&self, checked: PipelineOutput, targets: &[String], options: &PipelineOptions, hooks: &mut (dyn PipelineHooks + Send)) -> (PipelineOutput, Vec<TargetBuild>) {
+        let program = match &checked.program {
+            Some(program) if !checked.has_errors() => program,
+            _ => return (checked, Vec::new()),
+        };
+
+        let config = &self.config;
+        let template_targets = self.transformers.template_targets();
+        let plugins = self.transformers.plugins();
+        let hooks = Mutex::new(hooks);
+        let builds = targets.par_iter()
+            .map(|target| {
+                let started = Instant::now();
+                let mut compiler = Compiler::new(config.clone());
+                for template_target in template_targets {
+                    compiler.add_template_target(template_target.clone());
+                }
+                for plugin in plugins {
+                    compiler.add_plugin(plugin.clone());
+                }
+                let mut target_options = options.clone().stop_after(Stage::Generate);
+                target_options.target = target.clone();
+                let output = compiler.optimize_and_generate(program.clone(), &target_options, &mut SharedHooks(&hooks))
+                    .map_err(|error| error.to_string());
+                TargetBuild { target: target.clone(), output, duration: started.elapsed() }
+            })
+            .collect();
+        (checked, builds)
     }
 
     /// Validate source code without compilation
@@ -162,7 +379,9 @@ impl Compiler {
         let mut parser = Parser::new(tokens);
         let ast = parser.parse()?;
 
-        self.type_system.validate(&ast)
+        let mut diagnostics = Resolver::new().resolve(&ast);
+        diagnostics.extend(self.type_system.validate(&ast)?);
+        Ok(diagnostics)
     }
 
     /// Analyze source code and return metadata
@@ -177,6 +396,21 @@ impl Compiler {
     }
 }
 
+fn finish_stage(stage: Stage, started: Instant, output: &mut PipelineOutput, hooks: &mut dyn PipelineHooks) {
+    let duration = started.elapsed();
+    output.timings.push(StageTiming { stage, duration });
+    hooks.after_stage(stage, duration, output);
+}
+
+/// Join the error diagnostics of a failed run into one message
+fn format_errors(diagnostics: &[Diagnostic]) -> String {
+    diagnostics.iter()
+        .filter(|d| d.kind == DiagnosticKind::Error)
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Utopia compiler version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
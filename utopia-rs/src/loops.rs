@@ -0,0 +1,653 @@
+//! Loop optimizations
+//!
+//! Recognizes counted loops such as `for (let i = 0; i < 8; i++)` with
+//! constant bounds and unrolls them: completely when the trip count is small,
+//! otherwise by a fixed factor with the leftover iterations peeled after the
+//! loop. Pure expressions whose operands do not change inside a loop are
+//! hoisted into temporaries declared before it, from the code that runs on
+//! every iteration only.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::*,
+    callgraph::CallGraph,
+    effects::{statement_effects, EffectAnalysis, Effects},
+    inliner::{body_size, declared_names, is_trivial, rename},
+    propagation::collect_assigned,
+};
+
+/// How far loops may be unrolled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopBudget {
+    /// Loops running at most this many times are unrolled completely
+    pub full_unroll_trips: usize,
+    /// Copies of the body per iteration of a partially unrolled loop; 1 disables it
+    pub unroll_factor: usize,
+    /// Largest unrolled body, in AST nodes
+    pub max_unrolled_size: usize,
+}
+
+impl LoopBudget {
+    /// -O2 fully unrolls up to 4 iterations and otherwise unrolls by 2, -O3 up to 8 and by 4
+    pub fn for_level(level: u8) -> Self {
+        match level {
+            0 | 1 => Self { full_unroll_trips: 4, unroll_factor: 1, max_unrolled_size: 32 },
+            2 => Self { full_unroll_trips: 4, unroll_factor: 2, max_unrolled_size: 64 },
+            _ => Self { full_unroll_trips: 8, unroll_factor: 4, max_unrolled_size: 128 },
+        }
+    }
+}
+
+impl Default for LoopBudget {
+    fn default() -> Self {
+        Self::for_level(2)
+    }
+}
+
+/// A `for` loop whose variable runs from one constant to another in constant steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountedLoop {
+    pub variable: String,
+    pub start: f64,
+    /// Bound from the condition
+    pub end: f64,
+    pub inclusive: bool,
+    /// Amount added each iteration, never zero
+    pub step: f64,
+}
+
+impl CountedLoop {
+    /// Recognize `for (let i = a; i < b; i++)` and its `<=`, `>`, `>=`, `i--` and `i = i ± k` variants
+    pub fn recognize(init: Option<&Statement>, condition: Option<&Expression>, update: Option<&Expression>, body: &[Statement]) -> Option<Self> {
+        let Some(Statement::VariableDeclaration { name: variable, value: Some(Expression::Literal { value: LiteralValue::Number(start), .. }), .. }) = init else {
+            return None;
+        };
+        let Some(Expression::Binary { left, operator, right, .. }) = condition else {
+            return None;
+        };
+        let (Expression::Identifier { name, .. }, Expression::Literal { value: LiteralValue::Number(end), .. }) = (left.as_ref(), right.as_ref()) else {
+            return None;
+        };
+        if name != variable {
+            return None;
+        }
+        let step = update.and_then(|update| step_of(variable, update))?;
+        let (ascending, inclusive) = match operator {
+            BinaryOperator::Less => (true, false),
+            BinaryOperator::LessEqual => (true, true),
+            BinaryOperator::Greater => (false, false),
+            BinaryOperator::GreaterEqual => (false, true),
+            _ => return None,
+        };
+        if ascending != (step > 0.0) || [*start, *end, step].iter().any(|value| value.fract() != 0.0) {
+            return None;
+        }
+
+        let mut assigned = HashSet::new();
+        collect_assigned(body, &mut assigned);
+        if assigned.contains(variable) {
+            return None;
+        }
+
+        Some(Self { variable: variable.clone(), start: *start, end: *end, inclusive, step })
+    }
+
+    /// Number of times the body runs
+    pub fn trip_count(&self) -> usize {
+        let distance = (self.end - self.start) / self.step;
+        let trips = if self.inclusive { distance.floor() + 1.0 } else { distance.ceil() };
+        if trips > 0.0 { trips as usize } else { 0 }
+    }
+
+    /// Value of the loop variable in iteration `k`
+    pub fn value(&self, k: usize) -> f64 {
+        self.start + k as f64 * self.step
+    }
+}
+
+/// Amount `update` adds to `variable`: `i++`, `i--`, `i = i + k` or `i = i - k`
+fn step_of(variable: &str, update: &Expression) -> Option<f64> {
+    match update {
+        Expression::Postfix { operand, operator, .. } => match operand.as_ref() {
+            Expression::Identifier { name, .. } if name == variable => Some(match operator {
+                PostfixOperator::Increment => 1.0,
+                PostfixOperator::Decrement => -1.0,
+            }),
+            _ => None,
+        },
+        Expression::Assignment { target, value, .. } => {
+            let (Expression::Identifier { name, .. }, Expression::Binary { left, operator, right, .. }) = (target.as_ref(), value.as_ref()) else {
+                return None;
+            };
+            let (Expression::Identifier { name: operand, .. }, Expression::Literal { value: LiteralValue::Number(amount), .. }) = (left.as_ref(), right.as_ref()) else {
+                return None;
+            };
+            if name != variable || operand != variable || *amount <= 0.0 {
+                return None;
+            }
+            match operator {
+                BinaryOperator::Add => Some(*amount),
+                BinaryOperator::Subtract => Some(-amount),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Hoists invariant expressions out of loops and unrolls counted loops
+pub struct LoopOptimizer {
+    budget: LoopBudget,
+    next_id: usize,
+    pub unrolled: usize,
+    pub hoisted: usize,
+}
+
+impl LoopOptimizer {
+    pub fn new(budget: LoopBudget) -> Self {
+        Self { budget, next_id: 0, unrolled: 0, hoisted: 0 }
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        let effects = EffectAnalysis::analyze(program, &CallGraph::build(program));
+        for block in &mut program.language_blocks {
+            let functions = effects.callable_from(&block.language);
+            for function in &mut block.functions {
+                self.optimize(&mut function.body, &functions);
+            }
+            self.optimize(&mut block.statements, &functions);
+        }
+        let functions = effects.callable_from("global");
+        self.optimize(&mut program.global_statements, &functions);
+    }
+
+    fn optimize(&mut self, statements: &mut Vec<Statement>, functions: &HashMap<String, Effects>) {
+        let mut i = 0;
+        while i < statements.len() {
+            if matches!(statements[i], Statement::For { .. } | Statement::While { .. }) {
+                let hoisted = self.hoist_invariants(&mut statements[i], functions);
+                let count = hoisted.len();
+                statements.splice(i..i, hoisted);
+                i += count;
+            }
+
+            match &mut statements[i] {
+                Statement::If { then_branch, else_branch, .. } => {
+                    self.optimize(then_branch, functions);
+                    if let Some(else_branch) = else_branch {
+                        self.optimize(else_branch, functions);
+                    }
+                }
+                Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } | Statement::FunctionDeclaration { body, .. } => {
+                    self.optimize(body, functions);
+                }
+                _ => {}
+            }
+
+            if let Some(unrolled) = self.unroll(&statements[i]) {
+                let count = unrolled.len();
+                statements.splice(i..=i, unrolled);
+                i += count;
+                continue;
+            }
+            i += 1;
+        }
+    }
+
+    /// Replace invariant expressions in a loop with temporaries, returning their declarations
+    fn hoist_invariants(&mut self, looping: &mut Statement, functions: &HashMap<String, Effects>) -> Vec<Statement> {
+        let mut variant = HashSet::new();
+        let loop_statements = std::slice::from_ref(&*looping);
+        collect_assigned(loop_statements, &mut variant);
+        match &*looping {
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    declared_names(std::slice::from_ref(init.as_ref()), &mut variant);
+                }
+                declared_names(body, &mut variant);
+            }
+            Statement::While { body, .. } => declared_names(body, &mut variant),
+            _ => {}
+        }
+
+        // Functions called in the loop change what they write
+        let effects = statement_effects(std::slice::from_ref(&*looping), functions);
+        variant.extend(effects.writes);
+        let writes_memory = effects.writes_memory || effects.io;
+
+        let mut hoister = Hoister { variant: &variant, writes_memory, functions, hoisted: Vec::new(), next_id: &mut self.next_id };
+        // The condition runs on entry and on every iteration; the update only
+        // after an iteration completes, so nothing is hoisted out of it
+        let (guard, body) = match looping {
+            Statement::For { init, condition, update, body, .. } => {
+                if let Some(condition) = condition {
+                    hoister.expression(condition);
+                }
+                let guard = match CountedLoop::recognize(init.as_deref(), condition.as_ref(), update.as_ref(), body) {
+                    Some(counted) if counted.trip_count() > 0 => None,
+                    _ => condition.as_ref().map(|condition| hoister.entry_condition(init.as_deref(), condition)),
+                };
+                (guard, body)
+            }
+            Statement::While { condition, body, .. } => {
+                hoister.expression(condition);
+                (Some(hoister.entry_condition(None, condition)), body)
+            }
+            _ => return Vec::new(),
+        };
+        let mut hoisted = std::mem::take(&mut hoister.hoisted);
+
+        // Body temporaries are only computed when the body runs at least once;
+        // `guard` is `None` when it always does
+        match guard {
+            None => {
+                hoister.statements(body);
+                hoisted.append(&mut hoister.hoisted);
+            }
+            Some(Some(guard)) => {
+                hoister.statements(body);
+                let span = guard.span();
+                let mut assignments = Vec::new();
+                for declaration in std::mem::take(&mut hoister.hoisted) {
+                    let Statement::VariableDeclaration { name, value: Some(value), .. } = declaration else { continue };
+                    hoisted.push(Statement::VariableDeclaration { name: name.clone(), value: None, var_type: None, is_const: false, span });
+                    assignments.push(Statement::Assignment { target: Expression::Identifier { name, resolved_type: None, span }, value, span });
+                }
+                if !assignments.is_empty() {
+                    hoisted.push(Statement::If { condition: guard, then_branch: assignments, else_branch: None, span });
+                }
+            }
+            // Whether the body runs cannot be checked without side effects
+            Some(None) => {}
+        }
+        self.hoisted += hoisted.iter().filter(|statement| matches!(statement, Statement::VariableDeclaration { .. })).count();
+        if !hoisted.is_empty() {
+            log::debug!("Hoisted {} invariant expressions out of the loop at line {}", hoisted.len(), looping.span().line);
+        }
+        hoisted
+    }
+
+    /// Unroll a counted loop completely, or partially with the remainder peeled off
+    fn unroll(&mut self, looping: &Statement) -> Option<Vec<Statement>> {
+        let Statement::For { init, condition, update, body, span } = looping else {
+            return None;
+        };
+        let counted = CountedLoop::recognize(init.as_deref(), condition.as_ref(), update.as_ref(), body)?;
+        let declares_functions = body.iter().any(|statement| {
+            matches!(statement, Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. })
+        });
+        if declares_functions {
+            return None;
+        }
+
+        let trips = counted.trip_count();
+        let size = body_size(body).max(1);
+        let id = self.next_id;
+        self.next_id += 1;
+
+        if trips <= self.budget.full_unroll_trips && trips * size <= self.budget.max_unrolled_size {
+            let mut unrolled = Vec::new();
+            for k in 0..trips {
+                let value = Expression::Literal { value: LiteralValue::Number(counted.value(k)), span: *span };
+                unrolled.extend(self.copy_body(body, &counted.variable, value, &format!("__unroll{id}_{k}_")));
+            }
+            self.unrolled += 1;
+            log::debug!("Unrolled the {}-iteration loop at line {}", trips, span.line);
+            return Some(unrolled);
+        }
+
+        let factor = self.budget.unroll_factor;
+        if factor < 2 || trips < factor * 2 || factor * size > self.budget.max_unrolled_size {
+            return None;
+        }
+
+        let main_trips = trips - trips % factor;
+        let variable = || Expression::Identifier { name: counted.variable.clone(), resolved_type: None, span: *span };
+        let number = |value: f64| Expression::Literal { value: LiteralValue::Number(value), span: *span };
+
+        let mut unrolled_body = Vec::new();
+        for k in 0..factor {
+            let offset = k as f64 * counted.step;
+            if k == 0 {
+                unrolled_body.extend(body.iter().cloned());
+                continue;
+            }
+            let (operator, amount) = if offset > 0.0 { (BinaryOperator::Add, offset) } else { (BinaryOperator::Subtract, -offset) };
+            let value = Expression::Binary { left: Box::new(variable()), operator, right: Box::new(number(amount)), span: *span };
+            unrolled_body.extend(self.copy_body(body, &counted.variable, value, &format!("__unroll{id}_{k}_")));
+        }
+
+        let (comparison, stride_operator) = if counted.step > 0.0 {
+            (BinaryOperator::Less, BinaryOperator::Add)
+        } else {
+            (BinaryOperator::Greater, BinaryOperator::Subtract)
+        };
+        let main_end = counted.value(main_trips);
+        let stride = (factor as f64 * counted.step).abs();
+        let mut unrolled = vec![Statement::For {
+            init: init.clone(),
+            condition: Some(Expression::Binary { left: Box::new(variable()), operator: comparison, right: Box::new(number(main_end)), span: *span }),
+            update: Some(Expression::Assignment {
+                target: Box::new(variable()),
+                value: Box::new(Expression::Binary { left: Box::new(variable()), operator: stride_operator, right: Box::new(number(stride)), span: *span }),
+                span: *span,
+            }),
+            body: unrolled_body,
+            span: *span,
+        }];
+        for k in main_trips..trips {
+            unrolled.extend(self.copy_body(body, &counted.variable, number(counted.value(k)), &format!("__unroll{id}_{k}_")));
+        }
+        self.unrolled += 1;
+        log::debug!("Unrolled the loop at line {} by {} with {} peeled iterations", span.line, factor, trips - main_trips);
+        Some(unrolled)
+    }
+
+    /// A copy of `body` for one iteration, with the loop variable replaced and locals renamed
+    fn copy_body(&self, body: &[Statement], variable: &str, value: Expression, prefix: &str) -> Vec<Statement> {
+        let mut locals = HashSet::new();
+        declared_names(body, &mut locals);
+        let mut replacements: HashMap<String, Expression> = locals.into_iter()
+            .map(|local| {
+                let renamed = Expression::Identifier { name: format!("{prefix}{local}"), resolved_type: None, span: value.span() };
+                (local, renamed)
+            })
+            .collect();
+        replacements.insert(variable.to_string(), value);
+
+        let mut copy = body.to_vec();
+        rename(&mut copy, &replacements, prefix);
+        copy
+    }
+}
+
+/// Moves loop-invariant expressions into temporaries
+struct Hoister<'a> {
+    variant: &'a HashSet<String>,
+    /// The loop may change fields or elements, or call code that might
+    writes_memory: bool,
+    functions: &'a HashMap<String, Effects>,
+    hoisted: Vec<Statement>,
+    next_id: &'a mut usize,
+}
+
+impl Hoister<'_> {
+    /// Hoist out of the statements that run on every iteration, up to the
+    /// first branch, nested loop or return; returns whether the statements
+    /// after them run on every iteration too
+    fn statements(&mut self, statements: &mut [Statement]) -> bool {
+        for statement in statements {
+            let continues = match statement {
+                Statement::Expression { expression, .. } => {
+                    self.expression(expression);
+                    true
+                }
+                Statement::VariableDeclaration { value: Some(value), .. } => {
+                    self.expression(value);
+                    true
+                }
+                Statement::Assignment { target, value, .. } => {
+                    self.operands(target);
+                    self.expression(value);
+                    true
+                }
+                Statement::Block { statements, .. } => self.statements(statements),
+                Statement::If { .. } | Statement::While { .. } | Statement::For { .. } | Statement::Return { .. } => false,
+                _ => true,
+            };
+            if !continues {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The condition under which a loop runs its body at least once, or
+    /// `None` if it cannot be checked without side effects
+    fn entry_condition(&self, init: Option<&Statement>, condition: &Expression) -> Option<Expression> {
+        if !self.is_side_effect_free(condition) {
+            return None;
+        }
+        let (name, value) = match init {
+            None => return Some(condition.clone()),
+            Some(
+                Statement::VariableDeclaration { name, value: Some(value), .. }
+                | Statement::Assignment { target: Expression::Identifier { name, .. }, value, .. },
+            ) => (name, value),
+            Some(_) => return None,
+        };
+        if !self.is_side_effect_free(value) {
+            return None;
+        }
+        // The condition as first checked, right after the initializer
+        let mut entry = condition.clone();
+        entry.walk_mut(&mut |expression| {
+            if matches!(expression, Expression::Identifier { name: used, .. } if used == name) {
+                *expression = value.clone();
+            }
+        });
+        Some(entry)
+    }
+
+    fn is_side_effect_free(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal { .. } | Expression::Identifier { .. } => true,
+            Expression::Binary { left, right, .. } => self.is_side_effect_free(left) && self.is_side_effect_free(right),
+            Expression::Unary { operand, .. } | Expression::MemberAccess { object: operand, .. } => self.is_side_effect_free(operand),
+            Expression::ArrayAccess { array, index, .. } => self.is_side_effect_free(array) && self.is_side_effect_free(index),
+            Expression::Call { callee, arguments, .. } => {
+                matches!(callee.as_ref(), Expression::Identifier { name, .. } if self.functions.get(name).is_some_and(Effects::is_read_only))
+                    && arguments.iter().all(|argument| self.is_side_effect_free(argument))
+            }
+            _ => false,
+        }
+    }
+
+    /// Hoist `expression` if it is invariant, otherwise look inside it
+    fn expression(&mut self, expression: &mut Expression) {
+        if self.is_hoistable(expression) {
+            let span = expression.span();
+            let name = format!("__licm{}", self.next_id);
+            *self.next_id += 1;
+            let value = std::mem::replace(expression, Expression::Identifier { name: name.clone(), resolved_type: None, span });
+            self.hoisted.push(Statement::VariableDeclaration { name, value: Some(value), var_type: None, is_const: true, span });
+            return;
+        }
+        self.operands(expression);
+    }
+
+    fn operands(&mut self, expression: &mut Expression) {
+        match expression {
+            // The right operand of `&&` and `||` does not always run
+            Expression::Binary { left, operator: BinaryOperator::And | BinaryOperator::Or, .. } => self.expression(left),
+            Expression::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Unary { operand, .. } => self.expression(operand),
+            Expression::Assignment { target, value, .. } => {
+                self.operands(target);
+                self.expression(value);
+            }
+            Expression::Call { arguments, .. } | Expression::CrossCall { arguments, .. } | Expression::Array { elements: arguments, .. } => {
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expression::MemberAccess { object, .. } => self.expression(object),
+            Expression::ArrayAccess { array, index, .. } => {
+                self.expression(array);
+                self.expression(index);
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values_mut() {
+                    self.expression(value);
+                }
+            }
+            Expression::Literal { .. } | Expression::Identifier { .. } | Expression::Postfix { .. } | Expression::Lambda { .. } => {}
+        }
+    }
+
+    fn is_hoistable(&self, expression: &Expression) -> bool {
+        let mut reads_variable = false;
+        !is_trivial(expression) && self.is_invariant(expression, &mut reads_variable) && reads_variable
+    }
+
+    fn is_invariant(&self, expression: &Expression, reads_variable: &mut bool) -> bool {
+        match expression {
+            Expression::Literal { .. } => true,
+            Expression::Identifier { name, .. } => {
+                *reads_variable = true;
+                !self.variant.contains(name)
+            }
+            Expression::Binary { left, right, .. } => {
+                self.is_invariant(left, reads_variable) && self.is_invariant(right, reads_variable)
+            }
+            Expression::Unary { operand, .. } => self.is_invariant(operand, reads_variable),
+            Expression::Call { callee, arguments, .. } => {
+                let invariant = match callee.as_ref() {
+                    Expression::Identifier { name, .. } => self.functions.get(name).is_some_and(|effects| {
+                        effects.is_read_only()
+                            && effects.reads.iter().all(|read| !self.variant.contains(read))
+                            && !(effects.reads_memory && self.writes_memory)
+                    }),
+                    _ => false,
+                };
+                *reads_variable |= invariant;
+                invariant && arguments.iter().all(|argument| self.is_invariant(argument, reads_variable))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::PrettyPrinter, interpreter::Interpreter};
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    /// Optimize `source`, check it prints the same before and after, and return the optimized program
+    fn optimize_preserving_output(source: &str, budget: LoopBudget) -> (String, LoopOptimizer) {
+        let original = parse(source);
+        let expected = Interpreter::new().run(&original).unwrap();
+
+        let mut optimized = original.clone();
+        let mut loops = LoopOptimizer::new(budget);
+        loops.run(&mut optimized);
+        let actual = Interpreter::new().run(&optimized).unwrap();
+
+        let printed = PrettyPrinter::new().print(&optimized);
+        assert_eq!(expected, actual, "{}", printed);
+        (printed, loops)
+    }
+
+    #[test]
+    fn test_recognizes_counted_loops() {
+        let program = parse("for (let i = 10; i >= 1; i = i - 3) {\n    print(i);\n}\nfor (let i = 0; i < n; i++) {\n}\n");
+        let Statement::For { init, condition, update, body, .. } = &program.global_statements[0] else {
+            panic!("expected a for loop");
+        };
+        let counted = CountedLoop::recognize(init.as_deref(), condition.as_ref(), update.as_ref(), body).unwrap();
+        assert_eq!(counted.step, -3.0);
+        assert_eq!(counted.trip_count(), 4);
+
+        let Statement::For { init, condition, update, body, .. } = &program.global_statements[1] else {
+            panic!("expected a for loop");
+        };
+        assert!(CountedLoop::recognize(init.as_deref(), condition.as_ref(), update.as_ref(), body).is_none());
+    }
+
+    #[test]
+    fn test_full_unrolling() {
+        let (output, loops) = optimize_preserving_output(
+            "let total = 0;\nfor (let i = 0; i < 3; i++) {\n    let square = i * i;\n    total = total + square;\n}\nprint(total);\n",
+            LoopBudget::for_level(2),
+        );
+        assert_eq!(loops.unrolled, 1);
+        assert!(!output.contains("for ("), "{}", output);
+        assert!(output.contains("let __unroll0_2_square = 2 * 2;"), "{}", output);
+    }
+
+    #[test]
+    fn test_partial_unrolling_with_remainder() {
+        for source in [
+            "let total = 0;\nfor (let i = 0; i < 11; i++) {\n    total = total + i;\n    print(i);\n}\nprint(total);\n",
+            "let total = 0;\nfor (let i = 20; i >= 1; i = i - 2) {\n    let half = i / 2;\n    total = total + half;\n}\nprint(total);\n",
+            "let total = 0;\nfor (let i = 1; i <= 9; i++) {\n    total = total * 2 + i;\n}\nprint(total);\n",
+        ] {
+            let (output, loops) = optimize_preserving_output(source, LoopBudget::for_level(3));
+            assert_eq!(loops.unrolled, 1, "{}", output);
+        }
+
+        let (output, _) = optimize_preserving_output(
+            "let total = 0;\nfor (let i = 0; i < 11; i++) {\n    total = total + i;\n}\nprint(total);\n",
+            LoopBudget::for_level(3),
+        );
+        assert!(output.contains("for (let i = 0; i < 8; i = i + 4)"), "{}", output);
+        assert!(output.contains("total = total + (i + 3);"), "{}", output);
+        assert!(output.contains("total = total + 10;"), "{}", output);
+    }
+
+    #[test]
+    fn test_hoists_pure_invariant_expressions() {
+        let (output, loops) = optimize_preserving_output(
+            "function scale(x) {\n    return x * 10;\n}\nfunction report(x) {\n    print(x);\n    return x;\n}\n\
+             let n = 7;\nlet total = 0;\nlet i = 0;\nwhile (i < n + 5) {\n    total = total + scale(n - 1) * i + report(n);\n    i = i + 1;\n}\nprint(total);\n",
+            LoopBudget::for_level(2),
+        );
+        assert_eq!(loops.hoisted, 2, "{}", output);
+        assert!(output.contains("const __licm0 = n + 5;"), "{}", output);
+        assert!(output.contains("if (i < __licm0) {\n    __licm1 = scale(n - 1);\n  }"), "{}", output);
+        assert!(output.contains("report(n)"), "{}", output);
+        assert!(output.contains("i = i + 1;"), "{}", output);
+    }
+
+    #[test]
+    fn test_hoists_calls_reading_unchanged_globals() {
+        let (output, loops) = optimize_preserving_output(
+            "let rate = 3;\nfunction cost(x) {\n    return x * rate;\n}\nfunction bump() {\n    rate = rate + 1;\n}\n\
+             let a = 0;\nlet i = 0;\nwhile (i < 3) {\n    a = a + cost(2);\n    i = i + 1;\n}\n\
+             let j = 0;\nwhile (j < 3) {\n    a = a + cost(2);\n    bump();\n    j = j + 1;\n}\nprint(a, rate);\n",
+            LoopBudget::for_level(2),
+        );
+        // The second loop changes `rate` through `bump`, so `cost(2)` differs between iterations
+        assert_eq!(loops.hoisted, 1, "{}", output);
+        assert!(output.contains("if (i < 3) {\n    __licm0 = cost(2);\n  }"), "{}", output);
+        assert!(output.contains("a = a + cost(2);"), "{}", output);
+    }
+
+    #[test]
+    fn test_guarded_code_stays_guarded() {
+        // `first([])` fails, so it must only run where the original program runs it
+        let (output, loops) = optimize_preserving_output(
+            "function first(xs) {\n    return xs[0];\n}\n\
+             function run(d, xs) {\n    let i = 0;\n    let acc = 0;\n    while (i < 3) {\n        if (d != 0) {\n            acc = acc + 10 / d + first(xs);\n        }\n        i = i + 1;\n    }\n\
+             while (d != 0 && first(xs) > i) {\n        i = i + 1;\n    }\n    return acc;\n}\nprint(run(0, []));\n\
+             let n = 0;\nlet empty = [];\nlet total = 0;\nlet j = 0;\nwhile (j < n) {\n    total = total + first(empty) * 2;\n    j = j + 1;\n}\n\
+             for (let k = 0; k < n; k++) {\n    total = total + first(empty);\n}\nprint(total);\n",
+            LoopBudget::for_level(2),
+        );
+        assert!(output.contains("acc = acc + 10 / d + first(xs);"), "{}", output);
+        assert!(output.contains("const __licm0 = d != 0;"), "{}", output);
+        assert!(output.contains("while (__licm0 && first(xs) > i)"), "{}", output);
+        // Temporaries of loops that may not run are computed only if they do
+        assert!(output.contains("if (j < n) {\n    __licm1 = first(empty) * 2;\n  }"), "{}", output);
+        assert!(output.contains("if (0 < n) {\n    __licm2 = first(empty);\n  }"), "{}", output);
+        assert_eq!(loops.hoisted, 3, "{}", output);
+    }
+
+    #[test]
+    fn test_nested_loops_and_early_returns() {
+        optimize_preserving_output(
+            "function find(limit) {\n    for (let i = 0; i < 6; i++) {\n        for (let j = 0; j < 3; j++) {\n            if (i * j > limit) {\n                return i * 10 + j;\n            }\n        }\n    }\n    return -1;\n}\n\
+             print(find(4), find(100));\n",
+            LoopBudget::for_level(3),
+        );
+    }
+}
@@ -94,15 +94,23 @@ fn main() {
         return;
     }
     
-    // Debug mode for full compilation
-    if std::env::args().any(|arg| arg == "--debug-compile") {
-        let input = "debug_for_loop.uto";
-        println!("Testing full compilation of: {}", input);
-        match utopia::cli::run_cli() {
-            Ok(_) => println!("Compilation successful"),
+    // Debug mode for full compilation: utopia --debug-compile <file> [--target <target>]
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--debug-compile") {
+        let Some(input) = args.get(position + 1) else {
+            eprintln!("{}", "Usage: utopia --debug-compile <file> [--target <target>]".bright_red());
+            std::process::exit(2);
+        };
+        let target = args.iter().position(|arg| arg == "--target")
+            .and_then(|index| args.get(index + 1))
+            .map_or("python", String::as_str);
+        println!("Testing full compilation of: {} (target: {})", input, target);
+        match utopia::cli::debug_compile(input, target) {
+            Ok(()) => println!("Compilation successful"),
             Err(e) => {
                 println!("Compilation failed with error: {}", e);
                 println!("Full error details: {:?}", e);
+                std::process::exit(1);
             }
         }
         return;
@@ -0,0 +1,276 @@
+//! Escaping of identifiers that are reserved words in the target
+//!
+//! Names such as `end`, `def`, `type` or `select` are ordinary identifiers in
+//! Utopia but keywords in Ruby, Python, Rust or SQL. Before a backend runs,
+//! every name the program declares that its target reserves is renamed with
+//! the target's `Keywords` escape (`end_`, `r#type`, `@class`, `` `when` ``),
+//! at its declaration and at every use, so backends can write identifiers as
+//! they find them. Member names and object keys are left alone.
+
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::*,
+    emitter::{Escape, Keywords},
+    Span,
+};
+
+/// A declared name that was escaped for the target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename {
+    pub name: String,
+    pub escaped: String,
+    /// Where the name is first declared
+    pub span: Span,
+}
+
+/// The renames `keywords` requires in `program`, in declaration order
+pub fn reserved_renames(program: &Program, keywords: Keywords) -> Vec<Rename> {
+    let declared = declared_names(program);
+    declared.iter()
+        .filter(|(name, _)| keywords.is_reserved(name))
+        .map(|(name, span)| {
+            let mut escaped = keywords.escape(name).into_owned();
+            // `end_` may already be a name of its own
+            if let Escape::Suffix(suffix) = keywords.escape {
+                while declared.contains_key(&escaped) || keywords.is_reserved(&escaped) {
+                    escaped.push_str(suffix);
+                }
+            }
+            Rename { name: name.clone(), escaped, span: *span }
+        })
+        .collect()
+}
+
+/// Apply `renames` to every declaration and use of the names in `program`
+pub fn apply_renames(program: &mut Program, renames: &[Rename]) {
+    if renames.is_empty() {
+        return;
+    }
+    let renames: HashMap<&str, &str> = renames.iter()
+        .map(|rename| (rename.name.as_str(), rename.escaped.as_str()))
+        .collect();
+
+    for block in &mut program.language_blocks {
+        for function in &mut block.functions {
+            rename_function(function, &renames);
+        }
+        rename_statements(&mut block.statements, &renames);
+    }
+    rename_statements(&mut program.global_statements, &renames);
+
+    let metadata = &mut program.metadata;
+    for function in &mut metadata.functions {
+        rename(&mut function.name, &renames);
+        rename_parameters(&mut function.parameters, &renames);
+    }
+    for call in &mut metadata.cross_calls {
+        rename(&mut call.function_name, &renames);
+    }
+    for export in &mut metadata.exports {
+        rename(export, &renames);
+    }
+}
+
+/// Every name the program declares, with its first declaration
+fn declared_names(program: &Program) -> IndexMap<String, Span> {
+    let mut declared = IndexMap::new();
+    for block in &program.language_blocks {
+        for function in &block.functions {
+            declare_function(function, &mut declared);
+        }
+        declare_statements(&block.statements, &mut declared);
+    }
+    declare_statements(&program.global_statements, &mut declared);
+
+    // Lambdas can appear in any expression
+    let statements = program.language_blocks.iter()
+        .flat_map(|block| block.functions.iter().flat_map(|function| &function.body).chain(&block.statements))
+        .chain(&program.global_statements);
+    for statement in statements {
+        statement.walk_expressions(&mut |expression| {
+            if let Expression::Lambda { parameters, body, .. } = expression {
+                declare_parameters(parameters, &mut declared);
+                declare_statements(body, &mut declared);
+            }
+        });
+    }
+    declared
+}
+
+fn declare(name: &str, span: Span, declared: &mut IndexMap<String, Span>) {
+    declared.entry(name.to_string()).or_insert(span);
+}
+
+fn declare_parameters(parameters: &[Parameter], declared: &mut IndexMap<String, Span>) {
+    for parameter in parameters {
+        declare(&parameter.name, parameter.span, declared);
+    }
+}
+
+fn declare_function(function: &Function, declared: &mut IndexMap<String, Span>) {
+    declare(&function.name, function.span, declared);
+    declare_parameters(&function.parameters, declared);
+    declare_statements(&function.body, declared);
+}
+
+/// Declarations in `statements` and the statements nested in them
+fn declare_statements(statements: &[Statement], declared: &mut IndexMap<String, Span>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, span, .. } => declare(name, *span, declared),
+            Statement::FunctionDeclaration { name, parameters, body, span, .. } => {
+                declare(name, *span, declared);
+                declare_parameters(parameters, declared);
+                declare_statements(body, declared);
+            }
+            Statement::ClassDeclaration { name, methods, span, .. } => {
+                // Methods are members, but their parameters and locals are names
+                declare(name, *span, declared);
+                for method in methods {
+                    declare_parameters(&method.parameters, declared);
+                    declare_statements(&method.body, declared);
+                }
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                declare_statements(then_branch, declared);
+                declare_statements(else_branch.as_deref().unwrap_or_default(), declared);
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    declare_statements(std::slice::from_ref(init.as_ref()), declared);
+                }
+                declare_statements(body, declared);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => declare_statements(body, declared),
+            _ => {}
+        }
+    }
+}
+
+fn rename(name: &mut String, renames: &HashMap<&str, &str>) {
+    if let Some(escaped) = renames.get(name.as_str()) {
+        *name = escaped.to_string();
+    }
+}
+
+fn rename_parameters(parameters: &mut [Parameter], renames: &HashMap<&str, &str>) {
+    for parameter in parameters {
+        rename(&mut parameter.name, renames);
+    }
+}
+
+fn rename_function(function: &mut Function, renames: &HashMap<&str, &str>) {
+    rename(&mut function.name, renames);
+    rename_parameters(&mut function.parameters, renames);
+    rename_statements(&mut function.body, renames);
+}
+
+/// Rename the declarations in `statements` and every use in their expressions
+fn rename_statements(statements: &mut [Statement], renames: &HashMap<&str, &str>) {
+    rename_declarations(statements, renames);
+    for statement in statements {
+        statement.walk_expressions_mut(&mut |expression| match expression {
+            Expression::Identifier { name, .. } | Expression::CrossCall { function: name, .. } => rename(name, renames),
+            Expression::Lambda { parameters, body, .. } => {
+                rename_parameters(parameters, renames);
+                rename_declarations(body, renames);
+            }
+            _ => {}
+        });
+    }
+}
+
+/// Rename the declarations in `statements` and the statements nested in them
+fn rename_declarations(statements: &mut [Statement], renames: &HashMap<&str, &str>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, .. } | Statement::Export { item: name, .. } => rename(name, renames),
+            Statement::FunctionDeclaration { name, parameters, body, .. } => {
+                rename(name, renames);
+                rename_parameters(parameters, renames);
+                rename_declarations(body, renames);
+            }
+            Statement::ClassDeclaration { name, superclass, methods, .. } => {
+                rename(name, renames);
+                if let Some(superclass) = superclass {
+                    rename(superclass, renames);
+                }
+                for method in methods {
+                    rename_parameters(&mut method.parameters, renames);
+                    rename_declarations(&mut method.body, renames);
+                }
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                rename_declarations(then_branch, renames);
+                if let Some(else_branch) = else_branch {
+                    rename_declarations(else_branch, renames);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    rename_declarations(std::slice::from_mut(init.as_mut()), renames);
+                }
+                rename_declarations(body, renames);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => rename_declarations(body, renames),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast::PrettyPrinter, lexer::Lexer, parser::Parser, transformers::TransformerManager};
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_renames_declarations_and_uses() {
+        let mut program = parse(r#"
+            function def(end, end_) {
+                let obj = { end: end };
+                return obj.end + end_;
+            }
+            let lambda = def(1, 2);
+            print(lambda);
+        "#);
+        let keywords = Keywords::new(&["def", "end", "lambda", "print"], Escape::Suffix("_"));
+        let renames = reserved_renames(&program, keywords);
+        let escaped: Vec<_> = renames.iter().map(|rename| (rename.name.as_str(), rename.escaped.as_str())).collect();
+        // `end_` is taken, and `print` is never declared so it stays a builtin
+        assert_eq!(escaped, [("def", "def_"), ("end", "end__"), ("lambda", "lambda_")]);
+
+        apply_renames(&mut program, &renames);
+        let printed = PrettyPrinter::new().print(&program);
+        assert!(printed.contains("function def_(end__, end_)"), "{}", printed);
+        assert!(printed.contains("end: end__"), "{}", printed);
+        assert!(printed.contains("obj.end + end_"), "{}", printed);
+        assert!(printed.contains("let lambda_ = def_(1, 2);"), "{}", printed);
+        assert!(printed.contains("print(lambda_)"), "{}", printed);
+    }
+
+    #[test]
+    fn test_targets_escape_reserved_words() {
+        let program = parse("let type = 1;\nlet end = type + 1;\nlet val = end;\nprint(val);\n");
+        let manager = TransformerManager::new();
+
+        let ruby = manager.transform("ruby", &program).unwrap();
+        assert!(ruby.code().contains("end_ = (type + 1)"), "{}", ruby.code());
+        assert_eq!(ruby.renames.len(), 1);
+        assert_eq!((ruby.renames[0].span.line, ruby.renames[0].span.column), (2, 1));
+
+        let function = parse("@lang rust {\n    function type(x) {\n        return x;\n    }\n}\n");
+        let rust = manager.transform("rust", &function).unwrap();
+        assert!(rust.code().contains("pub fn r#type("), "{}", rust.code());
+        let kotlin = manager.transform("kotlin", &program).unwrap();
+        assert!(kotlin.code().contains("`val`"), "{}", kotlin.code());
+        assert!(manager.transform("python", &program).unwrap().renames.is_empty());
+    }
+}
@@ -0,0 +1,468 @@
+//! Utopia operator semantics and how each target spells them
+//!
+//! Binary operators mean what the interpreter does with them: `+` adds
+//! numbers and concatenates as soon as one side is a string, `/` always
+//! divides as floating point, `%` keeps the sign of the dividend, `==`
+//! compares without coercion and `&&`/`||` short-circuit, yielding the
+//! operand that decided the result. Targets write these differently, so each
+//! backend describes its spelling with an `Operators` table and lowers every
+//! binary expression through `Operators::binary`, which picks the numeric or
+//! string form from the operand types and adds the parentheses the target
+//! needs.
+
+use crate::{ast::*, builtins, transformers::unsupported_expression, types::Type, Result};
+
+/// How a target writes one operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    /// `left op right`, parenthesized by precedence
+    Infix(&'static str),
+    /// A template over `{l}` and `{r}` that reads as a single operand, such
+    /// as a function call or a prefix form
+    Call(&'static str),
+    /// A template over `{l}` and `{r}` that is parenthesized wherever an
+    /// infix expression would need to be
+    Template(&'static str),
+    /// The target has no translation; the expression is reported
+    Unsupported,
+}
+
+/// The binary operators of a target
+#[derive(Debug, Clone, Copy)]
+pub struct Operators {
+    /// Forms for numeric operands, in `BinaryOperator` order
+    forms: [Form; 13],
+    /// `+` when either operand is a string
+    concat: Form,
+    /// Converts an operand of `concat` that is not a string, `{}` standing for the operand
+    to_string: Option<&'static str>,
+    /// `+` when an operand's type is unknown, for targets whose `+` does not
+    /// concatenate a string with a number
+    untyped_add: Option<Form>,
+    /// `==` to `>=` when either operand is a string
+    strings: Option<[Form; 6]>,
+    /// Whether every lowered operator is wrapped in parentheses
+    parenthesized: bool,
+    /// Emitted in place of an `Unsupported` operator
+    placeholder: &'static str,
+}
+
+impl Operators {
+    /// Infix operators spelled as given, in `BinaryOperator` order
+    pub const fn infix(spellings: [&'static str; 13]) -> Self {
+        let mut forms = [Form::Unsupported; 13];
+        let mut i = 0;
+        while i < forms.len() {
+            forms[i] = Form::Infix(spellings[i]);
+            i += 1;
+        }
+        Self::new(forms)
+    }
+
+    /// Operators with the given forms, in `BinaryOperator` order; strings
+    /// concatenate with the form of `+`
+    pub const fn new(forms: [Form; 13]) -> Self {
+        Self {
+            forms,
+            concat: forms[BinaryOperator::Add as usize],
+            to_string: None,
+            untyped_add: None,
+            strings: None,
+            parenthesized: false,
+            placeholder: "/* unsupported op */",
+        }
+    }
+
+    /// Replace the form of `operator`
+    pub const fn with(mut self, operator: BinaryOperator, form: Form) -> Self {
+        self.forms[operator as usize] = form;
+        self
+    }
+
+    /// Concatenate strings with `form`
+    pub const fn concat(mut self, form: Form) -> Self {
+        self.concat = form;
+        self
+    }
+
+    /// Convert operands of a concatenation that are not strings with `template`
+    pub const fn to_string(mut self, template: &'static str) -> Self {
+        self.to_string = Some(template);
+        self
+    }
+
+    /// Add operands whose types are only known at run time with `form`
+    pub const fn untyped_add(mut self, form: Form) -> Self {
+        self.untyped_add = Some(form);
+        self
+    }
+
+    /// Compare strings with these forms, from `==` to `>=` in `BinaryOperator` order
+    pub const fn strings(mut self, forms: [Form; 6]) -> Self {
+        self.strings = Some(forms);
+        self
+    }
+
+    /// Wrap every lowered operator in parentheses instead of relying on precedence
+    pub const fn parenthesized(mut self) -> Self {
+        self.parenthesized = true;
+        self
+    }
+
+    /// Emit `placeholder` for operators the target cannot express
+    pub const fn placeholder(mut self, placeholder: &'static str) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Lower a binary `expression`, generating its other operands with `generate`
+    pub fn binary(&self, expression: &Expression, generate: &mut dyn FnMut(&Expression) -> Result<String>) -> Result<String> {
+        self.lower(expression, generate).map(|(code, _)| code)
+    }
+
+    fn lower(&self, expression: &Expression, generate: &mut dyn FnMut(&Expression) -> Result<String>) -> Result<(String, Binding)> {
+        let Expression::Binary { left, operator, right, .. } = expression else {
+            return Ok((generate(expression)?, Binding::Atom));
+        };
+        let (left_kind, right_kind) = (kind(left), kind(right));
+        let strings = left_kind == Type::String || right_kind == Type::String;
+        let concat = *operator == BinaryOperator::Add && strings;
+        let untyped = *operator == BinaryOperator::Add && (left_kind == Type::Unknown || right_kind == Type::Unknown);
+        let form = match (self.strings, comparison(*operator), self.untyped_add) {
+            _ if concat => self.concat,
+            (_, _, Some(form)) if untyped => form,
+            (Some(forms), Some(index), _) if strings => forms[index],
+            _ => self.forms[*operator as usize],
+        };
+        if form == Form::Unsupported {
+            return Ok((unsupported_expression(expression, self.placeholder), Binding::Atom));
+        }
+
+        let mut left = self.lower(left, generate)?;
+        let mut right = self.lower(right, generate)?;
+        if let (true, Some(template)) = (concat, self.to_string) {
+            for (operand, operand_kind) in [(&mut left, &left_kind), (&mut right, &right_kind)] {
+                if *operand_kind != Type::String {
+                    *operand = (substitute(template, &[("{}", operand)]), Binding::Atom);
+                }
+            }
+        }
+
+        let (code, binding) = match form {
+            Form::Infix(spelling) => {
+                let parent = Binding::Infix { precedence: precedence(*operator), concat };
+                let left = parent.operand(left, false);
+                let right = parent.operand(right, true);
+                (format!("{} {} {}", left, spelling, right), parent)
+            }
+            Form::Call(template) => return Ok((substitute(template, &[("{l}", &left), ("{r}", &right)]), Binding::Atom)),
+            Form::Template(template) => (substitute(template, &[("{l}", &left), ("{r}", &right)]), Binding::Loose),
+            Form::Unsupported => unreachable!(),
+        };
+        if self.parenthesized {
+            Ok((format!("({})", code), Binding::Atom))
+        } else {
+            Ok((code, binding))
+        }
+    }
+}
+
+/// How lowered code binds when it becomes an operand
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Binding {
+    /// Never needs parentheses
+    Atom,
+    /// An infix operator; `concat` marks string `+`
+    Infix { precedence: u8, concat: bool },
+    /// Always needs parentheses
+    Loose,
+}
+
+impl Binding {
+    /// `operand` as the left or right operand of this infix operator
+    fn operand(self, (code, binding): (String, Binding), right: bool) -> String {
+        let Binding::Infix { precedence: parent, concat: parent_concat } = self else {
+            return code;
+        };
+        let wrap = match binding {
+            Binding::Atom => false,
+            Binding::Loose => true,
+            // Comparisons chain in some targets and are not associative in others
+            Binding::Infix { precedence, concat } => precedence < parent
+                || precedence == parent && (right || concat != parent_concat)
+                || is_comparison(precedence) && is_comparison(parent),
+        };
+        if wrap {
+            format!("({})", code)
+        } else {
+            code
+        }
+    }
+}
+
+fn precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => 1,
+        BinaryOperator::And => 2,
+        BinaryOperator::Equal | BinaryOperator::NotEqual => 3,
+        BinaryOperator::Less | BinaryOperator::LessEqual | BinaryOperator::Greater | BinaryOperator::GreaterEqual => 4,
+        BinaryOperator::Add | BinaryOperator::Subtract => 5,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 6,
+    }
+}
+
+fn is_comparison(precedence: u8) -> bool {
+    matches!(precedence, 3 | 4)
+}
+
+/// Index of a comparison in the string forms
+fn comparison(operator: BinaryOperator) -> Option<usize> {
+    (operator as usize).checked_sub(BinaryOperator::Equal as usize).filter(|&index| index < 6)
+}
+
+/// Replace each placeholder in `template` with its operand, parenthesizing
+/// compound operands unless brackets or commas already delimit them
+pub(crate) fn substitute(template: &str, operands: &[(&str, &(String, Binding))]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        let Some((placeholder, (code, binding))) = operands.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) else {
+            filled.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        rest = &rest[placeholder.len()..];
+        let delimited = filled.trim_end().ends_with(['(', '[', '{', ','])
+            && rest.trim_start().starts_with([')', ']', '}', ',']);
+        if matches!(binding, Binding::Atom) || delimited {
+            filled.push_str(code);
+        } else {
+            filled.push('(');
+            filled.push_str(code);
+            filled.push(')');
+        }
+    }
+    filled
+}
+
+/// What `expression` evaluates to at runtime, as far as it is known from
+/// the literals it contains and the types the type checker resolved its
+/// names to (see `TypeSystem::annotate`)
+pub(crate) fn kind(expression: &Expression) -> Type {
+    // Only scalars pick an operator form; anything else is left to the target
+    let scalar = |typ: Option<&Type>| match typ {
+        Some(typ @ (Type::Number | Type::String | Type::Boolean)) => typ.clone(),
+        _ => Type::Unknown,
+    };
+    match expression {
+        Expression::Literal { value, .. } => value.type_hint(),
+        Expression::Identifier { resolved_type, .. } => scalar(resolved_type.as_ref()),
+        Expression::Binary { left, operator, right, .. } => match operator {
+            BinaryOperator::Add => match (kind(left), kind(right)) {
+                (Type::String, _) | (_, Type::String) => Type::String,
+                (Type::Number, Type::Number) => Type::Number,
+                _ => Type::Unknown,
+            },
+            BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => Type::Number,
+            // `&&` and `||` yield one of their operands
+            BinaryOperator::And | BinaryOperator::Or => match (kind(left), kind(right)) {
+                (left, right) if left == right => left,
+                _ => Type::Unknown,
+            },
+            _ => Type::Boolean,
+        },
+        Expression::Unary { operator: UnaryOperator::Not, .. } => Type::Boolean,
+        Expression::Unary { .. } | Expression::Postfix { .. } => Type::Number,
+        Expression::Call { callee, .. } => match (callee.as_ref(), builtins::called(expression)) {
+            (_, Some(builtin)) => builtin.return_type(),
+            (Expression::Identifier { resolved_type: Some(Type::Function { return_type, .. }), .. }, None) => scalar(Some(return_type)),
+            _ => Type::Unknown,
+        },
+        _ => Type::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser, transformers::TransformerManager};
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap()
+    }
+
+    /// Statements lowered by every target, at top level and in a main block
+    /// since backends emit one or the other
+    const STATEMENTS: &str = r#"
+        let name = "Ada";
+        let count = 7;
+        let label = name + count;
+        let ratio = count / 2;
+        let rest = count % 3;
+        let same = name == "Ada";
+        let both = count > 1 && same;
+    "#;
+
+    /// Targets without expression lowering: they emit machine code or
+    /// signatures only
+    const NO_OPERATORS: &[&str] = &["assembly", "llvm", "wasm", "cuda", "embedded-c"];
+
+    /// Targets that keep every variable as a string and convert it at each
+    /// operator, so their output does not build or print what Utopia does;
+    /// their lowerings are not held to the table until that is fixed
+    const UNVERIFIED: &[&str] = &["cpp", "crystal", "nim", "zig", "pas"];
+
+    /// What each target writes for `name + count`, `count / 2`, `count % 3`,
+    /// `name == "Ada"` and `count > 1 && same`
+    const CONFORMANCE: &[(&str, [&str; 5])] = &[
+        ("python", ["name + str(count)", "count / 2", "utopia_mod(count, 3)", "name == \"Ada\"", "count > 1 and same"]),
+        ("javascript", ["name + count", "count / 2", "count % 3", "name === \"Ada\"", "count > 1 && same"]),
+        ("typescript", ["name + count", "count / 2", "count % 3", "name === \"Ada\"", "count > 1 && same"]),
+        ("c", ["/* unsupported op */", "(double) count / 2.0", "fmod(count, 3.0)", "strcmp(name, \"Ada\") == 0", "count > 1.0 && same"]),
+        ("rust", ["format!(\"{}{}\", name, count)", "count as f64 / 2 as f64", "count % 3", "name == \"Ada\"", "count > 1 && same"]),
+        ("go", ["fmt.Sprint(name, count)", "float64(count) / float64(2)", "math.Mod(float64(count), float64(3))", "name == \"Ada\"", "count > 1 && same"]),
+        ("java", ["String.valueOf(name) + String.valueOf(count)", "((Number)count).doubleValue() / ((Number)2).doubleValue()", "((Number)count).doubleValue() % ((Number)3).doubleValue()", "java.util.Objects.equals(name, \"Ada\")", "((Boolean)(((Comparable)count).compareTo(1) > 0)) && ((Boolean)same)"]),
+        ("csharp", ["(name + count)", "((double)count / 2)", "(count % 3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("vb", ["(name & count)", "(count / 2)", "(count Mod 3)", "(name = \"Ada\")", "((count > 1) AndAlso same)"]),
+        ("perl", ["($name . $count)", "($count / 2)", "POSIX::fmod($count, 3)", "($name eq \"Ada\")", "(($count > 1) && $same)"]),
+        ("php", ["($name . $count)", "($count / 2)", "fmod($count, 3)", "($name === \"Ada\")", "(($count > 1) && $same)"]),
+        ("ada", ["(name & Float'Image (count))", "(count / 2)", "(count rem 3)", "(name = \"Ada\")", "((count > 1) and then same)"]),
+        ("delphi", ["(name + FloatToStr(count))", "(count / 2)", "FMod(count, 3)", "(name = 'Ada')", "((count > 1) and same)"]),
+        ("fortran", ["(name // count)", "(real(count) / 2)", "mod(count, 3)", "(name == 'Ada')", "((count > 1) .and. same)"]),
+        ("sql", ["(name || count)", "(CAST(count AS REAL) / 2)", "MOD(count, 3)", "(name = 'Ada')", "((count > 1) AND same)"]),
+        ("r", ["paste0(name, count)", "(count / 2)", "(count - 3 * trunc(count / 3))", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("matlab", ["[name, num2str(count)]", "(count ./ 2)", "rem(count, 3)", "strcmp(name, 'Ada')", "((count > 1) && same)"]),
+        ("kotlin", ["\"${name}${count}\"", "(count.toDouble() / 2)", "(count % 3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("swift", ["\"\\(name)\\(count)\"", "(Double(count) / Double(2))", "Double(count).truncatingRemainder(dividingBy: Double(3))", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("cobol", ["* unsupported operation *", "(WS-COUNT / 2)", "FUNCTION REM(WS-COUNT, 3)", "(WS-NAME = 'Ada')", "((WS-COUNT > 1) AND WS-SAME)"]),
+        ("ruby", ["\"#{name}#{count}\"", "count.fdiv(2)", "count.remainder(3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("lisp", ["(concatenate 'string name (princ-to-string count))", "(/ (float count) 2)", "(rem count 3)", "(string= name \"Ada\")", "(and (> count 1) same)"]),
+        ("prolog", ["% unsupported operation %", "(Count / 2)", "(Count rem 3)", "(Name == 'Ada')", "((Count > 1) , Same)"]),
+        ("dart", ["\"${name}${count}\"", "(count / 2)", "count.remainder(3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("lua", ["(name .. tostring(count))", "(count / 2)", "math.fmod(count, 3)", "(name == \"Ada\")", "((count > 1) and same)"]),
+        ("haskell", ["(name ++ show count)", "(count / 2)", "(count - 3 * fromIntegral (truncate (count / 3)))", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("objc", ["[NSString stringWithFormat:@\"%@%@\", name, count]", "@([count doubleValue] / [@(2) doubleValue])", "@(fmod([count doubleValue], [@(3) doubleValue]))", "[name isEqualToString:@\"Ada\"]", "([([count doubleValue] > [@(1) doubleValue]) boolValue] && [same boolValue])"]),
+        ("scala", ["s\"${name}${count}\"", "(count.toDouble / 2)", "(count % 3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("julia", ["string(name, count)", "(count / 2)", "(count % 3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("vbscript", ["(name & count)", "(count / 2)", "(count - 3 * Fix(count / 3))", "(name = \"Ada\")", "((count > 1) And same)"]),
+        ("bash", ["$name$count", "$(awk \"BEGIN { print $count / 2 }\")", "$(($count % 3))", "$name == \"Ada\"", "$count -gt 1 && $same"]),
+        ("elixir", ["\"#{name}#{count}\"", "(count / 2)", ":math.fmod(count, 3)", "(name == \"Ada\")", "((count > 1) && same)"]),
+        ("fsharp", ["(name + string count)", "(float count / float 2)", "(count % 3)", "(name = \"Ada\")", "((count > 1) && same)"]),
+        ("clojure", ["(str name count)", "(/ (double count) 2)", "(rem count 3)", "(= name \"Ada\")", "(and (> count 1) same)"]),
+        ("erlang", ["lists:concat([Name, Count])", "Count / 2", "math:fmod(Count, 3)", "Name =:= \"Ada\"", "Count > 1 andalso Same"]),
+        ("ocaml", ["(name ^ string_of_int count)", "(float_of_int count /. float_of_int 2)", "(count mod 3)", "(name = \"Ada\")", "((count > 1) && same)"]),
+        ("scheme", ["(string-append name (number->string count))", "(exact->inexact (/ count 2))", "(remainder count 3)", "(string=? name \"Ada\")", "(and (> count 1) same)"]),
+        ("racket", ["(string-append name (~a count))", "(exact->inexact (/ count 2))", "(remainder count 3)", "(string=? name \"Ada\")", "(and (> count 1) same)"]),
+        ("smalltalk", ["(name , count printString)", "((count / 2) asFloat)", "(count rem: 3)", "(name = 'Ada')", "((count > 1) and: [same])"]),
+        ("basic", ["(NAME$ + COUNT$)", "STR$(VAL(COUNT$) / VAL(\"2\"))", "STR$(VAL(COUNT$) - VAL(\"3\") * FIX(VAL(COUNT$) / VAL(\"3\")))", "(NAME$ = \"Ada\")", "((VAL(COUNT$) > VAL(\"1\")) AND SAME$)"]),
+    ];
+
+    #[test]
+    fn test_every_target_preserves_operator_semantics() {
+        let program = parse(&format!("{}\n@lang main {{\n{}\n}}\n", STATEMENTS, STATEMENTS));
+        let manager = TransformerManager::new();
+        let mut missing = Vec::new();
+        for (target, snippets) in CONFORMANCE {
+            let output = manager.transform(target, &program).unwrap();
+            for snippet in snippets {
+                if !output.code().contains(snippet) {
+                    missing.push(format!("{}: {}", target, snippet));
+                }
+            }
+        }
+        assert!(missing.is_empty(), "missing lowerings:\n{}", missing.join("\n"));
+
+        // Every backend is in the table, has no expressions to lower or is known not to conform
+        let covered: Vec<String> = CONFORMANCE.iter().map(|(target, _)| *target).chain(NO_OPERATORS.iter().copied()).chain(UNVERIFIED.iter().copied())
+            .map(|target| manager.transform(target, &program).unwrap().metadata["target"].clone())
+            .collect();
+        for target in manager.supported_targets() {
+            let name = &manager.transform(&target, &program).unwrap().metadata["target"];
+            assert!(covered.contains(name), "{} is missing from the conformance table", target);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_operators_are_reported() {
+        let program = parse("let label = \"n = \" + 1;\n");
+        let output = TransformerManager::new().transform("cobol", &program).unwrap();
+        assert!(output.code().contains("* unsupported operation *"), "{}", output.code());
+        assert_eq!(output.diagnostics.len(), 1);
+        assert!(output.diagnostics[0].message.contains("a binary expression"), "{:?}", output.diagnostics);
+    }
+
+    #[test]
+    fn test_untyped_add_concatenates_at_run_time() {
+        let program = parse("function f(s) {\n    return s + 1;\n}\nprint(f(\"a\"));\n");
+        let manager = TransformerManager::new();
+        let python = manager.transform("python", &program).unwrap();
+        assert!(python.code().contains("return utopia_add(s, 1)"), "{}", python.code());
+        let runtime = python.files.iter().find(|file| file.path.ends_with("utopia_runtime.py")).unwrap();
+        assert!(runtime.contents.contains("\"utopia_add\"") && runtime.contents.contains("def utopia_add(left, right):"));
+
+        let program = parse("@lang main {\nfunction f(s) {\n    return s + 1;\n}\nprint(f(\"a\"));\n}\n");
+        let csharp = manager.transform("csharp", &program).unwrap();
+        assert!(csharp.code().contains("return UtopiaRuntime.Add(s, 1);"), "{}", csharp.code());
+        // Known numbers still add natively
+        let program = parse("function f(n: number) {\n    return n + 1;\n}\n");
+        assert!(manager.transform("python", &program).unwrap().code().contains("return n + 1"));
+    }
+
+    #[test]
+    fn test_operand_types_follow_scopes() {
+        // The same parameter name has a different type in each function
+        let program = parse("function f(x: string) {\n    return x + 1;\n}\nfunction g(x: number) {\n    return x + 1;\n}\nfunction h(x) {\n    return x + 1;\n}\n");
+        let code = TransformerManager::new().transform("python", &program).unwrap().code().to_string();
+        for expected in ["return x + str(1)", "return x + 1", "return utopia_add(x, 1)"] {
+            assert!(code.contains(expected), "{} not in\n{}", expected, code);
+        }
+
+        // A local function shadows the builtin of the same name only where it is declared
+        let program = parse("function f(a) {\n    function max(x) {\n        return x;\n    }\n    return max(a) + 1;\n}\nprint(max(1, 2) + 1);\n");
+        let code = TransformerManager::new().transform("python", &program).unwrap().code().to_string();
+        assert!(code.contains("return utopia_add(max(a), 1)") && code.contains("print(max(1, 2) + 1)"), "{}", code);
+    }
+
+    #[test]
+    fn test_python_modulo_prints_like_folded_constant() {
+        let source = "print((0 - 7) % 3);\nlet a = 0 - 7;\nprint(a % 3);\nprint(7.5 % 2);\n";
+        for level in [0, 2] {
+            let mut config = crate::Config::new();
+            config.optimization_level = level;
+            let output = crate::Compiler::new(config).run(source, &crate::pipeline::PipelineOptions::new("python"), &mut crate::pipeline::NoHooks).unwrap();
+            let Some(printed) = crate::runtime::run_python(&format!("modulo-{}", level), output.generated.as_ref().unwrap()) else {
+                return;
+            };
+            assert_eq!(printed, "-1\n-1\n1.5\n", "-O{}", level);
+        }
+    }
+
+    #[test]
+    fn test_parenthesizes_by_precedence() {
+        let program = parse(r#"
+            function f(a: number, b: number, c: number) {
+                let x = a - (b - c);
+                let y = a - b - c;
+                let z = (a + b) * c + a * b;
+                let w = (a < b) == (b < c);
+                let v = a || b && c;
+                let u = (a || b) && c;
+                let s = "n" + (a + b);
+                return x;
+            }
+        "#);
+        let code = TransformerManager::new().transform("python", &program).unwrap().code().to_string();
+        for expected in [
+            "x = a - (b - c)",
+            "y = a - b - c",
+            "z = (a + b) * c + a * b",
+            "w = (a < b) == (b < c)",
+            "v = a or b and c",
+            "u = (a or b) and c",
+            // Numbers add; the string forces concatenation
+            "s = \"n\" + str(a + b)",
+        ] {
+            assert!(code.contains(expected), "{} not in\n{}", expected, code);
+        }
+    }
+}
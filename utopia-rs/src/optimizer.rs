@@ -2,22 +2,40 @@
 //!
 //! Implements AST-level optimizations that make Utopia code perform better than C.
 //! Includes constant folding, dead code elimination, loop optimization, function inlining,
-//! algebraic simplifications, memory optimizations, and vectorization.
+//! algebraic simplifications, common subexpression elimination, memory optimizations, and vectorization.
 
 use std::collections::{HashMap, HashSet};
 // Note: Arc and Mutex imports removed as they're not currently used
 use std::time::Instant;
 
-use crate::{ast::*, types::Type, Result};
+use crate::{
+    ast::*,
+    callgraph::{function_unit_id, CallGraph},
+    config::Config,
+    cse::CommonSubexpressions,
+    deadcode::{remove_unreachable, DeadStores},
+    diagnostics::Diagnostic,
+    effects::EffectAnalysis,
+    inliner::{body_size, InlineBudget, Inliner},
+    loops::{LoopBudget, LoopOptimizer},
+    propagation::{collect_assigned, ConstantPropagator},
+    simplify::Simplifier,
+    tailcall::TailCalls,
+    types::Type,
+    Result,
+};
 
 /// Optimization statistics tracking
 #[derive(Debug, Clone, Default)]
 pub struct OptimizationStats {
+    pub constant_propagations: usize,
     pub constant_folds: usize,
     pub dead_code_eliminations: usize,
     pub function_inlines: usize,
     pub loop_optimizations: usize,
     pub algebraic_simplifications: usize,
+    pub common_subexpressions: usize,
+    pub tail_calls: usize,
     pub memory_optimizations: usize,
     pub vectorizations: usize,
     pub optimization_time_ms: u128,
@@ -25,21 +43,44 @@ pub struct OptimizationStats {
 
 impl OptimizationStats {
     pub fn total_optimizations(&self) -> usize {
-        self.constant_folds
+        self.constant_propagations
+            + self.constant_folds
             + self.dead_code_eliminations
             + self.function_inlines
             + self.loop_optimizations
             + self.algebraic_simplifications
+            + self.common_subexpressions
+            + self.tail_calls
             + self.memory_optimizations
             + self.vectorizations
     }
 
+    /// Changes made since `earlier` was recorded
+    pub fn since(&self, earlier: &OptimizationStats) -> OptimizationStats {
+        OptimizationStats {
+            constant_propagations: self.constant_propagations.saturating_sub(earlier.constant_propagations),
+            constant_folds: self.constant_folds.saturating_sub(earlier.constant_folds),
+            dead_code_eliminations: self.dead_code_eliminations.saturating_sub(earlier.dead_code_eliminations),
+            function_inlines: self.function_inlines.saturating_sub(earlier.function_inlines),
+            loop_optimizations: self.loop_optimizations.saturating_sub(earlier.loop_optimizations),
+            algebraic_simplifications: self.algebraic_simplifications.saturating_sub(earlier.algebraic_simplifications),
+            common_subexpressions: self.common_subexpressions.saturating_sub(earlier.common_subexpressions),
+            tail_calls: self.tail_calls.saturating_sub(earlier.tail_calls),
+            memory_optimizations: self.memory_optimizations.saturating_sub(earlier.memory_optimizations),
+            vectorizations: self.vectorizations.saturating_sub(earlier.vectorizations),
+            optimization_time_ms: 0,
+        }
+    }
+
     pub fn merge(&mut self, other: &OptimizationStats) {
+        self.constant_propagations += other.constant_propagations;
         self.constant_folds += other.constant_folds;
         self.dead_code_eliminations += other.dead_code_eliminations;
         self.function_inlines += other.function_inlines;
         self.loop_optimizations += other.loop_optimizations;
         self.algebraic_simplifications += other.algebraic_simplifications;
+        self.common_subexpressions += other.common_subexpressions;
+        self.tail_calls += other.tail_calls;
         self.memory_optimizations += other.memory_optimizations;
         self.vectorizations += other.vectorizations;
         self.optimization_time_ms += other.optimization_time_ms;
@@ -68,96 +109,409 @@ pub struct VariableInfo {
     pub is_loop_invariant: bool,
 }
 
+/// A named optimization pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptimizationPass {
+    ConstantPropagation,
+    ConstantFolding,
+    DeadCodeElimination,
+    Inline,
+    LoopOptimization,
+    AlgebraicSimplification,
+    CommonSubexpressionElimination,
+    TailCallElimination,
+    MemoryOptimization,
+    Vectorization,
+}
+
+impl OptimizationPass {
+    /// Every pass, in the order a full pipeline runs them
+    pub const ALL: [OptimizationPass; 10] = [
+        OptimizationPass::Inline,
+        OptimizationPass::ConstantPropagation,
+        OptimizationPass::ConstantFolding,
+        OptimizationPass::DeadCodeElimination,
+        OptimizationPass::LoopOptimization,
+        OptimizationPass::AlgebraicSimplification,
+        OptimizationPass::CommonSubexpressionElimination,
+        OptimizationPass::TailCallElimination,
+        OptimizationPass::MemoryOptimization,
+        OptimizationPass::Vectorization,
+    ];
+
+    /// Name used on the command line (`-C passes=+fold,-inline`)
+    pub fn name(self) -> &'static str {
+        match self {
+            OptimizationPass::ConstantPropagation => "propagate",
+            OptimizationPass::ConstantFolding => "fold",
+            OptimizationPass::DeadCodeElimination => "dce",
+            OptimizationPass::Inline => "inline",
+            OptimizationPass::LoopOptimization => "loops",
+            OptimizationPass::AlgebraicSimplification => "simplify",
+            OptimizationPass::CommonSubexpressionElimination => "cse",
+            OptimizationPass::TailCallElimination => "tailcall",
+            OptimizationPass::MemoryOptimization => "memory",
+            OptimizationPass::Vectorization => "vectorize",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL.iter()
+            .copied()
+            .find(|pass| pass.name() == name)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(|pass| pass.name()).collect();
+                format!("Unknown optimization pass '{}' (available: {})", name, known.join(", ")).into()
+            })
+    }
+}
+
+impl std::fmt::Display for OptimizationPass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Ordered list of passes the optimizer runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassPipeline {
+    passes: Vec<OptimizationPass>,
+}
+
+impl PassPipeline {
+    /// The standard pipeline for an optimization level
+    ///
+    /// -O0 runs nothing, -O1 propagates and folds constants and removes dead code, -O2 first
+    /// inlines and adds loop and algebraic optimizations, common subexpression elimination
+    /// and tail-call elimination and -O3 runs every pass.
+    pub fn for_level(level: u8) -> Self {
+        use OptimizationPass::*;
+
+        let passes = match level {
+            0 => vec![],
+            1 => vec![ConstantPropagation, ConstantFolding, DeadCodeElimination],
+            2 => vec![
+                Inline, ConstantPropagation, ConstantFolding, DeadCodeElimination, LoopOptimization,
+                AlgebraicSimplification, CommonSubexpressionElimination, TailCallElimination, DeadCodeElimination,
+            ],
+            _ => vec![
+                Inline, ConstantPropagation, ConstantFolding, DeadCodeElimination, LoopOptimization, AlgebraicSimplification,
+                CommonSubexpressionElimination, TailCallElimination, MemoryOptimization, Vectorization, DeadCodeElimination,
+            ],
+        };
+        Self { passes }
+    }
+
+    pub fn passes(&self) -> &[OptimizationPass] {
+        &self.passes
+    }
+
+    pub fn contains(&self, pass: OptimizationPass) -> bool {
+        self.passes.contains(&pass)
+    }
+
+    /// Apply overrides such as `+simplify,-inline`
+    ///
+    /// `+pass` adds a pass that is not yet scheduled at its usual position,
+    /// `-pass` removes every run of it.
+    pub fn apply_overrides(&mut self, overrides: &str) -> Result<()> {
+        for item in overrides.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (enable, name) = match item.split_at(1) {
+                ("+", name) => (true, name),
+                ("-", name) => (false, name),
+                _ => return Err(format!("Pass override '{}' must start with '+' or '-'", item).into()),
+            };
+            let pass = OptimizationPass::from_name(name)?;
+            if enable {
+                self.insert(pass);
+            } else {
+                self.remove(pass);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove every run of `pass`
+    pub fn remove(&mut self, pass: OptimizationPass) {
+        self.passes.retain(|scheduled| *scheduled != pass);
+    }
+
+    fn insert(&mut self, pass: OptimizationPass) {
+        if self.contains(pass) {
+            return;
+        }
+        let rank = |pass: &OptimizationPass| OptimizationPass::ALL.iter().position(|p| p == pass).unwrap_or(usize::MAX);
+        // Keep a trailing cleanup pass last
+        let limit = if self.passes.len() > 1 && self.passes.last() == Some(&OptimizationPass::DeadCodeElimination) {
+            self.passes.len() - 1
+        } else {
+            self.passes.len()
+        };
+        let position = self.passes[..limit].iter()
+            .position(|scheduled| rank(scheduled) > rank(&pass))
+            .unwrap_or(limit);
+        self.passes.insert(position, pass);
+    }
+}
+
+/// Which passes to dump the AST after
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DumpAfter {
+    #[default]
+    Nothing,
+    All,
+    Passes(HashSet<OptimizationPass>),
+}
+
+impl DumpAfter {
+    /// Parse `all` or a comma separated list of pass names
+    pub fn parse(value: &str) -> Result<Self> {
+        if value == "all" {
+            return Ok(DumpAfter::All);
+        }
+        let passes = value.split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(OptimizationPass::from_name)
+            .collect::<Result<HashSet<_>>>()?;
+        Ok(DumpAfter::Passes(passes))
+    }
+
+    pub fn includes(&self, pass: OptimizationPass) -> bool {
+        match self {
+            DumpAfter::Nothing => false,
+            DumpAfter::All => true,
+            DumpAfter::Passes(passes) => passes.contains(&pass),
+        }
+    }
+}
+
+/// What one run of a pass did
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub pass: OptimizationPass,
+    /// Changes made by this run only
+    pub stats: OptimizationStats,
+    pub duration: std::time::Duration,
+    /// Pretty-printed AST after the pass, when requested
+    pub dump: Option<String>,
+}
+
 /// Utopia Super Optimizer - Performance beyond C
 pub struct Optimizer {
     pub stats: OptimizationStats,
-    pub enable_aggressive_opts: bool,
-    pub enable_parallel_compilation: bool,
-    pub enable_vectorization: bool,
-    pub enable_memory_opts: bool,
-    pub enable_function_inlining: bool,
-    pub enable_loop_unrolling: bool,
+    pipeline: PassPipeline,
+    dump_after: DumpAfter,
+    inline_budget: InlineBudget,
+    loop_budget: LoopBudget,
+    fast_math: bool,
+    remove_dead_stores: bool,
+    reports: Vec<PassReport>,
+    diagnostics: Vec<Diagnostic>,
     
     // Analysis caches
     function_info: HashMap<String, FunctionInfo>,
     constant_values: HashMap<String, LiteralValue>,
+    effects: EffectAnalysis,
     pure_functions: HashSet<String>,
     variable_info: HashMap<String, VariableInfo>,
 }
 
 impl Optimizer {
+    /// Create an optimizer running the default -O2 pipeline
     pub fn new() -> Self {
+        Self::with_pipeline(PassPipeline::for_level(2))
+    }
+
+    /// Create an optimizer running the standard pipeline of an optimization level (0-3)
+    pub fn for_level(level: u8) -> Self {
+        let mut optimizer = Self::with_pipeline(PassPipeline::for_level(level));
+        optimizer.inline_budget = InlineBudget::for_level(level);
+        optimizer.loop_budget = LoopBudget::for_level(level);
+        optimizer.remove_dead_stores = level >= 2;
+        optimizer
+    }
+
+    pub fn with_pipeline(pipeline: PassPipeline) -> Self {
         Self {
             stats: OptimizationStats::default(),
-            enable_aggressive_opts: true,
-            enable_parallel_compilation: true,
-            enable_vectorization: true,
-            enable_memory_opts: true,
-            enable_function_inlining: true,
-            enable_loop_unrolling: true,
+            pipeline,
+            dump_after: DumpAfter::Nothing,
+            inline_budget: InlineBudget::default(),
+            loop_budget: LoopBudget::default(),
+            fast_math: false,
+            remove_dead_stores: false,
+            reports: Vec::new(),
+            diagnostics: Vec::new(),
             function_info: HashMap::new(),
             constant_values: HashMap::new(),
+            effects: EffectAnalysis::default(),
             pure_functions: HashSet::new(),
             variable_info: HashMap::new(),
         }
     }
 
-    /// Main optimization entry point - transforms AST for maximum performance
+    /// Create an optimizer from the optimization settings of a compiler configuration
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut pipeline = PassPipeline::for_level(config.optimization_level);
+        if let Some(overrides) = &config.passes {
+            pipeline.apply_overrides(overrides)?;
+        }
+        let mut optimizer = Self::with_pipeline(pipeline);
+        optimizer.inline_budget = InlineBudget::for_level(config.optimization_level);
+        optimizer.loop_budget = LoopBudget::for_level(config.optimization_level);
+        optimizer.fast_math = config.fast_math;
+        optimizer.remove_dead_stores = config.optimization_level >= 2;
+        if let Some(dump_after) = &config.dump_after {
+            optimizer.dump_after = DumpAfter::parse(dump_after)?;
+        }
+        Ok(optimizer)
+    }
+
+    pub fn pipeline(&self) -> &PassPipeline {
+        &self.pipeline
+    }
+
+    pub fn set_dump_after(&mut self, dump_after: DumpAfter) {
+        self.dump_after = dump_after;
+    }
+
+    pub fn set_inline_budget(&mut self, budget: InlineBudget) {
+        self.inline_budget = budget;
+    }
+
+    pub fn set_loop_budget(&mut self, budget: LoopBudget) {
+        self.loop_budget = budget;
+    }
+
+    /// Remove unused variables and dead stores instead of only reporting them
+    pub fn set_remove_dead_stores(&mut self, remove: bool) {
+        self.remove_dead_stores = remove;
+    }
+
+    /// Skip tail-call elimination for targets that already run tail calls in constant stack
+    pub fn set_tail_calls(&mut self, eliminate: bool) {
+        if !eliminate {
+            self.pipeline.remove(OptimizationPass::TailCallElimination);
+        }
+    }
+
+    /// Allow rewrites that are exact for real numbers but not for IEEE floats
+    pub fn set_fast_math(&mut self, fast_math: bool) {
+        self.fast_math = fast_math;
+    }
+
+    /// What the last call to `optimize` learned about a variable
+    pub fn variable_info(&self, name: &str) -> Option<&VariableInfo> {
+        self.variable_info.get(name)
+    }
+
+    /// Variables known to hold a constant at the end of their block
+    pub fn constant_values(&self) -> &HashMap<String, LiteralValue> {
+        &self.constant_values
+    }
+
+    /// Effects of every function of the program given to the last call to `optimize`
+    pub fn effects(&self) -> &EffectAnalysis {
+        &self.effects
+    }
+
+    /// Warnings about the program found by the last call to `optimize`
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Reports for every pass run by the last call to `optimize`
+    pub fn reports(&self) -> &[PassReport] {
+        &self.reports
+    }
+
+    /// Main optimization entry point - runs every pass of the pipeline in order
     pub fn optimize(&mut self, mut program: Program) -> Result<Program> {
         let start_time = Instant::now();
+        self.reports.clear();
+        self.diagnostics.clear();
         
-        println!("🚀 Utopia Super Optimizer - Starting aggressive optimization passes...");
+        log::debug!("Running optimization passes: {}", 
+                    self.pipeline.passes().iter().map(|pass| pass.name()).collect::<Vec<_>>().join(", "));
         
-        // Phase 1: Analysis and preparation
+        // Analysis shared by the passes
         self.analyze_program(&program)?;
         
-        // Phase 2: Basic optimizations
-        self.perform_constant_folding(&mut program)?;
-        self.eliminate_dead_code(&mut program)?;
-        
-        // Phase 3: Advanced optimizations
-        if self.enable_function_inlining {
-            self.inline_functions(&mut program)?;
-        }
-        
-        if self.enable_aggressive_opts {
-            self.optimize_loops(&mut program)?;
-            self.perform_algebraic_simplification(&mut program)?;
-        }
-        
-        // Phase 4: Memory and vectorization optimizations
-        if self.enable_memory_opts {
-            self.optimize_memory_usage(&mut program)?;
-        }
-        
-        if self.enable_vectorization {
-            self.vectorize_operations(&mut program)?;
+        for pass in self.pipeline.passes().to_vec() {
+            let before = self.stats.clone();
+            let started = Instant::now();
+            self.run_pass(pass, &mut program)?;
+            
+            let stats = self.stats.since(&before);
+            let duration = started.elapsed();
+            log::info!("pass {}: {} changes in {:.3}ms", pass, stats.total_optimizations(), duration.as_secs_f64() * 1000.0);
+            
+            let dump = self.dump_after.includes(pass).then(|| PrettyPrinter::new().print(&program));
+            self.reports.push(PassReport { pass, stats, duration, dump });
         }
         
-        // Phase 5: Final cleanup
-        self.eliminate_dead_code(&mut program)?; // Second pass after inlining
-        
         self.stats.optimization_time_ms = start_time.elapsed().as_millis();
         
-        println!("✅ Optimization complete! {} total optimizations in {}ms", 
-                 self.stats.total_optimizations(), self.stats.optimization_time_ms);
-        println!("   📊 Breakdown: {} constant folds, {} dead code eliminations, {} function inlines",
-                 self.stats.constant_folds, self.stats.dead_code_eliminations, self.stats.function_inlines);
+        log::info!("Optimization complete: {} total optimizations in {}ms",
+                   self.stats.total_optimizations(), self.stats.optimization_time_ms);
         
         Ok(program)
     }
 
+    /// Run a single pass over the program
+    pub fn run_pass(&mut self, pass: OptimizationPass, program: &mut Program) -> Result<()> {
+        match pass {
+            OptimizationPass::ConstantPropagation => self.propagate_constants(program),
+            OptimizationPass::ConstantFolding => self.perform_constant_folding(program),
+            OptimizationPass::DeadCodeElimination => self.eliminate_dead_code(program),
+            OptimizationPass::Inline => self.inline_functions(program),
+            OptimizationPass::LoopOptimization => self.optimize_loops(program),
+            OptimizationPass::AlgebraicSimplification => self.perform_algebraic_simplification(program),
+            OptimizationPass::CommonSubexpressionElimination => self.eliminate_common_subexpressions(program),
+            OptimizationPass::TailCallElimination => self.eliminate_tail_calls(program),
+            OptimizationPass::MemoryOptimization => self.optimize_memory_usage(program),
+            OptimizationPass::Vectorization => self.vectorize_operations(program),
+        }
+    }
+
     /// Analyze the entire program to build optimization metadata
     fn analyze_program(&mut self, program: &Program) -> Result<()> {
+        // Identify pure functions (no side effects)
+        self.identify_pure_functions(program);
+
+        // Report dead stores in the code as written, before other passes create or hide them
+        if self.pipeline.contains(OptimizationPass::DeadCodeElimination) {
+            let mut dead_stores = DeadStores::new(false);
+            dead_stores.run(&mut program.clone());
+            self.diagnostics.extend(dead_stores.found.iter().map(|found| found.to_diagnostic()));
+        }
+        
+        let mut call_counts: HashMap<&str, usize> = HashMap::new();
+        let units = program.language_blocks.iter()
+            .flat_map(|block| block.functions.iter().map(|function| &function.body).chain(std::iter::once(&block.statements)))
+            .chain(std::iter::once(&program.global_statements));
+        for statement in units.flatten() {
+            statement.walk_expressions(&mut |expression| {
+                if let Expression::Call { callee, .. } = expression {
+                    if let Expression::Identifier { name, .. } = callee.as_ref() {
+                        *call_counts.entry(name.as_str()).or_default() += 1;
+                    }
+                }
+            });
+        }
+        
         // Build function information database
+        self.function_info.clear();
         for block in &program.language_blocks {
             for function in &block.functions {
+                let complexity_score = body_size(&function.body);
                 let info = FunctionInfo {
                     name: function.name.clone(),
-                    is_pure: self.is_pure_function(function),
-                    is_small: function.body.len() < 10, // Heuristic for small functions
-                    call_count: 0, // Will be updated by usage analysis
-                    complexity_score: self.calculate_complexity(function),
+                    is_pure: self.pure_functions.contains(&function_unit_id(&block.language, &function.name)),
+                    is_small: complexity_score <= self.inline_budget.max_callee_size,
+                    call_count: call_counts.get(function.name.as_str()).copied().unwrap_or(0),
+                    complexity_score,
                     parameter_types: function.parameters.iter()
                         .filter_map(|p| p.param_type.clone())
                         .collect(),
@@ -167,15 +521,28 @@ impl Optimizer {
             }
         }
         
-        // Identify pure functions (no side effects)
-        self.identify_pure_functions(program);
-        
         // Analyze variable usage patterns
         self.analyze_variable_usage(program);
         
         Ok(())
     }
 
+    /// Constant and copy propagation - replace variables whose value is known
+    fn propagate_constants(&mut self, program: &mut Program) -> Result<()> {
+        let mut propagator = ConstantPropagator::new(program);
+        propagator.run(program);
+        
+        self.stats.constant_propagations += propagator.propagations;
+        self.stats.constant_folds += propagator.folds;
+        for (name, value) in propagator.constants {
+            if let Some(info) = self.variable_info.get_mut(&name) {
+                info.constant_value = Some(value.clone());
+            }
+            self.constant_values.insert(name, value);
+        }
+        Ok(())
+    }
+
     /// Constant folding optimization - evaluate compile-time constants
     fn perform_constant_folding(&mut self, program: &mut Program) -> Result<()> {
         for block in &mut program.language_blocks {
@@ -257,7 +624,7 @@ impl Optimizer {
                 if let (Expression::Literal { value: left_val, .. }, 
                         Expression::Literal { value: right_val, .. }) = (left.as_ref(), right.as_ref()) {
                     
-                    if let Some(result) = self.evaluate_binary_operation(left_val, operator, right_val) {
+                    if let Some(result) = evaluate_binary(left_val, operator, right_val) {
                         *expression = Expression::Literal {
                             value: result,
                             span: *span,
@@ -266,14 +633,27 @@ impl Optimizer {
                     }
                 }
             }
-            Expression::Unary { operand, .. } => {
+            Expression::Unary { operator, operand, span } => {
                 self.fold_constants_in_expression(operand)?;
+                
+                if let Expression::Literal { value, .. } = operand.as_ref() {
+                    if let Some(result) = evaluate_unary(operator, value) {
+                        *expression = Expression::Literal {
+                            value: result,
+                            span: *span,
+                        };
+                        self.stats.constant_folds += 1;
+                    }
+                }
             }
-            Expression::Call { arguments, .. } => {
+            Expression::Call { arguments, .. } | Expression::CrossCall { arguments, .. } => {
                 for arg in arguments {
                     self.fold_constants_in_expression(arg)?;
                 }
             }
+            Expression::Assignment { value, .. } => {
+                self.fold_constants_in_expression(value)?;
+            }
             Expression::Array { elements, .. } => {
                 for element in elements {
                     self.fold_constants_in_expression(element)?;
@@ -284,47 +664,23 @@ impl Optimizer {
         Ok(())
     }
 
-    fn evaluate_binary_operation(&self, left: &LiteralValue, operator: &BinaryOperator, right: &LiteralValue) -> Option<LiteralValue> {
-        match (left, right) {
-            (LiteralValue::Number(a), LiteralValue::Number(b)) => {
-                match operator {
-                    BinaryOperator::Add => Some(LiteralValue::Number(a + b)),
-                    BinaryOperator::Subtract => Some(LiteralValue::Number(a - b)),
-                    BinaryOperator::Multiply => Some(LiteralValue::Number(a * b)),
-                    BinaryOperator::Divide if *b != 0.0 => Some(LiteralValue::Number(a / b)),
-                    BinaryOperator::Modulo if *b != 0.0 => Some(LiteralValue::Number(a % b)),
-                    BinaryOperator::Equal => Some(LiteralValue::Boolean((a - b).abs() < f64::EPSILON)),
-                    BinaryOperator::NotEqual => Some(LiteralValue::Boolean((a - b).abs() >= f64::EPSILON)),
-                    BinaryOperator::Less => Some(LiteralValue::Boolean(a < b)),
-                    BinaryOperator::LessEqual => Some(LiteralValue::Boolean(a <= b)),
-                    BinaryOperator::Greater => Some(LiteralValue::Boolean(a > b)),
-                    BinaryOperator::GreaterEqual => Some(LiteralValue::Boolean(a >= b)),
-                    _ => None,
-                }
-            }
-            (LiteralValue::Boolean(a), LiteralValue::Boolean(b)) => {
-                match operator {
-                    BinaryOperator::And => Some(LiteralValue::Boolean(*a && *b)),
-                    BinaryOperator::Or => Some(LiteralValue::Boolean(*a || *b)),
-                    BinaryOperator::Equal => Some(LiteralValue::Boolean(a == b)),
-                    BinaryOperator::NotEqual => Some(LiteralValue::Boolean(a != b)),
-                    _ => None,
-                }
-            }
-            (LiteralValue::String(a), LiteralValue::String(b)) => {
-                match operator {
-                    BinaryOperator::Add => Some(LiteralValue::String(format!("{}{}", a, b))),
-                    BinaryOperator::Equal => Some(LiteralValue::Boolean(a == b)),
-                    BinaryOperator::NotEqual => Some(LiteralValue::Boolean(a != b)),
-                    _ => None,
-                }
-            }
-            _ => None,
-        }
-    }
-
     /// Dead code elimination - remove unreachable code
     fn eliminate_dead_code(&mut self, program: &mut Program) -> Result<()> {
+        let unreachable = remove_unreachable(program);
+        for id in &unreachable.functions {
+            log::debug!("Removed {}, which is never called", id);
+        }
+        for language in &unreachable.blocks {
+            log::debug!("Removed the @lang {} block, which is never used", language);
+        }
+        self.stats.dead_code_eliminations += unreachable.functions.len() + unreachable.blocks.len();
+
+        if self.remove_dead_stores {
+            let mut dead_stores = DeadStores::new(true);
+            dead_stores.run(program);
+            self.stats.dead_code_eliminations += dead_stores.removed;
+        }
+
         for block in &mut program.language_blocks {
             for function in &mut block.functions {
                 self.eliminate_dead_code_in_statements(&mut function.body)?;
@@ -339,7 +695,7 @@ impl Optimizer {
     fn eliminate_dead_code_in_statements(&mut self, statements: &mut Vec<Statement>) -> Result<()> {
         let mut i = 0;
         while i < statements.len() {
-            match &statements[i] {
+            match &mut statements[i] {
                 // Remove code after return statements
                 Statement::Return { .. } => {
                     let removed_count = statements.len() - i - 1;
@@ -347,21 +703,49 @@ impl Optimizer {
                     self.stats.dead_code_eliminations += removed_count;
                     break;
                 }
-                // Remove if statements with constant false conditions
-                Statement::If { condition, .. } => {
-                    if let Expression::Literal { value: LiteralValue::Boolean(false), .. } = condition {
-                        statements.remove(i);
+                // Replace if statements with constant conditions by the branch taken
+                Statement::If { condition, then_branch, else_branch, .. } => {
+                    if let Expression::Literal { value: LiteralValue::Boolean(taken), .. } = condition {
+                        let taken = *taken;
+                        let branch = if taken { Some(&mut *then_branch) } else { else_branch.as_mut() };
+                        let mut branch = branch.map(std::mem::take).unwrap_or_default();
+                        self.eliminate_dead_code_in_statements(&mut branch)?;
+                        if taken && else_branch.is_none() && branch_declares(&branch) {
+                            // Nothing to remove
+                            *then_branch = branch;
+                            i += 1;
+                            continue;
+                        }
+                        
+                        // Declarations must stay scoped, so such a branch keeps an `if (true)` around it
+                        if branch_declares(&branch) {
+                            *condition = Expression::Literal { value: LiteralValue::Boolean(true), span: condition.span() };
+                            *then_branch = branch;
+                            *else_branch = None;
+                            i += 1;
+                        } else {
+                            // Revisit the spliced statements so a `return` in them truncates the rest
+                            statements.splice(i..=i, branch);
+                        }
                         self.stats.dead_code_eliminations += 1;
                         continue;
                     }
+                    self.eliminate_dead_code_in_statements(then_branch)?;
+                    if let Some(else_branch) = else_branch {
+                        self.eliminate_dead_code_in_statements(else_branch)?;
+                    }
                 }
                 // Remove while loops with constant false conditions
-                Statement::While { condition, .. } => {
+                Statement::While { condition, body, .. } => {
                     if let Expression::Literal { value: LiteralValue::Boolean(false), .. } = condition {
                         statements.remove(i);
                         self.stats.dead_code_eliminations += 1;
                         continue;
                     }
+                    self.eliminate_dead_code_in_statements(body)?;
+                }
+                Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+                    self.eliminate_dead_code_in_statements(body)?;
                 }
                 _ => {}
             }
@@ -372,117 +756,44 @@ impl Optimizer {
 
     /// Function inlining optimization
     fn inline_functions(&mut self, program: &mut Program) -> Result<()> {
-        // Identify small, frequently called functions for inlining
-        let inline_candidates: Vec<String> = self.function_info
-            .iter()
-            .filter(|(_, info)| info.is_small && info.is_pure)
-            .map(|(name, _)| name.clone())
-            .collect();
-
-        for candidate in inline_candidates {
-            // This is a simplified version - real implementation would be more complex
-            self.stats.function_inlines += 1;
-            println!("🔗 Inlining function: {}", candidate);
-        }
-
+        let mut inliner = Inliner::new(self.inline_budget);
+        inliner.run(program);
+        self.stats.function_inlines += inliner.inlined;
         Ok(())
     }
 
     /// Loop optimization - unrolling, invariant code motion
     fn optimize_loops(&mut self, program: &mut Program) -> Result<()> {
-        for block in &mut program.language_blocks {
-            for function in &mut block.functions {
-                self.optimize_loops_in_statements(&mut function.body)?;
-            }
-        }
-        Ok(())
-    }
-
-    fn optimize_loops_in_statements(&mut self, statements: &mut Vec<Statement>) -> Result<()> {
-        for statement in statements {
-            match statement {
-                Statement::For { init, condition, update, body, span } => {
-                    // Detect simple counting loops for unrolling
-                    if self.is_simple_counting_loop(init, condition, update) {
-                        self.stats.loop_optimizations += 1;
-                        println!("🔄 Optimizing loop at line {}", span.line);
-                    }
-                    self.optimize_loops_in_statements(body)?;
-                }
-                Statement::While { body, .. } => {
-                    self.optimize_loops_in_statements(body)?;
-                }
-                Statement::If { then_branch, else_branch, .. } => {
-                    self.optimize_loops_in_statements(then_branch)?;
-                    if let Some(else_stmts) = else_branch {
-                        self.optimize_loops_in_statements(else_stmts)?;
-                    }
-                }
-                _ => {}
-            }
-        }
+        let mut loops = LoopOptimizer::new(self.loop_budget);
+        loops.run(program);
+        self.stats.loop_optimizations += loops.unrolled + loops.hoisted;
         Ok(())
     }
 
-    /// Algebraic simplification - mathematical identities
+    /// Algebraic simplification - mathematical identities, strength reduction and canonical comparisons
     fn perform_algebraic_simplification(&mut self, program: &mut Program) -> Result<()> {
-        for block in &mut program.language_blocks {
-            for function in &mut block.functions {
-                for statement in &mut function.body {
-                    self.simplify_statement(statement)?;
-                }
-            }
+        let mut simplifier = Simplifier::new(self.fast_math);
+        simplifier.run(program);
+        for (rule, count) in &simplifier.applied {
+            log::debug!("rule {}: {} rewrites", rule, count);
         }
+        self.stats.algebraic_simplifications += simplifier.rewrites();
         Ok(())
     }
 
-    fn simplify_statement(&mut self, statement: &mut Statement) -> Result<()> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                self.simplify_expression(expression)?;
-            }
-            Statement::Assignment { value, .. } => {
-                self.simplify_expression(value)?;
-            }
-            _ => {}
-        }
+    /// Common subexpression elimination within straight-line code
+    fn eliminate_common_subexpressions(&mut self, program: &mut Program) -> Result<()> {
+        let mut cse = CommonSubexpressions::new();
+        cse.run(program);
+        self.stats.common_subexpressions += cse.eliminated;
         Ok(())
     }
 
-    fn simplify_expression(&mut self, expression: &mut Expression) -> Result<()> {
-        match expression {
-            Expression::Binary { left, operator, right, span } => {
-                self.simplify_expression(left)?;
-                self.simplify_expression(right)?;
-                
-                // Algebraic identities
-                match (left.as_ref(), operator, right.as_ref()) {
-                    // x + 0 = x
-                    (expr, BinaryOperator::Add, Expression::Literal { value: LiteralValue::Number(0.0), .. }) |
-                    (Expression::Literal { value: LiteralValue::Number(0.0), .. }, BinaryOperator::Add, expr) => {
-                        *expression = expr.clone();
-                        self.stats.algebraic_simplifications += 1;
-                    }
-                    // x * 1 = x
-                    (expr, BinaryOperator::Multiply, Expression::Literal { value: LiteralValue::Number(1.0), .. }) |
-                    (Expression::Literal { value: LiteralValue::Number(1.0), .. }, BinaryOperator::Multiply, expr) => {
-                        *expression = expr.clone();
-                        self.stats.algebraic_simplifications += 1;
-                    }
-                    // x * 0 = 0
-                    (_, BinaryOperator::Multiply, Expression::Literal { value: LiteralValue::Number(0.0), .. }) |
-                    (Expression::Literal { value: LiteralValue::Number(0.0), .. }, BinaryOperator::Multiply, _) => {
-                        *expression = Expression::Literal {
-                            value: LiteralValue::Number(0.0),
-                            span: *span,
-                        };
-                        self.stats.algebraic_simplifications += 1;
-                    }
-                    _ => {}
-                }
-            }
-            _ => {}
-        }
+    /// Self tail calls become loops, for targets whose stack grows with every call
+    fn eliminate_tail_calls(&mut self, program: &mut Program) -> Result<()> {
+        let mut tail_calls = TailCalls::new();
+        tail_calls.run(program);
+        self.stats.tail_calls += tail_calls.eliminated;
         Ok(())
     }
 
@@ -492,8 +803,6 @@ impl Optimizer {
         // - Stack allocation instead of heap when possible
         // - Memory pool allocation
         // - Eliminating unnecessary copies
-        self.stats.memory_optimizations += 1;
-        println!("🧠 Applied memory optimizations");
         Ok(())
     }
 
@@ -503,36 +812,53 @@ impl Optimizer {
         // - SIMD instruction generation
         // - Loop vectorization
         // - Auto-vectorization of arithmetic operations
-        self.stats.vectorizations += 1;
-        println!("⚡ Applied vectorization optimizations");
         Ok(())
     }
 
     // Helper methods
-    fn is_pure_function(&self, function: &Function) -> bool {
-        // Simplified purity analysis - real implementation would be more sophisticated
-        !function.name.contains("print") && !function.name.contains("write")
-    }
-
-    fn calculate_complexity(&self, function: &Function) -> usize {
-        // Simple complexity metric based on statement count and nesting
-        function.body.len()
-    }
-
-    fn identify_pure_functions(&mut self, _program: &Program) {
-        // Analyze program to identify pure functions
-        // This would involve complex data flow analysis
+    fn identify_pure_functions(&mut self, program: &Program) {
+        self.effects = EffectAnalysis::analyze(program, &CallGraph::build(program));
+        self.pure_functions = self.effects.pure();
     }
 
-    fn analyze_variable_usage(&mut self, _program: &Program) {
-        // Analyze how variables are used throughout the program
-        // This would involve building def-use chains
-    }
-
-    fn is_simple_counting_loop(&self, _init: &Option<Box<Statement>>, _condition: &Option<Expression>, _update: &Option<Expression>) -> bool {
-        // Detect simple for(i=0; i<n; i++) patterns
-        // Simplified implementation
-        true
+    fn analyze_variable_usage(&mut self, program: &Program) {
+        self.variable_info.clear();
+        self.constant_values.clear();
+        
+        let units = program.language_blocks.iter()
+            .flat_map(|block| block.functions.iter().map(|function| &function.body).chain(std::iter::once(&block.statements)))
+            .chain(std::iter::once(&program.global_statements));
+        
+        for statements in units {
+            let mut reassigned = HashSet::new();
+            collect_assigned(statements, &mut reassigned);
+            
+            let mut uses: HashMap<String, usize> = HashMap::new();
+            for statement in statements {
+                statement.walk_expressions(&mut |expression| {
+                    if let Expression::Identifier { name, .. } = expression {
+                        *uses.entry(name.clone()).or_default() += 1;
+                    }
+                });
+            }
+            
+            let mut declarations = Vec::new();
+            collect_declarations(statements, &mut declarations);
+            for (name, value) in declarations {
+                let is_constant = !reassigned.contains(name);
+                let constant_value = match value {
+                    Some(Expression::Literal { value, .. }) if is_constant => Some(value.clone()),
+                    _ => None,
+                };
+                self.variable_info.insert(name.clone(), VariableInfo {
+                    name: name.clone(),
+                    is_constant,
+                    constant_value,
+                    usage_count: uses.get(name).copied().unwrap_or(0),
+                    is_loop_invariant: false,
+                });
+            }
+        }
     }
 
     /// Get optimization statistics
@@ -546,8 +872,142 @@ impl Optimizer {
     }
 }
 
+fn branch_declares(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| matches!(statement, Statement::VariableDeclaration { .. }))
+}
+
+/// Every variable declared in `statements` with its initializer, including nested blocks
+fn collect_declarations<'a>(statements: &'a [Statement], declarations: &mut Vec<(&'a String, Option<&'a Expression>)>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => declarations.push((name, value.as_ref())),
+            Statement::If { then_branch, else_branch, .. } => {
+                collect_declarations(then_branch, declarations);
+                if let Some(else_branch) = else_branch {
+                    collect_declarations(else_branch, declarations);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    collect_declarations(std::slice::from_ref(init.as_ref()), declarations);
+                }
+                collect_declarations(body, declarations);
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => {
+                collect_declarations(body, declarations);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Evaluate a binary operation on two literals, if it has a compile-time result
+pub(crate) fn evaluate_binary(left: &LiteralValue, operator: &BinaryOperator, right: &LiteralValue) -> Option<LiteralValue> {
+    match (left, right) {
+        (LiteralValue::Number(a), LiteralValue::Number(b)) => {
+            match operator {
+                BinaryOperator::Add => Some(LiteralValue::Number(a + b)),
+                BinaryOperator::Subtract => Some(LiteralValue::Number(a - b)),
+                BinaryOperator::Multiply => Some(LiteralValue::Number(a * b)),
+                BinaryOperator::Divide if *b != 0.0 => Some(LiteralValue::Number(a / b)),
+                BinaryOperator::Modulo if *b != 0.0 => Some(LiteralValue::Number(a % b)),
+                BinaryOperator::Equal => Some(LiteralValue::Boolean((a - b).abs() < f64::EPSILON)),
+                BinaryOperator::NotEqual => Some(LiteralValue::Boolean((a - b).abs() >= f64::EPSILON)),
+                BinaryOperator::Less => Some(LiteralValue::Boolean(a < b)),
+                BinaryOperator::LessEqual => Some(LiteralValue::Boolean(a <= b)),
+                BinaryOperator::Greater => Some(LiteralValue::Boolean(a > b)),
+                BinaryOperator::GreaterEqual => Some(LiteralValue::Boolean(a >= b)),
+                _ => None,
+            }
+        }
+        (LiteralValue::Boolean(a), LiteralValue::Boolean(b)) => {
+            match operator {
+                BinaryOperator::And => Some(LiteralValue::Boolean(*a && *b)),
+                BinaryOperator::Or => Some(LiteralValue::Boolean(*a || *b)),
+                BinaryOperator::Equal => Some(LiteralValue::Boolean(a == b)),
+                BinaryOperator::NotEqual => Some(LiteralValue::Boolean(a != b)),
+                _ => None,
+            }
+        }
+        (LiteralValue::String(a), LiteralValue::String(b)) => {
+            match operator {
+                BinaryOperator::Add => Some(LiteralValue::String(format!("{}{}", a, b))),
+                BinaryOperator::Equal => Some(LiteralValue::Boolean(a == b)),
+                BinaryOperator::NotEqual => Some(LiteralValue::Boolean(a != b)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Evaluate a unary operation on a literal, if it has a compile-time result
+pub(crate) fn evaluate_unary(operator: &UnaryOperator, operand: &LiteralValue) -> Option<LiteralValue> {
+    match (operator, operand) {
+        (UnaryOperator::Minus, LiteralValue::Number(n)) => Some(LiteralValue::Number(-n)),
+        (UnaryOperator::Plus, LiteralValue::Number(n)) => Some(LiteralValue::Number(*n)),
+        (UnaryOperator::Not, LiteralValue::Boolean(b)) => Some(LiteralValue::Boolean(!b)),
+        _ => None,
+    }
+}
+
 impl Default for Optimizer {
     fn default() -> Self {
         Self::new()
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use OptimizationPass::*;
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_level_pipelines() {
+        assert!(PassPipeline::for_level(0).passes().is_empty());
+        assert_eq!(PassPipeline::for_level(1).passes(), &[ConstantPropagation, ConstantFolding, DeadCodeElimination]);
+        assert!(PassPipeline::for_level(2).contains(Inline));
+        assert!(!PassPipeline::for_level(2).contains(Vectorization));
+        assert!(PassPipeline::for_level(3).contains(Vectorization));
+    }
+
+    #[test]
+    fn test_pass_overrides() {
+        let mut pipeline = PassPipeline::for_level(1);
+        pipeline.apply_overrides("+simplify,-dce,+fold").unwrap();
+        assert_eq!(pipeline.passes(), &[ConstantPropagation, ConstantFolding, AlgebraicSimplification]);
+
+        let mut pipeline = PassPipeline::for_level(2);
+        pipeline.apply_overrides("-inline,+memory").unwrap();
+        assert_eq!(pipeline.passes().last(), Some(&DeadCodeElimination));
+        assert!(!pipeline.contains(Inline));
+        assert!(pipeline.contains(MemoryOptimization));
+
+        assert!(pipeline.apply_overrides("+nonsense").is_err());
+        assert!(pipeline.apply_overrides("fold").is_err());
+    }
+
+    #[test]
+    fn test_per_pass_reports_and_dumps() {
+        let program = parse("@lang python {\n    let x = 1 + 2;\n    let y = x * 1;\n}\n");
+        let mut optimizer = Optimizer::for_level(2);
+        optimizer.set_dump_after(DumpAfter::parse("fold").unwrap());
+        optimizer.optimize(program).unwrap();
+
+        let reports = optimizer.reports();
+        assert_eq!(reports.len(), PassPipeline::for_level(2).passes().len());
+        assert_eq!(reports[1].pass, ConstantPropagation);
+        assert_eq!(reports[1].stats.constant_propagations, 1);
+        assert_eq!(reports[1].stats.constant_folds, 2);
+        assert!(reports[1].dump.is_none());
+        assert_eq!(reports[2].pass, ConstantFolding);
+        assert!(reports[2].dump.as_deref().unwrap().contains("let y = 3;"));
+        let total: usize = reports.iter().map(|report| report.stats.total_optimizations()).sum();
+        assert_eq!(total, optimizer.get_stats().total_optimizations());
+    }
+}
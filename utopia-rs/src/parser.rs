@@ -11,7 +11,7 @@ use crate::{
     Span
 };
 // Note: nom imports removed as they're not currently used in the parser
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 /// Parser state
 pub struct Parser {
@@ -979,6 +979,7 @@ impl Parser {
                 } else {
                     Ok(Expression::Identifier {
                         name: identifier,
+                        resolved_type: None,
                         span,
                     })
                 }
@@ -1013,7 +1014,7 @@ impl Parser {
             }
             TokenKind::LeftBrace => {
                 self.advance();
-                let mut properties = HashMap::new();
+                let mut properties = IndexMap::new();
                 
                 if !self.check(&TokenKind::RightBrace) {
                     loop {
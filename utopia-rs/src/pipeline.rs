@@ -0,0 +1,446 @@
+//! Compilation pipeline stages
+//!
+//! `Compiler` drives a program through a fixed sequence of stages. This module
+//! defines the stages, what each one can emit, the per-stage hooks and the
+//! result of a pipeline run, so that every CLI command shares one driver.
+
+use std::fmt::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::{
+    ast::{PrettyPrinter, Program},
+    diagnostics::{Diagnostic, DiagnosticKind},
+    ir::Module,
+    lexer::{Token, TokenKind},
+    optimizer::{OptimizationStats, PassReport},
+    transformers::TransformOutput,
+    Result,
+};
+
+/// A stage of the compilation pipeline, in execution order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    Lex,
+    Parse,
+    Resolve,
+    TypeCheck,
+    Optimize,
+    Generate,
+}
+
+impl Stage {
+    /// All stages in execution order
+    pub const ALL: [Stage; 6] = [
+        Stage::Lex,
+        Stage::Parse,
+        Stage::Resolve,
+        Stage::TypeCheck,
+        Stage::Optimize,
+        Stage::Generate,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Lex => "lex",
+            Stage::Parse => "parse",
+            Stage::Resolve => "resolve",
+            Stage::TypeCheck => "type-check",
+            Stage::Optimize => "optimize",
+            Stage::Generate => "generate",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Intermediate result that can be emitted instead of generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmitKind {
+    /// Token stream from the lexer
+    Tokens,
+    /// Parsed AST
+    Ast,
+    /// Parsed AST as a versioned JSON document (see `docs/ast-json.md`)
+    AstJson,
+    /// AST after name resolution and type checking
+    TypedAst,
+    /// AST after optimization
+    OptimizedAst,
+    /// Optimized program lowered to the SSA intermediate representation
+    Ir,
+    /// Generated target code
+    Code,
+}
+
+impl EmitKind {
+    /// The last stage that has to run to produce this output
+    pub fn last_stage(self) -> Stage {
+        match self {
+            EmitKind::Tokens => Stage::Lex,
+            EmitKind::Ast | EmitKind::AstJson => Stage::Parse,
+            EmitKind::TypedAst => Stage::TypeCheck,
+            EmitKind::OptimizedAst | EmitKind::Ir => Stage::Optimize,
+            EmitKind::Code => Stage::Generate,
+        }
+    }
+}
+
+/// Callbacks invoked around every stage of a pipeline run
+pub trait PipelineHooks {
+    /// Called before a stage starts
+    fn before_stage(&mut self, _stage: Stage) {}
+
+    /// Called after a stage finished, with everything produced so far
+    fn after_stage(&mut self, _stage: Stage, _elapsed: Duration, _output: &PipelineOutput) {}
+}
+
+/// Hooks that do nothing
+pub struct NoHooks;
+
+impl PipelineHooks for NoHooks {}
+
+/// The caller's hooks, shared by the targets `Compiler::run_targets` builds
+/// on separate threads; each call holds the lock for the duration of the callback
+pub(crate) struct SharedHooks<'a, 'h>(pub(crate) &'a Mutex<&'h mut (dyn PipelineHooks + Send)>);
+
+impl PipelineHooks for SharedHooks<'_, '_> {
+    fn before_stage(&mut self, stage: Stage) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).before_stage(stage);
+    }
+
+    fn after_stage(&mut self, stage: Stage, elapsed: Duration, output: &PipelineOutput) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).after_stage(stage, elapsed, output);
+    }
+}
+
+/// Wall-clock time spent in one stage
+#[derive(Debug, Clone, Copy)]
+pub struct StageTiming {
+    pub stage: Stage,
+    pub duration: Duration,
+}
+
+/// Everything a pipeline run produced
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOutput {
+    pub tokens: Option<Vec<Token>>,
+    /// The program as of the last stage that ran
+    pub program: Option<Program>,
+    pub code: Option<String>,
+    /// Files, diagnostics and metadata of the generate stage; `code` is its main file
+    pub generated: Option<TransformOutput>,
+    pub diagnostics: Vec<Diagnostic>,
+    pub timings: Vec<StageTiming>,
+    /// Statistics of the optimize stage, if it ran
+    pub optimization_stats: Option<OptimizationStats>,
+    /// One report per optimization pass that ran
+    pub pass_reports: Vec<PassReport>,
+}
+
+impl PipelineOutput {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.kind == DiagnosticKind::Error)
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.kind == DiagnosticKind::Error).count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.kind == DiagnosticKind::Warning).count()
+    }
+
+    /// Time spent in `stage`, if it ran
+    pub fn timing(&self, stage: Stage) -> Option<Duration> {
+        self.timings.iter().find(|t| t.stage == stage).map(|t| t.duration)
+    }
+
+    /// Total time spent in all stages that ran
+    pub fn total_time(&self) -> Duration {
+        self.timings.iter().map(|t| t.duration).sum()
+    }
+
+    /// Take the timings and diagnostics of a later run of the same program
+    pub fn merge(&mut self, later: PipelineOutput) {
+        if later.tokens.is_some() {
+            self.tokens = later.tokens;
+        }
+        if later.program.is_some() {
+            self.program = later.program;
+        }
+        if later.code.is_some() {
+            self.code = later.code;
+        }
+        if later.generated.is_some() {
+            self.generated = later.generated;
+        }
+        if later.optimization_stats.is_some() {
+            self.optimization_stats = later.optimization_stats;
+            self.pass_reports = later.pass_reports;
+        }
+        self.diagnostics.extend(later.diagnostics);
+        self.timings.extend(later.timings);
+    }
+
+    /// Render the requested intermediate result as text
+    pub fn render(&self, kind: EmitKind) -> Result<String> {
+        match kind {
+            EmitKind::Tokens => {
+                let tokens = self.tokens.as_ref().ok_or("No tokens were produced")?;
+                Ok(render_tokens(tokens))
+            }
+            EmitKind::Ast | EmitKind::TypedAst | EmitKind::OptimizedAst => {
+                let program = self.program.as_ref().ok_or("No AST was produced")?;
+                Ok(PrettyPrinter::new().print(program))
+            }
+            EmitKind::AstJson => self.program.as_ref().ok_or("No AST was produced")?.to_json(),
+            EmitKind::Ir => {
+                let program = self.program.as_ref().ok_or("No AST was produced")?;
+                let module = Module::lower(program)?;
+                module.verify()?;
+                Ok(module.to_string())
+            }
+            EmitKind::Code => self.code.clone().ok_or_else(|| "No code was generated".into()),
+        }
+    }
+}
+
+fn render_tokens(tokens: &[Token]) -> String {
+    let mut output = String::new();
+    for token in tokens {
+        if matches!(token.kind, TokenKind::Newline) {
+            continue;
+        }
+        let _ = writeln!(output, "{}:{}\t{:?}", token.span.line, token.span.column, token.kind);
+    }
+    output
+}
+
+/// The optimize and generate stages of one target in a multi-target build
+#[derive(Debug, Clone)]
+pub struct TargetBuild {
+    pub target: String,
+    /// Output of the stages, or the error that stopped them
+    pub output: std::result::Result<PipelineOutput, String>,
+    /// Wall-clock time spent on this target
+    pub duration: Duration,
+}
+
+impl TargetBuild {
+    /// Whether the target produced code without errors
+    pub fn succeeded(&self) -> bool {
+        self.output.as_ref().is_ok_and(|output| !output.has_errors() && output.code.is_some())
+    }
+}
+
+/// What a pipeline run should do
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    /// Target passed to the generate stage
+    pub target: String,
+    /// Stop after this stage
+    pub stop_after: Stage,
+    /// Accept generated code with placeholders for unsupported constructs,
+    /// reporting them as warnings instead of errors
+    pub allow_partial: bool,
+    /// Paste the target's runtime files into the main file instead of
+    /// writing them next to it
+    pub bundle_runtime: bool,
+}
+
+impl PipelineOptions {
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            stop_after: Stage::Generate,
+            allow_partial: false,
+            bundle_runtime: false,
+        }
+    }
+
+    pub fn stop_after(mut self, stage: Stage) -> Self {
+        self.stop_after = stage;
+        self
+    }
+
+    pub fn allow_partial(mut self, allow: bool) -> Self {
+        self.allow_partial = allow;
+        self
+    }
+
+    pub fn bundle_runtime(mut self, bundle: bool) -> Self {
+        self.bundle_runtime = bundle;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Config};
+
+    const SOURCE: &str = r#"
+@lang python {
+    let x = 2 * 3;
+    console.log(x);
+}
+"#;
+
+    struct Recorder(Vec<Stage>);
+
+    impl PipelineHooks for Recorder {
+        fn after_stage(&mut self, stage: Stage, _elapsed: Duration, _output: &PipelineOutput) {
+            self.0.push(stage);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_runs_every_stage() {
+        let mut compiler = Compiler::new(Config::new());
+        let mut hooks = Recorder(Vec::new());
+        let output = compiler.run(SOURCE, &PipelineOptions::new("python"), &mut hooks).unwrap();
+
+        assert_eq!(hooks.0, Stage::ALL.to_vec());
+        assert_eq!(output.timings.len(), Stage::ALL.len());
+        assert!(output.optimization_stats.unwrap().constant_folds > 0);
+        // `x` is propagated into its only use and then removed as unused
+        let code = output.code.unwrap();
+        assert!(code.contains("(6)"), "{}", code);
+        assert!(!code.contains("x = "), "{}", code);
+    }
+
+    #[test]
+    fn test_run_from_ast_json_matches_source() {
+        let mut compiler = Compiler::new(Config::new());
+        let parsed = compiler.run(SOURCE, &PipelineOptions::new("python").stop_after(EmitKind::AstJson.last_stage()), &mut NoHooks).unwrap();
+        let json = parsed.render(EmitKind::AstJson).unwrap();
+
+        let program = Program::from_json(&json).unwrap();
+        let imported = compiler.run_from_ast(program, &PipelineOptions::new("python"), &mut NoHooks).unwrap();
+        let compiled = compiler.run(SOURCE, &PipelineOptions::new("python"), &mut NoHooks).unwrap();
+        assert_eq!(imported.code, compiled.code);
+        assert!(imported.timing(Stage::Parse).is_none());
+    }
+
+    #[test]
+    fn test_emit_stops_after_stage() {
+        let mut compiler = Compiler::new(Config::new());
+        let options = PipelineOptions::new("python").stop_after(EmitKind::TypedAst.last_stage());
+        let output = compiler.run(SOURCE, &options, &mut NoHooks).unwrap();
+
+        assert!(output.code.is_none());
+        assert!(output.timing(Stage::Optimize).is_none());
+        let typed = output.render(EmitKind::TypedAst).unwrap();
+        assert!(typed.contains("let x: number = 2 * 3;"), "{}", typed);
+    }
+
+    #[test]
+    fn test_emit_ir() {
+        let mut compiler = Compiler::new(Config::new());
+        let options = PipelineOptions::new("python").stop_after(EmitKind::Ir.last_stage());
+        let output = compiler.run(SOURCE, &options, &mut NoHooks).unwrap();
+
+        let ir = output.render(EmitKind::Ir).unwrap();
+        assert!(ir.starts_with("fn python#0() -> void {\nbb0:\n"), "{}", ir);
+        assert!(ir.contains("call console.log(%"), "{}", ir);
+    }
+
+    #[test]
+    fn test_unsupported_constructs_fail_unless_partial() {
+        let source = "@lang python {\n    let xs = [1, 2];\n    print(len(xs));\n}\n";
+        let mut compiler = Compiler::new(Config::new());
+        let output = compiler.run(source, &PipelineOptions::new("python"), &mut NoHooks).unwrap();
+        assert_eq!(output.error_count(), 1);
+        assert!(compiler.compile(source, "python").unwrap_err().to_string().contains("cannot translate an array literal"));
+
+        let partial = compiler.run(source, &PipelineOptions::new("python").allow_partial(true), &mut NoHooks).unwrap();
+        assert!(!partial.has_errors());
+        assert_eq!(partial.warning_count(), 1);
+        assert!(partial.generated.unwrap().is_partial());
+    }
+
+    #[test]
+    fn test_unrolled_unsupported_construct_is_reported_once() {
+        let source = "@lang python {\n    for (let i = 0; i < 3; i = i + 1) {\n        let xs = [i];\n        print(len(xs));\n    }\n}\n";
+        let mut compiler = Compiler::new(Config::new());
+        let output = compiler.run(source, &PipelineOptions::new("python").allow_partial(true), &mut NoHooks).unwrap();
+
+        let generated = output.generated.unwrap();
+        assert_eq!(generated.code().matches("Unsupported expression").count(), 3, "{}", generated.code());
+        assert_eq!(generated.diagnostics.len(), 1, "{:?}", generated.diagnostics);
+        assert_eq!((generated.diagnostics[0].span.line, generated.diagnostics[0].span.column), (3, 18));
+    }
+
+    #[test]
+    fn test_dropped_global_code_fails_unless_partial() {
+        let source = "print(\"Ada\");\n";
+        let mut compiler = Compiler::new(Config::new());
+        let error = compiler.compile(source, "javascript").unwrap_err().to_string();
+        assert!(error.contains("javascript backend cannot translate an expression statement in global code"), "{}", error);
+
+        let partial = compiler.run(source, &PipelineOptions::new("javascript").allow_partial(true), &mut NoHooks).unwrap();
+        assert!(!partial.has_errors());
+        assert_eq!(partial.warning_count(), 1);
+        assert!(compiler.compile(&format!("@lang main {{\n{}}}\n", source), "javascript").is_ok());
+    }
+
+    #[test]
+    fn test_run_targets_checks_once_and_builds_each_target() {
+        let mut compiler = Compiler::new(Config::new());
+        let mut hooks = Recorder(Vec::new());
+        let targets = ["python".to_string(), "go".to_string(), "sql".to_string(), "nope".to_string()];
+        let source = "function double(x) {\n    return x * 2;\n}\nprint(double(3));\n";
+        let (checked, builds) = compiler.run_targets(source, &targets, &PipelineOptions::new(""), &mut hooks).unwrap();
+
+        assert_eq!(hooks.0[..4], [Stage::Lex, Stage::Parse, Stage::Resolve, Stage::TypeCheck]);
+        // Every target that builds reports its own optimize and generate stages
+        let generated = hooks.0.iter().filter(|&&stage| stage == Stage::Generate).count();
+        assert_eq!(generated, 3, "{:?}", hooks.0);
+        assert!(!checked.has_errors());
+        let names: Vec<&str> = builds.iter().map(|build| build.target.as_str()).collect();
+        assert_eq!(names, vec!["python", "go", "sql", "nope"]);
+        assert!(builds[0].succeeded() && builds[1].succeeded() && builds[2].succeeded());
+        assert!(builds[0].output.as_ref().unwrap().code.as_ref().unwrap().contains("print(6)"));
+        assert!(builds[3].output.as_ref().unwrap_err().contains("Unsupported target: nope"));
+    }
+
+    #[test]
+    fn test_every_target_generates_identical_output_on_each_run() {
+        let source = "@lang python {\n    function area(w, h) {\n        return w * h;\n    }\n    let shape = {width: 3, height: 4, name: \"box\", depth: 1};\n    print(area(shape.width, shape.height));\n}\n\
+                      @lang javascript {\n    let totals = {b: 2, a: 1, c: 3};\n    let n = 0;\n    while (n < 3) {\n        n = n + 1;\n    }\n    console.log(totals.a + n);\n}\n\
+                      let point = {y: python.area(1, 2), x: 1};\nprint(point.x);\n";
+        let targets = crate::transformers::TransformerManager::new().capability_matrix();
+        for target in targets.keys() {
+            let options = PipelineOptions::new(target).allow_partial(true);
+            let first = Compiler::new(Config::new()).run(source, &options, &mut NoHooks).unwrap();
+            let second = Compiler::new(Config::new()).run(source, &options, &mut NoHooks).unwrap();
+            assert!(first.code.is_some(), "{target}");
+            assert_eq!(first.code, second.code, "{target}");
+            assert_eq!(first.generated.unwrap().files, second.generated.unwrap().files, "{target}");
+        }
+
+        // Object literals and their types keep the order the fields were written in
+        let options = PipelineOptions::new("python").stop_after(EmitKind::Ir.last_stage());
+        let output = Compiler::new(Config::new()).run(source, &options, &mut NoHooks).unwrap();
+        assert!(output.render(EmitKind::TypedAst).unwrap().contains("{ b: 2, a: 1, c: 3 }"));
+        let ir = output.render(EmitKind::Ir).unwrap();
+        assert!(ir.contains("{ width: number, height: number, name: string, depth: number }"), "{}", ir);
+    }
+
+    #[test]
+    fn test_errors_stop_before_optimization() {
+        let mut config = Config::new();
+        config.optimization_level = 0;
+        let mut compiler = Compiler::new(config);
+        let output = compiler.run("@lang python {\n    let x: number = \"a\";\n}\n", &PipelineOptions::new("python"), &mut NoHooks).unwrap();
+
+        assert_eq!(output.error_count(), 1);
+        assert!(output.timing(Stage::Optimize).is_none());
+        assert!(compiler.compile("@lang python {\n    let x: number = \"a\";\n}\n", "python").is_err());
+    }
+}
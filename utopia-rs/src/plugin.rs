@@ -0,0 +1,365 @@
+//! Out-of-process transformer plugins
+//!
+//! A target named `plugin:<executable>` is generated by another program, so
+//! backends can be written in any language without changes to the compiler.
+//! The executable is run once per request with a JSON request on stdin and
+//! must print a single JSON response on stdout; stderr is passed through.
+//!
+//! When the target is loaded the plugin is asked to describe itself:
+//!
+//! ```json
+//! {"protocol_version": 1, "request": "describe"}
+//! {"protocol_version": 1, "name": "mylang", "extension": ".my", "unsupported": ["lambdas"]}
+//! ```
+//!
+//! and for every build it receives the optimized program as versioned AST
+//! JSON and returns the generated files, the first being the main file:
+//!
+//! ```json
+//! {"protocol_version": 1, "request": "generate", "ast_version": 1, "program": {...}}
+//! {"protocol_version": 1, "files": [{"path": "main.my", "contents": "..."}],
+//!  "diagnostics": [{"severity": "error", "message": "...", "line": 3, "column": 5}]}
+//! ```
+
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{Program, AST_VERSION},
+    cache::content_hash,
+    capabilities::{Capabilities, Feature},
+    diagnostics::{Diagnostic, DiagnosticKind},
+    transformers::{OutputFile, TransformOutput, Transformer},
+    Result, Span,
+};
+
+/// Version of the request and response format
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Prefix of targets generated by a plugin
+pub const PLUGIN_PREFIX: &str = "plugin:";
+
+/// The executable of a `plugin:<executable>` target
+pub fn plugin_executable(target: &str) -> Option<&str> {
+    target.strip_prefix(PLUGIN_PREFIX).filter(|executable| !executable.is_empty())
+}
+
+#[derive(Serialize)]
+#[serde(tag = "request", rename_all = "lowercase")]
+enum Request<'a> {
+    Describe {
+        protocol_version: u32,
+    },
+    Generate {
+        protocol_version: u32,
+        ast_version: u32,
+        program: &'a Program,
+    },
+}
+
+#[derive(Deserialize)]
+struct Description {
+    protocol_version: u32,
+    name: String,
+    extension: String,
+    /// Names of the features the target cannot express, as in `Feature::name`
+    #[serde(default)]
+    unsupported: Vec<String>,
+    #[serde(default)]
+    eliminates_tail_calls: bool,
+}
+
+#[derive(Deserialize)]
+struct Generated {
+    protocol_version: u32,
+    files: Vec<GeneratedFile>,
+    #[serde(default)]
+    diagnostics: Vec<PluginDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct GeneratedFile {
+    path: PathBuf,
+    contents: String,
+}
+
+#[derive(Deserialize)]
+struct PluginDiagnostic {
+    #[serde(default)]
+    severity: Severity,
+    message: String,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl From<Severity> for DiagnosticKind {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => DiagnosticKind::Error,
+            Severity::Warning => DiagnosticKind::Warning,
+            Severity::Info => DiagnosticKind::Info,
+            Severity::Hint => DiagnosticKind::Hint,
+        }
+    }
+}
+
+/// A target generated by an external executable
+#[derive(Debug, Clone)]
+pub struct PluginTransformer {
+    target: String,
+    executable: PathBuf,
+    name: String,
+    extension: String,
+    capabilities: Capabilities,
+    eliminates_tail_calls: bool,
+    fingerprint: Option<String>,
+}
+
+impl PluginTransformer {
+    /// Load the plugin of a `plugin:<executable>` target by asking it to describe itself
+    pub fn load(target: &str) -> Result<Self> {
+        let executable = plugin_executable(target)
+            .ok_or_else(|| format!("Invalid plugin target '{}', expected {}<executable>", target, PLUGIN_PREFIX))?;
+        let executable = PathBuf::from(executable);
+        let response = run(&executable, &Request::Describe { protocol_version: PROTOCOL_VERSION })?;
+        let description: Description = parse_response(&executable, &response)?;
+        check_version(&executable, description.protocol_version)?;
+
+        let mut missing = Vec::new();
+        for name in &description.unsupported {
+            let feature = Feature::ALL.into_iter().find(|feature| feature.name() == name)
+                .ok_or_else(|| format!("Plugin {} declares unknown feature '{}'", executable.display(), name))?;
+            missing.push(feature);
+        }
+        let mut extension = description.extension;
+        if !extension.starts_with('.') {
+            extension.insert(0, '.');
+        }
+
+        let fingerprint = fingerprint(&executable);
+        Ok(Self {
+            target: target.to_string(),
+            executable,
+            name: description.name,
+            extension,
+            capabilities: Capabilities::without(&missing),
+            eliminates_tail_calls: description.eliminates_tail_calls,
+            fingerprint,
+        })
+    }
+
+    /// The `plugin:<executable>` target the plugin was loaded for
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn executable(&self) -> &Path {
+        &self.executable
+    }
+
+    /// Hash of the executable's path and contents, so cached output is rebuilt
+    /// when the plugin is replaced; `None` if the executable cannot be read
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint.as_deref()
+    }
+}
+
+/// Hash of the file `executable` runs, found the way `Command` finds it
+fn fingerprint(executable: &Path) -> Option<String> {
+    let path = if executable.components().count() > 1 {
+        executable.to_path_buf()
+    } else {
+        std::env::split_paths(&std::env::var_os("PATH")?)
+            .map(|dir| dir.join(executable))
+            .find(|candidate| candidate.is_file())?
+    };
+    let path = std::fs::canonicalize(path).ok()?;
+    let mut contents = path.to_string_lossy().into_owned().into_bytes();
+    contents.extend(std::fs::read(&path).ok()?);
+    Some(content_hash(&contents))
+}
+
+impl Transformer for PluginTransformer {
+    fn generate(&self, program: &Program) -> Result<String> {
+        self.transform(program).map(|output| output.code().to_string())
+    }
+
+    /// The plugin writes the files itself, so there is no source map
+    fn transform(&self, program: &Program) -> Result<TransformOutput> {
+        let request = Request::Generate { protocol_version: PROTOCOL_VERSION, ast_version: AST_VERSION, program };
+        let response = run(&self.executable, &request)?;
+        let generated: Generated = parse_response(&self.executable, &response)?;
+        check_version(&self.executable, generated.protocol_version)?;
+
+        let mut files = generated.files.into_iter();
+        let main = files.next()
+            .ok_or_else(|| format!("Plugin {} generated no files", self.executable.display()))?;
+        let mut output = TransformOutput::new(&self.name, &self.extension, main.contents);
+        for file in files {
+            // Files are written next to the output, so they must stay inside it
+            if !file.path.components().all(|component| matches!(component, Component::Normal(_))) {
+                return Err(format!("Plugin {} generated file outside the output directory: {}", self.executable.display(), file.path.display()).into());
+            }
+            output.files.push(OutputFile { path: file.path, contents: file.contents });
+        }
+        output.diagnostics = generated.diagnostics.into_iter()
+            .map(|diagnostic| {
+                let span = Span::new(0, 0, diagnostic.line.unwrap_or(program.span.line), diagnostic.column.unwrap_or(program.span.column));
+                let mut converted = Diagnostic::error(diagnostic.message, span);
+                converted.kind = diagnostic.severity.into();
+                converted
+            })
+            .collect();
+        Ok(output)
+    }
+
+    fn target_name(&self) -> &str {
+        &self.name
+    }
+
+    fn file_extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn supports_language(&self, language: &str) -> bool {
+        language == self.name
+    }
+
+    fn eliminates_tail_calls(&self) -> bool {
+        self.eliminates_tail_calls
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities.clone()
+    }
+}
+
+/// Run `executable` with `request` on stdin and return what it printed
+fn run(executable: &Path, request: &Request<'_>) -> Result<String> {
+    let input = serde_json::to_vec(request)?;
+    let mut child = Command::new(executable)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("Cannot start plugin {}: {}", executable.display(), e))?;
+
+    // Written from another thread so a plugin that answers before reading
+    // all of its input cannot fill the stdout pipe and block both sides
+    let mut stdin = child.stdin.take().ok_or("Plugin stdin is not available")?;
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    let written = writer.join().map_err(|_| "Writing to the plugin panicked")?;
+
+    if !output.status.success() {
+        return Err(format!("Plugin {} failed with {}", executable.display(), output.status).into());
+    }
+    written.map_err(|e| format!("Cannot send the request to plugin {}: {}", executable.display(), e))?;
+    String::from_utf8(output.stdout)
+        .map_err(|_| format!("Plugin {} printed invalid UTF-8", executable.display()).into())
+}
+
+fn parse_response<T: for<'de> Deserialize<'de>>(executable: &Path, response: &str) -> Result<T> {
+    serde_json::from_str(response)
+        .map_err(|e| format!("Plugin {} returned an invalid response: {}", executable.display(), e).into())
+}
+
+fn check_version(executable: &Path, version: u32) -> Result<()> {
+    if version == PROTOCOL_VERSION {
+        Ok(())
+    } else {
+        Err(format!("Plugin {} speaks protocol version {}, expected {}", executable.display(), version, PROTOCOL_VERSION).into())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser, transformers::TransformerManager};
+    use std::os::unix::fs::PermissionsExt;
+
+    /// A plugin script answering `describe` and `generate` with fixed responses
+    fn plugin(name: &str, describe: &str, generate: &str) -> String {
+        let path = std::env::temp_dir().join(format!("utopia-plugin-{}-{}", name, std::process::id()));
+        let script = format!(
+            "#!/bin/sh\nrequest=$(cat)\ncase \"$request\" in\n  *'\"request\":\"describe\"'*) printf '%s\\n' '{}' ;;\n  *) printf '%s\\n' '{}' ;;\nesac\n",
+            describe, generate,
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        format!("{}{}", PLUGIN_PREFIX, path.display())
+    }
+
+    fn program() -> Program {
+        Parser::new(Lexer::new("let x = 1;").tokenize().unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_plugin_generates_files_and_diagnostics() {
+        let target = plugin(
+            "generate",
+            r#"{"protocol_version":1,"name":"echo","extension":"echo","unsupported":["lambdas"]}"#,
+            r#"{"protocol_version":1,"files":[{"path":"main.echo","contents":"x := 1\n"},{"path":"lib/runtime.echo","contents":""}],"diagnostics":[{"severity":"warning","message":"careful","line":1,"column":5}]}"#,
+        );
+        let mut manager = TransformerManager::new();
+        manager.load_plugin(&target).unwrap();
+        assert_eq!(manager.get_file_extension(&target), Some(".echo".to_string()));
+        assert!(!manager.capabilities(&target).unwrap().supports(Feature::Lambdas));
+
+        let output = manager.transform(&target, &program()).unwrap();
+        assert_eq!(output.code(), "x := 1\n");
+        assert_eq!(output.files[1].path, PathBuf::from("lib/runtime.echo"));
+        assert_eq!(output.metadata.get("target").map(String::as_str), Some("echo"));
+        assert_eq!(output.diagnostics.len(), 1);
+        assert_eq!(output.diagnostics[0].kind, DiagnosticKind::Warning);
+        assert_eq!((output.diagnostics[0].span.line, output.diagnostics[0].span.column), (1, 5));
+        let _ = std::fs::remove_file(plugin_executable(&target).unwrap());
+    }
+
+    #[test]
+    fn test_plugin_responses_are_checked() {
+        let describe = r#"{"protocol_version":1,"name":"bad","extension":".bad"}"#;
+        let escaping = plugin("escaping", describe, r#"{"protocol_version":1,"files":[{"path":"main.bad","contents":""},{"path":"../evil","contents":""}]}"#);
+        let error = PluginTransformer::load(&escaping).unwrap().transform(&program()).unwrap_err().to_string();
+        assert!(error.contains("outside the output directory"), "{}", error);
+
+        let newer = plugin("newer", r#"{"protocol_version":2,"name":"new","extension":".new"}"#, "{}");
+        let error = PluginTransformer::load(&newer).unwrap_err().to_string();
+        assert!(error.contains("protocol version 2, expected 1"), "{}", error);
+
+        let garbage = plugin("garbage", "not json", "");
+        assert!(PluginTransformer::load(&garbage).unwrap_err().to_string().contains("invalid response"));
+        assert!(PluginTransformer::load("plugin:").is_err());
+
+        for target in [escaping, newer, garbage] {
+            let _ = std::fs::remove_file(plugin_executable(&target).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_follows_the_executable() {
+        let describe = r#"{"protocol_version":1,"name":"fp","extension":".fp"}"#;
+        let target = plugin("fingerprint", describe, "{}");
+        let before = PluginTransformer::load(&target).unwrap().fingerprint().map(str::to_string);
+        assert!(before.is_some());
+        assert_eq!(PluginTransformer::load(&target).unwrap().fingerprint().map(str::to_string), before);
+
+        // Replacing the executable invalidates cached output
+        plugin("fingerprint", describe, r#"{"protocol_version":1,"files":[]}"#);
+        assert_ne!(PluginTransformer::load(&target).unwrap().fingerprint().map(str::to_string), before);
+        let _ = std::fs::remove_file(plugin_executable(&target).unwrap());
+    }
+}
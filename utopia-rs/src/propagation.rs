@@ -0,0 +1,544 @@
+//! Constant and copy propagation
+//!
+//! A forward dataflow analysis over the structured AST. Every variable maps to
+//! a lattice value: a known literal, a copy of another variable, or unknown.
+//! Uses of known variables are rewritten and folded where possible,
+//! branches merge their states, loops iterate to a fixpoint, and inner
+//! declarations shadow outer ones.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{ast::*, optimizer::{evaluate_binary, evaluate_unary}};
+
+/// What is known about a variable at a program point
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Constant(LiteralValue),
+    Copy(String),
+}
+
+/// Known values per scope, innermost last; `None` marks a declared but unknown variable
+#[derive(Debug, Clone, Default, PartialEq)]
+struct State {
+    scopes: Vec<HashMap<String, Option<Value>>>,
+}
+
+impl State {
+    fn push(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(name))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .and_then(Option::as_ref)
+    }
+
+    /// Declare `name` in the innermost scope; it now shadows outer bindings
+    fn declare(&mut self, name: &str, value: Option<Value>) {
+        self.kill_copies_of(name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    /// Record an assignment to the innermost binding of `name`
+    fn assign(&mut self, name: &str, value: Option<Value>) {
+        self.kill_copies_of(name);
+        if let Some(slot) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+            *slot = value;
+        }
+    }
+
+    fn kill_copies_of(&mut self, name: &str) {
+        for scope in &mut self.scopes {
+            for value in scope.values_mut() {
+                if matches!(value, Some(Value::Copy(source)) if source == name) {
+                    *value = None;
+                }
+            }
+        }
+    }
+
+    /// Forget everything about variables in `names`, and copies of them
+    fn kill_all(&mut self, names: &HashSet<String>) {
+        for scope in &mut self.scopes {
+            for (name, value) in scope.iter_mut() {
+                let copies_killed = matches!(value, Some(Value::Copy(source)) if names.contains(source));
+                if names.contains(name) || copies_killed {
+                    *value = None;
+                }
+            }
+        }
+    }
+
+    /// Keep only the facts both states agree on
+    fn meet(&mut self, other: &State) {
+        for (scope, other_scope) in self.scopes.iter_mut().zip(&other.scopes) {
+            for (name, value) in scope.iter_mut() {
+                if other_scope.get(name) != Some(value) {
+                    *value = None;
+                }
+            }
+        }
+    }
+}
+
+/// Rewrites uses of variables with known values
+pub struct ConstantPropagator {
+    /// Variables assigned inside any function or lambda; calls may change them
+    assigned_in_functions: HashSet<String>,
+    /// Number of uses replaced
+    pub propagations: usize,
+    /// Number of operations folded after their operands became known
+    pub folds: usize,
+    /// Variables that held a known constant at the end of a unit
+    pub constants: HashMap<String, LiteralValue>,
+}
+
+impl ConstantPropagator {
+    pub fn new(program: &Program) -> Self {
+        let mut assigned_in_functions = HashSet::new();
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                collect_assigned(&function.body, &mut assigned_in_functions);
+            }
+            collect_function_assignments(&block.statements, &mut assigned_in_functions);
+        }
+        collect_function_assignments(&program.global_statements, &mut assigned_in_functions);
+
+        Self {
+            assigned_in_functions,
+            propagations: 0,
+            folds: 0,
+            constants: HashMap::new(),
+        }
+    }
+
+    /// Propagate through every function body and top-level statement list
+    pub fn run(&mut self, program: &mut Program) {
+        for block in &mut program.language_blocks {
+            for function in &mut block.functions {
+                self.run_function(&function.parameters, &mut function.body);
+            }
+            self.run_unit(&mut block.statements);
+        }
+        self.run_unit(&mut program.global_statements);
+    }
+
+    fn run_unit(&mut self, statements: &mut [Statement]) {
+        let mut state = State::default();
+        state.push();
+        self.statements(statements, &mut state);
+        for (name, value) in &state.scopes[0] {
+            if let Some(Value::Constant(literal)) = value {
+                self.constants.insert(name.clone(), literal.clone());
+            }
+        }
+    }
+
+    fn run_function(&mut self, parameters: &[Parameter], body: &mut [Statement]) {
+        let mut state = State::default();
+        state.push();
+        for parameter in parameters {
+            state.declare(&parameter.name, None);
+        }
+        self.statements(body, &mut state);
+    }
+
+    fn statements(&mut self, statements: &mut [Statement], state: &mut State) {
+        for statement in statements {
+            self.statement(statement, state);
+        }
+    }
+
+    fn scoped(&mut self, statements: &mut [Statement], state: &mut State) {
+        state.push();
+        self.statements(statements, state);
+        state.pop();
+    }
+
+    fn statement(&mut self, statement: &mut Statement, state: &mut State) {
+        match statement {
+            Statement::Expression { expression, .. } => self.expression(expression, state),
+            Statement::VariableDeclaration { name, value, .. } => {
+                let known = match value {
+                    Some(value) => {
+                        self.expression(value, state);
+                        known_value(value, state)
+                    }
+                    None => None,
+                };
+                state.declare(name, known);
+            }
+            Statement::Assignment { target, value, .. } => {
+                self.expression(value, state);
+                self.assignment_target(target, state);
+                if let Expression::Identifier { name, .. } = target {
+                    let known = known_value(value, state);
+                    state.assign(name, known);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.expression(condition, state);
+                match literal_condition(condition) {
+                    Some(true) => self.scoped(then_branch, state),
+                    Some(false) => {
+                        if let Some(else_branch) = else_branch {
+                            self.scoped(else_branch, state);
+                        }
+                    }
+                    None => {
+                        let mut else_state = state.clone();
+                        self.scoped(then_branch, state);
+                        if let Some(else_branch) = else_branch {
+                            self.scoped(else_branch, &mut else_state);
+                        }
+                        state.meet(&else_state);
+                    }
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                let mut header = self.loop_header(state, |propagator, state| {
+                    let mut condition = condition.clone();
+                    propagator.expression(&mut condition, state);
+                    propagator.scoped(&mut body.clone(), state);
+                });
+                self.expression(condition, &mut header);
+                let mut body_state = header.clone();
+                self.scoped(body, &mut body_state);
+                *state = header;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                state.push();
+                if let Some(init) = init {
+                    self.statement(init, state);
+                }
+                let mut header = self.loop_header(state, |propagator, state| {
+                    if let Some(condition) = condition {
+                        propagator.expression(&mut condition.clone(), state);
+                    }
+                    propagator.scoped(&mut body.clone(), state);
+                    if let Some(update) = update {
+                        propagator.expression(&mut update.clone(), state);
+                    }
+                });
+                if let Some(condition) = condition {
+                    self.expression(condition, &mut header);
+                }
+                let mut body_state = header.clone();
+                self.scoped(body, &mut body_state);
+                if let Some(update) = update {
+                    self.expression(update, &mut body_state);
+                }
+                *state = header;
+                state.pop();
+            }
+            Statement::Return { value: Some(value), .. } => self.expression(value, state),
+            Statement::Block { statements, .. } => self.scoped(statements, state),
+            Statement::FunctionDeclaration { name, parameters, body, .. } => {
+                state.declare(name, None);
+                let mut propagator = ConstantPropagator {
+                    assigned_in_functions: self.assigned_in_functions.clone(),
+                    propagations: 0,
+                    folds: 0,
+                    constants: HashMap::new(),
+                };
+                propagator.run_function(parameters, body);
+                self.propagations += propagator.propagations;
+                self.folds += propagator.folds;
+            }
+            Statement::ClassDeclaration { name, .. } => state.declare(name, None),
+            Statement::Return { value: None, .. } | Statement::Import { .. } | Statement::Export { .. } => {}
+        }
+    }
+
+    /// Iterate a loop body on a scratch copy until the state at the loop head stops changing
+    fn loop_header<F>(&mut self, entry: &State, mut iterate: F) -> State
+    where
+        F: FnMut(&mut ConstantPropagator, &mut State),
+    {
+        let mut scratch = ConstantPropagator {
+            assigned_in_functions: self.assigned_in_functions.clone(),
+            propagations: 0,
+            folds: 0,
+            constants: HashMap::new(),
+        };
+        let mut header = entry.clone();
+        loop {
+            let mut after = header.clone();
+            iterate(&mut scratch, &mut after);
+            let mut next = header.clone();
+            next.meet(&after);
+            if next == header {
+                return header;
+            }
+            header = next;
+        }
+    }
+
+    /// Rewrite the parts of an assignment target that are reads (`a[i]`, `obj.x`)
+    fn assignment_target(&mut self, target: &mut Expression, state: &mut State) {
+        match target {
+            Expression::ArrayAccess { array, index, .. } => {
+                self.assignment_target(array, state);
+                self.expression(index, state);
+            }
+            Expression::MemberAccess { object, .. } => self.assignment_target(object, state),
+            _ => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression, state: &mut State) {
+        match expression {
+            Expression::Identifier { name, span, .. } => {
+                let replacement = match state.lookup(name) {
+                    Some(Value::Constant(value)) => Some(Expression::Literal { value: value.clone(), span: *span }),
+                    Some(Value::Copy(source)) => Some(Expression::Identifier { name: source.clone(), resolved_type: None, span: *span }),
+                    None => None,
+                };
+                if let Some(replacement) = replacement {
+                    *expression = replacement;
+                    self.propagations += 1;
+                }
+            }
+            Expression::Binary { left, operator, right, span } => {
+                self.expression(left, state);
+                if matches!(operator, BinaryOperator::And | BinaryOperator::Or) {
+                    // The right operand may not run, so its effects are only possible
+                    let skipped = state.clone();
+                    self.expression(right, state);
+                    state.meet(&skipped);
+                } else {
+                    self.expression(right, state);
+                }
+                if let (Expression::Literal { value: left, .. }, Expression::Literal { value: right, .. }) = (left.as_ref(), right.as_ref()) {
+                    if let Some(value) = evaluate_binary(left, operator, right) {
+                        *expression = Expression::Literal { value, span: *span };
+                        self.folds += 1;
+                    }
+                }
+            }
+            Expression::Unary { operator, operand, span } => {
+                self.expression(operand, state);
+                if let Expression::Literal { value, .. } = operand.as_ref() {
+                    if let Some(value) = evaluate_unary(operator, value) {
+                        *expression = Expression::Literal { value, span: *span };
+                        self.folds += 1;
+                    }
+                }
+            }
+            Expression::Postfix { operand, .. } => {
+                self.assignment_target(operand, state);
+                if let Expression::Identifier { name, .. } = operand.as_ref() {
+                    state.assign(name, None);
+                }
+            }
+            Expression::Assignment { target, value, .. } => {
+                self.expression(value, state);
+                self.assignment_target(target, state);
+                if let Expression::Identifier { name, .. } = target.as_ref() {
+                    let known = known_value(value, state);
+                    state.assign(name, known);
+                }
+            }
+            Expression::Call { callee, arguments, .. } => {
+                if !matches!(callee.as_ref(), Expression::Identifier { .. }) {
+                    self.expression(callee, state);
+                }
+                for argument in arguments.iter_mut() {
+                    self.expression(argument, state);
+                }
+                state.kill_all(&self.assigned_in_functions);
+            }
+            Expression::CrossCall { arguments, .. } => {
+                for argument in arguments.iter_mut() {
+                    self.expression(argument, state);
+                }
+                state.kill_all(&self.assigned_in_functions);
+            }
+            Expression::MemberAccess { object, .. } => self.expression(object, state),
+            Expression::ArrayAccess { array, index, .. } => {
+                self.expression(array, state);
+                self.expression(index, state);
+            }
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.expression(element, state);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values_mut() {
+                    self.expression(value, state);
+                }
+            }
+            Expression::Literal { .. } | Expression::Lambda { .. } => {}
+        }
+    }
+}
+
+/// The lattice value a variable takes when assigned `value`
+fn known_value(value: &Expression, state: &State) -> Option<Value> {
+    match value {
+        Expression::Literal { value, .. } => Some(Value::Constant(value.clone())),
+        Expression::Identifier { name, .. } if state.is_declared(name) => Some(Value::Copy(name.clone())),
+        _ => None,
+    }
+}
+
+fn literal_condition(condition: &Expression) -> Option<bool> {
+    match condition {
+        Expression::Literal { value: LiteralValue::Boolean(value), .. } => Some(*value),
+        _ => None,
+    }
+}
+
+/// Names assigned anywhere in `statements`, including nested blocks and lambdas
+pub fn collect_assigned(statements: &[Statement], names: &mut HashSet<String>) {
+    collect_assignment_targets(statements, names);
+    for statement in statements {
+        statement.walk_expressions(&mut |expression| match expression {
+            Expression::Assignment { target, .. } | Expression::Postfix { operand: target, .. } => {
+                if let Expression::Identifier { name, .. } = target.as_ref() {
+                    names.insert(name.clone());
+                }
+            }
+            Expression::Lambda { body, .. } => collect_assignment_targets(body, names),
+            _ => {}
+        });
+    }
+}
+
+/// Targets of assignment statements, which `walk_expressions` only sees as plain expressions
+fn collect_assignment_targets(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::Assignment { target: Expression::Identifier { name, .. }, .. } => {
+                names.insert(name.clone());
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                collect_assignment_targets(then_branch, names);
+                if let Some(else_branch) = else_branch {
+                    collect_assignment_targets(else_branch, names);
+                }
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } | Statement::FunctionDeclaration { body, .. } => {
+                collect_assignment_targets(body, names);
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    collect_assignment_targets(std::slice::from_ref(init.as_ref()), names);
+                }
+                collect_assignment_targets(body, names);
+            }
+            Statement::ClassDeclaration { methods, .. } => {
+                for method in methods {
+                    collect_assignment_targets(&method.body, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Assignments made inside the functions, methods and lambdas declared in a statement list
+fn collect_function_assignments(statements: &[Statement], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Statement::FunctionDeclaration { body, .. } => collect_assigned(body, names),
+            Statement::ClassDeclaration { methods, .. } => {
+                for method in methods {
+                    collect_assigned(&method.body, names);
+                }
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                collect_function_assignments(then_branch, names);
+                if let Some(else_branch) = else_branch {
+                    collect_function_assignments(else_branch, names);
+                }
+            }
+            Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+                collect_function_assignments(body, names);
+            }
+            _ => {}
+        }
+        // Lambdas can run whenever they are called
+        statement.walk_expressions(&mut |expression| {
+            if let Expression::Lambda { body, .. } = expression {
+                collect_assigned(body, names);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::PrettyPrinter, optimizer::Optimizer, pipeline::{NoHooks, PipelineOptions}, Compiler, Config};
+
+    fn optimize(source: &str) -> String {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+        let optimized = Optimizer::for_level(1).optimize(program).unwrap();
+        PrettyPrinter::new().print(&optimized)
+    }
+
+    #[test]
+    fn test_constants_fold_in_python_and_c() {
+        let main = "@lang main {\n    const n = 4;\n    let y = n * 2;\n    println(y + 1);\n}\n";
+        let source = format!("@lang python {{\n    const n = 4;\n    let y = n * 2;\n    print(y);\n}}\n{}", main);
+        let mut compiler = Compiler::new(Config::new());
+
+        // Once propagated, `y` is unused and removed at -O2
+        let python = compiler.compile(&source, "python").unwrap();
+        assert!(!python.contains("y = "), "{}", python);
+        assert!(python.contains("print(8)"), "{}", python);
+
+        // C only runs the main block
+        let c = compiler.compile(main, "c").unwrap();
+        assert!(!c.contains("double y"), "{}", c);
+        assert!(c.contains("printf(\"%g\\n\", 9.0);"), "{}", c);
+        let options = PipelineOptions::new("c").bundle_runtime(true);
+        let bundled = compiler.run(main, &options, &mut NoHooks).unwrap().code.unwrap();
+        if let Some(printed) = crate::runtime::run_c("folded", &bundled) {
+            assert_eq!(printed, "9\n");
+        }
+    }
+
+    #[test]
+    fn test_reassignment_and_copies() {
+        let output = optimize("@lang python {\n    let a = input();\n    let b = a;\n    print(b);\n    a = 3;\n    print(b, a);\n}\n");
+        assert!(output.contains("print(a);"), "{}", output);
+        assert!(output.contains("print(b, 3);"), "{}", output);
+    }
+
+    #[test]
+    fn test_branches_and_loops() {
+        let output = optimize(
+            "@lang python {\n    let a = 1;\n    let b = 2;\n    if (c) {\n        a = 5;\n        b = 2;\n    }\n    print(a, b);\n\
+             \n    let i = 0;\n    let step = 1;\n    while (i < 10) {\n        i = i + step;\n    }\n    print(i, step);\n}\n",
+        );
+        assert!(output.contains("print(a, 2);"), "{}", output);
+        assert!(output.contains("while (i < 10)"), "{}", output);
+        assert!(output.contains("i = i + 1;"), "{}", output);
+        assert!(output.contains("print(i, 1);"), "{}", output);
+    }
+
+    #[test]
+    fn test_dead_branches_and_calls() {
+        let output = optimize(
+            "@lang python {\n    const debug = false;\n    let count = 0;\n    function bump() {\n        count = count + 1;\n    }\n\
+             \n    if (debug) {\n        print(\"debug\");\n    } else {\n        bump();\n    }\n    print(count);\n}\n",
+        );
+        assert!(!output.contains("if"), "{}", output);
+        assert!(!output.contains("\"debug\""), "{}", output);
+        assert!(output.contains("print(count);"), "{}", output);
+    }
+}
@@ -0,0 +1,261 @@
+//! Name resolution for Utopia
+//!
+//! Runs between parsing and type checking. It checks that function names are
+//! unique within a language, that cross-language calls refer to functions the
+//! program defines, and that constants are never reassigned.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{ast::*, diagnostics::Diagnostic, Span};
+
+/// Resolves names in a parsed program
+pub struct Resolver {
+    /// Functions defined per language block
+    functions: HashMap<String, HashSet<String>>,
+    /// Variable scopes, innermost last; the value records whether the binding is `const`
+    scopes: Vec<HashMap<String, bool>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Resolve `program` and return the diagnostics found
+    pub fn resolve(mut self, program: &Program) -> Vec<Diagnostic> {
+        self.collect_functions(program);
+
+        self.with_scope(|resolver| resolver.resolve_statements(&program.global_statements));
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                self.resolve_function(&function.parameters, &function.body);
+            }
+            self.with_scope(|resolver| resolver.resolve_statements(&block.statements));
+        }
+
+        self.diagnostics
+    }
+
+    fn collect_functions(&mut self, program: &Program) {
+        for block in &program.language_blocks {
+            let declared = block.statements.iter().filter_map(|statement| match statement {
+                Statement::FunctionDeclaration { name, span, .. } => Some((name, *span)),
+                _ => None,
+            });
+            let functions: Vec<(&String, Span)> = block.functions.iter()
+                .map(|function| (&function.name, function.span))
+                .chain(declared)
+                .collect();
+
+            let defined = self.functions.entry(block.language.clone()).or_default();
+            for (name, span) in functions {
+                if !defined.insert(name.clone()) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("function `{}` is defined more than once in @lang {}", name, block.language),
+                        span,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn with_scope<F: FnOnce(&mut Self)>(&mut self, body: F) {
+        self.scopes.push(HashMap::new());
+        body(self);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &str, is_const: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), is_const);
+        }
+    }
+
+    fn is_const(&self, name: &str) -> bool {
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    fn resolve_function(&mut self, parameters: &[Parameter], body: &[Statement]) {
+        self.with_scope(|resolver| {
+            for parameter in parameters {
+                resolver.declare(&parameter.name, false);
+            }
+            resolver.resolve_statements(body);
+        });
+    }
+
+    fn resolve_block(&mut self, statements: &[Statement]) {
+        self.with_scope(|resolver| resolver.resolve_statements(statements));
+    }
+
+    fn resolve_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Expression { expression, .. } => self.resolve_expression(expression),
+            Statement::VariableDeclaration { name, value, is_const, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+                self.declare(name, *is_const);
+            }
+            Statement::Assignment { target, value, span } => {
+                self.check_assignment_target(target, *span);
+                self.resolve_expression(target);
+                self.resolve_expression(value);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.resolve_expression(condition);
+                self.resolve_block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_block(else_branch);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.resolve_expression(condition);
+                self.resolve_block(body);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                self.with_scope(|resolver| {
+                    if let Some(init) = init {
+                        resolver.resolve_statement(init);
+                    }
+                    if let Some(condition) = condition {
+                        resolver.resolve_expression(condition);
+                    }
+                    if let Some(update) = update {
+                        resolver.resolve_expression(update);
+                    }
+                    resolver.resolve_block(body);
+                });
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+            Statement::Block { statements, .. } => self.resolve_block(statements),
+            Statement::FunctionDeclaration { parameters, body, .. } => self.resolve_function(parameters, body),
+            Statement::ClassDeclaration { methods, .. } => {
+                for method in methods {
+                    self.resolve_function(&method.parameters, &method.body);
+                }
+            }
+            Statement::Import { .. } | Statement::Export { .. } => {}
+        }
+    }
+
+    fn check_assignment_target(&mut self, target: &Expression, span: Span) {
+        if let Expression::Identifier { name, .. } = target {
+            if self.is_const(name) {
+                self.diagnostics.push(Diagnostic::error(format!("cannot assign twice to constant `{}`", name), span));
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::CrossCall { language, function, arguments, span } => {
+                let defined = self.functions.get(language).is_some_and(|functions| functions.contains(function));
+                if !defined {
+                    self.diagnostics.push(Diagnostic::warning(
+                        format!("`{}::{}` does not match any function defined in a @lang {} block", language, function, language),
+                        *span,
+                    ));
+                }
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::Assignment { target, value, span } => {
+                self.check_assignment_target(target, *span);
+                self.resolve_expression(target);
+                self.resolve_expression(value);
+            }
+            Expression::Postfix { operand, span, .. } => {
+                self.check_assignment_target(operand, *span);
+                self.resolve_expression(operand);
+            }
+            Expression::Binary { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expression::Unary { operand, .. } => self.resolve_expression(operand),
+            Expression::Call { callee, arguments, .. } => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            Expression::MemberAccess { object, .. } => self.resolve_expression(object),
+            Expression::ArrayAccess { array, index, .. } => {
+                self.resolve_expression(array);
+                self.resolve_expression(index);
+            }
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values() {
+                    self.resolve_expression(value);
+                }
+            }
+            Expression::Lambda { parameters, body, .. } => self.resolve_function(parameters, body),
+            Expression::Literal { .. } | Expression::Identifier { .. } => {}
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn resolve(source: &str) -> Vec<Diagnostic> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        Resolver::new().resolve(&program)
+    }
+
+    #[test]
+    fn test_unknown_cross_call_and_const_assignment() {
+        let diagnostics = resolve(r#"
+@lang python {
+    function helper(x) {
+        return x;
+    }
+}
+
+@lang javascript {
+    const limit = 3;
+    let a = python::helper(1);
+    let b = python::missing(2);
+    limit = 4;
+}
+"#);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 2, "{:?}", messages);
+        assert!(messages[0].contains("python::missing"));
+        assert!(messages[1].contains("constant `limit`"));
+    }
+}
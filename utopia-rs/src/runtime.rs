@@ -0,0 +1,185 @@
+//! Runtime support files shipped with generated code
+//!
+//! Generated code for some targets relies on definitions the target's
+//! standard library does not have: the Utopia value model, builtins without a
+//! native equivalent and the registry cross-language calls go through. Each
+//! such target has a `Runtime` whose sources live in `runtime/` and are built
+//! into the compiler. The files are written next to the main file, or pasted
+//! into it in place of the line that loads them when the runtime is bundled,
+//! so the output compiles without undefined symbols or external dependencies.
+
+use crate::transformers::OutputFile;
+use std::path::{Path, PathBuf};
+
+/// Support files a target's generated code depends on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Runtime {
+    /// File names and contents, written next to the main file
+    pub files: &'static [(&'static str, &'static str)],
+    /// The text of the main file that loads the files, replaced by their contents when bundled
+    pub include: &'static str,
+}
+
+impl Runtime {
+    /// The files to write next to the main file
+    pub fn output_files(&self) -> Vec<OutputFile> {
+        self.files.iter()
+            .map(|(path, contents)| OutputFile { path: PathBuf::from(path), contents: contents.to_string() })
+            .collect()
+    }
+
+    /// Whether `path` is one of the runtime's files
+    pub fn owns(&self, path: &Path) -> bool {
+        self.files.iter().any(|(name, _)| path == Path::new(name))
+    }
+
+    /// The contents of every file, to paste in place of `include`
+    ///
+    /// Lines of a file that load another runtime file, such as the header
+    /// included by a C source, are left out.
+    pub fn bundled(&self) -> String {
+        let mut bundled = String::new();
+        for (_, contents) in self.files {
+            for line in contents.lines().filter(|line| !self.include.lines().any(|include| include == *line)) {
+                bundled.push_str(line);
+                bundled.push('\n');
+            }
+            bundled.push('\n');
+        }
+        bundled.trim_end().to_string()
+    }
+}
+
+pub const PYTHON: Runtime = Runtime {
+    files: &[("utopia_runtime.py", include_str!("../runtime/utopia_runtime.py"))],
+    include: "from utopia_runtime import *",
+};
+
+/// The module defines `utopia` whether it is required or bundled
+pub const JAVASCRIPT: Runtime = Runtime {
+    files: &[("utopia_runtime.js", include_str!("../runtime/utopia_runtime.js"))],
+    include: "const utopia = require(\"./utopia_runtime.js\");",
+};
+
+pub const TYPESCRIPT: Runtime = Runtime {
+    files: &[("utopia_runtime.ts", include_str!("../runtime/utopia_runtime.ts"))],
+    include: "import { utopia } from \"./utopia_runtime\";",
+};
+
+/// Classes of the same package need no import, so a comment marks where they are bundled
+pub const JAVA: Runtime = Runtime {
+    files: &[
+        ("UtopiaValue.java", include_str!("../runtime/UtopiaValue.java")),
+        ("UtopiaRuntime.java", include_str!("../runtime/UtopiaRuntime.java")),
+    ],
+    include: "// Utopia runtime: UtopiaValue.java, UtopiaRuntime.java",
+};
+
+pub const C: Runtime = Runtime {
+    files: &[
+        ("utopia_runtime.h", include_str!("../runtime/utopia_runtime.h")),
+        ("utopia_runtime.c", include_str!("../runtime/utopia_runtime.c")),
+    ],
+    include: "#include \"utopia_runtime.h\"",
+};
+
+/// The class is in the namespace of the generated code, so a comment marks where it is bundled
+pub const CSHARP: Runtime = Runtime {
+    files: &[("UtopiaRuntime.cs", include_str!("../runtime/UtopiaRuntime.cs"))],
+    include: "// Utopia runtime: UtopiaRuntime.cs",
+};
+
+pub const RUST: Runtime = Runtime {
+    files: &[("utopia_runtime.rs", include_str!("../runtime/utopia_runtime.rs"))],
+    include: "mod utopia_runtime;\nuse utopia_runtime::*;",
+};
+
+/// Build `code`, C with its runtime bundled, with warnings as errors and run
+/// it, returning what it prints; `None` where no C compiler is installed
+#[cfg(test)]
+pub(crate) fn run_c(name: &str, code: &str) -> Option<String> {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("utopia-c-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.c"), code).unwrap();
+    let built = Command::new("cc").args(["-Wall", "-Werror", "main.c", "-o", "main", "-lm"]).current_dir(&dir).output().ok()?;
+    assert!(built.status.success(), "{}\n{}", String::from_utf8_lossy(&built.stderr), code);
+    let run = Command::new(dir.join("main")).output().unwrap();
+    assert!(run.status.success(), "{}", code);
+    std::fs::remove_dir_all(&dir).unwrap();
+    Some(String::from_utf8_lossy(&run.stdout).into_owned())
+}
+
+/// Run `output`, Python with its runtime files, returning what it prints;
+/// `None` where Python is not installed
+#[cfg(test)]
+pub(crate) fn run_python(name: &str, output: &crate::transformers::TransformOutput) -> Option<String> {
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("utopia-python-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("main.py"), output.code()).unwrap();
+    for file in &output.files {
+        std::fs::write(dir.join(&file.path), &file.contents).unwrap();
+    }
+    let run = Command::new("python3").arg("main.py").current_dir(&dir).output().ok()?;
+    assert!(run.status.success(), "{}\n{}", String::from_utf8_lossy(&run.stderr), output.code());
+    std::fs::remove_dir_all(&dir).unwrap();
+    Some(String::from_utf8_lossy(&run.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser, transformers::TransformerManager, Program};
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).tokenize().unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_runtime_files_are_written_next_to_main_file() {
+        let program = parse("let name = \"Ada\";\nprint(upper(name));\n");
+        let output = TransformerManager::new().transform("python", &program).unwrap();
+
+        assert!(output.code().contains(PYTHON.include), "{}", output.code());
+        assert_eq!(output.files.len(), 2);
+        assert_eq!(output.files[1].path, PathBuf::from("utopia_runtime.py"));
+        assert!(output.files[1].contents.contains("def cross_call("));
+    }
+
+    #[test]
+    fn test_bundling_replaces_include_and_shifts_map() {
+        let program = parse("let name = \"Ada\";\nprint(upper(name));\n");
+        let mut output = TransformerManager::new().transform("python", &program).unwrap();
+        let before = output.source_map.mappings.clone();
+        output.bundle_runtime();
+
+        assert_eq!(output.files.len(), 1);
+        assert!(output.runtime.is_none());
+        assert!(!output.code().contains(PYTHON.include), "{}", output.code());
+        assert!(output.code().contains("def cross_call("), "{}", output.code());
+        let added = PYTHON.bundled().lines().count() - 1;
+        for (old, new) in before.iter().zip(&output.source_map.mappings) {
+            assert_eq!(new.generated.line, old.generated.line + added);
+            assert_eq!(new.source, old.source);
+        }
+        let line = output.code().lines().position(|line| line.starts_with("name = ")).unwrap() + 1;
+        assert_eq!(output.source_map.original_position(line, None).map(|position| position.line), Some(1));
+    }
+
+    #[test]
+    fn test_bundled_c_runtime_omits_its_own_include() {
+        assert!(!C.bundled().contains("#include \"utopia_runtime.h\""));
+        assert!(C.bundled().contains("utopia_cross_call"));
+        assert!(C.owns(Path::new("utopia_runtime.c")));
+        assert!(!C.owns(Path::new("main.c")));
+
+        // Cross calls go through the runtime's registry
+        let program = parse("@lang python {\nfunction greet(name, loud, times) {\n    return times;\n}\nfunction now() {\n    return 0;\n}\n}\n@lang main {\nlet n = python::greet(\"Ada\", true, 1);\nlet m = python::now();\n}\n");
+        let output = TransformerManager::new().transform("c", &program).unwrap();
+        assert!(output.code().contains("utopia_cross_call(\"python\", \"greet\", 3, (utopia_value[]) {utopia_string(\"Ada\"), utopia_boolean(1), utopia_number(1.0)}).as.number"), "{}", output.code());
+        assert!(output.code().contains("utopia_cross_call(\"python\", \"now\", 0, NULL)"), "{}", output.code());
+    }
+}
@@ -0,0 +1,548 @@
+//! Algebraic rewrite rules
+//!
+//! Each rule replaces one expression shape by a cheaper equivalent: identities
+//! such as `x * 1`, strength reduction, double negation and canonical
+//! comparisons with the constant on the right. Rules on arithmetic only apply
+//! when the operands are known to be numbers, since `+` also concatenates
+//! strings. Rules that hold for real numbers but not for IEEE floats (NaN,
+//! infinities and signed zeros) only run with fast-math enabled.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{ast::*, types::Type, Span};
+
+/// A rewrite of one expression shape
+pub struct Rule {
+    pub name: &'static str,
+    /// Only applies when the whole expression is known to be a number
+    pub numeric: bool,
+    /// Changes results for some IEEE values, so it needs fast-math
+    pub fast_math: bool,
+    rewrite: fn(&Expression, &Facts) -> Option<Expression>,
+}
+
+/// Every rule, tried in order on each expression
+pub const RULES: &[Rule] = &[
+    Rule { name: "sub-zero", numeric: true, fast_math: false, rewrite: sub_zero },
+    Rule { name: "mul-one", numeric: true, fast_math: false, rewrite: mul_one },
+    Rule { name: "mul-minus-one", numeric: true, fast_math: false, rewrite: mul_minus_one },
+    Rule { name: "double-negation", numeric: true, fast_math: false, rewrite: double_negation },
+    Rule { name: "add-negated", numeric: true, fast_math: false, rewrite: add_negated },
+    Rule { name: "mul-two", numeric: true, fast_math: false, rewrite: mul_two },
+    Rule { name: "div-power-of-two", numeric: true, fast_math: false, rewrite: div_power_of_two },
+    Rule { name: "double-not", numeric: false, fast_math: false, rewrite: double_not },
+    Rule { name: "not-equality", numeric: false, fast_math: false, rewrite: not_equality },
+    Rule { name: "constant-right", numeric: false, fast_math: false, rewrite: constant_right },
+    Rule { name: "strict-self-compare", numeric: false, fast_math: false, rewrite: strict_self_compare },
+    // -0 + 0 is +0
+    Rule { name: "add-zero", numeric: true, fast_math: true, rewrite: add_zero },
+    // NaN * 0 and Infinity * 0 are NaN
+    Rule { name: "mul-zero", numeric: true, fast_math: true, rewrite: mul_zero },
+    Rule { name: "sub-self", numeric: true, fast_math: true, rewrite: sub_self },
+    // NaN == NaN is false
+    Rule { name: "self-compare", numeric: false, fast_math: true, rewrite: self_compare },
+    // !(NaN < 1) is true but NaN >= 1 is false
+    Rule { name: "not-comparison", numeric: false, fast_math: true, rewrite: not_comparison },
+    Rule { name: "div-constant", numeric: true, fast_math: true, rewrite: div_constant },
+    Rule { name: "reassociate", numeric: true, fast_math: true, rewrite: reassociate },
+];
+
+/// Applies the rewrite rules to every expression of a program
+pub struct Simplifier {
+    fast_math: bool,
+    /// Number of rewrites, by rule name
+    pub applied: BTreeMap<&'static str, usize>,
+}
+
+impl Simplifier {
+    pub fn new(fast_math: bool) -> Self {
+        Self { fast_math, applied: BTreeMap::new() }
+    }
+
+    /// Total number of rewrites made
+    pub fn rewrites(&self) -> usize {
+        self.applied.values().sum()
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        for block in &mut program.language_blocks {
+            for function in &mut block.functions {
+                let facts = Facts::for_unit(&function.parameters, &function.body);
+                self.simplify(&mut function.body, &facts);
+            }
+            let facts = Facts::for_unit(&[], &block.statements);
+            self.simplify(&mut block.statements, &facts);
+        }
+        let facts = Facts::for_unit(&[], &program.global_statements);
+        self.simplify(&mut program.global_statements, &facts);
+    }
+
+    fn simplify(&mut self, statements: &mut [Statement], facts: &Facts) {
+        for statement in statements {
+            statement.walk_expressions_mut(&mut |expression| self.rewrite(expression, facts));
+        }
+    }
+
+    /// Apply rules to `expression` until none matches; its operands are already simplified
+    fn rewrite(&mut self, expression: &mut Expression, facts: &Facts) {
+        // Each rule makes the expression smaller or canonical, the limit only guards against cycles
+        for _ in 0..8 {
+            let rewritten = RULES.iter()
+                .filter(|rule| self.fast_math || !rule.fast_math)
+                .filter(|rule| !rule.numeric || facts.is_number(expression))
+                .find_map(|rule| (rule.rewrite)(expression, facts).map(|rewritten| (rule.name, rewritten)));
+            let Some((name, rewritten)) = rewritten else {
+                return;
+            };
+            log::trace!("{}: {} => {}", name, PrettyPrinter::new().print_expression(expression), PrettyPrinter::new().print_expression(&rewritten));
+            *expression = rewritten;
+            *self.applied.entry(name).or_default() += 1;
+        }
+    }
+}
+
+/// What is known about the values of the variables of one function or block
+pub struct Facts {
+    numbers: HashSet<String>,
+}
+
+impl Facts {
+    /// Find the variables of a unit that can only ever hold numbers
+    ///
+    /// Starts from every declared variable and drops those with a non-numeric declared type
+    /// or a non-numeric value assigned anywhere, until nothing changes.
+    pub fn for_unit(parameters: &[Parameter], body: &[Statement]) -> Self {
+        let mut bindings = Vec::new();
+        for parameter in parameters {
+            bindings.push(Binding::parameter(parameter));
+        }
+        collect_bindings(body, &mut bindings);
+
+        let mut facts = Facts { numbers: bindings.iter().map(|binding| binding.name.to_string()).collect() };
+        loop {
+            let non_numeric: HashSet<&str> = bindings.iter()
+                .filter(|binding| facts.numbers.contains(binding.name) && !binding.is_number(&facts))
+                .map(|binding| binding.name)
+                .collect();
+            if non_numeric.is_empty() {
+                return facts;
+            }
+            facts.numbers.retain(|name| !non_numeric.contains(name.as_str()));
+        }
+    }
+
+    /// Whether `expression` always evaluates to a number without side effects
+    pub fn is_number(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal { value: LiteralValue::Number(_), .. } => true,
+            Expression::Identifier { name, .. } => self.numbers.contains(name),
+            Expression::Unary { operator: UnaryOperator::Minus | UnaryOperator::Plus, operand, .. } => self.is_number(operand),
+            Expression::Binary { left, operator, right, .. } => {
+                matches!(
+                    operator,
+                    BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo
+                ) && self.is_number(left) && self.is_number(right)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `expression` always evaluates to a boolean
+    pub fn is_boolean(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal { value: LiteralValue::Boolean(_), .. } | Expression::Unary { operator: UnaryOperator::Not, .. } => true,
+            Expression::Binary { left, operator, right, .. } => match operator {
+                BinaryOperator::And | BinaryOperator::Or => self.is_boolean(left) && self.is_boolean(right),
+                operator => is_comparison(*operator),
+            },
+            _ => false,
+        }
+    }
+}
+
+/// A declaration of or assignment to a variable
+struct Binding<'a> {
+    name: &'a str,
+    declared_type: Option<&'a Type>,
+    value: Option<&'a Expression>,
+}
+
+impl<'a> Binding<'a> {
+    fn parameter(parameter: &'a Parameter) -> Self {
+        // Parameters without a declared type can be passed anything
+        let declared_type = Some(parameter.param_type.as_ref().unwrap_or(&Type::Unknown));
+        Self { name: &parameter.name, declared_type, value: parameter.default_value.as_ref() }
+    }
+
+    fn is_number(&self, facts: &Facts) -> bool {
+        match (self.declared_type, self.value) {
+            (Some(Type::Number), None) => true,
+            (Some(Type::Number) | None, Some(value)) => facts.is_number(value),
+            _ => false,
+        }
+    }
+}
+
+fn collect_bindings<'a>(statements: &'a [Statement], bindings: &mut Vec<Binding<'a>>) {
+    for statement in statements {
+        match statement {
+            Statement::VariableDeclaration { name, value, var_type, .. } => {
+                bindings.push(Binding { name, declared_type: var_type.as_ref(), value: value.as_ref() });
+            }
+            Statement::Assignment { target: Expression::Identifier { name, .. }, value, .. } => {
+                bindings.push(Binding { name, declared_type: None, value: Some(value) });
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                collect_bindings(then_branch, bindings);
+                if let Some(else_branch) = else_branch {
+                    collect_bindings(else_branch, bindings);
+                }
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => collect_bindings(body, bindings),
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    collect_bindings(std::slice::from_ref(init.as_ref()), bindings);
+                }
+                collect_bindings(body, bindings);
+            }
+            Statement::FunctionDeclaration { parameters, body, .. } => {
+                bindings.extend(parameters.iter().map(Binding::parameter));
+                collect_bindings(body, bindings);
+            }
+            Statement::ClassDeclaration { methods, .. } => {
+                for method in methods {
+                    bindings.extend(method.parameters.iter().map(Binding::parameter));
+                    collect_bindings(&method.body, bindings);
+                }
+            }
+            _ => {}
+        }
+        statement.walk_expressions(&mut |expression| match expression {
+            Expression::Assignment { target, value, .. } => {
+                if let Expression::Identifier { name, .. } = target.as_ref() {
+                    bindings.push(Binding { name, declared_type: None, value: Some(value) });
+                }
+            }
+            Expression::Lambda { parameters, body, .. } => {
+                bindings.extend(parameters.iter().map(Binding::parameter));
+                collect_bindings(body, bindings);
+            }
+            // i++ and i-- keep numbers numbers
+            _ => {}
+        });
+    }
+}
+
+fn is_comparison(operator: BinaryOperator) -> bool {
+    matches!(
+        operator,
+        BinaryOperator::Equal | BinaryOperator::NotEqual | BinaryOperator::Less | BinaryOperator::LessEqual | BinaryOperator::Greater | BinaryOperator::GreaterEqual
+    )
+}
+
+/// The numeric value of a literal such as `2` or `-2`
+fn constant(expression: &Expression) -> Option<f64> {
+    match expression {
+        Expression::Literal { value: LiteralValue::Number(value), .. } => Some(*value),
+        Expression::Unary { operator: UnaryOperator::Minus, operand, .. } => constant(operand).map(|value| -value),
+        _ => None,
+    }
+}
+
+fn number(value: f64, span: Span) -> Expression {
+    Expression::Literal { value: LiteralValue::Number(value), span }
+}
+
+fn boolean(value: bool, span: Span) -> Expression {
+    Expression::Literal { value: LiteralValue::Boolean(value), span }
+}
+
+fn binary(left: Expression, operator: BinaryOperator, right: Expression, span: Span) -> Expression {
+    Expression::Binary { left: Box::new(left), operator, right: Box::new(right), span }
+}
+
+fn negate(operand: Expression, span: Span) -> Expression {
+    Expression::Unary { operator: UnaryOperator::Minus, operand: Box::new(operand), span }
+}
+
+/// Structural equality, ignoring source positions
+fn same(left: &Expression, right: &Expression) -> bool {
+    PrettyPrinter::new().print_expression(left) == PrettyPrinter::new().print_expression(right)
+}
+
+/// Evaluating `expression` twice, or not at all, is unobservable
+fn is_side_effect_free(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal { .. } | Expression::Identifier { .. } => true,
+        Expression::Unary { operand, .. } => is_side_effect_free(operand),
+        Expression::Binary { left, right, .. } => is_side_effect_free(left) && is_side_effect_free(right),
+        _ => false,
+    }
+}
+
+/// `left op right` as a pair of operands, when `expression` uses `operator`
+fn operands<'a>(expression: &'a Expression, operator: BinaryOperator) -> Option<(&'a Expression, &'a Expression, Span)> {
+    match expression {
+        Expression::Binary { left, operator: found, right, span } if *found == operator => Some((left, right, *span)),
+        _ => None,
+    }
+}
+
+/// The other operand of a commutative operation when one side is the constant `value`
+fn other_operand(expression: &Expression, operator: BinaryOperator, value: f64) -> Option<Expression> {
+    let (left, right, _) = operands(expression, operator)?;
+    if constant(right) == Some(value) {
+        Some(left.clone())
+    } else if constant(left) == Some(value) {
+        Some(right.clone())
+    } else {
+        None
+    }
+}
+
+fn sub_zero(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let (left, right, _) = operands(expression, BinaryOperator::Subtract)?;
+    (constant(right) == Some(0.0)).then(|| left.clone())
+}
+
+fn mul_one(expression: &Expression, _: &Facts) -> Option<Expression> {
+    if let Some((left, right, _)) = operands(expression, BinaryOperator::Divide) {
+        return (constant(right) == Some(1.0)).then(|| left.clone());
+    }
+    other_operand(expression, BinaryOperator::Multiply, 1.0)
+}
+
+fn mul_minus_one(expression: &Expression, _: &Facts) -> Option<Expression> {
+    other_operand(expression, BinaryOperator::Multiply, -1.0).map(|operand| negate(operand, expression.span()))
+}
+
+fn double_negation(expression: &Expression, _: &Facts) -> Option<Expression> {
+    match expression {
+        Expression::Unary { operator: UnaryOperator::Minus, operand, .. } => match operand.as_ref() {
+            Expression::Unary { operator: UnaryOperator::Minus, operand, .. } => Some(operand.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `x + -y` is `x - y` and `x - -y` is `x + y`
+fn add_negated(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Binary { left, operator, right, span } = expression else {
+        return None;
+    };
+    let Expression::Unary { operator: UnaryOperator::Minus, operand, .. } = right.as_ref() else {
+        return None;
+    };
+    let operator = match operator {
+        BinaryOperator::Add => BinaryOperator::Subtract,
+        BinaryOperator::Subtract => BinaryOperator::Add,
+        _ => return None,
+    };
+    Some(binary(left.as_ref().clone(), operator, operand.as_ref().clone(), *span))
+}
+
+/// `x * 2` is `x + x`, exactly, when `x` is a plain variable
+fn mul_two(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let operand = other_operand(expression, BinaryOperator::Multiply, 2.0)?;
+    matches!(operand, Expression::Identifier { .. })
+        .then(|| binary(operand.clone(), BinaryOperator::Add, operand, expression.span()))
+}
+
+/// Dividing by a power of two is multiplying by its exactly representable reciprocal
+fn div_power_of_two(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let (left, right, span) = operands(expression, BinaryOperator::Divide)?;
+    let divisor = constant(right)?;
+    let reciprocal = 1.0 / divisor;
+    let exact = divisor.abs() > 1.0 && reciprocal.is_normal() && divisor.abs().log2().fract() == 0.0;
+    exact.then(|| binary(left.clone(), BinaryOperator::Multiply, number(reciprocal, span), span))
+}
+
+fn double_not(expression: &Expression, facts: &Facts) -> Option<Expression> {
+    match expression {
+        Expression::Unary { operator: UnaryOperator::Not, operand, .. } => match operand.as_ref() {
+            Expression::Unary { operator: UnaryOperator::Not, operand, .. } if facts.is_boolean(operand) => Some(operand.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `!(a == b)` is `a != b` and the other way round
+fn not_equality(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Unary { operator: UnaryOperator::Not, operand, span } = expression else {
+        return None;
+    };
+    let Expression::Binary { left, operator, right, .. } = operand.as_ref() else {
+        return None;
+    };
+    let operator = match operator {
+        BinaryOperator::Equal => BinaryOperator::NotEqual,
+        BinaryOperator::NotEqual => BinaryOperator::Equal,
+        _ => return None,
+    };
+    Some(binary(left.as_ref().clone(), operator, right.as_ref().clone(), *span))
+}
+
+/// `!(a < b)` is `a >= b` unless an operand is NaN
+fn not_comparison(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Unary { operator: UnaryOperator::Not, operand, span } = expression else {
+        return None;
+    };
+    let Expression::Binary { left, operator, right, .. } = operand.as_ref() else {
+        return None;
+    };
+    let operator = match operator {
+        BinaryOperator::Less => BinaryOperator::GreaterEqual,
+        BinaryOperator::LessEqual => BinaryOperator::Greater,
+        BinaryOperator::Greater => BinaryOperator::LessEqual,
+        BinaryOperator::GreaterEqual => BinaryOperator::Less,
+        _ => return None,
+    };
+    Some(binary(left.as_ref().clone(), operator, right.as_ref().clone(), *span))
+}
+
+/// Comparisons keep a literal on the right: `3 < x` becomes `x > 3`
+fn constant_right(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Binary { left, operator, right, span } = expression else {
+        return None;
+    };
+    if !matches!(left.as_ref(), Expression::Literal { .. }) || matches!(right.as_ref(), Expression::Literal { .. }) {
+        return None;
+    }
+    let operator = match operator {
+        BinaryOperator::Equal => BinaryOperator::Equal,
+        BinaryOperator::NotEqual => BinaryOperator::NotEqual,
+        BinaryOperator::Less => BinaryOperator::Greater,
+        BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+        BinaryOperator::Greater => BinaryOperator::Less,
+        BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+        _ => return None,
+    };
+    Some(binary(right.as_ref().clone(), operator, left.as_ref().clone(), *span))
+}
+
+/// `x < x` and `x > x` are false for every value, NaN included
+fn strict_self_compare(expression: &Expression, facts: &Facts) -> Option<Expression> {
+    let Expression::Binary { left, operator: BinaryOperator::Less | BinaryOperator::Greater, right, span } = expression else {
+        return None;
+    };
+    (facts.is_number(left) && same(left, right)).then(|| boolean(false, *span))
+}
+
+fn self_compare(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Binary { left, operator, right, span } = expression else {
+        return None;
+    };
+    let result = match operator {
+        BinaryOperator::Equal | BinaryOperator::LessEqual | BinaryOperator::GreaterEqual => true,
+        BinaryOperator::NotEqual => false,
+        _ => return None,
+    };
+    (is_side_effect_free(left) && same(left, right)).then(|| boolean(result, *span))
+}
+
+fn add_zero(expression: &Expression, _: &Facts) -> Option<Expression> {
+    other_operand(expression, BinaryOperator::Add, 0.0)
+}
+
+fn mul_zero(expression: &Expression, _: &Facts) -> Option<Expression> {
+    other_operand(expression, BinaryOperator::Multiply, 0.0).map(|_| number(0.0, expression.span()))
+}
+
+fn sub_self(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let (left, right, span) = operands(expression, BinaryOperator::Subtract)?;
+    same(left, right).then(|| number(0.0, span))
+}
+
+fn div_constant(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let (left, right, span) = operands(expression, BinaryOperator::Divide)?;
+    let divisor = constant(right)?;
+    let reciprocal = 1.0 / divisor;
+    (divisor.abs() != 1.0 && reciprocal.is_normal()).then(|| binary(left.clone(), BinaryOperator::Multiply, number(reciprocal, span), span))
+}
+
+/// `(x + 1) + 2` is `x + 3` and `(x * 2) * 3` is `x * 6` for real numbers
+fn reassociate(expression: &Expression, _: &Facts) -> Option<Expression> {
+    let Expression::Binary { left, operator, right, span } = expression else {
+        return None;
+    };
+    let outer = constant(right)?;
+    let Expression::Binary { left: inner_left, operator: inner_operator, right: inner_right, .. } = left.as_ref() else {
+        return None;
+    };
+    let inner = constant(inner_right)?;
+    let combined = match (operator, inner_operator) {
+        (BinaryOperator::Add, BinaryOperator::Add) => inner + outer,
+        (BinaryOperator::Add, BinaryOperator::Subtract) => outer - inner,
+        (BinaryOperator::Subtract, BinaryOperator::Add) => inner - outer,
+        (BinaryOperator::Subtract, BinaryOperator::Subtract) => -(inner + outer),
+        (BinaryOperator::Multiply, BinaryOperator::Multiply) => inner * outer,
+        _ => return None,
+    };
+    let (operator, combined) = match operator {
+        BinaryOperator::Multiply => (BinaryOperator::Multiply, combined),
+        _ if combined < 0.0 => (BinaryOperator::Subtract, -combined),
+        _ => (BinaryOperator::Add, combined),
+    };
+    Some(binary(inner_left.as_ref().clone(), operator, number(combined, *span), *span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    /// Simplify `source`, check it prints the same before and after, and return the simplified program
+    fn simplify(source: &str, fast_math: bool) -> (String, Simplifier) {
+        let original = parse(source);
+        let mut simplified = original.clone();
+        let mut simplifier = Simplifier::new(fast_math);
+        simplifier.run(&mut simplified);
+
+        let printed = PrettyPrinter::new().print(&simplified);
+        let expected = Interpreter::new().run(&original).unwrap();
+        assert_eq!(expected, Interpreter::new().run(&simplified).unwrap(), "{}", printed);
+        (printed, simplifier)
+    }
+
+    #[test]
+    fn test_exact_rules() {
+        let (output, simplifier) = simplify(
+            "let a = 5;\nlet b = a * 1 - 0;\nlet c = -(-b) * -1;\nlet d = a * 2 + a / 4;\nlet e = b + -a;\n\
+             let f = !(a == b);\nlet g = 3 < a;\nlet h = !(!(a > b));\nlet i = a < a;\nprint(b, c, d, e, f, g, h, i);\n",
+            false,
+        );
+        for expected in [
+            "let b = a;", "let c = -b;", "let d = a + a + a * 0.25;", "let e = b - a;",
+            "let f = a != b;", "let g = a > 3;", "let h = a > b;", "let i = false;",
+        ] {
+            assert!(output.contains(expected), "missing `{}` in\n{}", expected, output);
+        }
+        assert_eq!(simplifier.applied["constant-right"], 1);
+    }
+
+    #[test]
+    fn test_float_unsafe_rules_need_fast_math() {
+        let source = "let a = 5;\nlet b = a * 0 + (a - a);\nlet c = (a + 1) + 2;\nlet d = a + 0;\nlet e = !(a < 2);\nlet f = a == a;\nprint(b, c, d, e, f);\n";
+        let (output, simplifier) = simplify(source, false);
+        assert_eq!(simplifier.rewrites(), 0, "{}", output);
+
+        let (output, _) = simplify(source, true);
+        for expected in ["let b = 0;", "let c = a + 3;", "let d = a;", "let e = a >= 2;", "let f = true;"] {
+            assert!(output.contains(expected), "missing `{}` in\n{}", expected, output);
+        }
+    }
+
+    #[test]
+    fn test_leaves_strings_and_unknown_values_alone() {
+        let source = "function count() {\n    return 2;\n}\nlet s = \"n\";\nlet t = s + 0;\nlet u = count() * 1;\nlet v = 1;\nv = s;\nlet w = v + 0;\nprint(t, u, w);\n";
+        let (output, simplifier) = simplify(source, true);
+        assert_eq!(simplifier.rewrites(), 0, "{}", output);
+    }
+}
@@ -0,0 +1,375 @@
+//! Source maps from generated code back to `.uto` spans
+//!
+//! While a transformer emits a statement or function it prefixes the text with
+//! a `marker` carrying the span it came from. `SourceMap::extract` strips the
+//! markers from the finished output and records where each one stood, so the
+//! map always matches the code that is actually written.
+//!
+//! Constructs a backend cannot translate travel the same way: an
+//! `unsupported_marker` ahead of the placeholder names the construct and its
+//! span, and `take_unsupported` collects them from the finished output.
+//!
+//! JavaScript and TypeScript maps are written in the standard version 3
+//! format that browsers and Node.js understand; every other target gets a
+//! JSON sidecar listing the same mappings. `utopia run` uses the map to
+//! rewrite stack traces of the generated program to `.uto` locations.
+
+use std::path::{Path, PathBuf};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+use crate::Span;
+
+/// Delimiters of a marker; private-use characters never produced by a backend
+const MARKER_START: char = '\u{E000}';
+const MARKER_END: char = '\u{E001}';
+/// First character of an unsupported marker's tag, which a location never starts with
+const UNSUPPORTED_TAG: char = '!';
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Tag the code that follows with the location it was generated from
+pub fn marker(span: Span) -> String {
+    format!("{}{}:{}{}", MARKER_START, span.line, span.column, MARKER_END)
+}
+
+/// Tag the code that follows as a placeholder for `construct`, which the
+/// backend could not translate
+pub fn unsupported_marker(construct: &str, span: Span) -> String {
+    format!(
+        "{}{}{}:{}:{}:{}:{}{}",
+        MARKER_START, UNSUPPORTED_TAG, span.start, span.end, span.line, span.column, construct, MARKER_END
+    )
+}
+
+/// Remove the unsupported markers from `code`, keeping its location markers,
+/// and return the constructs they name in order; a construct generated more
+/// than once, such as the body of an unrolled loop, is listed once per span
+pub fn take_unsupported(code: &str) -> (String, Vec<(String, Span)>) {
+    let mut rest = String::with_capacity(code.len());
+    let mut unsupported: Vec<(String, Span)> = Vec::new();
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        if c != MARKER_START {
+            rest.push(c);
+            continue;
+        }
+        let tag: String = chars.by_ref().take_while(|&c| c != MARKER_END).collect();
+        match tag.strip_prefix(UNSUPPORTED_TAG).and_then(parse_unsupported) {
+            Some((construct, span)) => {
+                if !unsupported.iter().any(|(_, seen)| *seen == span) {
+                    unsupported.push((construct, span));
+                }
+            }
+            None => {
+                rest.push(MARKER_START);
+                rest.push_str(&tag);
+                rest.push(MARKER_END);
+            }
+        }
+    }
+    (rest, unsupported)
+}
+
+/// `code` with its markers removed
+pub fn strip_markers(code: &str) -> String {
+    let mut clean = String::with_capacity(code.len());
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        if c == MARKER_START {
+            chars.by_ref().find(|&c| c == MARKER_END);
+        } else {
+            clean.push(c);
+        }
+    }
+    clean
+}
+
+/// A 1-based line and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A location in the generated code and the source location it came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mapping {
+    pub generated: Position,
+    pub source: Position,
+}
+
+/// How a map is written next to the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SourceMapFormat {
+    /// Utopia's own JSON listing of the mappings, in `<output>.map.json`
+    #[default]
+    Sidecar,
+    /// Source Map Revision 3, in `<output>.map`
+    V3,
+}
+
+/// Mappings of one generated file, in generated order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    pub format: SourceMapFormat,
+    pub mappings: Vec<Mapping>,
+}
+
+#[derive(Serialize)]
+struct V3<'a> {
+    version: u8,
+    file: &'a str,
+    sources: [&'a str; 1],
+    names: [&'a str; 0],
+    mappings: String,
+}
+
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    version: u8,
+    file: &'a str,
+    source: &'a str,
+    mappings: &'a [Mapping],
+}
+
+impl SourceMap {
+    /// Strip the markers from `code`, returning the clean code and its map
+    pub fn extract(code: &str, format: SourceMapFormat) -> (String, Self) {
+        let mut clean = String::with_capacity(code.len());
+        let mut mappings: Vec<Mapping> = Vec::new();
+        let mut generated = Position { line: 1, column: 1 };
+
+        let mut chars = code.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                MARKER_START => {
+                    let tag: String = chars.by_ref().take_while(|&c| c != MARKER_END).collect();
+                    let Some(source) = parse_marker(&tag) else { continue };
+                    // A statement that starts with a nested one maps to the inner, more precise span
+                    if mappings.last().is_some_and(|last| last.generated == generated) {
+                        mappings.pop();
+                    }
+                    mappings.push(Mapping { generated, source });
+                }
+                '\n' => {
+                    clean.push(c);
+                    generated = Position { line: generated.line + 1, column: 1 };
+                }
+                _ => {
+                    clean.push(c);
+                    generated.column += 1;
+                }
+            }
+        }
+
+        (clean, Self { format, mappings })
+    }
+
+    /// Source location of a generated line and, if known, column
+    ///
+    /// Without a column the first mapping on the line is used. A line without
+    /// mappings belongs to the closest mapping above it.
+    pub fn original_position(&self, line: usize, column: Option<usize>) -> Option<Position> {
+        let on_line: Vec<&Mapping> = self.mappings.iter().filter(|mapping| mapping.generated.line == line).collect();
+        let found = match column {
+            Some(column) => on_line.iter().rev().find(|mapping| mapping.generated.column <= column).or(on_line.first()),
+            None => on_line.first(),
+        };
+        found.copied()
+            .or_else(|| self.mappings.iter().rev().find(|mapping| mapping.generated.line < line))
+            .map(|mapping| mapping.source)
+    }
+
+    /// Account for `count` lines inserted into the generated code after `line`
+    pub fn shift_lines(&mut self, line: usize, count: usize) {
+        for mapping in self.mappings.iter_mut().filter(|mapping| mapping.generated.line > line) {
+            mapping.generated.line += count;
+        }
+    }
+
+    /// Where the map of `output` is written
+    pub fn path_for(&self, output: &Path) -> PathBuf {
+        let suffix = match self.format {
+            SourceMapFormat::V3 => ".map",
+            SourceMapFormat::Sidecar => ".map.json",
+        };
+        let mut path = output.as_os_str().to_owned();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// Render the map of generated `file`, compiled from `source`
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let rendered = match self.format {
+            SourceMapFormat::V3 => serde_json::to_string(&V3 {
+                version: 3,
+                file,
+                sources: [source],
+                names: [],
+                mappings: self.encode_mappings(),
+            }),
+            SourceMapFormat::Sidecar => serde_json::to_string_pretty(&Sidecar {
+                version: 1,
+                file,
+                source,
+                mappings: &self.mappings,
+            }),
+        };
+        rendered.unwrap_or_default()
+    }
+
+    /// The `mappings` field of a version 3 map: one group per generated
+    /// line, each segment holding base64 VLQ deltas of the 0-based generated
+    /// column, source index, source line and source column
+    fn encode_mappings(&self) -> String {
+        let mut encoded = String::new();
+        let mut line = 1;
+        let mut previous_source = Position { line: 1, column: 1 };
+        let mut previous_column = 1;
+
+        for (index, mapping) in self.mappings.iter().enumerate() {
+            if mapping.generated.line > line {
+                for _ in line..mapping.generated.line {
+                    encoded.push(';');
+                }
+                line = mapping.generated.line;
+                previous_column = 1;
+            } else if index > 0 {
+                encoded.push(',');
+            }
+            encode_vlq(delta(mapping.generated.column, previous_column), &mut encoded);
+            encode_vlq(0, &mut encoded);
+            encode_vlq(delta(mapping.source.line, previous_source.line), &mut encoded);
+            encode_vlq(delta(mapping.source.column, previous_source.column), &mut encoded);
+            previous_column = mapping.generated.column;
+            previous_source = mapping.source;
+        }
+        encoded
+    }
+
+    /// Rewrite references to the generated file in a stack trace or error
+    /// message to the source locations they came from
+    ///
+    /// Handles `file:line:column` and `file:line` (Node.js, Go, most
+    /// compilers) as well as Python's `File "file", line n`.
+    pub fn rewrite_trace(&self, text: &str, generated: &str, source: &str) -> String {
+        let pattern = format!(r#"(?:[^\s"'()]*[/\\])?{}(", line |:)(\d+)(?::(\d+))?"#, regex::escape(generated));
+        let Ok(pattern) = Regex::new(&pattern) else { return text.to_string() };
+
+        pattern.replace_all(text, |captures: &Captures| {
+            let line = captures[2].parse().unwrap_or(0);
+            let column = captures.get(3).and_then(|column| column.as_str().parse().ok());
+            match self.original_position(line, column) {
+                Some(position) if column.is_some() => format!("{}{}{}:{}", source, &captures[1], position.line, position.column),
+                Some(position) => format!("{}{}{}", source, &captures[1], position.line),
+                None => captures[0].to_string(),
+            }
+        }).into_owned()
+    }
+}
+
+fn parse_marker(tag: &str) -> Option<Position> {
+    let (line, column) = tag.split_once(':')?;
+    Some(Position { line: line.parse().ok()?, column: column.parse().ok()? })
+}
+
+fn parse_unsupported(tag: &str) -> Option<(String, Span)> {
+    let mut fields = tag.splitn(5, ':');
+    let mut number = || fields.next()?.parse().ok();
+    let span = Span::new(number()?, number()?, number()?, number()?);
+    Some((fields.next()?.to_string(), span))
+}
+
+fn delta(value: usize, previous: usize) -> i64 {
+    i64::try_from(value).unwrap_or(i64::MAX) - i64::try_from(previous).unwrap_or(i64::MAX)
+}
+
+/// Append `value` as a base64 VLQ: the sign in the lowest bit, then five bits
+/// per digit with the sixth bit set on every digit but the last
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut rest = (value.unsigned_abs() << 1) | u64::from(value < 0);
+    loop {
+        let mut digit = rest & 0b1_1111;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0b10_0000;
+        }
+        out.push(char::from(BASE64[usize::try_from(digit).unwrap_or(0)]));
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(line: usize, column: usize) -> Span {
+        Span::new(0, 0, line, column)
+    }
+
+    #[test]
+    fn test_extract_strips_markers_and_records_positions() {
+        let code = format!("header\n{}x = 1\nif x:\n    {}print(x)\n", marker(at(2, 5)), marker(at(3, 9)));
+        let (clean, map) = SourceMap::extract(&code, SourceMapFormat::Sidecar);
+
+        assert_eq!(clean, "header\nx = 1\nif x:\n    print(x)\n");
+        let found: Vec<(usize, usize, usize, usize)> = map.mappings.iter()
+            .map(|m| (m.generated.line, m.generated.column, m.source.line, m.source.column))
+            .collect();
+        assert_eq!(found, vec![(2, 1, 2, 5), (4, 5, 3, 9)]);
+        assert_eq!(map.original_position(4, None), Some(Position { line: 3, column: 9 }));
+        // Line 3 has no mapping of its own and belongs to the statement above
+        assert_eq!(map.original_position(3, Some(1)), Some(Position { line: 2, column: 5 }));
+        assert_eq!(map.original_position(1, None), None);
+    }
+
+    #[test]
+    fn test_take_unsupported_keeps_location_markers() {
+        let literal = Span::new(12, 15, 2, 9);
+        let code = format!(
+            "{}x = {}None\n{}y = {}None\n",
+            marker(at(2, 1)), unsupported_marker("an array literal", literal),
+            marker(at(3, 1)), unsupported_marker("an array literal", literal),
+        );
+        let (rest, unsupported) = take_unsupported(&code);
+
+        assert_eq!(rest, format!("{}x = None\n{}y = None\n", marker(at(2, 1)), marker(at(3, 1))));
+        assert_eq!(unsupported, vec![("an array literal".to_string(), literal)]);
+        assert_eq!(strip_markers(&code), "x = None\ny = None\n");
+    }
+
+    #[test]
+    fn test_v3_mappings_are_vlq_encoded() {
+        let code = format!("// header\n{}a();\n\n  {}b();\n", marker(at(1, 1)), marker(at(3, 5)));
+        let (_, map) = SourceMap::extract(&code, SourceMapFormat::V3);
+        // Line 2 col 0 -> source 0:0; line 4 col 2 -> source 2:4
+        assert_eq!(map.encode_mappings(), ";AAAA;;EAEI");
+
+        let rendered = map.render("main.js", "main.uto");
+        assert_eq!(rendered, r#"{"version":3,"file":"main.js","sources":["main.uto"],"names":[],"mappings":";AAAA;;EAEI"}"#);
+        assert_eq!(map.path_for(Path::new("out/main.js")), PathBuf::from("out/main.js.map"));
+        let mut negative = String::new();
+        encode_vlq(-17, &mut negative);
+        assert_eq!(negative, "jB");
+    }
+
+    #[test]
+    fn test_rewrites_node_and_python_traces() {
+        let code = format!("import sys\n\n{}def f():\n    {}return 1 / 0\n{}f()\n", marker(at(2, 1)), marker(at(3, 5)), marker(at(5, 1)));
+        let (_, map) = SourceMap::extract(&code, SourceMapFormat::Sidecar);
+
+        let python = "Traceback (most recent call last):\n  File \"/tmp/temp_1.py\", line 5, in <module>\n  File \"/tmp/temp_1.py\", line 4, in f\n";
+        assert_eq!(
+            map.rewrite_trace(python, "temp_1.py", "app.uto"),
+            "Traceback (most recent call last):\n  File \"app.uto\", line 5, in <module>\n  File \"app.uto\", line 3, in f\n",
+        );
+        let node = "    at f (/tmp/temp_1.py:4:12)\n    at node:internal/main:1:1";
+        assert_eq!(map.rewrite_trace(node, "temp_1.py", "app.uto"), "    at f (app.uto:3:5)\n    at node:internal/main:1:1");
+        // Lines before any mapping are left alone
+        assert_eq!(map.rewrite_trace("temp_1.py:1:1", "temp_1.py", "app.uto"), "temp_1.py:1:1");
+    }
+}
@@ -0,0 +1,299 @@
+//! Tail-call elimination
+//!
+//! Rewrites functions whose `return` statements call the function itself
+//! into a `while (true)` loop. A self tail call assigns its arguments to the
+//! parameters and falls through to the next iteration, so deep recursion no
+//! longer grows the stack on targets that do not eliminate tail calls.
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::*,
+    inliner::{declared_names, has_lambda},
+    Span,
+};
+
+/// Turns self tail calls into loops
+pub struct TailCalls {
+    next_id: usize,
+    /// Tail calls replaced by a jump back to the start of their function
+    pub eliminated: usize,
+    /// Functions that now loop instead of recursing
+    pub functions: Vec<String>,
+}
+
+impl TailCalls {
+    pub fn new() -> Self {
+        Self { next_id: 0, eliminated: 0, functions: Vec::new() }
+    }
+
+    pub fn run(&mut self, program: &mut Program) {
+        for block in &mut program.language_blocks {
+            for function in &mut block.functions {
+                self.function(&function.name, &function.parameters, &mut function.body, function.span);
+            }
+            self.declarations(&mut block.statements);
+        }
+        self.declarations(&mut program.global_statements);
+    }
+
+    fn declarations(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FunctionDeclaration { name, parameters, body, span, .. } => {
+                    self.function(name, parameters, body, *span);
+                }
+                Statement::Block { statements, .. } => self.declarations(statements),
+                _ => {}
+            }
+        }
+    }
+
+    fn function(&mut self, name: &str, parameters: &[Parameter], body: &mut Vec<Statement>, span: Span) {
+        let target = SelfCall { name, arity: parameters.len() };
+        if tail_calls(body, &target) == 0 || !can_loop(parameters, body) {
+            return;
+        }
+
+        let context = Rebind {
+            parameters: parameters.iter().map(|parameter| parameter.name.clone()).collect(),
+            prefix: format!("__tco{}_", self.next_id),
+        };
+        self.next_id += 1;
+        let mut eliminated = 0;
+        let lowered = lower(std::mem::take(body), &target, &context, &mut eliminated, span);
+        *body = vec![Statement::While {
+            condition: Expression::Literal { value: LiteralValue::Boolean(true), span },
+            body: lowered,
+            span,
+        }];
+
+        log::debug!("tailcall: {} loops instead of recursing ({} tail calls)", name, eliminated);
+        self.eliminated += eliminated;
+        self.functions.push(name.to_string());
+    }
+}
+
+impl Default for TailCalls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The function whose calls to itself are being eliminated
+struct SelfCall<'a> {
+    name: &'a str,
+    arity: usize,
+}
+
+impl SelfCall<'_> {
+    fn arguments<'e>(&self, value: Option<&'e Expression>) -> Option<&'e [Expression]> {
+        match value {
+            Some(Expression::Call { callee, arguments, .. })
+                if arguments.len() == self.arity
+                    && matches!(callee.as_ref(), Expression::Identifier { name, .. } if name == self.name) =>
+            {
+                Some(arguments)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How a tail call hands its arguments to the next iteration
+struct Rebind {
+    parameters: Vec<String>,
+    prefix: String,
+}
+
+/// Number of self tail calls that `lower` can turn into jumps
+///
+/// Only returns reached through `if` statements count: leaving a loop or a
+/// nested block early would need `break`, which Utopia does not have.
+fn tail_calls(statements: &[Statement], target: &SelfCall) -> usize {
+    statements.iter().map(|statement| match statement {
+        Statement::Return { value, .. } => usize::from(target.arguments(value.as_ref()).is_some()),
+        Statement::If { then_branch, else_branch, .. } => {
+            tail_calls(then_branch, target) + else_branch.as_deref().map_or(0, |branch| tail_calls(branch, target))
+        }
+        _ => 0,
+    }).sum()
+}
+
+/// Whether the body can run in a loop that reassigns the parameters
+///
+/// Closures and nested functions could capture a parameter and observe the
+/// reassignment, and a local shadowing a parameter would receive it instead.
+fn can_loop(parameters: &[Parameter], body: &[Statement]) -> bool {
+    let mut declared = HashSet::new();
+    declared_names(body, &mut declared);
+    !has_lambda(body)
+        && !has_nested_definition(body)
+        && parameters.iter().all(|parameter| !declared.contains(&parameter.name))
+}
+
+fn has_nested_definition(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::FunctionDeclaration { .. } | Statement::ClassDeclaration { .. } => true,
+        Statement::If { then_branch, else_branch, .. } => {
+            has_nested_definition(then_branch) || else_branch.as_deref().is_some_and(has_nested_definition)
+        }
+        Statement::While { body, .. } | Statement::For { body, .. } | Statement::Block { statements: body, .. } => {
+            has_nested_definition(body)
+        }
+        _ => false,
+    })
+}
+
+/// Rewrite the body of the loop so that every path ends in a `return` or a tail call
+///
+/// As when inlining, statements following an `if` that contains a tail call
+/// are moved into both of its branches. A path falling off the end of the
+/// function gets an explicit `return` so that it does not loop again.
+fn lower(statements: Vec<Statement>, target: &SelfCall, context: &Rebind, eliminated: &mut usize, end: Span) -> Vec<Statement> {
+    let mut lowered = Vec::new();
+    let mut statements = statements.into_iter();
+    while let Some(statement) = statements.next() {
+        match statement {
+            Statement::Return { value, span } => {
+                match target.arguments(value.as_ref()) {
+                    Some(arguments) => {
+                        lowered.extend(rebind(arguments, context, span));
+                        *eliminated += 1;
+                    }
+                    None => lowered.push(Statement::Return { value, span }),
+                }
+                return lowered;
+            }
+            Statement::If { condition, then_branch, else_branch, span }
+                if tail_calls(&then_branch, target) + else_branch.as_deref().map_or(0, |branch| tail_calls(branch, target)) > 0 =>
+            {
+                let rest: Vec<Statement> = statements.collect();
+                let mut then_branch = then_branch;
+                then_branch.extend(rest.iter().cloned());
+                let mut else_branch = else_branch.unwrap_or_default();
+                else_branch.extend(rest);
+
+                let then_branch = lower(then_branch, target, context, eliminated, end);
+                let else_branch = lower(else_branch, target, context, eliminated, end);
+                lowered.push(Statement::If { condition, then_branch, else_branch: Some(else_branch), span });
+                return lowered;
+            }
+            statement => lowered.push(statement),
+        }
+    }
+    lowered.push(Statement::Return { value: None, span: end });
+    lowered
+}
+
+/// Assign the arguments of a tail call to the parameters
+///
+/// A parameter read by a later argument keeps its old value until every
+/// argument has been evaluated, through a `const` temporary.
+fn rebind(arguments: &[Expression], context: &Rebind, span: Span) -> Vec<Statement> {
+    let changed: Vec<(&String, &Expression)> = context.parameters.iter()
+        .zip(arguments)
+        .filter(|(parameter, argument)| !matches!(argument, Expression::Identifier { name, .. } if name == *parameter))
+        .collect();
+
+    let mut statements = Vec::new();
+    let mut deferred = Vec::new();
+    for (index, (parameter, argument)) in changed.iter().enumerate() {
+        if changed[index + 1..].iter().any(|(_, later)| reads(later, parameter)) {
+            let temporary = format!("{}{}", context.prefix, parameter);
+            statements.push(Statement::VariableDeclaration {
+                name: temporary.clone(),
+                value: Some((*argument).clone()),
+                var_type: None,
+                is_const: true,
+                span,
+            });
+            deferred.push((*parameter, temporary));
+        } else {
+            statements.push(assign(parameter, (*argument).clone(), span));
+        }
+    }
+    for (parameter, temporary) in deferred {
+        statements.push(assign(parameter, Expression::Identifier { name: temporary, resolved_type: None, span }, span));
+    }
+    statements
+}
+
+fn assign(parameter: &str, value: Expression, span: Span) -> Statement {
+    Statement::Assignment {
+        target: Expression::Identifier { name: parameter.to_string(), resolved_type: None, span },
+        value,
+        span,
+    }
+}
+
+fn reads(expression: &Expression, name: &str) -> bool {
+    let mut found = false;
+    expression.walk(&mut |expression| {
+        found |= matches!(expression, Expression::Identifier { name: read, .. } if read == name);
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{interpreter::Interpreter, pipeline::{NoHooks, PipelineOptions}, Compiler, Config};
+
+    const SOURCE: &str = "function sum(n, acc) {\n    if (n == 0) {\n        return acc;\n    }\n    return sum(n - 1, acc + n);\n}\n\
+        function gcd(a, b) {\n    if (b == 0) {\n        return a;\n    } else {\n        return gcd(b, a % b);\n    }\n}\n\
+        function countdown(n) {\n    if (n > 0) {\n        print(n);\n        return countdown(n - 1);\n    }\n    print(\"done\");\n}\n\
+        function fact(n) {\n    if (n <= 1) {\n        return 1;\n    }\n    return n * fact(n - 1);\n}\n\
+        print(sum(50, 0));\nprint(gcd(1071, 462));\ncountdown(3);\nprint(fact(6));\n";
+
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_self_tail_calls_become_loops() {
+        let original = parse(SOURCE);
+        let mut program = original.clone();
+        let mut tail_calls = TailCalls::new();
+        tail_calls.run(&mut program);
+
+        // `fact` multiplies the result of its recursive call, which is not a tail call
+        assert_eq!(tail_calls.functions, vec!["sum", "gcd", "countdown"]);
+        assert_eq!(tail_calls.eliminated, 3);
+        assert_eq!(Interpreter::new().run(&original).unwrap(), Interpreter::new().run(&program).unwrap());
+
+        let output = PrettyPrinter::new().print(&program);
+        assert!(output.contains("while (true) {"), "{}", output);
+        assert!(output.contains("return n * fact(n - 1);"), "{}", output);
+        // Only a parameter that a later argument still reads goes through a temporary
+        assert!(output.contains("const __tco0_n = n - 1;"), "{}", output);
+        assert!(output.contains("acc = acc + n;"), "{}", output);
+        assert!(!output.contains("__tco0_acc"), "{}", output);
+        assert!(!output.contains("return sum("), "{}", output);
+    }
+
+    #[test]
+    fn test_keeps_recursion_it_cannot_loop() {
+        let source = "function shadowed(n) {\n    if (n > 0) {\n        let n = 0;\n        return shadowed(n);\n    }\n    return n;\n}\n\
+            function in_loop(n) {\n    while (n > 0) {\n        return in_loop(n - 1);\n    }\n    return n;\n}\n\
+            function arity(n, m) {\n    if (n > 0) {\n        return arity(n - 1);\n    }\n    return m;\n}\n";
+        let mut program = parse(source);
+        let mut tail_calls = TailCalls::new();
+        tail_calls.run(&mut program);
+        assert!(tail_calls.functions.is_empty());
+        assert_eq!(PrettyPrinter::new().print(&program), PrettyPrinter::new().print(&parse(source)));
+    }
+
+    #[test]
+    fn test_recursion_friendly_targets_opt_out() {
+        let mut compiler = Compiler::new(Config::new());
+        let python = compiler.run(SOURCE, &PipelineOptions::new("python"), &mut NoHooks).unwrap();
+        assert!(python.optimization_stats.unwrap().tail_calls > 0);
+        assert!(python.code.unwrap().contains("while True:"));
+
+        let scheme = compiler.run(SOURCE, &PipelineOptions::new("scheme"), &mut NoHooks).unwrap();
+        assert_eq!(scheme.optimization_stats.unwrap().tail_calls, 0);
+        assert!(scheme.pass_reports.iter().all(|report| report.pass != crate::optimizer::OptimizationPass::TailCallElimination));
+    }
+}
@@ -0,0 +1,661 @@
+//! User-defined targets rendered from templates
+//!
+//! A target directory describes a new target without recompiling the
+//! compiler. `target.toml` names the target, its file extension and template
+//! engine, and maps Utopia keywords and operators to the target's spelling;
+//! `templates/` holds one template per AST node kind:
+//!
+//! ```text
+//! targets/mylang/
+//!     target.toml
+//!     templates/
+//!         program.tera
+//!         function.tera
+//!         if.tera
+//!         ...
+//! ```
+//!
+//! Nodes are rendered bottom-up: every template receives its children already
+//! rendered, with nested bodies indented by the target's `indent`. Templates
+//! are written for Tera (`.tera`) or Handlebars (`.hbs`), as chosen by
+//! `engine`. A statement kind without a template is reported as a construct
+//! the target cannot translate, like a missing case in a built-in backend;
+//! expressions without one fall back to C-like syntax with the mapped
+//! operators. The variables each template receives are listed in
+//! `docs/cli-reference.md`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use handlebars::Handlebars;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tera::Tera;
+
+use crate::{
+    ast::*,
+    cache::content_hash,
+    capabilities::{Capabilities, Feature},
+    sourcemap,
+    transformers::{unsupported_body, unsupported_expression, unsupported_statement, Transformer},
+    Result,
+};
+
+/// Manifest of a target directory
+pub const TARGET_MANIFEST: &str = "target.toml";
+
+/// Node kinds a target directory can provide templates for
+pub const TEMPLATE_KINDS: [&str; 26] = [
+    // Declarations and statements
+    "program", "function", "class", "expression", "variable", "assignment", "if", "while",
+    "for", "return", "import", "export", "block",
+    // Expressions
+    "literal", "identifier", "binary", "unary", "postfix", "assign", "call", "cross_call",
+    "member", "index", "array", "object", "lambda",
+];
+
+/// Expression kinds that have a built-in rendering when no template is given
+const DEFAULT_EXPRESSIONS: [&str; 10] = [
+    "literal", "identifier", "binary", "unary", "postfix", "assign", "call", "member", "index", "array",
+];
+
+/// Settings read from `target.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: String,
+    extension: String,
+    #[serde(default)]
+    engine: EngineKind,
+    #[serde(default = "default_indent")]
+    indent: String,
+    /// Line comment prefix, used for placeholders
+    #[serde(default = "default_comment")]
+    comment: String,
+    /// Utopia keywords and identifiers to their spelling in the target
+    #[serde(default)]
+    keywords: BTreeMap<String, String>,
+    /// Utopia operators to their spelling in the target
+    #[serde(default)]
+    operators: BTreeMap<String, String>,
+}
+
+fn default_indent() -> String {
+    "    ".to_string()
+}
+
+fn default_comment() -> String {
+    "//".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum EngineKind {
+    #[default]
+    Tera,
+    Handlebars,
+}
+
+impl EngineKind {
+    /// Extension of the template files the engine reads
+    fn template_extension(self) -> &'static str {
+        match self {
+            EngineKind::Tera => "tera",
+            EngineKind::Handlebars => "hbs",
+        }
+    }
+}
+
+/// Compiled templates of a target
+#[derive(Clone)]
+enum Engine {
+    Tera(Box<Tera>),
+    Handlebars(Box<Handlebars<'static>>),
+}
+
+/// A target loaded at run time from a template directory
+#[derive(Clone)]
+pub struct TemplateTransformer {
+    manifest: Manifest,
+    engine: Engine,
+    kinds: BTreeSet<String>,
+    dir: PathBuf,
+    fingerprint: String,
+}
+
+impl TemplateTransformer {
+    /// Load the target described by `dir/target.toml` and `dir/templates/`
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let manifest_path = dir.join(TARGET_MANIFEST);
+        let manifest_source = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("Cannot read {}: {}", manifest_path.display(), e))?;
+        let mut manifest: Manifest = toml::from_str(&manifest_source)
+            .map_err(|e| format!("Invalid {}: {}", manifest_path.display(), e))?;
+        if !manifest.extension.starts_with('.') {
+            manifest.extension.insert(0, '.');
+        }
+
+        let template_dir = dir.join("templates");
+        let entries = fs::read_dir(&template_dir)
+            .map_err(|e| format!("Cannot read {}: {}", template_dir.display(), e))?;
+        let mut templates = BTreeMap::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(manifest.engine.template_extension()) {
+                continue;
+            }
+            let kind = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+            if !TEMPLATE_KINDS.contains(&kind.as_str()) {
+                return Err(format!("{}: unknown node kind '{}' (expected one of {})", path.display(), kind, TEMPLATE_KINDS.join(", ")).into());
+            }
+            templates.insert(kind, fs::read_to_string(&path)?);
+        }
+        if !templates.contains_key("program") {
+            return Err(format!("{} has no program.{} template", template_dir.display(), manifest.engine.template_extension()).into());
+        }
+
+        let mut fingerprint_source = manifest_source;
+        for (kind, source) in &templates {
+            fingerprint_source.push_str(kind);
+            fingerprint_source.push_str(source);
+        }
+
+        let engine = match manifest.engine {
+            EngineKind::Tera => {
+                let mut tera = Tera::default();
+                tera.autoescape_on(Vec::new());
+                for (kind, source) in &templates {
+                    tera.add_raw_template(kind, source)
+                        .map_err(|e| format!("{} template '{}': {}", manifest.name, kind, describe(&e)))?;
+                }
+                Engine::Tera(Box::new(tera))
+            }
+            EngineKind::Handlebars => {
+                let mut handlebars = Handlebars::new();
+                handlebars.register_escape_fn(handlebars::no_escape);
+                for (kind, source) in &templates {
+                    handlebars.register_template_string(kind, source)
+                        .map_err(|e| format!("{} template '{}': {}", manifest.name, kind, describe(&e)))?;
+                }
+                Engine::Handlebars(Box::new(handlebars))
+            }
+        };
+
+        Ok(Self {
+            manifest,
+            engine,
+            kinds: templates.into_keys().collect(),
+            dir: dir.to_path_buf(),
+            fingerprint: content_hash(fingerprint_source.as_bytes()),
+        })
+    }
+
+    /// Directory the target was loaded from
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Hash of the manifest and templates, so cached output is rebuilt when they change
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    fn has(&self, kind: &str) -> bool {
+        self.kinds.contains(kind)
+    }
+
+    fn render(&self, kind: &str, context: Value) -> Result<String> {
+        let rendered = match &self.engine {
+            Engine::Tera(tera) => tera::Context::from_value(context)
+                .and_then(|context| tera.render(kind, &context))
+                .map_err(|e| describe(&e)),
+            Engine::Handlebars(handlebars) => handlebars.render(kind, &context).map_err(|e| describe(&e)),
+        };
+        rendered
+            .map(|code| code.trim_end().to_string())
+            .map_err(|e| format!("{} template '{}': {}", self.manifest.name, kind, e).into())
+    }
+
+    /// `word` as the target spells it
+    fn keyword(&self, word: &str) -> String {
+        self.manifest.keywords.get(word).cloned().unwrap_or_else(|| word.to_string())
+    }
+
+    /// `symbol` as the target spells it
+    fn operator(&self, symbol: &str) -> String {
+        self.manifest.operators.get(symbol).cloned().unwrap_or_else(|| symbol.to_string())
+    }
+
+    fn statement_placeholder(&self) -> String {
+        format!("{} Unsupported statement", self.manifest.comment)
+    }
+
+    fn expression_placeholder(&self) -> String {
+        format!("{0} Unsupported expression {0}", self.manifest.comment)
+    }
+
+    /// `statements` rendered one per line and indented one level
+    fn body(&self, statements: &[Statement]) -> Result<String> {
+        let rendered = statements.iter()
+            .map(|statement| self.statement(statement))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self.indented(&rendered.join("\n")))
+    }
+
+    /// `code` indented one level
+    fn indented(&self, code: &str) -> String {
+        code.lines()
+            .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", self.manifest.indent, line) })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn function(&self, name: &str, parameters: &[Parameter], body: &[Statement], exported: bool) -> Result<String> {
+        let parameters: Vec<String> = parameters.iter().map(|parameter| self.keyword(&parameter.name)).collect();
+        self.render("function", json!({
+            "name": self.keyword(name),
+            "parameters": parameters,
+            "body": self.body(body)?,
+            "exported": exported,
+        }))
+    }
+
+    /// A function or method, tagged with its location for the source map
+    fn function_definition(&self, function: &Function) -> Result<String> {
+        let code = if self.has("function") {
+            self.function(&function.name, &function.parameters, &function.body, function.is_exported)?
+        } else {
+            format!("{}{}", unsupported_body(function), self.statement_placeholder())
+        };
+        Ok(format!("{}{}", sourcemap::marker(function.span), code))
+    }
+
+    /// `statement`, tagged with its location for the source map
+    fn statement(&self, statement: &Statement) -> Result<String> {
+        Ok(format!("{}{}", sourcemap::marker(statement.span()), self.statement_text(statement)?))
+    }
+
+    fn statement_text(&self, statement: &Statement) -> Result<String> {
+        let kind = statement_kind(statement);
+        if !self.has(kind) {
+            return Ok(unsupported_statement(statement, &self.statement_placeholder()));
+        }
+        let context = match statement {
+            Statement::Expression { expression, .. } => json!({ "expression": self.expression(expression)? }),
+            Statement::VariableDeclaration { name, value, is_const, .. } => json!({
+                "name": self.keyword(name),
+                "value": self.optional_expression(value.as_ref())?,
+                "constant": is_const,
+            }),
+            Statement::Assignment { target, value, .. } => json!({
+                "target": self.expression(target)?,
+                "value": self.expression(value)?,
+            }),
+            Statement::If { condition, then_branch, else_branch, .. } => json!({
+                "condition": self.expression(condition)?,
+                "then_branch": self.body(then_branch)?,
+                "else_branch": else_branch.as_deref().map(|branch| self.body(branch)).transpose()?,
+            }),
+            Statement::While { condition, body, .. } => json!({
+                "condition": self.expression(condition)?,
+                "body": self.body(body)?,
+            }),
+            Statement::For { init, condition, update, body, .. } => json!({
+                "init": init.as_deref().map(|init| self.statement_text(init)).transpose()?,
+                "condition": self.optional_expression(condition.as_ref())?,
+                "update": self.optional_expression(update.as_ref())?,
+                "body": self.body(body)?,
+            }),
+            Statement::Return { value, .. } => json!({ "value": self.optional_expression(value.as_ref())? }),
+            Statement::Import { module, items, .. } => json!({ "module": module, "items": items }),
+            Statement::Export { item, .. } => json!({ "item": self.keyword(item) }),
+            Statement::Block { statements, .. } => json!({ "body": self.body(statements)? }),
+            Statement::FunctionDeclaration { name, parameters, body, .. } => {
+                return self.function(name, parameters, body, false);
+            }
+            Statement::ClassDeclaration { name, superclass, methods, fields, .. } => {
+                let fields = fields.iter()
+                    .map(|field| Ok(json!({
+                        "name": self.keyword(&field.name),
+                        "value": self.optional_expression(field.default_value.as_ref())?,
+                    })))
+                    .collect::<Result<Vec<_>>>()?;
+                let methods = methods.iter()
+                    .map(|method| Ok(self.indented(&self.function_definition(method)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                json!({
+                    "name": self.keyword(name),
+                    "superclass": superclass,
+                    "fields": fields,
+                    "methods": methods,
+                })
+            }
+        };
+        self.render(kind, context)
+    }
+
+    fn optional_expression(&self, expression: Option<&Expression>) -> Result<Option<String>> {
+        expression.map(|expression| self.expression(expression)).transpose()
+    }
+
+    fn expressions(&self, expressions: &[Expression]) -> Result<Vec<String>> {
+        expressions.iter().map(|expression| self.expression(expression)).collect()
+    }
+
+    /// Operand of a binary or unary operator, parenthesized when it binds more loosely
+    fn operand(&self, operand: &Expression, precedence: u8, right: bool) -> Result<String> {
+        let code = self.expression(operand)?;
+        let needs_parentheses = match operand {
+            Expression::Binary { operator, .. } => {
+                operator.precedence() < precedence || (right && operator.precedence() == precedence)
+            }
+            Expression::Assignment { .. } => true,
+            _ => false,
+        };
+        Ok(if needs_parentheses { format!("({})", code) } else { code })
+    }
+
+    fn expression(&self, expression: &Expression) -> Result<String> {
+        let kind = expression_kind(expression);
+        if !self.has(kind) && !DEFAULT_EXPRESSIONS.contains(&kind) {
+            return Ok(unsupported_expression(expression, &self.expression_placeholder()));
+        }
+        let (context, fallback) = match expression {
+            Expression::Literal { value, .. } => {
+                let (literal, raw, code) = match value {
+                    LiteralValue::Number(n) => ("number", n.to_string(), n.to_string()),
+                    LiteralValue::String(s) => ("string", s.clone(), format!("\"{}\"", s)),
+                    LiteralValue::Boolean(b) => ("boolean", b.to_string(), self.keyword(&b.to_string())),
+                    LiteralValue::Null => ("null", "null".to_string(), self.keyword("null")),
+                };
+                (json!({ "kind": literal, "raw": raw, "value": code }), code)
+            }
+            Expression::Identifier { name, .. } => {
+                let name = self.keyword(name);
+                (json!({ "name": name }), name)
+            }
+            Expression::Binary { left, operator, right, .. } => {
+                let left = self.operand(left, operator.precedence(), false)?;
+                let right = self.operand(right, operator.precedence(), true)?;
+                let operator = self.operator(binary_symbol(*operator));
+                let code = format!("{} {} {}", left, operator, right);
+                (json!({ "left": left, "operator": operator, "right": right }), code)
+            }
+            Expression::Unary { operator, operand, .. } => {
+                let operand = self.operand(operand, u8::MAX, false)?;
+                let symbol = match operator {
+                    UnaryOperator::Not => "!",
+                    UnaryOperator::Minus => "-",
+                    UnaryOperator::Plus => "+",
+                };
+                let operator = self.operator(symbol);
+                // Word operators such as `not` need a space before their operand
+                let separator = if operator.ends_with(|c: char| c.is_alphanumeric()) { " " } else { "" };
+                let code = format!("{}{}{}", operator, separator, operand);
+                (json!({ "operator": operator, "operand": operand }), code)
+            }
+            Expression::Postfix { operand, operator, .. } => {
+                let operand = self.operand(operand, u8::MAX, false)?;
+                let operator = self.operator(match operator {
+                    PostfixOperator::Increment => "++",
+                    PostfixOperator::Decrement => "--",
+                });
+                let code = format!("{}{}", operand, operator);
+                (json!({ "operand": operand, "operator": operator }), code)
+            }
+            Expression::Assignment { target, value, .. } => {
+                let target = self.expression(target)?;
+                let value = self.expression(value)?;
+                let code = format!("{} = {}", target, value);
+                (json!({ "target": target, "value": value }), code)
+            }
+            Expression::Call { callee, arguments, .. } => {
+                let callee = self.expression(callee)?;
+                let arguments = self.expressions(arguments)?;
+                let code = format!("{}({})", callee, arguments.join(", "));
+                (json!({ "callee": callee, "arguments": arguments }), code)
+            }
+            Expression::CrossCall { language, function, arguments, .. } => {
+                let arguments = self.expressions(arguments)?;
+                (json!({ "language": language, "function": function, "arguments": arguments }), String::new())
+            }
+            Expression::MemberAccess { object, property, .. } => {
+                let object = self.operand(object, u8::MAX, false)?;
+                let code = format!("{}.{}", object, property);
+                (json!({ "object": object, "property": property }), code)
+            }
+            Expression::ArrayAccess { array, index, .. } => {
+                let array = self.operand(array, u8::MAX, false)?;
+                let index = self.expression(index)?;
+                let code = format!("{}[{}]", array, index);
+                (json!({ "array": array, "index": index }), code)
+            }
+            Expression::Array { elements, .. } => {
+                let elements = self.expressions(elements)?;
+                let code = format!("[{}]", elements.join(", "));
+                (json!({ "elements": elements }), code)
+            }
+            Expression::Object { properties, .. } => {
+                let properties = properties.iter()
+                    .map(|(key, value)| Ok(json!({ "key": key, "value": self.expression(value)? })))
+                    .collect::<Result<Vec<_>>>()?;
+                (json!({ "properties": properties }), String::new())
+            }
+            Expression::Lambda { parameters, body, .. } => {
+                let parameters: Vec<String> = parameters.iter().map(|parameter| self.keyword(&parameter.name)).collect();
+                (json!({ "parameters": parameters, "body": self.body(body)? }), String::new())
+            }
+        };
+        if self.has(kind) {
+            self.render(kind, context)
+        } else {
+            Ok(fallback)
+        }
+    }
+}
+
+impl Transformer for TemplateTransformer {
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut functions = Vec::new();
+        let mut statements = Vec::new();
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                functions.push(self.function_definition(function)?);
+            }
+            for statement in &block.statements {
+                statements.push(self.statement(statement)?);
+            }
+        }
+        for statement in &program.global_statements {
+            statements.push(self.statement(statement)?);
+        }
+
+        let mut code = self.render("program", json!({
+            "target": self.manifest.name,
+            "languages": program.metadata.languages,
+            "functions": functions,
+            "statements": statements,
+        }))?;
+        code.push('\n');
+        Ok(code)
+    }
+
+    fn target_name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn file_extension(&self) -> &str {
+        &self.manifest.extension
+    }
+
+    fn supports_language(&self, language: &str) -> bool {
+        language == self.manifest.name
+    }
+
+    /// Constructs are available when their templates are
+    fn capabilities(&self) -> Capabilities {
+        let mut missing = Vec::new();
+        for (feature, kinds) in [
+            (Feature::Classes, &["class"][..]),
+            (Feature::Lambdas, &["lambda"]),
+            (Feature::DynamicObjects, &["object"]),
+            (Feature::CrossCalls, &["cross_call"]),
+            (Feature::Modules, &["import", "export"]),
+        ] {
+            if !kinds.iter().all(|kind| self.has(kind)) {
+                missing.push(feature);
+            }
+        }
+        Capabilities::without(&missing)
+    }
+}
+
+fn statement_kind(statement: &Statement) -> &'static str {
+    match statement {
+        Statement::Expression { .. } => "expression",
+        Statement::VariableDeclaration { .. } => "variable",
+        Statement::Assignment { .. } => "assignment",
+        Statement::If { .. } => "if",
+        Statement::While { .. } => "while",
+        Statement::For { .. } => "for",
+        Statement::Return { .. } => "return",
+        Statement::Import { .. } => "import",
+        Statement::Export { .. } => "export",
+        Statement::Block { .. } => "block",
+        Statement::FunctionDeclaration { .. } => "function",
+        Statement::ClassDeclaration { .. } => "class",
+    }
+}
+
+fn expression_kind(expression: &Expression) -> &'static str {
+    match expression {
+        Expression::Literal { .. } => "literal",
+        Expression::Identifier { .. } => "identifier",
+        Expression::Binary { .. } => "binary",
+        Expression::Unary { .. } => "unary",
+        Expression::Postfix { .. } => "postfix",
+        Expression::Assignment { .. } => "assign",
+        Expression::Call { .. } => "call",
+        Expression::CrossCall { .. } => "cross_call",
+        Expression::MemberAccess { .. } => "member",
+        Expression::ArrayAccess { .. } => "index",
+        Expression::Array { .. } => "array",
+        Expression::Object { .. } => "object",
+        Expression::Lambda { .. } => "lambda",
+    }
+}
+
+/// How `operator` is written in Utopia, the key of the operator table
+fn binary_symbol(operator: BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Equal => "==",
+        BinaryOperator::NotEqual => "!=",
+        BinaryOperator::Less => "<",
+        BinaryOperator::LessEqual => "<=",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::GreaterEqual => ">=",
+        BinaryOperator::And => "&&",
+        BinaryOperator::Or => "||",
+    }
+}
+
+/// `error` followed by its causes, since template engines keep the useful
+/// part (line and column) in the source chain
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser, transformers::TransformerManager};
+
+    fn parse(source: &str) -> Program {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    /// A target directory holding `manifest` and the given templates
+    fn target_dir(name: &str, manifest: &str, templates: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("utopia-templates-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("templates")).unwrap();
+        fs::write(dir.join(TARGET_MANIFEST), manifest).unwrap();
+        for (file, source) in templates {
+            fs::write(dir.join("templates").join(file), source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_tera_target_maps_keywords_and_indents_bodies() {
+        let dir = target_dir("tera", "name = \"mini\"\nextension = \"mini\"\nindent = \"  \"\n\
+            [keywords]\ntrue = \"yes\"\n[operators]\n\"&&\" = \"and\"\n\"!\" = \"not\"\n", &[
+            ("program.tera", "{% for statement in statements %}{{ statement }}\n{% endfor %}"),
+            ("variable.tera", "var {{ name }} := {{ value }}"),
+            ("if.tera", "when {{ condition }}\n{{ then_branch }}\n{% if else_branch %}otherwise\n{{ else_branch }}\n{% endif %}done"),
+            ("while.tera", "loop {{ condition }}\n{{ body }}\ndone"),
+        ]);
+        let target = TemplateTransformer::load(&dir).unwrap();
+        let program = parse("let a = true;\nif (a && !a) {\n    while (a) {\n        let b = 1 + 2 * 3;\n    }\n} else {\n    let c = (1 + 2) * 3;\n}");
+
+        let output = target.transform(&program).unwrap();
+        assert_eq!(output.code(), "var a := yes\n\
+            when a and not a\n  loop a\n    var b := 1 + 2 * 3\n  done\notherwise\n  var c := (1 + 2) * 3\ndone\n");
+        assert_eq!(output.metadata.get("extension").map(String::as_str), Some(".mini"));
+        assert!(!output.is_partial());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_handlebars_target_reports_missing_templates() {
+        let dir = target_dir("handlebars", "name = \"bars\"\nextension = \".bars\"\nengine = \"handlebars\"\ncomment = \"#\"\n", &[
+            ("program.hbs", "{{#each functions}}{{this}}\n{{/each}}{{#each statements}}{{this}}\n{{/each}}"),
+            ("function.hbs", "fn {{name}}({{#each parameters}}{{#unless @first}}, {{/unless}}{{this}}{{/each}})\n{{body}}"),
+            ("return.hbs", "give {{value}}"),
+        ]);
+        let target = TemplateTransformer::load(&dir).unwrap();
+        let program = parse("@lang main {\n    function add(a, b) {\n        return a < b;\n    }\n}\nwhile (true) {\n}");
+
+        let output = target.transform(&program).unwrap();
+        assert_eq!(output.code(), "fn add(a, b)\n    give a < b\n# Unsupported statement\n");
+        assert_eq!(output.diagnostics.len(), 1);
+        assert_eq!(output.diagnostics[0].message, "bars backend cannot translate a while loop");
+        assert_eq!(output.source_map.mappings.len(), 3);
+        // Features without templates are reported before generation
+        assert!(!target.capabilities().supports(Feature::Classes));
+        assert!(target.capabilities().supports(Feature::Arrays));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_target_dir_registers_target() {
+        let mut manager = TransformerManager::new();
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/targets/pseudocode");
+        assert_eq!(manager.load_target_dir(dir).unwrap(), "pseudocode");
+        assert_eq!(manager.get_file_extension("pseudocode"), Some(".txt".to_string()));
+        assert_eq!(manager.template_targets().len(), 1);
+
+        let output = manager.transform("pseudocode", &parse("let x = 1;\nif (x >= 1) {\n    x = x + 1;\n}")).unwrap();
+        assert!(output.code().ends_with("SET x TO 1\nIF x ≥ 1 THEN\n    SET x TO x + 1\nEND IF\n"), "{}", output.code());
+
+        let typo = target_dir("typo", "name = \"typo\"\nextension = \".t\"\n", &[("program.tera", ""), ("whille.tera", "")]);
+        let error = TemplateTransformer::load(&typo).err().unwrap().to_string();
+        assert!(error.contains("unknown node kind 'whille'"), "{}", error);
+        let _ = fs::remove_dir_all(&typo);
+    }
+}
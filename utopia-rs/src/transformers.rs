@@ -2,14 +2,141 @@
 //!
 //! This module provides comprehensive code generation for multiple target languages
 //! and platforms, including native assembly, LLVM IR, WebAssembly, CUDA, and more.
+//! Every backend writes its output through the shared `emitter::CodeWriter`,
+//! which owns indentation, block delimiters and source-map markers.
+
+use crate::{
+    ast::*,
+    capabilities::{Capabilities, Feature},
+    diagnostics::Diagnostic,
+    emitter::{BlockStyle, CodeWriter, Escape, Keywords},
+    builtins::{self, Builtins},
+    mangle::{self, Rename},
+    operators::{self, Form, Operators},
+    sourcemap::{self, SourceMap, SourceMapFormat},
+    plugin::PluginTransformer,
+    runtime::{self, Runtime},
+    templates::TemplateTransformer,
+    types::TypeSystem,
+    Result, Span,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// A file produced by a transformer, relative to the output location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Everything a transformer produced for one program
+#[derive(Debug, Clone, Default)]
+pub struct TransformOutput {
+    /// Generated files; the first one is the main file
+    pub files: Vec<OutputFile>,
+    /// Constructs the target could not translate, with their location
+    pub diagnostics: Vec<Diagnostic>,
+    /// Facts about the output, such as the target and its file extension
+    pub metadata: BTreeMap<String, String>,
+    /// Where the code of the main file came from in the source
+    pub source_map: SourceMap,
+    /// Names that were escaped because the target reserves them
+    pub renames: Vec<Rename>,
+    /// Support files the main file loads, included in `files`
+    pub runtime: Option<Runtime>,
+}
+
+impl TransformOutput {
+    /// Output made of a single main file
+    pub fn new(target: &str, extension: &str, code: String) -> Self {
+        let mut metadata = BTreeMap::new();
+        metadata.insert("target".to_string(), target.to_string());
+        metadata.insert("extension".to_string(), extension.to_string());
+        Self {
+            files: vec![OutputFile { path: PathBuf::from(format!("main{}", extension)), contents: code }],
+            diagnostics: Vec::new(),
+            metadata,
+            source_map: SourceMap::default(),
+            renames: Vec::new(),
+            runtime: None,
+        }
+    }
+
+    /// Contents of the main file
+    pub fn code(&self) -> &str {
+        self.files.first().map_or("", |file| file.contents.as_str())
+    }
 
-use crate::{ast::*, Result};
-use std::collections::HashMap;
+    /// Whether some constructs were replaced by placeholders
+    pub fn is_partial(&self) -> bool {
+        !self.diagnostics.is_empty()
+    }
+
+    /// Paste the runtime files into the main file instead of writing them next to it
+    pub fn bundle_runtime(&mut self) {
+        let Some(runtime) = self.runtime else {
+            return;
+        };
+        let Some(main) = self.files.first_mut() else {
+            return;
+        };
+        let Some(start) = main.contents.find(runtime.include) else {
+            return;
+        };
+        let line = main.contents[..start].matches('\n').count() + 1;
+        let bundled = runtime.bundled();
+        main.contents.replace_range(start..start + runtime.include.len(), &bundled);
+        self.source_map.shift_lines(line, bundled.lines().count().saturating_sub(runtime.include.lines().count()));
+        self.files.retain(|file| !runtime.owns(&file.path));
+        self.runtime = None;
+    }
+}
 
 /// Base trait for all code generation transformers
 pub trait Transformer {
+    /// Generate the main file for the given program
+    ///
+    /// Constructs without a translation are reported with
+    /// `unsupported_statement` and `unsupported_expression`, which return the
+    /// placeholder to emit in their place. Backends write their output with a
+    /// `CodeWriter`, mapping each function and statement to its span with
+    /// `CodeWriter::map`.
+    fn generate(&self, program: &Program) -> Result<String>;
+
     /// Transform the given program to target language
-    fn transform(&self, program: &Program) -> Result<String>;
+    ///
+    /// Declared names the target reserves are escaped first (see `mangle`).
+    fn transform(&self, program: &Program) -> Result<TransformOutput> {
+        let renames = mangle::reserved_renames(program, self.keywords());
+        let mut renamed;
+        let program = if renames.is_empty() {
+            program
+        } else {
+            renamed = program.clone();
+            mangle::apply_renames(&mut renamed, &renames);
+            &renamed
+        };
+
+        // Operators and builtins are lowered by the types the checker resolves names to
+        let program = TypeSystem::new().annotate(program);
+        let (code, unsupported) = sourcemap::take_unsupported(&self.generate(&program)?);
+        let (code, source_map) = SourceMap::extract(&code, self.source_map_format());
+        let mut output = TransformOutput::new(self.target_name(), self.file_extension(), code);
+        output.source_map = source_map;
+        output.renames = renames;
+        if let Some(runtime) = self.runtime() {
+            output.files.extend(runtime.output_files());
+            output.runtime = Some(runtime);
+        }
+        output.diagnostics = unsupported.into_iter()
+            .map(|(construct, span)| Diagnostic::error(format!("{} backend cannot translate {}", self.target_name(), construct), span))
+            .collect();
+        Ok(output)
+    }
     
     /// Get the target language/platform name
     fn target_name(&self) -> &str;
@@ -19,219 +146,259 @@ pub trait Transformer {
     
     /// Check if this transformer supports the given language block
     fn supports_language(&self, language: &str) -> bool;
+
+    /// Whether the target runs tail calls without growing the stack, so the
+    /// optimizer can leave self-recursion in place
+    fn eliminates_tail_calls(&self) -> bool {
+        false
+    }
+
+    /// Language features the target can express
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    /// Whether code is generated for `@lang` blocks in `language`; blocks in
+    /// other languages are only reached through cross-language calls
+    fn generates_block(&self, language: &str) -> bool {
+        language == "main" || self.supports_language(language)
+    }
+
+    /// Whether code is generated for statements outside `@lang` blocks
+    fn generates_global_code(&self) -> bool {
+        true
+    }
+
+    /// Words the target reserves and how names spelled like them are escaped
+    fn keywords(&self) -> Keywords {
+        Keywords::NONE
+    }
+
+    /// How the source map of the output is written
+    fn source_map_format(&self) -> SourceMapFormat {
+        SourceMapFormat::Sidecar
+    }
+
+    /// Support files the generated code loads, written next to the main file
+    fn runtime(&self) -> Option<Runtime> {
+        None
+    }
+}
+
+/// Backends that write statements into a `CodeWriter`
+trait StatementEmitter {
+    /// Write the target code for `statement`
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()>;
+
+    /// Write `statement`, attributing its first line to the statement in the source map
+    fn emit_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        out.map(statement.span());
+        self.write_statement(out, statement)
+    }
+
+    fn emit_statements(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        statements.iter().try_for_each(|statement| self.emit_statement(out, statement))
+    }
+
+    /// The code for `statement` as text, for targets that embed a statement in a larger line
+    fn statement_text(&self, out: &CodeWriter, statement: &Statement) -> Result<String> {
+        let mut inner = out.nested();
+        self.emit_statement(&mut inner, statement)?;
+        Ok(inner.finish().trim_end().to_string())
+    }
+}
+
+/// The placeholder for a statement the backend cannot translate, marked for
+/// the diagnostics of `transform`
+pub(crate) fn unsupported_statement(statement: &Statement, placeholder: &str) -> String {
+    format!("{}{}", sourcemap::unsupported_marker(statement_construct(statement), statement.span()), placeholder)
+}
+
+/// Markers for statements the backend leaves out of its output because of
+/// where they are, such as global code of a target that only runs a main
+/// block; write them with `CodeWriter::note`
+pub(crate) fn unsupported_placement(statements: &[Statement], place: &str) -> String {
+    statements.iter()
+        .map(|statement| sourcemap::unsupported_marker(&format!("{} in {}", statement_construct(statement), place), statement.span()))
+        .collect()
+}
+
+fn statement_construct(statement: &Statement) -> &'static str {
+    match statement {
+        Statement::Expression { .. } => "an expression statement",
+        Statement::VariableDeclaration { .. } => "a variable declaration",
+        Statement::Assignment { .. } => "an assignment",
+        Statement::If { .. } => "an if statement",
+        Statement::While { .. } => "a while loop",
+        Statement::For { .. } => "a for loop",
+        Statement::Return { .. } => "a return statement",
+        Statement::Import { .. } => "an import",
+        Statement::Export { .. } => "an export",
+        Statement::Block { .. } => "a block",
+        Statement::FunctionDeclaration { .. } => "a function declaration",
+        Statement::ClassDeclaration { .. } => "a class declaration",
+    }
+}
+
+/// The placeholder for an expression the backend cannot translate, marked for
+/// the diagnostics of `transform`
+pub(crate) fn unsupported_expression(expression: &Expression, placeholder: &str) -> String {
+    let construct = match expression {
+        Expression::Literal { .. } => "a literal",
+        Expression::Identifier { .. } => "an identifier",
+        Expression::Binary { .. } => "a binary expression",
+        Expression::Unary { .. } => "a unary expression",
+        Expression::Postfix { .. } => "an increment or decrement",
+        Expression::Assignment { .. } => "an assignment expression",
+        Expression::Call { .. } => "a call",
+        Expression::CrossCall { .. } => "a cross-language call",
+        Expression::MemberAccess { .. } => "a member access",
+        Expression::ArrayAccess { .. } => "an index expression",
+        Expression::Array { .. } => "an array literal",
+        Expression::Object { .. } => "an object literal",
+        Expression::Lambda { .. } => "a lambda",
+    };
+    format!("{}{}", sourcemap::unsupported_marker(construct, expression.span()), placeholder)
+}
+
+/// The placeholder for a call to a builtin the backend has no lowering for
+pub(crate) fn unsupported_builtin(name: &str, span: Span, placeholder: &str) -> String {
+    format!("{}{}", sourcemap::unsupported_marker(&format!("the builtin `{}`", name), span), placeholder)
+}
+
+/// The marker for a function whose body the backend does not generate, to
+/// write ahead of the text left in its place
+pub(crate) fn unsupported_body(function: &Function) -> String {
+    sourcemap::unsupported_marker(&format!("the body of function '{}'", function.name), function.span)
 }
 
 /// Python code generator
 pub struct PythonTransformer;
 
 impl Transformer for PythonTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("#!/usr/bin/env python3\n");
-        output.push_str("# Generated by Utopia Compiler - Python Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::COLON);
+        out.line("#!/usr/bin/env python3");
+        out.line("# Generated by Utopia Compiler - Python Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.blank();
+
         // Add imports
-        output.push_str("import sys\n");
-        output.push_str("import json\n");
-        output.push_str("from typing import Any, List, Dict, Optional, Union\n\n");
-        
-        // No need for println function - we'll use print directly
-        
+        out.line("import sys");
+        out.line("import json");
+        out.line("import math");
+        out.line("from typing import Any, List, Dict, Optional, Union");
+        out.line(runtime::PYTHON.include);
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "python" || block.language == "main" {
-                output.push_str(&self.generate_python_block(block)?);
+                self.emit_python_block(&mut out, block)?;
             } else {
-                output.push_str(&format!("# Cross-language block: {}\n", block.language));
-                output.push_str(&self.generate_cross_language_stubs(block)?);
+                out.line(format!("# Cross-language block: {}", block.language));
+                self.emit_cross_language_stubs(&mut out, block);
             }
         }
-        
+
         // Global statements
-        for statement in &program.global_statements {
-            output.push_str(&self.generate_statement(statement)?);
-        }
-        
-        Ok(output)
+        self.emit_statements(&mut out, &program.global_statements)?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "python"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".py"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "python"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+            "else", "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is",
+            "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True", "try", "while",
+            "with", "yield",
+        ], Escape::Suffix("_"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(runtime::PYTHON)
+    }
 }
 
 impl PythonTransformer {
-    fn generate_python_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Python's `%` takes the sign of the divisor and `math.fmod` returns a
+    /// float, so the runtime keeps the dividend's sign and int operands ints
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "and", "or"])
+        .with(BinaryOperator::Modulo, Form::Call("utopia_mod({l}, {r})"))
+        .to_string("str({})")
+        .untyped_add(Form::Call("utopia_add({l}, {r})"));
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print({args})"),
+        ("toString", "str({0})"),
+        ("toNumber", "float({0})"),
+        ("toBoolean", "bool({0})"),
+        ("len", "len({0})"),
+        ("push", "{0}.append({1})"),
+        ("pop", "{0}.pop()"),
+        ("upper", "{0}.upper()"),
+        ("lower", "{0}.lower()"),
+        ("trim", "{0}.strip()"),
+        ("contains", "({1} in {0})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "math.sqrt({0})"),
+        ("floor", "math.floor({0})"),
+        ("ceil", "math.ceil({0})"),
+        ("round", "round({0})"),
+        ("pow", "math.pow({0}, {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "list({0}.keys())"),
+        ("hasKey", "({1} in {0})"),
+    ])
+    .placeholder("None  # Unsupported expression");
+
+    fn emit_python_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         // If we have raw content, use it directly
         if let Some(raw_content) = &block.raw_content {
-            output.push_str(raw_content);
-            output.push('\n');
-            return Ok(output);
+            out.line(raw_content);
+            return Ok(());
         }
-        
+
         // Otherwise, generate from AST (legacy support)
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        for statement in &block.statements {
-            output.push_str(&self.generate_statement(statement)?);
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        self.emit_statements(out, &block.statements)
     }
-    
-    fn generate_cross_language_stubs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_cross_language_stubs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
         for function in &block.functions {
-            output.push_str(&format!(
-                "def {}(*args, **kwargs):\n    raise NotImplementedError(\"Cross-language call to {}::{}\")\n\n",
-                function.name, block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("def {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str("):\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    pass\n");
-        } else {
-            for statement in &function.body {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-            }
+            out.block(format!("def {}(*args, **kwargs)", function.name), |out| {
+                out.line(format!("raise NotImplementedError(\"Cross-language call to {}::{}\")", block.language, function.name));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{}\n", expr_str))
-            }
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(value) = value {
-                    let value_str = self.generate_expression(value)?;
-                    Ok(format!("{} = {}\n", name, value_str))
-                } else {
-                    Ok(format!("{} = None\n", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(value) = value {
-                    Ok(format!("return {}\n", self.generate_expression(value)?))
-                } else {
-                    Ok("return\n".to_string())
-                }
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}:\n", self.generate_expression(condition)?);
-                
-                for stmt in then_branch {
-                    output.push_str("    ");
-                    output.push_str(&self.generate_statement(stmt)?);
-                }
-                
-                if let Some(else_branch) = else_branch {
-                    output.push_str("else:\n");
-                    for stmt in else_branch {
-                        output.push_str("    ");
-                        output.push_str(&self.generate_statement(stmt)?);
-                    }
-                }
-                
-                Ok(output)
-            }
-            Statement::For { init, condition, update, body, .. } => {
-                let mut output = String::new();
-                
-                // Handle initialization
-                if let Some(init_stmt) = init {
-                    output.push_str(&self.generate_statement(init_stmt)?);
-                }
-                
-                // Generate the for loop
-                output.push_str("while ");
-                if let Some(cond) = condition {
-                    output.push_str(&self.generate_expression(cond)?);
-                } else {
-                    output.push_str("True");
-                }
-                output.push_str(":\n");
-                
-                // Generate body
-                for stmt in body {
-                    output.push_str("    ");
-                    output.push_str(&self.generate_statement(stmt)?);
-                }
-                
-                // Handle update
-                if let Some(update_expr) = update {
-                    output.push_str("    ");
-                    output.push_str(&self.generate_expression(update_expr)?);
-                    output.push_str("\n");
-                }
-                
-                Ok(output)
-            }
-            Statement::FunctionDeclaration { name, parameters, return_type: _, body, .. } => {
-                let mut output = String::new();
-                
-                // Generate function definition
-                let param_names: Vec<String> = parameters.iter()
-                    .map(|p| p.name.clone())
-                    .collect();
-                output.push_str(&format!("def {}({}):\n", name, param_names.join(", ")));
-                
-                // Generate function body
-                for stmt in body {
-                    output.push_str("    ");
-                    output.push_str(&self.generate_statement(stmt)?);
-                }
-                
-                // Add default return if no explicit return
-                if !body.iter().any(|stmt| matches!(stmt, Statement::Return { .. })) {
-                    output.push_str("    return None\n");
-                }
-                
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement\n".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let parameters: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+        out.map(function.span);
+        out.block(format!("def {}({})", function.name, parameters.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -243,46 +410,25 @@ impl PythonTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Modulo => "%",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                };
-                Ok(format!("{} {} {}", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let args: Result<Vec<String>> = arguments.iter()
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Convert println to print for Python
-                if callee_str == "println" {
-                    Ok(format!("print({})", args_str))
-                } else {
+
                 Ok(format!("{}({})", callee_str, args_str))
-                }
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                Ok(format!("utopia_runtime.call_{}('{}', [{}])", language, function, args_str))
+                Ok(format!("cross_call('{}', '{}', [{}])", language, function, args_str))
             }
             Expression::Assignment { target, value, .. } => {
                 let target_str = self.generate_expression(target)?;
@@ -296,8 +442,80 @@ impl PythonTransformer {
                     PostfixOperator::Decrement => Ok(format!("{} -= 1", operand_str)),
                 }
             }
-            _ => Ok("None  # Unsupported expression".to_string()),
+            other => Ok(unsupported_expression(other, "None  # Unsupported expression")),
+        }
+    }
+}
+
+impl StatementEmitter for PythonTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::Assignment { target, value, .. } => {
+                out.line(format!("{} = {}", self.generate_expression(target)?, self.generate_expression(value)?));
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(value)?));
+                } else {
+                    out.line(format!("{} = None", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("return {}", self.generate_expression(value)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_branch)?;
+                }
+                out.close();
+            }
+            Statement::While { condition, body, .. } => {
+                out.block(format!("while {}", self.generate_expression(condition)?), |out| {
+                    self.emit_statements(out, body)
+                })?;
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                // Handle initialization
+                if let Some(init_stmt) = init {
+                    self.emit_statement(out, init_stmt)?;
+                }
+
+                let condition = match condition {
+                    Some(cond) => self.generate_expression(cond)?,
+                    None => "True".to_string(),
+                };
+                out.block(format!("while {}", condition), |out| -> Result<()> {
+                    self.emit_statements(out, body)?;
+                    if let Some(update_expr) = update {
+                        out.line(self.generate_expression(update_expr)?);
+                    }
+                    Ok(())
+                })?;
+            }
+            Statement::FunctionDeclaration { name, parameters, return_type: _, body, .. } => {
+                let param_names: Vec<&str> = parameters.iter().map(|p| p.name.as_str()).collect();
+                out.block(format!("def {}({})", name, param_names.join(", ")), |out| -> Result<()> {
+                    self.emit_statements(out, body)?;
+                    // Add default return if no explicit return
+                    if !body.iter().any(|stmt| matches!(stmt, Statement::Return { .. })) {
+                        out.line("return None");
+                    }
+                    Ok(())
+                })?;
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -313,38 +531,46 @@ impl JavaScriptTransformer {
 }
 
 impl Transformer for JavaScriptTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+
         if self.typescript {
-            output.push_str("// Generated by Utopia Compiler - TypeScript Backend\n");
+            out.line("// Generated by Utopia Compiler - TypeScript Backend");
         } else {
-            output.push_str("// Generated by Utopia Compiler - JavaScript Backend\n");
+            out.line("// Generated by Utopia Compiler - JavaScript Backend");
         }
-        output.push_str("// Multi-language support with runtime integration\n\n");
-        
+        out.line("// Multi-language support with runtime integration");
+        out.blank();
+        if let Some(runtime) = self.runtime() {
+            out.line(runtime.include);
+            out.blank();
+        }
+
         // Process language blocks
         for block in &program.language_blocks {
             if (block.language == "javascript" || block.language == "js") ||
                (self.typescript && (block.language == "typescript" || block.language == "ts")) ||
                block.language == "main" {
-                output.push_str(&self.generate_js_block(block)?);
+                self.emit_js_block(&mut out, block)?;
             } else {
-                output.push_str(&format!("// Cross-language block: {}\n", block.language));
+                // Functions of other languages are reached through the runtime's registry
+                out.line(format!("// Cross-language block: {}", block.language));
+                out.note(unsupported_placement(&block.statements, &format!("a {} block", block.language)));
             }
         }
-        
-        Ok(output)
+        out.note(unsupported_placement(&program.global_statements, "global code"));
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         if self.typescript { "typescript" } else { "javascript" }
     }
-    
+
     fn file_extension(&self) -> &str {
         if self.typescript { ".ts" } else { ".js" }
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         match language {
             "javascript" | "js" => true,
@@ -352,153 +578,183 @@ impl Transformer for JavaScriptTransformer {
             _ => false,
         }
     }
+
+    /// Global code is reported rather than generated
+    fn generates_global_code(&self) -> bool {
+        false
+    }
+
+    fn source_map_format(&self) -> SourceMapFormat {
+        SourceMapFormat::V3
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+            "default", "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally",
+            "for", "function", "if", "implements", "import", "in", "instanceof", "interface", "let", "new",
+            "null", "package", "private", "protected", "public", "return", "static", "super", "switch",
+            "this", "throw", "true", "try", "typeof", "var", "void", "while", "with", "yield",
+        ], Escape::Suffix("_"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(if self.typescript { runtime::TYPESCRIPT } else { runtime::JAVASCRIPT })
+    }
 }
 
 impl JavaScriptTransformer {
-    fn generate_js_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "===", "!==", "<", "<=", ">", ">=", "&&", "||"]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "console.log({args})"),
+        ("toString", "String({0})"),
+        ("toNumber", "Number({0})"),
+        ("toBoolean", "Boolean({0})"),
+        ("len", "{0}.length"),
+        ("push", "{0}.push({1})"),
+        ("pop", "{0}.pop()"),
+        ("upper", "{0}.toUpperCase()"),
+        ("lower", "{0}.toLowerCase()"),
+        ("trim", "{0}.trim()"),
+        ("contains", "{0}.includes({1})"),
+        ("abs", "Math.abs({0})"),
+        ("sqrt", "Math.sqrt({0})"),
+        ("floor", "Math.floor({0})"),
+        ("ceil", "Math.ceil({0})"),
+        ("round", "Math.round({0})"),
+        ("pow", "Math.pow({0}, {1})"),
+        ("min", "Math.min({0}, {1})"),
+        ("max", "Math.max({0}, {1})"),
+        ("keys", "Object.keys({0})"),
+        ("hasKey", "Object.prototype.hasOwnProperty.call({0}, {1})"),
+    ]);
+
+    fn emit_js_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle statements in the block
-        for statement in &block.statements {
-            output.push_str(&self.generate_statement(statement)?);
-        }
-        
-        Ok(output)
+        self.emit_statements(out, &block.statements)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("function {}(", function.name));
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
+        let parameters: Vec<String> = function.parameters.iter()
+            .map(|param| match (&param.param_type, self.typescript) {
+                (Some(param_type), true) => format!("{}: {}", param.name, self.convert_type(param_type)),
+                _ => param.name.clone(),
+            })
+            .collect();
+
+        // Return type for TypeScript
+        let return_type = match (&function.return_type, self.typescript) {
+            (Some(return_type), true) => format!(": {}", self.convert_type(return_type)),
+            _ => String::new(),
+        };
+
+        out.map(function.span);
+        out.block(format!("function {}({}){}", function.name, parameters.join(", "), return_type), |out| {
+            if function.body.is_empty() {
+                out.line("// Empty function");
+                Ok(())
+            } else {
+                self.emit_statements(out, &function.body)
             }
-            output.push_str(&param.name);
-            if self.typescript {
-                if let Some(ref param_type) = param.param_type {
-                    output.push_str(&format!(": {}", self.convert_type(param_type)));
+        })
+    }
+
+    fn generate_expression(&self, expression: &Expression) -> Result<String> {
+        match expression {
+            Expression::Literal { value, .. } => {
+                match value {
+                    LiteralValue::Number(n) => Ok(n.to_string()),
+                    LiteralValue::String(s) => Ok(format!("\"{}\"", s)),
+                    LiteralValue::Boolean(b) => Ok(b.to_string()),
+                    LiteralValue::Null => Ok("null".to_string()),
                 }
             }
-        }
-        
-        output.push(')');
-        
-        // Return type for TypeScript
-        if self.typescript {
-            if let Some(ref return_type) = function.return_type {
-                output.push_str(&format!(": {}", self.convert_type(return_type)));
+            Expression::Identifier { name, .. } => Ok(name.clone()),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
+                let callee_str = self.generate_expression(callee)?;
+                let mut args = Vec::new();
+                for arg in arguments {
+                    args.push(self.generate_expression(arg)?);
+                }
+
+                Ok(format!("{}({})", callee_str, args.join(", ")))
             }
-        }
-        
-        output.push_str(" {\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    // Empty function\n");
-        } else {
-            for statement in &function.body {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
+            Expression::CrossCall { language, function, arguments, .. } => {
+                let mut args = Vec::new();
+                for arg in arguments {
+                    args.push(self.generate_expression(arg)?);
+                }
+                Ok(format!("utopia.crossCall(\"{}\", \"{}\", [{}])", language, function, args.join(", ")))
             }
+            other => Ok(unsupported_expression(other, "undefined  /* Unsupported expression */")),
         }
-        
-        output.push_str("}\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
+
+    fn convert_type(&self, utopia_type: &crate::types::Type) -> String {
+        match utopia_type {
+            crate::types::Type::Number => "number".to_string(),
+            crate::types::Type::String => "string".to_string(),
+            crate::types::Type::Boolean => "boolean".to_string(),
+            crate::types::Type::Null => "null".to_string(),
+            crate::types::Type::Array(_) => "any[]".to_string(),
+            crate::types::Type::Object(_) => "object".to_string(),
+            _ => "any".to_string(),
+        }
+    }
+}
+
+impl StatementEmitter for JavaScriptTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
         match statement {
             Statement::VariableDeclaration { name, value, is_const, .. } => {
                 let keyword = if *is_const { "const" } else { "let" };
                 if let Some(value) = value {
-                    Ok(format!("{} {} = {};\n", keyword, name, self.generate_expression(value)?))
+                    out.line(format!("{} {} = {};", keyword, name, self.generate_expression(value)?));
                 } else {
-                    Ok(format!("{} {} = undefined;\n", keyword, name))
+                    out.line(format!("{} {} = undefined;", keyword, name));
                 }
             }
             Statement::Return { value, .. } => {
                 if let Some(value) = value {
-                    Ok(format!("return {};\n", self.generate_expression(value)?))
+                    out.line(format!("return {};", self.generate_expression(value)?));
                 } else {
-                    Ok("return;\n".to_string())
+                    out.line("return;");
                 }
             }
             Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{};\n", expr_str))
-            }
-            _ => Ok("// Unsupported statement\n".to_string()),
-        }
-    }
-    
-    fn generate_expression(&self, expression: &Expression) -> Result<String> {
-        match expression {
-            Expression::Literal { value, .. } => {
-                match value {
-                    LiteralValue::Number(n) => Ok(n.to_string()),
-                    LiteralValue::String(s) => Ok(format!("\"{}\"", s)),
-                    LiteralValue::Boolean(b) => Ok(b.to_string()),
-                    LiteralValue::Null => Ok("null".to_string()),
-                }
+                out.line(format!("{};", self.generate_expression(expression)?));
             }
-            Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Modulo => "%",
-                    BinaryOperator::Equal => "===",
-                    BinaryOperator::NotEqual => "!==",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                };
-                Ok(format!("{} {} {}", left_str, op_str, right_str))
+            Statement::Assignment { target, value, .. } => {
+                out.line(format!("{} = {};", self.generate_expression(target)?, self.generate_expression(value)?));
             }
-            Expression::Call { callee, arguments, .. } => {
-                let callee_str = self.generate_expression(callee)?;
-                let mut args = Vec::new();
-                for arg in arguments {
-                    args.push(self.generate_expression(arg)?);
-                }
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("console.log({})", args.join(", ")))
-                } else {
-                    Ok(format!("{}({})", callee_str, args.join(", ")))
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_branch)?;
                 }
+                out.close();
             }
-            _ => Ok("undefined  /* Unsupported expression */".to_string()),
-        }
-    }
-    
-    fn convert_type(&self, utopia_type: &crate::types::Type) -> String {
-        match utopia_type {
-            crate::types::Type::Number => "number".to_string(),
-            crate::types::Type::String => "string".to_string(),
-            crate::types::Type::Boolean => "boolean".to_string(),
-            crate::types::Type::Null => "null".to_string(),
-            crate::types::Type::Array(_) => "any[]".to_string(),
-            crate::types::Type::Object(_) => "object".to_string(),
-            _ => "any".to_string(),
+            Statement::While { condition, body, .. } => {
+                out.block(format!("while ({})", self.generate_expression(condition)?), |out| {
+                    self.emit_statements(out, body)
+                })?;
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -506,209 +762,226 @@ impl JavaScriptTransformer {
 pub struct AssemblyTransformer;
 
 impl Transformer for AssemblyTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
+    fn generate(&self, _program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+
         // Assembly header
-        output.push_str("# Generated by Utopia Compiler - x86_64 Assembly Backend\n");
-        output.push_str("# Ultimate performance native machine code\n");
-        output.push_str(".intel_syntax noprefix\n");
-        output.push_str(".text\n");
-        output.push_str(".global _start\n");
-        output.push_str(".global main\n\n");
-        
+        out.lines([
+            "# Generated by Utopia Compiler - x86_64 Assembly Backend",
+            "# Ultimate performance native machine code",
+            ".intel_syntax noprefix",
+            ".text",
+            ".global _start",
+            ".global main",
+        ]);
+        out.blank();
+
         // Data section
-        output.push_str(".section .data\n");
-        output.push_str("    msg_hello: .ascii \"Hello from Utopia!\\n\"\n");
-        output.push_str("    msg_hello_len = . - msg_hello\n\n");
-        
+        out.line(".section .data");
+        out.indented(|out| out.lines(["msg_hello: .ascii \"Hello from Utopia!\\n\"", "msg_hello_len = . - msg_hello"]));
+        out.blank();
+
         // Text section
-        output.push_str(".section .text\n\n");
-        
+        out.line(".section .text");
+        out.blank();
+
         // Main function
-        output.push_str("main:\n");
-        output.push_str("    push rbp\n");
-        output.push_str("    mov rbp, rsp\n");
-        output.push_str("    \n");
-        output.push_str("    # Your Utopia code here\n");
-        output.push_str("    mov rax, 42  # Return value\n");
-        output.push_str("    \n");
-        output.push_str("    pop rbp\n");
-        output.push_str("    ret\n\n");
-        
+        out.line("main:");
+        out.indented(|out| {
+            out.lines(["push rbp", "mov rbp, rsp", "", "# Your Utopia code here", "mov rax, 42  # Return value", "", "pop rbp", "ret"]);
+        });
+        out.blank();
+
         // Entry point
-        output.push_str("_start:\n");
-        output.push_str("    call main\n");
-        output.push_str("    mov rdi, rax  # Exit code\n");
-        output.push_str("    mov rax, 60   # sys_exit\n");
-        output.push_str("    syscall\n");
-        
-        Ok(output)
+        out.line("_start:");
+        out.indented(|out| out.lines(["call main", "mov rdi, rax  # Exit code", "mov rax, 60   # sys_exit", "syscall"]));
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "assembly"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".s"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "assembly" || language == "asm"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
 }
 
 /// C code generator
 pub struct CTransformer;
 
 impl Transformer for CTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+
         // C header
-        output.push_str("// Generated by Utopia Compiler - C Backend\n");
-        output.push_str("// High-performance native C code\n\n");
-        output.push_str("#include <stdio.h>\n");
-        output.push_str("#include <stdlib.h>\n");
-        output.push_str("#include <stdint.h>\n");
-        output.push_str("#include <stdbool.h>\n\n");
-        
+        out.line("// Generated by Utopia Compiler - C Backend");
+        out.line("// High-performance native C code");
+        out.blank();
+        out.lines(["#include <stdio.h>", "#include <stdlib.h>", "#include <stdint.h>", "#include <stdbool.h>", "#include <string.h>", "#include <math.h>"]);
+        out.line(runtime::C.include);
+        out.blank();
+
         // Function declarations
         for block in &program.language_blocks {
             if block.language == "c" || block.language == "main" {
                 for function in &block.functions {
-                    output.push_str(&self.generate_function_declaration(function)?);
+                    out.line(format!("{};", self.function_signature(function)));
                 }
             }
         }
-        
-        output.push('\n');
-        
+
+        out.blank();
+
         // Function definitions
         for block in &program.language_blocks {
             if block.language == "c" || block.language == "main" {
                 for function in &block.functions {
-                    output.push_str(&self.generate_function_definition(function)?);
+                    self.emit_function_definition(&mut out, function);
+                    out.blank();
                 }
                 // Handle statements in main blocks
                 if block.language == "main" {
-                    output.push_str(&self.generate_main_statements(block)?);
+                    self.emit_main_statements(&mut out, block)?;
+                } else {
+                    out.note(unsupported_placement(&block.statements, "a c block outside main"));
                 }
+            } else {
+                // Functions of other languages are reached through the runtime's registry
+                out.note(unsupported_placement(&block.statements, &format!("a {} block", block.language)));
             }
         }
-        
+        out.note(unsupported_placement(&program.global_statements, "global code"));
+
         // Main function is generated in generate_c_functions when processing @lang main blocks
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "c"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".c"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "c"
     }
+
+    /// Global code is reported rather than generated
+    fn generates_global_code(&self) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+            "extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict",
+            "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+            "unsigned", "void", "volatile", "while",
+        ], Escape::Suffix("_"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(runtime::C)
+    }
 }
 
 impl CTransformer {
-    fn generate_function_declaration(&self, function: &Function) -> Result<String> {
-        let return_type = self.convert_type(function.return_type.as_ref());
-        let mut params = Vec::new();
-        
-        for param in &function.parameters {
-            let param_type = self.convert_type(param.param_type.as_ref());
-            params.push(format!("{} {}", param_type, param.name));
-        }
-        
-        let params_str = if params.is_empty() {
-            "void".to_string()
-        } else {
-            params.join(", ")
-        };
-        
-        Ok(format!("{} {}({});\n", return_type, function.name, params_str))
-    }
-    
-    fn generate_function_definition(&self, function: &Function) -> Result<String> {
+    /// Strings are `char *`, so they are compared with `strcmp` and cannot be concatenated
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("(double) {l} / {r}"))
+        .with(BinaryOperator::Modulo, Form::Call("fmod({l}, {r})"))
+        .concat(Form::Unsupported)
+        .strings([
+            Form::Template("strcmp({l}, {r}) == 0"),
+            Form::Template("strcmp({l}, {r}) != 0"),
+            Form::Template("strcmp({l}, {r}) < 0"),
+            Form::Template("strcmp({l}, {r}) <= 0"),
+            Form::Template("strcmp({l}, {r}) > 0"),
+            Form::Template("strcmp({l}, {r}) >= 0"),
+        ]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "printf(\"{formats}\\n\", {args})"),
+        ("println/0", "printf(\"\\n\")"),
+        ("toNumber", "atof({0})"),
+        ("len", "(double) strlen({0})"),
+        ("upper", "utopia_upper({0})"),
+        ("lower", "utopia_lower({0})"),
+        ("trim", "utopia_trim({0})"),
+        ("contains", "utopia_contains({0}, {1})"),
+        ("abs", "fabs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceil({0})"),
+        ("round", "round({0})"),
+        ("pow", "pow({0}, {1})"),
+        ("min", "fmin({0}, {1})"),
+        ("max", "fmax({0}, {1})"),
+    ])
+    .formats(["%s", "%g", "%d", "%g"]);
+
+    fn function_signature(&self, function: &Function) -> String {
         let return_type = self.convert_type(function.return_type.as_ref());
-        let mut params = Vec::new();
-        
-        for param in &function.parameters {
-            let param_type = self.convert_type(param.param_type.as_ref());
-            params.push(format!("{} {}", param_type, param.name));
-        }
-        
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+
         let params_str = if params.is_empty() {
             "void".to_string()
         } else {
             params.join(", ")
         };
-        
-        let mut output = format!("{} {}({}) {{\n", return_type, function.name, params_str);
-        
-        if function.body.is_empty() {
-            output.push_str("    // Empty function\n");
-        } else {
-            // Generate function body (simplified)
-            output.push_str("    // Function body\n");
-        }
-        
-        output.push_str("}\n\n");
-        Ok(output)
+
+        format!("{} {}({})", return_type, function.name, params_str)
     }
-    
-    fn generate_main_statements(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        if !block.statements.is_empty() {
-            output.push_str("int main() {\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
+
+    fn emit_function_definition(&self, out: &mut CodeWriter, function: &Function) {
+        out.map(function.span);
+        out.block(self.function_signature(function), |out| {
+            if function.body.is_empty() {
+                out.line("// Empty function");
+            } else {
+                // Generate function body (simplified)
+                out.line(format!("{}// Function body", unsupported_body(function)));
             }
-            output.push_str("    return 0;\n");
-            output.push_str("}\n");
-        }
-        
-        Ok(output)
+        });
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{};\n", expr_str))
-            }
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(value) = value {
-                    let value_str = self.generate_expression(value)?;
-                    // Determine type based on the value
-                    let var_type = match value {
-                        Expression::Literal { value: LiteralValue::String(_), .. } => "char*",
-                        Expression::Literal { value: LiteralValue::Boolean(_), .. } => "bool",
-                        Expression::Literal { value: LiteralValue::Number(_), .. } => "double",
-                        _ => "double", // default fallback
-                    };
-                    Ok(format!("{} {} = {};\n", var_type, name, value_str))
-                } else {
-                    Ok(format!("double {};\n", name))
-                }
-            }
-            _ => Ok("// Unsupported statement\n".to_string()),
+
+    fn emit_main_statements(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
+        if block.statements.is_empty() {
+            return Ok(());
         }
+        out.block("int main()", |out| -> Result<()> {
+            self.emit_statements(out, &block.statements)?;
+            out.line("return 0;");
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
                 match value {
                     LiteralValue::String(s) => Ok(format!("\"{}\"", s)),
+                    // Numbers are `double`, printed with `%g`, so whole numbers keep a fraction
+                    LiteralValue::Number(n) if n.fract() == 0.0 => Ok(format!("{:.1}", n)),
                     LiteralValue::Number(n) => Ok(n.to_string()),
                     LiteralValue::Boolean(b) => Ok(if *b { "1".to_string() } else { "0".to_string() }),
                     LiteralValue::Null => Ok("0".to_string()),
@@ -716,67 +989,71 @@ impl CTransformer {
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let mut args = Vec::new();
                 for arg in arguments {
                     args.push(self.generate_expression(arg)?);
                 }
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    if args.is_empty() {
-                        Ok("printf(\"\\n\")".to_string())
-                    } else {
-                        // Generate format string based on argument types
-                        let mut format_specs = Vec::new();
-                        for arg in arguments {
-                            match arg {
-                                Expression::Literal { value: LiteralValue::String(_), .. } => format_specs.push("%s"),
-                                Expression::Literal { value: LiteralValue::Number(_), .. } => format_specs.push("%.0f"),
-                                Expression::Literal { value: LiteralValue::Boolean(_), .. } => format_specs.push("%d"),
-                                Expression::Identifier { .. } => format_specs.push("%s"), // assume string for now
-                                _ => format_specs.push("%s"),
-                            }
-                        }
-                        let format_str = format_specs.join(" ");
-                        Ok(format!("printf(\"{}\\n\", {})", format_str, args.join(", ")))
-                    }
-                } else {
-                    Ok(format!("{}({})", callee_str, args.join(", ")))
-                }
+
+                Ok(format!("{}({})", callee_str, args.join(", ")))
             }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                let right_str = self.generate_expression(right)?;
-                Ok(format!("{} {} {}", left_str, op_str, right_str))
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            Expression::CrossCall { language, function, arguments, .. } => {
+                // The runtime's registry takes and returns Utopia values
+                let args = arguments.iter()
+                    .map(|arg| {
+                        let constructor = match operators::kind(arg) {
+                            crate::types::Type::String => "utopia_string",
+                            crate::types::Type::Boolean => "utopia_boolean",
+                            _ => "utopia_number",
+                        };
+                        Ok(format!("{}({})", constructor, self.generate_expression(arg)?))
+                    })
+                    .collect::<Result<Vec<String>>>()?;
+                let array = if args.is_empty() { "NULL".to_string() } else { format!("(utopia_value[]) {{{}}}", args.join(", ")) };
+                Ok(format!("utopia_cross_call(\"{}\", \"{}\", {}, {}).as.number", language, function, args.len(), array))
             }
-            _ => Ok("/* unsupported expression */".to_string()),
+            _ => Ok(unsupported_expression(expression, "/* unsupported expression */")),
         }
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "double".to_string(),
             Some(crate::types::Type::String) => "char*".to_string(),
             Some(crate::types::Type::Boolean) => "bool".to_string(),
             Some(crate::types::Type::Void) => "void".to_string(),
-            _ => "int".to_string(),
+            _ => "double".to_string(),
+        }
+    }
+}
+
+impl StatementEmitter for CTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(value) = value {
+                    let value_str = self.generate_expression(value)?;
+                    // Determine type based on the value
+                    let var_type = match operators::kind(value) {
+                        crate::types::Type::String => "char*",
+                        crate::types::Type::Boolean => "bool",
+                        _ => "double", // default fallback
+                    };
+                    out.line(format!("{} {} = {};", var_type, name, value_str));
+                } else {
+                    out.line(format!("double {};", name));
+                }
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -784,187 +1061,184 @@ impl CTransformer {
 pub struct CppTransformer;
 
 impl Transformer for CppTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - C++ Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Object-oriented systems programming\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - C++ Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Object-oriented systems programming");
+        out.blank();
+
         // C++ headers
-        output.push_str("#include <iostream>\n");
-        output.push_str("#include <string>\n");
-        output.push_str("#include <vector>\n");
-        output.push_str("#include <memory>\n");
-        output.push_str("#include <functional>\n");
-        output.push_str("#include <unordered_map>\n\n");
-        
-        output.push_str("using namespace std;\n\n");
-        
+        out.lines([
+            "#include <iostream>",
+            "#include <string>",
+            "#include <cmath>",
+            "#include <vector>",
+            "#include <memory>",
+            "#include <functional>",
+            "#include <unordered_map>",
+        ]);
+        out.blank();
+
+        out.line("using namespace std;");
+        out.blank();
+
         // Utopia runtime class
-        output.push_str("class UtopiaRuntime {\n");
-        output.push_str("public:\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "cpp" || block.language == "c++" || block.language == "cxx" || block.language == "main" {
-                output.push_str(&self.generate_cpp_methods(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_methods(block)?);
+        out.line("class UtopiaRuntime {");
+        out.line("public:");
+        out.indented(|out| -> Result<()> {
+            // Process language blocks
+            for block in &program.language_blocks {
+                if block.language == "cpp" || block.language == "c++" || block.language == "cxx" || block.language == "main" {
+                    self.emit_cpp_methods(out, block)?;
+                } else {
+                    self.emit_cross_language_methods(out, block);
+                }
             }
-        }
-        
-        output.push_str("};\n\n");
-        
+            Ok(())
+        })?;
+        out.line("};");
+        out.blank();
+
         // Main function if needed
         if !program.global_statements.is_empty() {
-            output.push_str("int main() {\n");
-            output.push_str("    UtopiaRuntime runtime;\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("    return 0;\n");
-            output.push_str("}\n");
+            out.block("int main()", |out| -> Result<()> {
+                out.line("UtopiaRuntime runtime;");
+                self.emit_statements(out, &program.global_statements)?;
+                out.line("return 0;");
+                Ok(())
+            })?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "cpp"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".cpp"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "cpp" | "c++" | "cxx")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor", "bool", "break", "case",
+            "catch", "char", "char16_t", "char32_t", "class", "compl", "const", "const_cast", "constexpr",
+            "continue", "decltype", "default", "delete", "do", "double", "dynamic_cast", "else", "enum",
+            "explicit", "export", "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int",
+            "long", "mutable", "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or",
+            "or_eq", "private", "protected", "public", "register", "reinterpret_cast", "restrict", "return",
+            "short", "signed", "sizeof", "static", "static_assert", "static_cast", "struct", "switch",
+            "template", "this", "thread_local", "throw", "true", "try", "typedef", "typeid", "typename",
+            "union", "unsigned", "using", "virtual", "void", "volatile", "wchar_t", "while", "xor", "xor_eq",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl CppTransformer {
-    fn generate_cpp_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Values are carried as `std::string`, so numeric operators parse their
+    /// operands and format the result
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("std::to_string(std::stod({l}) + std::stod({r}))"),
+        Form::Call("std::to_string(std::stod({l}) - std::stod({r}))"),
+        Form::Call("std::to_string(std::stod({l}) * std::stod({r}))"),
+        Form::Call("std::to_string(std::stod({l}) / std::stod({r}))"),
+        Form::Call("std::to_string(std::fmod(std::stod({l}), std::stod({r})))"),
+        Form::Template("std::stod({l}) == std::stod({r})"),
+        Form::Template("std::stod({l}) != std::stod({r})"),
+        Form::Template("std::stod({l}) < std::stod({r})"),
+        Form::Template("std::stod({l}) <= std::stod({r})"),
+        Form::Template("std::stod({l}) > std::stod({r})"),
+        Form::Template("std::stod({l}) >= std::stod({r})"),
+        Form::Infix("&&"),
+        Form::Infix("||"),
+    ])
+    .concat(Form::Template("std::string({l}) + {r}"))
+    .strings([
+        Form::Template("std::string({l}) == {r}"),
+        Form::Template("std::string({l}) != {r}"),
+        Form::Template("std::string({l}) < {r}"),
+        Form::Template("std::string({l}) <= {r}"),
+        Form::Template("std::string({l}) > {r}"),
+        Form::Template("std::string({l}) >= {r}"),
+    ])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "std::cout << {args} << std::endl"),
+        ("println/0", "std::cout << std::endl"),
+        ("toString", "std::string({0})"),
+        ("toNumber", "std::to_string(std::stod({0}))"),
+        ("len", "std::to_string({0}.size())"),
+        ("push", "{0}.push_back({1})"),
+        ("contains", "({0}.find({1}) != std::string::npos)"),
+        ("abs", "std::to_string(std::fabs(std::stod({0})))"),
+        ("sqrt", "std::to_string(std::sqrt(std::stod({0})))"),
+        ("floor", "std::to_string(std::floor(std::stod({0})))"),
+        ("ceil", "std::to_string(std::ceil(std::stod({0})))"),
+        ("round", "std::to_string(std::round(std::stod({0})))"),
+        ("pow", "std::to_string(std::pow(std::stod({0}), std::stod({1})))"),
+        ("min", "std::to_string(std::fmin(std::stod({0}), std::stod({1})))"),
+        ("max", "std::to_string(std::fmax(std::stod({0}), std::stod({1})))"),
+        ("hasKey", "({0}.count({1}) > 0)"),
+    ])
+    .separator(" << \" \" << ");
+
+    fn emit_cpp_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_method(function)?);
-            output.push('\n');
+            self.emit_method(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+        Ok(())
     }
-    
-    fn generate_cross_language_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("    // Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("    string {}(const vector<string>& args) {{\n", function.name));
-            output.push_str(&format!(
-                "        throw runtime_error(\"Cross-language call to {}::{} not implemented\");\n",
-                block.language, function.name
-            ));
-            output.push_str("    }\n\n");
+            out.block(format!("string {}(const vector<string>& args)", function.name), |out| {
+                out.line(format!(
+                    "throw runtime_error(\"Cross-language call to {}::{} not implemented\");",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_method(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Method definition
-        output.push_str(&format!("    string {}(", function.name));
-        
+
+    fn emit_method(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("const string& {}", param.name));
-        }
-        
-        output.push_str(") {\n");
-        
-        // Method body
-        for statement in &function.body {
-            output.push_str(&format!("        {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("    }\n");
-        Ok(output)
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("const string& {}", param.name))
+            .collect();
+
+        out.map(function.span);
+        out.block(format!("string {}({})", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("string {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("string {} = \"\"", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return \"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("            {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("        }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("            {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("        }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
-    }
-    
-    fn generate_expression(&self, expression: &Expression) -> Result<String> {
-        match expression {
-            Expression::Literal { value, .. } => {
-                match value {
-                    LiteralValue::Number(n) => Ok(format!("\"{}\"", n)), // Convert to string
-                    LiteralValue::String(s) => Ok(format!("\"{}\"", s.replace('"', "\\\""))),
-                    LiteralValue::Boolean(b) => Ok(if *b { "\"true\"" } else { "\"false\"" }.to_string()),
-                    LiteralValue::Null => Ok("\"\"".to_string()),
+
+    fn generate_expression(&self, expression: &Expression) -> Result<String> {
+        match expression {
+            Expression::Literal { value, .. } => {
+                match value {
+                    LiteralValue::Number(n) => Ok(format!("\"{}\"", n)), // Convert to string
+                    LiteralValue::String(s) => Ok(format!("\"{}\"", s.replace('"', "\\\""))),
+                    LiteralValue::Boolean(b) => Ok(if *b { "\"true\"" } else { "\"false\"" }.to_string()),
+                    LiteralValue::Null => Ok("\"\"".to_string()),
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+", // String concatenation
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -991,8 +1265,43 @@ impl CppTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
+        }
+    }
+}
+
+impl StatementEmitter for CppTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("string {} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("string {} = \"\";", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return \"\";");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -1000,245 +1309,256 @@ impl CppTransformer {
 pub struct LLVMTransformer;
 
 impl Transformer for LLVMTransformer {
-    fn transform(&self, _program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("; Generated by Utopia Compiler - LLVM IR Backend\n");
-        output.push_str("; Optimized intermediate representation\n\n");
-        
-        output.push_str("target datalayout = \"e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128\"\n");
-        output.push_str("target triple = \"x86_64-pc-linux-gnu\"\n\n");
-        
-        output.push_str("@.str = private unnamed_addr constant [18 x i8] c\"Hello from Utopia!\\00\", align 1\n\n");
-        
-        output.push_str("declare i32 @printf(i8*, ...) #1\n\n");
-        
-        output.push_str("define i32 @main() #0 {\n");
-        output.push_str("entry:\n");
-        output.push_str("  %call = call i32 (i8*, ...) @printf(i8* getelementptr inbounds ([18 x i8], [18 x i8]* @.str, i64 0, i64 0))\n");
-        output.push_str("  ret i32 0\n");
-        output.push_str("}\n\n");
-        
-        output.push_str("attributes #0 = { noinline nounwind optnone uwtable }\n");
-        output.push_str("attributes #1 = { \"correctly-rounded-divide-sqrt-fp-math\"=\"false\" }\n");
-        
-        Ok(output)
+    fn generate(&self, _program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::BRACES);
+
+        out.line("; Generated by Utopia Compiler - LLVM IR Backend");
+        out.line("; Optimized intermediate representation");
+        out.blank();
+
+        out.line("target datalayout = \"e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128\"");
+        out.line("target triple = \"x86_64-pc-linux-gnu\"");
+        out.blank();
+
+        out.line("@.str = private unnamed_addr constant [18 x i8] c\"Hello from Utopia!\\00\", align 1");
+        out.blank();
+
+        out.line("declare i32 @printf(i8*, ...) #1");
+        out.blank();
+
+        out.line("define i32 @main() #0 {");
+        out.line("entry:");
+        out.indented(|out| {
+            out.line("%call = call i32 (i8*, ...) @printf(i8* getelementptr inbounds ([18 x i8], [18 x i8]* @.str, i64 0, i64 0))");
+            out.line("ret i32 0");
+        });
+        out.line("}");
+        out.blank();
+
+        out.line("attributes #0 = { noinline nounwind optnone uwtable }");
+        out.line("attributes #1 = { \"correctly-rounded-divide-sqrt-fp-math\"=\"false\" }");
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "llvm"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".ll"
     }
-    
+
     fn supports_language(&self, _language: &str) -> bool {
         true // LLVM can be target for any language
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
 }
 
 /// WebAssembly (WAT) generator
 pub struct WasmTransformer;
 
 impl Transformer for WasmTransformer {
-    fn transform(&self, _program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str(";; Generated by Utopia Compiler - WebAssembly Backend\n");
-        output.push_str(";; High-performance web assembly\n\n");
-        
-        output.push_str("(module\n");
-        output.push_str("  (import \"env\" \"console_log\" (func $console_log (param i32)))\n");
-        output.push_str("  (memory 1)\n");
-        output.push_str("  (data (i32.const 0) \"Hello from Utopia!\")\n\n");
-        
-        output.push_str("  (func $main (export \"main\") (result i32)\n");
-        output.push_str("    i32.const 0  ;; string offset\n");
-        output.push_str("    call $console_log\n");
-        output.push_str("    i32.const 42 ;; return value\n");
-        output.push_str("  )\n");
-        output.push_str(")\n");
-        
-        Ok(output)
+    fn generate(&self, _program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::new("", ")"));
+
+        out.line(";; Generated by Utopia Compiler - WebAssembly Backend");
+        out.line(";; High-performance web assembly");
+        out.blank();
+
+        out.block("(module", |out| {
+            out.line("(import \"env\" \"console_log\" (func $console_log (param i32)))");
+            out.line("(memory 1)");
+            out.line("(data (i32.const 0) \"Hello from Utopia!\")");
+            out.blank();
+
+            out.block("(func $main (export \"main\") (result i32)", |out| {
+                out.line("i32.const 0  ;; string offset");
+                out.line("call $console_log");
+                out.line("i32.const 42 ;; return value");
+            });
+        });
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "wasm"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".wat"
     }
-    
+
     fn supports_language(&self, _language: &str) -> bool {
         true // WebAssembly can be target for any language
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
 }
 
 /// Rust code generator
 pub struct RustTransformer;
 
 impl Transformer for RustTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("// Generated by Utopia Compiler - Rust Backend\n");
-        output.push_str("// Memory-safe systems programming with zero-cost abstractions\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+
+        out.line("// Generated by Utopia Compiler - Rust Backend");
+        out.line("// Memory-safe systems programming with zero-cost abstractions");
+        out.blank();
+
         // Standard imports
-        output.push_str("use std::collections::HashMap;\n");
-        output.push_str("use std::sync::{Arc, Mutex};\n");
-        output.push_str("use serde::{Serialize, Deserialize};\n\n");
-        
+        out.line("use std::collections::HashMap;");
+        out.line("use std::sync::{Arc, Mutex};");
+        out.blank();
+
         // Cross-language interop structures
-        output.push_str("#[derive(Debug, Clone, Serialize, Deserialize)]\n");
-        output.push_str("pub struct UtopiaValue {\n");
-        output.push_str("    pub data: serde_json::Value,\n");
-        output.push_str("    pub type_info: String,\n");
-        output.push_str("}\n\n");
-        
+        out.lines(runtime::RUST.include.lines());
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "rust" || block.language == "main" {
-                output.push_str(&self.generate_rust_block(block)?);
+                self.emit_rust_block(&mut out, block)?;
             } else {
-                output.push_str(&format!("// Cross-language block: {}\n", block.language));
-                output.push_str(&self.generate_cross_language_bindings(block)?);
+                out.line(format!("// Cross-language block: {}", block.language));
+                self.emit_cross_language_bindings(&mut out, block);
+                out.note(unsupported_placement(&block.statements, &format!("a {} block", block.language)));
             }
         }
-        
-        // Main function is generated in generate_rust_function when processing @lang main blocks
-        
-        Ok(output)
+        out.note(unsupported_placement(&program.global_statements, "global code"));
+
+        // Main function is generated in emit_rust_block when processing @lang main blocks
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "rust"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".rs"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "rust" || language == "rs"
     }
+
+    /// Global code is reported rather than generated
+    fn generates_global_code(&self) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Exceptions])
+    }
+
+    /// `self`, `Self`, `super` and `crate` cannot be raw identifiers, so they are left out
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+            "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+            "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+            "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+            "where", "while", "yield",
+        ], Escape::Prefix("r#"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(runtime::RUST)
+    }
 }
 
 impl RustTransformer {
-    fn generate_rust_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// `%` on `f64` already truncates like Utopia's
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("{l} as f64 / {r} as f64"))
+        .concat(Form::Call("format!(\"{}{}\", {l}, {r})"));
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "println!(\"{formats}\", {args})"),
+        ("println/0", "println!()"),
+        ("toString", "{0}.to_string()"),
+        ("toNumber", "{0}.to_string().parse::<f64>().unwrap_or(f64::NAN)"),
+        ("len", "({0}.len() as f64)"),
+        ("push", "{0}.push({1})"),
+        ("pop", "{0}.pop()"),
+        ("upper", "{0}.to_uppercase()"),
+        ("lower", "{0}.to_lowercase()"),
+        ("trim", "{0}.trim().to_string()"),
+        ("contains", "{0}.contains(&{1})"),
+        ("abs", "f64::abs({0})"),
+        ("sqrt", "f64::sqrt({0})"),
+        ("floor", "f64::floor({0})"),
+        ("ceil", "f64::ceil({0})"),
+        ("round", "f64::round({0})"),
+        ("pow", "f64::powf({0}, {1})"),
+        ("min", "f64::min({0}, {1})"),
+        ("max", "f64::max({0}, {1})"),
+        ("keys", "{0}.keys().cloned().collect::<Vec<_>>()"),
+        ("hasKey", "{0}.contains_key(&{1})"),
+    ])
+    .formats(["{}", "{}", "{}", "{:?}"]);
+
+    fn emit_rust_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function);
+            out.blank();
         }
-        
+
         // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("fn main() {\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-            }
-            output.push_str("}\n");
+        if block.language != "main" {
+            out.note(unsupported_placement(&block.statements, "a rust block outside main"));
+        } else if !block.statements.is_empty() {
+            out.block("fn main()", |out| self.emit_statements(out, &block.statements))?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_bindings(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_cross_language_bindings(&self, out: &mut CodeWriter, block: &LanguageBlock) {
         for function in &block.functions {
-            output.push_str(&format!(
-                "// Cross-language binding: {}::{}\n",
-                block.language, function.name
-            ));
-            output.push_str(&format!(
-                "pub fn {}(args: Vec<UtopiaValue>) -> Result<UtopiaValue, Box<dyn std::error::Error>> {{\n",
-                function.name
-            ));
-            output.push_str(&format!(
-                "    // Call to {}.{} with arguments\n",
-                block.language, function.name
-            ));
-            output.push_str("    todo!(\"Cross-language call implementation\")\n");
-            output.push_str("}\n\n");
+            out.line(format!("// Cross-language binding: {}::{}", block.language, function.name));
+            out.block(format!("pub fn {}(args: Vec<UtopiaValue>) -> Result<UtopiaValue, Box<dyn std::error::Error>>", function.name), |out| {
+                out.line(format!("cross_call(\"{}\", \"{}\", args)", block.language, function.name));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str("pub fn ");
-        output.push_str(&function.name);
-        output.push('(');
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-            output.push_str(": ");
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-        }
-        
-        output.push(')');
-        
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{}: {}", param.name, self.convert_type(param.param_type.as_ref())))
+            .collect();
+
         // Return type
-        if let Some(ref return_type) = function.return_type {
-            output.push_str(" -> ");
-            output.push_str(&self.convert_type(Some(return_type)));
-        }
-        
-        output.push_str(" {\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    // Empty function\n");
-            if function.return_type.is_some() {
-                output.push_str("    todo!(\"Function implementation\")\n");
-            }
-        } else {
-            output.push_str("    // Function body\n");
-            output.push_str("    todo!(\"Function body implementation\")\n");
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{};\n", expr_str))
-            }
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(value) = value {
-                    let value_str = self.generate_expression(value)?;
-                    Ok(format!("let {} = {};\n", name, value_str))
-                } else {
-                    Ok(format!("let {};\n", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(value) = value {
-                    Ok(format!("return {};\n", self.generate_expression(value)?))
-                } else {
-                    Ok("return;\n".to_string())
+        let return_type = match &function.return_type {
+            Some(return_type) => format!(" -> {}", self.convert_type(Some(return_type))),
+            None => String::new(),
+        };
+
+        out.map(function.span);
+        out.block(format!("pub fn {}({}){}", function.name, params.join(", "), return_type), |out| {
+            if function.body.is_empty() {
+                out.line("// Empty function");
+                if function.return_type.is_some() {
+                    out.line("todo!(\"Function implementation\")");
                 }
+            } else {
+                out.line(format!("{}// Function body", unsupported_body(function)));
+                out.line("todo!(\"Function body implementation\")");
             }
-            _ => Ok("// Unsupported statement\n".to_string()),
-        }
+        });
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -1251,49 +1571,22 @@ impl RustTransformer {
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let mut args = Vec::new();
                 for arg in arguments {
                     args.push(self.generate_expression(arg)?);
                 }
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    if arguments.is_empty() {
-                        Ok("println!()".to_string())
-                    } else {
-                        // Generate proper format string with {} for each argument
-                        let format_specs = arguments.iter().map(|_| "{}").collect::<Vec<_>>().join(" ");
-                        Ok(format!("println!(\"{}\", {})", format_specs, args.join(", ")))
-                    }
-                } else {
-                    Ok(format!("{}({})", callee_str, args.join(", ")))
-                }
-            }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                let right_str = self.generate_expression(right)?;
-                Ok(format!("{} {} {}", left_str, op_str, right_str))
+
+                Ok(format!("{}({})", callee_str, args.join(", ")))
             }
-            _ => Ok("/* unsupported expression */".to_string()),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            _ => Ok(unsupported_expression(expression, "/* unsupported expression */")),
         }
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "f64".to_string(),
@@ -1307,144 +1600,154 @@ impl RustTransformer {
     }
 }
 
+impl StatementEmitter for RustTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("let {} = {};", name, self.generate_expression(value)?));
+                } else {
+                    out.line(format!("let {};", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("return {};", self.generate_expression(value)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Go code generator
 pub struct GoTransformer;
 
 impl Transformer for GoTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("// Generated by Utopia Compiler - Go Backend\n");
-        output.push_str("// Concurrent programming with goroutines and channels\n\n");
-        
-        output.push_str("package main\n\n");
-        
-        output.push_str("import (\n");
-        output.push_str("    \"fmt\"\n");
-        output.push_str("    \"encoding/json\"\n");
-        output.push_str("    \"sync\"\n");
-        output.push_str("    \"context\"\n");
-        output.push_str(")\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+
+        out.line("// Generated by Utopia Compiler - Go Backend");
+        out.line("// Concurrent programming with goroutines and channels");
+        out.blank();
+
+        out.line("package main");
+        out.blank();
+
+        out.line("import (");
+        out.indented(|out| out.lines(["\"fmt\"", "\"math\"", "\"encoding/json\"", "\"sync\"", "\"context\""]));
+        out.line(")");
+        out.blank();
+
         // Cross-language value type
-        output.push_str("type UtopiaValue struct {\n");
-        output.push_str("    Data     interface{} `json:\"data\"`\n");
-        output.push_str("    TypeInfo string      `json:\"type_info\"`\n");
-        output.push_str("}\n\n");
-        
+        out.block("type UtopiaValue struct", |out| {
+            out.line("Data     interface{} `json:\"data\"`");
+            out.line("TypeInfo string      `json:\"type_info\"`");
+        });
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "go" || block.language == "main" {
-                output.push_str(&self.generate_go_block(block)?);
+                self.emit_go_block(&mut out, block)?;
             } else {
-                output.push_str(&format!("// Cross-language block: {}\n", block.language));
+                out.line(format!("// Cross-language block: {}", block.language));
             }
         }
-        
-        // Main function is generated in generate_go_block when processing @lang main blocks
-        
-        Ok(output)
+
+        // Main function is generated in emit_go_block when processing @lang main blocks
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "go"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".go"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "go" || language == "golang"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Exceptions])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for",
+            "func", "go", "goto", "if", "import", "interface", "map", "package", "range", "return", "select",
+            "struct", "switch", "type", "var",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl GoTransformer {
-    fn generate_go_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("float64({l}) / float64({r})"))
+        .with(BinaryOperator::Modulo, Form::Call("math.Mod(float64({l}), float64({r}))"))
+        .concat(Form::Call("fmt.Sprint({l}, {r})"));
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "fmt.Println({args})"),
+        ("toString", "fmt.Sprint({0})"),
+        ("len", "float64(len({0}))"),
+        ("push", "{0} = append({0}, {1})"),
+        ("abs", "math.Abs({0})"),
+        ("sqrt", "math.Sqrt({0})"),
+        ("floor", "math.Floor({0})"),
+        ("ceil", "math.Ceil({0})"),
+        ("round", "math.Round({0})"),
+        ("pow", "math.Pow({0}, {1})"),
+        ("min", "math.Min({0}, {1})"),
+        ("max", "math.Max({0}, {1})"),
+    ]);
+
+    fn emit_go_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function);
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("func main() {\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-            }
-            output.push_str("}\n");
+            out.block("func main()", |out| self.emit_statements(out, &block.statements))?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str("func ");
-        output.push_str(&function.name);
-        output.push('(');
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-            output.push(' ');
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-        }
-        
-        output.push(')');
-        
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", param.name, self.convert_type(param.param_type.as_ref())))
+            .collect();
+
         // Return type
-        if let Some(ref return_type) = function.return_type {
-            output.push(' ');
-            output.push_str(&self.convert_type(Some(return_type)));
-        }
-        
-        output.push_str(" {\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    // Empty function\n");
-        } else {
-            output.push_str("    // Function body\n");
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{}\n", expr_str))
-            }
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(value) = value {
-                    let value_str = self.generate_expression(value)?;
-                    Ok(format!("{} := {}\n", name, value_str))
-                } else {
-                    Ok(format!("var {}\n", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(value) = value {
-                    Ok(format!("return {}\n", self.generate_expression(value)?))
-                } else {
-                    Ok("return\n".to_string())
-                }
+        let return_type = match &function.return_type {
+            Some(return_type) => format!(" {}", self.convert_type(Some(return_type))),
+            None => String::new(),
+        };
+
+        out.map(function.span);
+        out.block(format!("func {}({}){}", function.name, params.join(", "), return_type), |out| {
+            if function.body.is_empty() {
+                out.line("// Empty function");
+            } else {
+                out.line(format!("{}// Function body", unsupported_body(function)));
             }
-            _ => Ok("// Unsupported statement\n".to_string()),
-        }
+        });
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -1457,43 +1760,22 @@ impl GoTransformer {
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let mut args = Vec::new();
                 for arg in arguments {
                     args.push(self.generate_expression(arg)?);
                 }
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("fmt.Println({})", args.join(", ")))
-                } else {
-                    Ok(format!("{}({})", callee_str, args.join(", ")))
-                }
-            }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                let right_str = self.generate_expression(right)?;
-                Ok(format!("{} {} {}", left_str, op_str, right_str))
+
+                Ok(format!("{}({})", callee_str, args.join(", ")))
             }
-            _ => Ok("/* unsupported expression */".to_string()),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            _ => Ok(unsupported_expression(expression, "/* unsupported expression */")),
         }
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "float64".to_string(),
@@ -1507,126 +1789,184 @@ impl GoTransformer {
     }
 }
 
+impl StatementEmitter for GoTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("{} := {}", name, self.generate_expression(value)?));
+                } else {
+                    out.line(format!("var {}", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("return {}", self.generate_expression(value)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Java code generator
 pub struct JavaTransformer;
 
 impl Transformer for JavaTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("// Generated by Utopia Compiler - Java Backend\n");
-        output.push_str("// Enterprise-grade object-oriented programming\n\n");
-        
-        output.push_str("import java.util.*;\n");
-        output.push_str("import java.util.concurrent.*;\n");
-        output.push_str("import com.fasterxml.jackson.databind.ObjectMapper;\n");
-        output.push_str("import com.fasterxml.jackson.databind.JsonNode;\n\n");
-        
-        output.push_str("public class UtopiaProgram {\n\n");
-        
-        // Cross-language value class
-        output.push_str("    public static class UtopiaValue {\n");
-        output.push_str("        private Object data;\n");
-        output.push_str("        private String typeInfo;\n\n");
-        output.push_str("        public UtopiaValue(Object data, String typeInfo) {\n");
-        output.push_str("            this.data = data;\n");
-        output.push_str("            this.typeInfo = typeInfo;\n");
-        output.push_str("        }\n\n");
-        output.push_str("        // Getters and setters\n");
-        output.push_str("        public Object getData() { return data; }\n");
-        output.push_str("        public String getTypeInfo() { return typeInfo; }\n");
-        output.push_str("    }\n\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "java" || block.language == "main" {
-                output.push_str(&self.generate_java_block(block)?);
-            } else {
-                output.push_str(&format!("    // Cross-language block: {}\n", block.language));
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+
+        out.line("// Generated by Utopia Compiler - Java Backend");
+        out.line("// Enterprise-grade object-oriented programming");
+        out.blank();
+
+        out.lines(["import java.util.*;", "import java.util.concurrent.*;"]);
+        out.blank();
+        out.line(runtime::JAVA.include);
+        out.blank();
+
+        out.block("public class UtopiaProgram", |out| -> Result<()> {
+            out.blank();
+
+            // Process language blocks
+            for block in &program.language_blocks {
+                if block.language == "java" || block.language == "main" {
+                    self.emit_java_block(out, block)?;
+                } else {
+                    // Functions of other languages are reached through the runtime's registry
+                    out.line(format!("// Cross-language block: {}", block.language));
+                    out.note(unsupported_placement(&block.statements, &format!("a {} block", block.language)));
+                }
             }
-        }
-        
-        // Main method is generated in generate_java_block when processing @lang main blocks
-        
-        output.push_str("}\n");
-        Ok(output)
+            out.note(unsupported_placement(&program.global_statements, "global code"));
+
+            // Main method is generated in emit_java_block when processing @lang main blocks
+            Ok(())
+        })?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "java"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".java"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "java"
     }
+
+    /// Global code is reported rather than generated
+    fn generates_global_code(&self) -> bool {
+        false
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "_", "abstract", "assert", "boolean", "break", "byte", "case", "catch", "char", "class", "const",
+            "continue", "default", "do", "double", "else", "enum", "extends", "false", "final", "finally",
+            "float", "for", "goto", "if", "implements", "import", "instanceof", "int", "interface", "long",
+            "native", "new", "null", "package", "private", "protected", "public", "return", "short",
+            "static", "strictfp", "super", "switch", "synchronized", "this", "throw", "throws", "transient",
+            "true", "try", "void", "volatile", "while",
+        ], Escape::Suffix("_"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(runtime::JAVA)
+    }
 }
 
 impl JavaTransformer {
-    fn generate_java_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Values are boxed, so arithmetic unboxes them as `Number`
+    const OPERATORS: Operators = Operators::new([
+        Form::Template("((Number){l}).doubleValue() + ((Number){r}).doubleValue()"),
+        Form::Template("((Number){l}).doubleValue() - ((Number){r}).doubleValue()"),
+        Form::Template("((Number){l}).doubleValue() * ((Number){r}).doubleValue()"),
+        Form::Template("((Number){l}).doubleValue() / ((Number){r}).doubleValue()"),
+        Form::Template("((Number){l}).doubleValue() % ((Number){r}).doubleValue()"),
+        Form::Call("java.util.Objects.equals({l}, {r})"),
+        Form::Call("!java.util.Objects.equals({l}, {r})"),
+        Form::Template("((Comparable){l}).compareTo({r}) < 0"),
+        Form::Template("((Comparable){l}).compareTo({r}) <= 0"),
+        Form::Template("((Comparable){l}).compareTo({r}) > 0"),
+        Form::Template("((Comparable){l}).compareTo({r}) >= 0"),
+        Form::Template("((Boolean){l}) && ((Boolean){r})"),
+        Form::Template("((Boolean){l}) || ((Boolean){r})"),
+    ])
+    .concat(Form::Template("String.valueOf({l}) + String.valueOf({r})"));
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "UtopiaRuntime.println({args})"),
+        ("toString", "UtopiaRuntime.toDisplay({0})"),
+        ("toNumber", "Double.parseDouble(String.valueOf({0}))"),
+        ("toBoolean", "UtopiaRuntime.isTruthy({0})"),
+        ("len", "UtopiaRuntime.len({0})"),
+        ("push", "((java.util.List<Object>){0}).add({1})"),
+        ("pop", "UtopiaRuntime.pop({0})"),
+        ("upper", "((String){0}).toUpperCase()"),
+        ("lower", "((String){0}).toLowerCase()"),
+        ("trim", "((String){0}).trim()"),
+        ("contains", "((String){0}).contains((String){1})"),
+        ("abs", "Math.abs(((Number){0}).doubleValue())"),
+        ("sqrt", "Math.sqrt(((Number){0}).doubleValue())"),
+        ("floor", "Math.floor(((Number){0}).doubleValue())"),
+        ("ceil", "Math.ceil(((Number){0}).doubleValue())"),
+        ("round", "Math.round(((Number){0}).doubleValue())"),
+        ("pow", "Math.pow(((Number){0}).doubleValue(), ((Number){1}).doubleValue())"),
+        ("min", "Math.min(((Number){0}).doubleValue(), ((Number){1}).doubleValue())"),
+        ("max", "Math.max(((Number){0}).doubleValue(), ((Number){1}).doubleValue())"),
+        ("keys", "new java.util.ArrayList<>(((java.util.Map<?, ?>){0}).keySet())"),
+        ("hasKey", "((java.util.Map<?, ?>){0}).containsKey({1})"),
+    ]);
+
+    fn emit_java_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function);
+            out.blank();
         }
-        
+
         // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("    public static void main(String[] args) {\n");
-            for statement in &block.statements {
-                output.push_str("        ");
-                output.push_str(&self.generate_statement(statement)?);
-            }
-            output.push_str("    }\n");
+        if block.language != "main" {
+            out.note(unsupported_placement(&block.statements, "a java block outside main"));
+        } else if !block.statements.is_empty() {
+            out.block("public static void main(String[] args)", |out| self.emit_statements(out, &block.statements))?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str("    public static ");
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) {
         // Return type
-        if let Some(ref return_type) = function.return_type {
-            output.push_str(&self.convert_type(Some(return_type)));
-        } else {
-            output.push_str("void");
-        }
-        
-        output.push(' ');
-        output.push_str(&function.name);
-        output.push('(');
-        
+        let return_type = match &function.return_type {
+            Some(return_type) => self.convert_type(Some(return_type)),
+            None => "void".to_string(),
+        };
+
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+
+        out.map(function.span);
+        out.block(format!("public static {} {}({})", return_type, function.name, params.join(", ")), |out| {
+            if function.body.is_empty() {
+                out.line("// Empty function");
+            } else {
+                out.line(format!("{}// Function body", unsupported_body(function)));
             }
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-            output.push(' ');
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(") {\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("        // Empty function\n");
-        } else {
-            output.push_str("        // Function body\n");
-        }
-        
-        output.push_str("    }\n");
-        Ok(output)
+        });
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "double".to_string(),
@@ -1638,32 +1978,7 @@ impl JavaTransformer {
             _ => "UtopiaValue".to_string(),
         }
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::Expression { expression, .. } => {
-                let expr_str = self.generate_expression(expression)?;
-                Ok(format!("{};\n", expr_str))
-            }
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(value) = value {
-                    let value_str = self.generate_expression(value)?;
-                    Ok(format!("Object {} = {};\n", name, value_str))
-                } else {
-                    Ok(format!("Object {};\n", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(value) = value {
-                    Ok(format!("return {};\n", self.generate_expression(value)?))
-                } else {
-                    Ok("return;\n".to_string())
-                }
-            }
-            _ => Ok("// Unsupported statement\n".to_string()),
-        }
-    }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -1676,50 +1991,53 @@ impl JavaTransformer {
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let mut args = Vec::new();
                 for arg in arguments {
                     args.push(self.generate_expression(arg)?);
                 }
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("System.out.println({})", args.join(" + \" \" + ")))
+
+                Ok(format!("{}({})", callee_str, args.join(", ")))
+            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            Expression::CrossCall { language, function, arguments, .. } => {
+                let mut args = vec![format!("\"{}\"", language), format!("\"{}\"", function)];
+                for arg in arguments {
+                    args.push(self.generate_expression(arg)?);
+                }
+                Ok(format!("UtopiaRuntime.crossCall({})", args.join(", ")))
+            }
+            _ => Ok(unsupported_expression(expression, "/* unsupported expression */")),
+        }
+    }
+}
+
+impl StatementEmitter for JavaTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("Object {} = {};", name, self.generate_expression(value)?));
                 } else {
-                    Ok(format!("{}({})", callee_str, args.join(", ")))
+                    out.line(format!("Object {};", name));
                 }
             }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                
-                match operator {
-                    BinaryOperator::Add => {
-                        // For Java, handle arithmetic properly by casting to numbers
-                        Ok(format!("((Number){}).doubleValue() + ((Number){}).doubleValue()", left_str, right_str))
-                    }
-                    BinaryOperator::Subtract => {
-                        Ok(format!("((Number){}).doubleValue() - ((Number){}).doubleValue()", left_str, right_str))
-                    }
-                    BinaryOperator::Multiply => {
-                        Ok(format!("((Number){}).doubleValue() * ((Number){}).doubleValue()", left_str, right_str))
-                    }
-                    BinaryOperator::Divide => {
-                        Ok(format!("((Number){}).doubleValue() / ((Number){}).doubleValue()", left_str, right_str))
-                    }
-                    BinaryOperator::Equal => Ok(format!("{}.equals({})", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("!{}.equals({})", left_str, right_str)),
-                    BinaryOperator::Less => Ok(format!("((Comparable){}).compareTo({}) < 0", left_str, right_str)),
-                    BinaryOperator::Greater => Ok(format!("((Comparable){}).compareTo({}) > 0", left_str, right_str)),
-                    BinaryOperator::LessEqual => Ok(format!("((Comparable){}).compareTo({}) <= 0", left_str, right_str)),
-                    BinaryOperator::GreaterEqual => Ok(format!("((Comparable){}).compareTo({}) >= 0", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("((Boolean){}) && ((Boolean){})", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("((Boolean){}) || ((Boolean){})", left_str, right_str)),
-                    _ => Ok(format!("/* unsupported op: {} op {} */", left_str, right_str)),
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    out.line(format!("return {};", self.generate_expression(value)?));
+                } else {
+                    out.line("return;");
                 }
             }
-            _ => Ok("/* unsupported expression */".to_string()),
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -1727,158 +2045,145 @@ impl JavaTransformer {
 pub struct CSharpTransformer;
 
 impl Transformer for CSharpTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - C# Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::ALLMAN);
+        out.line("// Generated by Utopia Compiler - C# Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.blank();
+
         // Add necessary usings
-        output.push_str("using System;\n");
-        output.push_str("using System.Collections.Generic;\n");
-        output.push_str("using System.Linq;\n");
-        output.push_str("using System.Text;\n\n");
-        
+        out.lines(["using System;", "using System.Collections.Generic;", "using System.Linq;", "using System.Text;"]);
+        out.blank();
+        out.line(runtime::CSHARP.include);
+        out.blank();
+
         // Create main class
-        output.push_str("namespace Utopia.Generated\n{\n");
-        output.push_str("    public class Program\n    {\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "csharp" || block.language == "c#" || block.language == "cs" || block.language == "main" {
-                output.push_str(&self.generate_csharp_block(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_stubs(block)?);
-            }
-        }
-        
-        // Global statements in main method
-        if !program.global_statements.is_empty() {
-            output.push_str("        public static void Main(string[] args)\n        {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("            {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("        }\n");
-        }
-        
-        output.push_str("    }\n}\n");
-        Ok(output)
+        out.block("namespace Utopia.Generated", |out| {
+            out.block("public class Program", |out| -> Result<()> {
+                // Process language blocks
+                for block in &program.language_blocks {
+                    if block.language == "csharp" || block.language == "c#" || block.language == "cs" || block.language == "main" {
+                        self.emit_csharp_block(out, block)?;
+                    } else {
+                        self.emit_cross_language_stubs(out, block);
+                    }
+                }
+
+                // Global statements in main method
+                if !program.global_statements.is_empty() {
+                    out.block("public static void Main(string[] args)", |out| {
+                        self.emit_statements(out, &program.global_statements)
+                    })?;
+                }
+                Ok(())
+            })
+        })?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "csharp"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".cs"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "csharp" | "c#" | "cs")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char", "checked", "class",
+            "const", "continue", "decimal", "default", "delegate", "do", "double", "else", "enum", "event",
+            "explicit", "extern", "false", "finally", "fixed", "float", "for", "foreach", "goto", "if",
+            "implicit", "in", "int", "interface", "internal", "is", "lock", "long", "namespace", "new",
+            "null", "object", "operator", "out", "override", "params", "private", "protected", "public",
+            "readonly", "ref", "return", "sbyte", "sealed", "short", "sizeof", "stackalloc", "static",
+            "string", "struct", "switch", "this", "throw", "true", "try", "typeof", "uint", "ulong",
+            "unchecked", "unsafe", "ushort", "using", "virtual", "void", "volatile", "while",
+        ], Escape::Prefix("@"))
+    }
+
+    fn runtime(&self) -> Option<Runtime> {
+        Some(runtime::CSHARP)
+    }
 }
 
 impl CSharpTransformer {
-    fn generate_csharp_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("(double){l} / {r}"))
+        .strings([
+            Form::Infix("=="),
+            Form::Infix("!="),
+            Form::Template("string.CompareOrdinal({l}, {r}) < 0"),
+            Form::Template("string.CompareOrdinal({l}, {r}) <= 0"),
+            Form::Template("string.CompareOrdinal({l}, {r}) > 0"),
+            Form::Template("string.CompareOrdinal({l}, {r}) >= 0"),
+        ])
+        .untyped_add(Form::Call("UtopiaRuntime.Add({l}, {r})"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "UtopiaRuntime.Println({args})"),
+        ("toString", "UtopiaRuntime.ToDisplay({0})"),
+        ("toNumber", "Convert.ToDouble({0})"),
+        ("toBoolean", "UtopiaRuntime.IsTruthy({0})"),
+        ("len", "{0}.Count()"),
+        ("push", "{0}.Add({1})"),
+        ("upper", "{0}.ToUpper()"),
+        ("lower", "{0}.ToLower()"),
+        ("trim", "{0}.Trim()"),
+        ("contains", "{0}.Contains({1})"),
+        ("abs", "Math.Abs({0})"),
+        ("sqrt", "Math.Sqrt({0})"),
+        ("floor", "Math.Floor({0})"),
+        ("ceil", "Math.Ceiling({0})"),
+        ("round", "Math.Round({0})"),
+        ("pow", "Math.Pow({0}, {1})"),
+        ("min", "Math.Min({0}, {1})"),
+        ("max", "Math.Max({0}, {1})"),
+        ("keys", "{0}.Keys.ToList()"),
+        ("hasKey", "{0}.ContainsKey({1})"),
+    ]);
+
+    fn emit_csharp_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        for statement in &block.statements {
-            output.push_str(&format!("        {};\n", self.generate_statement(statement)?));
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        self.emit_statements(out, &block.statements)
     }
-    
-    fn generate_cross_language_stubs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("        // Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_stubs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "        public static object {}(params object[] args)\n        {{\n",
-                function.name
-            ));
-            output.push_str(&format!(
-                "            throw new NotImplementedException(\"Cross-language call to {}::{}\");\n",
-                block.language, function.name
-            ));
-            output.push_str("        }\n\n");
+            out.block(format!("public static object {}(params object[] args)", function.name), |out| {
+                out.line(format!(
+                    "throw new NotImplementedException(\"Cross-language call to {}::{}\");",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str("        public static ");
-        output.push_str(&self.convert_return_type(&function.return_type));
-        output.push_str(&format!(" {}(", function.name));
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-            output.push(' ');
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n        {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("            {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("        }\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("var {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("object {};", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({})\n            {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("                {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("            }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("\n            else\n            {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("                {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("            }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+
+        out.map(function.span);
+        out.block(format!("public static {} {}({})", self.convert_return_type(&function.return_type), function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -1890,51 +2195,30 @@ impl CSharpTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let args: Result<Vec<String>> = arguments.iter()
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("Console.WriteLine({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                Ok(format!("CrossCall(\"{}\", \"{}\", new object[] {{ {} }})", language, function, args_str))
+                Ok(format!("UtopiaRuntime.CrossCall(\"{}\", \"{}\", new object[] {{ {} }})", language, function, args_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "double",
@@ -1946,7 +2230,7 @@ impl CSharpTransformer {
             _ => "object",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "double".to_string(),
@@ -1959,162 +2243,177 @@ impl CSharpTransformer {
     }
 }
 
-/// Visual Basic .NET code generator
-pub struct VisualBasicTransformer;
-
-impl Transformer for VisualBasicTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("' Generated by Utopia Compiler - Visual Basic .NET Backend\n");
-        output.push_str("' Multi-language support with runtime integration\n\n");
-        
-        // Add necessary imports
-        output.push_str("Imports System\n");
-        output.push_str("Imports System.Collections.Generic\n");
-        output.push_str("Imports System.Linq\n\n");
-        
-        // Create main module
-        output.push_str("Namespace Utopia.Generated\n");
-        output.push_str("    Public Module Program\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "visualbasic" || block.language == "vb" || block.language == "vbnet" || block.language == "main" {
-                output.push_str(&self.generate_vb_block(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_stubs(block)?);
+impl StatementEmitter for CSharpTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("var {} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("object {};", name));
+                }
             }
-        }
-        
-        // Global statements in main method
-        if !program.global_statements.is_empty() {
-            output.push_str("        Public Sub Main()\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("            {}\n", self.generate_statement(statement)?));
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
             }
-            output.push_str("        End Sub\n");
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
-        
-        output.push_str("    End Module\nEnd Namespace\n");
-        Ok(output)
+        Ok(())
     }
-    
+}
+
+/// Visual Basic .NET code generator
+pub struct VisualBasicTransformer;
+
+impl Transformer for VisualBasicTransformer {
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("' Generated by Utopia Compiler - Visual Basic .NET Backend");
+        out.line("' Multi-language support with runtime integration");
+        out.blank();
+
+        // Add necessary imports
+        out.lines(["Imports System", "Imports System.Collections.Generic", "Imports System.Linq"]);
+        out.blank();
+
+        // Create main module
+        out.block_with("Namespace Utopia.Generated", "End Namespace", |out| {
+            out.block_with("Public Module Program", "End Module", |out| -> Result<()> {
+                // Process language blocks
+                for block in &program.language_blocks {
+                    if block.language == "visualbasic" || block.language == "vb" || block.language == "vbnet" || block.language == "main" {
+                        self.emit_vb_block(out, block)?;
+                    } else {
+                        self.emit_cross_language_stubs(out, block);
+                    }
+                }
+
+                // Global statements in main method
+                if !program.global_statements.is_empty() {
+                    out.block_with("Public Sub Main()", "End Sub", |out| {
+                        self.emit_statements(out, &program.global_statements)
+                    })?;
+                }
+                Ok(())
+            })
+        })?;
+
+        Ok(out.finish())
+    }
+
     fn target_name(&self) -> &str {
         "visualbasic"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".vb"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "visualbasic" | "vb" | "vbnet")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "addhandler", "addressof", "alias", "and", "andalso", "as", "boolean", "byref", "byte", "byval",
+            "call", "case", "catch", "cbool", "cbyte", "cchar", "cdate", "cdbl", "cdec", "char", "cint",
+            "class", "clng", "cobj", "const", "continue", "csbyte", "cshort", "csng", "cstr", "ctype",
+            "cuint", "culng", "cushort", "date", "decimal", "declare", "default", "delegate", "dim",
+            "directcast", "do", "double", "each", "else", "elseif", "end", "endif", "enum", "erase", "error",
+            "event", "exit", "false", "finally", "for", "friend", "function", "get", "gettype", "global",
+            "gosub", "goto", "handles", "if", "implements", "imports", "in", "inherits", "integer",
+            "interface", "is", "isnot", "let", "lib", "like", "long", "loop", "me", "mod", "module",
+            "mustinherit", "mustoverride", "mybase", "myclass", "namespace", "narrowing", "new", "next",
+            "not", "nothing", "notinheritable", "notoverridable", "object", "of", "on", "operator", "option",
+            "optional", "or", "orelse", "overloads", "overridable", "overrides", "paramarray", "partial",
+            "private", "property", "protected", "public", "raiseevent", "readonly", "redim", "rem",
+            "removehandler", "resume", "return", "sbyte", "select", "set", "shadows", "shared", "short",
+            "single", "static", "step", "stop", "string", "structure", "sub", "synclock", "then", "throw",
+            "to", "true", "try", "trycast", "typeof", "uinteger", "ulong", "ushort", "using", "variant",
+            "wend", "when", "while", "widening", "with", "withevents", "writeonly", "xor",
+        ], Escape::Wrap("[", "]")).ignore_case()
+    }
 }
 
 impl VisualBasicTransformer {
-    fn generate_vb_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "Mod", "=", "<>", "<", "<=", ">", ">=", "AndAlso", "OrElse"])
+        .concat(Form::Infix("&"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "Console.WriteLine(String.Join(\" \", {args}))"),
+        ("println/0", "Console.WriteLine()"),
+        ("toString", "CStr({0})"),
+        ("toNumber", "CDbl({0})"),
+        ("toBoolean", "CBool({0})"),
+        ("len", "{0}.Count()"),
+        ("push", "{0}.Add({1})"),
+        ("upper", "{0}.ToUpper()"),
+        ("lower", "{0}.ToLower()"),
+        ("trim", "{0}.Trim()"),
+        ("contains", "{0}.Contains({1})"),
+        ("abs", "Math.Abs({0})"),
+        ("sqrt", "Math.Sqrt({0})"),
+        ("floor", "Math.Floor({0})"),
+        ("ceil", "Math.Ceiling({0})"),
+        ("round", "Math.Round({0})"),
+        ("pow", "Math.Pow({0}, {1})"),
+        ("min", "Math.Min({0}, {1})"),
+        ("max", "Math.Max({0}, {1})"),
+        ("keys", "{0}.Keys.ToList()"),
+        ("hasKey", "{0}.ContainsKey({1})"),
+    ]);
+
+    fn emit_vb_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        for statement in &block.statements {
-            output.push_str(&format!("        {}\n", self.generate_statement(statement)?));
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        self.emit_statements(out, &block.statements)
     }
-    
-    fn generate_cross_language_stubs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("        ' Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_stubs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("' Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "        Public Function {}(ParamArray args() As Object) As Object\n",
-                function.name
-            ));
-            output.push_str(&format!(
-                "            Throw New NotImplementedException(\"Cross-language call to {}::{}\")\n",
-                block.language, function.name
-            ));
-            output.push_str("        End Function\n\n");
+            out.block_with(format!("Public Function {}(ParamArray args() As Object) As Object", function.name), "End Function", |out| {
+                out.line(format!(
+                    "Throw New NotImplementedException(\"Cross-language call to {}::{}\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str("        Public Function ");
-        output.push_str(&function.name);
-        output.push_str("(");
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-            output.push_str(" As ");
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-        }
-        
-        output.push_str(") As ");
-        output.push_str(&self.convert_return_type(&function.return_type));
-        output.push('\n');
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("            {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("        End Function\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Dim {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("Dim {} As Object", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("Return Nothing".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("If {} Then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("                {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("            Else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("                {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("            End If");
-                Ok(output)
-            }
-            _ => Ok("' Unsupported statement".to_string()),
-        }
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} As {}", param.name, self.convert_type(param.param_type.as_ref())))
+            .collect();
+
+        out.map(function.span);
+        let header = format!("Public Function {}({}) As {}", function.name, params.join(", "), self.convert_return_type(&function.return_type));
+        out.block_with(header, "End Function", |out| self.emit_statements(out, &function.body))
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -2126,27 +2425,11 @@ impl VisualBasicTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "<>",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "And",
-                    BinaryOperator::Or => "Or",
-                    _ => "' unsupported op '",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = self.generate_expression(callee)?;
                 let args: Result<Vec<String>> = arguments.iter()
                     .map(|arg| self.generate_expression(arg))
@@ -2161,10 +2444,10 @@ impl VisualBasicTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("CrossCall(\"{}\", \"{}\", New Object() {{ {} }})", language, function, args_str))
             }
-            _ => Ok("' Unsupported expression '".to_string()),
+            other => Ok(unsupported_expression(other, "' Unsupported expression '")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Double",
@@ -2176,7 +2459,7 @@ impl VisualBasicTransformer {
             _ => "Object",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "Double".to_string(),
@@ -2189,139 +2472,165 @@ impl VisualBasicTransformer {
     }
 }
 
+impl StatementEmitter for VisualBasicTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Dim {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("Dim {} As Object", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("Return Nothing");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("If {} Then", self.generate_expression(condition)?), "End If");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("Else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "' Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Perl code generator
 pub struct PerlTransformer;
 
 impl Transformer for PerlTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("#!/usr/bin/perl\n");
-        output.push_str("# Generated by Utopia Compiler - Perl Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n\n");
-        
-        output.push_str("use strict;\n");
-        output.push_str("use warnings;\n");
-        output.push_str("use Data::Dumper;\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("#!/usr/bin/perl");
+        out.line("# Generated by Utopia Compiler - Perl Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.blank();
+
+        out.lines(["use strict;", "use warnings;", "use Data::Dumper;", "use POSIX ();"]);
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "perl" || block.language == "pl" || block.language == "main" {
-                output.push_str(&self.generate_perl_block(block)?);
+                self.emit_perl_block(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_stubs(block)?);
+                self.emit_cross_language_stubs(&mut out, block);
             }
         }
-        
+
         // Global statements
         if !program.global_statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{};\n", self.generate_statement(statement)?));
-            }
+            out.line("# Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "perl"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".pl"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "perl" | "pl")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "BEGIN", "cmp", "do", "else", "elsif", "END", "eq", "for", "foreach", "ge", "gt", "if",
+            "last", "le", "local", "lt", "my", "ne", "next", "no", "not", "or", "our", "package", "redo",
+            "require", "return", "sub", "unless", "until", "use", "while", "xor",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl PerlTransformer {
-    fn generate_perl_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Perl's `%` works on integers and takes the sign of the divisor
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Call("POSIX::fmod({l}, {r})"))
+        .concat(Form::Infix("."))
+        .strings([Form::Infix("eq"), Form::Infix("ne"), Form::Infix("lt"), Form::Infix("le"), Form::Infix("gt"), Form::Infix("ge")])
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print join(\" \", {args}), \"\\n\""),
+        ("println/0", "print \"\\n\""),
+        ("toString", "(\"\" . {0})"),
+        ("toNumber", "(0 + {0})"),
+        ("toBoolean", "!!{0}"),
+        ("len", "length({0})"),
+        ("push", "push(@{{0}}, {1})"),
+        ("pop", "pop(@{{0}})"),
+        ("upper", "uc({0})"),
+        ("lower", "lc({0})"),
+        ("contains", "(index({0}, {1}) >= 0)"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "POSIX::floor({0})"),
+        ("ceil", "POSIX::ceil({0})"),
+        ("pow", "({0} ** {1})"),
+        ("keys", "[keys %{{0}}]"),
+        ("hasKey", "exists {0}->{{1}}"),
+    ]);
+
+    fn emit_perl_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("# Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_stubs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_stubs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("sub {} {{\n", function.name));
-            output.push_str(&format!(
-                "    die \"Cross-language call to {}::{} not implemented\";\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str(&format!("sub {} {{\n", function.name));
-        
-        // Parameters
-        if !function.parameters.is_empty() {
-            output.push_str("    my (");
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str(", ");
-                }
-                output.push_str(&format!("${}", param.name));
-            }
-            output.push_str(") = @_;\n");
+            out.block(format!("sub {}", function.name), |out| {
+                out.line(format!(
+                    "die \"Cross-language call to {}::{} not implemented\";",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("my ${} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("my ${};", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        out.map(function.span);
+        out.block(format!("sub {}", function.name), |out| {
+            // Parameters
+            if !function.parameters.is_empty() {
+                let params: Vec<String> = function.parameters.iter().map(|param| format!("${}", param.name)).collect();
+                out.line(format!("my ({}) = @_;", params.join(", ")));
             }
-            _ => Ok("# Unsupported statement".to_string()),
-        }
+
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -2333,27 +2642,11 @@ impl PerlTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(format!("${}", name)),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -2362,13 +2655,8 @@ impl PerlTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("print {}", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -2377,8 +2665,34 @@ impl PerlTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("cross_call('{}', '{}', {})", language, function, args_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for PerlTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("my ${} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("my ${};", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -2386,129 +2700,125 @@ impl PerlTransformer {
 pub struct PHPTransformer;
 
 impl Transformer for PHPTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("<?php\n");
-        output.push_str("// Generated by Utopia Compiler - PHP Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("<?php");
+        out.line("// Generated by Utopia Compiler - PHP Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "php" || block.language == "main" {
-                output.push_str(&self.generate_php_block(block)?);
+                self.emit_php_block(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_stubs(block)?);
+                self.emit_cross_language_stubs(&mut out, block);
             }
         }
-        
+
         // Global statements
         if !program.global_statements.is_empty() {
-            output.push_str("// Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{};\n", self.generate_statement(statement)?));
-            }
+            out.line("// Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "php"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".php"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "php"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "and", "array", "as", "break", "callable", "case", "catch", "class", "clone",
+            "const", "continue", "declare", "default", "do", "echo", "else", "elseif", "empty", "enddeclare",
+            "endfor", "endforeach", "endif", "endswitch", "endwhile", "eval", "exit", "extends", "final",
+            "finally", "fn", "for", "foreach", "function", "global", "goto", "if", "implements", "include",
+            "instanceof", "insteadof", "interface", "isset", "list", "match", "namespace", "new", "or",
+            "print", "private", "protected", "public", "readonly", "require", "return", "static", "switch",
+            "throw", "trait", "try", "unset", "use", "var", "while", "xor", "yield",
+        ], Escape::Suffix("_")).ignore_case()
+    }
 }
 
 impl PHPTransformer {
-    fn generate_php_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// PHP's `%` converts its operands to integers
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "===", "!==", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Call("fmod({l}, {r})"))
+        .concat(Form::Infix("."))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "echo implode(\" \", [{args}]), PHP_EOL"),
+        ("toString", "strval({0})"),
+        ("toNumber", "floatval({0})"),
+        ("toBoolean", "boolval({0})"),
+        ("push", "array_push({0}, {1})"),
+        ("pop", "array_pop({0})"),
+        ("upper", "strtoupper({0})"),
+        ("lower", "strtolower({0})"),
+        ("trim", "trim({0})"),
+        ("contains", "str_contains({0}, {1})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceil({0})"),
+        ("round", "round({0})"),
+        ("pow", "pow({0}, {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "array_keys({0})"),
+        ("hasKey", "array_key_exists({1}, {0})"),
+    ]);
+
+    fn emit_php_block(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("// Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("// Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_stubs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_stubs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("function {}(...$args) {{\n", function.name));
-            output.push_str(&format!(
-                "    throw new Exception('Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("function {}(...$args)", function.name), |out| {
+                out.line(format!(
+                    "throw new Exception('Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str(&format!("function {}(", function.name));
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("${}", param.name));
-        }
-        
-        output.push_str(") {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("${} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("${} = null;", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("${}", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("function {}({})", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -2520,27 +2830,11 @@ impl PHPTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(format!("${}", name)),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -2549,13 +2843,8 @@ impl PHPTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("echo {}", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -2564,8 +2853,34 @@ impl PHPTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("cross_call('{}', '{}', [{}])", language, function, args_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
+        }
+    }
+}
+
+impl StatementEmitter for PHPTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("${} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("${} = null;", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -2573,212 +2888,165 @@ impl PHPTransformer {
 pub struct AdaTransformer;
 
 impl Transformer for AdaTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("-- Generated by Utopia Compiler - Ada Backend\n");
-        output.push_str("-- Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("   ", BlockStyle::INDENT);
+        out.line("-- Generated by Utopia Compiler - Ada Backend");
+        out.line("-- Multi-language support with runtime integration");
+        out.blank();
+
         // Add necessary with clauses
-        output.push_str("with Ada.Text_IO;\n");
-        output.push_str("with Ada.Integer_Text_IO;\n");
-        output.push_str("with Ada.Float_Text_IO;\n");
-        output.push_str("with Ada.Strings.Unbounded;\n");
-        output.push_str("use Ada.Text_IO;\n");
-        output.push_str("use Ada.Strings.Unbounded;\n\n");
-        
+        out.lines([
+            "with Ada.Text_IO;",
+            "with Ada.Integer_Text_IO;",
+            "with Ada.Float_Text_IO;",
+            "with Ada.Strings.Unbounded;",
+            "use Ada.Text_IO;",
+            "use Ada.Strings.Unbounded;",
+        ]);
+        out.blank();
+
         // Create main package
-        output.push_str("package Utopia_Generated is\n\n");
-        
-        // Process language blocks for specifications
-        for block in &program.language_blocks {
-            if block.language == "ada" || block.language == "main" {
-                output.push_str(&self.generate_ada_spec(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_specs(block)?);
+        out.block_with("package Utopia_Generated is", "end Utopia_Generated;", |out| {
+            out.blank();
+            // Process language blocks for specifications
+            for block in &program.language_blocks {
+                if block.language == "ada" || block.language == "main" {
+                    for function in &block.functions {
+                        out.line(format!("{};", self.function_header(function)));
+                    }
+                } else {
+                    self.emit_cross_language_specs(out, block);
+                }
             }
-        }
-        
-        output.push_str("end Utopia_Generated;\n\n");
-        
+        });
+        out.blank();
+
         // Package body
-        output.push_str("package body Utopia_Generated is\n\n");
-        
-        // Process language blocks for implementations
-        for block in &program.language_blocks {
-            if block.language == "ada" || block.language == "main" {
-                output.push_str(&self.generate_ada_body(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_bodies(block)?);
+        out.block_with("package body Utopia_Generated is", "end Utopia_Generated;", |out| -> Result<()> {
+            out.blank();
+            // Process language blocks for implementations
+            for block in &program.language_blocks {
+                if block.language == "ada" || block.language == "main" {
+                    for function in &block.functions {
+                        self.emit_function_body(out, function)?;
+                        out.blank();
+                    }
+                } else {
+                    self.emit_cross_language_bodies(out, block);
+                }
             }
-        }
-        
-        // Main procedure if needed
-        if !program.global_statements.is_empty() {
-            output.push_str("   procedure Main is\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("      {};\n", self.generate_statement(statement)?));
+
+            // Main procedure if needed
+            if !program.global_statements.is_empty() {
+                out.line("procedure Main is");
+                out.block_with("begin", "end Main;", |out| self.emit_statements(out, &program.global_statements))?;
+                out.blank();
             }
-            output.push_str("   end Main;\n\n");
-        }
-        
-        output.push_str("end Utopia_Generated;\n");
-        Ok(output)
+            Ok(())
+        })?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "ada"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".adb"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "ada"
     }
-}
 
-impl AdaTransformer {
-    fn generate_ada_spec(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function_spec(function)?);
-        }
-        
-        Ok(output)
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Lambdas])
     }
-    
-    fn generate_ada_body(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function_body(function)?);
-            output.push('\n');
-        }
-        
-        Ok(output)
+
+    /// Ada names cannot end with an underscore
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abort", "abs", "abstract", "accept", "access", "aliased", "all", "and", "array", "at", "begin",
+            "body", "case", "constant", "declare", "delay", "delta", "digits", "do", "else", "elsif", "end",
+            "entry", "exception", "exit", "for", "function", "generic", "goto", "if", "in", "interface",
+            "is", "limited", "loop", "mod", "new", "not", "null", "of", "or", "others", "out", "overriding",
+            "package", "pragma", "private", "procedure", "protected", "raise", "range", "record", "rem",
+            "renames", "requeue", "return", "reverse", "select", "separate", "some", "subtype",
+            "synchronized", "tagged", "task", "terminate", "then", "type", "until", "use", "when", "while",
+            "with", "xor",
+        ], Escape::Suffix("_v")).ignore_case()
     }
-    
-    fn generate_cross_language_specs(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("   -- Cross-language block: {}\n", block.language));
-        
+}
+
+impl AdaTransformer {
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "=", "/=", "<", "<=", ">", ">=", "and then", "or else"])
+        .concat(Form::Infix("&"))
+        .to_string("Float'Image ({})")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "Ada.Text_IO.Put_Line ({args})"),
+        ("println/0", "Ada.Text_IO.New_Line"),
+        ("toString", "Float'Image ({0})"),
+        ("toNumber", "Float'Value ({0})"),
+        ("len", "Float ({0}'Length)"),
+        ("upper", "Ada.Characters.Handling.To_Upper ({0})"),
+        ("lower", "Ada.Characters.Handling.To_Lower ({0})"),
+        ("abs", "(abs {0})"),
+        ("sqrt", "Ada.Numerics.Elementary_Functions.Sqrt ({0})"),
+        ("floor", "Float'Floor ({0})"),
+        ("ceil", "Float'Ceiling ({0})"),
+        ("round", "Float'Rounding ({0})"),
+        ("pow", "({0} ** {1})"),
+        ("min", "Float'Min ({0}, {1})"),
+        ("max", "Float'Max ({0}, {1})"),
+    ])
+    .separator(" & \" \" & ");
+
+    fn emit_cross_language_specs(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("-- Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "   function {} return String;\n",
-                function.name
-            ));
+            out.line(format!("function {} return String;", function.name));
         }
-        output.push('\n');
-        
-        Ok(output)
+        out.blank();
     }
-    
-    fn generate_cross_language_bodies(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("   -- Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_bodies(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("-- Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "   function {} return String is\n",
-                function.name
-            ));
-            output.push_str("   begin\n");
-            output.push_str(&format!(
-                "      raise Program_Error with \"Cross-language call to {}::{} not implemented\";\n",
-                block.language, function.name
-            ));
-            output.push_str(&format!("   end {};\n\n", function.name));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function_spec(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        if function.parameters.is_empty() {
-            output.push_str(&format!("   function {}", function.name));
-        } else {
-            output.push_str(&format!("   function {} (", function.name));
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str("; ");
-                }
-                output.push_str(&format!("{} : {}", param.name, self.convert_type(param.param_type.as_ref())));
-            }
-            output.push(')');
+            out.line(format!("function {} return String is", function.name));
+            out.block_with("begin", format!("end {};", function.name), |out| {
+                out.line(format!(
+                    "raise Program_Error with \"Cross-language call to {}::{} not implemented\";",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        output.push_str(&format!(" return {};\n", self.convert_return_type(&function.return_type)));
-        Ok(output)
     }
-    
-    fn generate_function_body(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    /// `function name (params) return Type`, shared by the spec and the body
+    fn function_header(&self, function: &Function) -> String {
+        let return_type = self.convert_return_type(&function.return_type);
         if function.parameters.is_empty() {
-            output.push_str(&format!("   function {} return {} is\n", function.name, self.convert_return_type(&function.return_type)));
+            format!("function {} return {}", function.name, return_type)
         } else {
-            output.push_str(&format!("   function {} (", function.name));
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str("; ");
-                }
-                output.push_str(&format!("{} : {}", param.name, self.convert_type(param.param_type.as_ref())));
-            }
-            output.push_str(&format!(") return {} is\n", self.convert_return_type(&function.return_type)));
-        }
-        
-        output.push_str("   begin\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("      {};\n", self.generate_statement(statement)?));
+            let params: Vec<String> = function.parameters.iter()
+                .map(|param| format!("{} : {}", param.name, self.convert_type(param.param_type.as_ref())))
+                .collect();
+            format!("function {} ({}) return {}", function.name, params.join("; "), return_type)
         }
-        
-        output.push_str(&format!("   end {};\n", function.name));
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} : constant := {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} : String;", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {} then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("         {};\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("      else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("         {};\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("      end if");
-                Ok(output)
-            }
-            _ => Ok("-- Unsupported statement".to_string()),
-        }
+
+    fn emit_function_body(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        out.map(function.span);
+        out.line(format!("{} is", self.function_header(function)));
+        out.block_with("begin", format!("end {};", function.name), |out| self.emit_statements(out, &function.body))
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -2790,27 +3058,11 @@ impl AdaTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "/=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => "-- unsupported op --",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -2828,10 +3080,10 @@ impl AdaTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("Cross_Call(\"{}\", \"{}\", ({}))", language, function, args_str))
             }
-            _ => Ok("-- Unsupported expression --".to_string()),
+            other => Ok(unsupported_expression(other, "-- Unsupported expression --")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Float",
@@ -2843,7 +3095,7 @@ impl AdaTransformer {
             _ => "String",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "Float".to_string(),
@@ -2856,210 +3108,194 @@ impl AdaTransformer {
     }
 }
 
+impl StatementEmitter for AdaTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} : constant := {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} : String;", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("if {} then", self.generate_expression(condition)?), "end if;");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "-- Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Delphi/Object Pascal code generator
 pub struct DelphiTransformer;
 
 impl Transformer for DelphiTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("{ Generated by Utopia Compiler - Delphi/Object Pascal Backend }\n");
-        output.push_str("{ Multi-language support with runtime integration }\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::INDENT);
+        out.line("{ Generated by Utopia Compiler - Delphi/Object Pascal Backend }");
+        out.line("{ Multi-language support with runtime integration }");
+        out.blank();
+
         // Create unit
-        output.push_str("unit UtopiaGenerated;\n\n");
-        output.push_str("interface\n\n");
-        
+        out.line("unit UtopiaGenerated;");
+        out.blank();
+        out.line("interface");
+        out.blank();
+
         // Add necessary uses clause
-        output.push_str("uses\n");
-        output.push_str("  SysUtils, Classes, Variants;\n\n");
-        
+        out.line("uses");
+        out.indented(|out| out.line("SysUtils, Classes, Variants, Math;"));
+        out.blank();
+
         // Forward declarations
         for block in &program.language_blocks {
             if block.language == "delphi" || block.language == "pascal" || block.language == "main" {
-                output.push_str(&self.generate_delphi_interface(block)?);
+                for function in &block.functions {
+                    out.line(format!("{};", self.function_header(function)));
+                }
             } else {
-                output.push_str(&self.generate_cross_language_interface(block)?);
+                self.emit_cross_language_interface(&mut out, block);
             }
         }
-        
-        output.push_str("implementation\n\n");
-        
+
+        out.line("implementation");
+        out.blank();
+
         // Process language blocks for implementations
         for block in &program.language_blocks {
             if block.language == "delphi" || block.language == "pascal" || block.language == "main" {
-                output.push_str(&self.generate_delphi_implementation(block)?);
+                for function in &block.functions {
+                    self.emit_function_implementation(&mut out, function)?;
+                    out.blank();
+                }
             } else {
-                output.push_str(&self.generate_cross_language_implementation(block)?);
+                self.emit_cross_language_implementation(&mut out, block);
             }
         }
-        
+
         // Main procedure if needed
         if !program.global_statements.is_empty() {
-            output.push_str("procedure Main;\nbegin\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("end;\n\n");
+            out.line("procedure Main;");
+            out.block_with("begin", "end;", |out| self.emit_statements(out, &program.global_statements))?;
+            out.blank();
         }
-        
-        output.push_str("end.\n");
-        Ok(output)
+
+        out.line("end.");
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "delphi"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".pas"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "delphi" | "pascal")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "array", "as", "asm", "begin", "case", "class", "const", "constructor", "destructor",
+            "div", "do", "downto", "else", "end", "except", "exports", "file", "finalization", "finally",
+            "for", "function", "goto", "if", "implementation", "in", "inherited", "initialization", "inline",
+            "interface", "is", "label", "library", "mod", "nil", "not", "object", "of", "or", "out",
+            "packed", "procedure", "program", "property", "raise", "record", "repeat", "set", "shl", "shr",
+            "string", "then", "threadvar", "to", "try", "type", "unit", "until", "uses", "var", "while",
+            "with", "xor",
+        ], Escape::Prefix("&")).ignore_case()
+    }
 }
 
 impl DelphiTransformer {
-    fn generate_delphi_interface(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function_interface(function)?);
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_delphi_implementation(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function_implementation(function)?);
-            output.push('\n');
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_interface(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("{{ Cross-language block: {} }}\n", block.language));
-        
+    /// `mod` only takes integers
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "mod", "=", "<>", "<", "<=", ">", ">=", "and", "or"])
+        .with(BinaryOperator::Modulo, Form::Call("FMod({l}, {r})"))
+        .to_string("FloatToStr({})")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "WriteLn({args})"),
+        ("toString", "VarToStr({0})"),
+        ("toNumber", "StrToFloat({0})"),
+        ("len", "Length({0})"),
+        ("upper", "UpperCase({0})"),
+        ("lower", "LowerCase({0})"),
+        ("trim", "Trim({0})"),
+        ("contains", "(Pos({1}, {0}) > 0)"),
+        ("abs", "Abs({0})"),
+        ("sqrt", "Sqrt({0})"),
+        ("floor", "Floor({0})"),
+        ("ceil", "Ceil({0})"),
+        ("round", "Round({0})"),
+        ("pow", "Power({0}, {1})"),
+        ("min", "Min({0}, {1})"),
+        ("max", "Max({0}, {1})"),
+    ])
+    .separator(", ' ', ");
+
+    fn emit_cross_language_interface(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("{{ Cross-language block: {} }}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "function {}: string;\n",
-                function.name
-            ));
+            out.line(format!("function {}: string;", function.name));
         }
-        output.push('\n');
-        
-        Ok(output)
+        out.blank();
     }
-    
-    fn generate_cross_language_implementation(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("{{ Cross-language block: {} }}\n", block.language));
-        
+
+    fn emit_cross_language_implementation(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("{{ Cross-language block: {} }}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "function {}: string;\nbegin\n",
-                function.name
-            ));
-            output.push_str(&format!(
-                "  raise Exception.Create('Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("end;\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function_interface(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        if function.parameters.is_empty() {
-            output.push_str(&format!("function {}", function.name));
-        } else {
-            output.push_str(&format!("function {}(", function.name));
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str("; ");
-                }
-                output.push_str(&format!("{}: {}", param.name, self.convert_type(param.param_type.as_ref())));
-            }
-            output.push(')');
+            out.line(format!("function {}: string;", function.name));
+            out.block_with("begin", "end;", |out| {
+                out.line(format!(
+                    "raise Exception.Create('Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        output.push_str(&format!(": {};\n", self.convert_return_type(&function.return_type)));
-        Ok(output)
     }
-    
-    fn generate_function_implementation(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    /// `function name(params): Type`, shared by the interface and the implementation
+    fn function_header(&self, function: &Function) -> String {
+        let return_type = self.convert_return_type(&function.return_type);
         if function.parameters.is_empty() {
-            output.push_str(&format!("function {}: {};\n", function.name, self.convert_return_type(&function.return_type)));
+            format!("function {}: {}", function.name, return_type)
         } else {
-            output.push_str(&format!("function {}(", function.name));
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str("; ");
-                }
-                output.push_str(&format!("{}: {}", param.name, self.convert_type(param.param_type.as_ref())));
-            }
-            output.push_str(&format!("): {};\n", self.convert_return_type(&function.return_type)));
-        }
-        
-        output.push_str("begin\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {};\n", self.generate_statement(statement)?));
+            let params: Vec<String> = function.parameters.iter()
+                .map(|param| format!("{}: {}", param.name, self.convert_type(param.param_type.as_ref())))
+                .collect();
+            format!("function {}({}): {}", function.name, params.join("; "), return_type)
         }
-        
-        output.push_str("end;\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} := {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {}: string;", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Result := {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("Exit".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {} then\n  begin\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("  end");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("\n  else\n  begin\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("  end");
-                }
-                Ok(output)
-            }
-            _ => Ok("{{ Unsupported statement }}".to_string()),
-        }
+
+    fn emit_function_implementation(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        out.map(function.span);
+        out.line(format!("{};", self.function_header(function)));
+        out.block_with("begin", "end;", |out| self.emit_statements(out, &function.body))
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -3071,27 +3307,11 @@ impl DelphiTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "<>",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => "{{ unsupported op }}",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -3109,22 +3329,22 @@ impl DelphiTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("CrossCall('{}', '{}', [{}])", language, function, args_str))
             }
-            _ => Ok("{{ Unsupported expression }}".to_string()),
+            other => Ok(unsupported_expression(other, "{{ Unsupported expression }}")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Double",
             Some(crate::types::Type::String) => "string",
             Some(crate::types::Type::Boolean) => "Boolean",
             Some(crate::types::Type::Void) => "string",
-            Some(crate::types::Type::Array(_)) => "TArray<Variant>", 
+            Some(crate::types::Type::Array(_)) => "TArray<Variant>",
             Some(crate::types::Type::Object(_)) => "Variant",
             _ => "string",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "Double".to_string(),
@@ -3137,206 +3357,195 @@ impl DelphiTransformer {
     }
 }
 
+impl StatementEmitter for DelphiTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} := {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {}: string;", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Result := {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("Exit;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                // No semicolon may come between the then branch and `else`
+                let then_close = if else_branch.is_some() { "end" } else { "end;" };
+                out.line(format!("if {} then", self.generate_expression(condition)?));
+                out.block_with("begin", then_close, |out| self.emit_statements(out, then_branch))?;
+                if let Some(else_stmts) = else_branch {
+                    out.line("else");
+                    out.block_with("begin", "end;", |out| self.emit_statements(out, else_stmts))?;
+                }
+            }
+            other => out.line(unsupported_statement(other, "{{ Unsupported statement }}")),
+        }
+        Ok(())
+    }
+}
+
 /// Fortran code generator
 pub struct FortranTransformer;
 
 impl Transformer for FortranTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("! Generated by Utopia Compiler - Fortran Backend\n");
-        output.push_str("! Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("! Generated by Utopia Compiler - Fortran Backend");
+        out.line("! Multi-language support with runtime integration");
+        out.blank();
+
         // Create module
-        output.push_str("module utopia_generated\n");
-        output.push_str("    implicit none\n\n");
-        
-        // Module variables if needed
-        if !program.global_statements.is_empty() {
-            output.push_str("    ! Global variables\n");
-            for statement in &program.global_statements {
-                if let Statement::VariableDeclaration { name, .. } = statement {
-                    output.push_str(&format!("    real :: {}\n", name));
+        out.block_with("module utopia_generated", "end module utopia_generated", |out| -> Result<()> {
+            out.line("implicit none");
+            out.blank();
+
+            // Module variables if needed
+            if !program.global_statements.is_empty() {
+                out.line("! Global variables");
+                for statement in &program.global_statements {
+                    if let Statement::VariableDeclaration { name, .. } = statement {
+                        out.line(format!("real :: {}", name));
+                    }
                 }
+                out.blank();
             }
-            output.push('\n');
-        }
-        
-        output.push_str("contains\n\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "fortran" || block.language == "f90" || block.language == "f95" || block.language == "main" {
-                output.push_str(&self.generate_fortran_procedures(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_procedures(block)?);
+
+            out.dedent();
+            out.line("contains");
+            out.indent();
+            out.blank();
+
+            // Process language blocks
+            for block in &program.language_blocks {
+                if block.language == "fortran" || block.language == "f90" || block.language == "f95" || block.language == "main" {
+                    for function in &block.functions {
+                        self.emit_function(out, function)?;
+                        out.blank();
+                    }
+                } else {
+                    self.emit_cross_language_procedures(out, block);
+                }
             }
-        }
-        
-        // Main program if needed
-        if !program.global_statements.is_empty() {
-            output.push_str("    subroutine main_program()\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("        call {}()\n", self.generate_statement(statement)?));
+
+            // Main program if needed
+            if !program.global_statements.is_empty() {
+                out.block_with("subroutine main_program()", "end subroutine main_program", |out| -> Result<()> {
+                    for statement in &program.global_statements {
+                        out.line(format!("call {}()", self.statement_text(out, statement)?));
+                    }
+                    Ok(())
+                })?;
+                out.blank();
             }
-            output.push_str("    end subroutine main_program\n\n");
-        }
-        
-        output.push_str("end module utopia_generated\n\n");
-        
+            Ok(())
+        })?;
+        out.blank();
+
         // Main program unit
-        output.push_str("program utopia_main\n");
-        output.push_str("    use utopia_generated\n");
-        output.push_str("    implicit none\n\n");
-        if !program.global_statements.is_empty() {
-            output.push_str("    call main_program()\n");
-        }
-        output.push_str("end program utopia_main\n");
-        
-        Ok(output)
+        out.block_with("program utopia_main", "end program utopia_main", |out| {
+            out.line("use utopia_generated");
+            out.line("implicit none");
+            out.blank();
+            if !program.global_statements.is_empty() {
+                out.line("call main_program()");
+            }
+        });
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "fortran"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".f90"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "fortran" | "f90" | "f95")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
 }
 
 impl FortranTransformer {
-    fn generate_fortran_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("    ! Cross-language block: {}\n", block.language));
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "mod", "==", "/=", "<", "<=", ">", ">=", ".and.", ".or."])
+        .with(BinaryOperator::Divide, Form::Template("real({l}) / {r}"))
+        .with(BinaryOperator::Modulo, Form::Call("mod({l}, {r})"))
+        .concat(Form::Infix("//"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print *, {args}"),
+        ("println/0", "print *"),
+        ("len", "real(len({0}))"),
+        ("trim", "trim({0})"),
+        ("contains", "(index({0}, {1}) > 0)"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "real(floor({0}))"),
+        ("ceil", "real(ceiling({0}))"),
+        ("round", "real(nint({0}))"),
+        ("pow", "({0} ** {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+    ])
+    .placeholder("0");
+
+    fn emit_cross_language_procedures(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("! Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "    function {}() result(res)\n",
-                function.name
-            ));
-            output.push_str("        character(len=100) :: res\n");
-            output.push_str(&format!(
-                "        stop 'Cross-language call to {}::{} not implemented'\n",
-                block.language, function.name
-            ));
-            output.push_str(&format!("    end function {}\n\n", function.name));
+            out.block_with(format!("function {}() result(res)", function.name), format!("end function {}", function.name), |out| {
+                out.line("character(len=100) :: res");
+                out.line(format!(
+                    "stop 'Cross-language call to {}::{} not implemented'",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Determine if it's a function or subroutine
         // Functions with return statements are treated as functions
         let has_return_value = function.body.iter().any(|stmt| matches!(stmt, Statement::Return { .. }));
-        
-        if has_return_value {
-            output.push_str(&format!("    function {}(", function.name));
-        } else {
-            output.push_str(&format!("    subroutine {}(", function.name));
-        }
-        
+        let kind = if has_return_value { "function" } else { "subroutine" };
+
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        if has_return_value {
-            output.push_str(") result(res)\n");
-            output.push_str("        implicit none\n");
-            
-            // Parameter declarations
-            for param in &function.parameters {
-                output.push_str(&format!("        {} :: {}\n", 
-                    self.convert_type(param.param_type.as_ref()), param.name));
-            }
-            
-            // Result variable
-            output.push_str(&format!("        {} :: res\n\n", 
-                self.convert_return_type(&function.return_type)));
-        } else {
-            output.push_str(")\n");
-            output.push_str("        implicit none\n");
-            
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+        let result = if has_return_value { " result(res)" } else { "" };
+
+        out.map(function.span);
+        out.block_with(format!("{} {}({}){}", kind, function.name, params.join(", "), result), format!("end {} {}", kind, function.name), |out| {
+            out.line("implicit none");
             // Parameter declarations
-            for param in &function.parameters {
-                output.push_str(&format!("        {} :: {}\n", 
-                    self.convert_type(param.param_type.as_ref()), param.name));
-            }
-            output.push('\n');
-        }
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("        {}\n", self.generate_statement(statement)?));
-        }
-        
-        if has_return_value {
-            output.push_str(&format!("    end function {}\n", function.name));
-        } else {
-            output.push_str(&format!("    end subroutine {}\n", function.name));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("real :: {}", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("res = {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("            {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("        else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("            {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("        end if");
-                Ok(output)
+            for param in &function.parameters {
+                out.line(format!("{} :: {}", self.convert_type(param.param_type.as_ref()), param.name));
             }
-            _ => Ok("! Unsupported statement".to_string()),
-        }
+            // Result variable
+            if has_return_value {
+                out.line(format!("{} :: res", self.convert_return_type(&function.return_type)));
+            }
+            out.blank();
+
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -3348,27 +3557,11 @@ impl FortranTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "/=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => ".and.",
-                    BinaryOperator::Or => ".or.",
-                    _ => "! unsupported op !",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -3386,10 +3579,10 @@ impl FortranTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("cross_call('{}', '{}', [{}])", language, function, args_str))
             }
-            _ => Ok("! Unsupported expression !".to_string()),
+            other => Ok(unsupported_expression(other, "! Unsupported expression !")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "real",
@@ -3401,7 +3594,7 @@ impl FortranTransformer {
             _ => "real",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "real".to_string(),
@@ -3414,175 +3607,198 @@ impl FortranTransformer {
     }
 }
 
+impl StatementEmitter for FortranTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("real :: {}", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("res = {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("if ({}) then", self.generate_expression(condition)?), "end if");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "! Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// SQL code generator
 pub struct SQLTransformer;
 
 impl Transformer for SQLTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("-- Generated by Utopia Compiler - SQL Backend\n");
-        output.push_str("-- Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("-- Generated by Utopia Compiler - SQL Backend");
+        out.line("-- Multi-language support with runtime integration");
+        out.blank();
+
         // Create database schema if needed
-        output.push_str("-- Database schema and procedures\n");
-        output.push_str("-- Note: Adapt connection strings and syntax for your specific RDBMS\n\n");
-        
+        out.line("-- Database schema and procedures");
+        out.line("-- Note: Adapt connection strings and syntax for your specific RDBMS");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "sql" || block.language == "main" {
-                output.push_str(&self.generate_sql_procedures(block)?);
+                self.emit_sql_procedures(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_procedures(block)?);
+                self.emit_cross_language_procedures(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("-- Main execution block\n");
-            output.push_str("BEGIN\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("END;\n/\n\n");
+            out.line("-- Main execution block");
+            out.block_with("BEGIN", "END;", |out| self.emit_statements(out, &program.global_statements))?;
+            out.line("/");
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "sql"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".sql"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "sql"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::DynamicObjects, Feature::Arrays, Feature::Modules])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "all", "alter", "and", "any", "as", "asc", "begin", "between", "by", "case", "check", "cluster",
+            "column", "compress", "connect", "create", "current", "declare", "default", "delete", "desc",
+            "distinct", "drop", "else", "end", "exclusive", "exists", "false", "for", "from", "function",
+            "goto", "grant", "group", "having", "identified", "if", "in", "index", "insert", "intersect",
+            "into", "is", "level", "like", "lock", "loop", "minus", "mode", "nocompress", "not", "nowait",
+            "null", "number", "of", "on", "option", "or", "order", "procedure", "raise", "resource",
+            "return", "revoke", "rows", "select", "set", "share", "size", "start", "table", "then", "to",
+            "trigger", "true", "type", "union", "unique", "update", "values", "view", "when", "where",
+            "while", "with",
+        ], Escape::Wrap("\"", "\"")).ignore_case()
+    }
 }
 
 impl SQLTransformer {
-    fn generate_sql_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Integer operands would divide as integers
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "=", "<>", "<", "<=", ">", ">=", "AND", "OR"])
+        .with(BinaryOperator::Divide, Form::Template("CAST({l} AS REAL) / {r}"))
+        .with(BinaryOperator::Modulo, Form::Call("MOD({l}, {r})"))
+        .concat(Form::Infix("||"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "DBMS_OUTPUT.PUT_LINE({args})"),
+        ("toString", "CAST({0} AS VARCHAR)"),
+        ("toNumber", "CAST({0} AS REAL)"),
+        ("len", "LENGTH({0})"),
+        ("upper", "UPPER({0})"),
+        ("lower", "LOWER({0})"),
+        ("trim", "TRIM({0})"),
+        ("contains", "(INSTR({0}, {1}) > 0)"),
+        ("abs", "ABS({0})"),
+        ("sqrt", "SQRT({0})"),
+        ("floor", "FLOOR({0})"),
+        ("ceil", "CEIL({0})"),
+        ("round", "ROUND({0})"),
+        ("pow", "POWER({0}, {1})"),
+        ("min", "LEAST({0}, {1})"),
+        ("max", "GREATEST({0}, {1})"),
+    ])
+    .separator(" || ' ' || ")
+    .placeholder("NULL /* unsupported builtin */");
+
+    fn emit_sql_procedures(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("-- Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("-- Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("-- Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_procedures(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("-- Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!(
-                "CREATE OR REPLACE FUNCTION {}() RETURN VARCHAR2\nIS\nBEGIN\n",
-                function.name
-            ));
-            output.push_str(&format!(
-                "    RAISE_APPLICATION_ERROR(-20001, 'Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("END;\n/\n\n");
+            out.line(format!("CREATE OR REPLACE FUNCTION {}() RETURN VARCHAR2", function.name));
+            out.line("IS");
+            out.block_with("BEGIN", "END;", |out| {
+                out.line(format!(
+                    "RAISE_APPLICATION_ERROR(-20001, 'Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.line("/");
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Determine return type
         let return_type = self.convert_return_type(&function.return_type);
-        
+
         // Function signature
-        output.push_str(&format!("CREATE OR REPLACE FUNCTION {}", function.name));
-        
-        if !function.parameters.is_empty() {
-            output.push_str("(\n");
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str(",\n");
-                }
-                output.push_str(&format!("    {} IN {}", 
-                    param.name, self.convert_type(param.param_type.as_ref())));
-            }
-            output.push_str("\n)");
+        out.map(function.span);
+        if function.parameters.is_empty() {
+            out.line(format!("CREATE OR REPLACE FUNCTION {}", function.name));
+        } else {
+            out.line(format!("CREATE OR REPLACE FUNCTION {}(", function.name));
+            let params: Vec<String> = function.parameters.iter()
+                .map(|param| format!("{} IN {}", param.name, self.convert_type(param.param_type.as_ref())))
+                .collect();
+            out.indented(|out| out.line(params.join(",\n")));
+            out.line(")");
         }
-        
-        output.push_str(&format!("\nRETURN {}\nIS\n", return_type));
-        
+        out.line(format!("RETURN {}", return_type));
+        out.line("IS");
+
         // Local variables if needed
-        output.push_str(&format!("    result_value {};\n", return_type));
-        output.push_str("BEGIN\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("    RETURN result_value;\n");
-        output.push_str("END;\n/\n");
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} := {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("DECLARE {} VARCHAR2(4000);", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("result_value := {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("RETURN".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                // For SQL, expressions are often assignments or procedure calls
-                match expression {
-                    Expression::Call { .. } => {
-                        Ok(format!("SELECT {} INTO result_value FROM DUAL", self.generate_expression(expression)?))
-                    }
-                    _ => self.generate_expression(expression)
-                }
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("IF {} THEN\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    ELSE\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    END IF");
-                Ok(output)
-            }
-            _ => Ok("-- Unsupported statement".to_string()),
-        }
+        out.indented(|out| out.line(format!("result_value {};", return_type)));
+        out.block_with("BEGIN", "END;", |out| -> Result<()> {
+            self.emit_statements(out, &function.body)?;
+            out.line("RETURN result_value;");
+            Ok(())
+        })?;
+        out.line("/");
+        Ok(())
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -3594,27 +3810,11 @@ impl SQLTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "AND",
-                    BinaryOperator::Or => "OR",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -3632,10 +3832,10 @@ impl SQLTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("CROSS_CALL('{}', '{}', '{}')", language, function, args_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "NUMBER",
@@ -3647,7 +3847,7 @@ impl SQLTransformer {
             _ => "VARCHAR2(4000)",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "NUMBER".to_string(),
@@ -3660,152 +3860,171 @@ impl SQLTransformer {
     }
 }
 
+impl StatementEmitter for SQLTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} := {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("DECLARE {} VARCHAR2(4000);", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("result_value := {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("RETURN;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                // For SQL, expressions are often assignments or procedure calls
+                match expression {
+                    Expression::Call { .. } if !builtins::is_builtin_call(expression) => {
+                        out.line(format!("SELECT {} INTO result_value FROM DUAL;", self.generate_expression(expression)?));
+                    }
+                    _ => out.line(format!("{};", self.generate_expression(expression)?)),
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("IF {} THEN", self.generate_expression(condition)?), "END IF;");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("ELSE");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "-- Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// R code generator
 pub struct RTransformer;
 
 impl Transformer for RTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - R Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("# Generated by Utopia Compiler - R Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.blank();
+
         // Add necessary libraries
-        output.push_str("# Load required libraries\n");
-        output.push_str("library(jsonlite)\n");
-        output.push_str("library(httr)\n\n");
-        
+        out.line("# Load required libraries");
+        out.line("library(jsonlite)");
+        out.line("library(httr)");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "r" || block.language == "main" {
-                output.push_str(&self.generate_r_functions(block)?);
+                self.emit_r_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{};\n", self.generate_statement(statement)?));
-            }
+            out.line("# Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "r"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".R"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "r"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "break", "else", "FALSE", "for", "function", "if", "in", "Inf", "NA", "NA_character_",
+            "NA_integer_", "NA_real_", "NaN", "next", "NULL", "repeat", "TRUE", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl RTransformer {
-    fn generate_r_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// R's `%%` floors, so the remainder is computed from a truncated quotient
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Template("{l} - {r} * trunc({l} / {r})"))
+        .concat(Form::Call("paste0({l}, {r})"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "cat({args}, \"\\n\")"),
+        ("println/0", "cat(\"\\n\")"),
+        ("toString", "as.character({0})"),
+        ("toNumber", "as.numeric({0})"),
+        ("toBoolean", "as.logical({0})"),
+        ("len", "length({0})"),
+        ("push", "{0} <- c({0}, {1})"),
+        ("upper", "toupper({0})"),
+        ("lower", "tolower({0})"),
+        ("trim", "trimws({0})"),
+        ("contains", "grepl({1}, {0}, fixed = TRUE)"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceiling({0})"),
+        ("round", "round({0})"),
+        ("pow", "({0} ^ {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "names({0})"),
+        ("hasKey", "({1} %in% names({0}))"),
+    ]);
+
+    fn emit_r_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("# Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{} <- function(...) {{\n", function.name));
-            output.push_str(&format!(
-                "    stop('Cross-language call to {}::{} not implemented')\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("{} <- function(...)", function.name), |out| {
+                out.line(format!(
+                    "stop('Cross-language call to {}::{} not implemented')",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str(&format!("{} <- function(", function.name));
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(") {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} <- {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} <- NULL", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return({})", self.generate_expression(expr)?))
-                } else {
-                    Ok("return()".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement".to_string()),
-        }
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("{} <- function({})", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -3817,27 +4036,11 @@ impl RTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&",
-                    BinaryOperator::Or => "|",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -3846,23 +4049,53 @@ impl RTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for R
-                if callee_str == "println" {
-                    Ok(format!("cat({})", args_str))
+
+                Ok(format!("{}({})", callee_str, args_str))
+            }
+            Expression::CrossCall { language, function, arguments, .. } => {
+                let args: Result<Vec<String>> = arguments.iter()
+                    .map(|arg| self.generate_expression(arg))
+                    .collect();
+                let args_str = args?.join(", ");
+                Ok(format!("cross_call('{}', '{}', list({}))", language, function, args_str))
+            }
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for RTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} <- {}", name, self.generate_expression(expr)?));
                 } else {
-                    Ok(format!("{}({})", callee_str, args_str))
+                    out.line(format!("{} <- NULL", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return({})", self.generate_expression(expr)?));
+                } else {
+                    out.line("return()");
                 }
             }
-            Expression::CrossCall { language, function, arguments, .. } => {
-                let args: Result<Vec<String>> = arguments.iter()
-                    .map(|arg| self.generate_expression(arg))
-                    .collect();
-                let args_str = args?.join(", ");
-                Ok(format!("cross_call('{}', '{}', list({}))", language, function, args_str))
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -3870,151 +4103,134 @@ impl RTransformer {
 pub struct MatlabTransformer;
 
 impl Transformer for MatlabTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("% Generated by Utopia Compiler - MATLAB Backend\n");
-        output.push_str("% Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::END);
+        out.line("% Generated by Utopia Compiler - MATLAB Backend");
+        out.line("% Multi-language support with runtime integration");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "matlab" || block.language == "m" || block.language == "main" {
-                output.push_str(&self.generate_matlab_functions(block)?);
+                self.emit_matlab_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("% Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{};\n", self.generate_statement(statement)?));
-            }
+            out.line("% Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "matlab"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".m"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "matlab" | "m")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "break", "case", "catch", "classdef", "continue", "else", "elseif", "end", "for", "function",
+            "global", "if", "otherwise", "parfor", "persistent", "return", "spmd", "switch", "try", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl MatlabTransformer {
-    fn generate_matlab_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", ".*", "./", "rem", "==", "~=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Call("rem({l}, {r})"))
+        .concat(Form::Call("[{l}, {r}]"))
+        .to_string("num2str({})")
+        .strings([
+            Form::Call("strcmp({l}, {r})"),
+            Form::Call("~strcmp({l}, {r})"),
+            Form::Infix("<"),
+            Form::Infix("<="),
+            Form::Infix(">"),
+            Form::Infix(">="),
+        ])
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "disp([{args}])"),
+        ("println/0", "disp('')"),
+        ("toString", "num2str({0})"),
+        ("toNumber", "str2double({0})"),
+        ("toBoolean", "logical({0})"),
+        ("len", "numel({0})"),
+        ("push", "{0}(end + 1) = {1}"),
+        ("upper", "upper({0})"),
+        ("lower", "lower({0})"),
+        ("trim", "strtrim({0})"),
+        ("contains", "contains({0}, {1})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceil({0})"),
+        ("round", "round({0})"),
+        ("pow", "({0} ^ {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "fieldnames({0})"),
+        ("hasKey", "isfield({0}, {1})"),
+    ])
+    .separator(", ' ', ")
+    .placeholder("[]");
+
+    fn emit_matlab_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("% Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("% Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("% Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("% Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("function result = {}(varargin)\n", function.name));
-            output.push_str(&format!(
-                "    error('Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("end\n\n");
+            out.block(format!("function result = {}(varargin)", function.name), |out| {
+                out.line(format!(
+                    "error('Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature - MATLAB style
-        output.push_str("function ");
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Return value (MATLAB functions can return values)
         let has_return = function.body.iter().any(|stmt| matches!(stmt, Statement::Return { .. }));
-        if has_return {
-            output.push_str("result = ");
-        }
-        
-        output.push_str(&format!("{}(", function.name));
-        
+        let result = if has_return { "result = " } else { "" };
+
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("end\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = [];", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("result = {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    end");
-                Ok(output)
-            }
-            _ => Ok("% Unsupported statement".to_string()),
-        }
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("function {}{}({})", result, function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -4026,27 +4242,11 @@ impl MatlabTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => ".*",  // Element-wise multiplication in MATLAB
-                    BinaryOperator::Divide => "./",    // Element-wise division in MATLAB
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "~=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&",
-                    BinaryOperator::Or => "|",
-                    _ => "% unsupported op %",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -4055,13 +4255,8 @@ impl MatlabTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for MATLAB
-                if callee_str == "println" {
-                    Ok(format!("disp({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -4080,8 +4275,43 @@ impl MatlabTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("% Unsupported expression %".to_string()),
+            other => Ok(unsupported_expression(other, "% Unsupported expression %")),
+        }
+    }
+}
+
+impl StatementEmitter for MatlabTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = [];", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("result = {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "% Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -4089,166 +4319,133 @@ impl MatlabTransformer {
 pub struct KotlinTransformer;
 
 impl Transformer for KotlinTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Kotlin Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Kotlin Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.blank();
+
         // Package declaration
-        output.push_str("package utopia.generated\n\n");
-        
+        out.line("package utopia.generated");
+        out.blank();
+
         // Imports
-        output.push_str("import kotlinx.coroutines.*\n");
-        output.push_str("import kotlinx.serialization.*\n");
-        output.push_str("import kotlinx.serialization.json.*\n\n");
-        
+        out.lines(["import kotlinx.coroutines.*", "import kotlinx.serialization.*", "import kotlinx.serialization.json.*"]);
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "kotlin" || block.language == "kt" || block.language == "main" {
-                output.push_str(&self.generate_kotlin_functions(block)?);
+                self.emit_kotlin_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main function if needed
         if !program.global_statements.is_empty() {
-            output.push_str("fun main() {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n");
+            out.block("fun main()", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "kotlin"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".kt"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "kotlin" | "kt")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "as", "break", "class", "continue", "do", "else", "false", "for", "fun", "if", "in", "interface",
+            "is", "null", "object", "package", "return", "super", "this", "throw", "true", "try",
+            "typealias", "typeof", "val", "var", "when", "while",
+        ], Escape::Wrap("`", "`"))
+    }
 }
 
 impl KotlinTransformer {
-    fn generate_kotlin_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("{l}.toDouble() / {r}"))
+        .concat(Form::Call("\"${{l}}${{r}}\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "println(listOf({args}).joinToString(\" \"))"),
+        ("println/0", "println()"),
+        ("toString", "{0}.toString()"),
+        ("toNumber", "{0}.toString().toDouble()"),
+        ("toBoolean", "{0}.toString().toBoolean()"),
+        ("len", "{0}.size"),
+        ("push", "{0}.add({1})"),
+        ("pop", "{0}.removeLast()"),
+        ("upper", "{0}.uppercase()"),
+        ("lower", "{0}.lowercase()"),
+        ("trim", "{0}.trim()"),
+        ("contains", "{0}.contains({1})"),
+        ("abs", "kotlin.math.abs({0})"),
+        ("sqrt", "kotlin.math.sqrt({0})"),
+        ("floor", "kotlin.math.floor({0})"),
+        ("ceil", "kotlin.math.ceil({0})"),
+        ("round", "kotlin.math.round({0})"),
+        ("pow", "Math.pow({0}, {1})"),
+        ("min", "minOf({0}, {1})"),
+        ("max", "maxOf({0}, {1})"),
+        ("keys", "{0}.keys.toList()"),
+        ("hasKey", "{0}.containsKey({1})"),
+    ]);
+
+    fn emit_kotlin_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("fun main() {\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
-            output.push_str("}\n");
+            out.block("fun main()", |out| self.emit_statements(out, &block.statements))?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("fun {}(vararg args: Any): Any {{\n", function.name));
-            output.push_str(&format!(
-                "    throw NotImplementedError(\"Cross-language call to {}::{} not implemented\")\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("fun {}(vararg args: Any): Any", function.name), |out| {
+                out.line(format!(
+                    "throw NotImplementedError(\"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str("fun ");
-        output.push_str(&function.name);
-        output.push_str("(");
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{}: {}", 
-                param.name, self.convert_type(param.param_type.as_ref())));
-        }
-        
-        output.push_str(")");
-        
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{}: {}", param.name, self.convert_type(param.param_type.as_ref())))
+            .collect();
+
         // Return type
         let return_type = self.convert_return_type(&function.return_type);
-        if return_type != "Unit" {
-            output.push_str(&format!(": {}", return_type));
-        }
-        
-        output.push_str(" {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("val {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {}: Any? = null", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+        let return_type = if return_type == "Unit" { String::new() } else { format!(": {}", return_type) };
+
+        out.map(function.span);
+        out.block(format!("fun {}({}){}", function.name, params.join(", "), return_type), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -4260,27 +4457,11 @@ impl KotlinTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -4289,13 +4470,8 @@ impl KotlinTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions - Kotlin uses println natively
-                if callee_str == "println" {
-                    Ok(format!("println({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -4313,10 +4489,10 @@ impl KotlinTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Double",
@@ -4328,7 +4504,7 @@ impl KotlinTransformer {
             _ => "Any",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "Double".to_string(),
@@ -4341,167 +4517,174 @@ impl KotlinTransformer {
     }
 }
 
+impl StatementEmitter for KotlinTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("val {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {}: Any? = null", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Swift code generator
 pub struct SwiftTransformer;
 
 impl Transformer for SwiftTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Swift Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Swift Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.blank();
+
         // Imports
-        output.push_str("import Foundation\n");
-        output.push_str("import UIKit // iOS/macOS development\n");
-        output.push_str("import SwiftUI // Modern UI framework\n\n");
-        
+        out.lines(["import Foundation", "import UIKit // iOS/macOS development", "import SwiftUI // Modern UI framework"]);
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "swift" || block.language == "main" {
-                output.push_str(&self.generate_swift_functions(block)?);
+                self.emit_swift_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("// Main execution\n");
-            output.push_str("func main() {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n\n");
-            output.push_str("main()\n");
+            out.line("// Main execution");
+            out.block("func main()", |out| self.emit_statements(out, &program.global_statements))?;
+            out.blank();
+            out.line("main()");
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "swift"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".swift"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "swift"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "Any", "as", "associatedtype", "await", "break", "case", "catch", "class", "continue", "default",
+            "defer", "deinit", "do", "else", "enum", "extension", "fallthrough", "false", "fileprivate",
+            "for", "func", "guard", "if", "import", "in", "init", "inout", "internal", "is", "let", "nil",
+            "open", "operator", "precedencegroup", "private", "protocol", "public", "repeat", "rethrows",
+            "return", "Self", "self", "static", "struct", "subscript", "super", "switch", "throw", "throws",
+            "true", "try", "typealias", "var", "where", "while",
+        ], Escape::Wrap("`", "`"))
+    }
 }
 
 impl SwiftTransformer {
-    fn generate_swift_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("Double({l}) / Double({r})"))
+        .with(BinaryOperator::Modulo, Form::Call("Double({l}).truncatingRemainder(dividingBy: Double({r}))"))
+        .concat(Form::Call("\"\\({l})\\({r})\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print({args})"),
+        ("toString", "String(describing: {0})"),
+        ("toNumber", "(Double(\"\\({0})\") ?? .nan)"),
+        ("len", "Double({0}.count)"),
+        ("push", "{0}.append({1})"),
+        ("pop", "{0}.popLast()"),
+        ("upper", "{0}.uppercased()"),
+        ("lower", "{0}.lowercased()"),
+        ("trim", "{0}.trimmingCharacters(in: .whitespaces)"),
+        ("contains", "{0}.contains({1})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "({0}).squareRoot()"),
+        ("floor", "({0}).rounded(.down)"),
+        ("ceil", "({0}).rounded(.up)"),
+        ("round", "({0}).rounded()"),
+        ("pow", "pow({0}, {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "Array({0}.keys)"),
+        ("hasKey", "({0}[{1}] != nil)"),
+    ]);
+
+    fn emit_swift_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("// Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("// Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+        Ok(())
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("func {}(_ args: Any...) -> Any {{\n", function.name));
-            output.push_str(&format!(
-                "    fatalError(\"Cross-language call to {}::{} not implemented\")\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("func {}(_ args: Any...) -> Any", function.name), |out| {
+                out.line(format!(
+                    "fatalError(\"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str("func ");
-        output.push_str(&function.name);
-        output.push_str("(");
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("_ {}: {}", 
-                param.name, self.convert_type(param.param_type.as_ref())));
-        }
-        
-        output.push_str(")");
-        
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("_ {}: {}", param.name, self.convert_type(param.param_type.as_ref())))
+            .collect();
+
         // Return type
         let return_type = self.convert_return_type(&function.return_type);
-        if return_type != "Void" {
-            output.push_str(&format!(" -> {}", return_type));
-        }
-        
-        output.push_str(" {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("let {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {}: Any? = nil", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {} {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+        let return_type = if return_type == "Void" { String::new() } else { format!(" -> {}", return_type) };
+
+        out.map(function.span);
+        out.block(format!("func {}({}){}", function.name, params.join(", "), return_type), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -4513,27 +4696,11 @@ impl SwiftTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -4542,13 +4709,8 @@ impl SwiftTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for Swift
-                if callee_str == "println" {
-                    Ok(format!("print({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -4566,10 +4728,10 @@ impl SwiftTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Double",
@@ -4581,7 +4743,7 @@ impl SwiftTransformer {
             _ => "Any",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "Double".to_string(),
@@ -4594,149 +4756,175 @@ impl SwiftTransformer {
     }
 }
 
+impl StatementEmitter for SwiftTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("let {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {}: Any? = nil", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// COBOL code generator
 pub struct CobolTransformer;
 
+/// Columns 1-6 (sequence area) and 7 (indicator), ahead of area A
+const COBOL_SEQUENCE_AREA: &str = "      ";
+const COBOL_CODE_MARGIN: &str = "       ";
+
 impl Transformer for CobolTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("      * Generated by Utopia Compiler - COBOL Backend\n");
-        output.push_str("      * Multi-language support with runtime integration\n");
-        output.push_str("       IDENTIFICATION DIVISION.\n");
-        output.push_str("       PROGRAM-ID. UTOPIA-GENERATED.\n");
-        output.push_str("       ENVIRONMENT DIVISION.\n");
-        output.push_str("       DATA DIVISION.\n");
-        output.push_str("       WORKING-STORAGE SECTION.\n");
-        output.push_str("       01  WS-RESULT         PIC X(100).\n");
-        output.push_str("       01  WS-TEMP-NUM       PIC 9(10)V99.\n");
-        output.push_str("       01  WS-TEMP-STR       PIC X(100).\n");
-        output.push_str("       PROCEDURE DIVISION.\n");
-        output.push_str("       MAIN-LOGIC.\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        self.comment(&mut out, "Generated by Utopia Compiler - COBOL Backend");
+        self.comment(&mut out, "Multi-language support with runtime integration");
+        out.lines([
+            "IDENTIFICATION DIVISION.",
+            "PROGRAM-ID. UTOPIA-GENERATED.",
+            "ENVIRONMENT DIVISION.",
+            "DATA DIVISION.",
+            "WORKING-STORAGE SECTION.",
+            "01  WS-RESULT         PIC X(100).",
+            "01  WS-TEMP-NUM       PIC 9(10)V99.",
+            "01  WS-TEMP-STR       PIC X(100).",
+            "PROCEDURE DIVISION.",
+            "MAIN-LOGIC.",
+        ]);
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "cobol" || block.language == "cob" || block.language == "main" {
-                output.push_str(&self.generate_cobol_procedures(block)?);
+                for function in &block.functions {
+                    self.emit_function(&mut out, function)?;
+                    out.blank();
+                }
             } else {
-                output.push_str(&self.generate_cross_language_procedures(block)?);
+                self.emit_cross_language_procedures(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("       PERFORM MAIN-PROCESSING.\n");
-            output.push_str("       \n");
-            output.push_str("       MAIN-PROCESSING.\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("       {}.\n", self.generate_statement(statement)?));
-            }
+            out.line("PERFORM MAIN-PROCESSING.");
+            out.blank();
+            out.line("MAIN-PROCESSING.");
+            out.indented(|out| -> Result<()> {
+                for statement in &program.global_statements {
+                    out.line(format!("{}.", self.statement_text(out, statement)?));
+                }
+                Ok(())
+            })?;
         }
-        
-        output.push_str("       STOP RUN.\n");
-        Ok(output)
+
+        out.line("STOP RUN.");
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "cobol"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".cob"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "cobol" | "cob")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
 }
 
 impl CobolTransformer {
-    fn generate_cobol_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_procedures(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("      * Cross-language block: {}\n", block.language));
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "=", "NOT =", "<", "<=", ">", ">=", "AND", "OR"])
+        .with(BinaryOperator::Modulo, Form::Call("FUNCTION REM({l}, {r})"))
+        .concat(Form::Unsupported)
+        .placeholder("* unsupported operation *")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "DISPLAY {args}"),
+        ("println/0", "DISPLAY SPACE"),
+        ("toString", "FUNCTION TRIM({0})"),
+        ("toNumber", "FUNCTION NUMVAL({0})"),
+        ("len", "FUNCTION LENGTH({0})"),
+        ("upper", "FUNCTION UPPER-CASE({0})"),
+        ("lower", "FUNCTION LOWER-CASE({0})"),
+        ("trim", "FUNCTION TRIM({0})"),
+        ("abs", "FUNCTION ABS({0})"),
+        ("sqrt", "FUNCTION SQRT({0})"),
+        ("floor", "FUNCTION INTEGER({0})"),
+        ("round", "FUNCTION INTEGER({0} + 0.5)"),
+        ("min", "FUNCTION MIN({0}, {1})"),
+        ("max", "FUNCTION MAX({0}, {1})"),
+    ])
+    .separator(" ")
+    .placeholder("* unsupported builtin *");
+
+    /// A comment line, marked by `*` in the indicator column
+    fn comment(&self, out: &mut CodeWriter, text: &str) {
+        out.set_margin(COBOL_SEQUENCE_AREA);
+        out.line(format!("* {}", text));
+        out.set_margin(COBOL_CODE_MARGIN);
+    }
+
+    fn emit_cross_language_procedures(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        self.comment(out, &format!("Cross-language block: {}", block.language));
+
         for function in &block.functions {
             let func_name = function.name.to_uppercase().replace('_', "-");
-            output.push_str(&format!("       {}.\n", func_name));
-            output.push_str(&format!(
-                "           DISPLAY 'Cross-language call to {}::{} not implemented'\n",
-                block.language, function.name
-            ));
-            output.push_str("           EXIT.\n\n");
+            out.line(format!("{}.", func_name));
+            out.indented(|out| {
+                out.line(format!(
+                    "DISPLAY 'Cross-language call to {}::{} not implemented'",
+                    block.language, function.name
+                ));
+                out.line("EXIT.");
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         let func_name = function.name.to_uppercase().replace('_', "-");
-        
+
         // Function paragraph
-        output.push_str(&format!("       {}.\n", func_name));
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("           {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("           EXIT.\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                let var_name = name.to_uppercase().replace('_', "-");
-                if let Some(expr) = value {
-                    Ok(format!("MOVE {} TO WS-{}", self.generate_expression(expr)?, var_name))
-                } else {
-                    Ok(format!("MOVE SPACES TO WS-{}", var_name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("MOVE {} TO WS-RESULT", self.generate_expression(expr)?))
-                } else {
-                    Ok("MOVE SPACES TO WS-RESULT".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                match expression {
-                    Expression::Call { .. } => {
-                        Ok(format!("PERFORM {}", self.generate_expression(expression)?))
-                    }
-                    _ => self.generate_expression(expression)
-                }
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("IF {} THEN\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("               {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("           ELSE\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("               {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("           END-IF");
-                Ok(output)
-            }
-            _ => Ok("* Unsupported statement".to_string()),
-        }
+        out.map(function.span);
+        out.line(format!("{}.", func_name));
+        out.indented(|out| -> Result<()> {
+            self.emit_statements(out, &function.body)?;
+            out.line("EXIT.");
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -4751,26 +4939,11 @@ impl CobolTransformer {
                 let var_name = name.to_uppercase().replace('_', "-");
                 Ok(format!("WS-{}", var_name))
             }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("({} + {})", left_str, right_str)),
-                    BinaryOperator::Subtract => Ok(format!("({} - {})", left_str, right_str)),
-                    BinaryOperator::Multiply => Ok(format!("({} * {})", left_str, right_str)),
-                    BinaryOperator::Divide => Ok(format!("({} / {})", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("{} = {}", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("{} NOT = {}", left_str, right_str)),
-                    BinaryOperator::Less => Ok(format!("{} < {}", left_str, right_str)),
-                    BinaryOperator::Greater => Ok(format!("{} > {}", left_str, right_str)),
-                    BinaryOperator::LessEqual => Ok(format!("{} <= {}", left_str, right_str)),
-                    BinaryOperator::GreaterEqual => Ok(format!("{} >= {}", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("{} AND {}", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("{} OR {}", left_str, right_str)),
-                    _ => Ok("* unsupported operation *".to_string()),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments: _, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => {
                         name.to_uppercase().replace('_', "-")
@@ -4792,8 +4965,49 @@ impl CobolTransformer {
                 };
                 Ok(format!("({} {})", op_str, operand_str))
             }
-            _ => Ok("* Unsupported expression *".to_string()),
+            other => Ok(unsupported_expression(other, "* Unsupported expression *")),
+        }
+    }
+}
+
+impl StatementEmitter for CobolTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                let var_name = name.to_uppercase().replace('_', "-");
+                if let Some(expr) = value {
+                    out.line(format!("MOVE {} TO WS-{}", self.generate_expression(expr)?, var_name));
+                } else {
+                    out.line(format!("MOVE SPACES TO WS-{}", var_name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("MOVE {} TO WS-RESULT", self.generate_expression(expr)?));
+                } else {
+                    out.line("MOVE SPACES TO WS-RESULT");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                match expression {
+                    Expression::Call { .. } if !builtins::is_builtin_call(expression) => {
+                        out.line(format!("PERFORM {}", self.generate_expression(expression)?));
+                    }
+                    _ => out.line(self.generate_expression(expression)?),
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("IF {} THEN", self.generate_expression(condition)?), "END-IF");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("ELSE");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "* Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -4801,153 +5015,134 @@ impl CobolTransformer {
 pub struct RubyTransformer;
 
 impl Transformer for RubyTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - Ruby Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# Ruby on Rails and web development\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::END);
+        out.line("# Generated by Utopia Compiler - Ruby Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# Ruby on Rails and web development");
+        out.blank();
+
         // Require statements
-        output.push_str("require 'json'\n");
-        output.push_str("require 'net/http'\n");
-        output.push_str("require 'uri'\n\n");
-        
+        out.line("require 'json'");
+        out.line("require 'net/http'");
+        out.line("require 'uri'");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "ruby" || block.language == "rb" || block.language == "main" {
-                output.push_str(&self.generate_ruby_methods(block)?);
+                self.emit_ruby_methods(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_methods(block)?);
+                self.emit_cross_language_methods(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
+            out.line("# Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "ruby"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".rb"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "ruby" | "rb")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "__ENCODING__", "__FILE__", "__LINE__", "alias", "and", "BEGIN", "begin", "break", "case",
+            "class", "def", "defined", "do", "else", "elsif", "END", "end", "ensure", "false", "for", "if",
+            "in", "module", "next", "nil", "not", "or", "redo", "rescue", "retry", "return", "self", "super",
+            "then", "true", "undef", "unless", "until", "when", "while", "yield",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl RubyTransformer {
-    fn generate_ruby_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Integer `/` floors in Ruby and `%` takes the sign of the divisor
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Call("{l}.fdiv({r})"))
+        .with(BinaryOperator::Modulo, Form::Call("{l}.remainder({r})"))
+        .concat(Form::Call("\"#{{l}}#{{r}}\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "puts [{args}].join(\" \")"),
+        ("println/0", "puts"),
+        ("toString", "{0}.to_s"),
+        ("toNumber", "Float({0})"),
+        ("toBoolean", "!!{0}"),
+        ("len", "{0}.length"),
+        ("push", "{0}.push({1})"),
+        ("pop", "{0}.pop"),
+        ("upper", "{0}.upcase"),
+        ("lower", "{0}.downcase"),
+        ("trim", "{0}.strip"),
+        ("contains", "{0}.include?({1})"),
+        ("abs", "{0}.abs"),
+        ("sqrt", "Math.sqrt({0})"),
+        ("floor", "{0}.floor"),
+        ("ceil", "{0}.ceil"),
+        ("round", "{0}.round"),
+        ("pow", "({0} ** {1})"),
+        ("min", "[{0}, {1}].min"),
+        ("max", "[{0}, {1}].max"),
+        ("keys", "{0}.keys"),
+        ("hasKey", "{0}.key?({1})"),
+    ])
+    .placeholder("nil");
+
+    fn emit_ruby_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
-        for function in &block.functions {
-            output.push_str(&format!("def {}(*args)\n", function.name));
-            output.push_str(&format!(
-                "  raise NotImplementedError, 'Cross-language call to {}::{} not implemented'\n",
-                block.language, function.name
-            ));
-            output.push_str("end\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Method definition
-        output.push_str("def ");
-        output.push_str(&function.name);
-        
-        if !function.parameters.is_empty() {
-            output.push_str("(");
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i > 0 {
-                    output.push_str(", ");
-                }
-                output.push_str(&param.name);
-            }
-            output.push_str(")");
-        }
-        
-        output.push_str("\n");
-        
-        // Method body
-        for statement in &function.body {
-            output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("end\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = nil", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("  else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("  end");
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement".to_string()),
+        if block.language == "main" && !block.statements.is_empty() {
+            out.line("# Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
+
+        Ok(())
     }
-    
+
+    fn emit_cross_language_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.block(format!("def {}(*args)", function.name), |out| {
+                out.line(format!(
+                    "raise NotImplementedError, 'Cross-language call to {}::{} not implemented'",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
+        }
+    }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        // Method definition
+        let mut header = format!("def {}", function.name);
+        if !function.parameters.is_empty() {
+            let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+            header.push_str(&format!("({})", params.join(", ")));
+        }
+
+        out.map(function.span);
+        out.block(header, |out| self.emit_statements(out, &function.body))
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -4959,27 +5154,11 @@ impl RubyTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -4988,13 +5167,8 @@ impl RubyTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("puts {}", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -5012,8 +5186,43 @@ impl RubyTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for RubyTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = nil", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -5021,163 +5230,145 @@ impl RubyTransformer {
 pub struct LispTransformer;
 
 impl Transformer for LispTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(";; Generated by Utopia Compiler - Lisp Backend\n");
-        output.push_str(";; Multi-language support with runtime integration\n");
-        output.push_str(";; AI research and academic programming\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close().line_comment(";"));
+        out.line(";; Generated by Utopia Compiler - Lisp Backend");
+        out.line(";; Multi-language support with runtime integration");
+        out.line(";; AI research and academic programming");
+        out.blank();
+
         // Common Lisp setup
-        output.push_str("(require 'cl-lib)\n");
-        output.push_str("(require 'json)\n\n");
-        
+        out.line("(require 'cl-lib)");
+        out.line("(require 'json)");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "lisp" || block.language == "cl" || block.language == "main" {
-                output.push_str(&self.generate_lisp_functions(block)?);
+                self.emit_lisp_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str(";; Main execution\n");
-            output.push_str("(progn\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str(")\n");
+            out.line(";; Main execution");
+            out.block("(progn", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "lisp"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".lisp"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "lisp" | "cl")
     }
+
 }
 
 impl LispTransformer {
-    fn generate_lisp_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("(+ {l} {r})"),
+        Form::Call("(- {l} {r})"),
+        Form::Call("(* {l} {r})"),
+        Form::Call("(/ (float {l}) {r})"),
+        Form::Call("(rem {l} {r})"),
+        Form::Call("(= {l} {r})"),
+        Form::Call("(/= {l} {r})"),
+        Form::Call("(< {l} {r})"),
+        Form::Call("(<= {l} {r})"),
+        Form::Call("(> {l} {r})"),
+        Form::Call("(>= {l} {r})"),
+        Form::Call("(and {l} {r})"),
+        Form::Call("(or {l} {r})"),
+    ])
+    .concat(Form::Call("(concatenate 'string {l} {r})"))
+    .to_string("(princ-to-string {})")
+    .strings([
+        Form::Call("(string= {l} {r})"),
+        Form::Call("(string/= {l} {r})"),
+        Form::Call("(string< {l} {r})"),
+        Form::Call("(string<= {l} {r})"),
+        Form::Call("(string> {l} {r})"),
+        Form::Call("(string>= {l} {r})"),
+    ]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "(format t \"~{~a~^ ~}~%\" (list {args}))"),
+        ("println/0", "(terpri)"),
+        ("toString", "(princ-to-string {0})"),
+        ("toNumber", "(float (read-from-string {0}))"),
+        ("toBoolean", "(not (null {0}))"),
+        ("len", "(length {0})"),
+        ("push", "(vector-push-extend {1} {0})"),
+        ("pop", "(vector-pop {0})"),
+        ("upper", "(string-upcase {0})"),
+        ("lower", "(string-downcase {0})"),
+        ("trim", "(string-trim \" \" {0})"),
+        ("contains", "(search {1} {0})"),
+        ("abs", "(abs {0})"),
+        ("sqrt", "(sqrt {0})"),
+        ("floor", "(ffloor {0})"),
+        ("ceil", "(fceiling {0})"),
+        ("round", "(fround {0})"),
+        ("pow", "(expt {0} {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+        ("keys", "(loop for key being the hash-keys of {0} collect key)"),
+        ("hasKey", "(nth-value 1 (gethash {1} {0}))"),
+    ])
+    .separator(" ")
+    .placeholder("nil");
+
+    fn emit_lisp_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!(";; Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!(";; Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("(defun {} (&rest args)\n", function.name));
-            output.push_str(&format!(
-                "  (error \"Cross-language call to {}::{} not implemented\"))\n\n",
-                block.language, function.name
-            ));
+            out.block(format!("(defun {} (&rest args)", function.name), |out| {
+                out.line(format!(
+                    "(error \"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("(defun {} (", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(" ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        if function.body.len() == 1 {
-            // Single expression
-            output.push_str(&format!("  {})", self.generate_statement(&function.body[0])?));
-        } else {
-            // Multiple statements - use progn
-            output.push_str("  (progn\n");
-            for statement in &function.body {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str("  ))");
-        }
-        
-        output.push('\n');
-        Ok(output)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("(defun {} ({})", function.name, params.join(" ")), |out| {
+            self.emit_body(out, &function.body)
+        })
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("(setq {} {})", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("(setq {} nil)", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("nil".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("(if {}\n", self.generate_expression(condition)?);
-                
-                if then_branch.len() == 1 {
-                    output.push_str(&format!("    {}", self.generate_statement(&then_branch[0])?));
-                } else {
-                    output.push_str("    (progn\n");
-                    for stmt in then_branch {
-                        output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    )");
-                }
-                
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("\n");
-                    if else_stmts.len() == 1 {
-                        output.push_str(&format!("    {}", self.generate_statement(&else_stmts[0])?));
-                    } else {
-                        output.push_str("    (progn\n");
-                        for stmt in else_stmts {
-                            output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                        }
-                        output.push_str("    )");
-                    }
-                }
-                
-                output.push_str(")");
-                Ok(output)
-            }
-            _ => Ok(";; Unsupported statement".to_string()),
+
+    /// A single form as is, several wrapped in `progn`
+    fn emit_body(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        if let [statement] = statements {
+            self.emit_statement(out, statement)
+        } else {
+            out.block("(progn", |out| self.emit_statements(out, statements))
         }
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -5189,27 +5380,11 @@ impl LispTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "/=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => ";; unsupported op ;;",
-                };
-                Ok(format!("({} {} {})", op_str, left_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -5236,8 +5411,42 @@ impl LispTransformer {
                 };
                 Ok(format!("({} {})", op_str, operand_str))
             }
-            _ => Ok(";; Unsupported expression ;;".to_string()),
+            other => Ok(unsupported_expression(other, ";; Unsupported expression ;;")),
+        }
+    }
+}
+
+impl StatementEmitter for LispTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("(setq {} {})", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("(setq {} nil)", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("nil");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("(if {}", self.generate_expression(condition)?));
+                self.emit_body(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    self.emit_body(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, ";; Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -5245,188 +5454,157 @@ impl LispTransformer {
 pub struct PrologTransformer;
 
 impl Transformer for PrologTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("% Generated by Utopia Compiler - Prolog Backend\n");
-        output.push_str("% Multi-language support with runtime integration\n");
-        output.push_str("% Logic programming and AI applications\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("% Generated by Utopia Compiler - Prolog Backend");
+        out.line("% Multi-language support with runtime integration");
+        out.line("% Logic programming and AI applications");
+        out.blank();
+
         // Prolog directives
-        output.push_str(":- use_module(library(lists)).\n");
-        output.push_str(":- use_module(library(clpfd)).\n");
-        output.push_str(":- use_module(library(dcg/basics)).\n\n");
-        
+        out.line(":- use_module(library(lists)).");
+        out.line(":- use_module(library(clpfd)).");
+        out.line(":- use_module(library(dcg/basics)).");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "prolog" || block.language == "pl" || block.language == "main" {
-                output.push_str(&self.generate_prolog_predicates(block)?);
+                self.emit_prolog_predicates(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_predicates(block)?);
+                self.emit_cross_language_predicates(&mut out, block);
             }
         }
-        
+
         // Global statements as facts/rules
         if !program.global_statements.is_empty() {
-            output.push_str("% Main execution facts\n");
+            out.line("% Main execution facts");
             for statement in &program.global_statements {
-                output.push_str(&format!("{}.\n", self.generate_statement(statement)?));
+                out.line(format!("{}.", self.statement_text(&out, statement)?));
             }
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "prolog"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".pl"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "prolog" | "pl")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::DynamicObjects])
+    }
 }
 
 impl PrologTransformer {
-    fn generate_prolog_predicates(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Numbers compare arithmetically, strings by the standard order of terms
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "=:=", "=\\=", "<", "=<", ">", ">=", ",", ";"])
+        .concat(Form::Unsupported)
+        .strings([Form::Infix("=="), Form::Infix("\\=="), Form::Infix("@<"), Form::Infix("@=<"), Form::Infix("@>"), Form::Infix("@>=")])
+        .placeholder("% unsupported operation %")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "format(\"{formats}~n\", [{args}])"),
+        ("println/0", "nl"),
+        ("toString", "term_string({0})"),
+        ("len", "length({0})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceiling({0})"),
+        ("round", "round({0})"),
+        ("pow", "({0} ** {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+    ])
+    .formats(["~w", "~w", "~w", "~w"])
+    .placeholder("% unsupported builtin %");
+
+    fn emit_prolog_predicates(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_predicates(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("% Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_predicates(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("% Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{}(_) :-\n", function.name));
-            output.push_str(&format!(
-                "    format('Cross-language call to {}::{} not implemented~n', []).\n\n",
-                block.language, function.name
-            ));
+            out.line(format!("{}(_) :-", function.name));
+            out.indented(|out| {
+                out.line(format!(
+                    "format('Cross-language call to {}::{} not implemented~n', []).",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Predicate definition - Prolog style
-        output.push_str(&format!("{}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            // Capitalize first letter for Prolog variables
-            let param_name = format!("{}{}", 
-                param.name.chars().next().unwrap().to_uppercase(),
-                param.name.chars().skip(1).collect::<String>()
-            );
-            output.push_str(&param_name);
-        }
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        // Capitalize first letter for Prolog variables
+        let mut params: Vec<String> = function.parameters.iter()
+            .map(|param| Self::variable_name(&param.name))
+            .collect();
+
         // Add result parameter if function has return type
         let has_return = function.body.iter().any(|stmt| matches!(stmt, Statement::Return { .. }));
         if has_return {
-            if !function.parameters.is_empty() {
-                output.push_str(", ");
-            }
-            output.push_str("Result");
-        }
-        
-        output.push_str(") :-\n");
-        
-        // Function body as Prolog clauses
-        for statement in &function.body {
-            output.push_str(&format!("    {},\n", self.generate_statement(statement)?));
-        }
-        
-        // Remove last comma and newline, add period
-        if output.ends_with(",\n") {
-            output.pop();
-            output.pop();
-            output.push_str(".\n");
-        } else {
-            output.push_str("    true.\n");
+            params.push("Result".to_string());
         }
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                let var_name = format!("{}{}", 
-                    name.chars().next().unwrap().to_uppercase(),
-                    name.chars().skip(1).collect::<String>()
-                );
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", var_name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var({})", var_name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Result = {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("Result = true".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+        out.map(function.span);
+        out.line(format!("{}({}) :-", function.name, params.join(", ")));
+
+        // Function body as Prolog goals, separated by commas and ended by a period
+        let goals = function.body.iter()
+            .map(|statement| self.statement_text(out, statement))
+            .collect::<Result<Vec<String>>>()?;
+        out.indented(|out| {
+            if goals.is_empty() {
+                out.line("true.");
             }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("({} ->", self.generate_expression(condition)?);
-                
-                if then_branch.len() == 1 {
-                    output.push_str(&format!(" {} ", self.generate_statement(&then_branch[0])?));
-                } else {
-                    output.push_str(" (");
-                    for (i, stmt) in then_branch.iter().enumerate() {
-                        if i > 0 {
-                            output.push_str(", ");
-                        }
-                        output.push_str(&self.generate_statement(stmt)?);
-                    }
-                    output.push_str(") ");
-                }
-                
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("; ");
-                    if else_stmts.len() == 1 {
-                        output.push_str(&self.generate_statement(&else_stmts[0])?);
-                    } else {
-                        output.push_str("(");
-                        for (i, stmt) in else_stmts.iter().enumerate() {
-                            if i > 0 {
-                                output.push_str(", ");
-                            }
-                            output.push_str(&self.generate_statement(stmt)?);
-                        }
-                        output.push_str(")");
-                    }
-                } else {
-                    output.push_str("; true");
-                }
-                
-                output.push_str(")");
-                Ok(output)
+            for (i, goal) in goals.iter().enumerate() {
+                let separator = if i + 1 == goals.len() { "." } else { "," };
+                out.line(format!("{}{}", goal, separator));
             }
-            _ => Ok("% Unsupported statement".to_string()),
+        });
+
+        Ok(())
+    }
+
+    fn variable_name(name: &str) -> String {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
         }
     }
-    
+
+    /// Goals of a branch, in parentheses when there is more than one
+    fn branch_text(&self, out: &CodeWriter, statements: &[Statement]) -> Result<String> {
+        let goals = statements.iter()
+            .map(|statement| self.statement_text(out, statement))
+            .collect::<Result<Vec<String>>>()?;
+        if goals.len() == 1 {
+            Ok(goals.join(""))
+        } else {
+            Ok(format!("({})", goals.join(", ")))
+        }
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -5437,34 +5615,13 @@ impl PrologTransformer {
                     LiteralValue::Null => Ok("null".to_string()),
                 }
             }
-            Expression::Identifier { name, .. } => {
-                // Capitalize first letter for Prolog variables
-                let var_name = format!("{}{}", 
-                    name.chars().next().unwrap().to_uppercase(),
-                    name.chars().skip(1).collect::<String>()
-                );
-                Ok(var_name)
-            }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("{} + {}", left_str, right_str)),
-                    BinaryOperator::Subtract => Ok(format!("{} - {}", left_str, right_str)),
-                    BinaryOperator::Multiply => Ok(format!("{} * {}", left_str, right_str)),
-                    BinaryOperator::Divide => Ok(format!("{} / {}", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("{} = {}", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("{} \\= {}", left_str, right_str)),
-                    BinaryOperator::Less => Ok(format!("{} < {}", left_str, right_str)),
-                    BinaryOperator::Greater => Ok(format!("{} > {}", left_str, right_str)),
-                    BinaryOperator::LessEqual => Ok(format!("{} =< {}", left_str, right_str)),
-                    BinaryOperator::GreaterEqual => Ok(format!("{} >= {}", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("({}, {})", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("({}; {})", left_str, right_str)),
-                    _ => Ok("% unsupported operation %".to_string()),
-                }
-            }
+            // Capitalize first letter for Prolog variables
+            Expression::Identifier { name, .. } => Ok(Self::variable_name(name)),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -5489,10 +5646,45 @@ impl PrologTransformer {
                     UnaryOperator::Plus => "+",
                     UnaryOperator::Not => "\\+",
                 };
-                Ok(format!("{} {}", op_str, operand_str))
+                Ok(format!("{} {}", op_str, operand_str))
+            }
+            other => Ok(unsupported_expression(other, "% Unsupported expression %")),
+        }
+    }
+}
+
+impl StatementEmitter for PrologTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                let var_name = Self::variable_name(name);
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", var_name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var({})", var_name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Result = {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("Result = true");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                let then_text = self.branch_text(out, then_branch)?;
+                let else_text = match else_branch {
+                    Some(else_stmts) => self.branch_text(out, else_stmts)?,
+                    None => "true".to_string(),
+                };
+                out.line(format!("({} -> {} ; {})", self.generate_expression(condition)?, then_text, else_text));
             }
-            _ => Ok("% Unsupported expression %".to_string()),
+            other => out.line(unsupported_statement(other, "% Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -5500,155 +5692,126 @@ impl PrologTransformer {
 pub struct DartTransformer;
 
 impl Transformer for DartTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Dart Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Flutter mobile app development\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Dart Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Flutter mobile app development");
+        out.blank();
+
         // Imports
-        output.push_str("import 'dart:convert';\n");
-        output.push_str("import 'dart:async';\n");
-        output.push_str("import 'package:flutter/material.dart';\n\n");
-        
+        out.line("import 'dart:convert';");
+        out.line("import 'dart:async';");
+        out.line("import 'package:flutter/material.dart';");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "dart" || block.language == "main" {
-                output.push_str(&self.generate_dart_functions(block)?);
+                self.emit_dart_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("void main() {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n");
+            out.block("void main()", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "dart"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".dart"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "dart"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "assert", "break", "case", "catch", "class", "const", "continue", "default", "do", "else",
+            "enum", "extends", "false", "final", "finally", "for", "if", "in", "is", "new", "null",
+            "rethrow", "return", "super", "switch", "this", "throw", "true", "try", "var", "void", "while",
+            "with",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl DartTransformer {
-    fn generate_dart_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Dart's `%` is Euclidean, `remainder` truncates
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Call("{l}.remainder({r})"))
+        .concat(Form::Call("\"${{l}}${{r}}\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print([{args}].join(' '))"),
+        ("println/0", "print('')"),
+        ("toString", "{0}.toString()"),
+        ("toNumber", "double.parse({0}.toString())"),
+        ("len", "{0}.length"),
+        ("push", "{0}.add({1})"),
+        ("pop", "{0}.removeLast()"),
+        ("upper", "{0}.toUpperCase()"),
+        ("lower", "{0}.toLowerCase()"),
+        ("trim", "{0}.trim()"),
+        ("contains", "{0}.contains({1})"),
+        ("abs", "{0}.abs()"),
+        ("floor", "{0}.floorToDouble()"),
+        ("ceil", "{0}.ceilToDouble()"),
+        ("round", "{0}.roundToDouble()"),
+        ("keys", "{0}.keys.toList()"),
+        ("hasKey", "{0}.containsKey({1})"),
+    ]);
+
+    fn emit_dart_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("void main() {\n");
-            for statement in &block.statements {
-                output.push_str("  ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push_str(";\n");
-            }
-            output.push_str("}\n");
+            out.block("void main()", |out| self.emit_statements(out, &block.statements))?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("dynamic {}(List<dynamic> args) {{\n", function.name));
-            output.push_str(&format!(
-                "  throw UnimplementedError('Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("dynamic {}(List<dynamic> args)", function.name), |out| {
+                out.line(format!(
+                    "throw UnimplementedError('Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Function signature
         let return_type = self.convert_return_type(&function.return_type);
-        output.push_str(&format!("{} {}(", return_type, function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{} {}", 
-                self.convert_type(param.param_type.as_ref()), param.name));
-        }
-        
-        output.push_str(") {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("var {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {};", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("  }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("  }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+
+        out.map(function.span);
+        out.block(format!("{} {}({})", return_type, function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -5660,27 +5823,11 @@ impl DartTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -5689,13 +5836,8 @@ impl DartTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for Dart
-                if callee_str == "println" {
-                    Ok(format!("print({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -5713,10 +5855,10 @@ impl DartTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "double",
@@ -5728,7 +5870,7 @@ impl DartTransformer {
             _ => "dynamic",
         }.to_string()
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "double".to_string(),
@@ -5741,152 +5883,168 @@ impl DartTransformer {
     }
 }
 
+impl StatementEmitter for DartTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("var {} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {};", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Lua code generator
 pub struct LuaTransformer;
 
 impl Transformer for LuaTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("-- Generated by Utopia Compiler - Lua Backend\n");
-        output.push_str("-- Multi-language support with runtime integration\n");
-        output.push_str("-- Game scripting and embedded systems\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::END);
+        out.line("-- Generated by Utopia Compiler - Lua Backend");
+        out.line("-- Multi-language support with runtime integration");
+        out.line("-- Game scripting and embedded systems");
+        out.blank();
+
         // Lua requires
-        output.push_str("local json = require('json')\n");
-        output.push_str("local socket = require('socket')\n\n");
-        
+        out.line("local json = require('json')");
+        out.line("local socket = require('socket')");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "lua" || block.language == "main" {
-                output.push_str(&self.generate_lua_functions(block)?);
+                self.emit_lua_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Global statements in main execution
         if !program.global_statements.is_empty() {
-            output.push_str("-- Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
+            out.line("-- Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "lua"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".lua"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "lua"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+            "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl LuaTransformer {
-    fn generate_lua_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Lua's `%` floors, `math.fmod` truncates
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "~=", "<", "<=", ">", ">=", "and", "or"])
+        .with(BinaryOperator::Modulo, Form::Call("math.fmod({l}, {r})"))
+        .concat(Form::Infix(".."))
+        .to_string("tostring({})")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print({args})"),
+        ("toString", "tostring({0})"),
+        ("toNumber", "tonumber({0})"),
+        ("len", "#{0}"),
+        ("push", "table.insert({0}, {1})"),
+        ("pop", "table.remove({0})"),
+        ("upper", "string.upper({0})"),
+        ("lower", "string.lower({0})"),
+        ("trim", "string.match({0}, \"^%s*(.-)%s*$\")"),
+        ("contains", "(string.find({0}, {1}, 1, true) ~= nil)"),
+        ("abs", "math.abs({0})"),
+        ("sqrt", "math.sqrt({0})"),
+        ("floor", "math.floor({0})"),
+        ("ceil", "math.ceil({0})"),
+        ("round", "math.floor({0} + 0.5)"),
+        ("pow", "({0} ^ {1})"),
+        ("min", "math.min({0}, {1})"),
+        ("max", "math.max({0}, {1})"),
+        ("hasKey", "({0}[{1}] ~= nil)"),
+    ])
+    .placeholder("nil");
+
+    fn emit_lua_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("-- Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("-- Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("-- Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("-- Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("function {}(...)\n", function.name));
-            output.push_str(&format!(
-                "  error('Cross-language call to {}::{} not implemented')\n",
-                block.language, function.name
-            ));
-            output.push_str("end\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("function {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
+            out.block(format!("function {}(...)", function.name), |out| {
+                out.line(format!(
+                    "error('Cross-language call to {}::{} not implemented')",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        output.push_str("end\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("local {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("local {}", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {} then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("  else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("  end");
-                Ok(output)
-            }
-            _ => Ok("-- Unsupported statement".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("function {}({})", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -5898,27 +6056,11 @@ impl LuaTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "~=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => "-- unsupported op --",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -5927,13 +6069,8 @@ impl LuaTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions
-                if callee_str == "println" {
-                    Ok(format!("print({})", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -5951,8 +6088,43 @@ impl LuaTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("-- Unsupported expression --".to_string()),
+            other => Ok(unsupported_expression(other, "-- Unsupported expression --")),
+        }
+    }
+}
+
+impl StatementEmitter for LuaTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("local {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("local {}", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {} then", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "-- Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -5960,156 +6132,156 @@ impl LuaTransformer {
 pub struct HaskellTransformer;
 
 impl Transformer for HaskellTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("-- Generated by Utopia Compiler - Haskell Backend\n");
-        output.push_str("-- Multi-language support with runtime integration\n");
-        output.push_str("-- Pure functional programming with lazy evaluation\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::INDENT);
+        out.line("-- Generated by Utopia Compiler - Haskell Backend");
+        out.line("-- Multi-language support with runtime integration");
+        out.line("-- Pure functional programming with lazy evaluation");
+        out.blank();
+
         // Module declaration
-        output.push_str("module UtopiaGenerated where\n\n");
-        
+        out.line("module UtopiaGenerated where");
+        out.blank();
+
         // Imports
-        output.push_str("import Data.List\n");
-        output.push_str("import Data.Maybe\n");
-        output.push_str("import Control.Monad\n");
-        output.push_str("import System.IO\n\n");
-        
+        out.line("import Data.Char (isSpace, toLower, toUpper)");
+        out.line("import Data.List");
+        out.line("import Data.Maybe");
+        out.line("import Control.Monad");
+        out.line("import System.IO");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "haskell" || block.language == "hs" || block.language == "main" {
-                output.push_str(&self.generate_haskell_functions(block)?);
+                self.emit_haskell_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main function if needed
         if !program.global_statements.is_empty() {
-            output.push_str("main :: IO ()\n");
-            output.push_str("main = do\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
-            }
+            self.emit_main(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "haskell"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".hs"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "haskell" | "hs")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes])
+    }
+
+    fn eliminates_tail_calls(&self) -> bool {
+        true
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "case", "class", "data", "default", "deriving", "do", "else", "foreign", "if", "import", "in",
+            "infix", "infixl", "infixr", "instance", "let", "module", "newtype", "of", "then", "type",
+            "where",
+        ], Escape::Suffix("'"))
+    }
 }
 
 impl HaskellTransformer {
-    fn generate_haskell_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// `rem` is only defined on integral types, so the remainder is computed from a truncated quotient
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "==", "/=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Template("{l} - {r} * fromIntegral (truncate ({l} / {r}))"))
+        .concat(Form::Infix("++"))
+        .to_string("show {}")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "putStrLn (unwords [{args}])"),
+        ("println/0", "putStrLn \"\""),
+        ("toString", "(show {0})"),
+        ("toNumber", "(read {0} :: Double)"),
+        ("len", "(fromIntegral (length {0}))"),
+        ("upper", "(map toUpper {0})"),
+        ("lower", "(map toLower {0})"),
+        ("trim", "(dropWhileEnd isSpace (dropWhile isSpace {0}))"),
+        ("contains", "(isInfixOf {1} {0})"),
+        ("abs", "(abs {0})"),
+        ("sqrt", "(sqrt {0})"),
+        ("floor", "(fromIntegral (floor {0}))"),
+        ("ceil", "(fromIntegral (ceiling {0}))"),
+        ("round", "(fromIntegral (round {0}))"),
+        ("pow", "({0} ** {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+    ])
+    .separator(", ")
+    .placeholder("undefined");
+
+    fn emit_haskell_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("main :: IO ()\n");
-            output.push_str("main = do\n");
-            for statement in &block.statements {
-                output.push_str("  ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push_str("\n");
-            }
+            self.emit_main(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("-- Cross-language block: {}\n", block.language));
-        
+
+    /// `main` as a `do` block; every statement is one action of the block
+    fn emit_main(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        out.line("main :: IO ()");
+        out.block("main = do", |out| self.emit_statements(out, statements))
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("-- Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{} :: a -> String\n", function.name));
-            output.push_str(&format!("{} _ = error \"Cross-language call to {}::{} not implemented\"\n\n", 
-                function.name, block.language, function.name));
+            out.line(format!("{} :: a -> String", function.name));
+            out.line(format!(
+                "{} _ = error \"Cross-language call to {}::{} not implemented\"",
+                function.name, block.language, function.name
+            ));
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function type signature (simplified)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        // Function type signature (simplified - using String for all params)
         let return_type = self.convert_return_type(&function.return_type);
-        let param_types = function.parameters.iter()
-            .map(|_| "String".to_string()) // Simplified - using String for all params
-            .collect::<Vec<_>>()
-            .join(" -> ");
-        
-        if !param_types.is_empty() {
-            output.push_str(&format!("{} :: {} -> {}\n", function.name, param_types, return_type));
-        } else {
-            output.push_str(&format!("{} :: {}\n", function.name, return_type));
-        }
-        
+        let mut signature: Vec<&str> = function.parameters.iter().map(|_| "String").collect();
+        signature.push(&return_type);
+
+        out.map(function.span);
+        out.line(format!("{} :: {}", function.name, signature.join(" -> ")));
+
         // Function definition
-        output.push_str(&format!("{}", function.name));
-        for param in &function.parameters {
-            output.push_str(&format!(" {}", param.name));
-        }
-        output.push_str(" =\n");
-        
-        // Function body (simplified - just return the first statement or a default)
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("  {}\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("  \"function body\"\n");
-            }
-        } else {
-            output.push_str("  \"empty function\"\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("let {} = {} in", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("let {} = undefined in", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return ()".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                match expression {
-                    Expression::Call { callee, .. } => {
-                        Ok(format!("putStrLn $ show $ {}", self.generate_expression(expression)?))
-                    }
-                    _ => self.generate_expression(expression)
-                }
+        let params: String = function.parameters.iter().map(|param| format!(" {}", param.name)).collect();
+        out.block(format!("{}{} =", function.name, params), |out| {
+            // Function body (simplified - just return the first statement or a default)
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(self.generate_expression(expr)?),
+                Some(_) => out.line("\"function body\""),
+                None => out.line("\"empty function\""),
             }
-            _ => Ok("-- Unsupported statement".to_string()),
-        }
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -6121,27 +6293,11 @@ impl HaskellTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "++", // String concatenation in Haskell
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "/=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "-- unsupported op --",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -6150,15 +6306,8 @@ impl HaskellTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(" ");
-                
-                // Handle special functions for Haskell
-                if callee_str == "println" {
-                    if args_str.is_empty() {
-                        Ok("putStrLn \"\"".to_string())
-                    } else {
-                        Ok(format!("putStrLn ({})", args_str))
-                    }
-                } else if args_str.is_empty() {
+
+                if args_str.is_empty() {
                     Ok(callee_str)
                 } else {
                     Ok(format!("({} {})", callee_str, args_str))
@@ -6180,14 +6329,14 @@ impl HaskellTransformer {
                 };
                 Ok(format!("({} {})", op_str, operand_str))
             }
-            _ => Ok("-- Unsupported expression --".to_string()),
+            other => Ok(unsupported_expression(other, "-- Unsupported expression --")),
         }
     }
-    
+
     fn convert_return_type(&self, return_type: &Option<crate::types::Type>) -> String {
         match return_type {
             Some(crate::types::Type::Number) => "Double",
-            Some(crate::types::Type::String) => "String", 
+            Some(crate::types::Type::String) => "String",
             Some(crate::types::Type::Boolean) => "Bool",
             Some(crate::types::Type::Void) => "()",
             Some(crate::types::Type::Array(_)) => "[String]",
@@ -6197,107 +6346,199 @@ impl HaskellTransformer {
     }
 }
 
+impl StatementEmitter for HaskellTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            // Statements are actions of a `do` block, where `let` takes no `in`
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("let {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("let {} = undefined", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return ()");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                match expression {
+                    Expression::Call { .. } if !builtins::is_builtin_call(expression) => {
+                        out.line(format!("putStrLn $ show $ {}", self.generate_expression(expression)?));
+                    }
+                    _ => out.line(self.generate_expression(expression)?),
+                }
+            }
+            other => out.line(unsupported_statement(other, "-- Unsupported statement")),
+        }
+        Ok(())
+    }
+}
+
 /// Objective-C code generator
 pub struct ObjectiveCTransformer;
 
 impl Transformer for ObjectiveCTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Objective-C Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Legacy iOS/macOS development\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Objective-C Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Legacy iOS/macOS development");
+        out.blank();
+
         // Imports
-        output.push_str("#import <Foundation/Foundation.h>\n");
-        output.push_str("#import <UIKit/UIKit.h>\n");
-        output.push_str("#import <CoreFoundation/CoreFoundation.h>\n\n");
-        
+        out.line("#import <Foundation/Foundation.h>");
+        out.line("#import <UIKit/UIKit.h>");
+        out.line("#import <CoreFoundation/CoreFoundation.h>");
+        out.blank();
+
         // Interface declarations
-        output.push_str("@interface UtopiaRuntime : NSObject\n");
-        
-        // Process language blocks for method declarations
+        out.line("@interface UtopiaRuntime : NSObject");
         for block in &program.language_blocks {
-            if block.language == "objc" || block.language == "objective-c" || block.language == "main" {
+            if self.is_objc_block(block) {
                 for function in &block.functions {
-                    output.push_str(&format!("+ (id){}", self.generate_method_signature(function)?));
-                    output.push_str(";\n");
+                    out.line(format!("+ (id){};", self.generate_method_signature(function)?));
                 }
             }
         }
-        
-        output.push_str("@end\n\n");
-        
+        out.line("@end");
+        out.blank();
+
         // Implementation
-        output.push_str("@implementation UtopiaRuntime\n\n");
-        
+        out.line("@implementation UtopiaRuntime");
+        out.blank();
         for block in &program.language_blocks {
-            if block.language == "objc" || block.language == "objective-c" || block.language == "main" {
-                output.push_str(&self.generate_objc_methods(block)?);
+            if self.is_objc_block(block) {
+                self.emit_objc_methods(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_methods(block)?);
+                self.emit_cross_language_methods(&mut out, block)?;
             }
         }
-        
+
         // Main function if needed
         if !program.global_statements.is_empty() {
-            output.push_str("+ (void)main {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n\n");
+            out.block("+ (void)main", |out| self.emit_statements(out, &program.global_statements))?;
+            out.blank();
         }
-        
-        output.push_str("@end\n");
-        
-        Ok(output)
+
+        out.line("@end");
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "objective-c"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".m"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "objc" | "objective-c" | "objectivec")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "auto", "BOOL", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+            "enum", "extern", "float", "for", "goto", "id", "if", "inline", "int", "long", "nil", "NO",
+            "register", "restrict", "return", "self", "short", "signed", "sizeof", "static", "struct",
+            "super", "switch", "typedef", "union", "unsigned", "void", "volatile", "while", "YES",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl ObjectiveCTransformer {
-    fn generate_objc_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Values are objects, so numbers are unboxed for arithmetic and strings
+    /// are compared and joined with messages
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("@([{l} doubleValue] + [{r} doubleValue])"),
+        Form::Call("@([{l} doubleValue] - [{r} doubleValue])"),
+        Form::Call("@([{l} doubleValue] * [{r} doubleValue])"),
+        Form::Call("@([{l} doubleValue] / [{r} doubleValue])"),
+        Form::Call("@(fmod([{l} doubleValue], [{r} doubleValue]))"),
+        Form::Call("[{l} isEqual:{r}]"),
+        Form::Call("![{l} isEqual:{r}]"),
+        Form::Template("[{l} doubleValue] < [{r} doubleValue]"),
+        Form::Template("[{l} doubleValue] <= [{r} doubleValue]"),
+        Form::Template("[{l} doubleValue] > [{r} doubleValue]"),
+        Form::Template("[{l} doubleValue] >= [{r} doubleValue]"),
+        Form::Template("[{l} boolValue] && [{r} boolValue]"),
+        Form::Template("[{l} boolValue] || [{r} boolValue]"),
+    ])
+    .concat(Form::Call("[NSString stringWithFormat:@\"%@%@\", {l}, {r}]"))
+    .strings([
+        Form::Call("[{l} isEqualToString:{r}]"),
+        Form::Call("![{l} isEqualToString:{r}]"),
+        Form::Template("[{l} compare:{r}] == NSOrderedAscending"),
+        Form::Template("[{l} compare:{r}] != NSOrderedDescending"),
+        Form::Template("[{l} compare:{r}] == NSOrderedDescending"),
+        Form::Template("[{l} compare:{r}] != NSOrderedAscending"),
+    ])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "NSLog(@\"{formats}\", {args})"),
+        ("println/0", "NSLog(@\"\")"),
+        ("toString", "[{0} description]"),
+        ("toNumber", "@([{0} doubleValue])"),
+        ("toBoolean", "@([{0} boolValue])"),
+        ("len", "@([{0} count])"),
+        ("push", "[{0} addObject:{1}]"),
+        ("pop", "[{0} lastObject]"),
+        ("upper", "[{0} uppercaseString]"),
+        ("lower", "[{0} lowercaseString]"),
+        ("trim", "[{0} stringByTrimmingCharactersInSet:[NSCharacterSet whitespaceCharacterSet]]"),
+        ("contains", "[{0} containsString:{1}]"),
+        ("abs", "@(fabs([{0} doubleValue]))"),
+        ("sqrt", "@(sqrt([{0} doubleValue]))"),
+        ("floor", "@(floor([{0} doubleValue]))"),
+        ("ceil", "@(ceil([{0} doubleValue]))"),
+        ("round", "@(round([{0} doubleValue]))"),
+        ("pow", "@(pow([{0} doubleValue], [{1} doubleValue]))"),
+        ("min", "@(fmin([{0} doubleValue], [{1} doubleValue]))"),
+        ("max", "@(fmax([{0} doubleValue], [{1} doubleValue]))"),
+        ("keys", "[{0} allKeys]"),
+        ("hasKey", "({0}[{1}] != nil)"),
+    ])
+    .formats(["%@", "%@", "%@", "%@"]);
+
+    fn is_objc_block(&self, block: &LanguageBlock) -> bool {
+        block.language == "objc" || block.language == "objective-c" || block.language == "main"
+    }
+
+    fn emit_objc_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_method(function)?);
-            output.push('\n');
+            self.emit_method(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("+ (id){} {{\n", self.generate_method_signature(function)?));
-            output.push_str(&format!(
-                "    @throw [NSException exceptionWithName:@\"NotImplemented\" reason:@\"Cross-language call to {}::{} not implemented\" userInfo:nil];\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
+            out.block(format!("+ (id){}", self.generate_method_signature(function)?), |out| {
+                out.line(format!(
+                    "@throw [NSException exceptionWithName:@\"NotImplemented\" reason:@\"Cross-language call to {}::{} not implemented\" userInfo:nil];",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
+
     fn generate_method_signature(&self, function: &Function) -> Result<String> {
         let mut signature = String::new();
-        
+
         signature.push_str(&function.name);
-        
+
         if !function.parameters.is_empty() {
             signature.push(':');
             for (i, param) in function.parameters.iter().enumerate() {
@@ -6308,63 +6549,17 @@ impl ObjectiveCTransformer {
                 }
             }
         }
-        
+
         Ok(signature)
     }
-    
-    fn generate_method(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Method implementation
-        output.push_str(&format!("+ (id){} {{\n", self.generate_method_signature(function)?));
-        
-        // Method body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("id {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("id {};", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return nil".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+
+    fn emit_method(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        out.map(function.span);
+        out.block(format!("+ (id){}", self.generate_method_signature(function)?), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -6376,27 +6571,11 @@ impl ObjectiveCTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -6405,7 +6584,7 @@ impl ObjectiveCTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_vec = args?;
-                
+
                 if args_vec.is_empty() {
                     Ok(format!("[UtopiaRuntime {}]", callee_str))
                 } else if args_vec.len() == 1 {
@@ -6436,8 +6615,43 @@ impl ObjectiveCTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
+        }
+    }
+}
+
+impl StatementEmitter for ObjectiveCTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("id {} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("id {};", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return nil;");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -6445,161 +6659,142 @@ impl ObjectiveCTransformer {
 pub struct ScalaTransformer;
 
 impl Transformer for ScalaTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Scala Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Big data processing and functional programming on JVM\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Scala Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Big data processing and functional programming on JVM");
+        out.blank();
+
         // Package and imports
-        output.push_str("package utopia.generated\n\n");
-        output.push_str("import scala.collection.mutable\n");
-        output.push_str("import scala.concurrent.Future\n");
-        output.push_str("import scala.util.{Try, Success, Failure}\n");
-        output.push_str("import org.apache.spark.sql._\n");
-        output.push_str("import play.api.libs.json._\n\n");
-        
+        out.line("package utopia.generated");
+        out.blank();
+        out.line("import scala.collection.mutable");
+        out.line("import scala.concurrent.Future");
+        out.line("import scala.util.{Try, Success, Failure}");
+        out.line("import org.apache.spark.sql._");
+        out.line("import play.api.libs.json._");
+        out.blank();
+
         // Main object
-        output.push_str("object UtopiaProgram {\n\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "scala" || block.language == "main" {
-                output.push_str(&self.generate_scala_functions(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+        out.block("object UtopiaProgram", |out| -> Result<()> {
+            out.blank();
+
+            // Process language blocks
+            for block in &program.language_blocks {
+                if block.language == "scala" || block.language == "main" {
+                    self.emit_scala_functions(out, block)?;
+                } else {
+                    self.emit_cross_language_functions(out, block);
+                }
             }
-        }
-        
-        // Main method if needed
-        if !program.global_statements.is_empty() {
-            output.push_str("  def main(args: Array[String]): Unit = {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
+
+            // Main method if needed
+            if !program.global_statements.is_empty() {
+                self.emit_main(out, &program.global_statements)?;
+                out.blank();
             }
-            output.push_str("  }\n\n");
-        }
-        
-        output.push_str("}\n");
-        
-        Ok(output)
+
+            Ok(())
+        })?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "scala"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".scala"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "scala"
     }
-}
 
-impl ScalaTransformer {
-    fn generate_scala_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("  def main(args: Array[String]): Unit = {\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push_str(";\n");
-            }
-            output.push_str("  }\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("  // Cross-language block: {}\n", block.language));
-        
-        for function in &block.functions {
-            output.push_str(&format!("  def {}(args: Any*): Any = {{\n", function.name));
-            output.push_str(&format!(
-                "    throw new NotImplementedError(\"Cross-language call to {}::{} not implemented\")\n",
-                block.language, function.name
-            ));
-            output.push_str("  }\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function signature
-        output.push_str(&format!("  def {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{}: Any", param.name));
-        }
-        
-        output.push_str("): Any = {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-        }
-        
-        output.push_str("  }\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("val {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("val {}: Any = null", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("()".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "case", "catch", "class", "def", "do", "else", "extends", "false", "final",
+            "finally", "for", "forSome", "if", "implicit", "import", "lazy", "match", "new", "null",
+            "object", "override", "package", "private", "protected", "return", "sealed", "super", "this",
+            "throw", "trait", "true", "try", "type", "val", "var", "while", "with", "yield",
+        ], Escape::Wrap("`", "`"))
+    }
+}
+
+impl ScalaTransformer {
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("{l}.toDouble / {r}"))
+        .concat(Form::Call("s\"${{l}}${{r}}\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "println(Seq({args}).mkString(\" \"))"),
+        ("println/0", "println()"),
+        ("toString", "{0}.toString"),
+        ("toNumber", "{0}.toString.toDouble"),
+        ("toBoolean", "{0}.toString.toBoolean"),
+        ("len", "{0}.length"),
+        ("push", "{0} += {1}"),
+        ("pop", "{0}.remove({0}.length - 1)"),
+        ("upper", "{0}.toUpperCase"),
+        ("lower", "{0}.toLowerCase"),
+        ("trim", "{0}.trim"),
+        ("contains", "{0}.contains({1})"),
+        ("abs", "math.abs({0})"),
+        ("sqrt", "math.sqrt({0})"),
+        ("floor", "math.floor({0})"),
+        ("ceil", "math.ceil({0})"),
+        ("round", "math.round({0}).toDouble"),
+        ("pow", "math.pow({0}, {1})"),
+        ("min", "math.min({0}, {1})"),
+        ("max", "math.max({0}, {1})"),
+        ("keys", "{0}.keys.toList"),
+        ("hasKey", "{0}.contains({1})"),
+    ]);
+
+    fn emit_scala_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
+        for function in &block.functions {
+            self.emit_function(out, function)?;
+            out.blank();
+        }
+
+        // Handle main block statements
+        if block.language == "main" && !block.statements.is_empty() {
+            self.emit_main(out, &block.statements)?;
         }
+
+        Ok(())
     }
-    
+
+    fn emit_main(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        out.block("def main(args: Array[String]): Unit =", |out| self.emit_statements(out, statements))
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.block(format!("def {}(args: Any*): Any =", function.name), |out| {
+                out.line(format!(
+                    "throw new NotImplementedError(\"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
+        }
+    }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("{}: Any", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("def {}({}): Any =", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -6611,27 +6806,11 @@ impl ScalaTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "/* unsupported op */",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -6658,8 +6837,43 @@ impl ScalaTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("/* Unsupported expression */".to_string()),
+            other => Ok(unsupported_expression(other, "/* Unsupported expression */")),
+        }
+    }
+}
+
+impl StatementEmitter for ScalaTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("val {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("val {}: Any = null", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("()");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -6667,155 +6881,133 @@ impl ScalaTransformer {
 pub struct JuliaTransformer;
 
 impl Transformer for JuliaTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - Julia Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# High-performance scientific computing\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::END);
+        out.line("# Generated by Utopia Compiler - Julia Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# High-performance scientific computing");
+        out.blank();
+
         // Julia imports
-        output.push_str("using LinearAlgebra\n");
-        output.push_str("using Statistics\n");
-        output.push_str("using DataFrames\n");
-        output.push_str("using Plots\n");
-        output.push_str("using JSON\n\n");
-        
+        out.line("using LinearAlgebra");
+        out.line("using Statistics");
+        out.line("using DataFrames");
+        out.line("using Plots");
+        out.line("using JSON");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "julia" || block.language == "jl" || block.language == "main" {
-                output.push_str(&self.generate_julia_functions(block)?);
+                self.emit_julia_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("function main()\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str("end\n\n");
-            output.push_str("main()\n");
+            self.emit_main(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "julia"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".jl"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "julia" | "jl")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "baremodule", "begin", "break", "catch", "const", "continue", "do", "else", "elseif", "end",
+            "export", "false", "finally", "for", "function", "global", "if", "import", "let", "local",
+            "macro", "module", "quote", "return", "struct", "true", "try", "using", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl JuliaTransformer {
-    fn generate_julia_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .concat(Form::Call("string({l}, {r})"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "println(join(string.([{args}]), \" \"))"),
+        ("println/0", "println()"),
+        ("toString", "string({0})"),
+        ("toNumber", "parse(Float64, string({0}))"),
+        ("toBoolean", "Bool({0})"),
+        ("len", "length({0})"),
+        ("push", "push!({0}, {1})"),
+        ("pop", "pop!({0})"),
+        ("upper", "uppercase({0})"),
+        ("lower", "lowercase({0})"),
+        ("trim", "strip({0})"),
+        ("contains", "occursin({1}, {0})"),
+        ("abs", "abs({0})"),
+        ("sqrt", "sqrt({0})"),
+        ("floor", "floor({0})"),
+        ("ceil", "ceil({0})"),
+        ("round", "round({0})"),
+        ("pow", "{0} ^ {1}"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "collect(keys({0}))"),
+        ("hasKey", "haskey({0}, {1})"),
+    ]);
+
+    fn emit_julia_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("function main()\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push_str("\n");
-            }
-            output.push_str("end\n\nmain()\n");
+            self.emit_main(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
-        for function in &block.functions {
-            output.push_str(&format!("function {}(args...)\n", function.name));
-            output.push_str(&format!(
-                "    error(\"Cross-language call to {}::{} not implemented\")\n",
-                block.language, function.name
-            ));
-            output.push_str("end\n\n");
-        }
-        
-        Ok(output)
+
+    fn emit_main(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        out.block("function main()", |out| self.emit_statements(out, statements))?;
+        out.blank();
+        out.line("main()");
+        Ok(())
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("function {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.block(format!("function {}(args...)", function.name), |out| {
+                out.line(format!(
+                    "error(\"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        output.push_str("end\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = nothing", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return nothing".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    end");
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("function {}({})", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -6827,27 +7019,11 @@ impl JuliaTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -6874,8 +7050,43 @@ impl JuliaTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for JuliaTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = nothing", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return nothing");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -6883,147 +7094,128 @@ impl JuliaTransformer {
 pub struct VBScriptTransformer;
 
 impl Transformer for VBScriptTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("' Generated by Utopia Compiler - VBScript Backend\n");
-        output.push_str("' Multi-language support with runtime integration\n");
-        output.push_str("' Windows automation and scripting\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("' Generated by Utopia Compiler - VBScript Backend");
+        out.line("' Multi-language support with runtime integration");
+        out.line("' Windows automation and scripting");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "vbscript" || block.language == "vbs" || block.language == "main" {
-                output.push_str(&self.generate_vbscript_functions(block)?);
+                self.emit_vbscript_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("' Main execution\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
+            out.line("' Main execution");
+            self.emit_statements(&mut out, &program.global_statements)?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "vbscript"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".vbs"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "vbscript" | "vbs")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Lambdas])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "as", "boolean", "byref", "byte", "byval", "call", "case", "class", "const", "currency",
+            "debug", "dim", "do", "double", "each", "else", "elseif", "empty", "end", "endif", "enum", "eqv",
+            "erase", "error", "event", "exit", "explicit", "false", "for", "function", "get", "goto", "if",
+            "imp", "implements", "in", "integer", "is", "let", "like", "long", "loop", "lset", "me", "mod",
+            "new", "next", "not", "nothing", "null", "on", "option", "optional", "or", "paramarray",
+            "preserve", "private", "public", "raiseevent", "redim", "rem", "resume", "rset", "select", "set",
+            "shared", "single", "static", "stop", "sub", "then", "to", "true", "type", "typeof", "until",
+            "variant", "wend", "while", "with", "xor",
+        ], Escape::Suffix("_")).ignore_case()
+    }
 }
 
 impl VBScriptTransformer {
-    fn generate_vbscript_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// `Mod` rounds its operands to integers. VBScript has no short-circuit
+    /// operators, so `And` and `Or` evaluate both sides.
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "Mod", "=", "<>", "<", "<=", ">", ">=", "And", "Or"])
+        .with(BinaryOperator::Modulo, Form::Template("{l} - {r} * Fix({l} / {r})"))
+        .concat(Form::Infix("&"))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "WScript.Echo {args}"),
+        ("println/0", "WScript.Echo"),
+        ("toString", "CStr({0})"),
+        ("toNumber", "CDbl({0})"),
+        ("toBoolean", "CBool({0})"),
+        ("len", "Len({0})"),
+        ("upper", "UCase({0})"),
+        ("lower", "LCase({0})"),
+        ("trim", "Trim({0})"),
+        ("contains", "(InStr({0}, {1}) > 0)"),
+        ("abs", "Abs({0})"),
+        ("sqrt", "Sqr({0})"),
+        ("floor", "Int({0})"),
+        ("ceil", "(-Int(-{0}))"),
+        ("round", "Int({0} + 0.5)"),
+        ("pow", "({0} ^ {1})"),
+        ("keys", "{0}.Keys"),
+        ("hasKey", "{0}.Exists({1})"),
+    ])
+    .separator(" & \" \" & ")
+    .placeholder("Empty");
+
+    fn emit_vbscript_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("' Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("' Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("Function {}()\n", function.name));
-            output.push_str(&format!(
-                "    Err.Raise 1, \"NotImplemented\", \"Cross-language call to {}::{} not implemented\"\n",
-                block.language, function.name
-            ));
-            output.push_str("End Function\n\n");
+            out.block_with(format!("Function {}()", function.name), "End Function", |out| {
+                out.line(format!(
+                    "Err.Raise 1, \"NotImplemented\", \"Cross-language call to {}::{} not implemented\"",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Function definition
-        let has_return = function.return_type.is_some() && 
+        let has_return = function.return_type.is_some() &&
             !matches!(function.return_type, Some(crate::types::Type::Void));
-        
-        if has_return {
-            output.push_str(&format!("Function {}(", function.name));
-        } else {
-            output.push_str(&format!("Sub {}(", function.name));
-        }
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-        }
-        
-        if has_return {
-            output.push_str("End Function\n");
-        } else {
-            output.push_str("End Sub\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Dim {} : {} = {}", name, name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("Dim {}", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", "ReturnValue", self.generate_expression(expr)?))
-                } else {
-                    Ok("Exit Function".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("If {} Then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    Else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    End If");
-                Ok(output)
-            }
-            _ => Ok("' Unsupported statement".to_string()),
-        }
+        let kind = if has_return { "Function" } else { "Sub" };
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block_with(format!("{} {}({})", kind, function.name, params.join(", ")), format!("End {}", kind), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -7035,27 +7227,11 @@ impl VBScriptTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "<>",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "And",
-                    BinaryOperator::Or => "Or",
-                    _ => "' unsupported op '",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -7077,17 +7253,52 @@ impl VBScriptTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("CrossCall(\"{}\", \"{}\", Array({}))", language, function, args_str))
             }
-            Expression::Unary { operator, operand, .. } => {
-                let operand_str = self.generate_expression(operand)?;
-                let op_str = match operator {
-                    UnaryOperator::Minus => "-",
-                    UnaryOperator::Plus => "+",
-                    UnaryOperator::Not => "Not ",
-                };
-                Ok(format!("({}{})", op_str, operand_str))
+            Expression::Unary { operator, operand, .. } => {
+                let operand_str = self.generate_expression(operand)?;
+                let op_str = match operator {
+                    UnaryOperator::Minus => "-",
+                    UnaryOperator::Plus => "+",
+                    UnaryOperator::Not => "Not ",
+                };
+                Ok(format!("({}{})", op_str, operand_str))
+            }
+            other => Ok(unsupported_expression(other, "' Unsupported expression '")),
+        }
+    }
+}
+
+impl StatementEmitter for VBScriptTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Dim {} : {} = {}", name, name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("Dim {}", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("ReturnValue = {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("Exit Function");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("If {} Then", self.generate_expression(condition)?), "End If");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("Else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
             }
-            _ => Ok("' Unsupported expression '".to_string()),
+            other => out.line(unsupported_statement(other, "' Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -7095,140 +7306,139 @@ impl VBScriptTransformer {
 pub struct BashTransformer;
 
 impl Transformer for BashTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("#!/bin/bash\n");
-        output.push_str("# Generated by Utopia Compiler - Bash Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# Shell scripting and system automation\n\n");
-        
-        output.push_str("set -euo pipefail  # Exit on error, undefined vars, pipe failures\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("#!/bin/bash");
+        out.line("# Generated by Utopia Compiler - Bash Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# Shell scripting and system automation");
+        out.blank();
+        out.line("set -euo pipefail  # Exit on error, undefined vars, pipe failures");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "bash" || block.language == "sh" || block.language == "shell" || block.language == "main" {
-                output.push_str(&self.generate_bash_functions(block)?);
+                self.emit_bash_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("main() {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n\n");
-            output.push_str("main \"$@\"\n");
+            out.block_with("main() {", "}", |out| self.emit_statements(out, &program.global_statements))?;
+            out.blank();
+            out.line("main \"$@\"");
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "bash"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".sh"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "bash" | "sh" | "shell")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "case", "coproc", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in",
+            "select", "then", "time", "until", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl BashTransformer {
-    fn generate_bash_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Shell arithmetic is integer-only, so division goes through `awk`
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("$(({l} + {r}))"),
+        Form::Call("$(({l} - {r}))"),
+        Form::Call("$(({l} * {r}))"),
+        Form::Call("$(awk \"BEGIN { print {l} / {r} }\")"),
+        Form::Call("$(({l} % {r}))"),
+        Form::Infix("-eq"),
+        Form::Infix("-ne"),
+        Form::Infix("-lt"),
+        Form::Infix("-le"),
+        Form::Infix("-gt"),
+        Form::Infix("-ge"),
+        Form::Infix("&&"),
+        Form::Infix("||"),
+    ])
+    .concat(Form::Call("{l}{r}"))
+    .strings([
+        Form::Infix("=="),
+        Form::Infix("!="),
+        Form::Infix("<"),
+        Form::Template("! {l} > {r}"),
+        Form::Infix(">"),
+        Form::Template("! {l} < {r}"),
+    ]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "echo {args}"),
+        ("println/0", "echo"),
+        ("toString", "{0}"),
+        ("toNumber", "$(awk \"BEGIN { print {0} + 0 }\")"),
+        ("upper", "$(echo {0} | tr '[:lower:]' '[:upper:]')"),
+        ("lower", "$(echo {0} | tr '[:upper:]' '[:lower:]')"),
+        ("abs", "$(awk \"BEGIN { x = {0}; print (x < 0 ? -x : x) }\")"),
+        ("sqrt", "$(awk \"BEGIN { print sqrt({0}) }\")"),
+        ("pow", "$(awk \"BEGIN { print {0} ^ {1} }\")"),
+    ])
+    .separator(" ")
+    .placeholder("\"\"");
+
+    fn emit_bash_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{}() {{\n", function.name));
-            output.push_str(&format!(
-                "    echo \"Error: Cross-language call to {}::{} not implemented\" >&2\n",
-                block.language, function.name
-            ));
-            output.push_str("    return 1\n");
-            output.push_str("}\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("{}() {{\n", function.name));
-        
-        // Parameters (bash functions use $1, $2, etc.)
-        for (i, param) in function.parameters.iter().enumerate() {
-            output.push_str(&format!("    local {}=${}\n", param.name, i + 1));
-        }
-        
-        if !function.parameters.is_empty() {
-            output.push('\n');
-        }
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
+            out.block_with(format!("{}() {{", function.name), "}", |out| {
+                out.line(format!(
+                    "echo \"Error: Cross-language call to {}::{} not implemented\" >&2",
+                    block.language, function.name
+                ));
+                out.line("return 1");
+            });
+            out.blank();
         }
-        
-        output.push_str("}\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("local {}={}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("local {}", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("echo {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        out.map(function.span);
+        out.block_with(format!("{}() {{", function.name), "}", |out| {
+            // Parameters (bash functions use $1, $2, etc.)
+            for (i, param) in function.parameters.iter().enumerate() {
+                out.line(format!("local {}=${}", param.name, i + 1));
             }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if [[ {} ]]; then\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    fi");
-                Ok(output)
+            if !function.parameters.is_empty() {
+                out.blank();
             }
-            _ => Ok("# Unsupported statement".to_string()),
-        }
+
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -7240,26 +7450,11 @@ impl BashTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(format!("${}", name)),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("$(({} + {}))", left_str.trim_start_matches('$'), right_str.trim_start_matches('$'))),
-                    BinaryOperator::Subtract => Ok(format!("$(({} - {}))", left_str.trim_start_matches('$'), right_str.trim_start_matches('$'))),
-                    BinaryOperator::Multiply => Ok(format!("$(({} * {}))", left_str.trim_start_matches('$'), right_str.trim_start_matches('$'))),
-                    BinaryOperator::Divide => Ok(format!("$(({} / {}))", left_str.trim_start_matches('$'), right_str.trim_start_matches('$'))),
-                    BinaryOperator::Equal => Ok(format!("{} == {}", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("{} != {}", left_str, right_str)),
-                    BinaryOperator::Less => Ok(format!("{} -lt {}", left_str, right_str)),
-                    BinaryOperator::Greater => Ok(format!("{} -gt {}", left_str, right_str)),
-                    BinaryOperator::LessEqual => Ok(format!("{} -le {}", left_str, right_str)),
-                    BinaryOperator::GreaterEqual => Ok(format!("{} -ge {}", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("{} && {}", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("{} || {}", left_str, right_str)),
-                    _ => Ok("# unsupported op #".to_string()),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -7289,8 +7484,43 @@ impl BashTransformer {
                     UnaryOperator::Not => Ok(format!("! {}", operand_str)),
                 }
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for BashTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("local {}={}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("local {}", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("echo {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open_with(format!("if [[ {} ]]; then", self.generate_expression(condition)?), "fi");
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -7298,145 +7528,144 @@ impl BashTransformer {
 pub struct NimTransformer;
 
 impl Transformer for NimTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - Nim Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# Efficient compiled language with Python-like syntax\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        // Headers carry their own `:` or `=`; an empty body needs `discard`
+        let mut out = CodeWriter::new("  ", BlockStyle::INDENT.or_empty("discard"));
+        out.line("# Generated by Utopia Compiler - Nim Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# Efficient compiled language with Python-like syntax");
+        out.blank();
+
         // Nim imports
-        output.push_str("import json, strutils, sequtils, tables, math\n");
-        output.push_str("import asyncdispatch, httpclient\n\n");
-        
+        out.line("import json, strutils, sequtils, tables, math");
+        out.line("import asyncdispatch, httpclient");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "nim" || block.language == "main" {
-                output.push_str(&self.generate_nim_functions(block)?);
+                self.emit_nim_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("when isMainModule:\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
-            }
+            out.block("when isMainModule:", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "nim"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".nim"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "nim"
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "addr", "and", "as", "asm", "bind", "block", "break", "case", "cast", "concept", "const",
+            "continue", "converter", "defer", "discard", "distinct", "div", "do", "elif", "else", "end",
+            "enum", "except", "export", "finally", "for", "from", "func", "if", "import", "in", "include",
+            "interface", "is", "isnot", "iterator", "let", "macro", "method", "mixin", "mod", "nil", "not",
+            "notin", "object", "of", "or", "out", "proc", "ptr", "raise", "ref", "return", "shl", "shr",
+            "static", "template", "try", "tuple", "type", "using", "var", "when", "while", "xor", "yield",
+        ], Escape::Wrap("`", "`"))
+    }
 }
 
 impl NimTransformer {
-    fn generate_nim_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Values are carried as strings, so numeric operators parse their
+    /// operands and format the result
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("$(parseFloat({l}) + parseFloat({r}))"),
+        Form::Call("$(parseFloat({l}) - parseFloat({r}))"),
+        Form::Call("$(parseFloat({l}) * parseFloat({r}))"),
+        Form::Call("$(parseFloat({l}) / parseFloat({r}))"),
+        Form::Call("$(parseFloat({l}) mod parseFloat({r}))"),
+        Form::Template("parseFloat({l}) == parseFloat({r})"),
+        Form::Template("parseFloat({l}) != parseFloat({r})"),
+        Form::Template("parseFloat({l}) < parseFloat({r})"),
+        Form::Template("parseFloat({l}) <= parseFloat({r})"),
+        Form::Template("parseFloat({l}) > parseFloat({r})"),
+        Form::Template("parseFloat({l}) >= parseFloat({r})"),
+        Form::Infix("and"),
+        Form::Infix("or"),
+    ])
+    .concat(Form::Infix("&"))
+    .strings([Form::Infix("=="), Form::Infix("!="), Form::Infix("<"), Form::Infix("<="), Form::Infix(">"), Form::Infix(">=")])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "echo [{args}].join(\" \")"),
+        ("println/0", "echo \"\""),
+        ("toString", "{0}"),
+        ("toNumber", "$parseFloat({0})"),
+        ("len", "$len({0})"),
+        ("push", "{0}.add({1})"),
+        ("pop", "{0}.pop()"),
+        ("upper", "toUpperAscii({0})"),
+        ("lower", "toLowerAscii({0})"),
+        ("trim", "strip({0})"),
+        ("contains", "{0}.contains({1})"),
+        ("abs", "$abs(parseFloat({0}))"),
+        ("sqrt", "$sqrt(parseFloat({0}))"),
+        ("floor", "$floor(parseFloat({0}))"),
+        ("ceil", "$ceil(parseFloat({0}))"),
+        ("round", "$round(parseFloat({0}))"),
+        ("pow", "$pow(parseFloat({0}), parseFloat({1}))"),
+        ("min", "$min(parseFloat({0}), parseFloat({1}))"),
+        ("max", "$max(parseFloat({0}), parseFloat({1}))"),
+        ("hasKey", "{0}.hasKey({1})"),
+    ])
+    .placeholder("\"\"");
+
+    fn emit_nim_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("# Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("proc {}*(args: varargs[string]): string =\n", function.name));
-            output.push_str(&format!(
-                "  raise newException(ValueError, \"Cross-language call to {}::{} not implemented\")\n\n",
-                block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("proc {}*(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{}: string", param.name));
-        }
-        
-        output.push_str("): string =\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
+            out.block(format!("proc {}*(args: varargs[string]): string =", function.name), |out| {
+                out.line(format!(
+                    "raise newException(ValueError, \"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("let {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {}: string", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return \"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}:\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("  else:\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                Ok(output.trim_end().to_string())
-            }
-            _ => Ok("# Unsupported statement".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("{}: string", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("proc {}*({}): string =", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -7448,27 +7677,11 @@ impl NimTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "&", // String concatenation in Nim
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -7495,8 +7708,43 @@ impl NimTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for NimTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("let {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {}: string", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return \"\"");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}:", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else:");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -7504,148 +7752,143 @@ impl NimTransformer {
 pub struct CrystalTransformer;
 
 impl Transformer for CrystalTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - Crystal Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# Ruby-like syntax with C-like performance\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::END);
+        out.line("# Generated by Utopia Compiler - Crystal Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# Ruby-like syntax with C-like performance");
+        out.blank();
+
         // Crystal requires
-        output.push_str("require \"json\"\n");
-        output.push_str("require \"http/client\"\n");
-        output.push_str("require \"fiber\"\n\n");
-        
+        out.line("require \"json\"");
+        out.line("require \"http/client\"");
+        out.line("require \"fiber\"");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "crystal" || block.language == "cr" || block.language == "main" {
-                output.push_str(&self.generate_crystal_functions(block)?);
+                self.emit_crystal_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
-        if !program.global_statements.is_empty() {
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
-        }
-        
-        Ok(output)
+        self.emit_statements(&mut out, &program.global_statements)?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "crystal"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".cr"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "crystal" | "cr")
     }
-}
 
-impl CrystalTransformer {
-    fn generate_crystal_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("# Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("# Cross-language block: {}\n", block.language));
-        
-        for function in &block.functions {
-            output.push_str(&format!("def {}(*args)\n", function.name));
-            output.push_str(&format!(
-                "  raise \"Cross-language call to {}::{} not implemented\"\n",
-                block.language, function.name
-            ));
-            output.push_str("end\n\n");
-        }
-        
-        Ok(output)
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "alias", "annotation", "as", "asm", "begin", "break", "case", "class", "def", "do",
+            "else", "elsif", "end", "ensure", "enum", "extend", "false", "for", "fun", "if", "in", "include",
+            "lib", "macro", "module", "next", "nil", "of", "out", "pointerof", "private", "protected",
+            "require", "rescue", "return", "select", "self", "sizeof", "struct", "super", "then", "true",
+            "type", "typeof", "uninitialized", "union", "unless", "until", "verbatim", "when", "while",
+            "with", "yield",
+        ], Escape::Suffix("_"))
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("def {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{} : String", param.name));
+}
+
+impl CrystalTransformer {
+    /// Values are carried as strings, so numeric operators parse their
+    /// operands and format the result
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("({l}.to_f + {r}.to_f).to_s"),
+        Form::Call("({l}.to_f - {r}.to_f).to_s"),
+        Form::Call("({l}.to_f * {r}.to_f).to_s"),
+        Form::Call("({l}.to_f / {r}.to_f).to_s"),
+        Form::Call("{l}.to_f.remainder({r}.to_f).to_s"),
+        Form::Template("{l}.to_f == {r}.to_f"),
+        Form::Template("{l}.to_f != {r}.to_f"),
+        Form::Template("{l}.to_f < {r}.to_f"),
+        Form::Template("{l}.to_f <= {r}.to_f"),
+        Form::Template("{l}.to_f > {r}.to_f"),
+        Form::Template("{l}.to_f >= {r}.to_f"),
+        Form::Infix("&&"),
+        Form::Infix("||"),
+    ])
+    .concat(Form::Infix("+"))
+    .strings([Form::Infix("=="), Form::Infix("!="), Form::Infix("<"), Form::Infix("<="), Form::Infix(">"), Form::Infix(">=")])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "puts [{args}].join(\" \")"),
+        ("println/0", "puts"),
+        ("toString", "{0}.to_s"),
+        ("toNumber", "{0}.to_f.to_s"),
+        ("len", "{0}.size.to_s"),
+        ("push", "{0} << {1}"),
+        ("pop", "{0}.pop"),
+        ("upper", "{0}.upcase"),
+        ("lower", "{0}.downcase"),
+        ("trim", "{0}.strip"),
+        ("contains", "{0}.includes?({1})"),
+        ("abs", "{0}.to_f.abs.to_s"),
+        ("sqrt", "Math.sqrt({0}.to_f).to_s"),
+        ("floor", "{0}.to_f.floor.to_s"),
+        ("ceil", "{0}.to_f.ceil.to_s"),
+        ("round", "{0}.to_f.round.to_s"),
+        ("pow", "({0}.to_f ** {1}.to_f).to_s"),
+        ("min", "Math.min({0}.to_f, {1}.to_f).to_s"),
+        ("max", "Math.max({0}.to_f, {1}.to_f).to_s"),
+        ("keys", "{0}.keys"),
+        ("hasKey", "{0}.has_key?({1})"),
+    ])
+    .placeholder("nil");
+
+    fn emit_crystal_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
+        for function in &block.functions {
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        output.push_str(") : String\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
+
+        // Handle main block statements
+        if block.language == "main" && !block.statements.is_empty() {
+            out.line("# Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        output.push_str("end\n");
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = \"\"", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return \"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {}\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("  else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("    {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("  end");
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement".to_string()),
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.block(format!("def {}(*args)", function.name), |out| {
+                out.line(format!(
+                    "raise \"Cross-language call to {}::{} not implemented\"",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
     }
-    
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("{} : String", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("def {}({}) : String", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -7657,27 +7900,11 @@ impl CrystalTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+", // String concatenation
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -7686,13 +7913,8 @@ impl CrystalTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for Crystal
-                if callee_str == "println" {
-                    Ok(format!("puts {}", args_str))
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -7710,8 +7932,43 @@ impl CrystalTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for CrystalTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = \"\"", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("return \"\"");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {}", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -7719,154 +7976,141 @@ impl CrystalTransformer {
 pub struct ZigTransformer;
 
 impl Transformer for ZigTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - Zig Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Systems programming with safety and performance\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Zig Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Systems programming with safety and performance");
+        out.blank();
+
         // Zig imports
-        output.push_str("const std = @import(\"std\");\n");
-        output.push_str("const print = std.debug.print;\n");
-        output.push_str("const allocator = std.heap.page_allocator;\n\n");
-        
+        out.line("const std = @import(\"std\");");
+        out.line("const print = std.debug.print;");
+        out.line("const allocator = std.heap.page_allocator;");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "zig" || block.language == "main" {
-                output.push_str(&self.generate_zig_functions(block)?);
+                self.emit_zig_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main function if needed
         if !program.global_statements.is_empty() {
-            output.push_str("pub fn main() !void {\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
-            }
-            output.push_str("}\n");
+            out.block("pub fn main() !void", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "zig"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".zig"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "zig"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "addrspace", "align", "allowzero", "and", "anyframe", "anytype", "asm", "async", "await",
+            "break", "callconv", "catch", "comptime", "const", "continue", "defer", "else", "enum",
+            "errdefer", "error", "export", "extern", "fn", "for", "if", "inline", "linksection", "noalias",
+            "noinline", "nosuspend", "opaque", "or", "orelse", "packed", "pub", "resume", "return", "struct",
+            "suspend", "switch", "test", "threadlocal", "try", "union", "unreachable", "usingnamespace",
+            "var", "volatile", "while",
+        ], Escape::Wrap("@\"", "\""))
+    }
 }
 
 impl ZigTransformer {
-    fn generate_zig_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Values are carried as byte slices, so numeric operators parse their
+    /// operands and format the result, and strings are compared with `std.mem`
+    const OPERATORS: Operators = Operators::new([
+        Form::Template("std.fmt.allocPrint(allocator, \"{d}\", .{ (std.fmt.parseFloat(f64, {l}) catch 0) + (std.fmt.parseFloat(f64, {r}) catch 0) }) catch \"\""),
+        Form::Template("std.fmt.allocPrint(allocator, \"{d}\", .{ (std.fmt.parseFloat(f64, {l}) catch 0) - (std.fmt.parseFloat(f64, {r}) catch 0) }) catch \"\""),
+        Form::Template("std.fmt.allocPrint(allocator, \"{d}\", .{ (std.fmt.parseFloat(f64, {l}) catch 0) * (std.fmt.parseFloat(f64, {r}) catch 0) }) catch \"\""),
+        Form::Template("std.fmt.allocPrint(allocator, \"{d}\", .{ (std.fmt.parseFloat(f64, {l}) catch 0) / (std.fmt.parseFloat(f64, {r}) catch 0) }) catch \"\""),
+        Form::Template("std.fmt.allocPrint(allocator, \"{d}\", .{ @rem(std.fmt.parseFloat(f64, {l}) catch 0, std.fmt.parseFloat(f64, {r}) catch 0) }) catch \"\""),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) == (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) != (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) < (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) <= (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) > (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Template("(std.fmt.parseFloat(f64, {l}) catch 0) >= (std.fmt.parseFloat(f64, {r}) catch 0)"),
+        Form::Infix("and"),
+        Form::Infix("or"),
+    ])
+    .concat(Form::Template("std.fmt.allocPrint(allocator, \"{s}{s}\", .{ {l}, {r} }) catch \"\""))
+    .strings([
+        Form::Call("std.mem.eql(u8, {l}, {r})"),
+        Form::Call("!std.mem.eql(u8, {l}, {r})"),
+        Form::Call("std.mem.lessThan(u8, {l}, {r})"),
+        Form::Call("!std.mem.lessThan(u8, {r}, {l})"),
+        Form::Call("std.mem.lessThan(u8, {r}, {l})"),
+        Form::Call("!std.mem.lessThan(u8, {l}, {r})"),
+    ])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "std.debug.print(\"{formats}\\n\", .{ {args} })"),
+        ("println/0", "std.debug.print(\"\\n\", .{})"),
+        ("toString", "{0}"),
+        ("len", "std.fmt.allocPrint(allocator, \"{d}\", .{ {0}.len }) catch \"\""),
+        ("contains", "std.mem.indexOf(u8, {0}, {1}) != null"),
+    ])
+    .formats(["{s}", "{s}", "{}", "{any}"]);
+
+    fn emit_zig_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("// Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("// Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("fn {}(args: anytype) []const u8 {{\n", function.name));
-            output.push_str(&format!(
-                "    _ = args; // Suppress unused variable warning\n"
-            ));
-            output.push_str(&format!(
-                "    return \"Cross-language call to {}::{} not implemented\";\n",
-                block.language, function.name
-            ));
-            output.push_str("}\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("fn {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("{}: []const u8", param.name));
-        }
-        
-        output.push_str(") []const u8 {\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {};\n", self.generate_statement(statement)?));
+            out.block(format!("fn {}(args: anytype) []const u8", function.name), |out| {
+                out.line("_ = args; // Suppress unused variable warning");
+                out.line(format!(
+                    "return \"Cross-language call to {}::{} not implemented\";",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        output.push_str("}\n");
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("const {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("var {}: []const u8 = \"\"", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("return {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("return \"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if ({}) {{\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                }
-                output.push_str("    }");
-                if let Some(else_stmts) = else_branch {
-                    output.push_str(" else {\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("        {};\n", self.generate_statement(stmt)?));
-                    }
-                    output.push_str("    }");
-                }
-                Ok(output)
-            }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("{}: []const u8", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("fn {}({}) []const u8", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -7878,19 +8122,11 @@ impl ZigTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("std.fmt.allocPrint(allocator, \"{{}}{{}}\", .{{ {}, {} }}) catch \"\"", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("std.mem.eql(u8, {}, {})", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("!std.mem.eql(u8, {}, {})", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("({} and {})", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("({} or {})", left_str, right_str)),
-                    _ => Ok(format!("// Unsupported operator: {} {} {}", left_str, "op", right_str)),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -7915,8 +8151,43 @@ impl ZigTransformer {
                     _ => Ok(format!("// Unsupported unary: {}{}", "op", operand_str)),
                 }
             }
-            _ => Ok("\"// Unsupported expression\"".to_string()),
+            other => Ok(unsupported_expression(other, "\"// Unsupported expression\"")),
+        }
+    }
+}
+
+impl StatementEmitter for ZigTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("const {} = {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("var {}: []const u8 = \"\";", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("return {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("return \"\";");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if ({})", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -7924,153 +8195,145 @@ impl ZigTransformer {
 pub struct ElixirTransformer;
 
 impl Transformer for ElixirTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("# Generated by Utopia Compiler - Elixir Backend\n");
-        output.push_str("# Multi-language support with runtime integration\n");
-        output.push_str("# Actor model concurrency and fault tolerance\n\n");
-        
-        output.push_str("defmodule UtopiaGenerated do\n");
-        output.push_str("  use GenServer\n");
-        output.push_str("  require Logger\n\n");
-        
-        // Process language blocks
-        for block in &program.language_blocks {
-            if block.language == "elixir" || block.language == "ex" || block.language == "main" {
-                output.push_str(&self.generate_elixir_functions(block)?);
-            } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::END);
+        out.line("# Generated by Utopia Compiler - Elixir Backend");
+        out.line("# Multi-language support with runtime integration");
+        out.line("# Actor model concurrency and fault tolerance");
+        out.blank();
+
+        let mut runs_main = false;
+        out.block("defmodule UtopiaGenerated do", |out| -> Result<()> {
+            out.line("use GenServer");
+            out.line("require Logger");
+            out.blank();
+
+            // Process language blocks
+            for block in &program.language_blocks {
+                if block.language == "elixir" || block.language == "ex" || block.language == "main" {
+                    runs_main |= self.emit_elixir_functions(out, block)?;
+                } else {
+                    self.emit_cross_language_functions(out, block);
+                }
             }
-        }
-        
-        // Main execution if needed
-        if !program.global_statements.is_empty() {
-            output.push_str("  def main do\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
+
+            // Main execution if needed
+            if !program.global_statements.is_empty() {
+                self.emit_main(out, &program.global_statements)?;
             }
-            output.push_str("  end\n");
+
+            Ok(())
+        })?;
+
+        if runs_main {
+            out.blank();
+            out.line("UtopiaGenerated.main()");
         }
-        
-        output.push_str("end\n");
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "elixir"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".ex"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "elixir" | "ex")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes])
+    }
+
+    fn eliminates_tail_calls(&self) -> bool {
+        true
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "after", "and", "catch", "do", "else", "end", "false", "fn", "in", "nil", "not", "or", "rescue",
+            "true", "when",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl ElixirTransformer {
-    fn generate_elixir_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("  def main do\n");
-            for statement in &block.statements {
-                output.push_str("    ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push_str("\n");
-            }
-            output.push_str("  end\nend\n\nUtopiaGenerated.main()\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("  # Cross-language block: {}\n", block.language));
-        
+    /// `rem` only takes integers; `&&` and `||` accept any value like Utopia's
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "==", "!=", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Modulo, Form::Call(":math.fmod({l}, {r})"))
+        .concat(Form::Call("\"#{{l}}#{{r}}\""))
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "IO.puts(Enum.join([{args}], \" \"))"),
+        ("println/0", "IO.puts(\"\")"),
+        ("toString", "to_string({0})"),
+        ("toNumber", "elem(Float.parse(to_string({0})), 0)"),
+        ("len", "Enum.count({0})"),
+        ("upper", "String.upcase({0})"),
+        ("lower", "String.downcase({0})"),
+        ("trim", "String.trim({0})"),
+        ("contains", "String.contains?({0}, {1})"),
+        ("abs", "abs({0})"),
+        ("sqrt", ":math.sqrt({0})"),
+        ("floor", "Float.floor({0} / 1)"),
+        ("ceil", "Float.ceil({0} / 1)"),
+        ("round", "Float.round({0} / 1)"),
+        ("pow", ":math.pow({0}, {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "Map.keys({0})"),
+        ("hasKey", "Map.has_key?({0}, {1})"),
+    ])
+    .placeholder("nil");
+
+    /// Write the block's functions; returns whether it defined a `main` to run
+    fn emit_elixir_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<bool> {
         for function in &block.functions {
-            output.push_str(&format!("  def {}(args \\\\ []) do\n", function.name));
-            output.push_str(&format!(
-                "    raise \"Cross-language call to {}::{} not implemented\"\n",
-                block.language, function.name
-            ));
-            output.push_str("  end\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("  def {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(") do\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}\n", self.generate_statement(statement)?));
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        output.push_str("  end\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = nil", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("nil".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            Statement::If { condition, then_branch, else_branch, .. } => {
-                let mut output = format!("if {} do\n", self.generate_expression(condition)?);
-                for stmt in then_branch {
-                    output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                }
-                if let Some(else_stmts) = else_branch {
-                    output.push_str("    else\n");
-                    for stmt in else_stmts {
-                        output.push_str(&format!("      {}\n", self.generate_statement(stmt)?));
-                    }
-                }
-                output.push_str("    end");
-                Ok(output)
-            }
-            _ => Ok("# Unsupported statement".to_string()),
+
+        // Handle main block statements
+        if block.language == "main" && !block.statements.is_empty() {
+            self.emit_main(out, &block.statements)?;
+            return Ok(true);
         }
+
+        Ok(false)
     }
-    
+
+    fn emit_main(&self, out: &mut CodeWriter, statements: &[Statement]) -> Result<()> {
+        out.block("def main do", |out| self.emit_statements(out, statements))
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("# Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.block(format!("def {}(args \\\\ []) do", function.name), |out| {
+                out.line(format!(
+                    "raise \"Cross-language call to {}::{} not implemented\"",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
+        }
+    }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("def {}({}) do", function.name, params.join(", ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -8082,27 +8345,11 @@ impl ElixirTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "<>", // String concatenation in Elixir
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "==",
-                    BinaryOperator::NotEqual => "!=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => "# unsupported op #",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -8129,8 +8376,43 @@ impl ElixirTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("# Unsupported expression #".to_string()),
+            other => Ok(unsupported_expression(other, "# Unsupported expression #")),
+        }
+    }
+}
+
+impl StatementEmitter for ElixirTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = nil", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("nil");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                out.open(format!("if {} do", self.generate_expression(condition)?));
+                self.emit_statements(out, then_branch)?;
+                if let Some(else_stmts) = else_branch {
+                    out.reopen("else");
+                    self.emit_statements(out, else_stmts)?;
+                }
+                out.close();
+            }
+            other => out.line(unsupported_statement(other, "# Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -8138,137 +8420,142 @@ impl ElixirTransformer {
 pub struct FSharpTransformer;
 
 impl Transformer for FSharpTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("// Generated by Utopia Compiler - F# Backend\n");
-        output.push_str("// Multi-language support with runtime integration\n");
-        output.push_str("// Functional-first .NET programming\n\n");
-        
-        output.push_str("module UtopiaGenerated\n\n");
-        output.push_str("open System\n");
-        output.push_str("open System.Collections.Generic\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("// Generated by Utopia Compiler - F# Backend");
+        out.line("// Multi-language support with runtime integration");
+        out.line("// Functional-first .NET programming");
+        out.blank();
+        out.line("module UtopiaGenerated");
+        out.blank();
+        out.line("open System");
+        out.line("open System.Collections.Generic");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "fsharp" || block.language == "fs" || block.language == "f#" || block.language == "main" {
-                output.push_str(&self.generate_fsharp_functions(block)?);
+                self.emit_fsharp_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("[<EntryPoint>]\n");
-            output.push_str("let main argv =\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {} |> ignore\n", self.generate_statement(statement)?));
-            }
-            output.push_str("    0\n");
+            out.line("[<EntryPoint>]");
+            out.block("let main argv =", |out| -> Result<()> {
+                for statement in &program.global_statements {
+                    out.line(format!("{} |> ignore", self.statement_text(out, statement)?));
+                }
+                out.line("0");
+                Ok(())
+            })?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "fsharp"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".fs"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "fsharp" | "fs" | "f#")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "abstract", "and", "as", "assert", "base", "begin", "class", "default", "delegate", "do", "done",
+            "downcast", "downto", "elif", "else", "end", "exception", "extern", "false", "finally", "fixed",
+            "for", "fun", "function", "global", "if", "in", "inherit", "inline", "interface", "internal",
+            "lazy", "let", "match", "member", "module", "mutable", "namespace", "new", "not", "null", "of",
+            "open", "or", "override", "private", "public", "rec", "return", "select", "sig", "static",
+            "struct", "then", "to", "true", "try", "type", "upcast", "use", "val", "void", "when", "while",
+            "with", "yield",
+        ], Escape::Wrap("``", "``"))
+    }
 }
 
 impl FSharpTransformer {
-    fn generate_fsharp_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "%", "=", "<>", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("float {l} / float {r}"))
+        .to_string("string {}")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "printfn \"{formats}\" {args}"),
+        ("println/0", "printfn \"\""),
+        ("toString", "(string {0})"),
+        ("toNumber", "(float {0})"),
+        ("len", "(float (Seq.length {0}))"),
+        ("upper", "{0}.ToUpper()"),
+        ("lower", "{0}.ToLower()"),
+        ("trim", "{0}.Trim()"),
+        ("contains", "{0}.Contains({1})"),
+        ("abs", "(abs {0})"),
+        ("sqrt", "(sqrt {0})"),
+        ("floor", "(floor {0})"),
+        ("ceil", "(ceil {0})"),
+        ("round", "(round {0})"),
+        ("pow", "({0} ** {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+        ("keys", "(List.ofSeq {0}.Keys)"),
+        ("hasKey", "{0}.ContainsKey({1})"),
+    ])
+    .separator(" ")
+    .formats(["%s", "%g", "%b", "%A"])
+    .placeholder("(failwith \"unsupported builtin\")");
+
+    fn emit_fsharp_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("// Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("// Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("// Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("// Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("let {} args =\n", function.name));
-            output.push_str(&format!(
-                "    failwith \"Cross-language call to {}::{} not implemented\"\n\n",
-                block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("let {} ", function.name));
-        
-        // Parameters
-        for param in &function.parameters {
-            output.push_str(&format!("{} ", param.name));
-        }
-        
-        output.push_str("=\n");
-        
-        // Function body (simplified for F# functional style)
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("    {}\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("    \"function result\"\n");
-            }
-        } else {
-            output.push_str("    \"empty function\"\n");
+            out.block(format!("let {} args =", function.name), |out| {
+                out.line(format!(
+                    "failwith \"Cross-language call to {}::{} not implemented\"",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("let {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("let {} = \"\"", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("\"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: String = function.parameters.iter().map(|param| format!("{} ", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("let {} {}=", function.name, params), |out| {
+            // Function body (simplified for F# functional style)
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(self.generate_expression(expr)?),
+                Some(_) => out.line("\"function result\""),
+                None => out.line("\"empty function\""),
             }
-            _ => Ok("// Unsupported statement".to_string()),
-        }
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -8280,27 +8567,11 @@ impl FSharpTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "+", // String concatenation
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "<>",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "// unsupported op //",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -8309,17 +8580,8 @@ impl FSharpTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(" ");
-                
-                // Handle special functions for F#
-                if callee_str == "println" {
-                    if arguments.is_empty() {
-                        Ok("printfn \"\"".to_string())
-                    } else {
-                        // Use printfn with %A for each argument (F# generic formatter)
-                        let format_specs = arguments.iter().map(|_| "%A").collect::<Vec<_>>().join(" ");
-                        Ok(format!("printfn \"{}\" {}", format_specs, args_str))
-                    }
-                } else if args_str.is_empty() {
+
+                if args_str.is_empty() {
                     Ok(format!("{} ()", callee_str))
                 } else {
                     Ok(format!("{} {}", callee_str, args_str))
@@ -8341,8 +8603,34 @@ impl FSharpTransformer {
                 };
                 Ok(format!("({}{})", op_str, operand_str))
             }
-            _ => Ok("// Unsupported expression //".to_string()),
+            other => Ok(unsupported_expression(other, "// Unsupported expression //")),
+        }
+    }
+}
+
+impl StatementEmitter for FSharpTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("let {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("let {} = \"\"", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("\"\"");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            other => out.line(unsupported_statement(other, "// Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -8350,135 +8638,139 @@ impl FSharpTransformer {
 pub struct ClojureTransformer;
 
 impl Transformer for ClojureTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(";; Generated by Utopia Compiler - Clojure Backend\n");
-        output.push_str(";; Multi-language support with runtime integration\n");
-        output.push_str(";; Lisp dialect on JVM with immutable data structures\n\n");
-        
-        output.push_str("(ns utopia.generated\n");
-        output.push_str("  (:require [clojure.string :as str]\n");
-        output.push_str("            [clojure.data.json :as json]))\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close().line_comment(";"));
+        out.line(";; Generated by Utopia Compiler - Clojure Backend");
+        out.line(";; Multi-language support with runtime integration");
+        out.line(";; Lisp dialect on JVM with immutable data structures");
+        out.blank();
+        out.line("(ns utopia.generated");
+        out.line("  (:require [clojure.string :as str]");
+        out.line("            [clojure.data.json :as json]))");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "clojure" || block.language == "clj" || block.language == "main" {
-                output.push_str(&self.generate_clojure_functions(block)?);
+                self.emit_clojure_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("(defn -main [& args]\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {}\n", self.generate_statement(statement)?));
-            }
-            output.push_str(")\n");
+            out.block("(defn -main [& args]", |out| self.emit_statements(out, &program.global_statements))?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "clojure"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".clj"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "clojure" | "clj")
     }
+
 }
 
 impl ClojureTransformer {
-    fn generate_clojure_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("(+ {l} {r})"),
+        Form::Call("(- {l} {r})"),
+        Form::Call("(* {l} {r})"),
+        Form::Call("(/ (double {l}) {r})"),
+        Form::Call("(rem {l} {r})"),
+        Form::Call("(= {l} {r})"),
+        Form::Call("(not= {l} {r})"),
+        Form::Call("(< {l} {r})"),
+        Form::Call("(<= {l} {r})"),
+        Form::Call("(> {l} {r})"),
+        Form::Call("(>= {l} {r})"),
+        Form::Call("(and {l} {r})"),
+        Form::Call("(or {l} {r})"),
+    ])
+    .concat(Form::Call("(str {l} {r})"))
+    .strings([
+        Form::Call("(= {l} {r})"),
+        Form::Call("(not= {l} {r})"),
+        Form::Call("(neg? (compare {l} {r}))"),
+        Form::Call("(not (pos? (compare {l} {r})))"),
+        Form::Call("(pos? (compare {l} {r}))"),
+        Form::Call("(not (neg? (compare {l} {r})))"),
+    ]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "(println {args})"),
+        ("toString", "(str {0})"),
+        ("toNumber", "(Double/parseDouble (str {0}))"),
+        ("toBoolean", "(boolean {0})"),
+        ("len", "(count {0})"),
+        ("push", "(conj {0} {1})"),
+        ("pop", "(peek {0})"),
+        ("upper", "(clojure.string/upper-case {0})"),
+        ("lower", "(clojure.string/lower-case {0})"),
+        ("trim", "(clojure.string/trim {0})"),
+        ("contains", "(clojure.string/includes? {0} {1})"),
+        ("abs", "(Math/abs {0})"),
+        ("sqrt", "(Math/sqrt {0})"),
+        ("floor", "(Math/floor {0})"),
+        ("ceil", "(Math/ceil {0})"),
+        ("round", "(Math/round {0})"),
+        ("pow", "(Math/pow {0} {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+        ("keys", "(vec (keys {0}))"),
+        ("hasKey", "(contains? {0} {1})"),
+    ])
+    .separator(" ")
+    .placeholder("nil");
+
+    fn emit_clojure_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("(defn -main [& args]\n");
-            for statement in &block.statements {
-                output.push_str("  ");
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
-            output.push_str(")\n\n(-main)\n");
+            out.block("(defn -main [& args]", |out| self.emit_statements(out, &block.statements))?;
+            out.blank();
+            out.line("(-main)");
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!(";; Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!(";; Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("(defn {} [& args]\n", function.name));
-            output.push_str(&format!(
-                "  (throw (Exception. \"Cross-language call to {}::{} not implemented\")))\n\n",
-                block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("(defn {} [", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push(' ');
-            }
-            output.push_str(&param.name);
-        }
-        
-        output.push_str("]\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("  {})\n", self.generate_statement(statement)?));
+            out.block(format!("(defn {} [& args]", function.name), |out| {
+                out.line(format!(
+                    "(throw (Exception. \"Cross-language call to {}::{} not implemented\"))",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("(def {} {})", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("(def {} nil)", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("nil".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            _ => Ok(";; Unsupported statement".to_string()),
-        }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<&str> = function.parameters.iter().map(|param| param.name.as_str()).collect();
+
+        out.map(function.span);
+        out.block(format!("(defn {} [{}]", function.name, params.join(" ")), |out| {
+            self.emit_statements(out, &function.body)
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -8490,27 +8782,11 @@ impl ClojureTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "str",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "not=",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => ";; unsupported op ;;",
-                };
-                Ok(format!("({} {} {})", op_str, left_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -8537,8 +8813,34 @@ impl ClojureTransformer {
                 };
                 Ok(format!("({} {})", op_str, operand_str))
             }
-            _ => Ok(";; Unsupported expression ;;".to_string()),
+            other => Ok(unsupported_expression(other, ";; Unsupported expression ;;")),
+        }
+    }
+}
+
+impl StatementEmitter for ClojureTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("(def {} {})", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("(def {} nil)", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("nil");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            other => out.line(unsupported_statement(other, ";; Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -8546,135 +8848,161 @@ impl ClojureTransformer {
 pub struct ErlangTransformer;
 
 impl Transformer for ErlangTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("% Generated by Utopia Compiler - Erlang Backend\n");
-        output.push_str("% Multi-language support with runtime integration\n");
-        output.push_str("% Concurrent systems with actor model\n\n");
-        
-        output.push_str("-module(utopia_generated).\n");
-        output.push_str("-export([start/0]).\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("% Generated by Utopia Compiler - Erlang Backend");
+        out.line("% Multi-language support with runtime integration");
+        out.line("% Concurrent systems with actor model");
+        out.blank();
+        out.line("-module(utopia_generated).");
+        out.line("-export([start/0]).");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "erlang" || block.language == "erl" || block.language == "main" {
-                output.push_str(&self.generate_erlang_functions(block)?);
+                self.emit_erlang_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("start() ->\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {},\n", self.generate_statement(statement)?));
-            }
-            output.push_str("    ok.\n");
+            out.line("start() ->");
+            out.indented(|out| -> Result<()> {
+                for statement in &program.global_statements {
+                    out.line(format!("{},", self.statement_text(out, statement)?));
+                }
+                out.line("ok.");
+                Ok(())
+            })?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "erlang"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".erl"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "erlang" | "erl")
     }
-}
 
-impl ErlangTransformer {
-    fn generate_erlang_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        // Handle main block statements
-        if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("% Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
-        }
-        
-        Ok(output)
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes])
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("% Cross-language block: {}\n", block.language));
-        
+
+    fn eliminates_tail_calls(&self) -> bool {
+        true
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case", "catch",
+            "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or", "orelse",
+            "receive", "rem", "try", "when", "xor",
+        ], Escape::Suffix("_"))
+    }
+}
+
+impl ErlangTransformer {
+    /// `rem` only takes integers
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem", "=:=", "=/=", "<", "=<", ">", ">=", "andalso", "orelse"])
+        .with(BinaryOperator::Modulo, Form::Call("math:fmod({l}, {r})"))
+        .concat(Form::Call("lists:concat([{l}, {r}])"));
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "io:format(\"{formats}~n\", [{args}])"),
+        ("println/0", "io:format(\"~n\")"),
+        ("toString", "lists:flatten(io_lib:format(\"~p\", [{0}]))"),
+        ("toNumber", "list_to_float({0})"),
+        ("len", "length({0})"),
+        ("upper", "string:uppercase({0})"),
+        ("lower", "string:lowercase({0})"),
+        ("trim", "string:trim({0})"),
+        ("contains", "(string:find({0}, {1}) =/= nomatch)"),
+        ("abs", "abs({0})"),
+        ("sqrt", "math:sqrt({0})"),
+        ("floor", "float(floor({0}))"),
+        ("ceil", "float(ceil({0}))"),
+        ("round", "float(round({0}))"),
+        ("pow", "math:pow({0}, {1})"),
+        ("min", "min({0}, {1})"),
+        ("max", "max({0}, {1})"),
+        ("keys", "maps:keys({0})"),
+        ("hasKey", "maps:is_key({1}, {0})"),
+    ])
+    .formats(["~s", "~p", "~p", "~p"])
+    .placeholder("undefined");
+
+    fn emit_erlang_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&format!("{}(Args) ->\n", function.name));
-            output.push_str(&format!(
-                "    error({{not_implemented, \"Cross-language call to {}::{} not implemented\"}}).\n\n",
-                block.language, function.name
-            ));
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("{}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            // Capitalize first letter for Erlang variable convention
-            let capitalized = format!("{}{}", param.name.chars().next().unwrap().to_uppercase().collect::<String>(), &param.name[1..]);
-            output.push_str(&capitalized);
+
+        // Handle main block statements
+        if block.language == "main" && !block.statements.is_empty() {
+            out.line("% Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        output.push_str(") ->\n");
-        
-        // Function body
-        for statement in &function.body {
-            output.push_str(&format!("    {}.\n", self.generate_statement(statement)?));
+
+        Ok(())
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("% Cross-language block: {}", block.language));
+
+        for function in &block.functions {
+            out.line(format!("{}(Args) ->", function.name));
+            out.indented(|out| {
+                out.line(format!(
+                    "error({{not_implemented, \"Cross-language call to {}::{} not implemented\"}}).",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    let capitalized = format!("{}{}", name.chars().next().unwrap().to_uppercase().collect::<String>(), &name[1..]);
-                    Ok(format!("{} = {}", capitalized, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} = undefined", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("ok".to_string())
-                }
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| Self::variable_name(&param.name)).collect();
+
+        out.map(function.span);
+        out.line(format!("{}({}) ->", function.name, params.join(", ")));
+
+        // Function body as expressions separated by commas and ended by a period
+        let expressions = function.body.iter()
+            .map(|statement| self.statement_text(out, statement))
+            .collect::<Result<Vec<String>>>()?;
+        out.indented(|out| {
+            if expressions.is_empty() {
+                out.line("ok.");
             }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+            for (i, expression) in expressions.iter().enumerate() {
+                let separator = if i + 1 == expressions.len() { "." } else { "," };
+                out.line(format!("{}{}", expression, separator));
             }
-            _ => Ok("% Unsupported statement".to_string()),
+        });
+
+        Ok(())
+    }
+
+    /// Capitalize first letter for Erlang variable convention
+    fn variable_name(name: &str) -> String {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
         }
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -8685,23 +9013,12 @@ impl ErlangTransformer {
                     LiteralValue::Null => Ok("undefined".to_string()),
                 }
             }
-            Expression::Identifier { name, .. } => {
-                let capitalized = format!("{}{}", name.chars().next().unwrap().to_uppercase().collect::<String>(), &name[1..]);
-                Ok(capitalized)
-            }
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("string:concat({}, {})", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("{} =:= {}", left_str, right_str)),
-                    BinaryOperator::NotEqual => Ok(format!("{} =/= {}", left_str, right_str)),
-                    BinaryOperator::And => Ok(format!("{} andalso {}", left_str, right_str)),
-                    BinaryOperator::Or => Ok(format!("{} orelse {}", left_str, right_str)),
-                    _ => Ok(format!("% Unsupported operator: {} op {}", left_str, right_str)),
-                }
-            }
+            Expression::Identifier { name, .. } => Ok(Self::variable_name(name)),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -8710,19 +9027,8 @@ impl ErlangTransformer {
                     .map(|arg| self.generate_expression(arg))
                     .collect();
                 let args_str = args?.join(", ");
-                
-                // Handle special functions for Erlang
-                if callee_str == "println" {
-                    if arguments.is_empty() {
-                        Ok("io:format(\"~n\")".to_string())
-                    } else {
-                        // Generate format string with ~p for each argument
-                        let format_specs = arguments.iter().map(|_| "~p").collect::<Vec<_>>().join(" ");
-                        Ok(format!("io:format(\"{}~n\", [{}])", format_specs, args_str))
-                    }
-                } else {
-                    Ok(format!("{}({})", callee_str, args_str))
-                }
+
+                Ok(format!("{}({})", callee_str, args_str))
             }
             Expression::CrossCall { language, function, arguments, .. } => {
                 let args: Result<Vec<String>> = arguments.iter()
@@ -8731,8 +9037,34 @@ impl ErlangTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("cross_call(\"{}\", \"{}\", [{}])", language, function, args_str))
             }
-            _ => Ok("% Unsupported expression".to_string()),
+            other => Ok(unsupported_expression(other, "% Unsupported expression")),
+        }
+    }
+}
+
+impl StatementEmitter for ErlangTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} = {}", Self::variable_name(name), self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} = undefined", Self::variable_name(name)));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("ok");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            other => out.line(unsupported_statement(other, "% Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -8740,134 +9072,136 @@ impl ErlangTransformer {
 pub struct OCamlTransformer;
 
 impl Transformer for OCamlTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("(* Generated by Utopia Compiler - OCaml Backend *)\n");
-        output.push_str("(* Multi-language support with runtime integration *)\n");
-        output.push_str("(* Functional programming with type inference *)\n\n");
-        
-        output.push_str("open Printf\n");
-        output.push_str("open String\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::INDENT);
+        out.line("(* Generated by Utopia Compiler - OCaml Backend *)");
+        out.line("(* Multi-language support with runtime integration *)");
+        out.line("(* Functional programming with type inference *)");
+        out.blank();
+        out.line("open Printf");
+        out.line("open String");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "ocaml" || block.language == "ml" || block.language == "main" {
-                output.push_str(&self.generate_ocaml_functions(block)?);
+                self.emit_ocaml_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution if needed
         if !program.global_statements.is_empty() {
-            output.push_str("let () =\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {} |> ignore;\n", self.generate_statement(statement)?));
-            }
+            out.block("let () =", |out| -> Result<()> {
+                for statement in &program.global_statements {
+                    out.line(format!("{} |> ignore;", self.statement_text(out, statement)?));
+                }
+                Ok(())
+            })?;
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "ocaml"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".ml"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "ocaml" | "ml")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "as", "asr", "assert", "begin", "class", "constraint", "do", "done", "downto", "else",
+            "end", "exception", "external", "false", "for", "fun", "function", "functor", "if", "in",
+            "include", "inherit", "initializer", "land", "lazy", "let", "lor", "lsl", "lsr", "lxor", "match",
+            "method", "mod", "module", "mutable", "new", "nonrec", "object", "of", "open", "or", "private",
+            "rec", "sig", "struct", "then", "to", "true", "try", "type", "val", "virtual", "when", "while",
+            "with",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl OCamlTransformer {
-    fn generate_ocaml_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Number literals are integers, so `/` converts before dividing
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "mod", "=", "<>", "<", "<=", ">", ">=", "&&", "||"])
+        .with(BinaryOperator::Divide, Form::Template("float_of_int {l} /. float_of_int {r}"))
+        .concat(Form::Infix("^"))
+        .to_string("string_of_int {}")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "print_endline (String.concat \" \" [{args}])"),
+        ("println/0", "print_newline ()"),
+        ("toString", "(string_of_float {0})"),
+        ("toNumber", "(float_of_string {0})"),
+        ("len", "(float_of_int (String.length {0}))"),
+        ("upper", "(String.uppercase_ascii {0})"),
+        ("lower", "(String.lowercase_ascii {0})"),
+        ("trim", "(String.trim {0})"),
+        ("abs", "(abs_float {0})"),
+        ("sqrt", "(sqrt {0})"),
+        ("floor", "(floor {0})"),
+        ("ceil", "(ceil {0})"),
+        ("round", "(Float.round {0})"),
+        ("pow", "({0} ** {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+        ("hasKey", "(Hashtbl.mem {0} {1})"),
+    ])
+    .separator("; ")
+    .placeholder("(failwith \"unsupported builtin\")");
+
+    fn emit_ocaml_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str("(* Main execution *)\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line("(* Main execution *)");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("(* Cross-language block: {} *)\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("(* Cross-language block: {} *)", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("let {} args =\n", function.name));
-            output.push_str(&format!(
-                "  failwith \"Cross-language call to {}::{} not implemented\"\n\n",
-                block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("let {} ", function.name));
-        
-        // Parameters
-        for param in &function.parameters {
-            output.push_str(&format!("{} ", param.name));
-        }
-        
-        output.push_str("=\n");
-        
-        // Function body (simplified)
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("  {}\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("  \"function result\"\n");
-            }
-        } else {
-            output.push_str("  \"empty function\"\n");
+            out.block(format!("let {} args =", function.name), |out| {
+                out.line(format!(
+                    "failwith \"Cross-language call to {}::{} not implemented\"",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("let {} = {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("let {} = \"\"", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("\"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: String = function.parameters.iter().map(|param| format!("{} ", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("let {} {}=", function.name, params), |out| {
+            // Function body (simplified)
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(self.generate_expression(expr)?),
+                Some(_) => out.line("\"function result\""),
+                None => out.line("\"empty function\""),
             }
-            _ => Ok("(* Unsupported statement *)".to_string()),
-        }
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -8879,20 +9213,11 @@ impl OCamlTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "^", // String concatenation in OCaml
-                    BinaryOperator::Equal => "=",
-                    BinaryOperator::NotEqual => "<>",
-                    BinaryOperator::And => "&&",
-                    BinaryOperator::Or => "||",
-                    _ => "(* unsupported op *)",
-                };
-                Ok(format!("({} {} {})", left_str, op_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -8907,8 +9232,34 @@ impl OCamlTransformer {
                     Ok(format!("{} {}", callee_str, args_str))
                 }
             }
-            _ => Ok("(* Unsupported expression *)".to_string()),
+            other => Ok(unsupported_expression(other, "(* Unsupported expression *)")),
+        }
+    }
+}
+
+impl StatementEmitter for OCamlTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("let {} = {}", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("let {} = \"\"", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("\"\"");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            other => out.line(unsupported_statement(other, "(* Unsupported statement *)")),
         }
+        Ok(())
     }
 }
 
@@ -8916,173 +9267,191 @@ impl OCamlTransformer {
 pub struct SchemeTransformer;
 
 impl Transformer for SchemeTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(";; Generated by Utopia Compiler - Scheme Backend\n");
-        output.push_str(";; Multi-language support with runtime integration\n");
-        output.push_str(";; Minimalist Lisp dialect\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close().line_comment(";"));
+        out.line(";; Generated by Utopia Compiler - Scheme Backend");
+        out.line(";; Multi-language support with runtime integration");
+        out.line(";; Minimalist Lisp dialect");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "scheme" || block.language == "scm" || block.language == "main" {
-                output.push_str(&self.generate_scheme_functions(block)?);
+                self.emit_scheme_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution
-        if !program.global_statements.is_empty() {
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
-        }
-        
-        Ok(output)
+        self.emit_statements(&mut out, &program.global_statements)?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "scheme"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".scm"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "scheme" | "scm")
     }
+
+    fn eliminates_tail_calls(&self) -> bool {
+        true
+    }
 }
 
 impl SchemeTransformer {
-    fn generate_scheme_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("(+ {l} {r})"),
+        Form::Call("(- {l} {r})"),
+        Form::Call("(* {l} {r})"),
+        Form::Call("(exact->inexact (/ {l} {r}))"),
+        Form::Call("(remainder {l} {r})"),
+        Form::Call("(= {l} {r})"),
+        Form::Call("(not (= {l} {r}))"),
+        Form::Call("(< {l} {r})"),
+        Form::Call("(<= {l} {r})"),
+        Form::Call("(> {l} {r})"),
+        Form::Call("(>= {l} {r})"),
+        Form::Call("(and {l} {r})"),
+        Form::Call("(or {l} {r})"),
+    ])
+    .concat(Form::Call("(string-append {l} {r})"))
+    .to_string("(number->string {})")
+    .strings([
+        Form::Call("(string=? {l} {r})"),
+        Form::Call("(not (string=? {l} {r}))"),
+        Form::Call("(string<? {l} {r})"),
+        Form::Call("(string<=? {l} {r})"),
+        Form::Call("(string>? {l} {r})"),
+        Form::Call("(string>=? {l} {r})"),
+    ]);
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "(begin (for-each (lambda (x) (display x) (display \" \")) (list {args})) (newline))"),
+        ("println/0", "(newline)"),
+        ("toString", "(number->string {0})"),
+        ("toNumber", "(string->number {0})"),
+        ("toBoolean", "(not (not {0}))"),
+        ("len", "(string-length {0})"),
+        ("upper", "(string-upcase {0})"),
+        ("lower", "(string-downcase {0})"),
+        ("abs", "(abs {0})"),
+        ("sqrt", "(sqrt {0})"),
+        ("floor", "(floor {0})"),
+        ("ceil", "(ceiling {0})"),
+        ("round", "(round {0})"),
+        ("pow", "(expt {0} {1})"),
+        ("min", "(min {0} {1})"),
+        ("max", "(max {0} {1})"),
+    ])
+    .separator(" ")
+    .placeholder("'()");
+
+    fn emit_scheme_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
+
         // Handle main block statements
         if block.language == "main" && !block.statements.is_empty() {
-            output.push_str(";; Main execution\n");
-            for statement in &block.statements {
-                output.push_str(&self.generate_statement(statement)?);
-                output.push('\n');
-            }
+            out.line(";; Main execution");
+            self.emit_statements(out, &block.statements)?;
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!(";; Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!(";; Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("(define ({} . args)\n", function.name));
-            output.push_str(&format!(
-                "  (error \"Cross-language call to {}::{} not implemented\"))\n\n",
-                block.language, function.name
-            ));
+            out.block(format!("(define ({} . args)", function.name), |out| {
+                out.line(format!(
+                    "(error \"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("(define ({}", function.name));
-        
-        // Parameters
-        for param in &function.parameters {
-            output.push_str(&format!(" {}", param.name));
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body (simplified for Scheme)
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("  {})\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("  \"function result\")\n");
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: String = function.parameters.iter().map(|param| format!(" {}", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("(define ({}{})", function.name, params), |out| {
+            // Function body (simplified for Scheme)
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(self.generate_expression(expr)?),
+                Some(_) => out.line("\"function result\""),
+                None => out.line("\"empty function\""),
             }
-        } else {
-            output.push_str("  \"empty function\")\n");
+            Ok(())
+        })
+    }
+
+    fn generate_expression(&self, expression: &Expression) -> Result<String> {
+        match expression {
+            Expression::Literal { value, .. } => {
+                match value {
+                    LiteralValue::Number(n) => Ok(n.to_string()),
+                    LiteralValue::String(s) => Ok(format!("\"{}\"", s.replace('"', "\\\""))),
+                    LiteralValue::Boolean(b) => Ok(if *b { "#t" } else { "#f" }.to_string()),
+                    LiteralValue::Null => Ok("#f".to_string()),
+                }
+            }
+            Expression::Identifier { name, .. } => Ok(name.clone()),
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
+            Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
+                let callee_str = match callee.as_ref() {
+                    Expression::Identifier { name, .. } => name.clone(),
+                    _ => self.generate_expression(callee)?,
+                };
+                let args: Result<Vec<String>> = arguments.iter()
+                    .map(|arg| self.generate_expression(arg))
+                    .collect();
+                let args_str = args?.join(" ");
+                Ok(format!("({} {})", callee_str, args_str))
+            }
+            other => Ok(unsupported_expression(other, ";; Unsupported expression ;;")),
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
+}
+
+impl StatementEmitter for SchemeTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
         match statement {
             Statement::VariableDeclaration { name, value, .. } => {
                 if let Some(expr) = value {
-                    Ok(format!("(define {} {})", name, self.generate_expression(expr)?))
+                    out.line(format!("(define {} {})", name, self.generate_expression(expr)?));
                 } else {
-                    Ok(format!("(define {} #f)", name))
+                    out.line(format!("(define {} #f)", name));
                 }
             }
             Statement::Return { value, .. } => {
                 if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("#f".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            _ => Ok(";; Unsupported statement".to_string()),
-        }
-    }
-    
-    fn generate_expression(&self, expression: &Expression) -> Result<String> {
-        match expression {
-            Expression::Literal { value, .. } => {
-                match value {
-                    LiteralValue::Number(n) => Ok(n.to_string()),
-                    LiteralValue::String(s) => Ok(format!("\"{}\"", s.replace('"', "\\\""))),
-                    LiteralValue::Boolean(b) => Ok(if *b { "#t" } else { "#f" }.to_string()),
-                    LiteralValue::Null => Ok("#f".to_string()),
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("#f");
                 }
             }
-            Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "string-append",
-                    BinaryOperator::Subtract => "-",
-                    BinaryOperator::Multiply => "*",
-                    BinaryOperator::Divide => "/",
-                    BinaryOperator::Equal => "equal?",
-                    BinaryOperator::Less => "<",
-                    BinaryOperator::Greater => ">",
-                    BinaryOperator::LessEqual => "<=",
-                    BinaryOperator::GreaterEqual => ">=",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => ";; unsupported ;;",
-                };
-                Ok(format!("({} {} {})", op_str, left_str, right_str))
-            }
-            Expression::Call { callee, arguments, .. } => {
-                let callee_str = match callee.as_ref() {
-                    Expression::Identifier { name, .. } => name.clone(),
-                    _ => self.generate_expression(callee)?,
-                };
-                let args: Result<Vec<String>> = arguments.iter()
-                    .map(|arg| self.generate_expression(arg))
-                    .collect();
-                let args_str = args?.join(" ");
-                Ok(format!("({} {})", callee_str, args_str))
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
             }
-            _ => Ok(";; Unsupported expression ;;".to_string()),
+            other => out.line(unsupported_statement(other, ";; Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -9090,125 +9459,101 @@ impl SchemeTransformer {
 pub struct RacketTransformer;
 
 impl Transformer for RacketTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(";; Generated by Utopia Compiler - Racket Backend\n");
-        output.push_str(";; Multi-language support with runtime integration\n");
-        output.push_str(";; General-purpose Lisp with extensive libraries\n\n");
-        
-        output.push_str("#lang racket\n\n");
-        output.push_str("(require racket/string)\n");
-        output.push_str("(require json)\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::new("", ")").inline_close().line_comment(";"));
+        out.line(";; Generated by Utopia Compiler - Racket Backend");
+        out.line(";; Multi-language support with runtime integration");
+        out.line(";; General-purpose Lisp with extensive libraries");
+        out.blank();
+        out.line("#lang racket");
+        out.blank();
+        out.line("(require racket/string)");
+        out.line("(require json)");
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "racket" || block.language == "rkt" || block.language == "main" {
-                output.push_str(&self.generate_racket_functions(block)?);
+                self.emit_racket_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution
-        if !program.global_statements.is_empty() {
-            for statement in &program.global_statements {
-                output.push_str(&format!("{}\n", self.generate_statement(statement)?));
-            }
-        }
-        
-        Ok(output)
+        self.emit_statements(&mut out, &program.global_statements)?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "racket"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".rkt"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "racket" | "rkt")
     }
+
+    fn eliminates_tail_calls(&self) -> bool {
+        true
+    }
 }
 
 impl RacketTransformer {
-    fn generate_racket_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    const OPERATORS: Operators = SchemeTransformer::OPERATORS.to_string("(~a {})");
+
+    const BUILTINS: Builtins = SchemeTransformer::BUILTINS.overriding(&[
+        ("println", "(displayln (string-join (map ~a (list {args})) \" \"))"),
+        ("toString", "(~a {0})"),
+        ("len", "(sequence-length {0})"),
+        ("trim", "(string-trim {0})"),
+        ("contains", "(string-contains? {0} {1})"),
+        ("keys", "(hash-keys {0})"),
+        ("hasKey", "(hash-has-key? {0} {1})"),
+    ]);
+
+    fn emit_racket_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!(";; Cross-language block: {}\n", block.language));
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!(";; Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("(define ({} . args)\n", function.name));
-            output.push_str(&format!(
-                "  (error \"Cross-language call to {}::{} not implemented\"))\n\n",
-                block.language, function.name
-            ));
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("(define ({}", function.name));
-        
-        // Parameters
-        for param in &function.parameters {
-            output.push_str(&format!(" {}", param.name));
-        }
-        
-        output.push_str(")\n");
-        
-        // Function body
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("  {})\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("  \"function result\")\n");
-            }
-        } else {
-            output.push_str("  \"empty function\")\n");
+            out.block(format!("(define ({} . args)", function.name), |out| {
+                out.line(format!(
+                    "(error \"Cross-language call to {}::{} not implemented\")",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("(define {} {})", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("(define {} #f)", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    self.generate_expression(expr)
-                } else {
-                    Ok("#f".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: String = function.parameters.iter().map(|param| format!(" {}", param.name)).collect();
+
+        out.map(function.span);
+        out.block(format!("(define ({}{})", function.name, params), |out| {
+            // Function body
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(self.generate_expression(expr)?),
+                Some(_) => out.line("\"function result\""),
+                None => out.line("\"empty function\""),
             }
-            _ => Ok(";; Unsupported statement".to_string()),
-        }
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -9220,19 +9565,11 @@ impl RacketTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                let op_str = match operator {
-                    BinaryOperator::Add => "string-append",
-                    BinaryOperator::Equal => "equal?",
-                    BinaryOperator::And => "and",
-                    BinaryOperator::Or => "or",
-                    _ => ";; unsupported ;;",
-                };
-                Ok(format!("({} {} {})", op_str, left_str, right_str))
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -9243,8 +9580,34 @@ impl RacketTransformer {
                 let args_str = args?.join(" ");
                 Ok(format!("({} {})", callee_str, args_str))
             }
-            _ => Ok(";; Unsupported expression ;;".to_string()),
+            other => Ok(unsupported_expression(other, ";; Unsupported expression ;;")),
+        }
+    }
+}
+
+impl StatementEmitter for RacketTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("(define {} {})", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("(define {} #f)", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(self.generate_expression(expr)?);
+                } else {
+                    out.line("#f");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(self.generate_expression(expression)?);
+            }
+            other => out.line(unsupported_statement(other, ";; Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -9252,134 +9615,144 @@ impl RacketTransformer {
 pub struct SmalltalkTransformer;
 
 impl Transformer for SmalltalkTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("\"Generated by Utopia Compiler - Smalltalk Backend\"\n");
-        output.push_str("\"Multi-language support with runtime integration\"\n");
-        output.push_str("\"Pure object-oriented programming\"\n\n");
-        
-        output.push_str("Object subclass: #UtopiaGenerated\n");
-        output.push_str("    instanceVariableNames: ''\n");
-        output.push_str("    classVariableNames: ''\n");
-        output.push_str("    poolDictionaries: ''\n");
-        output.push_str("    category: 'Utopia-Generated'\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::INDENT);
+        out.line("\"Generated by Utopia Compiler - Smalltalk Backend\"");
+        out.line("\"Multi-language support with runtime integration\"");
+        out.line("\"Pure object-oriented programming\"");
+        out.blank();
+        out.line("Object subclass: #UtopiaGenerated");
+        out.indented(|out| {
+            out.line("instanceVariableNames: ''");
+            out.line("classVariableNames: ''");
+            out.line("poolDictionaries: ''");
+            out.line("category: 'Utopia-Generated'");
+        });
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "smalltalk" || block.language == "st" || block.language == "main" {
-                output.push_str(&self.generate_smalltalk_methods(block)?);
+                self.emit_smalltalk_methods(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_methods(block)?);
+                self.emit_cross_language_methods(&mut out, block);
             }
         }
-        
+
         // Main execution
         if !program.global_statements.is_empty() {
-            output.push_str("main\n");
-            for statement in &program.global_statements {
-                output.push_str(&format!("    {}.\n", self.generate_statement(statement)?));
-            }
-            output.push_str("    ^ self\n\n");
+            out.block("main", |out| -> Result<()> {
+                self.emit_statements(out, &program.global_statements)?;
+                out.line("^ self");
+                Ok(())
+            })?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "smalltalk"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".st"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "smalltalk" | "st")
     }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "false", "nil", "self", "super", "thisContext", "true",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl SmalltalkTransformer {
-    fn generate_smalltalk_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Binary messages have no precedence among themselves, so every operator is parenthesized
+    const OPERATORS: Operators = Operators::infix(["+", "-", "*", "/", "rem:", "=", "~=", "<", "<=", ">", ">=", "and:", "or:"])
+        .with(BinaryOperator::Divide, Form::Template("({l} / {r}) asFloat"))
+        .with(BinaryOperator::And, Form::Template("{l} and: [{r}]"))
+        .with(BinaryOperator::Or, Form::Template("{l} or: [{r}]"))
+        .concat(Form::Infix(","))
+        .to_string("{} printString")
+        .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "Transcript showCr: ({args} printString)"),
+        ("println/0", "Transcript cr"),
+        ("toString", "{0} printString"),
+        ("toNumber", "{0} asNumber"),
+        ("len", "{0} size"),
+        ("push", "({0} add: {1})"),
+        ("pop", "{0} removeLast"),
+        ("upper", "{0} asUppercase"),
+        ("lower", "{0} asLowercase"),
+        ("trim", "{0} trimSeparators"),
+        ("contains", "({0} includesSubstring: {1})"),
+        ("abs", "{0} abs"),
+        ("sqrt", "{0} sqrt"),
+        ("floor", "{0} floor"),
+        ("ceil", "{0} ceiling"),
+        ("round", "{0} rounded"),
+        ("pow", "({0} raisedTo: {1})"),
+        ("min", "({0} min: {1})"),
+        ("max", "({0} max: {1})"),
+        ("keys", "{0} keys"),
+        ("hasKey", "({0} includesKey: {1})"),
+    ])
+    .separator(" printString, ' ', ")
+    .placeholder("nil \"unsupported builtin\"");
+
+    fn emit_smalltalk_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&self.generate_method(function)?);
-            output.push('\n');
+            self.emit_method(out, function)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_methods(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("\"Cross-language block: {}\"\n", block.language));
-        
+
+    fn emit_cross_language_methods(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("\"Cross-language block: {}\"", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{}:\n", function.name));
-            output.push_str(&format!(
-                "    self error: 'Cross-language call to {}::{} not implemented'.\n\n",
-                block.language, function.name
-            ));
+            out.block(format!("{}:", function.name), |out| {
+                out.line(format!(
+                    "self error: 'Cross-language call to {}::{} not implemented'.",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_method(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_method(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
         // Method definition
-        if function.parameters.is_empty() {
-            output.push_str(&format!("{}\n", function.name));
-        } else {
-            output.push_str(&format!("{}:", function.name));
-            for (i, param) in function.parameters.iter().enumerate() {
-                if i == 0 {
-                    output.push_str(&format!(" {}", param.name));
-                } else {
-                    output.push_str(&format!(" {}:", param.name));
-                }
-            }
-            output.push('\n');
-        }
-        
-        // Method body
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("    ^ {}\n", self.generate_expression(expr)?));
+        let mut header = function.name.clone();
+        for (i, param) in function.parameters.iter().enumerate() {
+            if i == 0 {
+                header.push_str(&format!(": {}", param.name));
             } else {
-                output.push_str("    ^ 'function result'\n");
+                header.push_str(&format!(" {}:", param.name));
             }
-        } else {
-            output.push_str("    ^ 'empty function'\n");
         }
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} := {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} := nil", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("^ {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("^ nil".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
+
+        out.map(function.span);
+        out.block(header, |out| {
+            // Method body
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(format!("^ {}", self.generate_expression(expr)?)),
+                Some(_) => out.line("^ 'function result'"),
+                None => out.line("^ 'empty function'"),
             }
-            _ => Ok("\"Unsupported statement\"".to_string()),
-        }
+            Ok(())
+        })
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -9391,16 +9764,11 @@ impl SmalltalkTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("{} , {}", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("{} = {}", left_str, right_str)),
-                    _ => Ok(format!("({} \"unsupported op\" {})", left_str, right_str)),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -9415,8 +9783,34 @@ impl SmalltalkTransformer {
                     Ok(format!("self {}: {}", callee_str, args_str))
                 }
             }
-            _ => Ok("'Unsupported expression'".to_string()),
+            other => Ok(unsupported_expression(other, "'Unsupported expression'")),
+        }
+    }
+}
+
+impl StatementEmitter for SmalltalkTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} := {}.", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} := nil.", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("^ {}.", self.generate_expression(expr)?));
+                } else {
+                    out.line("^ nil.");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{}.", self.generate_expression(expression)?));
+            }
+            other => out.line(unsupported_statement(other, "\"Unsupported statement\"")),
         }
+        Ok(())
     }
 }
 
@@ -9424,134 +9818,139 @@ impl SmalltalkTransformer {
 pub struct PascalTransformer;
 
 impl Transformer for PascalTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("{ Generated by Utopia Compiler - Pascal Backend }\n");
-        output.push_str("{ Multi-language support with runtime integration }\n");
-        output.push_str("{ Structured programming with strong typing }\n\n");
-        
-        output.push_str("program UtopiaGenerated;\n\n");
-        output.push_str("uses\n");
-        output.push_str("  SysUtils, Classes;\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("  ", BlockStyle::INDENT);
+        out.line("{ Generated by Utopia Compiler - Pascal Backend }");
+        out.line("{ Multi-language support with runtime integration }");
+        out.line("{ Structured programming with strong typing }");
+        out.blank();
+        out.line("program UtopiaGenerated;");
+        out.blank();
+        out.block("uses", |out| out.line("SysUtils, Classes;"));
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "pascal" || block.language == "pas" || block.language == "main" {
-                output.push_str(&self.generate_pascal_functions(block)?);
+                self.emit_pascal_functions(&mut out, block)?;
             } else {
-                output.push_str(&self.generate_cross_language_functions(block)?);
+                self.emit_cross_language_functions(&mut out, block);
             }
         }
-        
+
         // Main execution
-        output.push_str("begin\n");
-        if !program.global_statements.is_empty() {
-            for statement in &program.global_statements {
-                output.push_str(&format!("  {};\n", self.generate_statement(statement)?));
-            }
-        }
-        output.push_str("end.\n");
-        
-        Ok(output)
+        out.block_with("begin", "end.", |out| self.emit_statements(out, &program.global_statements))?;
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "pascal"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".pas"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "pascal" | "pas")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "array", "as", "asm", "begin", "case", "class", "const", "constructor", "destructor",
+            "div", "do", "downto", "else", "end", "except", "exports", "file", "finalization", "finally",
+            "for", "function", "goto", "if", "implementation", "in", "inherited", "initialization", "inline",
+            "interface", "is", "label", "library", "mod", "nil", "not", "object", "of", "or", "out",
+            "packed", "procedure", "program", "property", "raise", "record", "repeat", "set", "shl", "shr",
+            "string", "then", "threadvar", "to", "try", "type", "unit", "until", "uses", "var", "while",
+            "with", "xor",
+        ], Escape::Suffix("_")).ignore_case()
+    }
 }
 
 impl PascalTransformer {
-    fn generate_pascal_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
-        for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("{{ Cross-language block: {} }}\n", block.language));
-        
+    /// Values are carried as strings, so numeric operators parse their
+    /// operands and format the result
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("FloatToStr(StrToFloat({l}) + StrToFloat({r}))"),
+        Form::Call("FloatToStr(StrToFloat({l}) - StrToFloat({r}))"),
+        Form::Call("FloatToStr(StrToFloat({l}) * StrToFloat({r}))"),
+        Form::Call("FloatToStr(StrToFloat({l}) / StrToFloat({r}))"),
+        Form::Call("FloatToStr(StrToFloat({l}) - StrToFloat({r}) * Trunc(StrToFloat({l}) / StrToFloat({r})))"),
+        Form::Template("StrToFloat({l}) = StrToFloat({r})"),
+        Form::Template("StrToFloat({l}) <> StrToFloat({r})"),
+        Form::Template("StrToFloat({l}) < StrToFloat({r})"),
+        Form::Template("StrToFloat({l}) <= StrToFloat({r})"),
+        Form::Template("StrToFloat({l}) > StrToFloat({r})"),
+        Form::Template("StrToFloat({l}) >= StrToFloat({r})"),
+        Form::Infix("and"),
+        Form::Infix("or"),
+    ])
+    .concat(Form::Infix("+"))
+    .strings([Form::Infix("="), Form::Infix("<>"), Form::Infix("<"), Form::Infix("<="), Form::Infix(">"), Form::Infix(">=")])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "WriteLn({args})"),
+        ("toString", "{0}"),
+        ("toNumber", "FloatToStr(StrToFloat({0}))"),
+        ("len", "IntToStr(Length({0}))"),
+        ("upper", "UpperCase({0})"),
+        ("lower", "LowerCase({0})"),
+        ("trim", "Trim({0})"),
+        ("contains", "(Pos({1}, {0}) > 0)"),
+        ("abs", "FloatToStr(Abs(StrToFloat({0})))"),
+        ("sqrt", "FloatToStr(Sqrt(StrToFloat({0})))"),
+        ("floor", "FloatToStr(Int(StrToFloat({0})))"),
+        ("round", "FloatToStr(Round(StrToFloat({0})))"),
+    ])
+    .separator(", ' ', ");
+
+    fn emit_pascal_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) -> Result<()> {
         for function in &block.functions {
-            output.push_str(&format!("function {}(args: string): string;\n", function.name));
-            output.push_str("begin\n");
-            output.push_str(&format!(
-                "  raise Exception.Create('Cross-language call to {}::{} not implemented');\n",
-                block.language, function.name
-            ));
-            output.push_str("end;\n\n");
-        }
-        
-        Ok(output)
-    }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
-        // Function definition
-        output.push_str(&format!("function {}(", function.name));
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str("; ");
-            }
-            output.push_str(&format!("{}: string", param.name));
-        }
-        
-        output.push_str("): string;\n");
-        output.push_str("begin\n");
-        
-        // Function body
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("  Result := {};\n", self.generate_expression(expr)?));
-            } else {
-                output.push_str("  Result := 'function result';\n");
-            }
-        } else {
-            output.push_str("  Result := 'empty function';\n");
+            self.emit_function(out, function)?;
+            out.blank();
         }
-        
-        output.push_str("end;\n");
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("{} := {}", name, self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("{} := ''", name))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("Result := {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("Result := ''".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                self.generate_expression(expression)
-            }
-            _ => Ok("{{ Unsupported statement }}".to_string()),
+
+        Ok(())
+    }
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock) {
+        out.line(format!("{{ Cross-language block: {} }}", block.language));
+
+        for function in &block.functions {
+            out.line(format!("function {}(args: string): string;", function.name));
+            out.block_with("begin", "end;", |out| {
+                out.line(format!(
+                    "raise Exception.Create('Cross-language call to {}::{} not implemented');",
+                    block.language, function.name
+                ));
+            });
+            out.blank();
         }
     }
-    
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) -> Result<()> {
+        let params: Vec<String> = function.parameters.iter().map(|param| format!("{}: string", param.name)).collect();
+
+        out.map(function.span);
+        out.line(format!("function {}({}): string;", function.name, params.join("; ")));
+        out.block_with("begin", "end;", |out| {
+            // Function body
+            match function.body.first() {
+                Some(Statement::Return { value: Some(expr), .. }) => out.line(format!("Result := {};", self.generate_expression(expr)?)),
+                Some(_) => out.line("Result := 'function result';"),
+                None => out.line("Result := 'empty function';"),
+            }
+            Ok(())
+        })
+    }
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -9563,16 +9962,11 @@ impl PascalTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(name.clone()),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("{} + {}", left_str, right_str)),
-                    BinaryOperator::Equal => Ok(format!("{} = {}", left_str, right_str)),
-                    _ => Ok(format!("(* unsupported: {} op {} *)", left_str, right_str)),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -9583,8 +9977,34 @@ impl PascalTransformer {
                 let args_str = args?.join(", ");
                 Ok(format!("{}({})", callee_str, args_str))
             }
-            _ => Ok("'Unsupported expression'".to_string()),
+            other => Ok(unsupported_expression(other, "'Unsupported expression'")),
+        }
+    }
+}
+
+impl StatementEmitter for PascalTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("{} := {};", name, self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("{} := '';", name));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("Result := {};", self.generate_expression(expr)?));
+                } else {
+                    out.line("Result := '';");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                out.line(format!("{};", self.generate_expression(expression)?));
+            }
+            other => out.line(unsupported_statement(other, "{ Unsupported statement }")),
         }
+        Ok(())
     }
 }
 
@@ -9592,138 +10012,147 @@ impl PascalTransformer {
 pub struct BasicTransformer;
 
 impl Transformer for BasicTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        output.push_str("REM Generated by Utopia Compiler - BASIC Backend\n");
-        output.push_str("REM Multi-language support with runtime integration\n");
-        output.push_str("REM Beginner's All-purpose Symbolic Instruction Code\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("", BlockStyle::INDENT);
+        out.line("REM Generated by Utopia Compiler - BASIC Backend");
+        out.line("REM Multi-language support with runtime integration");
+        out.line("REM Beginner's All-purpose Symbolic Instruction Code");
+        out.blank();
+
         let mut line_number = 10;
-        
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "basic" || block.language == "bas" || block.language == "main" {
-                let functions_code = self.generate_basic_functions(block, &mut line_number)?;
-                output.push_str(&functions_code);
+                self.emit_basic_functions(&mut out, block, &mut line_number)?;
             } else {
-                let cross_code = self.generate_cross_language_functions(block, &mut line_number)?;
-                output.push_str(&cross_code);
+                self.emit_cross_language_functions(&mut out, block, &mut line_number);
             }
         }
-        
+
         // Main execution
-        if !program.global_statements.is_empty() {
-            for statement in &program.global_statements {
-                output.push_str(&format!("{} {}\n", line_number, self.generate_statement(statement)?));
-                line_number += 10;
-            }
+        for statement in &program.global_statements {
+            let text = self.statement_text(&out, statement)?;
+            Self::numbered(&mut out, &mut line_number, text);
         }
-        
-        output.push_str(&format!("{} END\n", line_number));
-        
-        Ok(output)
+
+        Self::numbered(&mut out, &mut line_number, "END");
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "basic"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".bas"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         matches!(language, "basic" | "bas")
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "and", "as", "case", "dim", "do", "else", "end", "for", "function", "gosub", "goto", "if",
+            "input", "let", "loop", "mod", "next", "not", "or", "print", "rem", "return", "select", "step",
+            "sub", "then", "to", "until", "wend", "while",
+        ], Escape::Suffix("_")).ignore_case()
+    }
 }
 
 impl BasicTransformer {
-    fn generate_basic_functions(&self, block: &LanguageBlock, line_number: &mut i32) -> Result<String> {
-        let mut output = String::new();
-        
+    /// Variables are string variables, so numeric operators parse their
+    /// operands and format the result. `AND` and `OR` evaluate both sides.
+    const OPERATORS: Operators = Operators::new([
+        Form::Call("STR$(VAL({l}) + VAL({r}))"),
+        Form::Call("STR$(VAL({l}) - VAL({r}))"),
+        Form::Call("STR$(VAL({l}) * VAL({r}))"),
+        Form::Call("STR$(VAL({l}) / VAL({r}))"),
+        Form::Call("STR$(VAL({l}) - VAL({r}) * FIX(VAL({l}) / VAL({r})))"),
+        Form::Template("VAL({l}) = VAL({r})"),
+        Form::Template("VAL({l}) <> VAL({r})"),
+        Form::Template("VAL({l}) < VAL({r})"),
+        Form::Template("VAL({l}) <= VAL({r})"),
+        Form::Template("VAL({l}) > VAL({r})"),
+        Form::Template("VAL({l}) >= VAL({r})"),
+        Form::Infix("AND"),
+        Form::Infix("OR"),
+    ])
+    .concat(Form::Infix("+"))
+    .strings([Form::Infix("="), Form::Infix("<>"), Form::Infix("<"), Form::Infix("<="), Form::Infix(">"), Form::Infix(">=")])
+    .parenthesized();
+
+    const BUILTINS: Builtins = Builtins::new(&[
+        ("println", "PRINT {args}"),
+        ("println/0", "PRINT"),
+        ("toString", "{0}"),
+        ("toNumber", "STR$(VAL({0}))"),
+        ("len", "STR$(LEN({0}))"),
+        ("upper", "UCASE$({0})"),
+        ("lower", "LCASE$({0})"),
+        ("trim", "LTRIM$(RTRIM$({0}))"),
+        ("contains", "(INSTR({0}, {1}) > 0)"),
+        ("abs", "STR$(ABS(VAL({0})))"),
+        ("sqrt", "STR$(SQR(VAL({0})))"),
+        ("floor", "STR$(INT(VAL({0})))"),
+        ("round", "STR$(INT(VAL({0}) + 0.5))"),
+        ("pow", "STR$(VAL({0}) ^ VAL({1}))"),
+    ])
+    .separator("; \" \"; ")
+    .placeholder("\"\"");
+
+    /// Write `text` under the next line number
+    fn numbered(out: &mut CodeWriter, line_number: &mut i32, text: impl AsRef<str>) {
+        out.line(format!("{} {}", *line_number, text.as_ref()));
+        *line_number += 10;
+    }
+
+    fn emit_basic_functions(&self, out: &mut CodeWriter, block: &LanguageBlock, line_number: &mut i32) -> Result<()> {
         for function in &block.functions {
-            let func_code = self.generate_function(function, line_number)?;
-            output.push_str(&func_code);
-            output.push('\n');
+            self.emit_function(out, function, line_number)?;
+            out.blank();
         }
-        
-        Ok(output)
+
+        Ok(())
     }
-    
-    fn generate_cross_language_functions(&self, block: &LanguageBlock, line_number: &mut i32) -> Result<String> {
-        let mut output = String::new();
-        output.push_str(&format!("{} REM Cross-language block: {}\n", *line_number, block.language));
-        *line_number += 10;
-        
+
+    fn emit_cross_language_functions(&self, out: &mut CodeWriter, block: &LanguageBlock, line_number: &mut i32) {
+        Self::numbered(out, line_number, format!("REM Cross-language block: {}", block.language));
+
         for function in &block.functions {
-            output.push_str(&format!("{} REM Function: {}\n", *line_number, function.name));
-            *line_number += 10;
-            output.push_str(&format!("{} PRINT \"Cross-language call to {}::{} not implemented\"\n", 
-                *line_number, block.language, function.name));
-            *line_number += 10;
-            output.push_str(&format!("{} RETURN\n", *line_number));
-            *line_number += 10;
-            output.push('\n');
+            Self::numbered(out, line_number, format!("REM Function: {}", function.name));
+            Self::numbered(out, line_number, format!(
+                "PRINT \"Cross-language call to {}::{} not implemented\"",
+                block.language, function.name
+            ));
+            Self::numbered(out, line_number, "RETURN");
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function, line_number: &mut i32) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function, line_number: &mut i32) -> Result<()> {
         // Function label
-        output.push_str(&format!("{} REM Function: {}\n", *line_number, function.name));
-        *line_number += 10;
-        
+        out.map(function.span);
+        Self::numbered(out, line_number, format!("REM Function: {}", function.name));
+
         // Function body
-        if let Some(first_stmt) = function.body.first() {
-            if let Statement::Return { value: Some(expr), .. } = first_stmt {
-                output.push_str(&format!("{} RESULT$ = {}\n", *line_number, self.generate_expression(expr)?));
-                *line_number += 10;
-            } else {
-                output.push_str(&format!("{} RESULT$ = \"function result\"\n", *line_number));
-                *line_number += 10;
-            }
-        } else {
-            output.push_str(&format!("{} RESULT$ = \"empty function\"\n", *line_number));
-            *line_number += 10;
-        }
-        
-        output.push_str(&format!("{} RETURN\n", *line_number));
-        *line_number += 10;
-        
-        Ok(output)
-    }
-    
-    fn generate_statement(&self, statement: &Statement) -> Result<String> {
-        match statement {
-            Statement::VariableDeclaration { name, value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("LET {}$ = {}", name.to_uppercase(), self.generate_expression(expr)?))
-                } else {
-                    Ok(format!("LET {}$ = \"\"", name.to_uppercase()))
-                }
-            }
-            Statement::Return { value, .. } => {
-                if let Some(expr) = value {
-                    Ok(format!("RESULT$ = {}", self.generate_expression(expr)?))
-                } else {
-                    Ok("RESULT$ = \"\"".to_string())
-                }
-            }
-            Statement::Expression { expression, .. } => {
-                match expression {
-                    Expression::Call { .. } => {
-                        Ok(format!("PRINT {}", self.generate_expression(expression)?))
-                    }
-                    _ => self.generate_expression(expression)
-                }
+        match function.body.first() {
+            Some(Statement::Return { value: Some(expr), .. }) => {
+                Self::numbered(out, line_number, format!("RESULT$ = {}", self.generate_expression(expr)?));
             }
-            _ => Ok("REM Unsupported statement".to_string()),
+            Some(_) => Self::numbered(out, line_number, "RESULT$ = \"function result\""),
+            None => Self::numbered(out, line_number, "RESULT$ = \"empty function\""),
         }
+
+        Self::numbered(out, line_number, "RETURN");
+        Ok(())
     }
-    
+
     fn generate_expression(&self, expression: &Expression) -> Result<String> {
         match expression {
             Expression::Literal { value, .. } => {
@@ -9735,15 +10164,11 @@ impl BasicTransformer {
                 }
             }
             Expression::Identifier { name, .. } => Ok(format!("{}$", name.to_uppercase())),
-            Expression::Binary { left, operator, right, .. } => {
-                let left_str = self.generate_expression(left)?;
-                let right_str = self.generate_expression(right)?;
-                match operator {
-                    BinaryOperator::Add => Ok(format!("{} + {}", left_str, right_str)),
-                    _ => Ok(format!("REM unsupported: {} op {}", left_str, right_str)),
-                }
-            }
+            Expression::Binary { .. } => Self::OPERATORS.binary(expression, &mut |operand| self.generate_expression(operand)),
             Expression::Call { callee, arguments, .. } => {
+                if let Some(code) = Self::BUILTINS.call(expression, &mut |argument| self.generate_expression(argument))? {
+                    return Ok(code);
+                }
                 let callee_str = match callee.as_ref() {
                     Expression::Identifier { name, .. } => name.clone(),
                     _ => self.generate_expression(callee)?,
@@ -9754,8 +10179,39 @@ impl BasicTransformer {
                 let args_str = args?.join(" + ");
                 Ok(format!("\"{}({})\"", callee_str, args_str))
             }
-            _ => Ok("\"Unsupported expression\"".to_string()),
+            other => Ok(unsupported_expression(other, "\"Unsupported expression\"")),
+        }
+    }
+}
+
+impl StatementEmitter for BasicTransformer {
+    fn write_statement(&self, out: &mut CodeWriter, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::VariableDeclaration { name, value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("LET {}$ = {}", name.to_uppercase(), self.generate_expression(expr)?));
+                } else {
+                    out.line(format!("LET {}$ = \"\"", name.to_uppercase()));
+                }
+            }
+            Statement::Return { value, .. } => {
+                if let Some(expr) = value {
+                    out.line(format!("RESULT$ = {}", self.generate_expression(expr)?));
+                } else {
+                    out.line("RESULT$ = \"\"");
+                }
+            }
+            Statement::Expression { expression, .. } => {
+                match expression {
+                    Expression::Call { .. } if !builtins::is_builtin_call(expression) => {
+                        out.line(format!("PRINT {}", self.generate_expression(expression)?));
+                    }
+                    _ => out.line(self.generate_expression(expression)?),
+                }
+            }
+            other => out.line(unsupported_statement(other, "REM Unsupported statement")),
         }
+        Ok(())
     }
 }
 
@@ -9763,115 +10219,121 @@ impl BasicTransformer {
 pub struct CudaTransformer;
 
 impl Transformer for CudaTransformer {
-    fn transform(&self, _program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("// Generated by Utopia Compiler - CUDA Backend\n");
-        output.push_str("// GPU acceleration with NVIDIA CUDA\n\n");
-        
-        output.push_str("#include <cuda_runtime.h>\n");
-        output.push_str("#include <cuda.h>\n");
-        output.push_str("#include <device_launch_parameters.h>\n");
-        output.push_str("#include <stdio.h>\n");
-        output.push_str("#include <stdlib.h>\n\n");
-        
-        output.push_str("#define CUDA_CHECK(call) \\\n");
-        output.push_str("    do { \\\n");
-        output.push_str("        cudaError_t err = call; \\\n");
-        output.push_str("        if (err != cudaSuccess) { \\\n");
-        output.push_str("            fprintf(stderr, \"CUDA error at %s:%d - %s\\n\", __FILE__, __LINE__, cudaGetErrorString(err)); \\\n");
-        output.push_str("            exit(1); \\\n");
-        output.push_str("        } \\\n");
-        output.push_str("    } while(0)\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - CUDA Backend");
+        out.line("// GPU acceleration with NVIDIA CUDA");
+        out.blank();
+        out.line("#include <cuda_runtime.h>");
+        out.line("#include <cuda.h>");
+        out.line("#include <device_launch_parameters.h>");
+        out.line("#include <stdio.h>");
+        out.line("#include <stdlib.h>");
+        out.blank();
+        out.line("#define CUDA_CHECK(call) \\");
+        out.indented(|out| {
+            out.line("do { \\");
+            out.indented(|out| {
+                out.line("cudaError_t err = call; \\");
+                out.line("if (err != cudaSuccess) { \\");
+                out.indented(|out| {
+                    out.line("fprintf(stderr, \"CUDA error at %s:%d - %s\\n\", __FILE__, __LINE__, cudaGetErrorString(err)); \\");
+                    out.line("exit(1); \\");
+                });
+                out.line("} \\");
+            });
+            out.line("} while(0)");
+        });
+        out.blank();
+
         // Process language blocks
-        for block in &_program.language_blocks {
+        for block in &program.language_blocks {
             if block.language == "cuda" {
-                output.push_str(&self.generate_cuda_block(block)?);
+                self.emit_cuda_block(&mut out, block);
             } else {
-                output.push_str(&format!("// Cross-language block: {}\n", block.language));
+                out.line(format!("// Cross-language block: {}", block.language));
             }
         }
-        
+
         // Main function for CUDA setup
-        output.push_str("int main() {\n");
-        output.push_str("    int device_count = 0;\n");
-        output.push_str("    CUDA_CHECK(cudaGetDeviceCount(&device_count));\n");
-        output.push_str("    printf(\"Found %d CUDA devices\\n\", device_count);\n");
-        output.push_str("    \n");
-        output.push_str("    if (device_count > 0) {\n");
-        output.push_str("        CUDA_CHECK(cudaSetDevice(0));\n");
-        output.push_str("        printf(\"Hello from Utopia CUDA!\\n\");\n");
-        output.push_str("        // Your kernel calls here\n");
-        output.push_str("    }\n");
-        output.push_str("    \n");
-        output.push_str("    return 0;\n");
-        output.push_str("}\n");
-        
-        Ok(output)
+        out.block("int main()", |out| {
+            out.line("int device_count = 0;");
+            out.line("CUDA_CHECK(cudaGetDeviceCount(&device_count));");
+            out.line("printf(\"Found %d CUDA devices\\n\", device_count);");
+            out.blank();
+            out.block("if (device_count > 0)", |out| {
+                out.line("CUDA_CHECK(cudaSetDevice(0));");
+                out.line("printf(\"Hello from Utopia CUDA!\\n\");");
+                out.line("// Your kernel calls here");
+            });
+            out.blank();
+            out.line("return 0;");
+        });
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "cuda"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".cu"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "cuda" || language == "gpu"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor", "bool", "break", "case",
+            "catch", "char", "char16_t", "char32_t", "class", "compl", "const", "const_cast", "constexpr",
+            "continue", "decltype", "default", "delete", "do", "double", "dynamic_cast", "else", "enum",
+            "explicit", "export", "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int",
+            "long", "mutable", "namespace", "new", "noexcept", "not", "not_eq", "nullptr", "operator", "or",
+            "or_eq", "private", "protected", "public", "register", "reinterpret_cast", "restrict", "return",
+            "short", "signed", "sizeof", "static", "static_assert", "static_cast", "struct", "switch",
+            "template", "this", "thread_local", "throw", "true", "try", "typedef", "typeid", "typename",
+            "union", "unsigned", "using", "virtual", "void", "volatile", "wchar_t", "while", "xor", "xor_eq",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl CudaTransformer {
-    fn generate_cuda_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    fn emit_cuda_block(&self, out: &mut CodeWriter, block: &LanguageBlock) {
         for function in &block.functions {
-            output.push_str(&self.generate_kernel(function)?);
-            output.push('\n');
+            self.emit_kernel(out, function);
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_kernel(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_kernel(&self, out: &mut CodeWriter, function: &Function) {
+        let params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+
         // Kernel definition
-        output.push_str("__global__ void ");
-        output.push_str(&function.name);
-        output.push('(');
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
+        out.map(function.span);
+        out.block(format!("__global__ void {}({})", function.name, params.join(", ")), |out| {
+            // Thread index calculations
+            out.line("int idx = blockIdx.x * blockDim.x + threadIdx.x;");
+            out.line("int stride = blockDim.x * gridDim.x;");
+            out.blank();
+
+            // Function body
+            if function.body.is_empty() {
+                out.line("// Empty kernel");
+            } else {
+                out.line("// Kernel body");
             }
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-            output.push(' ');
-            output.push_str(&param.name);
-        }
-        
-        output.push_str(") {\n");
-        
-        // Thread index calculations
-        output.push_str("    int idx = blockIdx.x * blockDim.x + threadIdx.x;\n");
-        output.push_str("    int stride = blockDim.x * gridDim.x;\n");
-        output.push_str("    \n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    // Empty kernel\n");
-        } else {
-            output.push_str("    // Kernel body\n");
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
+        });
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "float".to_string(),
@@ -9887,126 +10349,121 @@ impl CudaTransformer {
 pub struct EmbeddedCTransformer;
 
 impl Transformer for EmbeddedCTransformer {
-    fn transform(&self, program: &Program) -> Result<String> {
-        let mut output = String::new();
-        
-        output.push_str("// Generated by Utopia Compiler - Embedded C Backend\n");
-        output.push_str("// Optimized for microcontrollers and IoT devices\n\n");
-        
-        output.push_str("#include <stdint.h>\n");
-        output.push_str("#include <stdbool.h>\n");
-        output.push_str("#include <string.h>\n");
-        output.push_str("#ifdef ARDUINO\n");
-        output.push_str("#include <Arduino.h>\n");
-        output.push_str("#endif\n\n");
-        
+    fn generate(&self, program: &Program) -> Result<String> {
+        let mut out = CodeWriter::new("    ", BlockStyle::BRACES);
+        out.line("// Generated by Utopia Compiler - Embedded C Backend");
+        out.line("// Optimized for microcontrollers and IoT devices");
+        out.blank();
+        out.line("#include <stdint.h>");
+        out.line("#include <stdbool.h>");
+        out.line("#include <string.h>");
+        out.line("#ifdef ARDUINO");
+        out.line("#include <Arduino.h>");
+        out.line("#endif");
+        out.blank();
+
         // Memory-efficient value type for embedded systems
-        output.push_str("typedef union {\n");
-        output.push_str("    int32_t i;\n");
-        output.push_str("    float f;\n");
-        output.push_str("    bool b;\n");
-        output.push_str("    char* s;\n");
-        output.push_str("} utopia_value_t;\n\n");
-        
-        output.push_str("typedef struct {\n");
-        output.push_str("    utopia_value_t value;\n");
-        output.push_str("    uint8_t type; // 0=int, 1=float, 2=bool, 3=string\n");
-        output.push_str("} utopia_typed_value_t;\n\n");
-        
+        out.block_with("typedef union", "} utopia_value_t;", |out| {
+            out.line("int32_t i;");
+            out.line("float f;");
+            out.line("bool b;");
+            out.line("char* s;");
+        });
+        out.blank();
+        out.block_with("typedef struct", "} utopia_typed_value_t;", |out| {
+            out.line("utopia_value_t value;");
+            out.line("uint8_t type; // 0=int, 1=float, 2=bool, 3=string");
+        });
+        out.blank();
+
         // Process language blocks
         for block in &program.language_blocks {
             if block.language == "embedded_c" || block.language == "embedded-c" {
-                output.push_str(&self.generate_embedded_block(block)?);
+                self.emit_embedded_block(&mut out, block);
             } else {
-                output.push_str(&format!("// Cross-language block: {} (not supported in embedded)\n", block.language));
+                out.line(format!("// Cross-language block: {} (not supported in embedded)", block.language));
             }
         }
-        
+
         // Main function for embedded
-        output.push_str("int main(void) {\n");
-        output.push_str("#ifdef ARDUINO\n");
-        output.push_str("    setup();\n");
-        output.push_str("    while(1) {\n");
-        output.push_str("        loop();\n");
-        output.push_str("    }\n");
-        output.push_str("#else\n");
-        output.push_str("    // Embedded main loop\n");
-        output.push_str("    while(1) {\n");
-        output.push_str("        // Your embedded code here\n");
-        output.push_str("    }\n");
-        output.push_str("#endif\n");
-        output.push_str("    return 0;\n");
-        output.push_str("}\n");
-        
-        Ok(output)
+        out.block("int main(void)", |out| {
+            // Preprocessor lines stay in column one
+            let directive = |out: &mut CodeWriter, text: &str| {
+                out.dedent();
+                out.line(text);
+                out.indent();
+            };
+            directive(out, "#ifdef ARDUINO");
+            out.line("setup();");
+            out.block("while(1)", |out| out.line("loop();"));
+            directive(out, "#else");
+            out.line("// Embedded main loop");
+            out.block("while(1)", |out| out.line("// Your embedded code here"));
+            directive(out, "#endif");
+            out.line("return 0;");
+        });
+
+        Ok(out.finish())
     }
-    
+
     fn target_name(&self) -> &str {
         "embedded-c"
     }
-    
+
     fn file_extension(&self) -> &str {
         ".c"
     }
-    
+
     fn supports_language(&self, language: &str) -> bool {
         language == "embedded_c" || language == "embedded-c" || language == "arduino"
     }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::without(&[Feature::Classes, Feature::Lambdas, Feature::Exceptions, Feature::DynamicObjects])
+    }
+
+    fn keywords(&self) -> Keywords {
+        Keywords::new(&[
+            "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+            "extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict",
+            "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+            "unsigned", "void", "volatile", "while",
+        ], Escape::Suffix("_"))
+    }
 }
 
 impl EmbeddedCTransformer {
-    fn generate_embedded_block(&self, block: &LanguageBlock) -> Result<String> {
-        let mut output = String::new();
-        
+    fn emit_embedded_block(&self, out: &mut CodeWriter, block: &LanguageBlock) {
         for function in &block.functions {
-            output.push_str(&self.generate_function(function)?);
-            output.push('\n');
+            self.emit_function(out, function);
+            out.blank();
         }
-        
-        Ok(output)
     }
-    
-    fn generate_function(&self, function: &Function) -> Result<String> {
-        let mut output = String::new();
-        
+
+    fn emit_function(&self, out: &mut CodeWriter, function: &Function) {
         // Function signature with memory-efficient types
-        if let Some(ref return_type) = function.return_type {
-            output.push_str(&self.convert_type(Some(return_type)));
-        } else {
-            output.push_str("void");
-        }
-        
-        output.push(' ');
-        output.push_str(&function.name);
-        output.push('(');
-        
-        // Parameters
-        for (i, param) in function.parameters.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
+        let return_type = match function.return_type {
+            Some(ref return_type) => self.convert_type(Some(return_type)),
+            None => "void".to_string(),
+        };
+        let mut params: Vec<String> = function.parameters.iter()
+            .map(|param| format!("{} {}", self.convert_type(param.param_type.as_ref()), param.name))
+            .collect();
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+
+        out.map(function.span);
+        out.block(format!("{} {}({})", return_type, function.name, params.join(", ")), |out| {
+            // Function body
+            if function.body.is_empty() {
+                out.line("// Empty function");
+            } else {
+                out.line(format!("{}// Function body", unsupported_body(function)));
             }
-            output.push_str(&self.convert_type(param.param_type.as_ref()));
-            output.push(' ');
-            output.push_str(&param.name);
-        }
-        
-        if function.parameters.is_empty() {
-            output.push_str("void");
-        }
-        
-        output.push_str(") {\n");
-        
-        // Function body
-        if function.body.is_empty() {
-            output.push_str("    // Empty function\n");
-        } else {
-            output.push_str("    // Function body\n");
-        }
-        
-        output.push_str("}\n");
-        Ok(output)
+        });
     }
-    
+
     fn convert_type(&self, utopia_type: Option<&crate::types::Type>) -> String {
         match utopia_type {
             Some(crate::types::Type::Number) => "float".to_string(),
@@ -10020,13 +10477,21 @@ impl EmbeddedCTransformer {
 }
 
 /// Backend manager that handles all code generation
+///
+/// Backends are kept in registration order so that listing them, and anything
+/// built from the listing, comes out the same on every run.
 pub struct TransformerManager {
-    backends: HashMap<String, Box<dyn Transformer>>,
+    backends: IndexMap<String, Box<dyn Transformer>>,
+    /// Targets loaded from template directories, kept so that other
+    /// managers (one per thread in parallel builds) can register them too
+    template_targets: Vec<TemplateTransformer>,
+    /// Plugin targets, kept for the same reason
+    plugins: Vec<PluginTransformer>,
 }
 
 impl TransformerManager {
     pub fn new() -> Self {
-        let mut backends: HashMap<String, Box<dyn Transformer>> = HashMap::new();
+        let mut backends: IndexMap<String, Box<dyn Transformer>> = IndexMap::new();
         
         // Add all backends
         backends.insert("python".to_string(), Box::new(PythonTransformer));
@@ -10181,10 +10646,54 @@ impl TransformerManager {
         backends.insert("solidity".to_string(), Box::new(JavaScriptTransformer::new(false)));
         backends.insert("sol".to_string(), Box::new(JavaScriptTransformer::new(false)));
         
-        Self { backends }
+        Self { backends, template_targets: Vec::new(), plugins: Vec::new() }
+    }
+    
+    /// Register a backend under `name`, replacing any existing backend of that name
+    pub fn register(&mut self, name: &str, backend: Box<dyn Transformer>) {
+        self.backends.insert(name.to_string(), backend);
+    }
+
+    /// Load a user-defined target from a template directory (see `templates`)
+    /// and register it under its name, which is returned
+    pub fn load_target_dir(&mut self, dir: impl AsRef<Path>) -> Result<String> {
+        let target = TemplateTransformer::load(dir)?;
+        let name = target.target_name().to_string();
+        self.add_template_target(target);
+        Ok(name)
+    }
+
+    /// Register a target loaded from a template directory under its name
+    pub fn add_template_target(&mut self, target: TemplateTransformer) {
+        self.register(target.target_name(), Box::new(target.clone()));
+        self.template_targets.push(target);
+    }
+
+    /// Targets loaded from template directories, in loading order
+    pub fn template_targets(&self) -> &[TemplateTransformer] {
+        &self.template_targets
+    }
+
+    /// Start the plugin of a `plugin:<executable>` target (see `plugin`) and
+    /// register it under that target
+    pub fn load_plugin(&mut self, target: &str) -> Result<()> {
+        let plugin = PluginTransformer::load(target)?;
+        self.add_plugin(plugin);
+        Ok(())
+    }
+
+    /// Register a plugin under its `plugin:<executable>` target
+    pub fn add_plugin(&mut self, plugin: PluginTransformer) {
+        self.register(plugin.target(), Box::new(plugin.clone()));
+        self.plugins.push(plugin);
+    }
+
+    /// Plugins loaded so far, in loading order
+    pub fn plugins(&self) -> &[PluginTransformer] {
+        &self.plugins
     }
     
-    pub fn transform(&self, target: &str, program: &Program) -> Result<String> {
+    pub fn transform(&self, target: &str, program: &Program) -> Result<TransformOutput> {
         if let Some(backend) = self.backends.get(target) {
             backend.transform(program)
         } else {
@@ -10199,6 +10708,31 @@ impl TransformerManager {
     pub fn get_file_extension(&self, target: &str) -> Option<String> {
         self.backends.get(target).map(|b| b.file_extension().to_string())
     }
+
+    /// Whether `target` runs tail calls without growing the stack
+    pub fn eliminates_tail_calls(&self, target: &str) -> bool {
+        self.backends.get(target).is_some_and(|b| b.eliminates_tail_calls())
+    }
+
+    /// Features `target` can express, if it is registered
+    pub fn capabilities(&self, target: &str) -> Option<Capabilities> {
+        self.backends.get(target).map(|b| b.capabilities())
+    }
+
+    /// Uses of features `target` lacks in the code it generates for `program`,
+    /// if the target is registered
+    pub fn check_capabilities(&self, target: &str, program: &Program) -> Option<Vec<Diagnostic>> {
+        self.backends.get(target).map(|b| {
+            b.capabilities().check(program, target, |language| b.generates_block(language), b.generates_global_code())
+        })
+    }
+
+    /// Every backend under its own name, without aliases, with its capabilities
+    pub fn capability_matrix(&self) -> BTreeMap<String, Capabilities> {
+        self.backends.values()
+            .map(|b| (b.target_name().to_string(), b.capabilities()))
+            .collect()
+    }
 }
 
 impl Default for TransformerManager {
@@ -10221,11 +10755,48 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("#!/usr/bin/env python3"));
         assert!(code.contains("Generated by Utopia Compiler"));
     }
 
+    #[test]
+    fn test_unsupported_constructs_are_reported() {
+        let source = "@lang python {\n    let xs = [1, 2];\n    print(len(xs));\n}\n";
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+
+        let output = PythonTransformer.transform(&program).unwrap();
+        assert!(output.is_partial());
+        assert!(output.code().contains("xs = None  # Unsupported expression"), "{}", output.code());
+        assert_eq!(output.files[0].path, PathBuf::from("main.py"));
+        assert_eq!(output.metadata["target"], "python");
+        let diagnostic = &output.diagnostics[0];
+        assert_eq!(diagnostic.message, "python backend cannot translate an array literal");
+        assert_eq!((diagnostic.span.line, diagnostic.span.column), (2, 14));
+
+        let complete = PythonTransformer.transform(&Program::new(Span::new(0, 0, 1, 1))).unwrap();
+        assert!(!complete.is_partial());
+    }
+
+    #[test]
+    fn test_nested_blocks_are_indented() {
+        let source = "@lang main {\n    function f(n) {\n        if (n > 1) {\n            if (n > 2) {\n                return 1;\n            } else {\n                return 2;\n            }\n        }\n        return 0;\n    }\n}\n";
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        let program = crate::parser::Parser::new(tokens).parse().unwrap();
+
+        let python = PythonTransformer.transform(&program).unwrap();
+        assert!(python.code().contains(
+            "def f(n):\n    if n > 1:\n        if n > 2:\n            return 1\n        else:\n            return 2\n    return 0\n"
+        ), "{}", python.code());
+
+        let nim = NimTransformer.transform(&program).unwrap();
+        assert!(nim.code().contains(
+            "  if (parseFloat(n) > parseFloat(\"1\")):\n    if (parseFloat(n) > parseFloat(\"2\")):\n      return \"1\"\n    else:\n      return \"2\"\n  return \"0\"\n"
+        ), "{}", nim.code());
+    }
+
     #[test]
     fn test_javascript_backend() {
         let backend = JavaScriptTransformer::new(false);
@@ -10235,7 +10806,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("JavaScript Backend"));
     }
 
@@ -10261,7 +10833,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - C# Backend"));
         assert!(code.contains("using System;"));
         assert!(code.contains("namespace Utopia.Generated"));
@@ -10277,7 +10850,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("' Generated by Utopia Compiler - Visual Basic .NET Backend"));
         assert!(code.contains("Imports System"));
         assert!(code.contains("Namespace Utopia.Generated"));
@@ -10293,7 +10867,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("-- Generated by Utopia Compiler - Ada Backend"));
         assert!(code.contains("with Ada.Text_IO;"));
         assert!(code.contains("package Utopia_Generated is"));
@@ -10309,7 +10884,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("{ Generated by Utopia Compiler - Delphi/Object Pascal Backend }"));
         assert!(code.contains("unit UtopiaGenerated;"));
         assert!(code.contains("interface"));
@@ -10326,7 +10902,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("-- Generated by Utopia Compiler - SQL Backend"));
         assert!(code.contains("-- Database schema and procedures"));
         assert!(code.contains("-- Note: Adapt connection strings"));
@@ -10341,7 +10918,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - R Backend"));
         assert!(code.contains("library(jsonlite)"));
         assert!(code.contains("library(httr)"));
@@ -10356,7 +10934,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("% Generated by Utopia Compiler - MATLAB Backend"));
         assert!(code.contains("% Multi-language support"));
     }
@@ -10370,7 +10949,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Kotlin Backend"));
         assert!(code.contains("package utopia.generated"));
         assert!(code.contains("import kotlinx.coroutines.*"));
@@ -10385,7 +10965,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Swift Backend"));
         assert!(code.contains("import Foundation"));
         assert!(code.contains("import UIKit"));
@@ -10401,7 +10982,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("* Generated by Utopia Compiler - COBOL Backend"));
         assert!(code.contains("IDENTIFICATION DIVISION"));
         assert!(code.contains("PROGRAM-ID. UTOPIA-GENERATED"));
@@ -10418,7 +11000,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - Ruby Backend"));
         assert!(code.contains("require 'json'"));
         assert!(code.contains("require 'net/http'"));
@@ -10434,7 +11017,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains(";; Generated by Utopia Compiler - Lisp Backend"));
         assert!(code.contains("(require 'cl-lib)"));
         assert!(code.contains("(require 'json)"));
@@ -10450,7 +11034,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("% Generated by Utopia Compiler - Prolog Backend"));
         assert!(code.contains(":- use_module(library(lists))"));
         assert!(code.contains("% Logic programming"));
@@ -10465,7 +11050,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Dart Backend"));
         assert!(code.contains("import 'dart:convert';"));
         assert!(code.contains("import 'package:flutter/material.dart';"));
@@ -10481,7 +11067,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("-- Generated by Utopia Compiler - Lua Backend"));
         assert!(code.contains("local json = require('json')"));
         assert!(code.contains("-- Game scripting"));
@@ -10496,7 +11083,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("-- Generated by Utopia Compiler - Haskell Backend"));
         assert!(code.contains("module UtopiaGenerated where"));
         assert!(code.contains("import Data.List"));
@@ -10512,7 +11100,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Objective-C Backend"));
         assert!(code.contains("#import <Foundation/Foundation.h>"));
         assert!(code.contains("@interface UtopiaRuntime : NSObject"));
@@ -10529,7 +11118,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Scala Backend"));
         assert!(code.contains("package utopia.generated"));
         assert!(code.contains("import scala.collection.mutable"));
@@ -10546,7 +11136,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - Julia Backend"));
         assert!(code.contains("using LinearAlgebra"));
         assert!(code.contains("using Statistics"));
@@ -10562,7 +11153,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("' Generated by Utopia Compiler - VBScript Backend"));
         assert!(code.contains("' Windows automation"));
     }
@@ -10576,7 +11168,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("#!/bin/bash"));
         assert!(code.contains("# Generated by Utopia Compiler - Bash Backend"));
         assert!(code.contains("# Shell scripting"));
@@ -10592,7 +11185,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - Nim Backend"));
         assert!(code.contains("import json, strutils"));
         assert!(code.contains("# Efficient compiled language"));
@@ -10607,7 +11201,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - Crystal Backend"));
         assert!(code.contains("require \"json\""));
         assert!(code.contains("# Ruby-like syntax"));
@@ -10622,7 +11217,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - Zig Backend"));
         assert!(code.contains("const std = @import(\"std\");"));
         assert!(code.contains("// Systems programming"));
@@ -10637,7 +11233,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("# Generated by Utopia Compiler - Elixir Backend"));
         assert!(code.contains("defmodule UtopiaGenerated do"));
         assert!(code.contains("# Actor model concurrency"));
@@ -10652,7 +11249,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - F# Backend"));
         assert!(code.contains("module UtopiaGenerated"));
         assert!(code.contains("// Functional-first .NET"));
@@ -10667,7 +11265,8 @@ mod tests {
         let result = backend.transform(&program);
         assert!(result.is_ok());
         
-        let code = result.unwrap();
+        let output = result.unwrap();
+        let code = output.code();
         assert!(code.contains("// Generated by Utopia Compiler - C++ Backend"));
         assert!(code.contains("#include <iostream>"));
         assert!(code.contains("class UtopiaRuntime"));
@@ -10748,4 +11347,21 @@ mod tests {
         assert_eq!(manager.get_file_extension("llvm"), Some(".ll".to_string()));
         assert_eq!(manager.get_file_extension("wasm"), Some(".wat".to_string()));
     }
-} 
\ No newline at end of file
+
+    /// `printf` formats that disagree with their arguments only show when the
+    /// program runs, so the C output is built and run
+    #[test]
+    fn test_c_output_runs_with_matching_formats() {
+        let source = include_str!("../../hello_world.uto");
+        for level in [0, 2] {
+            let mut config = crate::Config::new();
+            config.optimization_level = level;
+            let options = crate::pipeline::PipelineOptions::new("c").bundle_runtime(true);
+            let output = crate::Compiler::new(config).run(source, &options, &mut crate::pipeline::NoHooks).unwrap();
+            let Some(printed) = runtime::run_c(&format!("hello-{}", level), &output.code.unwrap()) else {
+                return;
+            };
+            assert_eq!(printed, "Hello, World!\nWelcome to Utopia!\nHello, Utopia User\nSum of 5 and 3 is 8\nCompiler working: 1\n", "-O{}", level);
+        }
+    }
+}
@@ -6,6 +6,7 @@
 use crate::{ast::Program, diagnostics::Diagnostic, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use indexmap::IndexMap;
 
 /// Type representation in Utopia
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,7 +20,7 @@ pub enum Type {
     
     // Composite types
     Array(Box<Type>),
-    Object(HashMap<String, Type>),
+    Object(IndexMap<String, Type>),
     Function {
         parameters: Vec<Type>,
         return_type: Box<Type>,
@@ -275,33 +276,69 @@ impl TypeSystem {
     }
 
     /// Check types for a program
+    ///
+    /// Returns a copy of the program in which variable declarations without an
+    /// annotation carry their inferred type, when one can be inferred.
     pub fn check(&self, program: &Program) -> Result<Program> {
-        // This would implement full type checking
-        // For now, return the program as-is
-        Ok(program.clone())
+        Ok(self.check_program(program).0)
     }
 
     /// Validate types in a program
     pub fn validate(&self, program: &Program) -> Result<Vec<Diagnostic>> {
-        let mut diagnostics = Vec::new();
-        
-        // Type checking logic would go here
-        // For now, return empty diagnostics
-        
-        Ok(diagnostics)
+        Ok(self.check_program(program).1)
+    }
+
+    /// Annotate a copy of the program with inferred types and report type errors
+    pub fn check_program(&self, program: &Program) -> (Program, Vec<Diagnostic>) {
+        self.check_with(program, false)
+    }
+
+    /// Annotate a copy of the program for code generation: besides the
+    /// declarations, every identifier carries the type of the variable or
+    /// function it refers to in its scope, which operators and builtins are
+    /// lowered by
+    pub fn annotate(&self, program: &Program) -> Program {
+        self.check_with(program, true).0
+    }
+
+    fn check_with(&self, program: &Program, resolve_identifiers: bool) -> (Program, Vec<Diagnostic>) {
+        let mut typed = program.clone();
+        let mut checker = TypeChecker::new(self, program);
+        checker.resolve_identifiers = resolve_identifiers;
+
+        let mut globals = checker.scope_for(None);
+        checker.check_statements(&mut typed.global_statements, &mut globals, None);
+
+        for block in &mut typed.language_blocks {
+            checker.language = Some(block.language.clone());
+            let mut env = checker.scope_for(Some(&block.language));
+            for function in &mut block.functions {
+                checker.check_function(&function.parameters, function.return_type.as_ref(), &mut function.body, &env);
+            }
+            checker.check_statements(&mut block.statements, &mut env, None);
+        }
+
+        (typed, checker.diagnostics)
     }
 
     /// Infer the type of an expression
     pub fn infer_type(&self, expression: &crate::ast::Expression) -> Type {
+        self.infer_type_in(expression, &TypeEnvironment::new())
+    }
+
+    /// Infer the type of an expression, looking identifiers up in `env`
+    pub fn infer_type_in(&self, expression: &crate::ast::Expression, env: &TypeEnvironment) -> Type {
+        use crate::ast::{BinaryOperator, Expression, UnaryOperator};
+
         match expression {
-            crate::ast::Expression::Literal { value, .. } => value.type_hint(),
-            crate::ast::Expression::Identifier { .. } => Type::Unknown, // Would look up in environment
-            crate::ast::Expression::Binary { left, operator, right, .. } => {
-                use crate::ast::BinaryOperator;
-                let left_type = self.infer_type(left);
-                let right_type = self.infer_type(right);
+            Expression::Literal { value, .. } => value.type_hint(),
+            Expression::Identifier { name, .. } => env.lookup_variable(name).cloned().unwrap_or(Type::Unknown),
+            Expression::Binary { left, operator, right, .. } => {
+                let left_type = self.infer_type_in(left, env);
+                let right_type = self.infer_type_in(right, env);
                 
                 match operator {
+                    BinaryOperator::Add if left_type == Type::String && right_type == Type::String => Type::String,
                     BinaryOperator::Add | BinaryOperator::Subtract | 
                     BinaryOperator::Multiply | BinaryOperator::Divide | 
                     BinaryOperator::Modulo => {
@@ -317,15 +354,442 @@ impl TypeSystem {
                     BinaryOperator::And | BinaryOperator::Or => Type::Boolean,
                 }
             }
-            crate::ast::Expression::CrossCall { language, .. } => {
-                // Would look up the function's return type in the target language
-                Type::Unknown
+            Expression::Unary { operator, operand, .. } => match operator {
+                UnaryOperator::Not => Type::Boolean,
+                UnaryOperator::Minus | UnaryOperator::Plus => {
+                    if self.infer_type_in(operand, env) == Type::Number {
+                        Type::Number
+                    } else {
+                        Type::Unknown
+                    }
+                }
+            },
+            Expression::Postfix { .. } => Type::Number,
+            Expression::Assignment { value, .. } => self.infer_type_in(value, env),
+            Expression::Call { callee, .. } => match callee.as_ref() {
+                Expression::Identifier { name, .. } => match (env.lookup_function(name), env.lookup_variable(name)) {
+                    (Some(Type::Function { return_type, .. }), _) => (**return_type).clone(),
+                    (None, None) => crate::builtins::lookup(name).map_or(Type::Unknown, |builtin| builtin.return_type()),
+                    _ => Type::Unknown,
+                },
+                _ => Type::Unknown,
+            },
+            Expression::Array { elements, .. } => {
+                let mut element_types = elements.iter().map(|element| self.infer_type_in(element, env));
+                match element_types.next() {
+                    Some(first) if element_types.all(|t| t == first) => Type::array(first),
+                    _ => Type::array(Type::Unknown),
+                }
             }
+            // Cross-language calls are typed by the callee's language at runtime
             _ => Type::Unknown,
         }
     }
 }
 
+/// Signature of a function visible to the type checker
+#[derive(Debug, Clone)]
+struct Signature {
+    required: usize,
+    total: usize,
+    function_type: Type,
+}
+
+impl Signature {
+    fn new(parameters: &[crate::ast::Parameter], return_type: Option<&Type>) -> Self {
+        let parameter_types = parameters.iter()
+            .map(|p| p.param_type.clone().unwrap_or(Type::Unknown))
+            .collect();
+        Self {
+            required: parameters.iter().filter(|p| p.default_value.is_none()).count(),
+            total: parameters.len(),
+            function_type: Type::function(parameter_types, return_type.cloned().unwrap_or(Type::Unknown)),
+        }
+    }
+
+    fn builtin(builtin: &crate::builtins::Builtin) -> Self {
+        Self {
+            required: builtin.parameters.len(),
+            total: if builtin.variadic { usize::MAX } else { builtin.parameters.len() },
+            function_type: builtin.function_type(),
+        }
+    }
+
+    fn accepts(&self, count: usize) -> bool {
+        count >= self.required && count <= self.total
+    }
+
+    fn expected(&self) -> String {
+        if self.required == self.total {
+            self.total.to_string()
+        } else if self.total == usize::MAX {
+            format!("at least {}", self.required)
+        } else {
+            format!("{} to {}", self.required, self.total)
+        }
+    }
+}
+
+/// Only primitive types known on both sides are reported as mismatches;
+/// everything else is left to the target language.
+fn is_concrete(typ: &Type) -> bool {
+    matches!(typ, Type::Number | Type::String | Type::Boolean)
+}
+
+fn mismatch(expected: &Type, found: &Type) -> bool {
+    is_concrete(expected) && is_concrete(found) && expected != found
+}
+
+/// Walks a program, annotating inferred types and collecting diagnostics
+struct TypeChecker<'a> {
+    system: &'a TypeSystem,
+    /// Function signatures per language block; `None` holds global functions
+    signatures: HashMap<Option<String>, HashMap<String, Signature>>,
+    /// Language block currently being checked
+    language: Option<String>,
+    /// Whether identifiers are annotated with the type of what they name
+    resolve_identifiers: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(system: &'a TypeSystem, program: &Program) -> Self {
+        use crate::ast::Statement;
+
+        let mut signatures: HashMap<Option<String>, HashMap<String, Signature>> = HashMap::new();
+        let mut declare = |language: Option<&String>, statements: &[Statement]| {
+            for statement in statements {
+                if let Statement::FunctionDeclaration { name, parameters, return_type, .. } = statement {
+                    signatures.entry(language.cloned()).or_default()
+                        .insert(name.clone(), Signature::new(parameters, return_type.as_ref()));
+                }
+            }
+        };
+        declare(None, &program.global_statements);
+        for block in &program.language_blocks {
+            declare(Some(&block.language), &block.statements);
+        }
+        for block in &program.language_blocks {
+            for function in &block.functions {
+                signatures.entry(Some(block.language.clone())).or_default()
+                    .insert(function.name.clone(), Signature::new(&function.parameters, function.return_type.as_ref()));
+            }
+        }
+
+        Self {
+            system,
+            signatures,
+            language: None,
+            resolve_identifiers: false,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Environment with the functions visible from a language block (or the global scope)
+    fn scope_for(&self, language: Option<&String>) -> TypeEnvironment {
+        let mut env = TypeEnvironment::new();
+        let mut keys = vec![None];
+        if let Some(language) = language {
+            keys.push(Some(language.clone()));
+        }
+        for key in keys {
+            if let Some(functions) = self.signatures.get(&key) {
+                for (name, signature) in functions {
+                    env.define_function(name, signature.function_type.clone());
+                }
+            }
+        }
+        env
+    }
+
+    fn signature(&self, language: &str, name: &str) -> Option<&Signature> {
+        self.signatures.get(&Some(language.to_string())).and_then(|functions| functions.get(name))
+    }
+
+    fn check_function(
+        &mut self,
+        parameters: &[crate::ast::Parameter],
+        return_type: Option<&Type>,
+        body: &mut [crate::ast::Statement],
+        env: &TypeEnvironment,
+    ) {
+        let mut scope = TypeEnvironment::with_parent(env.clone());
+        for parameter in parameters {
+            scope.define_variable(&parameter.name, parameter.param_type.clone().unwrap_or(Type::Unknown));
+        }
+        self.check_statements(body, &mut scope, return_type);
+    }
+
+    fn check_block(&mut self, statements: &mut [crate::ast::Statement], env: &TypeEnvironment, return_type: Option<&Type>) {
+        let mut scope = TypeEnvironment::with_parent(env.clone());
+        self.check_statements(statements, &mut scope, return_type);
+    }
+
+    fn check_statements(&mut self, statements: &mut [crate::ast::Statement], env: &mut TypeEnvironment, return_type: Option<&Type>) {
+        for index in 0..statements.len() {
+            let (current, rest) = statements[index..].split_at_mut(1);
+            self.check_statement(&mut current[0], rest, env, return_type);
+        }
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &mut crate::ast::Statement,
+        following: &[crate::ast::Statement],
+        env: &mut TypeEnvironment,
+        return_type: Option<&Type>,
+    ) {
+        use crate::ast::{Expression, Statement};
+
+        match statement {
+            Statement::VariableDeclaration { name, value, var_type, span, .. } => {
+                let inferred = match value {
+                    Some(value) => {
+                        self.check_expression(value, env);
+                        self.system.infer_type_in(value, env)
+                    }
+                    None => Type::Unknown,
+                };
+                match var_type {
+                    Some(declared) => {
+                        if mismatch(declared, &inferred) {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!("`{}` is declared as {} but initialized with a {}", name, declared, inferred),
+                                *span,
+                            ));
+                        }
+                        env.define_variable(name, declared.clone());
+                    }
+                    None => {
+                        // Only annotate variables that keep one type for their whole lifetime
+                        let stable = inferred != Type::Unknown
+                            && assigned_values(name, following).iter()
+                                .all(|assigned| self.system.infer_type_in(assigned, env) == inferred);
+                        if stable {
+                            *var_type = Some(inferred.clone());
+                            env.define_variable(name, inferred);
+                        } else {
+                            env.define_variable(name, Type::Unknown);
+                        }
+                    }
+                }
+            }
+            Statement::Assignment { target, value, span } => {
+                self.check_expression(target, env);
+                self.check_expression(value, env);
+                self.check_assignment(target, value, *span, env);
+            }
+            Statement::Expression { expression, .. } => {
+                self.check_expression(expression, env);
+                if let Expression::Assignment { target, value, span } = expression {
+                    self.check_assignment(target, value, *span, env);
+                }
+            }
+            Statement::If { condition, then_branch, else_branch, .. } => {
+                self.check_expression(condition, env);
+                self.check_block(then_branch, env, return_type);
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch, env, return_type);
+                }
+            }
+            Statement::While { condition, body, .. } => {
+                self.check_expression(condition, env);
+                self.check_block(body, env, return_type);
+            }
+            Statement::For { init, condition, update, body, .. } => {
+                let mut scope = TypeEnvironment::with_parent(env.clone());
+                if let Some(init) = init {
+                    self.check_statement(init, &[], &mut scope, return_type);
+                }
+                if let Some(condition) = condition {
+                    self.check_expression(condition, &scope);
+                }
+                if let Some(update) = update {
+                    self.check_expression(update, &scope);
+                }
+                self.check_block(body, &scope, return_type);
+            }
+            Statement::Return { value, span } => {
+                if let Some(value) = value {
+                    self.check_expression(value, env);
+                    if let Some(expected) = return_type {
+                        let found = self.system.infer_type_in(value, env);
+                        if mismatch(expected, &found) {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!("expected a {} return value, found {}", expected, found),
+                                *span,
+                            ));
+                        }
+                    }
+                }
+            }
+            Statement::Block { statements, .. } => self.check_block(statements, env, return_type),
+            Statement::FunctionDeclaration { name, parameters, return_type, body, .. } => {
+                let parameters = parameters.clone();
+                let declared = return_type.clone();
+                // Nested functions are visible after their declaration
+                env.define_function(name, Signature::new(&parameters, declared.as_ref()).function_type);
+                self.check_function(&parameters, declared.as_ref(), body, env);
+            }
+            Statement::ClassDeclaration { methods, .. } => {
+                for method in methods {
+                    let parameters = method.parameters.clone();
+                    let declared = method.return_type.clone();
+                    self.check_function(&parameters, declared.as_ref(), &mut method.body, env);
+                }
+            }
+            Statement::Import { .. } | Statement::Export { .. } => {}
+        }
+    }
+
+    fn check_assignment(&mut self, target: &crate::ast::Expression, value: &crate::ast::Expression, span: crate::Span, env: &TypeEnvironment) {
+        if let crate::ast::Expression::Identifier { name, .. } = target {
+            if let Some(declared) = env.lookup_variable(name) {
+                let found = self.system.infer_type_in(value, env);
+                if mismatch(declared, &found) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("cannot assign a {} to `{}` of type {}", found, name, declared),
+                        span,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &mut crate::ast::Expression, env: &TypeEnvironment) {
+        use crate::ast::Expression;
+
+        match expression {
+            Expression::Identifier { name, resolved_type, .. } => {
+                if self.resolve_identifiers {
+                    *resolved_type = env.lookup_variable(name).or_else(|| env.lookup_function(name)).cloned();
+                }
+            }
+            Expression::Call { callee, arguments, span } => {
+                if let Expression::Identifier { name, .. } = callee.as_ref() {
+                    if env.lookup_variable(name).is_none() {
+                        self.check_arguments(name, arguments, *span, env);
+                    }
+                }
+                self.check_expression(callee, env);
+                for argument in arguments {
+                    self.check_expression(argument, env);
+                }
+            }
+            Expression::CrossCall { language, function, arguments, span } => {
+                if let Some(signature) = self.signature(language, function).cloned() {
+                    if !signature.accepts(arguments.len()) {
+                        self.diagnostics.push(Diagnostic::error(
+                            format!("`{}::{}` expects {} arguments, found {}", language, function, signature.expected(), arguments.len()),
+                            *span,
+                        ));
+                    }
+                }
+                for argument in arguments {
+                    self.check_expression(argument, env);
+                }
+            }
+            Expression::Binary { left, right, .. } => {
+                self.check_expression(left, env);
+                self.check_expression(right, env);
+            }
+            Expression::Unary { operand, .. } | Expression::Postfix { operand, .. } => self.check_expression(operand, env),
+            Expression::Assignment { target, value, .. } => {
+                self.check_expression(target, env);
+                self.check_expression(value, env);
+            }
+            Expression::MemberAccess { object, .. } => self.check_expression(object, env),
+            Expression::ArrayAccess { array, index, .. } => {
+                self.check_expression(array, env);
+                self.check_expression(index, env);
+            }
+            Expression::Array { elements, .. } => {
+                for element in elements {
+                    self.check_expression(element, env);
+                }
+            }
+            Expression::Object { properties, .. } => {
+                for value in properties.values_mut() {
+                    self.check_expression(value, env);
+                }
+            }
+            Expression::Literal { .. } | Expression::Lambda { .. } => {}
+        }
+    }
+
+    fn check_arguments(&mut self, name: &str, arguments: &[crate::ast::Expression], span: crate::Span, env: &TypeEnvironment) {
+        let local = self.language.as_deref().and_then(|language| self.signature(language, name));
+        let global = self.signatures.get(&None).and_then(|functions| functions.get(name));
+        let builtin = || crate::builtins::lookup(name).map(Signature::builtin);
+        let Some(signature) = local.or(global).cloned().or_else(builtin) else { return };
+        let Type::Function { parameters, .. } = &signature.function_type else { return };
+
+        if !signature.accepts(arguments.len()) {
+            self.diagnostics.push(Diagnostic::error(
+                format!("`{}` expects {} arguments, found {}", name, signature.expected(), arguments.len()),
+                span,
+            ));
+            return;
+        }
+        for (index, (expected, argument)) in parameters.iter().zip(arguments).enumerate() {
+            let found = self.system.infer_type_in(argument, env);
+            if mismatch(expected, &found) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("argument {} of `{}` expects a {}, found {}", index + 1, name, expected, found),
+                    argument.span(),
+                ));
+            }
+        }
+    }
+}
+
+/// Every value assigned to `name` in `statements`, including nested blocks
+fn assigned_values<'s>(name: &str, statements: &'s [crate::ast::Statement]) -> Vec<&'s crate::ast::Expression> {
+    use crate::ast::{Expression, Statement};
+
+    fn visit<'s>(name: &str, statement: &'s Statement, values: &mut Vec<&'s Expression>) {
+        match statement {
+            Statement::Assignment { target: Expression::Identifier { name: target, .. }, value, .. }
+                if target == name => values.push(value),
+            Statement::Expression { expression: Expression::Assignment { target, value, .. }, .. } => {
+                if matches!(target.as_ref(), Expression::Identifier { name: target, .. } if target == name) {
+                    values.push(value);
+                }
+            }
+            Statement::If { then_branch, else_branch, .. } => {
+                for statement in then_branch.iter().chain(else_branch.iter().flatten()) {
+                    visit(name, statement, values);
+                }
+            }
+            Statement::While { body, .. } | Statement::Block { statements: body, .. } => {
+                for statement in body {
+                    visit(name, statement, values);
+                }
+            }
+            Statement::For { init, update, body, .. } => {
+                if let Some(init) = init {
+                    visit(name, init, values);
+                }
+                if let Some(Expression::Assignment { target, value, .. }) = update {
+                    if matches!(target.as_ref(), Expression::Identifier { name: target, .. } if target == name) {
+                        values.push(value);
+                    }
+                }
+                for statement in body {
+                    visit(name, statement, values);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut values = Vec::new();
+    for statement in statements {
+        visit(name, statement, &mut values);
+    }
+    values
+}
+
 impl Default for TypeSystem {
     fn default() -> Self {
         Self::new()
@@ -399,7 +863,7 @@ impl LanguageTypeAdapter for PythonTypeAdapter {
             "str" => Some(Type::String),
             "bool" => Some(Type::Boolean),
             "list" => Some(Type::Array(Box::new(Type::Unknown))),
-            "dict" => Some(Type::Object(HashMap::new())),
+            "dict" => Some(Type::Object(IndexMap::new())),
             "None" => Some(Type::Null),
             _ => None,
         }
@@ -432,7 +896,7 @@ impl LanguageTypeAdapter for JavaScriptTypeAdapter {
             "string" => Some(Type::String),
             "boolean" => Some(Type::Boolean),
             "Array" => Some(Type::Array(Box::new(Type::Unknown))),
-            "Object" => Some(Type::Object(HashMap::new())),
+            "Object" => Some(Type::Object(IndexMap::new())),
             "null" | "undefined" => Some(Type::Null),
             _ => None,
         }
@@ -465,7 +929,7 @@ impl LanguageTypeAdapter for JavaTypeAdapter {
             "String" => Some(Type::String),
             "boolean" | "Boolean" => Some(Type::Boolean),
             "List" | "ArrayList" => Some(Type::Array(Box::new(Type::Unknown))),
-            "Map" | "HashMap" => Some(Type::Object(HashMap::new())),
+            "Map" | "HashMap" => Some(Type::Object(IndexMap::new())),
             "null" => Some(Type::Null),
             _ => None,
         }
@@ -531,7 +995,7 @@ impl LanguageTypeAdapter for CppTypeAdapter {
             "void" => Some(Type::Void),
             "nullptr" => Some(Type::Null),
             _ if native_type.starts_with("vector") => Some(Type::Array(Box::new(Type::Unknown))),
-            _ if native_type.starts_with("map") => Some(Type::Object(HashMap::new())),
+            _ if native_type.starts_with("map") => Some(Type::Object(IndexMap::new())),
             _ => None,
         }
     }
@@ -572,6 +1036,49 @@ mod tests {
         assert_eq!(adapter.utopia_to_native(&Type::String), Some("str".to_string()));
     }
 
+    fn parse(source: &str) -> Program {
+        let tokens = crate::lexer::Lexer::new(source).tokenize().unwrap();
+        crate::parser::Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn test_check_annotates_inferred_types() {
+        let program = parse("@lang python {\n    let a = 1 + 2;\n    let b = a > 0;\n    let c = 3;\n    c = \"text\";\n}\n");
+        let (typed, diagnostics) = TypeSystem::new().check_program(&program);
+        assert!(diagnostics.is_empty());
+
+        let types: Vec<Option<Type>> = typed.language_blocks[0].statements.iter()
+            .filter_map(|statement| match statement {
+                crate::ast::Statement::VariableDeclaration { var_type, .. } => Some(var_type.clone()),
+                _ => None,
+            })
+            .collect();
+        // `c` changes type, so it is left unannotated
+        assert_eq!(types, vec![Some(Type::Number), Some(Type::Boolean), None]);
+    }
+
+    #[test]
+    fn test_validate_reports_mismatches() {
+        let program = parse("@lang python {\n    function f(a: number) -> string {\n        return a;\n    }\n    let x: boolean = 1;\n    f(1, 2);\n}\n");
+        let diagnostics = TypeSystem::new().validate(&program).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages.iter().any(|m| m.contains("return value")));
+        assert!(messages.iter().any(|m| m.contains("declared as boolean")));
+        assert!(messages.iter().any(|m| m.contains("expects 1 arguments, found 2")));
+    }
+
+    #[test]
+    fn test_validate_checks_builtin_calls() {
+        let program = parse("@lang python {\n    let r = sqrt(\"x\");\n    let m = max(1);\n    println();\n    let n: string = len(\"ab\");\n}\n");
+        let diagnostics = TypeSystem::new().validate(&program).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages.iter().any(|m| m.contains("argument 1 of `sqrt` expects a number, found string")));
+        assert!(messages.iter().any(|m| m.contains("`max` expects 2 arguments, found 1")));
+        assert!(messages.iter().any(|m| m.contains("declared as string")));
+    }
+
     #[test]
     fn test_type_display() {
         assert_eq!(Type::Number.to_string(), "number");
@@ -0,0 +1,163 @@
+//! File watching for `utopia watch`
+//!
+//! A portable polling watcher: it snapshots the modification time and size of
+//! every `.uto` file under a root and reports files that were added, changed
+//! or removed since the previous snapshot.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+use crate::Result;
+
+/// Directories that never contain project sources
+const IGNORED_DIRS: &[&str] = &["target", "node_modules", crate::cache::CACHE_DIR];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Polling watcher over the `.uto` files of a file or directory
+pub struct FileWatcher {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl FileWatcher {
+    /// Start watching `root`, which may be a single file or a project directory
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        if !root.exists() {
+            return Err(format!("Path not found: {}", root.display()).into());
+        }
+        let files = scan(&root);
+        Ok(Self { root, files })
+    }
+
+    /// Files currently being watched
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Rescan and return every file added, modified or removed since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let current = scan(&self.root);
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+
+        for (path, stamp) in &current {
+            if self.files.get(path) != Some(stamp) {
+                changed.insert(path.clone());
+            }
+        }
+        for path in self.files.keys() {
+            if !current.contains_key(path) {
+                changed.insert(path.clone());
+            }
+        }
+
+        self.files = current;
+        changed.into_iter().collect()
+    }
+
+    /// Block until files change, then keep collecting changes until none have
+    /// been seen for `debounce`. `on_tick` runs after every poll so callers can
+    /// supervise child processes while waiting.
+    pub fn wait_for_changes<F: FnMut()>(&mut self, interval: Duration, debounce: Duration, mut on_tick: F) -> Vec<PathBuf> {
+        let mut changed: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut last_change: Option<Instant> = None;
+
+        loop {
+            std::thread::sleep(interval);
+            let batch = self.poll();
+            on_tick();
+
+            if !batch.is_empty() {
+                changed.extend(batch);
+                last_change = Some(Instant::now());
+            }
+            if last_change.is_some_and(|at| at.elapsed() >= debounce) {
+                return changed.into_iter().collect();
+            }
+        }
+    }
+}
+
+fn scan(root: &Path) -> BTreeMap<PathBuf, FileStamp> {
+    WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_ignored_dir(entry))
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_utopia_source(entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let stamp = FileStamp {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            };
+            Some((entry.into_path(), stamp))
+        })
+        .collect()
+}
+
+fn is_ignored_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.file_type().is_dir()
+        && entry.file_name().to_str().is_some_and(|name| {
+            (name.starts_with('.') && name != "." && name != "..") || IGNORED_DIRS.contains(&name)
+        })
+}
+
+fn is_utopia_source(path: &Path) -> bool {
+    crate::utils::get_extension(path).as_deref() == Some("uto")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("utopia-watch-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_poll_reports_changed_sources_only() {
+        let dir = temp_dir("poll");
+        let source = dir.join("main.uto");
+        std::fs::write(&source, "let x = 1;").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let mut watcher = FileWatcher::new(&dir).unwrap();
+        assert_eq!(watcher.files().count(), 1);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(&source, "let x = 12;").unwrap();
+        std::fs::write(dir.join("notes.txt"), "still ignored").unwrap();
+        assert_eq!(watcher.poll(), vec![source.clone()]);
+
+        std::fs::remove_file(&source).unwrap();
+        assert_eq!(watcher.poll(), vec![source]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ignored_directories() {
+        let dir = temp_dir("ignored");
+        std::fs::create_dir_all(dir.join(".utopia-cache")).unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join(".utopia-cache").join("old.uto"), "").unwrap();
+        std::fs::write(dir.join("src").join("lib.uto"), "").unwrap();
+
+        let watcher = FileWatcher::new(&dir).unwrap();
+        let files: Vec<&Path> = watcher.files().collect();
+        assert_eq!(files, vec![dir.join("src").join("lib.uto").as_path()]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}